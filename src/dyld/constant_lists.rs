@@ -24,6 +24,7 @@ pub const CONSTANT_LISTS: &[super::ConstantExports] = &[
     core_graphics::cg_affine_transform::CONSTANTS,
     core_graphics::cg_color_space::CONSTANTS,
     core_graphics::cg_geometry::CONSTANTS,
+    core_graphics::cg_pdf_context::CONSTANTS,
     foundation::ns_error::CONSTANTS,
     foundation::ns_exception::CONSTANTS,
     foundation::ns_keyed_unarchiver::CONSTANTS,
@@ -34,5 +35,9 @@ pub const CONSTANT_LISTS: &[super::ConstantExports] = &[
     opengles::eagl::CONSTANTS,
     uikit::ui_application::CONSTANTS,
     uikit::ui_device::CONSTANTS,
-    uikit::ui_view::ui_window::CONSTANTS,
+    uikit::ui_image_picker_controller::CONSTANTS,
+    uikit::ui_nib::CONSTANTS,
+    uikit::ui_view::ui_control::ui_text_field::CONSTANTS,
+    uikit::ui_view::ui_keyboard::CONSTANTS,
+    uikit::ui_view::ui_scroll_view::ui_text_view::CONSTANTS,
 ];
@@ -78,9 +78,19 @@ pub const FUNCTION_LISTS: &[super::FunctionExports] = &[
     core_graphics::cg_color::FUNCTIONS,
     core_graphics::cg_color_space::FUNCTIONS,
     core_graphics::cg_context::FUNCTIONS,
+    core_graphics::cg_data_consumer::FUNCTIONS,
     core_graphics::cg_data_provider::FUNCTIONS,
+    core_graphics::cg_font::FUNCTIONS,
+    core_graphics::cg_function::FUNCTIONS,
     core_graphics::cg_geometry::FUNCTIONS,
+    core_graphics::cg_gradient::FUNCTIONS,
     core_graphics::cg_image::FUNCTIONS,
+    core_graphics::cg_path::FUNCTIONS,
+    core_graphics::cg_pattern::FUNCTIONS,
+    core_graphics::cg_pdf_context::FUNCTIONS,
+    core_graphics::cg_pdf_document::FUNCTIONS,
+    core_graphics::cg_pdf_page::FUNCTIONS,
+    core_graphics::cg_shading::FUNCTIONS,
     dnssd::FUNCTIONS,
     foundation::FUNCTIONS,
     foundation::ns_exception::FUNCTIONS,
@@ -93,4 +103,6 @@ pub const FUNCTION_LISTS: &[super::FunctionExports] = &[
     uikit::ui_application::FUNCTIONS,
     uikit::ui_geometry::FUNCTIONS,
     uikit::ui_graphics::FUNCTIONS,
+    uikit::ui_image::FUNCTIONS,
+    uikit::ui_image_picker_controller::FUNCTIONS,
 ];
@@ -5,20 +5,44 @@
  */
 //! Text layout and font rasterization abstraction.
 //!
-//! This is implemented using the [rusttype] library. All usage of that library
-//! should be confined to this module.
+//! This is implemented using the [rusttype] library for rasterization,
+//! [rustybuzz] for shaping (kerning, ligatures, combining marks, complex
+//! scripts) and [unicode_bidi] for ordering right-to-left text. All usage of
+//! those libraries should be confined to this module.
 //!
-//! TODO: Less terrible text layout. RustType doesn't do text layout so this
+//! TODO: Less terrible line breaking. None of these libraries do that, so this
 //! code has its own, not particularly good implementation. We might want to
 //! switch to something like cosmic-text in future, but that has a _lot_ more
 //! dependencies.
 
 use crate::paths;
-use rusttype::{Point, Scale};
+use owned_ttf_parser::{name_id, AsFaceRef, Face, PlatformId};
+use rusttype::{GlyphId, Point, Scale};
+use rustybuzz::{Direction, UnicodeBuffer};
+use std::borrow::Cow;
 use std::io::Read;
+use std::ops::Range;
+use std::rc::Rc;
+use unicode_bidi::{bidi_class, BidiClass, BidiInfo};
 
 pub struct Font {
+    /// Face for shaping with rustybuzz, which shares rusttype's parsed copy of
+    /// the font. It borrows from `font`, so it must be dropped first, see
+    /// [Font::from_bytes].
+    shaping_face: rustybuzz::Face<'static>,
     font: rusttype::Font<'static>,
+    names: FontNames,
+}
+
+/// The names a font file gives itself in its `name` table.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FontNames {
+    /// Family name, e.g. "Helvetica".
+    pub family: Option<String>,
+    /// Full name, e.g. "Helvetica Bold".
+    pub full: Option<String>,
+    /// PostScript name, e.g. "Helvetica-Bold".
+    pub postscript: Option<String>,
 }
 
 pub enum TextAlignment {
@@ -33,6 +57,60 @@ pub enum WrapMode {
     Char,
 }
 
+/// What to do with the last line of text when not all the text fits.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Truncation {
+    /// Just cut off the text that doesn't fit.
+    Clip,
+    /// Replace the start of the line with an ellipsis.
+    Head,
+    /// Replace the middle of the line with an ellipsis.
+    Middle,
+    /// Replace the end of the line with an ellipsis.
+    Tail,
+}
+
+/// Constraints on the layout of text.
+#[derive(Copy, Clone)]
+pub struct Wrap {
+    /// Maximum width of a line.
+    pub width: f32,
+    pub mode: WrapMode,
+    /// Maximum number of lines, if any. `Some(1)` means the text is not
+    /// wrapped at all, only truncated.
+    pub max_lines: Option<usize>,
+    pub truncation: Truncation,
+}
+
+const ELLIPSIS: &str = "\u{2026}";
+
+/// Fonts that are tried, in order, for characters that a font has no glyphs
+/// for, as pairs of regular and bold variants. Only the Noto Sans JP fonts
+/// come with touchHLE, the others are used if the user adds them to
+/// `touchHLE_fonts` (see the README there).
+const FALLBACK_FONT_FILES: &[(&str, &str)] = &[
+    ("NotoSansJP-Regular.otf", "NotoSansJP-Bold.otf"),
+    ("NotoSansKR-Regular.otf", "NotoSansKR-Bold.otf"),
+    ("NotoSansSC-Regular.otf", "NotoSansSC-Bold.otf"),
+    ("NotoSansTC-Regular.otf", "NotoSansTC-Bold.otf"),
+    ("NotoSansArabic-Regular.ttf", "NotoSansArabic-Bold.ttf"),
+    ("NotoSansHebrew-Regular.ttf", "NotoSansHebrew-Bold.ttf"),
+    ("NotoSansThai-Regular.ttf", "NotoSansThai-Bold.ttf"),
+    (
+        "NotoSansDevanagari-Regular.ttf",
+        "NotoSansDevanagari-Bold.ttf",
+    ),
+    ("NotoSansSymbols-Regular.ttf", "NotoSansSymbols-Bold.ttf"),
+    (
+        "NotoSansSymbols2-Regular.ttf",
+        "NotoSansSymbols2-Regular.ttf",
+    ),
+    ("NotoEmoji-Regular.ttf", "NotoEmoji-Bold.ttf"),
+];
+
+/// Number of fallback fonts that [Font::fallback] can be asked for.
+pub const FALLBACK_FONT_COUNT: usize = FALLBACK_FONT_FILES.len();
+
 fn scale(font_size: f32) -> Scale {
     // iPhone OS's interpretation of font size is slightly different, reason
     // unknown. This is not the same as the Windows pt vs Mac pt issue.
@@ -67,23 +145,55 @@ impl RasterGlyph<'_> {
 }
 
 impl Font {
-    fn from_resource_file(filename: &str) -> Font {
+    fn read_resource_file(filename: &str) -> Result<Vec<u8>, String> {
         let mut bytes = Vec::new();
         let path = format!("{}/{}", paths::FONTS_DIR, filename);
-        if let Err(e) = paths::ResourceFile::open(&path)
-            .and_then(|mut f| f.get().read_to_end(&mut bytes).map_err(|e| e.to_string()))
-        {
-            panic!(
+        paths::ResourceFile::open(&path)
+            .and_then(|mut f| f.get().read_to_end(&mut bytes).map_err(|e| e.to_string()))?;
+        Ok(bytes)
+    }
+
+    fn from_resource_file(filename: &str) -> Font {
+        let bytes = match Self::read_resource_file(filename) {
+            Ok(bytes) => bytes,
+            Err(e) => panic!(
                 "Couldn't read bundled font file {:?}: {}. Perhaps the directory is missing?",
-                path, e
-            );
-        }
+                filename, e
+            ),
+        };
+
+        let Some(font) = Self::from_bytes(bytes) else {
+            panic!("Couldn't parse bundled font file {:?}. This probably means the file is corrupt. Try re-downloading it.", filename);
+        };
+
+        font
+    }
 
-        let Some(font) = rusttype::Font::try_from_vec(bytes) else {
-            panic!("Couldn't parse bundled font file {:?}. This probably means the file is corrupt. Try re-downloading it.", path);
+    /// Load a font from the contents of a TrueType or OpenType file, e.g. one
+    /// bundled with an app.
+    pub fn from_bytes(bytes: Vec<u8>) -> Option<Font> {
+        let font = rusttype::Font::try_from_vec(bytes)?;
+        let rusttype::Font::Owned(ref face) = font else {
+            // try_from_vec() always creates an owned font.
+            unreachable!();
+        };
+        let names = names_from_face(face.as_face_ref());
+        let shaping_face = rustybuzz::Face::from_face(face.as_face_ref().clone())?;
+        // Safety: the owned font data is pinned on the heap, so it doesn't
+        // move when `font` does, and `shaping_face` is dropped before `font`.
+        // The 'static lifetime is never exposed outside this struct.
+        let shaping_face = unsafe {
+            std::mem::transmute::<rustybuzz::Face<'_>, rustybuzz::Face<'static>>(shaping_face)
         };
+        Some(Font {
+            shaping_face,
+            font,
+            names,
+        })
+    }
 
-        Font { font }
+    pub fn names(&self) -> &FontNames {
+        &self.names
     }
 
     pub fn mono_regular() -> Font {
@@ -122,11 +232,86 @@ impl Font {
     pub fn serif_italic() -> Font {
         Self::from_resource_file("LiberationSerif-Italic.ttf")
     }
-    pub fn sans_regular_ja() -> Font {
-        Self::from_resource_file("NotoSansJP-Regular.otf")
+    /// Load one of the fonts used for characters other fonts have no glyphs
+    /// for, if it is present. `index` must be less than
+    /// [FALLBACK_FONT_COUNT].
+    pub fn fallback(index: usize, bold: bool) -> Option<Font> {
+        let (regular, bold_variant) = FALLBACK_FONT_FILES[index];
+        let bytes = if bold {
+            Self::read_resource_file(bold_variant).or_else(|_| Self::read_resource_file(regular))
+        } else {
+            Self::read_resource_file(regular)
+        };
+        let bytes = bytes.ok()?;
+        let font = Self::from_bytes(bytes);
+        if font.is_none() {
+            log!("Warning: couldn't parse fallback font file {:?}", regular);
+        }
+        font
     }
-    pub fn sans_bold_ja() -> Font {
-        Self::from_resource_file("NotoSansJP-Bold.otf")
+
+    /// Scale for a font size that is the size of the em square, as Core
+    /// Graphics uses, rather than the UIKit approximation of [scale].
+    fn em_scale(&self, font_size: f32) -> Scale {
+        let v_metrics = self.font.v_metrics_unscaled();
+        let units_per_em = self.units_per_em() as f32;
+        Scale::uniform(font_size * (v_metrics.ascent - v_metrics.descent) / units_per_em)
+    }
+
+    pub fn units_per_em(&self) -> u16 {
+        self.font.units_per_em()
+    }
+    pub fn glyph_count(&self) -> usize {
+        self.font.glyph_count()
+    }
+    /// Get the ID of the glyph for a character. Glyph 0 is the "missing
+    /// character" glyph.
+    pub fn glyph_id(&self, c: char) -> u16 {
+        self.font.glyph(c).id().0
+    }
+    /// Get the advance width of a glyph in font units.
+    pub fn glyph_advance_units(&self, glyph: u16) -> f32 {
+        self.glyph_advance(self.units_per_em() as f32, glyph)
+    }
+    /// Get the advance width of a glyph for a Core Graphics font size.
+    pub fn glyph_advance(&self, font_size: f32, glyph: u16) -> f32 {
+        self.font
+            .glyph(GlyphId(glyph))
+            .scaled(self.em_scale(font_size))
+            .h_metrics()
+            .advance_width
+    }
+    /// Rasterize a single glyph with its origin at (0, 0), for a Core Graphics
+    /// font size. The glyph is rendered with `pixels_per_unit` pixels for each
+    /// unit of the font size, so that it is sharp when scaled, but the origin
+    /// and dimensions passed to `draw_glyph` are in pixels. y points upwards.
+    /// `draw_glyph` is not called for glyphs with no outline, e.g. spaces.
+    pub fn draw_glyph<F: FnOnce(RasterGlyph)>(
+        &self,
+        font_size: f32,
+        glyph: u16,
+        pixels_per_unit: f32,
+        draw_glyph: F,
+    ) {
+        let glyph = self
+            .font
+            .glyph(GlyphId(glyph))
+            .scaled(self.em_scale(font_size * pixels_per_unit))
+            .positioned(Point { x: 0.0, y: 0.0 });
+        let Some(glyph_bounds) = glyph.pixel_bounding_box() else {
+            return;
+        };
+        let width = glyph_bounds.width() as usize;
+        let mut glyph_bitmap = vec![0.0; width * glyph_bounds.height() as usize];
+        glyph.draw(|x, y, coverage| {
+            glyph_bitmap[y as usize * width + x as usize] = coverage;
+        });
+        draw_glyph(RasterGlyph {
+            // y needs to be flipped to point up
+            origin: (glyph_bounds.min.x as f32, -glyph_bounds.max.y as f32),
+            dimensions: (glyph_bounds.width(), glyph_bounds.height()),
+            pixels: &glyph_bitmap,
+        });
     }
 
     pub fn ascent(&self, font_size: f32) -> f32 {
@@ -143,22 +328,166 @@ impl Font {
         (v_metrics.ascent - v_metrics.descent, v_metrics.line_gap)
     }
 
+    /// Distance from the top of one line to the top of the next.
+    pub fn line_spacing(&self, font_size: f32) -> f32 {
+        let (line_height, line_gap) = self.line_height_and_gap(font_size);
+        line_height + line_gap
+    }
+
+    /// How many lines of text fit within a given height. This is always at
+    /// least one, since iPhone OS will draw a line even if it doesn't fit.
+    pub fn lines_in_height(&self, font_size: f32, height: f32) -> usize {
+        let (line_height, line_gap) = self.line_height_and_gap(font_size);
+        // The last line has no gap after it. A small tolerance is added so
+        // that a height calculated by [Self::calculate_text_size] gives back
+        // the same number of lines despite rounding errors.
+        let lines = ((height + line_gap) / (line_height + line_gap) + 0.001).floor();
+        if lines >= usize::MAX as f32 {
+            usize::MAX
+        } else {
+            (lines as usize).max(1)
+        }
+    }
+
+    /// Get the face for shaping with rustybuzz.
+    fn shaping_face(&self) -> &rustybuzz::Face<'_> {
+        &self.shaping_face
+    }
+}
+
+/// A glyph positioned by [FontChain::shape_line].
+struct ShapedGlyph {
+    /// Index of the font in the chain.
+    font: usize,
+    id: GlyphId,
+    /// Position of the glyph's origin relative to the start of the line.
+    /// y points upwards.
+    x: f32,
+    y: f32,
+    advance: f32,
+}
+
+/// A font followed by the fonts to use for characters it has no glyphs for.
+/// All text layout is done with one of these. The line metrics are always
+/// those of the first font.
+pub struct FontChain<'f> {
+    fonts: &'f [Rc<Font>],
+}
+impl<'f> FontChain<'f> {
+    pub fn new(fonts: &'f [Rc<Font>]) -> Self {
+        assert!(!fonts.is_empty());
+        FontChain { fonts }
+    }
+
+    /// The font that was asked for.
+    pub fn primary(&self) -> &'f Font {
+        &self.fonts[0]
+    }
+
+    /// Split text into runs of characters that use the same font. Each
+    /// character uses the first font that has a glyph for it.
+    fn font_runs(&self, text: &str) -> Vec<(usize, Range<usize>)> {
+        let mut runs: Vec<(usize, Range<usize>)> = Vec::new();
+        for (i, c) in text.char_indices() {
+            let font = match runs.last() {
+                // Combining marks and invisible formatting characters must
+                // stay with the character they belong to, so they can be
+                // shaped together.
+                Some(&(font, _)) if matches!(bidi_class(c), BidiClass::NSM | BidiClass::BN) => font,
+                _ => self
+                    .fonts
+                    .iter()
+                    .position(|font| font.glyph_id(c) != 0)
+                    .unwrap_or(0),
+            };
+            let end = i + c.len_utf8();
+            match runs.last_mut() {
+                Some((run_font, run)) if *run_font == font => run.end = end,
+                _ => runs.push((font, i..end)),
+            }
+        }
+        runs
+    }
+
+    /// Shape a run of text that uses a single font and direction, appending
+    /// the glyphs to `glyphs` in visual order and advancing `x`.
+    fn shape_run(
+        &self,
+        font_size: f32,
+        font_idx: usize,
+        text: &str,
+        rtl: bool,
+        x: &mut f32,
+        glyphs: &mut Vec<ShapedGlyph>,
+    ) {
+        let font = &self.fonts[font_idx];
+        let mut buffer = UnicodeBuffer::new();
+        buffer.push_str(text);
+        buffer.set_direction(if rtl {
+            Direction::RightToLeft
+        } else {
+            Direction::LeftToRight
+        });
+        let output = rustybuzz::shape(font.shaping_face(), &[], buffer);
+        // Shaping is done in font units.
+        let units_to_pixels = font.font.scale_for_pixel_height(scale(font_size).y);
+        for (info, position) in output.glyph_infos().iter().zip(output.glyph_positions()) {
+            glyphs.push(ShapedGlyph {
+                font: font_idx,
+                id: GlyphId(info.glyph_id as u16),
+                x: *x + position.x_offset as f32 * units_to_pixels,
+                y: position.y_offset as f32 * units_to_pixels,
+                advance: position.x_advance as f32 * units_to_pixels,
+            });
+            *x += position.x_advance as f32 * units_to_pixels;
+        }
+    }
+
+    /// Lay out a line of text as a sequence of glyphs in visual order,
+    /// handling font fallback, shaping and bidirectional text. This does not
+    /// handle newlines!
+    fn shape_line(&self, font_size: f32, line: &str) -> Vec<ShapedGlyph> {
+        let mut glyphs = Vec::new();
+        let mut x = 0.0;
+        let bidi_info = BidiInfo::new(line, None);
+        for paragraph in &bidi_info.paragraphs {
+            let (levels, level_runs) = bidi_info.visual_runs(paragraph, paragraph.range.clone());
+            for level_run in level_runs {
+                let rtl = levels[level_run.start].is_rtl();
+                let mut font_runs = self.font_runs(&line[level_run.clone()]);
+                if rtl {
+                    font_runs.reverse();
+                }
+                for (font_idx, font_run) in font_runs {
+                    let start = level_run.start + font_run.start;
+                    let end = level_run.start + font_run.end;
+                    let text = &line[start..end];
+                    self.shape_run(font_size, font_idx, text, rtl, &mut x, &mut glyphs);
+                }
+            }
+        }
+        glyphs
+    }
+
     /// Calculate the width of a line. This does not handle newlines!
     fn calculate_line_width(&self, font_size: f32, line: &str) -> f32 {
         let mut line_x_min: f32 = 0.0;
         let mut line_x_max: f32 = 0.0;
 
-        for glyph in self.font.layout(line, scale(font_size), Default::default()) {
-            let position = glyph.position();
-            let h_metrics = glyph.unpositioned().h_metrics();
+        for glyph in self.shape_line(font_size, line) {
+            let h_metrics = self.fonts[glyph.font]
+                .font
+                .glyph(glyph.id)
+                .scaled(scale(font_size))
+                .h_metrics();
 
             // This method used to use pixel_bounding_box() for metrics, but
             // now uses h_metrics() in order to support whitespace characters.
             // This definition of character width was chosen because it gave
             // similar results to the old implementation, not because it's
             // optimal; maybe it could be improved.
-            let glyph_x_min = position.x.min(position.x + h_metrics.left_side_bearing);
-            let glyph_x_max = position.x + h_metrics.advance_width;
+            let glyph_x_min = glyph.x.min(glyph.x + h_metrics.left_side_bearing);
+            let glyph_x_max = glyph.x + glyph.advance;
 
             line_x_min = line_x_min.min(glyph_x_min);
             line_x_min = line_x_min.min(glyph_x_max);
@@ -293,19 +622,154 @@ impl Font {
         lines
     }
 
+    /// Break text into lines with known widths, respecting the line limit and
+    /// truncating the last line if necessary.
+    fn layout_lines<'a>(
+        &self,
+        font_size: f32,
+        text: &'a str,
+        wrap: Option<Wrap>,
+    ) -> Vec<(f32, Cow<'a, str>)> {
+        let Some(wrap) = wrap else {
+            return self
+                .break_lines(font_size, text, None)
+                .into_iter()
+                .map(|(width, line)| (width, Cow::Borrowed(line)))
+                .collect();
+        };
+
+        let lines = if wrap.max_lines == Some(1) {
+            self.break_lines(font_size, text, None)
+        } else {
+            self.break_lines(font_size, text, Some((wrap.width, wrap.mode)))
+        };
+        let max_lines = wrap.max_lines.unwrap_or(usize::MAX);
+        let overflows = lines.len() > max_lines
+            || (max_lines == 1 && lines.first().is_some_and(|&(w, _)| w > wrap.width));
+
+        let mut lines: Vec<(f32, Cow<'a, str>)> = lines
+            .into_iter()
+            .take(max_lines)
+            .map(|(width, line)| (width, Cow::Borrowed(line)))
+            .collect();
+        if !overflows || wrap.truncation == Truncation::Clip {
+            return lines;
+        }
+
+        // The last line absorbs the rest of its paragraph, which is then
+        // truncated to fit.
+        let last = lines.last_mut().unwrap();
+        let Cow::Borrowed(last_line) = last.1 else {
+            unreachable!();
+        };
+        let start = last_line.as_ptr() as usize - text.as_ptr() as usize;
+        let rest = text[start..].lines().next().unwrap_or("");
+        let truncated = self.truncate_line(font_size, rest, wrap.width, wrap.truncation);
+        *last = (
+            self.calculate_line_width(font_size, &truncated),
+            Cow::Owned(truncated),
+        );
+        lines
+    }
+
+    /// Shorten a line with an ellipsis so it fits within a width.
+    fn truncate_line(
+        &self,
+        font_size: f32,
+        line: &str,
+        width: f32,
+        truncation: Truncation,
+    ) -> String {
+        if self.calculate_line_width(font_size, line) <= width {
+            return line.to_string();
+        }
+
+        let boundaries: Vec<usize> = line
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(line.len()))
+            .collect();
+        let fits = |candidate: &str| self.calculate_line_width(font_size, candidate) <= width;
+        // Find the largest number of characters that can be kept, by binary
+        // search. Even if nothing fits, the ellipsis is shown.
+        let mut keep_min = 0;
+        let mut keep_max = boundaries.len() - 1;
+        let candidate = |keep: usize| -> String {
+            let char_count = boundaries.len() - 1;
+            match truncation {
+                Truncation::Head => {
+                    let start = boundaries[char_count - keep];
+                    format!("{}{}", ELLIPSIS, line[start..].trim_start())
+                }
+                Truncation::Tail | Truncation::Clip => {
+                    let end = boundaries[keep];
+                    format!("{}{}", line[..end].trim_end(), ELLIPSIS)
+                }
+                Truncation::Middle => {
+                    let head_end = boundaries[keep.div_ceil(2)];
+                    let tail_start = boundaries[char_count - keep / 2];
+                    format!(
+                        "{}{}{}",
+                        line[..head_end].trim_end(),
+                        ELLIPSIS,
+                        line[tail_start..].trim_start()
+                    )
+                }
+            }
+        };
+        while keep_min < keep_max {
+            let keep = (keep_min + keep_max).div_ceil(2);
+            if fits(&candidate(keep)) {
+                keep_min = keep;
+            } else {
+                keep_max = keep - 1;
+            }
+        }
+        candidate(keep_min)
+    }
+
+    /// Break text into word-wrapped lines for editing, returning the byte range
+    /// of each line within `text`. There is always at least one line, and a
+    /// trailing newline starts a new, empty line, so that there is always
+    /// somewhere to put a caret.
+    pub fn editing_line_ranges(&self, font_size: f32, text: &str, width: f32) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = self
+            .break_lines(font_size, text, Some((width, WrapMode::Word)))
+            .into_iter()
+            .map(|(_width, line)| {
+                let start = line.as_ptr() as usize - text.as_ptr() as usize;
+                start..(start + line.len())
+            })
+            .collect();
+        if ranges.is_empty() || text.ends_with('\n') {
+            ranges.push(text.len()..text.len());
+        }
+        ranges
+    }
+
+    /// Horizontal distance from the start of a line to the character at byte
+    /// offset `idx` within it. This does not handle newlines!
+    pub fn offset_in_line(&self, font_size: f32, line: &str, idx: usize) -> f32 {
+        if idx == 0 {
+            0.0
+        } else {
+            self.calculate_line_width(font_size, &line[..idx])
+        }
+    }
+
     /// Calculate the on-screen width and height of text with a given font size.
     pub fn calculate_text_size(
         &self,
         font_size: f32,
         text: &str,
-        wrap: Option<(f32, WrapMode)>,
+        wrap: Option<Wrap>,
     ) -> (f32, f32) {
-        let lines = self.break_lines(font_size, text, wrap);
+        let lines = self.layout_lines(font_size, text, wrap);
 
         let width = lines
             .iter()
-            .fold(0f32, |widest, &(line_width, _line)| widest.max(line_width));
-        let (line_height, line_gap) = self.line_height_and_gap(font_size);
+            .fold(0f32, |widest, &(line_width, _)| widest.max(line_width));
+        let (line_height, line_gap) = self.primary().line_height_and_gap(font_size);
         let height =
             line_height * (lines.len() as f32) + line_gap * (lines.len().saturating_sub(1) as f32);
 
@@ -319,17 +783,17 @@ impl Font {
         font_size: f32,
         text: &str,
         origin: (f32, f32),
-        wrap: Option<(f32, WrapMode)>,
+        wrap: Option<Wrap>,
         alignment: TextAlignment,
         mut draw_glyph: F,
     ) {
         // TODO: This code has gone through a rather traumatic series of y sign
         //       flips and might benefit from refactoring for clarity?
 
-        let lines = self.break_lines(font_size, text, wrap);
+        let lines = self.layout_lines(font_size, text, wrap);
 
-        let mut line_y = self.font.v_metrics(scale(font_size)).ascent;
-        let (line_height, line_gap) = self.line_height_and_gap(font_size);
+        let mut line_y = self.primary().ascent(font_size);
+        let (line_height, line_gap) = self.primary().line_height_and_gap(font_size);
 
         // RustType requires a "draw pixel" callback that will be called for
         // each pixel in the glyph's bounding box, in left-to-right
@@ -349,14 +813,16 @@ impl Font {
                 TextAlignment::Center => -line_width / 2.0,
                 TextAlignment::Right => -line_width,
             };
-            for glyph in self.font.layout(
-                line_text,
-                scale(font_size),
-                Point {
-                    x: origin.0 + line_x_offset,
-                    y: 0.0,
-                },
-            ) {
+            for glyph in self.shape_line(font_size, &line_text) {
+                let glyph = self.fonts[glyph.font]
+                    .font
+                    .glyph(glyph.id)
+                    .scaled(scale(font_size))
+                    .positioned(Point {
+                        x: origin.0 + line_x_offset + glyph.x,
+                        // y needs to be flipped to point down
+                        y: -glyph.y,
+                    });
                 let Some(glyph_bounds) = glyph.pixel_bounding_box() else {
                     continue;
                 };
@@ -368,7 +834,10 @@ impl Font {
                 // TODO: Refactor this method to support y clipping too.
                 // It's not mandatory since the caller can do it, but it would
                 // be more efficient.
-                if let Some((wrap_width, _)) = wrap {
+                if let Some(Wrap {
+                    width: wrap_width, ..
+                }) = wrap
+                {
                     if glyph_bounds.min.x as f32 > origin.0 + wrap_width {
                         // Avoid wasting effort on glyphs that are entirely
                         // clipped. Partial clipping is the responsibility of
@@ -400,3 +869,110 @@ impl Font {
         }
     }
 }
+
+/// The upper half of the Mac OS Roman character set.
+const MAC_ROMAN_HIGH: &str = concat!(
+    "ÄÅÇÉÑÖÜáàâäãåçéèêëíìîïñóòôöõúùûü",
+    "†°¢£§•¶ß®©™´¨≠ÆØ∞±≤≥¥µ∂∑∏π∫ªºΩæø",
+    "¿¡¬√ƒ≈∆«»…\u{A0}ÀÃÕŒœ–—“”‘’÷◊ÿŸ⁄€‹›ﬁﬂ",
+    "‡·‚„‰ÂÊÁËÈÍÎÏÌÓÔ\u{F8FF}ÒÚÛÙıˆ˜¯˘˙˚¸˝˛ˇ",
+);
+
+/// Convert a byte of Mac OS Roman text to a character.
+pub fn decode_mac_roman(byte: u8) -> char {
+    if byte >= 0x80 {
+        MAC_ROMAN_HIGH.chars().nth((byte - 0x80) as usize).unwrap()
+    } else {
+        byte as char
+    }
+}
+
+/// Get the family, full and PostScript names from a font's `name` table.
+/// Windows and Unicode platform names are preferred over Macintosh ones, since
+/// they are always UTF-16 rather than some legacy encoding.
+fn names_from_face(face: &Face) -> FontNames {
+    // The best name found so far for each ID, with its priority.
+    let mut best: [Option<(u8, String)>; 3] = Default::default();
+    for name in face.names() {
+        let slot = match name.name_id {
+            name_id::FAMILY => 0,
+            name_id::FULL_NAME => 1,
+            name_id::POST_SCRIPT_NAME => 2,
+            _ => continue,
+        };
+        let priority = match (name.platform_id, name.encoding_id, name.language_id) {
+            (PlatformId::Windows, _, 0x409) => 4, // US English
+            (PlatformId::Windows, _, _) => 3,
+            (PlatformId::Unicode, _, _) => 2,
+            (PlatformId::Macintosh, 0, 0) => 1, // Roman, English
+            _ => continue,
+        };
+        if matches!(best[slot], Some((best_priority, _)) if best_priority >= priority) {
+            continue;
+        }
+        let string = if name.platform_id == PlatformId::Macintosh {
+            name.name
+                .iter()
+                .map(|&byte| decode_mac_roman(byte))
+                .collect()
+        } else {
+            let Some(string) = name.to_string() else {
+                continue;
+            };
+            string
+        };
+        best[slot] = Some((priority, string));
+    }
+
+    let [family, full, postscript] = best.map(|name| name.map(|(_, string)| string));
+    FontNames {
+        family,
+        full,
+        postscript,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_names() {
+        assert_eq!(
+            Font::sans_bold().names(),
+            &FontNames {
+                family: Some("Liberation Sans".to_string()),
+                full: Some("Liberation Sans Bold".to_string()),
+                postscript: Some("LiberationSans-Bold".to_string()),
+            }
+        );
+        assert_eq!(decode_mac_roman(b'A'), 'A');
+        assert_eq!(decode_mac_roman(0x80), 'Ä');
+        assert_eq!(decode_mac_roman(0xFF), 'ˇ');
+    }
+
+    #[test]
+    fn test_shaping() {
+        let fonts = [Rc::new(Font::sans_regular())];
+        let chain = FontChain::new(&fonts);
+        let width = |text| chain.calculate_line_width(20.0, text);
+
+        // Kerning
+        assert!(width("AV") < width("A") + width("V"));
+        // Combining marks don't take up space of their own
+        assert_eq!(width("e\u{301}"), width("e"));
+
+        // Right-to-left text is laid out in reverse
+        let ids: Vec<u16> = chain
+            .shape_line(20.0, "\u{5D0}\u{5D1}")
+            .iter()
+            .map(|glyph| glyph.id.0)
+            .collect();
+        let expected: Vec<u16> = ['\u{5D1}', '\u{5D0}']
+            .into_iter()
+            .map(|c| fonts[0].glyph_id(c))
+            .collect();
+        assert_eq!(ids, expected);
+        assert!(!ids.contains(&0));
+    }
+}
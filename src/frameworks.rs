@@ -42,6 +42,7 @@ pub mod uikit;
 pub struct State {
     audio_toolbox: audio_toolbox::State,
     core_animation: core_animation::State,
+    core_graphics: core_graphics::State,
     foundation: foundation::State,
     media_player: media_player::State,
     openal: openal::State,
@@ -50,13 +50,66 @@ pub(super) struct CALayerHostObject {
     pub(super) gles_texture: Option<crate::gles::gles11_raw::types::GLuint>,
     /// Internal state for compositor
     pub(super) gles_texture_is_up_to_date: bool,
+    pub(super) contents_gravity: ContentsGravity,
 }
 impl HostObject for CALayerHostObject {}
 
+/// How `contents` is positioned within the layer's bounds. See
+/// `contentsGravity`.
+///
+/// Note that "top" here means the top of the screen. On iPhone OS, layers have
+/// flipped geometry, so this is what the real `kCAGravityTop` etc do too.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ContentsGravity {
+    Center,
+    Top,
+    Bottom,
+    Left,
+    Right,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Resize,
+    ResizeAspect,
+    ResizeAspectFill,
+}
+
 pub const kCAFilterLinear: &str = "kCAFilterLinear";
 pub const kCAFilterNearest: &str = "kCAFilterNearest";
 pub const kCAFilterTrilinear: &str = "kCAFilterTrilinear";
 
+pub const kCAGravityCenter: &str = "center";
+pub const kCAGravityTop: &str = "top";
+pub const kCAGravityBottom: &str = "bottom";
+pub const kCAGravityLeft: &str = "left";
+pub const kCAGravityRight: &str = "right";
+pub const kCAGravityTopLeft: &str = "topLeft";
+pub const kCAGravityTopRight: &str = "topRight";
+pub const kCAGravityBottomLeft: &str = "bottomLeft";
+pub const kCAGravityBottomRight: &str = "bottomRight";
+pub const kCAGravityResize: &str = "resize";
+pub const kCAGravityResizeAspect: &str = "resizeAspect";
+pub const kCAGravityResizeAspectFill: &str = "resizeAspectFill";
+
+const GRAVITIES: &[(&str, ContentsGravity)] = &[
+    (kCAGravityCenter, ContentsGravity::Center),
+    (kCAGravityTop, ContentsGravity::Top),
+    (kCAGravityBottom, ContentsGravity::Bottom),
+    (kCAGravityLeft, ContentsGravity::Left),
+    (kCAGravityRight, ContentsGravity::Right),
+    (kCAGravityTopLeft, ContentsGravity::TopLeft),
+    (kCAGravityTopRight, ContentsGravity::TopRight),
+    (kCAGravityBottomLeft, ContentsGravity::BottomLeft),
+    (kCAGravityBottomRight, ContentsGravity::BottomRight),
+    (kCAGravityResize, ContentsGravity::Resize),
+    (kCAGravityResizeAspect, ContentsGravity::ResizeAspect),
+    (
+        kCAGravityResizeAspectFill,
+        ContentsGravity::ResizeAspectFill,
+    ),
+];
+
 pub const CONSTANTS: ConstantExports = &[
     ("_kCAFilterLinear", HostConstant::NSString(kCAFilterLinear)),
     (
@@ -67,6 +120,45 @@ pub const CONSTANTS: ConstantExports = &[
         "_kCAFilterTrilinear",
         HostConstant::NSString(kCAFilterTrilinear),
     ),
+    (
+        "_kCAGravityCenter",
+        HostConstant::NSString(kCAGravityCenter),
+    ),
+    ("_kCAGravityTop", HostConstant::NSString(kCAGravityTop)),
+    (
+        "_kCAGravityBottom",
+        HostConstant::NSString(kCAGravityBottom),
+    ),
+    ("_kCAGravityLeft", HostConstant::NSString(kCAGravityLeft)),
+    ("_kCAGravityRight", HostConstant::NSString(kCAGravityRight)),
+    (
+        "_kCAGravityTopLeft",
+        HostConstant::NSString(kCAGravityTopLeft),
+    ),
+    (
+        "_kCAGravityTopRight",
+        HostConstant::NSString(kCAGravityTopRight),
+    ),
+    (
+        "_kCAGravityBottomLeft",
+        HostConstant::NSString(kCAGravityBottomLeft),
+    ),
+    (
+        "_kCAGravityBottomRight",
+        HostConstant::NSString(kCAGravityBottomRight),
+    ),
+    (
+        "_kCAGravityResize",
+        HostConstant::NSString(kCAGravityResize),
+    ),
+    (
+        "_kCAGravityResizeAspect",
+        HostConstant::NSString(kCAGravityResizeAspect),
+    ),
+    (
+        "_kCAGravityResizeAspectFill",
+        HostConstant::NSString(kCAGravityResizeAspectFill),
+    ),
 ];
 
 pub const CLASSES: ClassExports = objc_classes! {
@@ -97,6 +189,7 @@ pub const CLASSES: ClassExports = objc_classes! {
         cg_context: None,
         gles_texture: None,
         gles_texture_is_up_to_date: false,
+        contents_gravity: ContentsGravity::Resize,
     });
     env.objc.alloc_object(this, host_object, &mut env.mem)
 }
@@ -384,6 +477,20 @@ pub const CLASSES: ClassExports = objc_classes! {
     release(env, old_contents);
 }
 
+- (id)contentsGravity {
+    let gravity = env.objc.borrow::<CALayerHostObject>(this).contents_gravity;
+    let &(name, _) = GRAVITIES.iter().find(|&&(_, g)| g == gravity).unwrap();
+    ns_string::get_static_str(env, name)
+}
+- (())setContentsGravity:(id)gravity { // NSString*
+    let name = ns_string::to_rust_string(env, gravity);
+    let Some(&(_, gravity)) = GRAVITIES.iter().find(|&&(n, _)| n == name) else {
+        log!("Warning: [(CALayer*){:?} setContentsGravity:{:?}] unknown gravity, ignoring", this, name);
+        return;
+    };
+    env.objc.borrow_mut::<CALayerHostObject>(this).contents_gravity = gravity;
+}
+
 - (())setEdgeAntialiasingMask:(u32)mask {
     log!("TODO: [(CALayer*){:?} setEdgeAntialiasingMask: {}]", this, mask); // TODO
 }
@@ -10,7 +10,7 @@
 //! diverges wildly from what the real iPhone OS does.
 
 use super::ca_eagl_layer::find_fullscreen_eagl_layer;
-use super::ca_layer::CALayerHostObject;
+use super::ca_layer::{CALayerHostObject, ContentsGravity};
 use crate::frameworks::core_graphics::{
     cg_bitmap_context, cg_color, cg_image, CGFloat, CGPoint, CGRect, CGSize,
 };
@@ -394,6 +394,9 @@ unsafe fn composite_layer_recursive(
         host_obj.gles_texture_is_up_to_date = true;
     }
 
+    // re-borrow immutably
+    let host_obj = objc.borrow::<CALayerHostObject>(layer);
+
     // Draw texture, if any
     if need_texture {
         gles.Color4f(opacity, opacity, opacity, opacity);
@@ -404,8 +407,26 @@ unsafe fn composite_layer_recursive(
             gles.BlendFunc(gles11::ONE, gles11::ONE_MINUS_SRC_ALPHA);
         }
 
-        let (x, y, w, h) = gl_rect_from_cg_rect(absolute_frame_clipped, scale_hack, fb_height);
+        // Images are positioned according to the contentsGravity, which may
+        // mean the image doesn't fill the layer, or overflows it.
+        let content_frame = if host_obj.contents != nil {
+            let image = cg_image::borrow_image(objc, host_obj.contents);
+            let (width, height) = image.dimensions();
+            let image_size = CGSize {
+                width: width as CGFloat,
+                height: height as CGFloat,
+            };
+            apply_gravity(host_obj.contents_gravity, absolute_frame, image_size)
+        } else {
+            absolute_frame_clipped
+        };
+        let (x, y, w, h) = gl_rect_from_cg_rect(
+            clip_rects(absolute_frame_clipped, content_frame),
+            scale_hack,
+            fb_height,
+        );
         gles.Scissor(x, y, w, h);
+        let (x, y, w, h) = gl_rect_from_cg_rect(content_frame, scale_hack, fb_height);
         gles.Viewport(x, y, w, h);
 
         gles.BindBuffer(gles11::ARRAY_BUFFER, 0);
@@ -429,6 +450,7 @@ unsafe fn composite_layer_recursive(
     }
 
     // avoid holding mutable borrow while recursing
+    let host_obj = objc.borrow_mut::<CALayerHostObject>(layer);
     let sublayers = std::mem::take(&mut host_obj.sublayers);
     for &child_layer in &sublayers {
         composite_layer_recursive(
@@ -499,6 +521,52 @@ fn clip_rects(a_clip: CGRect, b_clip: CGRect) -> CGRect {
     }
 }
 
+/// Work out where content of size `content_size` should be drawn within
+/// `frame` for a particular `contentsGravity`.
+fn apply_gravity(gravity: ContentsGravity, frame: CGRect, content_size: CGSize) -> CGRect {
+    let size = match gravity {
+        ContentsGravity::Resize => return frame,
+        ContentsGravity::ResizeAspect | ContentsGravity::ResizeAspectFill => {
+            if content_size.width == 0.0 || content_size.height == 0.0 {
+                return frame;
+            }
+            let scale_x = frame.size.width / content_size.width;
+            let scale_y = frame.size.height / content_size.height;
+            let scale = if gravity == ContentsGravity::ResizeAspect {
+                scale_x.min(scale_y)
+            } else {
+                scale_x.max(scale_y)
+            };
+            CGSize {
+                width: content_size.width * scale,
+                height: content_size.height * scale,
+            }
+        }
+        _ => content_size,
+    };
+
+    // 0.0 = left/top, 0.5 = center, 1.0 = right/bottom
+    let (x_align, y_align) = match gravity {
+        ContentsGravity::TopLeft => (0.0, 0.0),
+        ContentsGravity::Top => (0.5, 0.0),
+        ContentsGravity::TopRight => (1.0, 0.0),
+        ContentsGravity::Left => (0.0, 0.5),
+        ContentsGravity::Right => (1.0, 0.5),
+        ContentsGravity::BottomLeft => (0.0, 1.0),
+        ContentsGravity::Bottom => (0.5, 1.0),
+        ContentsGravity::BottomRight => (1.0, 1.0),
+        _ => (0.5, 0.5),
+    };
+
+    CGRect {
+        origin: CGPoint {
+            x: frame.origin.x + (frame.size.width - size.width) * x_align,
+            y: frame.origin.y + (frame.size.height - size.height) * y_align,
+        },
+        size,
+    }
+}
+
 fn gl_rect_from_cg_rect(
     rect: CGRect,
     scale_hack: u32,
@@ -511,3 +579,52 @@ fn gl_rect_from_cg_rect(
     // y points up in OpenGL ES, but down in UIKit and Core Animation
     (x, fb_height as GLint - h - y, w, h)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_gravity() {
+        let frame = CGRect {
+            origin: CGPoint { x: 10.0, y: 20.0 },
+            size: CGSize {
+                width: 100.0,
+                height: 50.0,
+            },
+        };
+        let content_size = CGSize {
+            width: 20.0,
+            height: 20.0,
+        };
+        let rect = |x, y, width, height| CGRect {
+            origin: CGPoint { x, y },
+            size: CGSize { width, height },
+        };
+
+        assert_eq!(
+            apply_gravity(ContentsGravity::Resize, frame, content_size),
+            frame
+        );
+        assert_eq!(
+            apply_gravity(ContentsGravity::ResizeAspect, frame, content_size),
+            rect(35.0, 20.0, 50.0, 50.0)
+        );
+        assert_eq!(
+            apply_gravity(ContentsGravity::ResizeAspectFill, frame, content_size),
+            rect(10.0, -5.0, 100.0, 100.0)
+        );
+        assert_eq!(
+            apply_gravity(ContentsGravity::Center, frame, content_size),
+            rect(50.0, 35.0, 20.0, 20.0)
+        );
+        assert_eq!(
+            apply_gravity(ContentsGravity::TopLeft, frame, content_size),
+            rect(10.0, 20.0, 20.0, 20.0)
+        );
+        assert_eq!(
+            apply_gravity(ContentsGravity::BottomRight, frame, content_size),
+            rect(90.0, 50.0, 20.0, 20.0)
+        );
+    }
+}
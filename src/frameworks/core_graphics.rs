@@ -13,10 +13,27 @@ pub mod cg_bitmap_context;
 pub mod cg_color;
 pub mod cg_color_space;
 pub mod cg_context;
+pub mod cg_data_consumer;
 pub mod cg_data_provider;
+pub mod cg_font;
+pub mod cg_function;
 pub mod cg_geometry;
+pub mod cg_gradient;
 pub mod cg_image;
+pub mod cg_path;
+pub mod cg_pattern;
+pub mod cg_pdf_context;
+pub mod cg_pdf_document;
+pub mod cg_pdf_page;
+pub mod cg_shading;
+mod pdf;
+mod pixel_format;
 
 pub type CGFloat = f32;
 
 pub use cg_geometry::{CGPoint, CGRect, CGSize};
+
+#[derive(Default)]
+pub struct State {
+    cg_font: cg_font::State,
+}
@@ -6,33 +6,64 @@
 //! `CGBitmapContext.h`
 
 use super::cg_affine_transform::{CGAffineTransform, CGAffineTransformIdentity};
-use super::cg_color_space::{
-    kCGColorSpaceGenericGray, kCGColorSpaceGenericRGB, CGColorSpaceHostObject, CGColorSpaceRef,
+use super::cg_color_space::{CGColorSpaceHostObject, CGColorSpaceRef};
+use super::cg_context::{
+    kCGBlendModeClear, kCGBlendModeColor, kCGBlendModeColorBurn, kCGBlendModeColorDodge,
+    kCGBlendModeCopy, kCGBlendModeDarken, kCGBlendModeDestinationAtop, kCGBlendModeDestinationIn,
+    kCGBlendModeDestinationOut, kCGBlendModeDestinationOver, kCGBlendModeDifference,
+    kCGBlendModeExclusion, kCGBlendModeHardLight, kCGBlendModeHue, kCGBlendModeLighten,
+    kCGBlendModeLuminosity, kCGBlendModeMultiply, kCGBlendModeNormal, kCGBlendModeOverlay,
+    kCGBlendModePlusDarker, kCGBlendModePlusLighter, kCGBlendModeSaturation, kCGBlendModeScreen,
+    kCGBlendModeSoftLight, kCGBlendModeSourceAtop, kCGBlendModeSourceIn, kCGBlendModeSourceOut,
+    kCGBlendModeXOR, kCGInterpolationNone, kCGTextClip, kCGTextFill, kCGTextFillClip,
+    kCGTextFillStroke, kCGTextFillStrokeClip, kCGTextInvisible, kCGTextStroke, kCGTextStrokeClip,
+    CGBlendMode, CGContextHostObject, CGContextRef, CGContextState, CGContextSubclass,
+    CGInterpolationQuality, Shadow,
 };
-use super::cg_context::{CGContextHostObject, CGContextRef, CGContextSubclass};
+use super::cg_font::CGGlyph;
 use super::cg_image::{
-    self, kCGBitmapAlphaInfoMask, kCGBitmapByteOrderMask, kCGImageAlphaFirst, kCGImageAlphaLast,
-    kCGImageAlphaNone, kCGImageAlphaNoneSkipFirst, kCGImageAlphaNoneSkipLast, kCGImageAlphaOnly,
-    kCGImageAlphaPremultipliedFirst, kCGImageAlphaPremultipliedLast, kCGImageByteOrder32Big,
-    kCGImageByteOrderDefault, CGBitmapInfo, CGImageAlphaInfo, CGImageRef,
+    self, kCGImageAlphaFirst, kCGImageAlphaLast, kCGImageAlphaPremultipliedLast, CGBitmapInfo,
+    CGImageAlphaInfo, CGImageRef,
 };
-use super::{CGFloat, CGPoint, CGRect};
+use super::cg_path::{self, Coverage, FillRule, Path};
+use super::cg_pattern::PatternTile;
+use super::cg_shading::Shader;
+use super::pixel_format::{ColorModel, PixelFormat};
+use super::{CGFloat, CGPoint, CGRect, CGSize};
 use crate::dyld::{export_c_func, FunctionExports};
+use crate::font::Font;
 use crate::image::{gamma_decode, gamma_encode, Image};
 use crate::mem::{GuestUSize, Mem, MutVoidPtr};
-use crate::objc::ObjC;
+use crate::objc::{nil, ObjC};
 use crate::Environment;
+use std::rc::Rc;
 
-#[derive(Copy, Clone)]
+#[derive(Clone)]
 pub(super) struct CGBitmapContextData {
     pub(super) data: MutVoidPtr,
     pub(super) data_is_owned: bool,
     width: GuestUSize,
     height: GuestUSize,
-    bits_per_component: GuestUSize,
     bytes_per_row: GuestUSize,
-    color_space: &'static str,
-    alpha_info: CGImageAlphaInfo,
+    format: PixelFormat,
+}
+impl CGBitmapContextData {
+    /// Allocate a transparent bitmap with 8-bit RGBA pixels, for contexts that
+    /// don't draw into memory provided by the app.
+    pub(super) fn alloc_rgba(mem: &mut Mem, width: GuestUSize, height: GuestUSize) -> Self {
+        let format =
+            PixelFormat::new(ColorModel::Rgb, 8, None, kCGImageAlphaPremultipliedLast).unwrap();
+        let bytes_per_row = width.checked_mul(4).unwrap();
+        let data = mem.alloc(bytes_per_row.checked_mul(height).unwrap());
+        CGBitmapContextData {
+            data,
+            data_is_owned: true,
+            width,
+            height,
+            bytes_per_row,
+            format,
+        }
+    }
 }
 
 pub fn CGBitmapContextCreate(
@@ -43,21 +74,41 @@ pub fn CGBitmapContextCreate(
     bits_per_component: GuestUSize,
     bytes_per_row: GuestUSize,
     color_space: CGColorSpaceRef,
-    bitmap_info: u32,
+    bitmap_info: CGBitmapInfo,
 ) -> CGContextRef {
-    assert!(bits_per_component == 8); // TODO: support other bit depths
-
-    let color_space = env.objc.borrow::<CGColorSpaceHostObject>(color_space).name;
-
-    let component_count = match color_space {
-        kCGColorSpaceGenericRGB => components_for_rgb(bitmap_info).unwrap(),
-        kCGColorSpaceGenericGray => components_for_gray(bitmap_info).unwrap(),
-        _ => unimplemented!("support other color spaces"),
+    let color_space =
+        (!color_space.is_null()).then(|| env.objc.borrow::<CGColorSpaceHostObject>(color_space));
+    let Some(model) = ColorModel::for_color_space(color_space) else {
+        log!(
+            "CGBitmapContextCreate: unsupported color space {}, returning NULL",
+            color_space.unwrap().name
+        );
+        return nil;
+    };
+    let format = match PixelFormat::new(model, bits_per_component, None, bitmap_info) {
+        Ok(format) => format,
+        Err(reason) => {
+            log!(
+                "CGBitmapContextCreate: unsupported parameter combination ({}): {} bits per \
+                 component, bitmap info {:#x}, returning NULL",
+                reason,
+                bits_per_component,
+                bitmap_info
+            );
+            return nil;
+        }
+    };
+    let Some(min_bytes_per_row) = format.min_bytes_per_row(width) else {
+        log!(
+            "CGBitmapContextCreate: width {} is too large, returning NULL",
+            width
+        );
+        return nil;
     };
 
     let (data, data_is_owned, bytes_per_row) = if data.is_null() {
         let bytes_per_row = if bytes_per_row == 0 {
-            width.checked_mul(component_count).unwrap()
+            min_bytes_per_row
         } else {
             bytes_per_row
         };
@@ -65,9 +116,19 @@ pub fn CGBitmapContextCreate(
         let data = env.mem.alloc(total_size);
         (data, true, bytes_per_row)
     } else {
-        assert!(bytes_per_row != 0);
         (data, false, bytes_per_row)
     };
+    if bytes_per_row < min_bytes_per_row {
+        log!(
+            "CGBitmapContextCreate: {} bytes per row is too small for {} pixels, returning NULL",
+            bytes_per_row,
+            width
+        );
+        if data_is_owned {
+            env.mem.free(data);
+        }
+        return nil;
+    }
 
     let host_object = CGContextHostObject {
         subclass: CGContextSubclass::CGBitmapContext(CGBitmapContextData {
@@ -75,15 +136,13 @@ pub fn CGBitmapContextCreate(
             data_is_owned,
             width,
             height,
-            bits_per_component,
             bytes_per_row,
-            color_space,
-            alpha_info: bitmap_info & kCGBitmapAlphaInfoMask,
+            format,
         }),
-        // TODO: is this the correct default?
-        rgb_fill_color: (0.0, 0.0, 0.0, 0.0),
-        transform: CGAffineTransformIdentity,
+        state: CGContextState::default(),
         state_stack: Vec::new(),
+        path: Path::default(),
+        text_matrix: CGAffineTransformIdentity,
     };
     let isa = env
         .objc
@@ -92,110 +151,71 @@ pub fn CGBitmapContextCreate(
         .alloc_object(isa, Box::new(host_object), &mut env.mem)
 }
 
+fn borrow_bitmap_data(objc: &ObjC, context: CGContextRef) -> &CGBitmapContextData {
+    objc.borrow::<CGContextHostObject>(context)
+        .subclass
+        .bitmap_data()
+}
+
 fn CGBitmapContextGetData(env: &mut Environment, context: CGContextRef) -> MutVoidPtr {
-    let host_obj = env.objc.borrow::<CGContextHostObject>(context);
-    let CGContextSubclass::CGBitmapContext(bitmap_data) = host_obj.subclass;
-    bitmap_data.data
+    borrow_bitmap_data(&env.objc, context).data
 }
 
 pub fn CGBitmapContextGetWidth(env: &mut Environment, context: CGContextRef) -> GuestUSize {
-    let host_obj = env.objc.borrow::<CGContextHostObject>(context);
-    let CGContextSubclass::CGBitmapContext(bitmap_data) = host_obj.subclass;
-    bitmap_data.width
+    borrow_bitmap_data(&env.objc, context).width
 }
 
 pub fn CGBitmapContextGetHeight(env: &mut Environment, context: CGContextRef) -> GuestUSize {
-    let host_obj = env.objc.borrow::<CGContextHostObject>(context);
-    let CGContextSubclass::CGBitmapContext(bitmap_data) = host_obj.subclass;
-    bitmap_data.height
+    borrow_bitmap_data(&env.objc, context).height
 }
 
-pub fn CGBitmapContextCreateImage(env: &mut Environment, context: CGContextRef) -> CGImageRef {
-    // TODO: Image::from_pixel_vec() should not exist, and this function should
-    // support any bitmap format.
-    let host_obj = env.objc.borrow::<CGContextHostObject>(context);
-    let CGContextSubclass::CGBitmapContext(bitmap_data) = host_obj.subclass;
-    assert!(
-        bitmap_data.bits_per_component == 8
-            && bitmap_data.bytes_per_row == bitmap_data.width * 4
-            && bitmap_data.color_space == kCGColorSpaceGenericRGB
-            && matches!(
-                bitmap_data.alpha_info,
-                kCGImageAlphaNoneSkipLast | kCGImageAlphaPremultipliedLast
-            )
-    );
-    let pixels = env
-        .mem
-        .bytes_at(
-            bitmap_data.data.cast(),
-            bitmap_data.bytes_per_row * bitmap_data.height,
-        )
-        .to_vec();
-    cg_image::from_image(
-        env,
-        Image::from_pixel_vec(pixels, (bitmap_data.width, bitmap_data.height)),
-    )
+fn CGBitmapContextGetBytesPerRow(env: &mut Environment, context: CGContextRef) -> GuestUSize {
+    borrow_bitmap_data(&env.objc, context).bytes_per_row
 }
 
-fn components_for_rgb(bitmap_info: CGBitmapInfo) -> Result<GuestUSize, ()> {
-    let byte_order = bitmap_info & kCGBitmapByteOrderMask;
-    if byte_order != kCGImageByteOrderDefault && byte_order != kCGImageByteOrder32Big {
-        return Err(()); // TODO: handle other byte orders
-    }
+fn CGBitmapContextGetBitsPerComponent(env: &mut Environment, context: CGContextRef) -> GuestUSize {
+    borrow_bitmap_data(&env.objc, context)
+        .format
+        .bits_per_component
+}
 
-    let alpha_info = bitmap_info & kCGBitmapAlphaInfoMask;
-    if (alpha_info | byte_order) != bitmap_info {
-        return Err(()); // TODO: handle other cases (float)
-    }
-    match alpha_info & kCGBitmapAlphaInfoMask {
-        kCGImageAlphaNone => Ok(3), // RGB
-        kCGImageAlphaPremultipliedLast
-        | kCGImageAlphaPremultipliedFirst
-        | kCGImageAlphaLast
-        | kCGImageAlphaFirst
-        | kCGImageAlphaNoneSkipLast
-        | kCGImageAlphaNoneSkipFirst => Ok(4), // RGBA/ARGB/RGBX/XRGB
-        kCGImageAlphaOnly => Ok(1), // A
-        _ => Err(()),               // unknown values
-    }
+fn CGBitmapContextGetBitsPerPixel(env: &mut Environment, context: CGContextRef) -> GuestUSize {
+    borrow_bitmap_data(&env.objc, context).format.bits_per_pixel
 }
 
-fn components_for_gray(bitmap_info: CGBitmapInfo) -> Result<GuestUSize, ()> {
-    let byte_order = bitmap_info & kCGBitmapByteOrderMask;
-    if byte_order != kCGImageByteOrderDefault && byte_order != kCGImageByteOrder32Big {
-        return Err(()); // TODO: handle other byte orders
-    }
+fn CGBitmapContextGetBitmapInfo(env: &mut Environment, context: CGContextRef) -> CGBitmapInfo {
+    borrow_bitmap_data(&env.objc, context).format.bitmap_info
+}
 
-    let alpha_info = bitmap_info & kCGBitmapAlphaInfoMask;
-    if (alpha_info | byte_order) != bitmap_info {
-        return Err(()); // TODO: handle other cases (float)
-    }
-    match alpha_info & kCGBitmapAlphaInfoMask {
-        kCGImageAlphaNone => Ok(1), // gray
-        kCGImageAlphaPremultipliedLast
-        | kCGImageAlphaPremultipliedFirst
-        | kCGImageAlphaLast
-        | kCGImageAlphaFirst
-        | kCGImageAlphaNoneSkipLast
-        | kCGImageAlphaNoneSkipFirst => Ok(2), // gray + alpha
-        kCGImageAlphaOnly => Ok(1), // A
-        _ => Err(()),               // unknown values
-    }
+fn CGBitmapContextGetAlphaInfo(env: &mut Environment, context: CGContextRef) -> CGImageAlphaInfo {
+    borrow_bitmap_data(&env.objc, context).format.alpha_info
+}
+
+pub fn CGBitmapContextCreateImage(env: &mut Environment, context: CGContextRef) -> CGImageRef {
+    let image = to_image(env, context);
+    cg_image::from_image(env, image)
 }
 
-fn bytes_per_pixel(data: &CGBitmapContextData) -> GuestUSize {
+/// Copy the contents of a bitmap context into a new [Image], converting them
+/// to its format.
+pub(super) fn to_image(env: &mut Environment, context: CGContextRef) -> Image {
+    // TODO: Image::from_pixel_vec() should not exist.
+    let bitmap_data = borrow_bitmap_data(&env.objc, context);
     let &CGBitmapContextData {
-        bits_per_component,
-        color_space,
-        alpha_info,
+        data,
+        width,
+        height,
+        bytes_per_row,
+        ref format,
         ..
-    } = data;
-    assert!(bits_per_component == 8);
-    match color_space {
-        kCGColorSpaceGenericRGB => components_for_rgb(alpha_info).unwrap(),
-        kCGColorSpaceGenericGray => components_for_gray(alpha_info).unwrap(),
-        _ => unimplemented!("support other color spaces"),
+    } = bitmap_data;
+    let src = env.mem.bytes_at(data.cast(), bytes_per_row * height);
+    if format.is_rgba8() && bytes_per_row == width * 4 {
+        return Image::from_pixel_vec(src.to_vec(), (width, height));
     }
+
+    let pixels = format.to_rgba8(src, (width, height), bytes_per_row, None);
+    Image::from_pixel_vec(pixels, (width, height))
 }
 
 fn get_pixels<'a>(data: &CGBitmapContextData, mem: &'a mut Mem) -> &'a mut [u8] {
@@ -203,101 +223,231 @@ fn get_pixels<'a>(data: &CGBitmapContextData, mem: &'a mut Mem) -> &'a mut [u8]
     mem.bytes_at_mut(data.data.cast(), pixel_data_size)
 }
 
-fn blend_alpha(bg: f32, fg: f32) -> f32 {
-    // Alpha is blended the same way in
-    // premultiplied and straight representation.
-    fg + bg * (1.0 - fg)
+/// Whether colors in this context are stored with premultiplied alpha.
+/// Contexts without an alpha channel are treated as not premultiplied, so
+/// colors for them can still have an alpha value.
+fn is_premultiplied(data: &CGBitmapContextData) -> bool {
+    data.format.is_premultiplied()
 }
 
-/// Blends two RGBA non gamma-encoded values, with straight alpha.
-fn blend_straight(bg: (f32, f32, f32, f32), fg: (f32, f32, f32, f32)) -> (f32, f32, f32, f32) {
-    if fg.3 == 0.0 {
-        // If fg.3 == 0.0 we attempt to blend fully transparent color.
-        bg
+/// Converts a color as represented by the context (see [is_premultiplied]) to
+/// one with premultiplied alpha.
+fn to_premultiplied(
+    data: &CGBitmapContextData,
+    color: (f32, f32, f32, f32),
+) -> (f32, f32, f32, f32) {
+    let (r, g, b, a) = color;
+    if is_premultiplied(data) {
+        color
     } else {
-        let new_a = blend_alpha(bg.3, fg.3); // Can't be 0 if fg.3 != 0
-        (
-            (fg.0 * fg.3 + bg.0 * bg.3 * (1.0 - fg.3)) / new_a,
-            (fg.1 * fg.3 + bg.1 * bg.3 * (1.0 - fg.3)) / new_a,
-            (fg.2 * fg.3 + bg.2 * bg.3 * (1.0 - fg.3)) / new_a,
-            new_a,
-        )
+        (r * a, g * a, b * a, a)
+    }
+}
+
+/// Inverse of [to_premultiplied], for storing a color in the context.
+fn from_premultiplied(
+    data: &CGBitmapContextData,
+    color: (f32, f32, f32, f32),
+) -> (f32, f32, f32, f32) {
+    let (r, g, b, a) = color;
+    match data.format.alpha_info {
+        kCGImageAlphaLast | kCGImageAlphaFirst if a > 0.0 => (r / a, g / a, b / a, a),
+        kCGImageAlphaLast | kCGImageAlphaFirst => (0.0, 0.0, 0.0, 0.0),
+        // Contexts without alpha just get the color composited onto black.
+        _ => color,
     }
 }
 
-/// Blends two RGBA non gamma-encoded values, with premultiplied alpha.
-fn blend_premultiplied(bg: (f32, f32, f32, f32), fg: (f32, f32, f32, f32)) -> (f32, f32, f32, f32) {
-    (
-        fg.0 + bg.0 * (1.0 - fg.3),
-        fg.1 + bg.1 * (1.0 - fg.3),
-        fg.2 + bg.2 * (1.0 - fg.3),
-        blend_alpha(bg.3, fg.3),
-    )
-}
-
-/// per component offsets (r, g, b, a)
-fn pixel_offsets(data: &CGBitmapContextData) -> (usize, usize, usize, Option<usize>) {
-    match data.color_space {
-        kCGColorSpaceGenericRGB => {
-            match data.alpha_info {
-                kCGImageAlphaNone => (0, 1, 2, None),
-                kCGImageAlphaPremultipliedLast | kCGImageAlphaLast => (0, 1, 2, Some(3)),
-                kCGImageAlphaPremultipliedFirst | kCGImageAlphaFirst => (1, 2, 3, Some(0)),
-                kCGImageAlphaNoneSkipLast => (0, 1, 2, None),
-                kCGImageAlphaNoneSkipFirst => (1, 2, 3, None),
-                kCGImageAlphaOnly => (0, 0, 0, Some(0)),
-                _ => unreachable!(), // checked by bytes_per_pixel
+/// Blend function for a separable blend mode, applied to each color
+/// component. `b` is the backdrop and `s` the source, without premultiplied
+/// alpha.
+fn blend_component(mode: CGBlendMode, b: f32, s: f32) -> f32 {
+    let multiply = |b: f32, s: f32| b * s;
+    let screen = |b: f32, s: f32| b + s - b * s;
+    let hard_light = |b: f32, s: f32| {
+        if s <= 0.5 {
+            multiply(b, 2.0 * s)
+        } else {
+            screen(b, 2.0 * s - 1.0)
+        }
+    };
+    match mode {
+        kCGBlendModeMultiply => multiply(b, s),
+        kCGBlendModeScreen => screen(b, s),
+        kCGBlendModeOverlay => hard_light(s, b),
+        kCGBlendModeDarken => b.min(s),
+        kCGBlendModeLighten => b.max(s),
+        kCGBlendModeColorDodge => {
+            if b <= 0.0 {
+                0.0
+            } else if s >= 1.0 {
+                1.0
+            } else {
+                (b / (1.0 - s)).min(1.0)
+            }
+        }
+        kCGBlendModeColorBurn => {
+            if b >= 1.0 {
+                1.0
+            } else if s <= 0.0 {
+                0.0
+            } else {
+                1.0 - ((1.0 - b) / s).min(1.0)
             }
         }
-        kCGColorSpaceGenericGray => {
-            // TODO: this is probably isn't doing RGB to grayscale conversion
-            // properly
-            match data.alpha_info {
-                kCGImageAlphaNone => (0, 0, 0, None),
-                kCGImageAlphaPremultipliedLast | kCGImageAlphaLast => (0, 0, 0, Some(1)),
-                kCGImageAlphaPremultipliedFirst | kCGImageAlphaFirst => (1, 1, 1, Some(0)),
-                kCGImageAlphaNoneSkipLast => (0, 0, 0, None),
-                kCGImageAlphaNoneSkipFirst => (1, 1, 1, None),
-                kCGImageAlphaOnly => (0, 0, 0, Some(0)),
-                _ => unreachable!(), // checked by bytes_per_pixel
+        kCGBlendModeSoftLight => {
+            if s <= 0.5 {
+                b - (1.0 - 2.0 * s) * b * (1.0 - b)
+            } else {
+                let d = if b <= 0.25 {
+                    ((16.0 * b - 12.0) * b + 4.0) * b
+                } else {
+                    b.sqrt()
+                };
+                b + (2.0 * s - 1.0) * (d - b)
             }
         }
-        _ => unimplemented!(),
+        kCGBlendModeHardLight => hard_light(b, s),
+        kCGBlendModeDifference => (b - s).abs(),
+        kCGBlendModeExclusion => b + s - 2.0 * b * s,
+        _ => s,
     }
 }
 
-/// Get gamma-decoded RGBA value.
-fn get_pixel(
-    data: &CGBitmapContextData,
-    pixels: &mut [u8],
-    first_component_idx: usize,
-) -> (f32, f32, f32, f32) {
-    let pixel_offset = pixel_offsets(data);
-    let pixel = (
-        pixels[first_component_idx + pixel_offset.0] as f32 / 255.0,
-        pixels[first_component_idx + pixel_offset.1] as f32 / 255.0,
-        pixels[first_component_idx + pixel_offset.2] as f32 / 255.0,
-        if let Some(alpha_offest) = pixel_offset.3 {
-            pixels[first_component_idx + alpha_offest] as f32 / 255.0
+/// Blend function for the non-separable blend modes (hue, saturation, color,
+/// luminosity). `b` is the backdrop and `s` the source, without
+/// premultiplied alpha.
+fn blend_non_separable(mode: CGBlendMode, b: [f32; 3], s: [f32; 3]) -> [f32; 3] {
+    fn lum(c: [f32; 3]) -> f32 {
+        0.3 * c[0] + 0.59 * c[1] + 0.11 * c[2]
+    }
+    fn sat(c: [f32; 3]) -> f32 {
+        c[0].max(c[1]).max(c[2]) - c[0].min(c[1]).min(c[2])
+    }
+    fn set_lum(c: [f32; 3], l: f32) -> [f32; 3] {
+        let d = l - lum(c);
+        let c = c.map(|x| x + d);
+        // Clip the color back into range while preserving its luminosity.
+        let l = lum(c);
+        let min = c[0].min(c[1]).min(c[2]);
+        let max = c[0].max(c[1]).max(c[2]);
+        let c = if min < 0.0 {
+            c.map(|x| l + (x - l) * l / (l - min))
         } else {
-            1.0
-        },
-    );
+            c
+        };
+        if max > 1.0 {
+            c.map(|x| l + (x - l) * (1.0 - l) / (max - l))
+        } else {
+            c
+        }
+    }
+    fn set_sat(c: [f32; 3], s: f32) -> [f32; 3] {
+        let min = c[0].min(c[1]).min(c[2]);
+        let max = c[0].max(c[1]).max(c[2]);
+        if max > min {
+            c.map(|x| (x - min) * s / (max - min))
+        } else {
+            [0.0; 3]
+        }
+    }
+    match mode {
+        kCGBlendModeHue => set_lum(set_sat(s, sat(b)), lum(b)),
+        kCGBlendModeSaturation => set_lum(set_sat(b, sat(s)), lum(b)),
+        kCGBlendModeColor => set_lum(s, lum(b)),
+        kCGBlendModeLuminosity => set_lum(b, lum(s)),
+        _ => unreachable!(),
+    }
+}
+
+/// Blends two RGBA non gamma-encoded values with premultiplied alpha,
+/// according to a `CGBlendMode`. These are the blend modes from the PDF
+/// specification plus Porter and Duff's compositing operators.
+fn blend(
+    bg: (f32, f32, f32, f32),
+    fg: (f32, f32, f32, f32),
+    mode: CGBlendMode,
+) -> (f32, f32, f32, f32) {
+    let (sa, da) = (fg.3, bg.3);
+    // result = source × fa + destination × fb
+    let porter_duff = |fa: f32, fb: f32| {
+        (
+            fg.0 * fa + bg.0 * fb,
+            fg.1 * fa + bg.1 * fb,
+            fg.2 * fa + bg.2 * fb,
+            sa * fa + da * fb,
+        )
+    };
+    match mode {
+        kCGBlendModeClear => (0.0, 0.0, 0.0, 0.0),
+        kCGBlendModeCopy => fg,
+        kCGBlendModeSourceIn => porter_duff(da, 0.0),
+        kCGBlendModeSourceOut => porter_duff(1.0 - da, 0.0),
+        kCGBlendModeSourceAtop => porter_duff(da, 1.0 - sa),
+        kCGBlendModeDestinationOver => porter_duff(1.0 - da, 1.0),
+        kCGBlendModeDestinationIn => porter_duff(0.0, sa),
+        kCGBlendModeDestinationOut => porter_duff(0.0, 1.0 - sa),
+        kCGBlendModeDestinationAtop => porter_duff(1.0 - da, sa),
+        kCGBlendModeXOR => porter_duff(1.0 - da, 1.0 - sa),
+        kCGBlendModePlusLighter => (
+            (fg.0 + bg.0).min(1.0),
+            (fg.1 + bg.1).min(1.0),
+            (fg.2 + bg.2).min(1.0),
+            (sa + da).min(1.0),
+        ),
+        kCGBlendModePlusDarker => {
+            let a = (sa + da).min(1.0);
+            (
+                (a - (da - bg.0) - (sa - fg.0)).max(0.0),
+                (a - (da - bg.1) - (sa - fg.1)).max(0.0),
+                (a - (da - bg.2) - (sa - fg.2)).max(0.0),
+                a,
+            )
+        }
+        kCGBlendModeNormal => porter_duff(1.0, 1.0 - sa),
+        _ => {
+            if sa == 0.0 || da == 0.0 {
+                // The blend function only matters where both are present.
+                return porter_duff(1.0, 1.0 - sa);
+            }
+            let unpremultiplied_bg = [bg.0 / da, bg.1 / da, bg.2 / da];
+            let unpremultiplied_fg = [fg.0 / sa, fg.1 / sa, fg.2 / sa];
+            let blended = if (kCGBlendModeHue..=kCGBlendModeLuminosity).contains(&mode) {
+                blend_non_separable(mode, unpremultiplied_bg, unpremultiplied_fg)
+            } else {
+                [0, 1, 2]
+                    .map(|i| blend_component(mode, unpremultiplied_bg[i], unpremultiplied_fg[i]))
+            };
+            let component =
+                |s: f32, b: f32, blended: f32| s * (1.0 - da) + b * (1.0 - sa) + sa * da * blended;
+            (
+                component(fg.0, bg.0, blended[0]),
+                component(fg.1, bg.1, blended[1]),
+                component(fg.2, bg.2, blended[2]),
+                sa + da - sa * da,
+            )
+        }
+    }
+}
 
-    (
-        gamma_decode(pixel.0),
-        gamma_decode(pixel.1),
-        gamma_decode(pixel.2),
-        pixel.3,
-    )
+/// Get gamma-decoded RGBA value.
+fn get_pixel(data: &CGBitmapContextData, row: &[u8], x: GuestUSize) -> (f32, f32, f32, f32) {
+    let (r, g, b, a) = data.format.read(row, x);
+    // Alpha is always linear.
+    (gamma_decode(r), gamma_decode(g), gamma_decode(b), a)
 }
 
+/// Composites `pixel` (linear RGB with premultiplied alpha) onto the pixel at
+/// `coords` using `blend_mode`. `coverage` is how much of the pixel is covered
+/// by what is being drawn: the result is interpolated between the old and new
+/// values accordingly.
 fn put_pixel(
     data: &CGBitmapContextData,
     pixels: &mut [u8],
     coords: (i32, i32),
     pixel: (CGFloat, CGFloat, CGFloat, CGFloat),
-    blend: bool,
+    coverage: CGFloat,
+    blend_mode: CGBlendMode,
 ) {
     let (x, y) = coords;
     if x < 0 || y < 0 {
@@ -312,50 +462,88 @@ fn put_pixel(
     // *seems* like the rows are nonetheless in top-to-bottom order?
     let y = data.height - 1 - y;
 
-    let pixel_size = bytes_per_pixel(data);
-    let first_component_idx = (y * data.bytes_per_row + x * pixel_size) as usize;
+    let row_start = (y * data.bytes_per_row) as usize;
+    let row = &mut pixels[row_start..][..data.bytes_per_row as usize];
 
-    let bg_pixel = get_pixel(data, pixels, first_component_idx);
+    let bg_pixel = to_premultiplied(data, get_pixel(data, row, x));
 
     // Blending like this must be done in linear RGB, so this must come before
     // gamma encoding.
-    let (r, g, b, a) = if blend {
-        match data.alpha_info {
-            kCGImageAlphaLast | kCGImageAlphaFirst => blend_straight(bg_pixel, pixel),
-            kCGImageAlphaPremultipliedLast | kCGImageAlphaPremultipliedFirst => {
-                blend_premultiplied(bg_pixel, pixel)
-            }
-            kCGImageAlphaOnly => (pixel.0, pixel.1, pixel.2, blend_alpha(bg_pixel.3, pixel.3)),
-            _ => pixel,
-        }
-    } else {
-        pixel
-    };
+    let blended = blend(bg_pixel, pixel, blend_mode);
+    let mix = |bg: f32, blended: f32| bg + (blended - bg) * coverage;
+    let (r, g, b, a) = from_premultiplied(
+        data,
+        (
+            mix(bg_pixel.0, blended.0),
+            mix(bg_pixel.1, blended.1),
+            mix(bg_pixel.2, blended.2),
+            mix(bg_pixel.3, blended.3),
+        ),
+    );
 
     // Alpha is always linear.
     let (r, g, b) = (gamma_encode(r), gamma_encode(g), gamma_encode(b));
-    let pixel_offset = pixel_offsets(data);
-    match data.alpha_info {
-        kCGImageAlphaOnly => {
-            pixels[first_component_idx] = (a * 255.0) as u8;
-        }
-        _ => {
-            pixels[first_component_idx + pixel_offset.0] = (r * 255.0) as u8;
-            pixels[first_component_idx + pixel_offset.1] = (g * 255.0) as u8;
-            pixels[first_component_idx + pixel_offset.2] = (b * 255.0) as u8;
-            if let Some(alpha_offset) = pixel_offset.3 {
-                pixels[first_component_idx + alpha_offset] = (a * 255.0) as u8;
+    data.format.write(row, x, (r, g, b, a));
+}
+
+/// Blurs a single-channel image in place. Three box blurs in each direction
+/// approximate a Gaussian blur.
+fn blur(values: &mut [f32], width: usize, height: usize, radius: usize) {
+    if radius == 0 {
+        return;
+    }
+    let mut scratch = vec![0.0; width.max(height)];
+    let mut blur_line = |values: &mut [f32], start: usize, stride: usize, len: usize| {
+        let index = |i: usize| start + i * stride;
+        let mut sum: f32 = (0..=radius.min(len - 1)).map(|i| values[index(i)]).sum();
+        for (i, blurred) in scratch[..len].iter_mut().enumerate() {
+            *blurred = sum / (2 * radius + 1) as f32;
+            if i + radius + 1 < len {
+                sum += values[index(i + radius + 1)];
             }
+            if i >= radius {
+                sum -= values[index(i - radius)];
+            }
+        }
+        for (i, &blurred) in scratch[..len].iter().enumerate() {
+            values[index(i)] = blurred;
+        }
+    };
+    for _ in 0..3 {
+        for y in 0..height {
+            blur_line(values, y * width, 1, width);
+        }
+        for x in 0..width {
+            blur_line(values, x, width, height);
         }
     }
 }
 
+/// When a shadow is set, everything is first drawn into one of these instead
+/// of the bitmap, so the shadow can be computed from the result.
+struct ShadowLayer {
+    shadow: Shadow,
+    /// Linear RGB with premultiplied alpha, one value per pixel of the bitmap.
+    pixels: Vec<(f32, f32, f32, f32)>,
+    /// Bounding box (x_min, y_min, x_max, y_max) of the pixels drawn so far.
+    bounds: Option<(i32, i32, i32, i32)>,
+}
+
 /// Abstract interface for use by host code that wants to draw in a bitmap
-/// context.
+/// context. Clipping, the blend mode and shadows are applied automatically.
 pub struct CGBitmapContextDrawer<'a> {
     bitmap_info: CGBitmapContextData,
     rgb_fill_color: (CGFloat, CGFloat, CGFloat, CGFloat),
+    rgb_stroke_color: (CGFloat, CGFloat, CGFloat, CGFloat),
     transform: CGAffineTransform,
+    alpha: CGFloat,
+    blend_mode: CGBlendMode,
+    clip: Option<Rc<Coverage>>,
+    interpolation_quality: CGInterpolationQuality,
+    shadow_layer: Option<ShadowLayer>,
+    fill_pattern: Option<Rc<PatternTile>>,
+    stroke_pattern: Option<Rc<PatternTile>>,
+    pattern_phase: CGSize,
     pixels: &'a mut [u8],
 }
 impl CGBitmapContextDrawer<'_> {
@@ -364,19 +552,44 @@ impl CGBitmapContextDrawer<'_> {
         mem: &'a mut Mem,
         context: CGContextRef,
     ) -> CGBitmapContextDrawer<'a> {
-        let &CGContextHostObject {
-            subclass: CGContextSubclass::CGBitmapContext(bitmap_info),
+        let host_obj = objc.borrow::<CGContextHostObject>(context);
+        let bitmap_info = host_obj.subclass.bitmap_data().clone();
+        let CGContextState {
             rgb_fill_color,
+            rgb_stroke_color,
             transform,
+            alpha,
+            blend_mode,
+            ref clip,
+            shadow,
+            interpolation_quality,
+            ref fill_pattern,
+            ref stroke_pattern,
+            pattern_phase,
             ..
-        } = objc.borrow(context);
+        } = host_obj.state;
+
+        let shadow_layer = shadow.map(|shadow| ShadowLayer {
+            shadow,
+            pixels: vec![(0.0, 0.0, 0.0, 0.0); (bitmap_info.width * bitmap_info.height) as usize],
+            bounds: None,
+        });
 
         let pixels = get_pixels(&bitmap_info, mem);
 
         CGBitmapContextDrawer {
             bitmap_info,
             rgb_fill_color,
+            rgb_stroke_color,
             transform,
+            alpha,
+            blend_mode,
+            clip: clip.clone(),
+            interpolation_quality,
+            shadow_layer,
+            fill_pattern: fill_pattern.clone(),
+            stroke_pattern: stroke_pattern.clone(),
+            pattern_phase,
             pixels,
         }
     }
@@ -390,30 +603,226 @@ impl CGBitmapContextDrawer<'_> {
     /// Get the current fill color. The returned color is linear RGB, not sRGB.
     /// It has premultiplied alpha if the context does.
     pub fn rgb_fill_color(&self) -> (CGFloat, CGFloat, CGFloat, CGFloat) {
-        let multiply_by = match self.bitmap_info.alpha_info {
-            kCGImageAlphaPremultipliedLast | kCGImageAlphaPremultipliedFirst => {
-                self.rgb_fill_color.3
-            }
-            _ => 1.0,
+        self.linear_color(self.rgb_fill_color)
+    }
+    /// Get the current stroke color. See [Self::rgb_fill_color].
+    pub fn rgb_stroke_color(&self) -> (CGFloat, CGFloat, CGFloat, CGFloat) {
+        self.linear_color(self.rgb_stroke_color)
+    }
+    fn linear_color(
+        &self,
+        color: (CGFloat, CGFloat, CGFloat, CGFloat),
+    ) -> (CGFloat, CGFloat, CGFloat, CGFloat) {
+        let alpha = color.3 * self.alpha;
+        let multiply_by = if is_premultiplied(&self.bitmap_info) {
+            alpha
+        } else {
+            1.0
         };
         // Multiplying before decoding matches the Simulator's output.
         (
-            gamma_decode(self.rgb_fill_color.0 * multiply_by),
-            gamma_decode(self.rgb_fill_color.1 * multiply_by),
-            gamma_decode(self.rgb_fill_color.2 * multiply_by),
-            self.rgb_fill_color.3, // alpha is always linear
+            gamma_decode(color.0 * multiply_by),
+            gamma_decode(color.1 * multiply_by),
+            gamma_decode(color.2 * multiply_by),
+            alpha, // alpha is always linear
         )
     }
-    /// Set the pixel at `coords` to `color`. `color` must be linear RGB, not
-    /// sRGB! Note that `coords` are absolute: you must do transformation
-    /// yourself.
-    pub fn put_pixel(
+    /// Get the context's global alpha.
+    pub fn alpha(&self) -> CGFloat {
+        self.alpha
+    }
+    /// Blend `color` into the pixel at `coords`, in proportion to `coverage`
+    /// (0 to 1, for anti-aliasing). `color` must be linear RGB, not sRGB, and
+    /// have premultiplied alpha only if the context does (like
+    /// [Self::rgb_fill_color]). Note that `coords` are absolute: you must do
+    /// transformation yourself.
+    pub fn blend_pixel(
+        &mut self,
+        coords: (i32, i32),
+        color: (CGFloat, CGFloat, CGFloat, CGFloat),
+        coverage: CGFloat,
+    ) {
+        let color = to_premultiplied(&self.bitmap_info, color);
+        self.composite(coords, color, coverage, self.blend_mode);
+    }
+    /// Blend `color` (as returned by [Self::rgb_fill_color]) into the pixels
+    /// covered by `coverage`, in proportion to how much they are covered.
+    pub fn fill_coverage(
+        &mut self,
+        coverage: &Coverage,
+        color: (CGFloat, CGFloat, CGFloat, CGFloat),
+    ) {
+        for (coords, amount) in coverage.iter() {
+            self.blend_pixel(coords, color, amount);
+        }
+    }
+
+    /// Paint the pixels covered by `coverage` with the current fill color or
+    /// pattern, or the stroke one if `stroke` is [true].
+    fn paint_coverage(&mut self, coverage: &Coverage, stroke: bool) {
+        let pattern = if stroke {
+            self.stroke_pattern.clone()
+        } else {
+            self.fill_pattern.clone()
+        };
+        let Some(pattern) = pattern else {
+            let color = if stroke {
+                self.rgb_stroke_color()
+            } else {
+                self.rgb_fill_color()
+            };
+            self.fill_coverage(coverage, color);
+            return;
+        };
+        let alpha = self.alpha;
+        let CGSize {
+            width: phase_x,
+            height: phase_y,
+        } = self.pattern_phase;
+        for ((x, y), amount) in coverage.iter() {
+            let point = CGPoint {
+                x: x as CGFloat + 0.5 - phase_x,
+                y: y as CGFloat + 0.5 - phase_y,
+            };
+            let (r, g, b, a) = pattern.color_at(point);
+            let color = (r * alpha, g * alpha, b * alpha, a * alpha);
+            self.composite((x, y), color, amount, self.blend_mode);
+        }
+    }
+
+    /// Draw a pixel with a color that has premultiplied alpha, going through
+    /// the shadow layer if there is one.
+    fn composite(
         &mut self,
         coords: (i32, i32),
         color: (CGFloat, CGFloat, CGFloat, CGFloat),
-        blend: bool,
+        coverage: CGFloat,
+        blend_mode: CGBlendMode,
     ) {
-        put_pixel(&self.bitmap_info, self.pixels, coords, color, blend)
+        if coverage <= 0.0 {
+            return;
+        }
+        let Some(layer) = self.shadow_layer.as_mut() else {
+            self.composite_onto_bitmap(coords, color, coverage, blend_mode);
+            return;
+        };
+
+        let (x, y) = coords;
+        if x < 0
+            || y < 0
+            || x as GuestUSize >= self.bitmap_info.width
+            || y as GuestUSize >= self.bitmap_info.height
+        {
+            return;
+        }
+        let idx = y as usize * self.bitmap_info.width as usize + x as usize;
+        // The blend mode is used when compositing the layer onto the bitmap.
+        let bg = layer.pixels[idx];
+        let blended = blend(bg, color, kCGBlendModeNormal);
+        let mix = |bg: f32, blended: f32| bg + (blended - bg) * coverage;
+        layer.pixels[idx] = (
+            mix(bg.0, blended.0),
+            mix(bg.1, blended.1),
+            mix(bg.2, blended.2),
+            mix(bg.3, blended.3),
+        );
+        layer.bounds = Some(match layer.bounds {
+            Some((x_min, y_min, x_max, y_max)) => {
+                (x_min.min(x), y_min.min(y), x_max.max(x), y_max.max(y))
+            }
+            None => (x, y, x, y),
+        });
+    }
+
+    /// Draw a pixel with a color that has premultiplied alpha directly onto
+    /// the bitmap, applying the clip.
+    fn composite_onto_bitmap(
+        &mut self,
+        coords: (i32, i32),
+        color: (CGFloat, CGFloat, CGFloat, CGFloat),
+        coverage: CGFloat,
+        blend_mode: CGBlendMode,
+    ) {
+        let coverage = match self.clip {
+            Some(ref clip) => coverage * clip.get(coords),
+            None => coverage,
+        };
+        if coverage <= 0.0 {
+            return;
+        }
+        put_pixel(
+            &self.bitmap_info,
+            self.pixels,
+            coords,
+            color,
+            coverage,
+            blend_mode,
+        )
+    }
+
+    /// Draw the shadow of everything in the shadow layer, then the layer
+    /// itself, if there is one.
+    fn finish_shadow_layer(&mut self) {
+        let Some(layer) = self.shadow_layer.take() else {
+            return;
+        };
+        let Some((x_min, y_min, x_max, y_max)) = layer.bounds else {
+            return;
+        };
+        let width = self.bitmap_info.width as usize;
+        let Shadow {
+            offset,
+            blur: blur_size,
+            color,
+        } = layer.shadow;
+
+        // The blur size is roughly twice the standard deviation of a Gaussian
+        // blur. Three passes of a box blur with this radius have about the same
+        // standard deviation.
+        let sigma = blur_size / 2.0;
+        let radius = ((sigma * sigma + 0.25).sqrt() - 0.5).round().max(0.0) as usize;
+        let spread = 3 * radius as i32;
+
+        let (shadow_x, shadow_y) = (x_min - spread, y_min - spread);
+        let shadow_width = (x_max - x_min + 1 + 2 * spread) as usize;
+        let shadow_height = (y_max - y_min + 1 + 2 * spread) as usize;
+        let mut shadow_alpha = vec![0.0; shadow_width * shadow_height];
+        for y in y_min..=y_max {
+            for x in x_min..=x_max {
+                let shadow_idx = (y - shadow_y) as usize * shadow_width + (x - shadow_x) as usize;
+                shadow_alpha[shadow_idx] = layer.pixels[y as usize * width + x as usize].3;
+            }
+        }
+        blur(&mut shadow_alpha, shadow_width, shadow_height, radius);
+
+        // Multiplying before decoding matches how fill colors are handled.
+        let (r, g, b, a) = color;
+        let shadow_color = (
+            gamma_decode(r * a),
+            gamma_decode(g * a),
+            gamma_decode(b * a),
+            a,
+        );
+        let (dx, dy) = (offset.width.round() as i32, offset.height.round() as i32);
+        for (i, &amount) in shadow_alpha.iter().enumerate() {
+            if amount <= 0.0 {
+                continue;
+            }
+            let x = shadow_x + (i % shadow_width) as i32 + dx;
+            let y = shadow_y + (i / shadow_width) as i32 + dy;
+            let (r, g, b, a) = shadow_color;
+            let color = (r * amount, g * amount, b * amount, a * amount);
+            self.composite_onto_bitmap((x, y), color, 1.0, self.blend_mode);
+        }
+
+        for y in y_min..=y_max {
+            for x in x_min..=x_max {
+                let color = layer.pixels[y as usize * width + x as usize];
+                if color.3 > 0.0 {
+                    self.composite_onto_bitmap((x, y), color, 1.0, self.blend_mode);
+                }
+            }
+        }
     }
 
     /// Takes a [CGRect] and applies the current transform to it, and iterates
@@ -461,10 +870,16 @@ impl CGBitmapContextDrawer<'_> {
     }
 }
 
+impl Drop for CGBitmapContextDrawer<'_> {
+    fn drop(&mut self) {
+        self.finish_shadow_layer();
+    }
+}
+
 #[cfg(test)]
 #[test]
 fn test_iter_transformed_pixels() {
-    use super::CGSize;
+    use super::cg_context::kCGInterpolationDefault;
 
     fn make_context(
         width: GuestUSize,
@@ -477,13 +892,20 @@ fn test_iter_transformed_pixels() {
                 data_is_owned: false,
                 width,
                 height,
-                bits_per_component: 8,
                 bytes_per_row: 3 * width,
-                color_space: "kCGColorSpaceGenericRGB",
-                alpha_info: 0,
+                format: PixelFormat::new(ColorModel::Rgb, 8, None, 0).unwrap(),
             },
             rgb_fill_color: (0.0, 0.0, 0.0, 0.0),
+            rgb_stroke_color: (0.0, 0.0, 0.0, 0.0),
             transform,
+            alpha: 1.0,
+            blend_mode: kCGBlendModeNormal,
+            clip: None,
+            interpolation_quality: kCGInterpolationDefault,
+            shadow_layer: None,
+            fill_pattern: None,
+            stroke_pattern: None,
+            pattern_phase: CGSize::default(),
             pixels: &mut [],
         }
     }
@@ -557,21 +979,143 @@ fn test_iter_transformed_pixels() {
         .eq(inverted_square_2x2_at_0_0.clone().into_iter()));
 }
 
+/// Maximum distance, in pixels, between a curve and the line segments used to
+/// approximate it when drawing.
+const FLATTENING_TOLERANCE: CGFloat = 0.1;
+
+fn rasterize_path(path: &Path, rule: FillRule, size: (GuestUSize, GuestUSize)) -> Coverage {
+    let polygons: Vec<_> = path
+        .flatten(FLATTENING_TOLERANCE)
+        .into_iter()
+        .map(|polyline| polyline.points)
+        .collect();
+    cg_path::rasterize(&polygons, rule, size)
+}
+
 /// Implementation of `CGContextFillRect` (`clear` == [false]) and
 /// `CGContextClearRect` (`clear` == [true]) for `CGBitmapContext`.
 pub(super) fn fill_rect(env: &mut Environment, context: CGContextRef, rect: CGRect, clear: bool) {
     let mut drawer = CGBitmapContextDrawer::new(&env.objc, &mut env.mem, context);
-    let color = if clear {
-        (0.0, 0.0, 0.0, 0.0)
+    let mut path = Path::default();
+    path.add_transformed(drawer.transform, |path| path.add_rect(rect));
+    let coverage = rasterize_path(&path, FillRule::Winding, (drawer.width(), drawer.height()));
+    if clear {
+        // Clearing ignores the blend mode and shadow.
+        for (coords, amount) in coverage.iter() {
+            drawer.composite_onto_bitmap(coords, (0.0, 0.0, 0.0, 0.0), amount, kCGBlendModeCopy);
+        }
     } else {
-        drawer.rgb_fill_color()
+        drawer.paint_coverage(&coverage, false);
+    }
+}
+
+/// Implementation of `CGContextFillPath` and `CGContextEOFillPath` for
+/// `CGBitmapContext`. `path` is in device space.
+pub(super) fn fill_path(env: &mut Environment, context: CGContextRef, path: &Path, rule: FillRule) {
+    let mut drawer = CGBitmapContextDrawer::new(&env.objc, &mut env.mem, context);
+    let coverage = rasterize_path(path, rule, (drawer.width(), drawer.height()));
+    drawer.paint_coverage(&coverage, false);
+}
+
+/// Implementation of `CGContextStrokePath` for `CGBitmapContext`. `path` is in
+/// device space.
+pub(super) fn stroke_path(env: &mut Environment, context: CGContextRef, path: &Path) {
+    let host_obj = env.objc.borrow::<CGContextHostObject>(context);
+    let mut style = host_obj.state.stroke_style.clone();
+    let transform = host_obj.state.transform;
+
+    // The line width and dash pattern are in user space, so the path is
+    // stroked there and the result transformed back to device space.
+    let scale = transform.a * transform.d - transform.b * transform.c;
+    let scale = scale.abs().sqrt().max(f32::EPSILON);
+    if style.width <= 0.0 {
+        // Zero means the thinnest line the device can draw.
+        style.width = 1.0 / scale;
+    }
+    let tolerance = FLATTENING_TOLERANCE / scale;
+    let polylines = path.transform(transform.invert()).flatten(tolerance);
+    let polygons: Vec<Vec<CGPoint>> = cg_path::stroke(polylines, &style, tolerance)
+        .into_iter()
+        .map(|polygon| {
+            polygon
+                .into_iter()
+                .map(|point| transform.apply_to_point(point))
+                .collect()
+        })
+        .collect();
+
+    let mut drawer = CGBitmapContextDrawer::new(&env.objc, &mut env.mem, context);
+    let coverage = cg_path::rasterize(
+        &polygons,
+        FillRule::Winding,
+        (drawer.width(), drawer.height()),
+    );
+    drawer.paint_coverage(&coverage, true);
+}
+
+/// Implementation of `CGContextDrawShading`, `CGContextDrawLinearGradient` and
+/// `CGContextDrawRadialGradient` for `CGBitmapContext`.
+pub(super) fn draw_shader(env: &mut Environment, context: CGContextRef, shader: &Shader) {
+    let mut drawer = CGBitmapContextDrawer::new(&env.objc, &mut env.mem, context);
+    let to_user_space = drawer.transform.invert();
+
+    // A shading covers everything, so only the clip limits what is drawn.
+    let (width, height) = (drawer.width() as i32, drawer.height() as i32);
+    let (x_range, y_range) = match drawer.clip {
+        Some(ref clip) => {
+            let ((x, y), (clip_width, clip_height)) = clip.bounds();
+            (
+                x.max(0)..(x + clip_width as i32).min(width),
+                y.max(0)..(y + clip_height as i32).min(height),
+            )
+        }
+        None => (0..width, 0..height),
     };
-    // TODO: correct anti-aliasing
-    for ((x, y), _) in drawer.iter_transformed_pixels(rect) {
-        drawer.put_pixel((x, y), color, /* blend: */ !clear)
+
+    for y in y_range {
+        for x in x_range.clone() {
+            let point = to_user_space.apply_to_point(CGPoint {
+                x: x as CGFloat + 0.5,
+                y: y as CGFloat + 0.5,
+            });
+            if let Some(color) = shader.color_at(point) {
+                let color = drawer.linear_color(color);
+                drawer.blend_pixel((x, y), color, 1.0);
+            }
+        }
     }
 }
 
+/// Samples an image with bilinear filtering. `at` is in pixels, with (0, 0)
+/// being the top-left corner of the image. The edge pixels are extended
+/// outwards.
+fn sample_bilinear(image: &Image, at: (f32, f32)) -> Option<(f32, f32, f32, f32)> {
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return None;
+    }
+    // Pixel centres are at half-integer co-ordinates.
+    let (x, y) = (at.0 - 0.5, at.1 - 0.5);
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let texel = |dx: i32, dy: i32| {
+        let x = (x0 as i32 + dx).clamp(0, width as i32 - 1);
+        let y = (y0 as i32 + dy).clamp(0, height as i32 - 1);
+        image.get_pixel((x, y)).unwrap()
+    };
+    let mix = |a: (f32, f32, f32, f32), b: (f32, f32, f32, f32), t: f32| {
+        (
+            a.0 + (b.0 - a.0) * t,
+            a.1 + (b.1 - a.1) * t,
+            a.2 + (b.2 - a.2) * t,
+            a.3 + (b.3 - a.3) * t,
+        )
+    };
+    let top = mix(texel(0, 0), texel(1, 0), fx);
+    let bottom = mix(texel(0, 1), texel(1, 1), fx);
+    Some(mix(top, bottom, fy))
+}
+
 /// Implementation of `CGContextDrawImage` for `CGBitmapContext`.
 pub(super) fn draw_image(
     env: &mut Environment,
@@ -579,10 +1123,18 @@ pub(super) fn draw_image(
     rect: CGRect,
     image: CGImageRef,
 ) {
+    let is_mask = cg_image::is_mask(&env.objc, image);
     let image = cg_image::borrow_image(&env.objc, image);
 
     let mut drawer = CGBitmapContextDrawer::new(&env.objc, &mut env.mem, context);
 
+    // Image masks are painted like a shape, with the fill color.
+    if is_mask {
+        let coverage = sample_mask(&drawer, rect, image, true);
+        drawer.paint_coverage(&coverage, false);
+        return;
+    }
+
     //let _ = std::fs::write(
     //  format!(
     //      "image-{:?}-{:?}.data",
@@ -602,16 +1154,27 @@ pub(super) fn draw_image(
     //);
 
     let (image_width, image_height) = image.dimensions();
-
-    // TODO: non-nearest-neighbour filtering? (what does CG actually do?)
+    let nearest = drawer.interpolation_quality == kCGInterpolationNone;
 
     for ((x, y), (texel_x, texel_y)) in drawer.iter_transformed_pixels(rect) {
-        let texel_x = (image_width as f32 * texel_x) as i32;
+        let texel_x = image_width as f32 * texel_x;
         // Image is in top-to-bottom order, but the bitmap is bottom-to-top
-        let texel_y = (image_height as f32 * (1.0 - texel_y)) as i32;
+        let texel_y = image_height as f32 * (1.0 - texel_y);
         // FIXME: might need alpha format conversion here
-        if let Some(color) = image.get_pixel((texel_x, texel_y)) {
-            drawer.put_pixel((x, y), color, /* blend: */ true)
+        let texel = if nearest {
+            image.get_pixel((texel_x as i32, texel_y as i32))
+        } else {
+            sample_bilinear(image, (texel_x, texel_y))
+        };
+        if let Some((r, g, b, a)) = texel {
+            // Pixels have premultiplied alpha, so all components are scaled.
+            let alpha = drawer.alpha();
+            drawer.composite(
+                (x, y),
+                (r * alpha, g * alpha, b * alpha, a * alpha),
+                1.0,
+                drawer.blend_mode,
+            )
         }
     }
 
@@ -625,13 +1188,208 @@ pub(super) fn draw_image(
     //);
 }
 
+/// Narrows the clipping area of a context to the part also covered by
+/// `coverage`.
+fn intersect_clip(objc: &mut ObjC, context: CGContextRef, coverage: Coverage) {
+    let host_obj = objc.borrow_mut::<CGContextHostObject>(context);
+    let clip = match host_obj.state.clip.take() {
+        Some(clip) => clip.intersect(&coverage),
+        None => coverage,
+    };
+    host_obj.state.clip = Some(Rc::new(clip));
+}
+
+/// Implementation of `CGContextClip` and `CGContextEOClip` for
+/// `CGBitmapContext`. `path` is in device space.
+pub(super) fn clip_to_path(
+    env: &mut Environment,
+    context: CGContextRef,
+    path: &Path,
+    rule: FillRule,
+) {
+    let host_obj = env.objc.borrow::<CGContextHostObject>(context);
+    let coverage = rasterize_path(path, rule, dimensions(host_obj.subclass.bitmap_data()));
+    intersect_clip(&mut env.objc, context, coverage);
+}
+
+/// Sample a mask drawn into `rect` at each pixel it covers. Pixels outside
+/// the rect have a coverage of zero.
+fn sample_mask(
+    drawer: &CGBitmapContextDrawer,
+    rect: CGRect,
+    image: &Image,
+    is_mask: bool,
+) -> Coverage {
+    let (image_width, image_height) = image.dimensions();
+
+    let mut samples = Vec::new();
+    for ((x, y), (texel_x, texel_y)) in drawer.iter_transformed_pixels(rect) {
+        let texel_x = (image_width as f32 * texel_x) as i32;
+        // Image is in top-to-bottom order, but the bitmap is bottom-to-top
+        let texel_y = (image_height as f32 * (1.0 - texel_y)) as i32;
+        let value = cg_image::mask_value(image, is_mask, (texel_x, texel_y));
+        samples.push(((x, y), value));
+    }
+    coverage_from_samples(samples)
+}
+
+/// Collects per-pixel coverage values into a [Coverage] that just fits them.
+/// If a pixel appears more than once, the greatest value is used.
+fn coverage_from_samples(samples: Vec<((i32, i32), f32)>) -> Coverage {
+    if samples.is_empty() {
+        return Coverage::from_fn((0, 0), (0, 0), |_| 0.0);
+    }
+    let (mut x_min, mut y_min, mut x_max, mut y_max) = (i32::MAX, i32::MAX, i32::MIN, i32::MIN);
+    for &((x, y), _) in &samples {
+        (x_min, y_min) = (x_min.min(x), y_min.min(y));
+        (x_max, y_max) = (x_max.max(x), y_max.max(y));
+    }
+    let width = (x_max - x_min + 1) as usize;
+    let height = (y_max - y_min + 1) as usize;
+    let mut values = vec![0.0f32; width * height];
+    for ((x, y), value) in samples {
+        let idx = (y - y_min) as usize * width + (x - x_min) as usize;
+        values[idx] = values[idx].max(value);
+    }
+    Coverage::from_fn((x_min, y_min), (width as u32, height as u32), |(x, y)| {
+        values[(y - y_min) as usize * width + (x - x_min) as usize]
+    })
+}
+
+/// Implementation of `CGContextClipToMask` for `CGBitmapContext`. Lighter
+/// parts of the mask image, or the unmasked parts of an image mask, are where
+/// drawing is allowed.
+pub(super) fn clip_to_mask(
+    env: &mut Environment,
+    context: CGContextRef,
+    rect: CGRect,
+    mask: CGImageRef,
+) {
+    let is_mask = cg_image::is_mask(&env.objc, mask);
+    let image = cg_image::borrow_image(&env.objc, mask);
+    let drawer = CGBitmapContextDrawer::new(&env.objc, &mut env.mem, context);
+    let coverage = sample_mask(&drawer, rect, image, is_mask);
+    drop(drawer);
+    intersect_clip(&mut env.objc, context, coverage);
+}
+
+/// Approximates the stroked outline of a shape, with lines `radius` pixels
+/// either side of its edges, as the difference between the shape grown and
+/// shrunk by that amount.
+/// TODO: Stroke the actual glyph outlines instead.
+fn outline_coverage(coverage: &Coverage, radius: i32) -> Coverage {
+    let ((x, y), (width, height)) = coverage.bounds();
+    let size = (width + 2 * radius as u32, height + 2 * radius as u32);
+    Coverage::from_fn((x - radius, y - radius), size, |(x, y)| {
+        let (mut min, mut max) = (1.0f32, 0.0f32);
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                let value = coverage.get((x + dx, y + dy));
+                (min, max) = (min.min(value), max.max(value));
+            }
+        }
+        max - min
+    })
+}
+
+/// Glyphs drawn with the same font and text matrix, for [show_glyphs].
+pub(super) struct GlyphRun {
+    pub(super) font: Rc<Font>,
+    pub(super) font_size: CGFloat,
+    /// Transform from text space to user space.
+    pub(super) text_matrix: CGAffineTransform,
+    /// Each glyph and the position of its origin in text space.
+    pub(super) glyphs: Vec<(CGGlyph, CGPoint)>,
+}
+
+/// Implementation of `CGContextShowGlyphsAtPositions` and the other text
+/// drawing functions for `CGBitmapContext`. The runs are drawn together using
+/// the context's text drawing mode, so that they make up a single clip.
+pub(super) fn show_glyphs(env: &mut Environment, context: CGContextRef, runs: &[GlyphRun]) {
+    let host_obj = env.objc.borrow::<CGContextHostObject>(context);
+    let line_width = host_obj.state.stroke_style.width;
+    let (fill, stroke, clip) = match host_obj.state.text_drawing_mode {
+        kCGTextFill => (true, false, false),
+        kCGTextStroke => (false, true, false),
+        kCGTextFillStroke => (true, true, false),
+        kCGTextInvisible => (false, false, false),
+        kCGTextFillClip => (true, false, true),
+        kCGTextStrokeClip => (false, true, true),
+        kCGTextFillStrokeClip => (true, true, true),
+        kCGTextClip => (false, false, true),
+        _ => unreachable!(), // validated by CGContextSetTextDrawingMode
+    };
+
+    let mut drawer = CGBitmapContextDrawer::new(&env.objc, &mut env.mem, context);
+    let ctm = drawer.transform;
+    let mut samples = Vec::new();
+    for run in runs {
+        let GlyphRun {
+            ref font,
+            font_size,
+            text_matrix,
+            ref glyphs,
+        } = *run;
+        for &(glyph, position) in glyphs {
+            let glyph_to_device = text_matrix.translate(position.x, position.y).concat(ctm);
+            // Glyphs are rasterized at the resolution they will be drawn at, so
+            // that they are sharp even when scaled up.
+            let CGAffineTransform { a, b, c, d, .. } = glyph_to_device;
+            let pixels_per_unit = (a * d - b * c).abs().sqrt();
+            if !pixels_per_unit.is_normal() {
+                continue;
+            }
+            font.draw_glyph(font_size, glyph, pixels_per_unit, |raster_glyph| {
+                let (x, y) = raster_glyph.origin();
+                let (width, height) = raster_glyph.dimensions();
+                let rect = CGRect {
+                    origin: CGPoint {
+                        x: x / pixels_per_unit,
+                        y: y / pixels_per_unit,
+                    },
+                    size: CGSize {
+                        width: width as CGFloat / pixels_per_unit,
+                        height: height as CGFloat / pixels_per_unit,
+                    },
+                };
+                drawer.transform = glyph_to_device;
+                for (coords, (texel_x, texel_y)) in drawer.iter_transformed_pixels(rect) {
+                    let texel_x = (width as f32 * texel_x) as i32;
+                    // Glyph is in top-to-bottom order, but the bitmap is
+                    // bottom-to-top
+                    let texel_y = (height as f32 * (1.0 - texel_y)) as i32;
+                    samples.push((coords, raster_glyph.pixel_at((texel_x, texel_y))));
+                }
+            });
+        }
+    }
+    drawer.transform = ctm;
+
+    let coverage = coverage_from_samples(samples);
+    if fill {
+        drawer.paint_coverage(&coverage, false);
+    }
+    if stroke {
+        let scale = (ctm.a * ctm.d - ctm.b * ctm.c).abs().sqrt();
+        let radius = (line_width * scale / 2.0).round().max(1.0) as i32;
+        drawer.paint_coverage(&outline_coverage(&coverage, radius), true);
+    }
+    drop(drawer);
+    if clip {
+        intersect_clip(&mut env.objc, context, coverage);
+    }
+}
+
+pub(super) fn dimensions(data: &CGBitmapContextData) -> (GuestUSize, GuestUSize) {
+    (data.width, data.height)
+}
+
 #[allow(rustdoc::broken_intra_doc_links)] // https://github.com/rust-lang/rust/issues/83049
 /// Shortcut for [crate::frameworks::core_animation::composition]. This is a
 /// workaround for not having a `&mut Environment` that should eventually be
 /// removed somehow (TODO).
 pub fn get_data(objc: &ObjC, context: CGContextRef) -> (GuestUSize, GuestUSize, MutVoidPtr) {
-    let host_obj = objc.borrow::<CGContextHostObject>(context);
-    let CGContextSubclass::CGBitmapContext(bitmap_data) = host_obj.subclass;
+    let bitmap_data = borrow_bitmap_data(objc, context);
     (bitmap_data.width, bitmap_data.height, bitmap_data.data)
 }
 
@@ -641,4 +1399,9 @@ pub const FUNCTIONS: FunctionExports = &[
     export_c_func!(CGBitmapContextGetData(_)),
     export_c_func!(CGBitmapContextGetWidth(_)),
     export_c_func!(CGBitmapContextGetHeight(_)),
+    export_c_func!(CGBitmapContextGetBytesPerRow(_)),
+    export_c_func!(CGBitmapContextGetBitsPerComponent(_)),
+    export_c_func!(CGBitmapContextGetBitsPerPixel(_)),
+    export_c_func!(CGBitmapContextGetBitmapInfo(_)),
+    export_c_func!(CGBitmapContextGetAlphaInfo(_)),
 ];
@@ -8,11 +8,14 @@
 use crate::dyld::{export_c_func, FunctionExports};
 use crate::frameworks::core_foundation::{CFRelease, CFRetain, CFTypeRef};
 use crate::frameworks::core_graphics::cg_color_space::{
-    kCGColorSpaceGenericRGB, CGColorSpaceHostObject, CGColorSpaceRef,
+    self, kCGColorSpaceGenericRGB, CGColorSpaceHostObject, CGColorSpaceRef, PATTERN_COLOR_SPACE,
+};
+use crate::frameworks::core_graphics::cg_pattern::{
+    CGPatternRef, CGPatternRelease, CGPatternRetain,
 };
 use crate::frameworks::core_graphics::CGFloat;
-use crate::mem::MutPtr;
-use crate::objc::{objc_classes, ClassExports, HostObject, ObjC};
+use crate::mem::{ConstPtr, MutPtr};
+use crate::objc::{nil, objc_classes, ClassExports, HostObject, ObjC};
 use crate::Environment;
 
 pub const CLASSES: ClassExports = objc_classes! {
@@ -23,6 +26,13 @@ pub const CLASSES: ClassExports = objc_classes! {
 // those are just Objective-C types, so we need a class for it, but its name is
 // not visible anywhere.
 @implementation _touchHLE_CGColor: NSObject
+
+- (())dealloc {
+    let pattern = env.objc.borrow::<CGColorHostObject>(this).pattern;
+    CGPatternRelease(env, pattern);
+    env.objc.dealloc_object(this, &mut env.mem)
+}
+
 @end
 
 };
@@ -36,6 +46,9 @@ struct CGColorHostObject {
     g: CGFloat,
     b: CGFloat,
     a: CGFloat,
+    /// For pattern colors, the pattern (strong reference). For uncolored
+    /// patterns, the other fields are the color it is painted with.
+    pattern: CGPatternRef,
 }
 impl HostObject for CGColorHostObject {}
 
@@ -59,18 +72,47 @@ fn CGColorCreate(
     components: MutPtr<CGFloat>,
 ) -> CGColorRef {
     let color_space = env.objc.borrow::<CGColorSpaceHostObject>(space).name;
-    assert_eq!(color_space, kCGColorSpaceGenericRGB);
-    let r = env.mem.read(components);
-    let g = env.mem.read(components + 1);
-    let b = env.mem.read(components + 2);
-    let a = env.mem.read(components + 3);
-    from_rgba(env, (r, g, b, a))
+    let rgba = cg_color_space::read_color(env, color_space, components.cast_const());
+    from_rgba(env, rgba)
+}
+
+fn CGColorCreateWithPattern(
+    env: &mut Environment,
+    space: CGColorSpaceRef,
+    pattern: CGPatternRef,
+    components: ConstPtr<CGFloat>,
+) -> CGColorRef {
+    let pattern_base = env
+        .objc
+        .borrow::<CGColorSpaceHostObject>(space)
+        .pattern_base;
+    let (r, g, b, a) = match pattern_base {
+        Some(base) => cg_color_space::read_color(env, base, components),
+        None => (0.0, 0.0, 0.0, 1.0),
+    };
+    let pattern = CGPatternRetain(env, pattern);
+    let host_obj = Box::new(CGColorHostObject {
+        color_space_name: PATTERN_COLOR_SPACE,
+        r,
+        g,
+        b,
+        a,
+        pattern,
+    });
+    let class = env.objc.get_known_class("_touchHLE_CGColor", &mut env.mem);
+    env.objc.alloc_object(class, host_obj, &mut env.mem)
+}
+
+fn CGColorGetPattern(env: &mut Environment, color: CGColorRef) -> CGPatternRef {
+    env.objc.borrow::<CGColorHostObject>(color).pattern
 }
 
 pub const FUNCTIONS: FunctionExports = &[
     export_c_func!(CGColorRetain(_)),
     export_c_func!(CGColorRelease(_)),
     export_c_func!(CGColorCreate(_, _)),
+    export_c_func!(CGColorCreateWithPattern(_, _, _)),
+    export_c_func!(CGColorGetPattern(_)),
 ];
 
 /// Shortcut for use by `UIColor`: directly construct a `CGColor` instance from
@@ -83,6 +125,7 @@ pub fn from_rgba(env: &mut Environment, rgba: (CGFloat, CGFloat, CGFloat, CGFloa
         g,
         b,
         a,
+        pattern: nil,
     });
     let class = env.objc.get_known_class("_touchHLE_CGColor", &mut env.mem);
     env.objc.alloc_object(class, host_obj, &mut env.mem)
@@ -93,3 +136,9 @@ pub fn to_rgba(objc: &ObjC, color: CGColorRef) -> (CGFloat, CGFloat, CGFloat, CG
     let &CGColorHostObject { r, g, b, a, .. } = objc.borrow(color);
     (r, g, b, a)
 }
+
+/// Get the pattern of a pattern color, if it is one.
+pub(super) fn get_pattern(objc: &ObjC, color: CGColorRef) -> Option<CGPatternRef> {
+    let pattern = objc.borrow::<CGColorHostObject>(color).pattern;
+    (pattern != nil).then_some(pattern)
+}
@@ -5,12 +5,15 @@
  */
 //! `CGColorSpace.h`
 
+use super::CGFloat;
 use crate::dyld::{export_c_func, ConstantExports, FunctionExports, HostConstant};
 use crate::frameworks::core_foundation::cf_string::CFStringRef;
 use crate::frameworks::core_foundation::{CFRelease, CFRetain, CFTypeRef};
 use crate::frameworks::foundation::ns_string;
+use crate::mem::{ConstPtr, GuestUSize};
 use crate::objc::{msg, objc_classes, ClassExports, HostObject};
 use crate::Environment;
+use std::rc::Rc;
 
 pub const CLASSES: ClassExports = objc_classes! {
 
@@ -35,16 +38,63 @@ pub const kCGColorSpaceModelCMYK: CGColorSpaceModel = 2;
 pub const kCGColorSpaceModelLab: CGColorSpaceModel = 3;
 #[allow(dead_code)]
 pub const kCGColorSpaceModelDeviceN: CGColorSpaceModel = 4;
-#[allow(dead_code)]
 pub const kCGColorSpaceModelIndexed: CGColorSpaceModel = 5;
-#[allow(dead_code)]
 pub const kCGColorSpaceModelPattern: CGColorSpaceModel = 6;
 
+#[derive(Clone)]
 pub(super) struct CGColorSpaceHostObject {
     pub(super) name: &'static str,
+    /// For a pattern color space, the color space of the colors that
+    /// uncolored patterns are painted with, if any.
+    pub(super) pattern_base: Option<&'static str>,
+    /// For an indexed color space, the colors (sRGB) of each index.
+    pub(super) palette: Option<Rc<[(CGFloat, CGFloat, CGFloat)]>>,
 }
 impl HostObject for CGColorSpaceHostObject {}
 
+/// Pattern color spaces have no name, so this is used internally instead.
+pub(super) const PATTERN_COLOR_SPACE: &str = "_touchHLE_pattern";
+/// Likewise for indexed color spaces.
+pub(super) const INDEXED_COLOR_SPACE: &str = "_touchHLE_indexed";
+
+/// Number of color components (not counting alpha) in a color space.
+pub(super) fn components_per_color(name: &'static str) -> GuestUSize {
+    match name {
+        kCGColorSpaceGenericGray => 1,
+        kCGColorSpaceGenericRGB => 3,
+        PATTERN_COLOR_SPACE => 0,
+        INDEXED_COLOR_SPACE => 1,
+        _ => unimplemented!(),
+    }
+}
+
+/// Convert color components (including alpha) in a color space to RGBA.
+pub(super) fn to_rgba(
+    name: &'static str,
+    components: &[CGFloat],
+) -> (CGFloat, CGFloat, CGFloat, CGFloat) {
+    match (name, components) {
+        (kCGColorSpaceGenericGray, &[gray, alpha]) => (gray, gray, gray, alpha),
+        (kCGColorSpaceGenericRGB, &[r, g, b, alpha]) => (r, g, b, alpha),
+        _ => panic!(
+            "Unexpected components {:?} for color space {}",
+            components, name
+        ),
+    }
+}
+
+/// Read a color (including alpha) in a color space from guest memory and
+/// convert it to RGBA.
+pub(super) fn read_color(
+    env: &Environment,
+    name: &'static str,
+    components: ConstPtr<CGFloat>,
+) -> (CGFloat, CGFloat, CGFloat, CGFloat) {
+    let count = components_per_color(name) + 1;
+    let components: Vec<CGFloat> = (0..count).map(|i| env.mem.read(components + i)).collect();
+    to_rgba(name, &components)
+}
+
 pub type CGColorSpaceRef = CFTypeRef;
 
 pub fn CGColorSpaceCreateWithName(env: &mut Environment, name: CFStringRef) -> CGColorSpaceRef {
@@ -59,6 +109,8 @@ pub fn CGColorSpaceCreateWithName(env: &mut Environment, name: CFStringRef) -> C
         isa,
         Box::new(CGColorSpaceHostObject {
             name: kCGColorSpaceGenericRGB,
+            pattern_base: None,
+            palette: None,
         }),
         &mut env.mem,
     )
@@ -74,6 +126,8 @@ pub fn CGColorSpaceCreateDeviceRGB(env: &mut Environment) -> CGColorSpaceRef {
         isa,
         Box::new(CGColorSpaceHostObject {
             name: kCGColorSpaceGenericRGB,
+            pattern_base: None,
+            palette: None,
         }),
         &mut env.mem,
     )
@@ -87,6 +141,62 @@ fn CGColorSpaceCreateDeviceGray(env: &mut Environment) -> CGColorSpaceRef {
         isa,
         Box::new(CGColorSpaceHostObject {
             name: kCGColorSpaceGenericGray,
+            pattern_base: None,
+            palette: None,
+        }),
+        &mut env.mem,
+    )
+}
+
+fn CGColorSpaceCreatePattern(env: &mut Environment, base: CGColorSpaceRef) -> CGColorSpaceRef {
+    let pattern_base = if base.is_null() {
+        None
+    } else {
+        Some(env.objc.borrow::<CGColorSpaceHostObject>(base).name)
+    };
+    let isa = env
+        .objc
+        .get_known_class("_touchHLE_CGColorSpace", &mut env.mem);
+    env.objc.alloc_object(
+        isa,
+        Box::new(CGColorSpaceHostObject {
+            name: PATTERN_COLOR_SPACE,
+            pattern_base,
+            palette: None,
+        }),
+        &mut env.mem,
+    )
+}
+
+fn CGColorSpaceCreateIndexed(
+    env: &mut Environment,
+    base: CGColorSpaceRef,
+    last_index: GuestUSize,
+    color_table: ConstPtr<u8>,
+) -> CGColorSpaceRef {
+    let base = env.objc.borrow::<CGColorSpaceHostObject>(base).name;
+    let components = components_per_color(base);
+    // Each entry in the table is a color in the base color space, with
+    // components scaled to 0-255.
+    let palette: Vec<_> = (0..=last_index.min(255))
+        .map(|i| {
+            let mut color: Vec<CGFloat> = (0..components)
+                .map(|j| env.mem.read(color_table + i * components + j) as CGFloat / 255.0)
+                .collect();
+            color.push(1.0);
+            let (r, g, b, _) = to_rgba(base, &color);
+            (r, g, b)
+        })
+        .collect();
+    let isa = env
+        .objc
+        .get_known_class("_touchHLE_CGColorSpace", &mut env.mem);
+    env.objc.alloc_object(
+        isa,
+        Box::new(CGColorSpaceHostObject {
+            name: INDEXED_COLOR_SPACE,
+            pattern_base: None,
+            palette: Some(palette.into()),
         }),
         &mut env.mem,
     )
@@ -110,10 +220,24 @@ pub fn CGColorSpaceGetModel(env: &mut Environment, cs: CGColorSpaceRef) -> CGCol
     match host_object.name {
         kCGColorSpaceGenericGray => kCGColorSpaceModelMonochrome,
         kCGColorSpaceGenericRGB => kCGColorSpaceModelRGB,
+        PATTERN_COLOR_SPACE => kCGColorSpaceModelPattern,
+        INDEXED_COLOR_SPACE => kCGColorSpaceModelIndexed,
         _ => unimplemented!(),
     }
 }
 
+fn CGColorSpaceGetNumberOfComponents(env: &mut Environment, cs: CGColorSpaceRef) -> GuestUSize {
+    components_per_color(env.objc.borrow::<CGColorSpaceHostObject>(cs).name)
+}
+
+fn CGColorSpaceGetColorTableCount(env: &mut Environment, cs: CGColorSpaceRef) -> GuestUSize {
+    let host_object = env.objc.borrow::<CGColorSpaceHostObject>(cs);
+    host_object
+        .palette
+        .as_ref()
+        .map_or(0, |palette| palette.len() as GuestUSize)
+}
+
 pub const kCGColorSpaceGenericRGB: &str = "kCGColorSpaceGenericRGB";
 pub const kCGColorSpaceGenericGray: &str = "kCGColorSpaceGenericGray";
 
@@ -132,7 +256,11 @@ pub const FUNCTIONS: FunctionExports = &[
     export_c_func!(CGColorSpaceCreateWithName(_)),
     export_c_func!(CGColorSpaceCreateDeviceRGB()),
     export_c_func!(CGColorSpaceCreateDeviceGray()),
+    export_c_func!(CGColorSpaceCreatePattern(_)),
+    export_c_func!(CGColorSpaceCreateIndexed(_, _, _)),
     export_c_func!(CGColorSpaceRetain(_)),
     export_c_func!(CGColorSpaceRelease(_)),
     export_c_func!(CGColorSpaceGetModel(_)),
+    export_c_func!(CGColorSpaceGetNumberOfComponents(_)),
+    export_c_func!(CGColorSpaceGetColorTableCount(_)),
 ];
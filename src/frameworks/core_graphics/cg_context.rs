@@ -5,13 +5,30 @@
  */
 //! `CGContext.h`
 
-use super::cg_affine_transform::CGAffineTransform;
+use super::cg_affine_transform::{CGAffineTransform, CGAffineTransformIdentity};
+use super::cg_bitmap_context::{self, GlyphRun};
+use super::cg_color::{self, CGColorRef};
+use super::cg_color_space::{
+    self, kCGColorSpaceGenericGray, CGColorSpaceHostObject, CGColorSpaceRef, PATTERN_COLOR_SPACE,
+};
+use super::cg_font::{self, kCGEncodingFontSpecific, CGFontRef, CGGlyph, CGTextEncoding};
+use super::cg_gradient::{self, CGGradientDrawingOptions, CGGradientRef};
 use super::cg_image::CGImageRef;
-use super::{cg_bitmap_context, CGFloat, CGRect};
+use super::cg_path::{
+    self, CGLineCap, CGLineJoin, CGPathRef, Coverage, FillRule, Path, StrokeStyle,
+};
+use super::cg_pattern::{self, CGPatternRef, PatternTile};
+use super::cg_pdf_context;
+use super::cg_pdf_page::{self, CGPDFPageRef};
+use super::cg_shading::{self, CGShadingRef, ShadingGeometry};
+use super::{CGFloat, CGPoint, CGRect, CGSize};
 use crate::dyld::{export_c_func, FunctionExports};
+use crate::font::Font;
 use crate::frameworks::core_foundation::{CFRelease, CFRetain, CFTypeRef};
+use crate::mem::{ConstPtr, GuestUSize};
 use crate::objc::{objc_classes, ClassExports, HostObject};
 use crate::Environment;
+use std::rc::Rc;
 
 pub const CLASSES: ClassExports = objc_classes! {
 
@@ -24,7 +41,11 @@ pub const CLASSES: ClassExports = objc_classes! {
 
 - (())dealloc {
     let host_obj = env.objc.borrow::<CGContextHostObject>(this);
-    let CGContextSubclass::CGBitmapContext(bitmap_data) = host_obj.subclass;
+    if matches!(host_obj.subclass, CGContextSubclass::CGPDFContext(_)) {
+        cg_pdf_context::finalize(env, this);
+    }
+    let host_obj = env.objc.borrow::<CGContextHostObject>(this);
+    let bitmap_data = host_obj.subclass.bitmap_data();
     if bitmap_data.data_is_owned {
         env.mem.free(bitmap_data.data);
     }
@@ -38,20 +59,166 @@ pub const CLASSES: ClassExports = objc_classes! {
 
 pub(super) struct CGContextHostObject {
     pub(super) subclass: CGContextSubclass,
+    pub(super) state: CGContextState,
+    pub(super) state_stack: Vec<CGContextState>,
+    /// Current path, in device space. Unlike the other state, it is not saved
+    /// and restored with the graphics state.
+    pub(super) path: Path,
+    /// Transform from text space to user space. Its translation is the text
+    /// position. Like the path, this is not part of the graphics state.
+    pub(super) text_matrix: CGAffineTransform,
+}
+impl HostObject for CGContextHostObject {}
+
+/// Graphics state saved and restored by `CGContextSaveGState` and
+/// `CGContextRestoreGState`.
+#[derive(Clone)]
+pub(super) struct CGContextState {
     pub(super) rgb_fill_color: (CGFloat, CGFloat, CGFloat, CGFloat),
+    pub(super) rgb_stroke_color: (CGFloat, CGFloat, CGFloat, CGFloat),
     /// Current transform.
     pub(super) transform: CGAffineTransform,
-    // TODO: keep more states saved once they are implemented
-    pub(super) state_stack: Vec<((CGFloat, CGFloat, CGFloat, CGFloat), CGAffineTransform)>,
+    /// Global alpha applied to everything drawn.
+    pub(super) alpha: CGFloat,
+    /// Line width, caps, joins and dash pattern, in user space.
+    pub(super) stroke_style: StrokeStyle,
+    pub(super) blend_mode: CGBlendMode,
+    /// Clipping area, in device space. [None] means the whole context. This
+    /// is reference-counted because saving the graphics state is common, but
+    /// changing the clip less so.
+    pub(super) clip: Option<Rc<Coverage>>,
+    pub(super) shadow: Option<Shadow>,
+    pub(super) interpolation_quality: CGInterpolationQuality,
+    /// Color spaces used by `CGContextSetFillColor`, `CGContextSetFillPattern`
+    /// and their stroke equivalents.
+    pub(super) fill_color_space: CGColorSpaceHostObject,
+    pub(super) stroke_color_space: CGColorSpaceHostObject,
+    /// Pattern painted instead of the fill color, if any.
+    pub(super) fill_pattern: Option<Rc<PatternTile>>,
+    /// Pattern painted instead of the stroke color, if any.
+    pub(super) stroke_pattern: Option<Rc<PatternTile>>,
+    /// Offset of patterns, in device space.
+    pub(super) pattern_phase: CGSize,
+    /// Font used by the text drawing functions, if one has been set.
+    pub(super) font: Option<Rc<Font>>,
+    /// Font size, in text space.
+    pub(super) font_size: CGFloat,
+    /// Encoding of the text passed to `CGContextShowText`.
+    pub(super) text_encoding: CGTextEncoding,
+    pub(super) text_drawing_mode: CGTextDrawingMode,
+    /// Extra space after each glyph, in text space.
+    pub(super) character_spacing: CGFloat,
+}
+/// The initial color space for colors and patterns (DeviceGray in real CG).
+const DEFAULT_COLOR_SPACE: CGColorSpaceHostObject = CGColorSpaceHostObject {
+    name: kCGColorSpaceGenericGray,
+    pattern_base: None,
+    palette: None,
+};
+impl Default for CGContextState {
+    fn default() -> Self {
+        CGContextState {
+            // TODO: is this the correct default?
+            rgb_fill_color: (0.0, 0.0, 0.0, 0.0),
+            rgb_stroke_color: (0.0, 0.0, 0.0, 1.0),
+            transform: CGAffineTransformIdentity,
+            alpha: 1.0,
+            stroke_style: StrokeStyle::default(),
+            blend_mode: kCGBlendModeNormal,
+            clip: None,
+            shadow: None,
+            interpolation_quality: kCGInterpolationDefault,
+            fill_color_space: DEFAULT_COLOR_SPACE,
+            stroke_color_space: DEFAULT_COLOR_SPACE,
+            fill_pattern: None,
+            stroke_pattern: None,
+            pattern_phase: CGSize::default(),
+            font: None,
+            // TODO: is this the correct default?
+            font_size: 12.0,
+            text_encoding: kCGEncodingFontSpecific,
+            text_drawing_mode: kCGTextFill,
+            character_spacing: 0.0,
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub(super) struct Shadow {
+    /// Offset in device space. Unlike most things, this is not affected by
+    /// the CTM.
+    pub(super) offset: CGSize,
+    pub(super) blur: CGFloat,
+    pub(super) color: (CGFloat, CGFloat, CGFloat, CGFloat),
 }
-impl HostObject for CGContextHostObject {}
 
 pub(super) enum CGContextSubclass {
     CGBitmapContext(cg_bitmap_context::CGBitmapContextData),
+    CGPDFContext(cg_pdf_context::CGPDFContextData),
+}
+impl CGContextSubclass {
+    /// The bitmap that drawing goes to. PDF contexts draw each page into a
+    /// bitmap of their own.
+    pub(super) fn bitmap_data(&self) -> &cg_bitmap_context::CGBitmapContextData {
+        match self {
+            CGContextSubclass::CGBitmapContext(bitmap_data) => bitmap_data,
+            CGContextSubclass::CGPDFContext(pdf_data) => &pdf_data.page,
+        }
+    }
 }
 
 pub type CGContextRef = CFTypeRef;
 
+pub type CGBlendMode = i32;
+pub const kCGBlendModeNormal: CGBlendMode = 0;
+pub const kCGBlendModeMultiply: CGBlendMode = 1;
+pub const kCGBlendModeScreen: CGBlendMode = 2;
+pub const kCGBlendModeOverlay: CGBlendMode = 3;
+pub const kCGBlendModeDarken: CGBlendMode = 4;
+pub const kCGBlendModeLighten: CGBlendMode = 5;
+pub const kCGBlendModeColorDodge: CGBlendMode = 6;
+pub const kCGBlendModeColorBurn: CGBlendMode = 7;
+pub const kCGBlendModeSoftLight: CGBlendMode = 8;
+pub const kCGBlendModeHardLight: CGBlendMode = 9;
+pub const kCGBlendModeDifference: CGBlendMode = 10;
+pub const kCGBlendModeExclusion: CGBlendMode = 11;
+pub const kCGBlendModeHue: CGBlendMode = 12;
+pub const kCGBlendModeSaturation: CGBlendMode = 13;
+pub const kCGBlendModeColor: CGBlendMode = 14;
+pub const kCGBlendModeLuminosity: CGBlendMode = 15;
+pub const kCGBlendModeClear: CGBlendMode = 16;
+pub const kCGBlendModeCopy: CGBlendMode = 17;
+pub const kCGBlendModeSourceIn: CGBlendMode = 18;
+pub const kCGBlendModeSourceOut: CGBlendMode = 19;
+pub const kCGBlendModeSourceAtop: CGBlendMode = 20;
+pub const kCGBlendModeDestinationOver: CGBlendMode = 21;
+pub const kCGBlendModeDestinationIn: CGBlendMode = 22;
+pub const kCGBlendModeDestinationOut: CGBlendMode = 23;
+pub const kCGBlendModeDestinationAtop: CGBlendMode = 24;
+pub const kCGBlendModeXOR: CGBlendMode = 25;
+pub const kCGBlendModePlusDarker: CGBlendMode = 26;
+pub const kCGBlendModePlusLighter: CGBlendMode = 27;
+
+pub type CGTextDrawingMode = i32;
+pub const kCGTextFill: CGTextDrawingMode = 0;
+pub const kCGTextStroke: CGTextDrawingMode = 1;
+pub const kCGTextFillStroke: CGTextDrawingMode = 2;
+pub const kCGTextInvisible: CGTextDrawingMode = 3;
+pub const kCGTextFillClip: CGTextDrawingMode = 4;
+pub const kCGTextStrokeClip: CGTextDrawingMode = 5;
+pub const kCGTextFillStrokeClip: CGTextDrawingMode = 6;
+pub const kCGTextClip: CGTextDrawingMode = 7;
+
+pub type CGInterpolationQuality = i32;
+pub const kCGInterpolationDefault: CGInterpolationQuality = 0;
+pub const kCGInterpolationNone: CGInterpolationQuality = 1;
+#[allow(dead_code)]
+pub const kCGInterpolationLow: CGInterpolationQuality = 2;
+#[allow(dead_code)]
+pub const kCGInterpolationHigh: CGInterpolationQuality = 3;
+#[allow(dead_code)]
+pub const kCGInterpolationMedium: CGInterpolationQuality = 4;
+
 pub fn CGContextRelease(env: &mut Environment, c: CGContextRef) {
     if !c.is_null() {
         CFRelease(env, c);
@@ -65,6 +232,27 @@ pub fn CGContextRetain(env: &mut Environment, c: CGContextRef) -> CGContextRef {
     }
 }
 
+/// Set the fill color, replacing any fill pattern.
+fn set_fill_color(
+    env: &mut Environment,
+    context: CGContextRef,
+    color: (CGFloat, CGFloat, CGFloat, CGFloat),
+) {
+    let state = &mut env.objc.borrow_mut::<CGContextHostObject>(context).state;
+    state.rgb_fill_color = color;
+    state.fill_pattern = None;
+}
+/// Set the stroke color, replacing any stroke pattern.
+fn set_stroke_color(
+    env: &mut Environment,
+    context: CGContextRef,
+    color: (CGFloat, CGFloat, CGFloat, CGFloat),
+) {
+    let state = &mut env.objc.borrow_mut::<CGContextHostObject>(context).state;
+    state.rgb_stroke_color = color;
+    state.stroke_pattern = None;
+}
+
 pub fn CGContextSetRGBFillColor(
     env: &mut Environment,
     context: CGContextRef,
@@ -73,10 +261,7 @@ pub fn CGContextSetRGBFillColor(
     blue: CGFloat,
     alpha: CGFloat,
 ) {
-    let color = (red, green, blue, alpha);
-    env.objc
-        .borrow_mut::<CGContextHostObject>(context)
-        .rgb_fill_color = color;
+    set_fill_color(env, context, (red, green, blue, alpha));
 }
 
 fn CGContextSetGrayFillColor(
@@ -85,10 +270,315 @@ fn CGContextSetGrayFillColor(
     gray: CGFloat,
     alpha: CGFloat,
 ) {
-    let color = (gray, gray, gray, alpha);
+    set_fill_color(env, context, (gray, gray, gray, alpha));
+}
+
+pub fn CGContextSetRGBStrokeColor(
+    env: &mut Environment,
+    context: CGContextRef,
+    red: CGFloat,
+    green: CGFloat,
+    blue: CGFloat,
+    alpha: CGFloat,
+) {
+    set_stroke_color(env, context, (red, green, blue, alpha));
+}
+
+fn CGContextSetGrayStrokeColor(
+    env: &mut Environment,
+    context: CGContextRef,
+    gray: CGFloat,
+    alpha: CGFloat,
+) {
+    set_stroke_color(env, context, (gray, gray, gray, alpha));
+}
+
+fn CGContextSetFillColorWithColor(env: &mut Environment, context: CGContextRef, color: CGColorRef) {
+    let rgba = cg_color::to_rgba(&env.objc, color);
+    match cg_color::get_pattern(&env.objc, color) {
+        Some(pattern) => set_pattern(env, context, pattern, rgba, false),
+        None => set_fill_color(env, context, rgba),
+    }
+}
+
+fn CGContextSetStrokeColorWithColor(
+    env: &mut Environment,
+    context: CGContextRef,
+    color: CGColorRef,
+) {
+    let rgba = cg_color::to_rgba(&env.objc, color);
+    match cg_color::get_pattern(&env.objc, color) {
+        Some(pattern) => set_pattern(env, context, pattern, rgba, true),
+        None => set_stroke_color(env, context, rgba),
+    }
+}
+
+fn CGContextSetFillColorSpace(
+    env: &mut Environment,
+    context: CGContextRef,
+    space: CGColorSpaceRef,
+) {
+    let space = env.objc.borrow::<CGColorSpaceHostObject>(space).clone();
+    env.objc
+        .borrow_mut::<CGContextHostObject>(context)
+        .state
+        .fill_color_space = space;
+    // Changing the color space resets the color to opaque black.
+    set_fill_color(env, context, (0.0, 0.0, 0.0, 1.0));
+}
+fn CGContextSetStrokeColorSpace(
+    env: &mut Environment,
+    context: CGContextRef,
+    space: CGColorSpaceRef,
+) {
+    let space = env.objc.borrow::<CGColorSpaceHostObject>(space).clone();
     env.objc
         .borrow_mut::<CGContextHostObject>(context)
-        .rgb_fill_color = color;
+        .state
+        .stroke_color_space = space;
+    set_stroke_color(env, context, (0.0, 0.0, 0.0, 1.0));
+}
+
+fn CGContextSetFillColor(
+    env: &mut Environment,
+    context: CGContextRef,
+    components: ConstPtr<CGFloat>,
+) {
+    let space = env
+        .objc
+        .borrow::<CGContextHostObject>(context)
+        .state
+        .fill_color_space
+        .name;
+    if space == PATTERN_COLOR_SPACE {
+        log!("Warning: CGContextSetFillColor() used with a pattern color space, ignoring");
+        return;
+    }
+    let color = cg_color_space::read_color(env, space, components);
+    set_fill_color(env, context, color);
+}
+fn CGContextSetStrokeColor(
+    env: &mut Environment,
+    context: CGContextRef,
+    components: ConstPtr<CGFloat>,
+) {
+    let space = env
+        .objc
+        .borrow::<CGContextHostObject>(context)
+        .state
+        .stroke_color_space
+        .name;
+    if space == PATTERN_COLOR_SPACE {
+        log!("Warning: CGContextSetStrokeColor() used with a pattern color space, ignoring");
+        return;
+    }
+    let color = cg_color_space::read_color(env, space, components);
+    set_stroke_color(env, context, color);
+}
+
+/// Set the fill (or stroke, if `stroke` is [true]) pattern. `tint` is only
+/// used by uncolored patterns.
+fn set_pattern(
+    env: &mut Environment,
+    context: CGContextRef,
+    pattern: CGPatternRef,
+    tint: (CGFloat, CGFloat, CGFloat, CGFloat),
+    stroke: bool,
+) {
+    let tile = Some(Rc::new(cg_pattern::render_tile(env, pattern, tint)));
+    let state = &mut env.objc.borrow_mut::<CGContextHostObject>(context).state;
+    if stroke {
+        state.stroke_pattern = tile;
+    } else {
+        state.fill_pattern = tile;
+    }
+}
+/// Shared implementation of `CGContextSetFillPattern` and
+/// `CGContextSetStrokePattern`. The components are the color for uncolored
+/// patterns, in the base color space of the pattern color space.
+fn set_pattern_with_components(
+    env: &mut Environment,
+    context: CGContextRef,
+    pattern: CGPatternRef,
+    components: ConstPtr<CGFloat>,
+    stroke: bool,
+) {
+    let state = &env.objc.borrow::<CGContextHostObject>(context).state;
+    let pattern_base = if stroke {
+        state.stroke_color_space.pattern_base
+    } else {
+        state.fill_color_space.pattern_base
+    };
+    let tint = match pattern_base {
+        Some(base) if !components.is_null() => cg_color_space::read_color(env, base, components),
+        _ => (0.0, 0.0, 0.0, 1.0),
+    };
+    set_pattern(env, context, pattern, tint, stroke);
+}
+fn CGContextSetFillPattern(
+    env: &mut Environment,
+    context: CGContextRef,
+    pattern: CGPatternRef,
+    components: ConstPtr<CGFloat>,
+) {
+    set_pattern_with_components(env, context, pattern, components, false);
+}
+fn CGContextSetStrokePattern(
+    env: &mut Environment,
+    context: CGContextRef,
+    pattern: CGPatternRef,
+    components: ConstPtr<CGFloat>,
+) {
+    set_pattern_with_components(env, context, pattern, components, true);
+}
+fn CGContextSetPatternPhase(env: &mut Environment, context: CGContextRef, phase: CGSize) {
+    env.objc
+        .borrow_mut::<CGContextHostObject>(context)
+        .state
+        .pattern_phase = phase;
+}
+
+pub fn CGContextSetAlpha(env: &mut Environment, context: CGContextRef, alpha: CGFloat) {
+    env.objc
+        .borrow_mut::<CGContextHostObject>(context)
+        .state
+        .alpha = alpha.clamp(0.0, 1.0);
+}
+
+pub fn CGContextSetBlendMode(env: &mut Environment, context: CGContextRef, mode: CGBlendMode) {
+    if !(kCGBlendModeNormal..=kCGBlendModePlusLighter).contains(&mode) {
+        log!(
+            "Warning: unknown blend mode {}, using normal blending",
+            mode
+        );
+    }
+    env.objc
+        .borrow_mut::<CGContextHostObject>(context)
+        .state
+        .blend_mode = mode;
+}
+
+fn CGContextSetInterpolationQuality(
+    env: &mut Environment,
+    context: CGContextRef,
+    quality: CGInterpolationQuality,
+) {
+    env.objc
+        .borrow_mut::<CGContextHostObject>(context)
+        .state
+        .interpolation_quality = quality;
+}
+fn CGContextGetInterpolationQuality(
+    env: &mut Environment,
+    context: CGContextRef,
+) -> CGInterpolationQuality {
+    env.objc
+        .borrow::<CGContextHostObject>(context)
+        .state
+        .interpolation_quality
+}
+
+fn CGContextSetShadowWithColor(
+    env: &mut Environment,
+    context: CGContextRef,
+    offset: CGSize,
+    blur: CGFloat,
+    color: CGColorRef,
+) {
+    let shadow = if color.is_null() {
+        None
+    } else {
+        let color = cg_color::to_rgba(&env.objc, color);
+        (color.3 > 0.0).then_some(Shadow {
+            offset,
+            blur: blur.max(0.0),
+            color,
+        })
+    };
+    env.objc
+        .borrow_mut::<CGContextHostObject>(context)
+        .state
+        .shadow = shadow;
+}
+fn CGContextSetShadow(env: &mut Environment, context: CGContextRef, offset: CGSize, blur: CGFloat) {
+    // The default shadow color is black with 1/3 alpha.
+    env.objc
+        .borrow_mut::<CGContextHostObject>(context)
+        .state
+        .shadow = Some(Shadow {
+        offset,
+        blur: blur.max(0.0),
+        color: (0.0, 0.0, 0.0, 1.0 / 3.0),
+    });
+}
+
+/// Narrows the clipping area to the current path, then clears the path.
+fn clip_to_current_path(env: &mut Environment, context: CGContextRef, rule: FillRule) {
+    let path = std::mem::take(&mut env.objc.borrow_mut::<CGContextHostObject>(context).path);
+    cg_bitmap_context::clip_to_path(env, context, &path, rule);
+}
+fn CGContextClip(env: &mut Environment, context: CGContextRef) {
+    clip_to_current_path(env, context, FillRule::Winding);
+}
+fn CGContextEOClip(env: &mut Environment, context: CGContextRef) {
+    clip_to_current_path(env, context, FillRule::EvenOdd);
+}
+fn CGContextClipToRect(env: &mut Environment, context: CGContextRef, rect: CGRect) {
+    let mut path = Path::default();
+    let transform = env
+        .objc
+        .borrow::<CGContextHostObject>(context)
+        .state
+        .transform;
+    path.add_transformed(transform, |path| path.add_rect(rect));
+    cg_bitmap_context::clip_to_path(env, context, &path, FillRule::Winding);
+}
+fn CGContextClipToRects(
+    env: &mut Environment,
+    context: CGContextRef,
+    rects: ConstPtr<CGRect>,
+    count: GuestUSize,
+) {
+    let mut path = Path::default();
+    let transform = env
+        .objc
+        .borrow::<CGContextHostObject>(context)
+        .state
+        .transform;
+    for i in 0..count {
+        let rect = env.mem.read(rects + i);
+        path.add_transformed(transform, |path| path.add_rect(rect));
+    }
+    cg_bitmap_context::clip_to_path(env, context, &path, FillRule::Winding);
+}
+fn CGContextClipToMask(
+    env: &mut Environment,
+    context: CGContextRef,
+    rect: CGRect,
+    mask: CGImageRef,
+) {
+    cg_bitmap_context::clip_to_mask(env, context, rect, mask);
+}
+fn CGContextGetClipBoundingBox(env: &mut Environment, context: CGContextRef) -> CGRect {
+    let host_obj = env.objc.borrow::<CGContextHostObject>(context);
+    let ((x, y), (width, height)) = match host_obj.state.clip {
+        Some(ref clip) => clip.bounds(),
+        None => {
+            let (width, height) = cg_bitmap_context::dimensions(host_obj.subclass.bitmap_data());
+            ((0, 0), (width, height))
+        }
+    };
+    let device_rect = CGRect {
+        origin: CGPoint {
+            x: x as CGFloat,
+            y: y as CGFloat,
+        },
+        size: CGSize {
+            width: width as CGFloat,
+            height: height as CGFloat,
+        },
+    };
+    host_obj.state.transform.invert().apply_to_rect(device_rect)
 }
 
 pub fn CGContextFillRect(env: &mut Environment, context: CGContextRef, rect: CGRect) {
@@ -106,22 +596,26 @@ pub fn CGContextConcatCTM(
 ) {
     log_dbg!("CGContextConcatCTM({:?})", transform);
     let host_obj = env.objc.borrow_mut::<CGContextHostObject>(context);
-    host_obj.transform = transform.concat(host_obj.transform);
+    host_obj.state.transform = transform.concat(host_obj.state.transform);
 }
 pub fn CGContextGetCTM(env: &mut Environment, context: CGContextRef) -> CGAffineTransform {
-    let res = env.objc.borrow::<CGContextHostObject>(context).transform;
+    let res = env
+        .objc
+        .borrow::<CGContextHostObject>(context)
+        .state
+        .transform;
     log_dbg!("CGContextGetCTM() => {:?}", res);
     res
 }
 pub fn CGContextRotateCTM(env: &mut Environment, context: CGContextRef, angle: CGFloat) {
     log_dbg!("CGContextRotateCTM({:?})", angle);
     let host_obj = env.objc.borrow_mut::<CGContextHostObject>(context);
-    host_obj.transform = host_obj.transform.rotate(angle);
+    host_obj.state.transform = host_obj.state.transform.rotate(angle);
 }
 pub fn CGContextScaleCTM(env: &mut Environment, context: CGContextRef, x: CGFloat, y: CGFloat) {
     log_dbg!("CGContextScaleCTM({:?})", (x, y));
     let host_obj = env.objc.borrow_mut::<CGContextHostObject>(context);
-    host_obj.transform = host_obj.transform.scale(x, y);
+    host_obj.state.transform = host_obj.state.transform.scale(x, y);
 }
 pub fn CGContextTranslateCTM(
     env: &mut Environment,
@@ -131,7 +625,7 @@ pub fn CGContextTranslateCTM(
 ) {
     log_dbg!("CGContextTranslateCTM({:?})", (tx, ty));
     let host_obj = env.objc.borrow_mut::<CGContextHostObject>(context);
-    host_obj.transform = host_obj.transform.translate(tx, ty);
+    host_obj.state.transform = host_obj.state.transform.translate(tx, ty);
 }
 
 pub fn CGContextDrawImage(
@@ -143,18 +637,571 @@ pub fn CGContextDrawImage(
     cg_bitmap_context::draw_image(env, context, rect, image);
 }
 
-fn CGContextSaveGState(env: &mut Environment, context: CGContextRef) {
+fn CGContextDrawPDFPage(env: &mut Environment, context: CGContextRef, page: CGPDFPageRef) {
+    if page.is_null() {
+        return;
+    }
+    cg_pdf_page::draw(env, context, page);
+}
+
+// These only do something for PDF contexts.
+fn CGContextBeginPage(env: &mut Environment, context: CGContextRef, media_box: ConstPtr<CGRect>) {
+    let media_box = (!media_box.is_null()).then(|| env.mem.read(media_box));
+    cg_pdf_context::begin_page(env, context, media_box);
+}
+fn CGContextEndPage(env: &mut Environment, context: CGContextRef) {
+    cg_pdf_context::end_page(env, context);
+}
+
+fn CGContextDrawLinearGradient(
+    env: &mut Environment,
+    context: CGContextRef,
+    gradient: CGGradientRef,
+    start: CGPoint,
+    end: CGPoint,
+    options: CGGradientDrawingOptions,
+) {
+    let geometry = ShadingGeometry::Axial { start, end };
+    let shader = cg_gradient::make_shader(&env.objc, gradient, geometry, options);
+    cg_bitmap_context::draw_shader(env, context, &shader);
+}
+fn CGContextDrawRadialGradient(
+    env: &mut Environment,
+    context: CGContextRef,
+    gradient: CGGradientRef,
+    start: CGPoint,
+    start_radius: CGFloat,
+    end: CGPoint,
+    end_radius: CGFloat,
+    options: CGGradientDrawingOptions,
+) {
+    let geometry = ShadingGeometry::Radial {
+        start,
+        start_radius,
+        end,
+        end_radius,
+    };
+    let shader = cg_gradient::make_shader(&env.objc, gradient, geometry, options);
+    cg_bitmap_context::draw_shader(env, context, &shader);
+}
+fn CGContextDrawShading(env: &mut Environment, context: CGContextRef, shading: CGShadingRef) {
+    let shader = cg_shading::make_shader(env, shading);
+    cg_bitmap_context::draw_shader(env, context, &shader);
+}
+
+pub fn CGContextSaveGState(env: &mut Environment, context: CGContextRef) {
+    let host_obj = env.objc.borrow_mut::<CGContextHostObject>(context);
+    host_obj.state_stack.push(host_obj.state.clone());
+}
+
+pub fn CGContextRestoreGState(env: &mut Environment, context: CGContextRef) {
+    let host_obj = env.objc.borrow_mut::<CGContextHostObject>(context);
+    host_obj.state = host_obj.state_stack.pop().unwrap();
+}
+
+fn CGContextSetLineWidth(env: &mut Environment, context: CGContextRef, width: CGFloat) {
+    let host_obj = env.objc.borrow_mut::<CGContextHostObject>(context);
+    host_obj.state.stroke_style.width = width;
+}
+fn CGContextSetLineCap(env: &mut Environment, context: CGContextRef, cap: CGLineCap) {
+    let host_obj = env.objc.borrow_mut::<CGContextHostObject>(context);
+    host_obj.state.stroke_style.cap = cap;
+}
+fn CGContextSetLineJoin(env: &mut Environment, context: CGContextRef, join: CGLineJoin) {
+    let host_obj = env.objc.borrow_mut::<CGContextHostObject>(context);
+    host_obj.state.stroke_style.join = join;
+}
+fn CGContextSetMiterLimit(env: &mut Environment, context: CGContextRef, limit: CGFloat) {
+    let host_obj = env.objc.borrow_mut::<CGContextHostObject>(context);
+    host_obj.state.stroke_style.miter_limit = limit;
+}
+fn CGContextSetLineDash(
+    env: &mut Environment,
+    context: CGContextRef,
+    phase: CGFloat,
+    lengths: ConstPtr<CGFloat>,
+    count: GuestUSize,
+) {
+    let lengths: Vec<CGFloat> = if lengths.is_null() {
+        Vec::new()
+    } else {
+        (0..count).map(|i| env.mem.read(lengths + i)).collect()
+    };
+    let host_obj = env.objc.borrow_mut::<CGContextHostObject>(context);
+    host_obj.state.stroke_style.dash_phase = phase;
+    host_obj.state.stroke_style.dash_lengths = lengths;
+}
+
+/// Adds to the current path, transforming everything by the CTM first.
+fn add_to_path(env: &mut Environment, context: CGContextRef, build: impl FnOnce(&mut Path)) {
     let host_obj = env.objc.borrow_mut::<CGContextHostObject>(context);
     host_obj
-        .state_stack
-        .push((host_obj.rgb_fill_color, host_obj.transform));
+        .path
+        .add_transformed(host_obj.state.transform, build);
 }
 
-fn CGContextRestoreGState(env: &mut Environment, context: CGContextRef) {
+fn CGContextBeginPath(env: &mut Environment, context: CGContextRef) {
+    env.objc
+        .borrow_mut::<CGContextHostObject>(context)
+        .path
+        .clear();
+}
+fn CGContextMoveToPoint(env: &mut Environment, context: CGContextRef, x: CGFloat, y: CGFloat) {
+    add_to_path(env, context, |path| path.move_to(CGPoint { x, y }));
+}
+fn CGContextAddLineToPoint(env: &mut Environment, context: CGContextRef, x: CGFloat, y: CGFloat) {
+    add_to_path(env, context, |path| path.line_to(CGPoint { x, y }));
+}
+fn CGContextAddLines(
+    env: &mut Environment,
+    context: CGContextRef,
+    points: ConstPtr<CGPoint>,
+    count: GuestUSize,
+) {
+    let points: Vec<CGPoint> = (0..count).map(|i| env.mem.read(points + i)).collect();
+    add_to_path(env, context, |path| {
+        for (i, &point) in points.iter().enumerate() {
+            if i == 0 {
+                path.move_to(point);
+            } else {
+                path.line_to(point);
+            }
+        }
+    });
+}
+fn CGContextAddQuadCurveToPoint(
+    env: &mut Environment,
+    context: CGContextRef,
+    cpx: CGFloat,
+    cpy: CGFloat,
+    x: CGFloat,
+    y: CGFloat,
+) {
+    add_to_path(env, context, |path| {
+        path.quad_curve_to(CGPoint { x: cpx, y: cpy }, CGPoint { x, y })
+    });
+}
+#[allow(clippy::too_many_arguments)]
+fn CGContextAddCurveToPoint(
+    env: &mut Environment,
+    context: CGContextRef,
+    cp1x: CGFloat,
+    cp1y: CGFloat,
+    cp2x: CGFloat,
+    cp2y: CGFloat,
+    x: CGFloat,
+    y: CGFloat,
+) {
+    add_to_path(env, context, |path| {
+        path.curve_to(
+            CGPoint { x: cp1x, y: cp1y },
+            CGPoint { x: cp2x, y: cp2y },
+            CGPoint { x, y },
+        )
+    });
+}
+#[allow(clippy::too_many_arguments)]
+fn CGContextAddArc(
+    env: &mut Environment,
+    context: CGContextRef,
+    x: CGFloat,
+    y: CGFloat,
+    radius: CGFloat,
+    start_angle: CGFloat,
+    end_angle: CGFloat,
+    clockwise: i32,
+) {
+    add_to_path(env, context, |path| {
+        path.add_arc(
+            CGPoint { x, y },
+            radius,
+            start_angle,
+            end_angle,
+            clockwise != 0,
+        )
+    });
+}
+fn CGContextAddArcToPoint(
+    env: &mut Environment,
+    context: CGContextRef,
+    x1: CGFloat,
+    y1: CGFloat,
+    x2: CGFloat,
+    y2: CGFloat,
+    radius: CGFloat,
+) {
+    add_to_path(env, context, |path| {
+        path.add_arc_to_point(CGPoint { x: x1, y: y1 }, CGPoint { x: x2, y: y2 }, radius)
+    });
+}
+fn CGContextAddRect(env: &mut Environment, context: CGContextRef, rect: CGRect) {
+    add_to_path(env, context, |path| path.add_rect(rect));
+}
+fn CGContextAddRects(
+    env: &mut Environment,
+    context: CGContextRef,
+    rects: ConstPtr<CGRect>,
+    count: GuestUSize,
+) {
+    let rects: Vec<CGRect> = (0..count).map(|i| env.mem.read(rects + i)).collect();
+    add_to_path(env, context, |path| {
+        for &rect in &rects {
+            path.add_rect(rect);
+        }
+    });
+}
+fn CGContextAddEllipseInRect(env: &mut Environment, context: CGContextRef, rect: CGRect) {
+    add_to_path(env, context, |path| path.add_ellipse_in_rect(rect));
+}
+fn CGContextClosePath(env: &mut Environment, context: CGContextRef) {
+    env.objc
+        .borrow_mut::<CGContextHostObject>(context)
+        .path
+        .close_subpath();
+}
+fn CGContextAddPath(env: &mut Environment, context: CGContextRef, path: CGPathRef) {
+    let path = cg_path::to_path(env, path);
     let host_obj = env.objc.borrow_mut::<CGContextHostObject>(context);
-    let state = host_obj.state_stack.pop().unwrap();
-    host_obj.rgb_fill_color = state.0;
-    host_obj.transform = state.1;
+    host_obj.path.add_path(&path, host_obj.state.transform);
+}
+
+fn CGContextIsPathEmpty(env: &mut Environment, context: CGContextRef) -> bool {
+    env.objc
+        .borrow::<CGContextHostObject>(context)
+        .path
+        .is_empty()
+}
+fn CGContextGetPathCurrentPoint(env: &mut Environment, context: CGContextRef) -> CGPoint {
+    let host_obj = env.objc.borrow::<CGContextHostObject>(context);
+    host_obj
+        .path
+        .current_point()
+        .map(|point| host_obj.state.transform.invert().apply_to_point(point))
+        .unwrap_or_default()
+}
+fn CGContextGetPathBoundingBox(env: &mut Environment, context: CGContextRef) -> CGRect {
+    let host_obj = env.objc.borrow::<CGContextHostObject>(context);
+    host_obj
+        .path
+        .transform(host_obj.state.transform.invert())
+        .bounding_box()
+}
+
+pub type CGPathDrawingMode = i32;
+pub const kCGPathFill: CGPathDrawingMode = 0;
+pub const kCGPathEOFill: CGPathDrawingMode = 1;
+pub const kCGPathStroke: CGPathDrawingMode = 2;
+pub const kCGPathFillStroke: CGPathDrawingMode = 3;
+pub const kCGPathEOFillStroke: CGPathDrawingMode = 4;
+
+fn CGContextDrawPath(env: &mut Environment, context: CGContextRef, mode: CGPathDrawingMode) {
+    let path = std::mem::take(&mut env.objc.borrow_mut::<CGContextHostObject>(context).path);
+    match mode {
+        kCGPathFill | kCGPathFillStroke => {
+            cg_bitmap_context::fill_path(env, context, &path, FillRule::Winding)
+        }
+        kCGPathEOFill | kCGPathEOFillStroke => {
+            cg_bitmap_context::fill_path(env, context, &path, FillRule::EvenOdd)
+        }
+        kCGPathStroke => (),
+        _ => unimplemented!("CGContextDrawPath mode {}", mode),
+    }
+    if matches!(
+        mode,
+        kCGPathStroke | kCGPathFillStroke | kCGPathEOFillStroke
+    ) {
+        cg_bitmap_context::stroke_path(env, context, &path);
+    }
+}
+fn CGContextFillPath(env: &mut Environment, context: CGContextRef) {
+    CGContextDrawPath(env, context, kCGPathFill);
+}
+fn CGContextEOFillPath(env: &mut Environment, context: CGContextRef) {
+    CGContextDrawPath(env, context, kCGPathEOFill);
+}
+fn CGContextStrokePath(env: &mut Environment, context: CGContextRef) {
+    CGContextDrawPath(env, context, kCGPathStroke);
+}
+
+/// Draws a temporary path built by `build` without disturbing the current
+/// path.
+fn draw_temporary_path(
+    env: &mut Environment,
+    context: CGContextRef,
+    mode: CGPathDrawingMode,
+    build: impl FnOnce(&mut Path),
+) {
+    let saved_path = std::mem::take(&mut env.objc.borrow_mut::<CGContextHostObject>(context).path);
+    add_to_path(env, context, build);
+    CGContextDrawPath(env, context, mode);
+    env.objc.borrow_mut::<CGContextHostObject>(context).path = saved_path;
+}
+
+fn CGContextStrokeRect(env: &mut Environment, context: CGContextRef, rect: CGRect) {
+    draw_temporary_path(env, context, kCGPathStroke, |path| path.add_rect(rect));
+}
+fn CGContextStrokeRectWithWidth(
+    env: &mut Environment,
+    context: CGContextRef,
+    rect: CGRect,
+    width: CGFloat,
+) {
+    CGContextSaveGState(env, context);
+    CGContextSetLineWidth(env, context, width);
+    CGContextStrokeRect(env, context, rect);
+    CGContextRestoreGState(env, context);
+}
+fn CGContextFillRects(
+    env: &mut Environment,
+    context: CGContextRef,
+    rects: ConstPtr<CGRect>,
+    count: GuestUSize,
+) {
+    for i in 0..count {
+        let rect = env.mem.read(rects + i);
+        CGContextFillRect(env, context, rect);
+    }
+}
+fn CGContextFillEllipseInRect(env: &mut Environment, context: CGContextRef, rect: CGRect) {
+    draw_temporary_path(env, context, kCGPathFill, |path| {
+        path.add_ellipse_in_rect(rect)
+    });
+}
+fn CGContextStrokeEllipseInRect(env: &mut Environment, context: CGContextRef, rect: CGRect) {
+    draw_temporary_path(env, context, kCGPathStroke, |path| {
+        path.add_ellipse_in_rect(rect)
+    });
+}
+fn CGContextStrokeLineSegments(
+    env: &mut Environment,
+    context: CGContextRef,
+    points: ConstPtr<CGPoint>,
+    count: GuestUSize,
+) {
+    let points: Vec<CGPoint> = (0..count).map(|i| env.mem.read(points + i)).collect();
+    draw_temporary_path(env, context, kCGPathStroke, |path| {
+        for pair in points.chunks_exact(2) {
+            path.move_to(pair[0]);
+            path.line_to(pair[1]);
+        }
+    });
+}
+
+fn CGContextSelectFont(
+    env: &mut Environment,
+    context: CGContextRef,
+    name: ConstPtr<u8>,
+    size: CGFloat,
+    encoding: CGTextEncoding,
+) {
+    let name = env.mem.cstr_at_utf8(name).unwrap().to_string();
+    let font = cg_font::font_with_name(env, &name);
+    let state = &mut env.objc.borrow_mut::<CGContextHostObject>(context).state;
+    state.font = Some(font);
+    state.font_size = size;
+    state.text_encoding = encoding;
+}
+fn CGContextSetFont(env: &mut Environment, context: CGContextRef, font: CGFontRef) {
+    let font = (!font.is_null()).then(|| cg_font::borrow_font(&env.objc, font));
+    env.objc
+        .borrow_mut::<CGContextHostObject>(context)
+        .state
+        .font = font;
+}
+fn CGContextSetFontSize(env: &mut Environment, context: CGContextRef, size: CGFloat) {
+    env.objc
+        .borrow_mut::<CGContextHostObject>(context)
+        .state
+        .font_size = size;
+}
+fn CGContextSetCharacterSpacing(env: &mut Environment, context: CGContextRef, spacing: CGFloat) {
+    env.objc
+        .borrow_mut::<CGContextHostObject>(context)
+        .state
+        .character_spacing = spacing;
+}
+fn CGContextSetTextDrawingMode(
+    env: &mut Environment,
+    context: CGContextRef,
+    mode: CGTextDrawingMode,
+) {
+    if !(kCGTextFill..=kCGTextClip).contains(&mode) {
+        log!(
+            "CGContextSetTextDrawingMode: invalid mode {}, ignoring",
+            mode
+        );
+        return;
+    }
+    env.objc
+        .borrow_mut::<CGContextHostObject>(context)
+        .state
+        .text_drawing_mode = mode;
+}
+fn CGContextSetTextMatrix(env: &mut Environment, context: CGContextRef, matrix: CGAffineTransform) {
+    env.objc
+        .borrow_mut::<CGContextHostObject>(context)
+        .text_matrix = matrix;
+}
+fn CGContextGetTextMatrix(env: &mut Environment, context: CGContextRef) -> CGAffineTransform {
+    env.objc.borrow::<CGContextHostObject>(context).text_matrix
+}
+fn CGContextSetTextPosition(env: &mut Environment, context: CGContextRef, x: CGFloat, y: CGFloat) {
+    let text_matrix = &mut env
+        .objc
+        .borrow_mut::<CGContextHostObject>(context)
+        .text_matrix;
+    text_matrix.tx = x;
+    text_matrix.ty = y;
+}
+fn CGContextGetTextPosition(env: &mut Environment, context: CGContextRef) -> CGPoint {
+    let text_matrix = env.objc.borrow::<CGContextHostObject>(context).text_matrix;
+    CGPoint {
+        x: text_matrix.tx,
+        y: text_matrix.ty,
+    }
+}
+
+/// Draws glyphs one after another from the text position, using either the
+/// font's advances or the provided ones, then moves the text position to the
+/// end of the run.
+fn show_glyph_run(
+    env: &mut Environment,
+    context: CGContextRef,
+    glyphs: &[CGGlyph],
+    advances: Option<&[CGSize]>,
+) {
+    let host_obj = env.objc.borrow::<CGContextHostObject>(context);
+    let state = &host_obj.state;
+    let Some(ref font) = state.font else {
+        log!(
+            "Warning: text drawn in context {:?} with no font set",
+            context
+        );
+        return;
+    };
+    let mut pen = CGPoint { x: 0.0, y: 0.0 };
+    let mut positioned = Vec::with_capacity(glyphs.len());
+    for (i, &glyph) in glyphs.iter().enumerate() {
+        positioned.push((glyph, pen));
+        let advance = match advances {
+            Some(advances) => advances[i],
+            None => CGSize {
+                width: font.glyph_advance(state.font_size, glyph) + state.character_spacing,
+                height: 0.0,
+            },
+        };
+        pen.x += advance.width;
+        pen.y += advance.height;
+    }
+
+    let run = GlyphRun {
+        font: font.clone(),
+        font_size: state.font_size,
+        text_matrix: host_obj.text_matrix,
+        glyphs: positioned,
+    };
+    cg_bitmap_context::show_glyphs(env, context, &[run]);
+
+    let text_matrix = &mut env
+        .objc
+        .borrow_mut::<CGContextHostObject>(context)
+        .text_matrix;
+    let end = text_matrix.apply_to_point(pen);
+    text_matrix.tx = end.x;
+    text_matrix.ty = end.y;
+}
+
+/// Maps the bytes of text to glyphs using the font and encoding chosen with
+/// `CGContextSelectFont`.
+fn text_to_glyphs(
+    env: &mut Environment,
+    context: CGContextRef,
+    string: ConstPtr<u8>,
+    length: GuestUSize,
+) -> Vec<CGGlyph> {
+    let state = &env.objc.borrow::<CGContextHostObject>(context).state;
+    let Some(ref font) = state.font else {
+        return Vec::new();
+    };
+    env.mem
+        .bytes_at(string, length)
+        .iter()
+        .map(|&byte| font.glyph_id(cg_font::decode_char(state.text_encoding, byte)))
+        .collect()
+}
+
+fn CGContextShowText(
+    env: &mut Environment,
+    context: CGContextRef,
+    string: ConstPtr<u8>,
+    length: GuestUSize,
+) {
+    let glyphs = text_to_glyphs(env, context, string, length);
+    show_glyph_run(env, context, &glyphs, None);
+}
+fn CGContextShowTextAtPoint(
+    env: &mut Environment,
+    context: CGContextRef,
+    x: CGFloat,
+    y: CGFloat,
+    string: ConstPtr<u8>,
+    length: GuestUSize,
+) {
+    CGContextSetTextPosition(env, context, x, y);
+    CGContextShowText(env, context, string, length);
+}
+fn CGContextShowGlyphs(
+    env: &mut Environment,
+    context: CGContextRef,
+    glyphs: ConstPtr<CGGlyph>,
+    count: GuestUSize,
+) {
+    let glyphs: Vec<CGGlyph> = (0..count).map(|i| env.mem.read(glyphs + i)).collect();
+    show_glyph_run(env, context, &glyphs, None);
+}
+fn CGContextShowGlyphsAtPoint(
+    env: &mut Environment,
+    context: CGContextRef,
+    x: CGFloat,
+    y: CGFloat,
+    glyphs: ConstPtr<CGGlyph>,
+    count: GuestUSize,
+) {
+    CGContextSetTextPosition(env, context, x, y);
+    CGContextShowGlyphs(env, context, glyphs, count);
+}
+fn CGContextShowGlyphsWithAdvances(
+    env: &mut Environment,
+    context: CGContextRef,
+    glyphs: ConstPtr<CGGlyph>,
+    advances: ConstPtr<CGSize>,
+    count: GuestUSize,
+) {
+    let glyphs: Vec<CGGlyph> = (0..count).map(|i| env.mem.read(glyphs + i)).collect();
+    let advances: Vec<CGSize> = (0..count).map(|i| env.mem.read(advances + i)).collect();
+    show_glyph_run(env, context, &glyphs, Some(&advances));
+}
+fn CGContextShowGlyphsAtPositions(
+    env: &mut Environment,
+    context: CGContextRef,
+    glyphs: ConstPtr<CGGlyph>,
+    positions: ConstPtr<CGPoint>,
+    count: GuestUSize,
+) {
+    let glyphs: Vec<(CGGlyph, CGPoint)> = (0..count)
+        .map(|i| (env.mem.read(glyphs + i), env.mem.read(positions + i)))
+        .collect();
+    let host_obj = env.objc.borrow::<CGContextHostObject>(context);
+    let Some(font) = host_obj.state.font.clone() else {
+        return;
+    };
+    let run = GlyphRun {
+        font,
+        font_size: host_obj.state.font_size,
+        text_matrix: host_obj.text_matrix,
+        glyphs,
+    };
+    cg_bitmap_context::show_glyphs(env, context, &[run]);
 }
 
 pub const FUNCTIONS: FunctionExports = &[
@@ -162,6 +1209,18 @@ pub const FUNCTIONS: FunctionExports = &[
     export_c_func!(CGContextRelease(_)),
     export_c_func!(CGContextSetRGBFillColor(_, _, _, _, _)),
     export_c_func!(CGContextSetGrayFillColor(_, _, _)),
+    export_c_func!(CGContextSetAlpha(_, _)),
+    export_c_func!(CGContextSetBlendMode(_, _)),
+    export_c_func!(CGContextSetInterpolationQuality(_, _)),
+    export_c_func!(CGContextGetInterpolationQuality(_)),
+    export_c_func!(CGContextSetShadow(_, _, _)),
+    export_c_func!(CGContextSetShadowWithColor(_, _, _, _)),
+    export_c_func!(CGContextClip(_)),
+    export_c_func!(CGContextEOClip(_)),
+    export_c_func!(CGContextClipToRect(_, _)),
+    export_c_func!(CGContextClipToRects(_, _, _)),
+    export_c_func!(CGContextClipToMask(_, _, _)),
+    export_c_func!(CGContextGetClipBoundingBox(_)),
     export_c_func!(CGContextFillRect(_, _)),
     export_c_func!(CGContextClearRect(_, _)),
     export_c_func!(CGContextConcatCTM(_, _)),
@@ -170,6 +1229,69 @@ pub const FUNCTIONS: FunctionExports = &[
     export_c_func!(CGContextScaleCTM(_, _, _)),
     export_c_func!(CGContextTranslateCTM(_, _, _)),
     export_c_func!(CGContextDrawImage(_, _, _)),
+    export_c_func!(CGContextDrawPDFPage(_, _)),
+    export_c_func!(CGContextBeginPage(_, _)),
+    export_c_func!(CGContextEndPage(_)),
+    export_c_func!(CGContextDrawLinearGradient(_, _, _, _, _)),
+    export_c_func!(CGContextDrawRadialGradient(_, _, _, _, _, _, _)),
+    export_c_func!(CGContextDrawShading(_, _)),
     export_c_func!(CGContextSaveGState(_)),
     export_c_func!(CGContextRestoreGState(_)),
+    export_c_func!(CGContextSetRGBStrokeColor(_, _, _, _, _)),
+    export_c_func!(CGContextSetGrayStrokeColor(_, _, _)),
+    export_c_func!(CGContextSetFillColorWithColor(_, _)),
+    export_c_func!(CGContextSetStrokeColorWithColor(_, _)),
+    export_c_func!(CGContextSetFillColorSpace(_, _)),
+    export_c_func!(CGContextSetStrokeColorSpace(_, _)),
+    export_c_func!(CGContextSetFillColor(_, _)),
+    export_c_func!(CGContextSetStrokeColor(_, _)),
+    export_c_func!(CGContextSetFillPattern(_, _, _)),
+    export_c_func!(CGContextSetStrokePattern(_, _, _)),
+    export_c_func!(CGContextSetPatternPhase(_, _)),
+    export_c_func!(CGContextSetLineWidth(_, _)),
+    export_c_func!(CGContextSetLineCap(_, _)),
+    export_c_func!(CGContextSetLineJoin(_, _)),
+    export_c_func!(CGContextSetMiterLimit(_, _)),
+    export_c_func!(CGContextSetLineDash(_, _, _, _)),
+    export_c_func!(CGContextBeginPath(_)),
+    export_c_func!(CGContextMoveToPoint(_, _, _)),
+    export_c_func!(CGContextAddLineToPoint(_, _, _)),
+    export_c_func!(CGContextAddLines(_, _, _)),
+    export_c_func!(CGContextAddQuadCurveToPoint(_, _, _, _, _)),
+    export_c_func!(CGContextAddCurveToPoint(_, _, _, _, _, _, _)),
+    export_c_func!(CGContextAddArc(_, _, _, _, _, _, _)),
+    export_c_func!(CGContextAddArcToPoint(_, _, _, _, _, _)),
+    export_c_func!(CGContextAddRect(_, _)),
+    export_c_func!(CGContextAddRects(_, _, _)),
+    export_c_func!(CGContextAddEllipseInRect(_, _)),
+    export_c_func!(CGContextClosePath(_)),
+    export_c_func!(CGContextAddPath(_, _)),
+    export_c_func!(CGContextIsPathEmpty(_)),
+    export_c_func!(CGContextGetPathCurrentPoint(_)),
+    export_c_func!(CGContextGetPathBoundingBox(_)),
+    export_c_func!(CGContextDrawPath(_, _)),
+    export_c_func!(CGContextFillPath(_)),
+    export_c_func!(CGContextEOFillPath(_)),
+    export_c_func!(CGContextStrokePath(_)),
+    export_c_func!(CGContextStrokeRect(_, _)),
+    export_c_func!(CGContextStrokeRectWithWidth(_, _, _)),
+    export_c_func!(CGContextFillRects(_, _, _)),
+    export_c_func!(CGContextFillEllipseInRect(_, _)),
+    export_c_func!(CGContextStrokeEllipseInRect(_, _)),
+    export_c_func!(CGContextStrokeLineSegments(_, _, _)),
+    export_c_func!(CGContextSelectFont(_, _, _, _)),
+    export_c_func!(CGContextSetFont(_, _)),
+    export_c_func!(CGContextSetFontSize(_, _)),
+    export_c_func!(CGContextSetCharacterSpacing(_, _)),
+    export_c_func!(CGContextSetTextDrawingMode(_, _)),
+    export_c_func!(CGContextSetTextMatrix(_, _)),
+    export_c_func!(CGContextGetTextMatrix(_)),
+    export_c_func!(CGContextSetTextPosition(_, _, _)),
+    export_c_func!(CGContextGetTextPosition(_)),
+    export_c_func!(CGContextShowText(_, _, _)),
+    export_c_func!(CGContextShowTextAtPoint(_, _, _, _, _)),
+    export_c_func!(CGContextShowGlyphs(_, _, _)),
+    export_c_func!(CGContextShowGlyphsAtPoint(_, _, _, _, _)),
+    export_c_func!(CGContextShowGlyphsWithAdvances(_, _, _, _)),
+    export_c_func!(CGContextShowGlyphsAtPositions(_, _, _, _)),
 ];
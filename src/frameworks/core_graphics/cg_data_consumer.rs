@@ -0,0 +1,123 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! `CGDataConsumer.h`
+
+use crate::dyld::{export_c_func, FunctionExports};
+use crate::frameworks::core_foundation::cf_data::CFDataRef;
+use crate::frameworks::core_foundation::cf_url::CFURLRef;
+use crate::frameworks::core_foundation::{CFRelease, CFRetain, CFTypeRef};
+use crate::frameworks::foundation::{ns_url, NSUInteger};
+use crate::fs::GuestPathBuf;
+use crate::mem::{ConstPtr, GuestUSize};
+use crate::objc::{msg, nil, objc_classes, ClassExports, HostObject};
+use crate::Environment;
+
+pub type CGDataConsumerRef = CFTypeRef;
+
+// Like CGDataProvider, a CGDataConsumer is supposed to be a collection of
+// callbacks, but we only support some specific destinations.
+
+enum CGDataConsumerHostObject {
+    /// A file that is replaced by what is written.
+    File(GuestPathBuf),
+    /// A retained `CFMutableData` that is appended to.
+    CFData(CFDataRef),
+}
+impl HostObject for CGDataConsumerHostObject {}
+
+pub const CLASSES: ClassExports = objc_classes! {
+
+(env, this, _cmd);
+
+// CGDataConsumer is a CFType-based type, but in our implementation those
+// are just Objective-C types, so we need a class for it, but its name is not
+// visible anywhere.
+@implementation _touchHLE_CGDataConsumer: NSObject
+
+- (())dealloc {
+    if let CGDataConsumerHostObject::CFData(cf_data) = *env.objc.borrow(this) {
+        CFRelease(env, cf_data);
+    }
+    env.objc.dealloc_object(this, &mut env.mem)
+}
+
+@end
+
+};
+
+pub fn CGDataConsumerRelease(env: &mut Environment, c: CGDataConsumerRef) {
+    if !c.is_null() {
+        CFRelease(env, c);
+    }
+}
+pub fn CGDataConsumerRetain(env: &mut Environment, c: CGDataConsumerRef) -> CGDataConsumerRef {
+    if !c.is_null() {
+        CFRetain(env, c)
+    } else {
+        c
+    }
+}
+
+fn create(env: &mut Environment, host_object: CGDataConsumerHostObject) -> CGDataConsumerRef {
+    let class = env
+        .objc
+        .get_known_class("_touchHLE_CGDataConsumer", &mut env.mem);
+    env.objc
+        .alloc_object(class, Box::new(host_object), &mut env.mem)
+}
+
+pub fn CGDataConsumerCreateWithURL(env: &mut Environment, url: CFURLRef) -> CGDataConsumerRef {
+    if url.is_null() {
+        return nil;
+    }
+    let path = ns_url::to_rust_path(env, url).into_owned();
+    create(env, CGDataConsumerHostObject::File(path))
+}
+
+pub fn CGDataConsumerCreateWithCFData(
+    env: &mut Environment,
+    cf_data: CFDataRef,
+) -> CGDataConsumerRef {
+    if cf_data.is_null() {
+        return nil;
+    }
+    let cf_data = CFRetain(env, cf_data);
+    create(env, CGDataConsumerHostObject::CFData(cf_data))
+}
+
+/// Generic interface for host code: write all of `bytes` to the consumer.
+pub(super) fn write_bytes(env: &mut Environment, consumer: CGDataConsumerRef, bytes: &[u8]) {
+    match *env.objc.borrow(consumer) {
+        CGDataConsumerHostObject::File(ref path) => {
+            let path = path.clone();
+            if env.fs.write(&path, bytes).is_err() {
+                log!(
+                    "Warning: couldn't write {} bytes to {:?}",
+                    bytes.len(),
+                    path
+                );
+            }
+        }
+        CGDataConsumerHostObject::CFData(cf_data) => {
+            let size: GuestUSize = bytes.len().try_into().unwrap();
+            let buffer = env.mem.alloc(size);
+            env.mem
+                .bytes_at_mut(buffer.cast(), size)
+                .copy_from_slice(bytes);
+            let buffer_ptr: ConstPtr<u8> = buffer.cast().cast_const();
+            let length: NSUInteger = size;
+            () = msg![env; cf_data appendBytes:buffer_ptr length:length];
+            env.mem.free(buffer);
+        }
+    }
+}
+
+pub const FUNCTIONS: FunctionExports = &[
+    export_c_func!(CGDataConsumerRetain(_)),
+    export_c_func!(CGDataConsumerRelease(_)),
+    export_c_func!(CGDataConsumerCreateWithURL(_)),
+    export_c_func!(CGDataConsumerCreateWithCFData(_)),
+];
@@ -13,8 +13,9 @@ use crate::frameworks::core_foundation::cf_allocator::kCFAllocatorDefault;
 use crate::frameworks::core_foundation::cf_data::{CFDataCreate, CFDataRef};
 use crate::frameworks::core_foundation::{CFRelease, CFRetain, CFTypeRef};
 use crate::frameworks::foundation::NSUInteger;
-use crate::mem::{ConstVoidPtr, GuestUSize, MutVoidPtr};
-use crate::objc::{id, msg, msg_class, objc_classes, ClassExports, HostObject};
+use crate::fs::GuestPath;
+use crate::mem::{ConstPtr, ConstVoidPtr, GuestUSize, MutVoidPtr};
+use crate::objc::{id, msg, msg_class, nil, objc_classes, ClassExports, HostObject};
 use crate::Environment;
 
 pub type CGDataProviderRef = CFTypeRef;
@@ -34,6 +35,17 @@ enum CGDataProviderHostObject {
         info: MutVoidPtr,
         release_callback: CGDataProviderReleaseDataCallback,
     },
+    /// Data read from a file, which the provider owns.
+    FileData {
+        data: MutVoidPtr,
+        size: GuestUSize,
+    },
+    /// The bytes of a retained `CFData`.
+    CFData {
+        cf_data: CFDataRef,
+        data: ConstVoidPtr,
+        size: GuestUSize,
+    },
     // TODO: Maybe we should store image data in guest memory so we don't
     // need a special variant for this.
     CGImage(CGImageRef),
@@ -68,6 +80,8 @@ pub const CLASSES: ClassExports = objc_classes! {
                 () = release_callback.call_from_host(env, args);
             }
         },
+        CGDataProviderHostObject::FileData { data, .. } => env.mem.free(data),
+        CGDataProviderHostObject::CFData { cf_data, .. } => CFRelease(env, cf_data),
         CGDataProviderHostObject::CGImage(cg_image) => CGImageRelease(env, cg_image),
     }
     env.objc.dealloc_object(this, &mut env.mem)
@@ -112,6 +126,58 @@ fn CGDataProviderCreateWithData(
     )
 }
 
+fn CGDataProviderCreateWithFilename(
+    env: &mut Environment,
+    filename: ConstPtr<u8>,
+) -> CGDataProviderRef {
+    let Ok(path) = env.mem.cstr_at_utf8(filename) else {
+        return nil;
+    };
+    let Ok(bytes) = env.fs.read(GuestPath::new(path)) else {
+        log!(
+            "CGDataProviderCreateWithFilename: couldn't read {:?}, returning NULL",
+            path
+        );
+        return nil;
+    };
+    let size: GuestUSize = bytes.len().try_into().unwrap();
+    let data = env.mem.alloc(size);
+    env.mem
+        .bytes_at_mut(data.cast(), size)
+        .copy_from_slice(&bytes);
+
+    let class = env
+        .objc
+        .get_known_class("_touchHLE_CGDataProvider", &mut env.mem);
+    env.objc.alloc_object(
+        class,
+        Box::new(CGDataProviderHostObject::FileData { data, size }),
+        &mut env.mem,
+    )
+}
+
+fn CGDataProviderCreateWithCFData(env: &mut Environment, cf_data: CFDataRef) -> CGDataProviderRef {
+    if cf_data.is_null() {
+        return nil;
+    }
+    let cf_data = CFRetain(env, cf_data);
+    let data: ConstVoidPtr = msg![env; cf_data bytes];
+    let size: NSUInteger = msg![env; cf_data length];
+
+    let class = env
+        .objc
+        .get_known_class("_touchHLE_CGDataProvider", &mut env.mem);
+    env.objc.alloc_object(
+        class,
+        Box::new(CGDataProviderHostObject::CFData {
+            cf_data,
+            data,
+            size,
+        }),
+        &mut env.mem,
+    )
+}
+
 #[allow(rustdoc::broken_intra_doc_links)] // https://github.com/rust-lang/rust/issues/83049
 /// This is for use by [super::cg_image::CGImageGetDataProvider].
 pub(super) fn from_cg_image(env: &mut Environment, cg_image: CGImageRef) -> CGDataProviderRef {
@@ -129,9 +195,11 @@ pub(super) fn from_cg_image(env: &mut Environment, cg_image: CGImageRef) -> CGDa
 /// Generic interface for host code.
 pub(super) fn borrow_bytes(env: &Environment, provider: CGDataProviderRef) -> &[u8] {
     match *env.objc.borrow(provider) {
-        CGDataProviderHostObject::DataWithSize { data, size, .. } => {
+        CGDataProviderHostObject::DataWithSize { data, size, .. }
+        | CGDataProviderHostObject::CFData { data, size, .. } => {
             env.mem.bytes_at(data.cast(), size)
         }
+        CGDataProviderHostObject::FileData { data, size } => env.mem.bytes_at(data.cast(), size),
         CGDataProviderHostObject::CGImage(cg_image) => {
             cg_image::borrow_image(&env.objc, cg_image).pixels()
         }
@@ -140,12 +208,19 @@ pub(super) fn borrow_bytes(env: &Environment, provider: CGDataProviderRef) -> &[
 
 fn CGDataProviderCopyData(env: &mut Environment, provider: CGDataProviderRef) -> CFDataRef {
     match *env.objc.borrow(provider) {
-        CGDataProviderHostObject::DataWithSize { data, size, .. } => CFDataCreate(
+        CGDataProviderHostObject::DataWithSize { data, size, .. }
+        | CGDataProviderHostObject::CFData { data, size, .. } => CFDataCreate(
             env,
             kCFAllocatorDefault,
             data.cast(),
             size.try_into().unwrap(),
         ),
+        CGDataProviderHostObject::FileData { data, size } => CFDataCreate(
+            env,
+            kCFAllocatorDefault,
+            data.cast_const().cast(),
+            size.try_into().unwrap(),
+        ),
         CGDataProviderHostObject::CGImage(cg_image) => {
             let bytes = cg_image::borrow_image(&env.objc, cg_image).pixels();
 
@@ -167,5 +242,7 @@ pub const FUNCTIONS: FunctionExports = &[
     export_c_func!(CGDataProviderRetain(_)),
     export_c_func!(CGDataProviderRelease(_)),
     export_c_func!(CGDataProviderCreateWithData(_, _, _, _)),
+    export_c_func!(CGDataProviderCreateWithFilename(_)),
+    export_c_func!(CGDataProviderCreateWithCFData(_)),
     export_c_func!(CGDataProviderCopyData(_)),
 ];
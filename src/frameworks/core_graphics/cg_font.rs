@@ -0,0 +1,405 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! `CGFont.h`
+
+use super::cg_data_provider::{self, CGDataProviderRef};
+use crate::dyld::{export_c_func, FunctionExports};
+use crate::font::{self, decode_mac_roman, Font};
+use crate::frameworks::core_foundation::cf_string::CFStringRef;
+use crate::frameworks::core_foundation::{CFRelease, CFRetain, CFTypeRef};
+use crate::frameworks::foundation::ns_string;
+use crate::mem::{ConstPtr, MutPtr};
+use crate::objc::{nil, objc_classes, ClassExports, HostObject, ObjC};
+use crate::Environment;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+#[derive(Default)]
+pub struct State {
+    /// Fonts bundled with the app. These are found the first time a font is
+    /// looked up by name.
+    bundle_fonts: Option<Vec<Rc<Font>>>,
+    /// Fonts that come with touchHLE, used in place of system fonts.
+    builtin_fonts: HashMap<BuiltinFont, Rc<Font>>,
+    /// Fonts used for characters other fonts have no glyphs for, by index
+    /// (see [Font::fallback]) and boldness. [None] means the font isn't
+    /// present.
+    fallback_fonts: HashMap<(usize, bool), Option<Rc<Font>>>,
+}
+impl State {
+    fn get(env: &mut Environment) -> &mut Self {
+        &mut env.framework_state.core_graphics.cg_font
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum BuiltinFamily {
+    Mono,
+    Sans,
+    Serif,
+}
+impl BuiltinFamily {
+    pub const ALL: [BuiltinFamily; 3] = [Self::Mono, Self::Sans, Self::Serif];
+
+    /// The family name the font files give themselves.
+    pub fn name(self) -> &'static str {
+        match self {
+            BuiltinFamily::Mono => "Liberation Mono",
+            BuiltinFamily::Sans => "Liberation Sans",
+            BuiltinFamily::Serif => "Liberation Serif",
+        }
+    }
+}
+
+/// One of the fonts that come with touchHLE (see `touchHLE_fonts`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BuiltinFont {
+    pub family: BuiltinFamily,
+    pub bold: bool,
+    pub italic: bool,
+}
+impl BuiltinFont {
+    /// Guess the closest touchHLE font from a font name, e.g. "Courier-Bold".
+    pub fn for_name(name: &str) -> BuiltinFont {
+        let style = Self::style_for_name(name);
+        let name = name.to_ascii_lowercase();
+        let family = if ["courier", "mono", "typewriter"]
+            .iter()
+            .any(|word| name.contains(word))
+        {
+            BuiltinFamily::Mono
+        } else if !name.contains("sans")
+            && ["times", "georgia", "serif", "palatino", "baskerville"]
+                .iter()
+                .any(|word| name.contains(word))
+        {
+            BuiltinFamily::Serif
+        } else {
+            BuiltinFamily::Sans
+        };
+        BuiltinFont { family, ..style }
+    }
+
+    /// Guess whether a font is bold and/or italic from its name. The family
+    /// of the result is meaningless.
+    pub fn style_for_name(name: &str) -> BuiltinFont {
+        let name = name.to_ascii_lowercase();
+        BuiltinFont {
+            family: BuiltinFamily::Sans,
+            bold: ["bold", "black", "heavy"]
+                .iter()
+                .any(|word| name.contains(word)),
+            italic: name.contains("italic") || name.contains("oblique"),
+        }
+    }
+
+    /// All the styles of a family, regular first.
+    pub fn all_in_family(family: BuiltinFamily) -> [BuiltinFont; 4] {
+        [(false, false), (true, false), (false, true), (true, true)].map(|(bold, italic)| {
+            BuiltinFont {
+                family,
+                bold,
+                italic,
+            }
+        })
+    }
+
+    /// The PostScript name the font file gives itself, e.g.
+    /// "LiberationSans-BoldItalic".
+    pub fn postscript_name(self) -> String {
+        let base = self.family.name().replace(' ', "");
+        match (self.bold, self.italic) {
+            (false, false) => base,
+            (true, false) => format!("{}-Bold", base),
+            (false, true) => format!("{}-Italic", base),
+            (true, true) => format!("{}-BoldItalic", base),
+        }
+    }
+
+    fn load(self) -> Font {
+        match (self.family, self.bold, self.italic) {
+            (BuiltinFamily::Mono, false, false) => Font::mono_regular(),
+            (BuiltinFamily::Mono, true, false) => Font::mono_bold(),
+            (BuiltinFamily::Mono, true, true) => Font::mono_bold_italic(),
+            (BuiltinFamily::Mono, false, true) => Font::mono_italic(),
+            (BuiltinFamily::Sans, false, false) => Font::sans_regular(),
+            (BuiltinFamily::Sans, true, false) => Font::sans_bold(),
+            (BuiltinFamily::Sans, true, true) => Font::sans_bold_italic(),
+            (BuiltinFamily::Sans, false, true) => Font::sans_italic(),
+            (BuiltinFamily::Serif, false, false) => Font::serif_regular(),
+            (BuiltinFamily::Serif, true, false) => Font::serif_bold(),
+            (BuiltinFamily::Serif, true, true) => Font::serif_bold_italic(),
+            (BuiltinFamily::Serif, false, true) => Font::serif_italic(),
+        }
+    }
+}
+
+pub const CLASSES: ClassExports = objc_classes! {
+
+(env, this, _cmd);
+
+// CGFont seems to be a CFType-based type, but in our implementation those
+// are just Objective-C types, so we need a class for it, but its name is not
+// visible anywhere.
+@implementation _touchHLE_CGFont: NSObject
+@end
+
+};
+
+struct CGFontHostObject {
+    font: Rc<Font>,
+}
+impl HostObject for CGFontHostObject {}
+
+pub type CGFontRef = CFTypeRef;
+
+pub type CGGlyph = u16;
+
+/// Text encodings for `CGContextSelectFont`.
+pub type CGTextEncoding = i32;
+pub const kCGEncodingFontSpecific: CGTextEncoding = 0;
+pub const kCGEncodingMacRoman: CGTextEncoding = 1;
+
+/// Convert a byte of text passed to `CGContextShowText` to a character.
+pub(super) fn decode_char(encoding: CGTextEncoding, byte: u8) -> char {
+    if encoding == kCGEncodingMacRoman {
+        decode_mac_roman(byte)
+    } else {
+        // Font-specific encodings are not supported, but most fonts used this
+        // way are ASCII-compatible.
+        byte as char
+    }
+}
+
+/// Find every font file in the app bundle and load it.
+fn load_bundle_fonts(env: &mut Environment) -> Vec<Rc<Font>> {
+    let bundle_path = env.bundle.bundle_path().to_owned();
+    let Ok(paths) = env.fs.enumerate_recursive(&bundle_path) else {
+        return Vec::new();
+    };
+    let mut fonts = Vec::new();
+    for path in paths {
+        let lowercase = path.as_str().to_ascii_lowercase();
+        if ![".ttf", ".otf", ".ttc"]
+            .iter()
+            .any(|extension| lowercase.ends_with(extension))
+        {
+            continue;
+        }
+        let path = bundle_path.join(path.as_str());
+        let Some(font) = env.fs.read(&path).ok().and_then(Font::from_bytes) else {
+            log!("Warning: couldn't load font file {:?}", path);
+            continue;
+        };
+        log_dbg!("Found font {:?} in {:?}", font.names(), path);
+        fonts.push(Rc::new(font));
+    }
+    fonts
+}
+
+/// Get the fonts bundled with the app. These are loaded the first time this
+/// is called.
+///
+/// Apps are supposed to list their fonts under `UIAppFonts` in `Info.plist`,
+/// but some older apps load them with `CGFontCreateWithDataProvider` instead,
+/// so every font file in the bundle is used.
+pub fn bundle_fonts(env: &mut Environment) -> &[Rc<Font>] {
+    if State::get(env).bundle_fonts.is_none() {
+        let fonts = load_bundle_fonts(env);
+        State::get(env).bundle_fonts = Some(fonts);
+    }
+    State::get(env).bundle_fonts.as_ref().unwrap()
+}
+
+/// Look up a font bundled with the app by its PostScript name, full name or
+/// family name, in that order of preference. If several fonts in a family
+/// match, the least bold and italic one is used.
+pub fn bundle_font_with_name(env: &mut Environment, name: &str) -> Option<Rc<Font>> {
+    let fonts = bundle_fonts(env);
+    let find = |get_name: fn(&Font) -> Option<&String>| {
+        fonts
+            .iter()
+            .filter(|font| get_name(font).is_some_and(|font_name| font_name == name))
+            .min_by_key(|font| {
+                let names = font.names();
+                let style_name = names.postscript.as_ref().or(names.full.as_ref());
+                let style = BuiltinFont::style_for_name(style_name.map_or("", |s| s));
+                style.bold as u8 + style.italic as u8
+            })
+            .cloned()
+    };
+    find(|font| font.names().postscript.as_ref())
+        .or_else(|| find(|font| font.names().full.as_ref()))
+        .or_else(|| find(|font| font.names().family.as_ref()))
+}
+
+/// Look up a font that comes with touchHLE by its PostScript name or family
+/// name, e.g. "LiberationSans-Bold" or "Liberation Serif".
+pub fn builtin_font_for_exact_name(name: &str) -> Option<BuiltinFont> {
+    BuiltinFamily::ALL.into_iter().find_map(|family| {
+        let styles = BuiltinFont::all_in_family(family);
+        if name == family.name() {
+            Some(styles[0])
+        } else {
+            styles
+                .into_iter()
+                .find(|font| font.postscript_name() == name)
+        }
+    })
+}
+
+/// Get a font that comes with touchHLE.
+pub fn builtin_font(env: &mut Environment, builtin: BuiltinFont) -> Rc<Font> {
+    State::get(env)
+        .builtin_fonts
+        .entry(builtin)
+        .or_insert_with(|| Rc::new(builtin.load()))
+        .clone()
+}
+
+/// Look up a font by name. Fonts bundled with the app are used if possible,
+/// otherwise the most similar font that comes with touchHLE is substituted.
+pub fn font_with_name(env: &mut Environment, name: &str) -> Rc<Font> {
+    if let Some(font) = bundle_font_with_name(env, name) {
+        return font;
+    }
+    let builtin = builtin_font_for_exact_name(name).unwrap_or_else(|| BuiltinFont::for_name(name));
+    builtin_font(env, builtin)
+}
+
+/// Get the font to use for `text`, followed by any fonts needed for characters
+/// it has no glyphs for, in order of preference. Fallback fonts are only
+/// loaded when a character needs them.
+pub fn fonts_for_text(
+    env: &mut Environment,
+    font: Rc<Font>,
+    bold: bool,
+    text: &str,
+) -> Vec<Rc<Font>> {
+    // The first candidate is Liberation Sans, in case the font was bundled
+    // with the app and is missing some Latin characters. The rest are the
+    // fallback fonts. The fonts are kept in the order of the candidates, so
+    // that which font is used for a character doesn't depend on the rest of
+    // the text.
+    let mut fonts = vec![(0, font)];
+    for c in text.chars() {
+        if c.is_control() || fonts.iter().any(|(_, font)| font.glyph_id(c) != 0) {
+            continue;
+        }
+        for candidate in 0..=font::FALLBACK_FONT_COUNT {
+            let fallback = if candidate == 0 {
+                let builtin = BuiltinFont {
+                    family: BuiltinFamily::Sans,
+                    bold,
+                    italic: false,
+                };
+                Some(builtin_font(env, builtin))
+            } else {
+                State::get(env)
+                    .fallback_fonts
+                    .entry((candidate - 1, bold))
+                    .or_insert_with(|| Font::fallback(candidate - 1, bold).map(Rc::new))
+                    .clone()
+            };
+            let Some(fallback) = fallback else {
+                continue;
+            };
+            if fallback.glyph_id(c) != 0 {
+                if !fonts.iter().any(|(_, font)| Rc::ptr_eq(font, &fallback)) {
+                    fonts.push((candidate + 1, fallback));
+                }
+                break;
+            }
+        }
+    }
+    fonts.sort_by_key(|&(candidate, _)| candidate);
+    fonts.into_iter().map(|(_, font)| font).collect()
+}
+
+fn create(env: &mut Environment, font: Rc<Font>) -> CGFontRef {
+    let class = env.objc.get_known_class("_touchHLE_CGFont", &mut env.mem);
+    env.objc
+        .alloc_object(class, Box::new(CGFontHostObject { font }), &mut env.mem)
+}
+
+pub(super) fn borrow_font(objc: &ObjC, font: CGFontRef) -> Rc<Font> {
+    objc.borrow::<CGFontHostObject>(font).font.clone()
+}
+
+pub fn CGFontRelease(env: &mut Environment, font: CGFontRef) {
+    if !font.is_null() {
+        CFRelease(env, font);
+    }
+}
+pub fn CGFontRetain(env: &mut Environment, font: CGFontRef) -> CGFontRef {
+    if !font.is_null() {
+        CFRetain(env, font)
+    } else {
+        font
+    }
+}
+
+fn CGFontCreateWithDataProvider(env: &mut Environment, provider: CGDataProviderRef) -> CGFontRef {
+    let bytes = cg_data_provider::borrow_bytes(env, provider).to_vec();
+    let Some(font) = Font::from_bytes(bytes) else {
+        log!(
+            "CGFontCreateWithDataProvider: couldn't parse font data from {:?}, returning NULL",
+            provider
+        );
+        return nil;
+    };
+    log_dbg!("CGFontCreateWithDataProvider: loaded {:?}", font.names());
+    create(env, Rc::new(font))
+}
+
+fn CGFontCreateWithFontName(env: &mut Environment, name: CFStringRef) -> CGFontRef {
+    let name = ns_string::to_rust_string(env, name);
+    let font = font_with_name(env, &name);
+    create(env, font)
+}
+
+fn CGFontCopyPostScriptName(env: &mut Environment, font: CGFontRef) -> CFStringRef {
+    let font = borrow_font(&env.objc, font);
+    match font.names().postscript {
+        Some(ref name) => ns_string::from_rust_string(env, name.clone()),
+        None => nil,
+    }
+}
+
+fn CGFontGetUnitsPerEm(env: &mut Environment, font: CGFontRef) -> i32 {
+    borrow_font(&env.objc, font).units_per_em().into()
+}
+
+fn CGFontGetNumberOfGlyphs(env: &mut Environment, font: CGFontRef) -> u32 {
+    borrow_font(&env.objc, font).glyph_count() as u32
+}
+
+fn CGFontGetGlyphAdvances(
+    env: &mut Environment,
+    font: CGFontRef,
+    glyphs: ConstPtr<CGGlyph>,
+    count: u32,
+    advances: MutPtr<i32>,
+) -> bool {
+    let font = borrow_font(&env.objc, font);
+    for i in 0..count {
+        let glyph = env.mem.read(glyphs + i);
+        let advance = font.glyph_advance_units(glyph).round() as i32;
+        env.mem.write(advances + i, advance);
+    }
+    true
+}
+
+pub const FUNCTIONS: FunctionExports = &[
+    export_c_func!(CGFontRetain(_)),
+    export_c_func!(CGFontRelease(_)),
+    export_c_func!(CGFontCreateWithDataProvider(_)),
+    export_c_func!(CGFontCreateWithFontName(_)),
+    export_c_func!(CGFontCopyPostScriptName(_)),
+    export_c_func!(CGFontGetUnitsPerEm(_)),
+    export_c_func!(CGFontGetNumberOfGlyphs(_)),
+    export_c_func!(CGFontGetGlyphAdvances(_, _, _, _)),
+];
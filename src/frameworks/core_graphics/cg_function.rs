@@ -0,0 +1,188 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! `CGFunction.h`
+
+use super::CGFloat;
+use crate::abi::{CallFromHost, GuestFunction};
+use crate::dyld::{export_c_func, FunctionExports};
+use crate::frameworks::core_foundation::{CFRelease, CFRetain, CFTypeRef};
+use crate::mem::{ConstPtr, GuestUSize, MutPtr, MutVoidPtr, SafeRead};
+use crate::objc::{objc_classes, ClassExports, HostObject};
+use crate::Environment;
+
+pub const CLASSES: ClassExports = objc_classes! {
+
+(env, this, _cmd);
+
+// CGFunction seems to be a CFType-based type, but in our implementation those
+// are just Objective-C types, so we need a class for it, but its name is not
+// visible anywhere.
+@implementation _touchHLE_CGFunction: NSObject
+
+- (())dealloc {
+    let &CGFunctionHostObject {
+        info,
+        release_info,
+        ..
+    } = env.objc.borrow(this);
+    if release_info.addr_with_thumb_bit() != 0 {
+        () = release_info.call_from_host(env, (info,));
+    }
+    env.objc.dealloc_object(this, &mut env.mem)
+}
+
+@end
+
+};
+
+/// `(*void)(void *info, const CGFloat *in, CGFloat *out)`
+type CGFunctionEvaluateCallback = GuestFunction;
+/// `(*void)(void *info)`
+type CGFunctionReleaseInfoCallback = GuestFunction;
+
+#[repr(C, packed)]
+pub struct CGFunctionCallbacks {
+    version: u32,
+    evaluate: CGFunctionEvaluateCallback,
+    release_info: CGFunctionReleaseInfoCallback,
+}
+unsafe impl SafeRead for CGFunctionCallbacks {}
+
+struct CGFunctionHostObject {
+    /// User-provided pointer passed to the callbacks.
+    info: MutVoidPtr,
+    /// Valid interval for each input value.
+    domain: Vec<(CGFloat, CGFloat)>,
+    range_dimension: GuestUSize,
+    /// Valid interval for each output value, if limited.
+    range: Option<Vec<(CGFloat, CGFloat)>>,
+    evaluate: CGFunctionEvaluateCallback,
+    release_info: CGFunctionReleaseInfoCallback,
+}
+impl HostObject for CGFunctionHostObject {}
+
+pub type CGFunctionRef = CFTypeRef;
+
+fn read_intervals(
+    env: &Environment,
+    ptr: ConstPtr<CGFloat>,
+    count: GuestUSize,
+) -> Vec<(CGFloat, CGFloat)> {
+    (0..count)
+        .map(|i| (env.mem.read(ptr + i * 2), env.mem.read(ptr + i * 2 + 1)))
+        .collect()
+}
+
+fn CGFunctionCreate(
+    env: &mut Environment,
+    info: MutVoidPtr,
+    domain_dimension: GuestUSize,
+    domain: ConstPtr<CGFloat>,
+    range_dimension: GuestUSize,
+    range: ConstPtr<CGFloat>,
+    callbacks: ConstPtr<CGFunctionCallbacks>,
+) -> CGFunctionRef {
+    let CGFunctionCallbacks {
+        version,
+        evaluate,
+        release_info,
+    } = env.mem.read(callbacks);
+    assert!(version == 0);
+
+    let domain = if domain.is_null() {
+        vec![(0.0, 1.0); domain_dimension as usize]
+    } else {
+        read_intervals(env, domain, domain_dimension)
+    };
+    let range = (!range.is_null()).then(|| read_intervals(env, range, range_dimension));
+
+    let host_object = CGFunctionHostObject {
+        info,
+        domain,
+        range_dimension,
+        range,
+        evaluate,
+        release_info,
+    };
+    let isa = env
+        .objc
+        .get_known_class("_touchHLE_CGFunction", &mut env.mem);
+    env.objc
+        .alloc_object(isa, Box::new(host_object), &mut env.mem)
+}
+
+pub fn CGFunctionRelease(env: &mut Environment, function: CGFunctionRef) {
+    if !function.is_null() {
+        CFRelease(env, function);
+    }
+}
+pub fn CGFunctionRetain(env: &mut Environment, function: CGFunctionRef) -> CGFunctionRef {
+    if !function.is_null() {
+        CFRetain(env, function)
+    } else {
+        function
+    }
+}
+
+/// Get the interval of valid values for the first input of a function.
+pub(super) fn input_domain(env: &Environment, function: CGFunctionRef) -> (CGFloat, CGFloat) {
+    let host_obj = env.objc.borrow::<CGFunctionHostObject>(function);
+    host_obj.domain.first().copied().unwrap_or((0.0, 1.0))
+}
+
+/// Call a function with a single input value (other inputs, if any, are at
+/// the start of their domain) and return its outputs.
+pub(super) fn evaluate(
+    env: &mut Environment,
+    function: CGFunctionRef,
+    input: CGFloat,
+) -> Vec<CGFloat> {
+    let host_obj = env.objc.borrow::<CGFunctionHostObject>(function);
+    let info = host_obj.info;
+    let evaluate = host_obj.evaluate;
+    let range_dimension = host_obj.range_dimension;
+    let inputs: Vec<CGFloat> = host_obj
+        .domain
+        .iter()
+        .enumerate()
+        .map(|(i, &(min, max))| if i == 0 { input.max(min).min(max) } else { min })
+        .collect();
+
+    let size = GuestUSize::try_from(std::mem::size_of::<CGFloat>()).unwrap();
+    let in_ptr: MutPtr<CGFloat> = env
+        .mem
+        .alloc(size * (inputs.len() as GuestUSize).max(1))
+        .cast();
+    let out_ptr: MutPtr<CGFloat> = env.mem.alloc(size * range_dimension.max(1)).cast();
+    for (i, &value) in inputs.iter().enumerate() {
+        env.mem.write(in_ptr + i as GuestUSize, value);
+    }
+    for i in 0..range_dimension {
+        env.mem.write(out_ptr + i, 0.0);
+    }
+
+    () = evaluate.call_from_host(env, (info, in_ptr.cast_const(), out_ptr));
+
+    let mut outputs: Vec<CGFloat> = (0..range_dimension)
+        .map(|i| env.mem.read(out_ptr + i))
+        .collect();
+    env.mem.free(in_ptr.cast());
+    env.mem.free(out_ptr.cast());
+
+    let host_obj = env.objc.borrow::<CGFunctionHostObject>(function);
+    if let Some(ref range) = host_obj.range {
+        for (output, &(min, max)) in outputs.iter_mut().zip(range) {
+            *output = output.max(min).min(max);
+        }
+    }
+    outputs
+}
+
+pub const FUNCTIONS: FunctionExports = &[
+    export_c_func!(CGFunctionCreate(_, _, _, _, _, _)),
+    export_c_func!(CGFunctionRetain(_)),
+    export_c_func!(CGFunctionRelease(_)),
+];
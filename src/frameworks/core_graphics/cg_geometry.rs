@@ -165,6 +165,16 @@ pub const CGRectZero: CGRect = CGRect {
     size: CGSizeZero,
 };
 
+/// The "null" rectangle, returned e.g. for the intersection of rectangles
+/// that don't intersect.
+pub const CGRectNull: CGRect = CGRect {
+    origin: CGPoint {
+        x: CGFloat::INFINITY,
+        y: CGFloat::INFINITY,
+    },
+    size: CGSizeZero,
+};
+
 fn CGRectContainsPoint(_env: &mut Environment, rect: CGRect, point: CGPoint) -> bool {
     rect.origin.x <= point.x
         && rect.origin.x + rect.size.width > point.x
@@ -192,4 +202,8 @@ pub const CONSTANTS: ConstantExports = &[
         "_CGRectZero",
         HostConstant::Custom(|mem, _| mem.alloc_and_write(CGRectZero).cast().cast_const()),
     ),
+    (
+        "_CGRectNull",
+        HostConstant::Custom(|mem, _| mem.alloc_and_write(CGRectNull).cast().cast_const()),
+    ),
 ];
@@ -0,0 +1,177 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! `CGGradient.h`
+
+use super::cg_color::{self, CGColorRef};
+use super::cg_color_space::{self, CGColorSpaceHostObject, CGColorSpaceRef};
+use super::cg_shading::{Shader, ShadingGeometry, SAMPLE_COUNT};
+use super::CGFloat;
+use crate::dyld::{export_c_func, FunctionExports};
+use crate::frameworks::core_foundation::cf_array::CFArrayRef;
+use crate::frameworks::core_foundation::{CFRelease, CFRetain, CFTypeRef};
+use crate::frameworks::foundation::NSUInteger;
+use crate::mem::{ConstPtr, GuestUSize};
+use crate::objc::{msg, objc_classes, ClassExports, HostObject, ObjC};
+use crate::Environment;
+
+pub const CLASSES: ClassExports = objc_classes! {
+
+(env, this, _cmd);
+
+// CGGradient seems to be a CFType-based type, but in our implementation those
+// are just Objective-C types, so we need a class for it, but its name is not
+// visible anywhere.
+@implementation _touchHLE_CGGradient: NSObject
+@end
+
+};
+
+pub type CGGradientDrawingOptions = u32;
+pub const kCGGradientDrawsBeforeStartLocation: CGGradientDrawingOptions = 1 << 0;
+pub const kCGGradientDrawsAfterEndLocation: CGGradientDrawingOptions = 1 << 1;
+
+struct CGGradientHostObject {
+    /// Locations and colors (sRGB, not premultiplied), sorted by location.
+    stops: Vec<(CGFloat, (CGFloat, CGFloat, CGFloat, CGFloat))>,
+}
+impl HostObject for CGGradientHostObject {}
+
+pub type CGGradientRef = CFTypeRef;
+
+fn create_gradient(
+    env: &mut Environment,
+    locations: ConstPtr<CGFloat>,
+    colors: Vec<(CGFloat, CGFloat, CGFloat, CGFloat)>,
+) -> CGGradientRef {
+    let count = colors.len() as GuestUSize;
+    let mut stops: Vec<_> = colors
+        .into_iter()
+        .enumerate()
+        .map(|(i, color)| {
+            let i = i as GuestUSize;
+            let location = if !locations.is_null() {
+                env.mem.read(locations + i)
+            } else if count > 1 {
+                // Without locations, the colors are spread out evenly.
+                i as CGFloat / (count - 1) as CGFloat
+            } else {
+                0.0
+            };
+            (location, color)
+        })
+        .collect();
+    stops.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+    let isa = env
+        .objc
+        .get_known_class("_touchHLE_CGGradient", &mut env.mem);
+    env.objc
+        .alloc_object(isa, Box::new(CGGradientHostObject { stops }), &mut env.mem)
+}
+
+fn CGGradientCreateWithColorComponents(
+    env: &mut Environment,
+    space: CGColorSpaceRef,
+    components: ConstPtr<CGFloat>,
+    locations: ConstPtr<CGFloat>,
+    count: GuestUSize,
+) -> CGGradientRef {
+    let color_space = env.objc.borrow::<CGColorSpaceHostObject>(space).name;
+    let components_per_stop = cg_color_space::components_per_color(color_space) + 1;
+    let colors = (0..count)
+        .map(|i| {
+            let stop_components = components + i * components_per_stop;
+            cg_color_space::read_color(env, color_space, stop_components)
+        })
+        .collect();
+    create_gradient(env, locations, colors)
+}
+
+fn CGGradientCreateWithColors(
+    env: &mut Environment,
+    _space: CGColorSpaceRef,
+    colors: CFArrayRef,
+    locations: ConstPtr<CGFloat>,
+) -> CGGradientRef {
+    // The CGColors are already RGB, so the color space doesn't matter.
+    let count: NSUInteger = msg![env; colors count];
+    let colors = (0..count)
+        .map(|i| {
+            let color: CGColorRef = msg![env; colors objectAtIndex:i];
+            cg_color::to_rgba(&env.objc, color)
+        })
+        .collect();
+    create_gradient(env, locations, colors)
+}
+
+pub fn CGGradientRelease(env: &mut Environment, gradient: CGGradientRef) {
+    if !gradient.is_null() {
+        CFRelease(env, gradient);
+    }
+}
+pub fn CGGradientRetain(env: &mut Environment, gradient: CGGradientRef) -> CGGradientRef {
+    if !gradient.is_null() {
+        CFRetain(env, gradient)
+    } else {
+        gradient
+    }
+}
+
+impl CGGradientHostObject {
+    /// Get the color at a location, interpolating between the stops either
+    /// side of it.
+    fn color_at(&self, location: CGFloat) -> (CGFloat, CGFloat, CGFloat, CGFloat) {
+        let Some(&(first_location, first_color)) = self.stops.first() else {
+            return (0.0, 0.0, 0.0, 0.0);
+        };
+        if location <= first_location {
+            return first_color;
+        }
+        for pair in self.stops.windows(2) {
+            let [(a_location, a), (b_location, b)] = [pair[0], pair[1]];
+            if location <= b_location {
+                let t = if b_location > a_location {
+                    (location - a_location) / (b_location - a_location)
+                } else {
+                    1.0
+                };
+                return (
+                    a.0 + (b.0 - a.0) * t,
+                    a.1 + (b.1 - a.1) * t,
+                    a.2 + (b.2 - a.2) * t,
+                    a.3 + (b.3 - a.3) * t,
+                );
+            }
+        }
+        self.stops.last().unwrap().1
+    }
+}
+
+/// Prepare a gradient for drawing with the given geometry.
+pub(super) fn make_shader(
+    objc: &ObjC,
+    gradient: CGGradientRef,
+    geometry: ShadingGeometry,
+    options: CGGradientDrawingOptions,
+) -> Shader {
+    let host_obj = objc.borrow::<CGGradientHostObject>(gradient);
+    let samples = (0..SAMPLE_COUNT)
+        .map(|i| host_obj.color_at(i as CGFloat / (SAMPLE_COUNT - 1) as CGFloat))
+        .collect();
+    Shader {
+        geometry,
+        extend_start: (options & kCGGradientDrawsBeforeStartLocation) != 0,
+        extend_end: (options & kCGGradientDrawsAfterEndLocation) != 0,
+        samples,
+    }
+}
+
+pub const FUNCTIONS: FunctionExports = &[
+    export_c_func!(CGGradientCreateWithColorComponents(_, _, _, _)),
+    export_c_func!(CGGradientCreateWithColors(_, _, _)),
+    export_c_func!(CGGradientRetain(_)),
+    export_c_func!(CGGradientRelease(_)),
+];
@@ -5,13 +5,17 @@
  */
 //! `CGImage.h`
 
-use super::cg_color_space::{kCGColorSpaceGenericRGB, CGColorSpaceCreateWithName, CGColorSpaceRef};
+use super::cg_color_space::{
+    self, kCGColorSpaceGenericRGB, CGColorSpaceCreateWithName, CGColorSpaceHostObject,
+    CGColorSpaceRef,
+};
 use super::cg_data_provider::{self, CGDataProviderRef};
-use super::CGFloat;
+use super::pixel_format::{ColorModel, PixelFormat};
+use super::{CGFloat, CGRect};
 use crate::dyld::{export_c_func, FunctionExports};
 use crate::frameworks::core_foundation::{CFRelease, CFRetain, CFTypeRef};
 use crate::frameworks::foundation::ns_string;
-use crate::image::Image;
+use crate::image::{gamma_encode, Image};
 use crate::mem::{ConstPtr, GuestUSize};
 use crate::objc::{autorelease, nil, objc_classes, ClassExports, HostObject, ObjC};
 use crate::Environment;
@@ -29,18 +33,15 @@ pub const kCGImageAlphaOnly: CGImageAlphaInfo = 7;
 pub type CGImageByteOrderInfo = u32;
 pub const kCGImageByteOrderMask: CGImageByteOrderInfo = 0x7000;
 pub const kCGImageByteOrderDefault: CGImageByteOrderInfo = 0 << 12;
-#[allow(dead_code)]
 pub const kCGImageByteOrder16Little: CGImageByteOrderInfo = 1 << 12;
-#[allow(dead_code)]
 pub const kCGImageByteOrder32Little: CGImageByteOrderInfo = 2 << 12;
-#[allow(dead_code)]
 pub const kCGImageByteOrder16Big: CGImageByteOrderInfo = 3 << 12;
 pub const kCGImageByteOrder32Big: CGImageByteOrderInfo = 4 << 12;
 
 pub type CGBitmapInfo = u32;
 pub const kCGBitmapAlphaInfoMask: CGBitmapInfo = 0x1F; // huh, it's not 0x7?
 pub const kCGBitmapByteOrderMask: CGBitmapInfo = kCGImageByteOrderMask;
-// TODO: other stuff in this enum (for now, always assert the rest is 0)
+pub const kCGBitmapFloatComponents: CGBitmapInfo = 1 << 8;
 
 pub const CLASSES: ClassExports = objc_classes! {
 
@@ -56,6 +57,10 @@ pub const CLASSES: ClassExports = objc_classes! {
 
 struct CGImageHostObject {
     image: Image,
+    /// Whether this is an image mask (from `CGImageMaskCreate`). The image's
+    /// alpha channel is then how much each pixel lets through, and its color
+    /// is meaningless.
+    is_mask: bool,
 }
 impl HostObject for CGImageHostObject {}
 
@@ -76,7 +81,11 @@ pub fn CGImageRetain(env: &mut Environment, c: CGImageRef) -> CGImageRef {
 /// Shortcut for use by `UIImage`: directly construct a `CGImage` instance from
 /// an [Image] instance.
 pub fn from_image(env: &mut Environment, image: Image) -> CGImageRef {
-    let host_obj = Box::new(CGImageHostObject { image });
+    create(env, image, false)
+}
+
+pub(super) fn create(env: &mut Environment, image: Image, is_mask: bool) -> CGImageRef {
+    let host_obj = Box::new(CGImageHostObject { image, is_mask });
     let class = env.objc.get_known_class("_touchHLE_CGImage", &mut env.mem);
     env.objc.alloc_object(class, host_obj, &mut env.mem)
 }
@@ -87,20 +96,53 @@ pub fn borrow_image(objc: &ObjC, image: CGImageRef) -> &Image {
     &objc.borrow::<CGImageHostObject>(image).image
 }
 
+/// Whether a `CGImage` is an image mask.
+pub(super) fn is_mask(objc: &ObjC, image: CGImageRef) -> bool {
+    objc.borrow::<CGImageHostObject>(image).is_mask
+}
+
+/// How much a mask lets through at a pixel: for an image mask, the inverse of
+/// its samples; for an ordinary image, its (gamma-encoded) intensity.
+pub(super) fn mask_value(image: &Image, is_mask: bool, at: (i32, i32)) -> CGFloat {
+    match image.get_pixel(at) {
+        Some((_, _, _, a)) if is_mask => a,
+        // The samples are used as they are, not as light intensities, so
+        // they need to be gamma-encoded again.
+        Some((r, g, b, _)) => gamma_encode(0.3 * r + 0.59 * g + 0.11 * b),
+        None => 0.0,
+    }
+}
+
 /// Shortcut used by the app picker, counterpart to [borrow_image].
 /// FIXME: This should not exist!
 pub fn borrow_image_mut(objc: &mut ObjC, image: CGImageRef) -> &mut Image {
     &mut objc.borrow_mut::<CGImageHostObject>(image).image
 }
 
-// TODO: More create methods.
+/// Read a decode array: a range for each of `count` components.
+fn read_decode(
+    env: &Environment,
+    decode: ConstPtr<CGFloat>,
+    count: GuestUSize,
+) -> Option<Vec<(CGFloat, CGFloat)>> {
+    (!decode.is_null()).then(|| {
+        (0..count)
+            .map(|i| {
+                (
+                    env.mem.read(decode + i * 2),
+                    env.mem.read(decode + i * 2 + 1),
+                )
+            })
+            .collect()
+    })
+}
 
-fn CGImageCreateWithPNGDataProvider(
+/// Shared implementation of `CGImageCreateWithPNGDataProvider` and
+/// `CGImageCreateWithJPEGDataProvider`.
+fn create_with_encoded_data(
     env: &mut Environment,
     source: CGDataProviderRef,
     decode: ConstPtr<CGFloat>,
-    _should_interpolate: bool, // TODO
-    _intent: i32,              // TODO (should be CGColorRenderingIntent)
 ) -> CGImageRef {
     assert!(decode.is_null()); // TODO
 
@@ -113,6 +155,211 @@ fn CGImageCreateWithPNGDataProvider(
     from_image(env, image)
 }
 
+fn CGImageCreateWithPNGDataProvider(
+    env: &mut Environment,
+    source: CGDataProviderRef,
+    decode: ConstPtr<CGFloat>,
+    _should_interpolate: bool, // TODO
+    _intent: i32,              // TODO (should be CGColorRenderingIntent)
+) -> CGImageRef {
+    create_with_encoded_data(env, source, decode)
+}
+
+fn CGImageCreateWithJPEGDataProvider(
+    env: &mut Environment,
+    source: CGDataProviderRef,
+    decode: ConstPtr<CGFloat>,
+    _should_interpolate: bool, // TODO
+    _intent: i32,              // TODO (should be CGColorRenderingIntent)
+) -> CGImageRef {
+    // stb_image can decode JPEGs too.
+    create_with_encoded_data(env, source, decode)
+}
+
+/// Shared implementation of `CGImageCreate` and `CGImageMaskCreate`: convert
+/// raw pixel data to an [Image].
+fn decode_raw_pixels(
+    env: &Environment,
+    function_name: &str,
+    model: ColorModel,
+    (width, height): (GuestUSize, GuestUSize),
+    bits_per_component: GuestUSize,
+    bits_per_pixel: GuestUSize,
+    bytes_per_row: GuestUSize,
+    bitmap_info: CGBitmapInfo,
+    provider: CGDataProviderRef,
+    decode: Option<&[(CGFloat, CGFloat)]>,
+) -> Option<Image> {
+    let format = PixelFormat::new(model, bits_per_component, Some(bits_per_pixel), bitmap_info);
+    let format = match format {
+        Ok(format) => format,
+        Err(reason) => {
+            log!(
+                "{}: unsupported format ({}): {} bits per component, {} bits per pixel, bitmap \
+                 info {:#x}, returning NULL",
+                function_name,
+                reason,
+                bits_per_component,
+                bits_per_pixel,
+                bitmap_info
+            );
+            return None;
+        }
+    };
+    if !matches!(format.min_bytes_per_row(width), Some(min) if bytes_per_row >= min) {
+        log!(
+            "{}: {} bytes per row is too small for {} pixels, returning NULL",
+            function_name,
+            bytes_per_row,
+            width
+        );
+        return None;
+    }
+
+    let bytes = cg_data_provider::borrow_bytes(env, provider);
+    let pixels = format.to_rgba8(bytes, (width, height), bytes_per_row, decode);
+    Some(Image::from_pixel_vec(pixels, (width, height)))
+}
+
+fn CGImageCreate(
+    env: &mut Environment,
+    width: GuestUSize,
+    height: GuestUSize,
+    bits_per_component: GuestUSize,
+    bits_per_pixel: GuestUSize,
+    bytes_per_row: GuestUSize,
+    space: CGColorSpaceRef,
+    bitmap_info: CGBitmapInfo,
+    provider: CGDataProviderRef,
+    decode: ConstPtr<CGFloat>,
+    _should_interpolate: bool, // TODO
+    _intent: i32,              // TODO (should be CGColorRenderingIntent)
+) -> CGImageRef {
+    let space = env.objc.borrow::<CGColorSpaceHostObject>(space);
+    let Some(model) = ColorModel::for_color_space(Some(space)) else {
+        log!(
+            "CGImageCreate: unsupported color space {}, returning NULL",
+            space.name
+        );
+        return nil;
+    };
+    let alpha_info = bitmap_info & kCGBitmapAlphaInfoMask;
+    let has_alpha = !matches!(
+        alpha_info,
+        kCGImageAlphaNone | kCGImageAlphaNoneSkipLast | kCGImageAlphaNoneSkipFirst
+    );
+    let component_count = cg_color_space::components_per_color(space.name) + has_alpha as u32;
+    let decode = read_decode(env, decode, component_count);
+
+    let Some(image) = decode_raw_pixels(
+        env,
+        "CGImageCreate",
+        model,
+        (width, height),
+        bits_per_component,
+        bits_per_pixel,
+        bytes_per_row,
+        bitmap_info,
+        provider,
+        decode.as_deref(),
+    ) else {
+        return nil;
+    };
+    from_image(env, image)
+}
+
+fn CGImageMaskCreate(
+    env: &mut Environment,
+    width: GuestUSize,
+    height: GuestUSize,
+    bits_per_component: GuestUSize,
+    bits_per_pixel: GuestUSize,
+    bytes_per_row: GuestUSize,
+    provider: CGDataProviderRef,
+    decode: ConstPtr<CGFloat>,
+    _should_interpolate: bool, // TODO
+) -> CGImageRef {
+    // A sample of 0 means the pixel is painted, and 1 means it is masked out,
+    // so the samples are the inverse of alpha. Inverting the decode range
+    // takes care of that.
+    let (min, max) = read_decode(env, decode, 1).map_or((0.0, 1.0), |decode| decode[0]);
+    let decode = [(1.0 - min, 1.0 - max)];
+
+    let Some(image) = decode_raw_pixels(
+        env,
+        "CGImageMaskCreate",
+        ColorModel::None,
+        (width, height),
+        bits_per_component,
+        bits_per_pixel,
+        bytes_per_row,
+        kCGImageAlphaOnly,
+        provider,
+        Some(&decode),
+    ) else {
+        return nil;
+    };
+    create(env, image, true)
+}
+
+fn CGImageCreateWithImageInRect(
+    env: &mut Environment,
+    image: CGImageRef,
+    rect: CGRect,
+) -> CGImageRef {
+    let host_obj = env.objc.borrow::<CGImageHostObject>(image);
+    let (width, height) = host_obj.image.dimensions();
+    // The rect is in pixels, with the origin at the top-left, and is rounded
+    // outwards to whole pixels.
+    let x_start = rect.origin.x.floor().clamp(0.0, width as CGFloat);
+    let y_start = rect.origin.y.floor().clamp(0.0, height as CGFloat);
+    let x_end = (rect.origin.x + rect.size.width)
+        .ceil()
+        .clamp(0.0, width as CGFloat);
+    let y_end = (rect.origin.y + rect.size.height)
+        .ceil()
+        .clamp(0.0, height as CGFloat);
+    if x_end <= x_start || y_end <= y_start {
+        return nil;
+    }
+    let sub_image = host_obj.image.sub_image(
+        (x_start as u32, y_start as u32),
+        ((x_end - x_start) as u32, (y_end - y_start) as u32),
+    );
+    let is_mask = host_obj.is_mask;
+    create(env, sub_image, is_mask)
+}
+
+fn CGImageCreateWithMask(env: &mut Environment, image: CGImageRef, mask: CGImageRef) -> CGImageRef {
+    let image_host_obj = env.objc.borrow::<CGImageHostObject>(image);
+    if image_host_obj.is_mask {
+        return nil;
+    }
+    let image = &image_host_obj.image;
+    let mask_host_obj = env.objc.borrow::<CGImageHostObject>(mask);
+    let mask = &mask_host_obj.image;
+
+    // The mask is stretched to cover the image.
+    let (width, height) = image.dimensions();
+    let (mask_width, mask_height) = mask.dimensions();
+    let mut pixels = image.pixels().to_vec();
+    for (i, rgba) in pixels.chunks_exact_mut(4).enumerate() {
+        let (x, y) = (i as u64 % width as u64, i as u64 / width as u64);
+        let mask_x = x * mask_width as u64 / width as u64;
+        let mask_y = y * mask_height as u64 / height as u64;
+        let value = mask_value(mask, mask_host_obj.is_mask, (mask_x as i32, mask_y as i32));
+        // The pixels have premultiplied alpha, so all components are scaled.
+        for component in rgba {
+            *component = (*component as CGFloat * value).round() as u8;
+        }
+    }
+    from_image(env, Image::from_pixel_vec(pixels, (width, height)))
+}
+
+fn CGImageIsMask(env: &mut Environment, image: CGImageRef) -> bool {
+    is_mask(&env.objc, image)
+}
+
 fn CGImageGetAlphaInfo(_env: &mut Environment, _image: CGImageRef) -> CGImageAlphaInfo {
     // our Image type always returns premultiplied RGBA
     // (the premultiplied part must match what the real UIImage does, but
@@ -147,6 +394,10 @@ fn CGImageGetHeight(env: &mut Environment, image: CGImageRef) -> GuestUSize {
 fn CGImageGetBitsPerPixel(_env: &mut Environment, _image: CGImageRef) -> GuestUSize {
     32
 }
+fn CGImageGetBytesPerRow(env: &mut Environment, image: CGImageRef) -> GuestUSize {
+    // Our Image type always has tightly-packed rows of 32-bit pixels.
+    CGImageGetWidth(env, image) * 4
+}
 
 fn CGImageGetDataProvider(env: &mut Environment, image: CGImageRef) -> CGDataProviderRef {
     // CGImageGetDataProvider() seems to be intended to return the underlying
@@ -167,12 +418,19 @@ fn CGImageGetBitsPerComponent(_: &mut Environment, _: CGImageRef) -> GuestUSize
 pub const FUNCTIONS: FunctionExports = &[
     export_c_func!(CGImageRelease(_)),
     export_c_func!(CGImageRetain(_)),
+    export_c_func!(CGImageCreate(_, _, _, _, _, _, _, _, _, _, _)),
     export_c_func!(CGImageCreateWithPNGDataProvider(_, _, _, _)),
+    export_c_func!(CGImageCreateWithJPEGDataProvider(_, _, _, _)),
+    export_c_func!(CGImageMaskCreate(_, _, _, _, _, _, _, _)),
+    export_c_func!(CGImageCreateWithImageInRect(_, _)),
+    export_c_func!(CGImageCreateWithMask(_, _)),
+    export_c_func!(CGImageIsMask(_)),
     export_c_func!(CGImageGetAlphaInfo(_)),
     export_c_func!(CGImageGetColorSpace(_)),
     export_c_func!(CGImageGetWidth(_)),
     export_c_func!(CGImageGetHeight(_)),
     export_c_func!(CGImageGetBitsPerPixel(_)),
+    export_c_func!(CGImageGetBytesPerRow(_)),
     export_c_func!(CGImageGetDataProvider(_)),
     export_c_func!(CGImageGetBitsPerComponent(_)),
 ];
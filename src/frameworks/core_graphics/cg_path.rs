@@ -0,0 +1,1224 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! `CGPath.h`
+//!
+//! Besides the `CGPath` API, this module contains the host-side geometry used
+//! for drawing paths in a `CGContext`: flattening curves into polygons,
+//! stroking and dashing, and an anti-aliasing scanline rasterizer.
+
+use super::cg_affine_transform::{CGAffineTransform, CGAffineTransformIdentity};
+use super::cg_geometry::CGRectNull;
+use super::{CGFloat, CGPoint, CGRect, CGSize};
+use crate::dyld::{export_c_func, FunctionExports};
+use crate::frameworks::core_foundation::{CFRelease, CFRetain, CFTypeRef};
+use crate::mem::{ConstPtr, GuestUSize};
+use crate::objc::{objc_classes, ClassExports, HostObject};
+use crate::Environment;
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+pub const CLASSES: ClassExports = objc_classes! {
+
+(env, this, _cmd);
+
+// CGPath seems to be a CFType-based type, but in our implementation those
+// are just Objective-C types, so we need a class for it, but its name is not
+// visible anywhere.
+@implementation _touchHLE_CGPath: NSObject
+@end
+
+};
+
+struct CGPathHostObject {
+    path: Path,
+}
+impl HostObject for CGPathHostObject {}
+
+pub type CGPathRef = CFTypeRef;
+pub type CGMutablePathRef = CFTypeRef;
+
+pub type CGLineCap = i32;
+pub const kCGLineCapButt: CGLineCap = 0;
+pub const kCGLineCapRound: CGLineCap = 1;
+pub const kCGLineCapSquare: CGLineCap = 2;
+
+pub type CGLineJoin = i32;
+pub const kCGLineJoinMiter: CGLineJoin = 0;
+pub const kCGLineJoinRound: CGLineJoin = 1;
+#[allow(dead_code)]
+pub const kCGLineJoinBevel: CGLineJoin = 2;
+
+fn point(x: CGFloat, y: CGFloat) -> CGPoint {
+    CGPoint { x, y }
+}
+fn add(a: CGPoint, b: CGPoint) -> CGPoint {
+    point(a.x + b.x, a.y + b.y)
+}
+fn sub(a: CGPoint, b: CGPoint) -> CGPoint {
+    point(a.x - b.x, a.y - b.y)
+}
+fn scale(a: CGPoint, factor: CGFloat) -> CGPoint {
+    point(a.x * factor, a.y * factor)
+}
+fn length(a: CGPoint) -> CGFloat {
+    a.x.hypot(a.y)
+}
+fn normalize(a: CGPoint) -> CGPoint {
+    scale(a, 1.0 / length(a))
+}
+fn dot(a: CGPoint, b: CGPoint) -> CGFloat {
+    a.x * b.x + a.y * b.y
+}
+fn cross(a: CGPoint, b: CGPoint) -> CGFloat {
+    a.x * b.y - a.y * b.x
+}
+/// Rotates a vector 90° counterclockwise (with y pointing up).
+fn perpendicular(a: CGPoint) -> CGPoint {
+    point(-a.y, a.x)
+}
+fn lerp(a: CGPoint, b: CGPoint, t: CGFloat) -> CGPoint {
+    add(a, scale(sub(b, a), t))
+}
+
+/// Mirrors `CGPathElement`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PathElement {
+    MoveToPoint(CGPoint),
+    AddLineToPoint(CGPoint),
+    AddQuadCurveToPoint(CGPoint, CGPoint),
+    AddCurveToPoint(CGPoint, CGPoint, CGPoint),
+    CloseSubpath,
+}
+impl PathElement {
+    fn transform(self, transform: CGAffineTransform) -> Self {
+        let apply = |p| transform.apply_to_point(p);
+        match self {
+            PathElement::MoveToPoint(p) => PathElement::MoveToPoint(apply(p)),
+            PathElement::AddLineToPoint(p) => PathElement::AddLineToPoint(apply(p)),
+            PathElement::AddQuadCurveToPoint(c, p) => {
+                PathElement::AddQuadCurveToPoint(apply(c), apply(p))
+            }
+            PathElement::AddCurveToPoint(c1, c2, p) => {
+                PathElement::AddCurveToPoint(apply(c1), apply(c2), apply(p))
+            }
+            PathElement::CloseSubpath => PathElement::CloseSubpath,
+        }
+    }
+}
+
+/// A sequence of connected lines and curves, possibly with multiple
+/// subpaths.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Path {
+    elements: Vec<PathElement>,
+}
+
+/// A piece of a flattened path.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polyline {
+    pub points: Vec<CGPoint>,
+    pub closed: bool,
+}
+
+impl Path {
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+    pub fn clear(&mut self) {
+        self.elements.clear();
+    }
+
+    /// The end point of the last element, or [None] if there is none.
+    pub fn current_point(&self) -> Option<CGPoint> {
+        match self.elements.last()? {
+            &PathElement::MoveToPoint(p)
+            | &PathElement::AddLineToPoint(p)
+            | &PathElement::AddQuadCurveToPoint(_, p)
+            | &PathElement::AddCurveToPoint(_, _, p) => Some(p),
+            // Closing a subpath moves back to where it started.
+            PathElement::CloseSubpath => self.elements.iter().rev().find_map(|el| match el {
+                &PathElement::MoveToPoint(p) => Some(p),
+                _ => None,
+            }),
+        }
+    }
+
+    pub fn move_to(&mut self, p: CGPoint) {
+        self.elements.push(PathElement::MoveToPoint(p));
+    }
+    pub fn line_to(&mut self, p: CGPoint) {
+        if self.current_point().is_none() {
+            log!("Warning: adding a line to a path with no current point, ignoring");
+            return;
+        }
+        self.elements.push(PathElement::AddLineToPoint(p));
+    }
+    pub fn quad_curve_to(&mut self, c: CGPoint, p: CGPoint) {
+        if self.current_point().is_none() {
+            log!("Warning: adding a curve to a path with no current point, ignoring");
+            return;
+        }
+        self.elements.push(PathElement::AddQuadCurveToPoint(c, p));
+    }
+    pub fn curve_to(&mut self, c1: CGPoint, c2: CGPoint, p: CGPoint) {
+        if self.current_point().is_none() {
+            log!("Warning: adding a curve to a path with no current point, ignoring");
+            return;
+        }
+        self.elements.push(PathElement::AddCurveToPoint(c1, c2, p));
+    }
+    pub fn close_subpath(&mut self) {
+        match self.elements.last() {
+            None | Some(PathElement::CloseSubpath) => (),
+            Some(_) => self.elements.push(PathElement::CloseSubpath),
+        }
+    }
+
+    pub fn add_rect(&mut self, rect: CGRect) {
+        let CGRect {
+            origin: CGPoint { x, y },
+            size: CGSize { width, height },
+        } = rect;
+        self.move_to(point(x, y));
+        self.line_to(point(x + width, y));
+        self.line_to(point(x + width, y + height));
+        self.line_to(point(x, y + height));
+        self.close_subpath();
+    }
+
+    pub fn add_ellipse_in_rect(&mut self, rect: CGRect) {
+        let rx = rect.size.width / 2.0;
+        let ry = rect.size.height / 2.0;
+        let centre = point(rect.origin.x + rx, rect.origin.y + ry);
+        // Build a unit circle and then stretch it to fit the rect.
+        let mut circle = Path::default();
+        circle.add_arc(point(0.0, 0.0), 1.0, 0.0, TAU, false);
+        circle.close_subpath();
+        let transform = CGAffineTransform::make_scale(rx, ry)
+            .concat(CGAffineTransform::make_translation(centre.x, centre.y));
+        self.add_path(&circle, transform);
+    }
+
+    /// Adds an arc of a circle, connected to the current point (if any) by a
+    /// line. Angles are in radians and `clockwise` means decreasing angle.
+    pub fn add_arc(
+        &mut self,
+        centre: CGPoint,
+        radius: CGFloat,
+        start_angle: CGFloat,
+        end_angle: CGFloat,
+        clockwise: bool,
+    ) {
+        let sweep = if clockwise {
+            let difference = start_angle - end_angle;
+            if difference >= TAU {
+                -TAU
+            } else {
+                -difference.rem_euclid(TAU)
+            }
+        } else {
+            let difference = end_angle - start_angle;
+            if difference >= TAU {
+                TAU
+            } else {
+                difference.rem_euclid(TAU)
+            }
+        };
+
+        let start = add(
+            centre,
+            scale(point(start_angle.cos(), start_angle.sin()), radius),
+        );
+        if self.current_point().is_some() {
+            self.line_to(start);
+        } else {
+            self.move_to(start);
+        }
+        self.add_arc_segments(centre, radius, start_angle, sweep);
+    }
+
+    /// Adds cubic Bézier approximations of an arc that starts at the current
+    /// point. Each curve covers at most a quarter circle.
+    fn add_arc_segments(
+        &mut self,
+        centre: CGPoint,
+        radius: CGFloat,
+        start: CGFloat,
+        sweep: CGFloat,
+    ) {
+        let count = (sweep.abs() / FRAC_PI_2).ceil().max(1.0) as u32;
+        let step = sweep / count as f32;
+        let k = 4.0 / 3.0 * (step / 4.0).tan() * radius;
+        let mut angle = start;
+        for _ in 0..count {
+            let next = angle + step;
+            let (sin_a, cos_a) = angle.sin_cos();
+            let (sin_b, cos_b) = next.sin_cos();
+            let from = add(centre, scale(point(cos_a, sin_a), radius));
+            let to = add(centre, scale(point(cos_b, sin_b), radius));
+            self.curve_to(
+                add(from, scale(point(-sin_a, cos_a), k)),
+                sub(to, scale(point(-sin_b, cos_b), k)),
+                to,
+            );
+            angle = next;
+        }
+    }
+
+    /// Adds an arc tangent to the line from the current point to `p1` and to
+    /// the line from `p1` to `p2`, preceded by a line to the first tangent
+    /// point.
+    pub fn add_arc_to_point(&mut self, p1: CGPoint, p2: CGPoint, radius: CGFloat) {
+        let Some(p0) = self.current_point() else {
+            log!("Warning: adding an arc to a path with no current point, ignoring");
+            return;
+        };
+        let v1 = sub(p0, p1);
+        let v2 = sub(p2, p1);
+        if radius <= 0.0 || length(v1) == 0.0 || length(v2) == 0.0 || cross(v1, v2) == 0.0 {
+            self.line_to(p1);
+            return;
+        }
+        let (v1, v2) = (normalize(v1), normalize(v2));
+        let angle = dot(v1, v2).clamp(-1.0, 1.0).acos();
+        let tangent_distance = radius / (angle / 2.0).tan();
+        let t1 = add(p1, scale(v1, tangent_distance));
+        let t2 = add(p1, scale(v2, tangent_distance));
+        let centre = add(
+            p1,
+            scale(normalize(add(v1, v2)), radius / (angle / 2.0).sin()),
+        );
+
+        let start = sub(t1, centre);
+        let end = sub(t2, centre);
+        let start_angle = start.y.atan2(start.x);
+        let sweep = (std::f32::consts::PI - angle).copysign(cross(start, end));
+        self.line_to(t1);
+        self.add_arc_segments(centre, radius, start_angle, sweep);
+    }
+
+    /// Appends the elements of another path after applying `transform`.
+    pub fn add_path(&mut self, other: &Path, transform: CGAffineTransform) {
+        self.elements.extend(
+            other
+                .elements
+                .iter()
+                .map(|&element| element.transform(transform)),
+        );
+    }
+
+    /// Runs `build` in the co-ordinate space of `transform`: everything it
+    /// adds is transformed before being appended to this path. The current
+    /// point is carried over, so arcs and lines connect as expected.
+    pub fn add_transformed(&mut self, transform: CGAffineTransform, build: impl FnOnce(&mut Path)) {
+        if transform.is_identity() {
+            build(self);
+            return;
+        }
+        let mut sub_path = Path::default();
+        let start = self.current_point();
+        if let Some(start) = start {
+            sub_path.move_to(transform.invert().apply_to_point(start));
+        }
+        build(&mut sub_path);
+        let skip = start.is_some() as usize;
+        self.elements.extend(
+            sub_path.elements[skip..]
+                .iter()
+                .map(|&element| element.transform(transform)),
+        );
+    }
+
+    pub fn transform(&self, transform: CGAffineTransform) -> Path {
+        let mut path = Path::default();
+        path.add_path(self, transform);
+        path
+    }
+
+    /// Bounding box of all points in the path, including control points.
+    /// Returns `CGRectNull` for an empty path.
+    pub fn bounding_box(&self) -> CGRect {
+        let mut points = self.elements.iter().flat_map(|element| match *element {
+            PathElement::MoveToPoint(p) | PathElement::AddLineToPoint(p) => vec![p],
+            PathElement::AddQuadCurveToPoint(c, p) => vec![c, p],
+            PathElement::AddCurveToPoint(c1, c2, p) => vec![c1, c2, p],
+            PathElement::CloseSubpath => vec![],
+        });
+        let Some(first) = points.next() else {
+            return CGRectNull;
+        };
+        let (min, max) = points.fold((first, first), |(min, max), p| {
+            (
+                point(min.x.min(p.x), min.y.min(p.y)),
+                point(max.x.max(p.x), max.y.max(p.y)),
+            )
+        });
+        CGRect {
+            origin: min,
+            size: CGSize {
+                width: max.x - min.x,
+                height: max.y - min.y,
+            },
+        }
+    }
+
+    /// Approximates the path with polylines. Curves are split into line
+    /// segments that stray from the curve by at most about `tolerance`.
+    pub fn flatten(&self, tolerance: CGFloat) -> Vec<Polyline> {
+        let mut polylines = Vec::new();
+        let mut current: Option<Polyline> = None;
+        let mut last = point(0.0, 0.0);
+        for &element in &self.elements {
+            match element {
+                PathElement::MoveToPoint(p) => {
+                    polylines.extend(current.take().filter(|polyline| polyline.points.len() > 1));
+                    current = Some(Polyline {
+                        points: vec![p],
+                        closed: false,
+                    });
+                    last = p;
+                }
+                PathElement::AddLineToPoint(p) => {
+                    current.as_mut().unwrap().points.push(p);
+                    last = p;
+                }
+                PathElement::AddQuadCurveToPoint(c, p) => {
+                    let deviation = length(add(sub(last, scale(c, 2.0)), p));
+                    let count = segment_count(deviation / 4.0, tolerance);
+                    let points = &mut current.as_mut().unwrap().points;
+                    for i in 1..=count {
+                        let t = i as f32 / count as f32;
+                        points.push(lerp(lerp(last, c, t), lerp(c, p, t), t));
+                    }
+                    last = p;
+                }
+                PathElement::AddCurveToPoint(c1, c2, p) => {
+                    let deviation = length(add(sub(last, scale(c1, 2.0)), c2))
+                        .max(length(add(sub(c1, scale(c2, 2.0)), p)));
+                    let count = segment_count(deviation * 0.75, tolerance);
+                    let points = &mut current.as_mut().unwrap().points;
+                    for i in 1..=count {
+                        let t = i as f32 / count as f32;
+                        let a = lerp(lerp(last, c1, t), lerp(c1, c2, t), t);
+                        let b = lerp(lerp(c1, c2, t), lerp(c2, p, t), t);
+                        points.push(lerp(a, b, t));
+                    }
+                    last = p;
+                }
+                PathElement::CloseSubpath => {
+                    let mut polyline = current.take().unwrap();
+                    polyline.closed = true;
+                    last = polyline.points[0];
+                    // Anything drawn after closing starts a new subpath at the
+                    // same point.
+                    current = Some(Polyline {
+                        points: vec![last],
+                        closed: false,
+                    });
+                    if polyline.points.len() > 1 {
+                        polylines.push(polyline);
+                    }
+                }
+            }
+        }
+        polylines.extend(current.filter(|polyline| polyline.points.len() > 1));
+        polylines
+    }
+}
+
+/// Number of line segments needed so that a curve whose control points
+/// deviate from a straight line by `deviation` is approximated within
+/// `tolerance`.
+fn segment_count(deviation: CGFloat, tolerance: CGFloat) -> u32 {
+    ((deviation / tolerance).sqrt().ceil() as u32).clamp(1, 100)
+}
+
+/// Parameters for stroking a path.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StrokeStyle {
+    pub width: CGFloat,
+    pub cap: CGLineCap,
+    pub join: CGLineJoin,
+    pub miter_limit: CGFloat,
+    pub dash_phase: CGFloat,
+    /// Lengths of alternating painted and unpainted segments. The line is
+    /// solid if this is empty.
+    pub dash_lengths: Vec<CGFloat>,
+}
+impl Default for StrokeStyle {
+    fn default() -> Self {
+        StrokeStyle {
+            width: 1.0,
+            cap: kCGLineCapButt,
+            join: kCGLineJoinMiter,
+            miter_limit: 10.0,
+            dash_phase: 0.0,
+            dash_lengths: Vec::new(),
+        }
+    }
+}
+
+/// Splits polylines into the painted pieces of a dash pattern.
+fn dash(polylines: Vec<Polyline>, phase: CGFloat, lengths: &[CGFloat]) -> Vec<Polyline> {
+    let total: CGFloat = lengths.iter().sum();
+    if lengths.iter().any(|&length| length < 0.0) || total <= 0.0 {
+        return polylines;
+    }
+
+    let mut dashes = Vec::new();
+    for polyline in polylines {
+        let mut points = polyline.points;
+        if polyline.closed {
+            points.push(points[0]);
+        }
+
+        // Find where in the pattern the phase puts the start of the line.
+        let mut index = 0;
+        let mut remaining = lengths[0];
+        let mut skip = phase.rem_euclid(total);
+        while skip >= remaining {
+            skip -= remaining;
+            index = (index + 1) % lengths.len();
+            remaining = lengths[index];
+        }
+        remaining -= skip;
+
+        let mut current = (index % 2 == 0).then(|| vec![points[0]]);
+        for segment in points.windows(2) {
+            let (a, b) = (segment[0], segment[1]);
+            let segment_length = length(sub(b, a));
+            let mut travelled = 0.0;
+            while segment_length - travelled > remaining {
+                travelled += remaining;
+                let split = lerp(a, b, travelled / segment_length);
+                if let Some(mut dash) = current.take() {
+                    dash.push(split);
+                    dashes.push(Polyline {
+                        points: dash,
+                        closed: false,
+                    });
+                } else {
+                    current = Some(vec![split]);
+                }
+                index = (index + 1) % lengths.len();
+                remaining = lengths[index];
+            }
+            remaining -= segment_length - travelled;
+            if let Some(dash) = current.as_mut() {
+                dash.push(b);
+            }
+        }
+        if let Some(dash) = current {
+            dashes.push(Polyline {
+                points: dash,
+                closed: false,
+            });
+        }
+    }
+    dashes
+}
+
+/// Adds a polygon to a list, making sure it has a positive (counterclockwise)
+/// orientation, so that overlapping pieces of a stroke don't cancel out with
+/// the non-zero winding rule.
+fn push_polygon(polygons: &mut Vec<Vec<CGPoint>>, mut polygon: Vec<CGPoint>) {
+    let area: CGFloat = (0..polygon.len())
+        .map(|i| cross(polygon[i], polygon[(i + 1) % polygon.len()]))
+        .sum();
+    if area == 0.0 || !area.is_finite() {
+        return;
+    }
+    if area < 0.0 {
+        polygon.reverse();
+    }
+    polygons.push(polygon);
+}
+
+fn circle_polygon(centre: CGPoint, radius: CGFloat, tolerance: CGFloat) -> Vec<CGPoint> {
+    let count = if radius > tolerance {
+        (TAU / (2.0 * (1.0 - tolerance / radius).acos())).ceil() as u32
+    } else {
+        0
+    }
+    .clamp(8, 256);
+    (0..count)
+        .map(|i| {
+            let angle = TAU * i as f32 / count as f32;
+            add(centre, scale(point(angle.cos(), angle.sin()), radius))
+        })
+        .collect()
+}
+
+/// Converts polylines into polygons covering their stroke. The result should
+/// be filled with the non-zero winding rule.
+pub fn stroke(
+    polylines: Vec<Polyline>,
+    style: &StrokeStyle,
+    tolerance: CGFloat,
+) -> Vec<Vec<CGPoint>> {
+    let polylines = if style.dash_lengths.is_empty() {
+        polylines
+    } else {
+        dash(polylines, style.dash_phase, &style.dash_lengths)
+    };
+
+    let half_width = style.width / 2.0;
+    let mut polygons = Vec::new();
+
+    let cap = |polygons: &mut Vec<_>, p: CGPoint, direction: CGPoint| match style.cap {
+        kCGLineCapRound => push_polygon(polygons, circle_polygon(p, half_width, tolerance)),
+        kCGLineCapSquare => {
+            let side = scale(perpendicular(direction), half_width);
+            let end = add(p, scale(direction, half_width));
+            push_polygon(
+                polygons,
+                vec![add(p, side), add(end, side), sub(end, side), sub(p, side)],
+            );
+        }
+        _ => (),
+    };
+
+    for polyline in polylines {
+        let mut points = polyline.points;
+        points.dedup_by(|a, b| length(sub(*a, *b)) < 1e-6);
+        if polyline.closed
+            && points.len() > 1
+            && length(sub(points[0], points[points.len() - 1])) < 1e-6
+        {
+            points.pop();
+        }
+
+        if points.len() == 1 {
+            // A zero-length subpath is only visible because of its caps.
+            match style.cap {
+                kCGLineCapRound => cap(&mut polygons, points[0], point(1.0, 0.0)),
+                kCGLineCapSquare => {
+                    let p = points[0];
+                    let offset = point(half_width, half_width);
+                    push_polygon(
+                        &mut polygons,
+                        vec![
+                            sub(p, offset),
+                            point(p.x + half_width, p.y - half_width),
+                            add(p, offset),
+                            point(p.x - half_width, p.y + half_width),
+                        ],
+                    );
+                }
+                _ => (),
+            }
+            continue;
+        }
+
+        let closed = polyline.closed && points.len() > 2;
+        let segment_count = if closed {
+            points.len()
+        } else {
+            points.len() - 1
+        };
+        let direction = |i: usize| {
+            normalize(sub(
+                points[(i + 1) % points.len()],
+                points[i % points.len()],
+            ))
+        };
+
+        for i in 0..segment_count {
+            let a = points[i];
+            let b = points[(i + 1) % points.len()];
+            let side = scale(perpendicular(direction(i)), half_width);
+            push_polygon(
+                &mut polygons,
+                vec![add(a, side), add(b, side), sub(b, side), sub(a, side)],
+            );
+        }
+
+        let joins = if closed {
+            0..points.len()
+        } else {
+            1..points.len() - 1
+        };
+        for i in joins {
+            let p = points[i];
+            let incoming = direction(i + points.len() - 1);
+            let outgoing = direction(i);
+            let turn = cross(incoming, outgoing);
+            let cos_turn = dot(incoming, outgoing);
+            if turn.abs() < 1e-6 && cos_turn > 0.0 {
+                continue;
+            }
+            if style.join == kCGLineJoinRound {
+                push_polygon(&mut polygons, circle_polygon(p, half_width, tolerance));
+                continue;
+            }
+            // The gap to fill is on the outside of the turn.
+            let outside = if turn > 0.0 { -half_width } else { half_width };
+            let o1 = scale(perpendicular(incoming), outside);
+            let o2 = scale(perpendicular(outgoing), outside);
+            // Ratio of the miter length to the line width.
+            let miter_ratio = 1.0 / ((1.0 + cos_turn) / 2.0).sqrt();
+            if style.join == kCGLineJoinMiter && miter_ratio <= style.miter_limit {
+                let tip = add(p, scale(normalize(add(o1, o2)), half_width * miter_ratio));
+                push_polygon(&mut polygons, vec![p, add(p, o1), tip, add(p, o2)]);
+            } else {
+                push_polygon(&mut polygons, vec![p, add(p, o1), add(p, o2)]);
+            }
+        }
+
+        if !closed {
+            cap(&mut polygons, points[0], scale(direction(0), -1.0));
+            cap(
+                &mut polygons,
+                points[points.len() - 1],
+                direction(points.len() - 2),
+            );
+        }
+    }
+    polygons
+}
+
+/// Mirrors the distinction between `CGContextFillPath` and
+/// `CGContextEOFillPath`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FillRule {
+    Winding,
+    EvenOdd,
+}
+impl FillRule {
+    fn is_inside(self, winding: i32) -> bool {
+        match self {
+            FillRule::Winding => winding != 0,
+            FillRule::EvenOdd => winding % 2 != 0,
+        }
+    }
+}
+
+/// Returns whether `p` is inside the polygons according to `rule`.
+pub fn contains_point(polygons: &[Vec<CGPoint>], p: CGPoint, rule: FillRule) -> bool {
+    let mut winding = 0;
+    for polygon in polygons {
+        for i in 0..polygon.len() {
+            let a = polygon[i];
+            let b = polygon[(i + 1) % polygon.len()];
+            if (a.y <= p.y) != (b.y <= p.y) {
+                let x = a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x);
+                if x > p.x {
+                    winding += if a.y < b.y { 1 } else { -1 };
+                }
+            }
+        }
+    }
+    rule.is_inside(winding)
+}
+
+/// Per-pixel coverage of a rasterized shape, in the range 0 to 1, for a
+/// rectangle of pixels. Co-ordinates are absolute pixel co-ordinates for the
+/// target bitmap, with the origin in the bottom-left corner.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Coverage {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    data: Vec<f32>,
+}
+impl Coverage {
+    /// Computes the coverage of each pixel in a rectangle with `f`.
+    pub fn from_fn(
+        origin: (i32, i32),
+        size: (u32, u32),
+        mut f: impl FnMut((i32, i32)) -> f32,
+    ) -> Coverage {
+        let (x, y) = origin;
+        let (width, height) = size;
+        let data = (0..height as i32)
+            .flat_map(|row| (0..width as i32).map(move |column| (x + column, y + row)))
+            .map(|coords| f(coords).clamp(0.0, 1.0))
+            .collect();
+        Coverage {
+            x,
+            y,
+            width,
+            height,
+            data,
+        }
+    }
+
+    /// The rectangle of pixels this covers, as an origin and a size.
+    pub fn bounds(&self) -> ((i32, i32), (u32, u32)) {
+        ((self.x, self.y), (self.width, self.height))
+    }
+
+    /// Coverage of the area covered by both `self` and `other`.
+    pub fn intersect(&self, other: &Coverage) -> Coverage {
+        let x0 = self.x.max(other.x);
+        let y0 = self.y.max(other.y);
+        let x1 = (self.x + self.width as i32).min(other.x + other.width as i32);
+        let y1 = (self.y + self.height as i32).min(other.y + other.height as i32);
+        let size = ((x1 - x0).max(0) as u32, (y1 - y0).max(0) as u32);
+        Coverage::from_fn((x0, y0), size, |coords| {
+            self.get(coords) * other.get(coords)
+        })
+    }
+
+    pub fn get(&self, coords: (i32, i32)) -> f32 {
+        let (x, y) = (coords.0 - self.x, coords.1 - self.y);
+        if x < 0 || y < 0 || x as u32 >= self.width || y as u32 >= self.height {
+            return 0.0;
+        }
+        self.data[y as usize * self.width as usize + x as usize]
+    }
+    /// Iterates over the pixels with non-zero coverage, in raster order.
+    pub fn iter(&self) -> impl Iterator<Item = ((i32, i32), f32)> + '_ {
+        self.data.iter().enumerate().filter_map(|(i, &coverage)| {
+            let x = self.x + (i % self.width as usize) as i32;
+            let y = self.y + (i / self.width as usize) as i32;
+            (coverage > 0.0).then_some(((x, y), coverage))
+        })
+    }
+}
+
+struct Edge {
+    top: f32,
+    bottom: f32,
+    /// x co-ordinate at `top`
+    x: f32,
+    dx_dy: f32,
+    winding: i32,
+}
+
+/// Number of sub-scanlines sampled per row of pixels. Horizontal coverage is
+/// computed exactly.
+const SUBSAMPLES: u32 = 8;
+
+/// Computes the coverage of polygons within a bitmap of the given size. This
+/// is an anti-aliasing scanline rasterizer: each row of pixels is sampled at
+/// several heights, and the spans found to be inside the shape contribute to
+/// pixels in proportion to how much of them they cover.
+pub fn rasterize(polygons: &[Vec<CGPoint>], rule: FillRule, size: (u32, u32)) -> Coverage {
+    let mut edges = Vec::new();
+    for polygon in polygons {
+        for i in 0..polygon.len() {
+            let a = polygon[i];
+            let b = polygon[(i + 1) % polygon.len()];
+            if a.y == b.y
+                || !(a.x.is_finite() && a.y.is_finite() && b.x.is_finite() && b.y.is_finite())
+            {
+                continue;
+            }
+            let (top, bottom, winding) = if a.y < b.y { (a, b, 1) } else { (b, a, -1) };
+            edges.push(Edge {
+                top: top.y,
+                bottom: bottom.y,
+                x: top.x,
+                dx_dy: (bottom.x - top.x) / (bottom.y - top.y),
+                winding,
+            });
+        }
+    }
+
+    let (x_min, x_max, y_min, y_max) = edges.iter().fold(
+        (
+            f32::INFINITY,
+            f32::NEG_INFINITY,
+            f32::INFINITY,
+            f32::NEG_INFINITY,
+        ),
+        |(x_min, x_max, y_min, y_max), edge| {
+            let x_end = edge.x + edge.dx_dy * (edge.bottom - edge.top);
+            (
+                x_min.min(edge.x).min(x_end),
+                x_max.max(edge.x).max(x_end),
+                y_min.min(edge.top),
+                y_max.max(edge.bottom),
+            )
+        },
+    );
+    let x0 = x_min.floor().max(0.0) as i32;
+    let x1 = x_max.ceil().min(size.0 as f32) as i32;
+    let y0 = y_min.floor().max(0.0) as i32;
+    let y1 = y_max.ceil().min(size.1 as f32) as i32;
+    if edges.is_empty() || x1 <= x0 || y1 <= y0 {
+        return Coverage::from_fn((0, 0), (0, 0), |_| 0.0);
+    }
+    let width = (x1 - x0) as usize;
+    let height = (y1 - y0) as usize;
+
+    edges.sort_by(|a, b| a.top.total_cmp(&b.top));
+
+    let mut data = vec![0.0; width * height];
+    let mut partial = vec![0.0f32; width + 1];
+    let mut full = vec![0.0f32; width + 1];
+    let mut active: Vec<usize> = Vec::new();
+    let mut next_edge = 0;
+    let mut crossings: Vec<(f32, i32)> = Vec::new();
+    let weight = 1.0 / SUBSAMPLES as f32;
+
+    for row in 0..height {
+        partial.fill(0.0);
+        full.fill(0.0);
+
+        for sample in 0..SUBSAMPLES {
+            let y = (y0 + row as i32) as f32 + (sample as f32 + 0.5) * weight;
+            while next_edge < edges.len() && edges[next_edge].top <= y {
+                active.push(next_edge);
+                next_edge += 1;
+            }
+            active.retain(|&i| edges[i].bottom > y);
+
+            crossings.clear();
+            crossings.extend(active.iter().map(|&i| {
+                let edge = &edges[i];
+                (edge.x + (y - edge.top) * edge.dx_dy, edge.winding)
+            }));
+            crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            let mut winding = 0;
+            let mut span_start = 0.0;
+            for &(x, edge_winding) in &crossings {
+                let was_inside = rule.is_inside(winding);
+                winding += edge_winding;
+                let is_inside = rule.is_inside(winding);
+                if !was_inside && is_inside {
+                    span_start = x;
+                } else if was_inside && !is_inside {
+                    let start = (span_start - x0 as f32).clamp(0.0, width as f32);
+                    let end = (x - x0 as f32).clamp(0.0, width as f32);
+                    if end <= start {
+                        continue;
+                    }
+                    let first = start.floor() as usize;
+                    let last = end.floor() as usize;
+                    if first == last {
+                        partial[first] += (end - start) * weight;
+                    } else {
+                        partial[first] += (first as f32 + 1.0 - start) * weight;
+                        full[first + 1] += weight;
+                        full[last] -= weight;
+                        partial[last] += (end - last as f32) * weight;
+                    }
+                }
+            }
+        }
+
+        let mut running = 0.0;
+        for x in 0..width {
+            running += full[x];
+            data[row * width + x] = (running + partial[x]).clamp(0.0, 1.0);
+        }
+    }
+
+    Coverage {
+        x: x0,
+        y: y0,
+        width: width as u32,
+        height: height as u32,
+        data,
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_rasterize() {
+    fn square(x: f32, y: f32, size: f32) -> Vec<CGPoint> {
+        vec![
+            point(x, y),
+            point(x + size, y),
+            point(x + size, y + size),
+            point(x, y + size),
+        ]
+    }
+
+    // Pixel-aligned shapes have no partially covered pixels.
+    let coverage = rasterize(&[square(1.0, 1.0, 2.0)], FillRule::Winding, (4, 4));
+    assert!(coverage
+        .iter()
+        .eq([((1, 1), 1.0), ((2, 1), 1.0), ((1, 2), 1.0), ((2, 2), 1.0)].into_iter()));
+
+    // A pixel-sized square straddling four pixels covers a quarter of each.
+    let coverage = rasterize(&[square(0.5, 0.5, 1.0)], FillRule::Winding, (4, 4));
+    assert!(coverage.iter().eq([
+        ((0, 0), 0.25),
+        ((1, 0), 0.25),
+        ((0, 1), 0.25),
+        ((1, 1), 0.25)
+    ]
+    .into_iter()));
+
+    // Clipped to the bitmap's bounds.
+    let coverage = rasterize(&[square(-1.0, -1.0, 2.0)], FillRule::Winding, (4, 4));
+    assert!(coverage.iter().eq([((0, 0), 1.0)].into_iter()));
+
+    // Nested squares with the same orientation: the even-odd rule leaves a
+    // hole, the non-zero winding rule doesn't.
+    let nested = [square(0.0, 0.0, 3.0), square(1.0, 1.0, 1.0)];
+    let coverage = rasterize(&nested, FillRule::Winding, (3, 3));
+    assert_eq!(coverage.get((1, 1)), 1.0);
+    let coverage = rasterize(&nested, FillRule::EvenOdd, (3, 3));
+    assert_eq!(coverage.get((1, 1)), 0.0);
+    assert_eq!(coverage.get((0, 1)), 1.0);
+}
+
+fn borrow_path(env: &Environment, path: CGPathRef) -> &Path {
+    &env.objc.borrow::<CGPathHostObject>(path).path
+}
+fn borrow_path_mut(env: &mut Environment, path: CGMutablePathRef) -> &mut Path {
+    &mut env.objc.borrow_mut::<CGPathHostObject>(path).path
+}
+
+fn read_transform(env: &Environment, m: ConstPtr<CGAffineTransform>) -> CGAffineTransform {
+    if m.is_null() {
+        CGAffineTransformIdentity
+    } else {
+        env.mem.read(m)
+    }
+}
+
+/// Shortcut for host code: create a new `CGPath` from a [Path].
+pub fn from_path(env: &mut Environment, path: Path) -> CGMutablePathRef {
+    let host_object = Box::new(CGPathHostObject { path });
+    let class = env.objc.get_known_class("_touchHLE_CGPath", &mut env.mem);
+    env.objc.alloc_object(class, host_object, &mut env.mem)
+}
+
+/// Shortcut for host code: get a copy of the [Path] of a `CGPath`.
+pub fn to_path(env: &Environment, path: CGPathRef) -> Path {
+    borrow_path(env, path).clone()
+}
+
+fn CGPathCreateMutable(env: &mut Environment) -> CGMutablePathRef {
+    from_path(env, Path::default())
+}
+fn CGPathCreateCopy(env: &mut Environment, path: CGPathRef) -> CGPathRef {
+    let path = to_path(env, path);
+    from_path(env, path)
+}
+fn CGPathCreateMutableCopy(env: &mut Environment, path: CGPathRef) -> CGMutablePathRef {
+    let path = to_path(env, path);
+    from_path(env, path)
+}
+
+pub fn CGPathRelease(env: &mut Environment, path: CGPathRef) {
+    if !path.is_null() {
+        CFRelease(env, path);
+    }
+}
+pub fn CGPathRetain(env: &mut Environment, path: CGPathRef) -> CGPathRef {
+    if !path.is_null() {
+        CFRetain(env, path)
+    } else {
+        path
+    }
+}
+
+fn CGPathMoveToPoint(
+    env: &mut Environment,
+    path: CGMutablePathRef,
+    m: ConstPtr<CGAffineTransform>,
+    x: CGFloat,
+    y: CGFloat,
+) {
+    let m = read_transform(env, m);
+    borrow_path_mut(env, path).add_transformed(m, |p| p.move_to(point(x, y)));
+}
+fn CGPathAddLineToPoint(
+    env: &mut Environment,
+    path: CGMutablePathRef,
+    m: ConstPtr<CGAffineTransform>,
+    x: CGFloat,
+    y: CGFloat,
+) {
+    let m = read_transform(env, m);
+    borrow_path_mut(env, path).add_transformed(m, |p| p.line_to(point(x, y)));
+}
+fn CGPathAddLines(
+    env: &mut Environment,
+    path: CGMutablePathRef,
+    m: ConstPtr<CGAffineTransform>,
+    points: ConstPtr<CGPoint>,
+    count: GuestUSize,
+) {
+    let m = read_transform(env, m);
+    let points: Vec<CGPoint> = (0..count).map(|i| env.mem.read(points + i)).collect();
+    borrow_path_mut(env, path).add_transformed(m, |p| {
+        for (i, &point) in points.iter().enumerate() {
+            if i == 0 {
+                p.move_to(point);
+            } else {
+                p.line_to(point);
+            }
+        }
+    });
+}
+fn CGPathAddQuadCurveToPoint(
+    env: &mut Environment,
+    path: CGMutablePathRef,
+    m: ConstPtr<CGAffineTransform>,
+    cpx: CGFloat,
+    cpy: CGFloat,
+    x: CGFloat,
+    y: CGFloat,
+) {
+    let m = read_transform(env, m);
+    borrow_path_mut(env, path)
+        .add_transformed(m, |p| p.quad_curve_to(point(cpx, cpy), point(x, y)));
+}
+#[allow(clippy::too_many_arguments)]
+fn CGPathAddCurveToPoint(
+    env: &mut Environment,
+    path: CGMutablePathRef,
+    m: ConstPtr<CGAffineTransform>,
+    cp1x: CGFloat,
+    cp1y: CGFloat,
+    cp2x: CGFloat,
+    cp2y: CGFloat,
+    x: CGFloat,
+    y: CGFloat,
+) {
+    let m = read_transform(env, m);
+    borrow_path_mut(env, path).add_transformed(m, |p| {
+        p.curve_to(point(cp1x, cp1y), point(cp2x, cp2y), point(x, y))
+    });
+}
+fn CGPathAddRect(
+    env: &mut Environment,
+    path: CGMutablePathRef,
+    m: ConstPtr<CGAffineTransform>,
+    rect: CGRect,
+) {
+    let m = read_transform(env, m);
+    borrow_path_mut(env, path).add_transformed(m, |p| p.add_rect(rect));
+}
+fn CGPathAddRects(
+    env: &mut Environment,
+    path: CGMutablePathRef,
+    m: ConstPtr<CGAffineTransform>,
+    rects: ConstPtr<CGRect>,
+    count: GuestUSize,
+) {
+    let m = read_transform(env, m);
+    let rects: Vec<CGRect> = (0..count).map(|i| env.mem.read(rects + i)).collect();
+    borrow_path_mut(env, path).add_transformed(m, |p| {
+        for &rect in &rects {
+            p.add_rect(rect);
+        }
+    });
+}
+fn CGPathAddEllipseInRect(
+    env: &mut Environment,
+    path: CGMutablePathRef,
+    m: ConstPtr<CGAffineTransform>,
+    rect: CGRect,
+) {
+    let m = read_transform(env, m);
+    borrow_path_mut(env, path).add_transformed(m, |p| p.add_ellipse_in_rect(rect));
+}
+#[allow(clippy::too_many_arguments)]
+fn CGPathAddArc(
+    env: &mut Environment,
+    path: CGMutablePathRef,
+    m: ConstPtr<CGAffineTransform>,
+    x: CGFloat,
+    y: CGFloat,
+    radius: CGFloat,
+    start_angle: CGFloat,
+    end_angle: CGFloat,
+    clockwise: bool,
+) {
+    let m = read_transform(env, m);
+    borrow_path_mut(env, path).add_transformed(m, |p| {
+        p.add_arc(point(x, y), radius, start_angle, end_angle, clockwise)
+    });
+}
+#[allow(clippy::too_many_arguments)]
+fn CGPathAddArcToPoint(
+    env: &mut Environment,
+    path: CGMutablePathRef,
+    m: ConstPtr<CGAffineTransform>,
+    x1: CGFloat,
+    y1: CGFloat,
+    x2: CGFloat,
+    y2: CGFloat,
+    radius: CGFloat,
+) {
+    let m = read_transform(env, m);
+    borrow_path_mut(env, path).add_transformed(m, |p| {
+        p.add_arc_to_point(point(x1, y1), point(x2, y2), radius)
+    });
+}
+fn CGPathCloseSubpath(env: &mut Environment, path: CGMutablePathRef) {
+    borrow_path_mut(env, path).close_subpath();
+}
+fn CGPathAddPath(
+    env: &mut Environment,
+    path1: CGMutablePathRef,
+    m: ConstPtr<CGAffineTransform>,
+    path2: CGPathRef,
+) {
+    let m = read_transform(env, m);
+    let other = to_path(env, path2);
+    borrow_path_mut(env, path1).add_path(&other, m);
+}
+
+fn CGPathIsEmpty(env: &mut Environment, path: CGPathRef) -> bool {
+    path.is_null() || borrow_path(env, path).is_empty()
+}
+fn CGPathEqualToPath(env: &mut Environment, path1: CGPathRef, path2: CGPathRef) -> bool {
+    path1 == path2 || borrow_path(env, path1) == borrow_path(env, path2)
+}
+fn CGPathGetCurrentPoint(env: &mut Environment, path: CGPathRef) -> CGPoint {
+    borrow_path(env, path)
+        .current_point()
+        .unwrap_or(point(0.0, 0.0))
+}
+fn CGPathGetBoundingBox(env: &mut Environment, path: CGPathRef) -> CGRect {
+    borrow_path(env, path).bounding_box()
+}
+fn CGPathContainsPoint(
+    env: &mut Environment,
+    path: CGPathRef,
+    m: ConstPtr<CGAffineTransform>,
+    point: CGPoint,
+    eo_fill: bool,
+) -> bool {
+    let m = read_transform(env, m);
+    let polygons: Vec<_> = borrow_path(env, path)
+        .transform(m)
+        .flatten(0.1)
+        .into_iter()
+        .map(|polyline| polyline.points)
+        .collect();
+    let rule = if eo_fill {
+        FillRule::EvenOdd
+    } else {
+        FillRule::Winding
+    };
+    contains_point(&polygons, point, rule)
+}
+
+pub const FUNCTIONS: FunctionExports = &[
+    export_c_func!(CGPathCreateMutable()),
+    export_c_func!(CGPathCreateCopy(_)),
+    export_c_func!(CGPathCreateMutableCopy(_)),
+    export_c_func!(CGPathRelease(_)),
+    export_c_func!(CGPathRetain(_)),
+    export_c_func!(CGPathMoveToPoint(_, _, _, _)),
+    export_c_func!(CGPathAddLineToPoint(_, _, _, _)),
+    export_c_func!(CGPathAddLines(_, _, _, _)),
+    export_c_func!(CGPathAddQuadCurveToPoint(_, _, _, _, _, _)),
+    export_c_func!(CGPathAddCurveToPoint(_, _, _, _, _, _, _, _)),
+    export_c_func!(CGPathAddRect(_, _, _)),
+    export_c_func!(CGPathAddRects(_, _, _, _)),
+    export_c_func!(CGPathAddEllipseInRect(_, _, _)),
+    export_c_func!(CGPathAddArc(_, _, _, _, _, _, _, _)),
+    export_c_func!(CGPathAddArcToPoint(_, _, _, _, _, _, _)),
+    export_c_func!(CGPathCloseSubpath(_)),
+    export_c_func!(CGPathAddPath(_, _, _)),
+    export_c_func!(CGPathIsEmpty(_)),
+    export_c_func!(CGPathEqualToPath(_, _)),
+    export_c_func!(CGPathGetCurrentPoint(_)),
+    export_c_func!(CGPathGetBoundingBox(_)),
+    export_c_func!(CGPathContainsPoint(_, _, _, _)),
+];
@@ -0,0 +1,254 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! `CGPattern.h`
+
+use super::cg_affine_transform::CGAffineTransform;
+use super::cg_bitmap_context::{self, CGBitmapContextCreate};
+use super::cg_color_space::{CGColorSpaceCreateDeviceRGB, CGColorSpaceRelease};
+use super::cg_context::{
+    CGContextRelease, CGContextScaleCTM, CGContextSetRGBFillColor, CGContextTranslateCTM,
+};
+use super::cg_image::kCGImageAlphaPremultipliedLast;
+use super::{CGFloat, CGPoint, CGRect};
+use crate::abi::{CallFromHost, GuestFunction};
+use crate::dyld::{export_c_func, FunctionExports};
+use crate::frameworks::core_foundation::{CFRelease, CFRetain, CFTypeRef};
+use crate::image::{gamma_decode, Image};
+use crate::mem::{ConstPtr, GuestUSize, MutVoidPtr, SafeRead};
+use crate::objc::{objc_classes, ClassExports, HostObject};
+use crate::Environment;
+
+pub const CLASSES: ClassExports = objc_classes! {
+
+(env, this, _cmd);
+
+// CGPattern seems to be a CFType-based type, but in our implementation those
+// are just Objective-C types, so we need a class for it, but its name is not
+// visible anywhere.
+@implementation _touchHLE_CGPattern: NSObject
+
+- (())dealloc {
+    let &CGPatternHostObject {
+        info,
+        release_info,
+        ..
+    } = env.objc.borrow(this);
+    if release_info.addr_with_thumb_bit() != 0 {
+        () = release_info.call_from_host(env, (info,));
+    }
+    env.objc.dealloc_object(this, &mut env.mem)
+}
+
+@end
+
+};
+
+/// `(*void)(void *info, CGContextRef context)`
+type CGPatternDrawPatternCallback = GuestFunction;
+/// `(*void)(void *info)`
+type CGPatternReleaseInfoCallback = GuestFunction;
+
+#[repr(C, packed)]
+pub struct CGPatternCallbacks {
+    version: u32,
+    draw_pattern: CGPatternDrawPatternCallback,
+    release_info: CGPatternReleaseInfoCallback,
+}
+unsafe impl SafeRead for CGPatternCallbacks {}
+
+pub type CGPatternTiling = i32;
+
+struct CGPatternHostObject {
+    /// User-provided pointer passed to the callbacks.
+    info: MutVoidPtr,
+    /// Bounds of the pattern cell, in pattern space.
+    bounds: CGRect,
+    /// Transform from pattern space to the context's default user space.
+    matrix: CGAffineTransform,
+    x_step: CGFloat,
+    y_step: CGFloat,
+    is_colored: bool,
+    draw_pattern: CGPatternDrawPatternCallback,
+    release_info: CGPatternReleaseInfoCallback,
+}
+impl HostObject for CGPatternHostObject {}
+
+pub type CGPatternRef = CFTypeRef;
+
+fn CGPatternCreate(
+    env: &mut Environment,
+    info: MutVoidPtr,
+    bounds: CGRect,
+    matrix: CGAffineTransform,
+    x_step: CGFloat,
+    y_step: CGFloat,
+    _tiling: CGPatternTiling, // TODO: tiling modes other than no distortion
+    is_colored: bool,
+    callbacks: ConstPtr<CGPatternCallbacks>,
+) -> CGPatternRef {
+    let CGPatternCallbacks {
+        version,
+        draw_pattern,
+        release_info,
+    } = env.mem.read(callbacks);
+    assert!(version == 0);
+
+    let host_object = CGPatternHostObject {
+        info,
+        bounds,
+        matrix,
+        x_step,
+        y_step,
+        is_colored,
+        draw_pattern,
+        release_info,
+    };
+    let isa = env
+        .objc
+        .get_known_class("_touchHLE_CGPattern", &mut env.mem);
+    env.objc
+        .alloc_object(isa, Box::new(host_object), &mut env.mem)
+}
+
+pub fn CGPatternRelease(env: &mut Environment, pattern: CGPatternRef) {
+    if !pattern.is_null() {
+        CFRelease(env, pattern);
+    }
+}
+pub fn CGPatternRetain(env: &mut Environment, pattern: CGPatternRef) -> CGPatternRef {
+    if !pattern.is_null() {
+        CFRetain(env, pattern)
+    } else {
+        pattern
+    }
+}
+
+/// Largest width or height, in pixels, that a pattern cell is rendered at.
+const MAX_TILE_SIZE: CGFloat = 1024.0;
+
+/// A pattern cell rendered to an image, ready to be painted with.
+pub(super) struct PatternTile {
+    image: Image,
+    /// Transform from device space to pattern space.
+    to_pattern: CGAffineTransform,
+    bounds: CGRect,
+    x_step: CGFloat,
+    y_step: CGFloat,
+    /// For uncolored patterns, the color (sRGB, not premultiplied) the cell's
+    /// shape is painted with.
+    tint: Option<(CGFloat, CGFloat, CGFloat, CGFloat)>,
+}
+impl PatternTile {
+    /// Get the color of the pattern at a point in device space, as linear RGB
+    /// with premultiplied alpha.
+    pub(super) fn color_at(&self, point: CGPoint) -> (CGFloat, CGFloat, CGFloat, CGFloat) {
+        let CGRect { origin, size } = self.bounds;
+        let point = self.to_pattern.apply_to_point(point);
+        // Position within the cell, which repeats every step.
+        let x = (point.x - origin.x).rem_euclid(self.x_step);
+        let y = (point.y - origin.y).rem_euclid(self.y_step);
+        if x >= size.width || y >= size.height {
+            return (0.0, 0.0, 0.0, 0.0);
+        }
+        let (width, height) = self.image.dimensions();
+        let texel_x = (x / size.width * width as CGFloat) as i32;
+        // Image is in top-to-bottom order, but pattern space is bottom-to-top
+        let texel_y = ((1.0 - y / size.height) * height as CGFloat) as i32;
+        let texel_y = texel_y.min(height as i32 - 1);
+        let Some((r, g, b, a)) = self.image.get_pixel((texel_x, texel_y)) else {
+            return (0.0, 0.0, 0.0, 0.0);
+        };
+        match self.tint {
+            None => (r, g, b, a),
+            Some((tint_r, tint_g, tint_b, tint_a)) => {
+                // Only the shape of an uncolored pattern's cell matters.
+                let alpha = a * tint_a;
+                (
+                    gamma_decode(tint_r) * alpha,
+                    gamma_decode(tint_g) * alpha,
+                    gamma_decode(tint_b) * alpha,
+                    alpha,
+                )
+            }
+        }
+    }
+}
+
+/// Render a pattern's cell by calling its drawing callback, which is app code.
+/// `tint` is used for uncolored patterns and ignored for colored ones.
+pub(super) fn render_tile(
+    env: &mut Environment,
+    pattern: CGPatternRef,
+    tint: (CGFloat, CGFloat, CGFloat, CGFloat),
+) -> PatternTile {
+    let &CGPatternHostObject {
+        info,
+        bounds,
+        matrix,
+        x_step,
+        y_step,
+        is_colored,
+        draw_pattern,
+        ..
+    } = env.objc.borrow(pattern);
+
+    // Render the cell at roughly the resolution it will be painted at.
+    let scale = (matrix.a * matrix.d - matrix.b * matrix.c).abs().sqrt();
+    let tile_size = |size: CGFloat| (size * scale).ceil().clamp(1.0, MAX_TILE_SIZE);
+    let (width, height) = (tile_size(bounds.size.width), tile_size(bounds.size.height));
+
+    let color_space = CGColorSpaceCreateDeviceRGB(env);
+    let context = CGBitmapContextCreate(
+        env,
+        MutVoidPtr::null(),
+        width as GuestUSize,
+        height as GuestUSize,
+        8,
+        width as GuestUSize * 4,
+        color_space,
+        kCGImageAlphaPremultipliedLast,
+    );
+    CGColorSpaceRelease(env, color_space);
+    if bounds.size.width > 0.0 && bounds.size.height > 0.0 {
+        CGContextScaleCTM(
+            env,
+            context,
+            width / bounds.size.width,
+            height / bounds.size.height,
+        );
+        CGContextTranslateCTM(env, context, -bounds.origin.x, -bounds.origin.y);
+        // Uncolored patterns usually don't set a color for their shapes, so
+        // they need the real default of opaque black.
+        CGContextSetRGBFillColor(env, context, 0.0, 0.0, 0.0, 1.0);
+        () = draw_pattern.call_from_host(env, (info, context));
+    }
+    let image = cg_bitmap_context::to_image(env, context);
+    CGContextRelease(env, context);
+
+    // A step of zero isn't meaningful, so use the cell size instead.
+    let step = |step: CGFloat, size: CGFloat| {
+        let step = step.abs();
+        if step > 0.0 {
+            step
+        } else {
+            size.max(f32::EPSILON)
+        }
+    };
+    PatternTile {
+        image,
+        to_pattern: matrix.invert(),
+        bounds,
+        x_step: step(x_step, bounds.size.width),
+        y_step: step(y_step, bounds.size.height),
+        tint: (!is_colored).then_some(tint),
+    }
+}
+
+pub const FUNCTIONS: FunctionExports = &[
+    export_c_func!(CGPatternCreate(_, _, _, _, _, _, _, _)),
+    export_c_func!(CGPatternRetain(_)),
+    export_c_func!(CGPatternRelease(_)),
+];
@@ -0,0 +1,298 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! `CGPDFContext.h`
+//!
+//! PDF contexts don't record drawing commands. Instead, each page is drawn
+//! into a bitmap like with `CGBitmapContext`, and the pages are written out as
+//! images when the context is closed. This loses the vector data, but apps
+//! mostly use this to produce documents to share, where that doesn't matter.
+
+use super::cg_affine_transform::CGAffineTransformIdentity;
+use super::cg_bitmap_context::{self, CGBitmapContextData};
+use super::cg_context::{CGContextHostObject, CGContextRef, CGContextState, CGContextSubclass};
+use super::cg_data_consumer::{self, CGDataConsumerRef};
+use super::cg_path::Path;
+use super::pdf::{self, PageImage};
+use super::{CGFloat, CGPoint, CGRect, CGSize};
+use crate::dyld::{export_c_func, ConstantExports, FunctionExports, HostConstant};
+use crate::frameworks::core_foundation::cf_dictionary::CFDictionaryRef;
+use crate::frameworks::core_foundation::cf_url::CFURLRef;
+use crate::frameworks::core_foundation::{CFRelease, CFRetain};
+use crate::frameworks::foundation::{ns_string, NSUInteger};
+use crate::mem::{ConstPtr, ConstVoidPtr, GuestUSize};
+use crate::objc::{id, msg, nil, ObjC};
+use crate::Environment;
+
+/// Pages are rasterized at this many pixels per point, so they still look
+/// good when zoomed in a bit.
+const PIXELS_PER_POINT: CGFloat = 2.0;
+
+/// The media box used if none is given: US Letter size, like in real Core
+/// Graphics.
+const DEFAULT_MEDIA_BOX: CGRect = CGRect {
+    origin: CGPoint { x: 0.0, y: 0.0 },
+    size: CGSize {
+        width: 612.0,
+        height: 792.0,
+    },
+};
+
+pub const kCGPDFContextMediaBox: &str = "kCGPDFContextMediaBox";
+
+pub(super) struct CGPDFContextData {
+    /// Bitmap the current page is drawn into.
+    pub(super) page: CGBitmapContextData,
+    consumer: CGDataConsumerRef,
+    default_media_box: CGRect,
+    /// Media box of the current page, if a page has been begun.
+    current_page: Option<CGRect>,
+    /// Pages that have been ended, to be written out when the context is
+    /// closed.
+    pages: Vec<PageImage>,
+    closed: bool,
+}
+
+fn borrow_pdf_data(objc: &mut ObjC, context: CGContextRef) -> Option<&mut CGPDFContextData> {
+    match objc.borrow_mut::<CGContextHostObject>(context).subclass {
+        CGContextSubclass::CGPDFContext(ref mut pdf_data) => Some(pdf_data),
+        _ => None,
+    }
+}
+
+fn page_pixel_size(media_box: CGRect) -> (GuestUSize, GuestUSize) {
+    let to_pixels = |points: CGFloat| (points * PIXELS_PER_POINT).ceil().max(1.0) as GuestUSize;
+    (
+        to_pixels(media_box.size.width),
+        to_pixels(media_box.size.height),
+    )
+}
+
+pub fn CGPDFContextCreate(
+    env: &mut Environment,
+    consumer: CGDataConsumerRef,
+    media_box: ConstPtr<CGRect>,
+    auxiliary_info: CFDictionaryRef,
+) -> CGContextRef {
+    let media_box = (!media_box.is_null()).then(|| env.mem.read(media_box));
+    create(env, consumer, media_box, auxiliary_info)
+}
+
+/// Shared implementation of [CGPDFContextCreate] for host code. If
+/// `media_box` is [None], US Letter size is used.
+pub fn create(
+    env: &mut Environment,
+    consumer: CGDataConsumerRef,
+    media_box: Option<CGRect>,
+    _auxiliary_info: CFDictionaryRef, // TODO: document info
+) -> CGContextRef {
+    if consumer.is_null() {
+        log!("CGPDFContextCreate: no consumer, returning NULL");
+        return nil;
+    }
+    let media_box = media_box.unwrap_or(DEFAULT_MEDIA_BOX);
+    if !(media_box.size.width > 0.0 && media_box.size.height > 0.0) {
+        log!(
+            "CGPDFContextCreate: empty media box {:?}, returning NULL",
+            media_box
+        );
+        return nil;
+    }
+
+    let consumer = CFRetain(env, consumer);
+    let (width, height) = page_pixel_size(media_box);
+    let page = CGBitmapContextData::alloc_rgba(&mut env.mem, width, height);
+    let host_object = CGContextHostObject {
+        subclass: CGContextSubclass::CGPDFContext(CGPDFContextData {
+            page,
+            consumer,
+            default_media_box: media_box,
+            current_page: None,
+            pages: Vec::new(),
+            closed: false,
+        }),
+        state: CGContextState::default(),
+        state_stack: Vec::new(),
+        path: Path::default(),
+        text_matrix: CGAffineTransformIdentity,
+    };
+    let isa = env
+        .objc
+        .get_known_class("_touchHLE_CGContext", &mut env.mem);
+    env.objc
+        .alloc_object(isa, Box::new(host_object), &mut env.mem)
+}
+
+fn CGPDFContextCreateWithURL(
+    env: &mut Environment,
+    url: CFURLRef,
+    media_box: ConstPtr<CGRect>,
+    auxiliary_info: CFDictionaryRef,
+) -> CGContextRef {
+    let consumer = cg_data_consumer::CGDataConsumerCreateWithURL(env, url);
+    if consumer.is_null() {
+        return nil;
+    }
+    let context = CGPDFContextCreate(env, consumer, media_box, auxiliary_info);
+    CFRelease(env, consumer);
+    context
+}
+
+/// Start a new page. If `media_box` is [None], the context's default is used.
+/// Returns the media box of the new page, or [None] if no page was begun,
+/// e.g. because this isn't a PDF context.
+pub fn begin_page(
+    env: &mut Environment,
+    context: CGContextRef,
+    media_box: Option<CGRect>,
+) -> Option<CGRect> {
+    let pdf_data = borrow_pdf_data(&mut env.objc, context)?;
+    if pdf_data.closed {
+        log!("Warning: can't begin a page in a closed PDF context");
+        return None;
+    }
+    if pdf_data.current_page.is_some() {
+        log!("Warning: PDF page begun without ending the previous one");
+        end_page(env, context);
+    }
+
+    let pdf_data = borrow_pdf_data(&mut env.objc, context).unwrap();
+    let media_box = media_box.unwrap_or(pdf_data.default_media_box);
+    pdf_data.current_page = Some(media_box);
+    let (width, height) = page_pixel_size(media_box);
+    if cg_bitmap_context::dimensions(&pdf_data.page) == (width, height) {
+        let size = width * height * 4;
+        env.mem
+            .bytes_at_mut(pdf_data.page.data.cast(), size)
+            .fill(0);
+    } else {
+        env.mem.free(pdf_data.page.data);
+        pdf_data.page = CGBitmapContextData::alloc_rgba(&mut env.mem, width, height);
+    }
+
+    // Each page starts with a fresh graphics state, with user space in points
+    // relative to the media box.
+    let host_obj = env.objc.borrow_mut::<CGContextHostObject>(context);
+    host_obj.state = CGContextState::default();
+    host_obj.state.transform = CGAffineTransformIdentity
+        .scale(PIXELS_PER_POINT, PIXELS_PER_POINT)
+        .translate(-media_box.origin.x, -media_box.origin.y);
+    host_obj.state_stack.clear();
+    host_obj.path = Path::default();
+    host_obj.text_matrix = CGAffineTransformIdentity;
+
+    Some(media_box)
+}
+
+/// Finish the current page. Does nothing for contexts that aren't PDF
+/// contexts.
+pub(super) fn end_page(env: &mut Environment, context: CGContextRef) {
+    let Some(pdf_data) = borrow_pdf_data(&mut env.objc, context) else {
+        return;
+    };
+    let Some(media_box) = pdf_data.current_page.take() else {
+        log!("Warning: PDF page ended without being begun");
+        return;
+    };
+
+    let image = cg_bitmap_context::to_image(env, context);
+    let (width, height) = image.dimensions();
+    // The page is on white paper. The pixels have premultiplied alpha, so
+    // compositing is an addition.
+    let rgb = image
+        .pixels()
+        .chunks_exact(4)
+        .flat_map(|pixel| {
+            let paper = 255 - pixel[3];
+            [0, 1, 2].map(|i| pixel[i].saturating_add(paper))
+        })
+        .collect();
+    let CGRect {
+        origin: CGPoint { x, y },
+        size: CGSize {
+            width: box_width,
+            height: box_height,
+        },
+    } = media_box;
+    borrow_pdf_data(&mut env.objc, context)
+        .unwrap()
+        .pages
+        .push(PageImage {
+            media_box: [x, y, x + box_width, y + box_height],
+            width,
+            height,
+            rgb,
+        });
+}
+
+fn CGPDFContextBeginPage(env: &mut Environment, context: CGContextRef, page_info: CFDictionaryRef) {
+    let media_box = page_info_media_box(env, page_info);
+    begin_page(env, context, media_box);
+}
+
+/// Get the `kCGPDFContextMediaBox` from a page info dictionary, if it has
+/// one. It is a `CFData` containing a `CGRect`.
+pub fn page_info_media_box(env: &mut Environment, page_info: CFDictionaryRef) -> Option<CGRect> {
+    if page_info.is_null() {
+        return None;
+    }
+    let key = ns_string::get_static_str(env, kCGPDFContextMediaBox);
+    let data: id = msg![env; page_info objectForKey:key];
+    if data == nil {
+        return None;
+    }
+    let length: NSUInteger = msg![env; data length];
+    if (length as usize) < std::mem::size_of::<CGRect>() {
+        return None;
+    }
+    let bytes: ConstVoidPtr = msg![env; data bytes];
+    Some(env.mem.read(bytes.cast()))
+}
+
+fn CGPDFContextEndPage(env: &mut Environment, context: CGContextRef) {
+    end_page(env, context);
+}
+
+/// Write out the document. Drawing after this does nothing.
+pub fn CGPDFContextClose(env: &mut Environment, context: CGContextRef) {
+    let Some(pdf_data) = borrow_pdf_data(&mut env.objc, context) else {
+        return;
+    };
+    if pdf_data.closed {
+        return;
+    }
+    if pdf_data.current_page.is_some() {
+        end_page(env, context);
+    }
+
+    let pdf_data = borrow_pdf_data(&mut env.objc, context).unwrap();
+    pdf_data.closed = true;
+    let pages = std::mem::take(&mut pdf_data.pages);
+    let consumer = pdf_data.consumer;
+    log_dbg!("Writing PDF with {} pages", pages.len());
+    let bytes = pdf::write_document(&pages);
+    cg_data_consumer::write_bytes(env, consumer, &bytes);
+}
+
+/// For use by `CGContext`'s dealloc: the document is written out if the app
+/// didn't close the context itself.
+pub(super) fn finalize(env: &mut Environment, context: CGContextRef) {
+    CGPDFContextClose(env, context);
+    let consumer = borrow_pdf_data(&mut env.objc, context).unwrap().consumer;
+    CFRelease(env, consumer);
+}
+
+pub const CONSTANTS: ConstantExports = &[(
+    "_kCGPDFContextMediaBox",
+    HostConstant::NSString(kCGPDFContextMediaBox),
+)];
+
+pub const FUNCTIONS: FunctionExports = &[
+    export_c_func!(CGPDFContextCreate(_, _, _)),
+    export_c_func!(CGPDFContextCreateWithURL(_, _, _)),
+    export_c_func!(CGPDFContextBeginPage(_, _)),
+    export_c_func!(CGPDFContextEndPage(_)),
+    export_c_func!(CGPDFContextClose(_)),
+];
@@ -0,0 +1,200 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! `CGPDFDocument.h`
+
+use super::cg_data_provider::{self, CGDataProviderRef};
+use super::cg_pdf_page::{self, CGPDFPageRef};
+use super::pdf::Document;
+use crate::dyld::{export_c_func, FunctionExports};
+use crate::frameworks::core_foundation::cf_url::CFURLRef;
+use crate::frameworks::core_foundation::{CFRelease, CFRetain, CFTypeRef};
+use crate::frameworks::foundation::ns_url;
+use crate::mem::{GuestUSize, MutPtr};
+use crate::objc::{nil, objc_classes, ClassExports, HostObject};
+use crate::Environment;
+use std::rc::Rc;
+
+pub const CLASSES: ClassExports = objc_classes! {
+
+(env, this, _cmd);
+
+// CGPDFDocument is a CFType-based type, but in our implementation those are
+// just Objective-C types, so we need a class for it, but its name is not
+// visible anywhere.
+@implementation _touchHLE_CGPDFDocument: NSObject
+
+- (())dealloc {
+    let pages = std::mem::take(&mut env.objc.borrow_mut::<CGPDFDocumentHostObject>(this).pages);
+    for page in pages {
+        CFRelease(env, page);
+    }
+    env.objc.dealloc_object(this, &mut env.mem)
+}
+
+@end
+
+};
+
+struct CGPDFDocumentHostObject {
+    document: Rc<Document>,
+    /// The document owns its pages, which are created along with it.
+    pages: Vec<CGPDFPageRef>,
+}
+impl HostObject for CGPDFDocumentHostObject {}
+
+pub type CGPDFDocumentRef = CFTypeRef;
+
+pub fn CGPDFDocumentRelease(env: &mut Environment, document: CGPDFDocumentRef) {
+    if !document.is_null() {
+        CFRelease(env, document);
+    }
+}
+pub fn CGPDFDocumentRetain(env: &mut Environment, document: CGPDFDocumentRef) -> CGPDFDocumentRef {
+    if !document.is_null() {
+        CFRetain(env, document)
+    } else {
+        document
+    }
+}
+
+fn create(env: &mut Environment, function_name: &str, bytes: Vec<u8>) -> CGPDFDocumentRef {
+    let document = match Document::parse(bytes) {
+        Ok(document) => Rc::new(document),
+        Err(e) => {
+            log!(
+                "{}: couldn't parse PDF ({}), returning NULL",
+                function_name,
+                e
+            );
+            return nil;
+        }
+    };
+    let pages = document.pages();
+    log_dbg!("{}: loaded PDF with {} pages", function_name, pages.len());
+
+    let class = env
+        .objc
+        .get_known_class("_touchHLE_CGPDFDocument", &mut env.mem);
+    let host_object = CGPDFDocumentHostObject {
+        document: document.clone(),
+        pages: Vec::new(),
+    };
+    let this = env
+        .objc
+        .alloc_object(class, Box::new(host_object), &mut env.mem);
+
+    let pages = pages
+        .into_iter()
+        .enumerate()
+        .map(|(i, page)| cg_pdf_page::create(env, this, document.clone(), page, i + 1))
+        .collect();
+    env.objc.borrow_mut::<CGPDFDocumentHostObject>(this).pages = pages;
+    this
+}
+
+fn CGPDFDocumentCreateWithURL(env: &mut Environment, url: CFURLRef) -> CGPDFDocumentRef {
+    if url.is_null() {
+        return nil;
+    }
+    let path = ns_url::to_rust_path(env, url);
+    let Ok(bytes) = env.fs.read(&path) else {
+        log!(
+            "CGPDFDocumentCreateWithURL: couldn't read {:?}, returning NULL",
+            path
+        );
+        return nil;
+    };
+    create(env, "CGPDFDocumentCreateWithURL", bytes)
+}
+
+fn CGPDFDocumentCreateWithProvider(
+    env: &mut Environment,
+    provider: CGDataProviderRef,
+) -> CGPDFDocumentRef {
+    if provider.is_null() {
+        return nil;
+    }
+    let bytes = cg_data_provider::borrow_bytes(env, provider).to_vec();
+    create(env, "CGPDFDocumentCreateWithProvider", bytes)
+}
+
+fn CGPDFDocumentGetNumberOfPages(env: &mut Environment, document: CGPDFDocumentRef) -> GuestUSize {
+    if document.is_null() {
+        return 0;
+    }
+    let host_obj = env.objc.borrow::<CGPDFDocumentHostObject>(document);
+    host_obj.pages.len().try_into().unwrap()
+}
+
+fn CGPDFDocumentGetPage(
+    env: &mut Environment,
+    document: CGPDFDocumentRef,
+    page_number: GuestUSize,
+) -> CGPDFPageRef {
+    if document.is_null() {
+        return nil;
+    }
+    let host_obj = env.objc.borrow::<CGPDFDocumentHostObject>(document);
+    // Page numbers start at 1.
+    let page = (page_number as usize)
+        .checked_sub(1)
+        .and_then(|index| host_obj.pages.get(index));
+    match page {
+        Some(&page) => page,
+        None => {
+            log_dbg!(
+                "CGPDFDocumentGetPage: no page {} in {:?}, returning NULL",
+                page_number,
+                document
+            );
+            nil
+        }
+    }
+}
+
+fn CGPDFDocumentGetVersion(
+    env: &mut Environment,
+    document: CGPDFDocumentRef,
+    major_version: MutPtr<i32>,
+    minor_version: MutPtr<i32>,
+) {
+    let (major, minor) = env
+        .objc
+        .borrow::<CGPDFDocumentHostObject>(document)
+        .document
+        .version();
+    env.mem.write(major_version, major);
+    env.mem.write(minor_version, minor);
+}
+
+// Encrypted documents can't be opened at all, so every document that exists
+// is unencrypted and allows everything.
+fn CGPDFDocumentIsEncrypted(_env: &mut Environment, _document: CGPDFDocumentRef) -> bool {
+    false
+}
+fn CGPDFDocumentIsUnlocked(_env: &mut Environment, _document: CGPDFDocumentRef) -> bool {
+    true
+}
+fn CGPDFDocumentAllowsPrinting(_env: &mut Environment, _document: CGPDFDocumentRef) -> bool {
+    true
+}
+fn CGPDFDocumentAllowsCopying(_env: &mut Environment, _document: CGPDFDocumentRef) -> bool {
+    true
+}
+
+pub const FUNCTIONS: FunctionExports = &[
+    export_c_func!(CGPDFDocumentRetain(_)),
+    export_c_func!(CGPDFDocumentRelease(_)),
+    export_c_func!(CGPDFDocumentCreateWithURL(_)),
+    export_c_func!(CGPDFDocumentCreateWithProvider(_)),
+    export_c_func!(CGPDFDocumentGetNumberOfPages(_)),
+    export_c_func!(CGPDFDocumentGetPage(_, _)),
+    export_c_func!(CGPDFDocumentGetVersion(_, _, _)),
+    export_c_func!(CGPDFDocumentIsEncrypted(_)),
+    export_c_func!(CGPDFDocumentIsUnlocked(_)),
+    export_c_func!(CGPDFDocumentAllowsPrinting(_)),
+    export_c_func!(CGPDFDocumentAllowsCopying(_)),
+];
@@ -0,0 +1,1690 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! `CGPDFPage.h`, and drawing PDF pages into a context.
+//!
+//! Pages are drawn by interpreting their content streams with the same
+//! primitives that the `CGContext` functions use. Paths, colors, images,
+//! form XObjects and text with TrueType/OpenType or standard fonts are
+//! supported. Shadings, patterns, soft masks and Type 1/Type 3 font programs
+//! are not.
+
+use super::cg_affine_transform::{CGAffineTransform, CGAffineTransformIdentity};
+use super::cg_bitmap_context::{self, GlyphRun};
+use super::cg_context::{
+    kCGBlendModeColor, kCGBlendModeColorBurn, kCGBlendModeColorDodge, kCGBlendModeDarken,
+    kCGBlendModeDifference, kCGBlendModeExclusion, kCGBlendModeHardLight, kCGBlendModeHue,
+    kCGBlendModeLighten, kCGBlendModeLuminosity, kCGBlendModeMultiply, kCGBlendModeNormal,
+    kCGBlendModeOverlay, kCGBlendModeSaturation, kCGBlendModeScreen, kCGBlendModeSoftLight,
+    kCGTextClip, kCGTextFill, kCGTextInvisible, CGBlendMode, CGContextConcatCTM,
+    CGContextHostObject, CGContextRef, CGContextRestoreGState, CGContextSaveGState, CGContextState,
+};
+use super::cg_font::{self, kCGEncodingMacRoman};
+use super::cg_image::{self, kCGImageAlphaNone, kCGImageAlphaOnly};
+use super::cg_path::{FillRule, Path, StrokeStyle};
+use super::cg_pdf_document::CGPDFDocumentRef;
+use super::pdf::{self, Dict, Document, Object, Page, Stream};
+use super::pixel_format::{ColorModel, PixelFormat};
+use super::{CGFloat, CGPoint, CGRect, CGSize};
+use crate::dyld::{export_c_func, FunctionExports};
+use crate::font::Font;
+use crate::frameworks::core_foundation::{CFRelease, CFRetain, CFTypeRef};
+use crate::image::Image;
+use crate::mem::GuestUSize;
+use crate::objc::{objc_classes, ClassExports, HostObject};
+use crate::Environment;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use yore::code_pages::CP1252;
+
+pub const CLASSES: ClassExports = objc_classes! {
+
+(env, this, _cmd);
+
+// CGPDFPage is a CFType-based type, but in our implementation those are just
+// Objective-C types, so we need a class for it, but its name is not visible
+// anywhere.
+@implementation _touchHLE_CGPDFPage: NSObject
+@end
+
+};
+
+struct CGPDFPageHostObject {
+    document: Rc<Document>,
+    page: Rc<Page>,
+    /// The `CGPDFDocument` this page belongs to. It is not retained, since
+    /// the document owns its pages.
+    document_ref: CGPDFDocumentRef,
+    /// Page number, starting at 1.
+    number: GuestUSize,
+}
+impl HostObject for CGPDFPageHostObject {}
+
+pub type CGPDFPageRef = CFTypeRef;
+
+pub type CGPDFBox = i32;
+pub const kCGPDFMediaBox: CGPDFBox = 0;
+pub const kCGPDFCropBox: CGPDFBox = 1;
+pub const kCGPDFBleedBox: CGPDFBox = 2;
+pub const kCGPDFTrimBox: CGPDFBox = 3;
+pub const kCGPDFArtBox: CGPDFBox = 4;
+
+/// For use by `CGPDFDocument`.
+pub(super) fn create(
+    env: &mut Environment,
+    document_ref: CGPDFDocumentRef,
+    document: Rc<Document>,
+    page: Page,
+    number: usize,
+) -> CGPDFPageRef {
+    let host_object = CGPDFPageHostObject {
+        document,
+        page: Rc::new(page),
+        document_ref,
+        number: number.try_into().unwrap(),
+    };
+    let class = env
+        .objc
+        .get_known_class("_touchHLE_CGPDFPage", &mut env.mem);
+    env.objc
+        .alloc_object(class, Box::new(host_object), &mut env.mem)
+}
+
+pub fn CGPDFPageRelease(env: &mut Environment, page: CGPDFPageRef) {
+    if !page.is_null() {
+        CFRelease(env, page);
+    }
+}
+pub fn CGPDFPageRetain(env: &mut Environment, page: CGPDFPageRef) -> CGPDFPageRef {
+    if !page.is_null() {
+        CFRetain(env, page)
+    } else {
+        page
+    }
+}
+
+fn CGPDFPageGetDocument(env: &mut Environment, page: CGPDFPageRef) -> CGPDFDocumentRef {
+    env.objc.borrow::<CGPDFPageHostObject>(page).document_ref
+}
+
+fn CGPDFPageGetPageNumber(env: &mut Environment, page: CGPDFPageRef) -> GuestUSize {
+    env.objc.borrow::<CGPDFPageHostObject>(page).number
+}
+
+fn CGPDFPageGetRotationAngle(env: &mut Environment, page: CGPDFPageRef) -> i32 {
+    env.objc.borrow::<CGPDFPageHostObject>(page).page.rotate
+}
+
+fn to_rect([x0, y0, x1, y1]: [f32; 4]) -> CGRect {
+    CGRect {
+        origin: CGPoint { x: x0, y: y0 },
+        size: CGSize {
+            width: x1 - x0,
+            height: y1 - y0,
+        },
+    }
+}
+
+/// Intersection of two rects with non-negative sizes. If they don't overlap,
+/// the result is empty.
+fn intersect(a: CGRect, b: CGRect) -> CGRect {
+    let x0 = a.origin.x.max(b.origin.x);
+    let y0 = a.origin.y.max(b.origin.y);
+    let x1 = (a.origin.x + a.size.width).min(b.origin.x + b.size.width);
+    let y1 = (a.origin.y + a.size.height).min(b.origin.y + b.size.height);
+    to_rect([x0, y0, x1.max(x0), y1.max(y0)])
+}
+
+/// Get one of a page's boxes. Like in real Core Graphics, the crop box is
+/// limited to the media box, and the other boxes to the crop box, which they
+/// also default to.
+fn box_rect(document: &Document, page: &Page, box_type: CGPDFBox) -> CGRect {
+    let media_box = to_rect(page.media_box);
+    let crop_box = page.crop_box.map_or(media_box, |crop_box| {
+        intersect(to_rect(crop_box), media_box)
+    });
+    let key = match box_type {
+        kCGPDFMediaBox => return media_box,
+        kCGPDFCropBox => return crop_box,
+        kCGPDFBleedBox => "BleedBox",
+        kCGPDFTrimBox => "TrimBox",
+        kCGPDFArtBox => "ArtBox",
+        _ => {
+            log!("Warning: unknown CGPDFBox {}, using media box", box_type);
+            return media_box;
+        }
+    };
+    document
+        .get_rect(&page.dict, key)
+        .map_or(crop_box, |rect| intersect(to_rect(rect), crop_box))
+}
+
+fn CGPDFPageGetBoxRect(env: &mut Environment, page: CGPDFPageRef, box_type: CGPDFBox) -> CGRect {
+    let host_obj = env.objc.borrow::<CGPDFPageHostObject>(page);
+    box_rect(&host_obj.document, &host_obj.page, box_type)
+}
+
+fn CGPDFPageGetDrawingTransform(
+    env: &mut Environment,
+    page: CGPDFPageRef,
+    box_type: CGPDFBox,
+    rect: CGRect,
+    rotate: i32,
+    preserve_aspect_ratio: bool,
+) -> CGAffineTransform {
+    let host_obj = env.objc.borrow::<CGPDFPageHostObject>(page);
+    let page_rect = box_rect(&host_obj.document, &host_obj.page, box_type);
+    // Both rotations are clockwise and in multiples of 90°.
+    let angle = (host_obj.page.rotate + rotate).rem_euclid(360) / 90 * 90;
+    let (width, height) = if angle % 180 == 0 {
+        (page_rect.size.width, page_rect.size.height)
+    } else {
+        (page_rect.size.height, page_rect.size.width)
+    };
+    if width <= 0.0 || height <= 0.0 {
+        return CGAffineTransformIdentity;
+    }
+
+    let mut scale_x = rect.size.width / width;
+    let mut scale_y = rect.size.height / height;
+    if preserve_aspect_ratio {
+        scale_x = scale_x.min(scale_y);
+        scale_y = scale_x;
+    }
+    // The page is shrunk to fit, but never enlarged.
+    scale_x = scale_x.min(1.0);
+    scale_y = scale_y.min(1.0);
+
+    // The page is centered in the rect.
+    CGAffineTransform::make_translation(
+        -(page_rect.origin.x + page_rect.size.width / 2.0),
+        -(page_rect.origin.y + page_rect.size.height / 2.0),
+    )
+    .concat(CGAffineTransform::make_rotation(
+        -(angle as CGFloat).to_radians(),
+    ))
+    .concat(CGAffineTransform::make_scale(scale_x, scale_y))
+    .concat(CGAffineTransform::make_translation(
+        rect.origin.x + rect.size.width / 2.0,
+        rect.origin.y + rect.size.height / 2.0,
+    ))
+}
+
+/// Implementation of `CGContextDrawPDFPage`. The page is drawn in the current
+/// user space, clipped to its crop box.
+pub(super) fn draw(env: &mut Environment, context: CGContextRef, page: CGPDFPageRef) {
+    let host_obj = env.objc.borrow::<CGPDFPageHostObject>(page);
+    let document = host_obj.document.clone();
+    let page = host_obj.page.clone();
+    let crop_box = box_rect(&document, &page, kCGPDFCropBox);
+
+    let mut content = Vec::new();
+    let streams = match document.get_key(&page.dict, "Contents") {
+        Object::Array(streams) => streams.iter().map(|s| document.resolve(s)).collect(),
+        stream => vec![stream],
+    };
+    for stream in streams {
+        let Some(stream) = stream.as_stream() else {
+            continue;
+        };
+        match document.decode_stream(stream) {
+            Ok(decoded) => content.extend_from_slice(&decoded.data),
+            Err(e) => {
+                log!("Warning: couldn't decode PDF content stream: {}", e);
+            }
+        }
+        // Streams are concatenated, but tokens can't span them.
+        content.push(b'\n');
+    }
+
+    let mut renderer = Renderer {
+        env,
+        context,
+        document: &document,
+        state: PdfState::default(),
+        state_stack: Vec::new(),
+        path: Path::default(),
+        current_point: CGPoint::default(),
+        pending_clip: None,
+        text_matrix: CGAffineTransformIdentity,
+        text_line_matrix: CGAffineTransformIdentity,
+        text_clip: None,
+        fonts: HashMap::new(),
+        form_depth: 0,
+        warned: HashSet::new(),
+    };
+    renderer.save_state();
+    // The context's own line style etc don't apply to the page.
+    let state = renderer.host_state();
+    state.stroke_style = StrokeStyle::default();
+    state.blend_mode = kCGBlendModeNormal;
+    renderer.clip_to_rect(crop_box);
+    renderer.run(&content, &page.resources);
+    renderer.restore_state();
+}
+
+/// A color space, reduced to what is needed to convert colors to RGB.
+#[derive(Clone, Debug)]
+enum ColorSpace {
+    Gray,
+    Rgb,
+    Cmyk,
+    /// Each color is an index into a table of RGB colors.
+    Indexed(Rc<[(CGFloat, CGFloat, CGFloat)]>),
+    /// Separation and DeviceN color spaces, with this many tint components.
+    /// These are approximated as shades of gray, which is what they most
+    /// often are anyway.
+    Tint(usize),
+    Pattern,
+}
+impl ColorSpace {
+    fn components(&self) -> usize {
+        match *self {
+            ColorSpace::Gray | ColorSpace::Indexed(_) => 1,
+            ColorSpace::Rgb => 3,
+            ColorSpace::Cmyk => 4,
+            ColorSpace::Tint(count) => count,
+            ColorSpace::Pattern => 0,
+        }
+    }
+
+    /// The color selected along with the color space.
+    fn initial_color(&self) -> Vec<CGFloat> {
+        match *self {
+            ColorSpace::Cmyk => vec![0.0, 0.0, 0.0, 1.0],
+            ColorSpace::Tint(count) => vec![1.0; count],
+            _ => vec![0.0; self.components()],
+        }
+    }
+
+    fn to_rgb(&self, components: &[CGFloat]) -> Option<(CGFloat, CGFloat, CGFloat)> {
+        if components.len() < self.components() {
+            return None;
+        }
+        Some(match *self {
+            ColorSpace::Gray => (components[0], components[0], components[0]),
+            ColorSpace::Rgb => (components[0], components[1], components[2]),
+            ColorSpace::Cmyk => {
+                let [c, m, y, k] = components[..4] else {
+                    unreachable!();
+                };
+                (
+                    (1.0 - c) * (1.0 - k),
+                    (1.0 - m) * (1.0 - k),
+                    (1.0 - y) * (1.0 - k),
+                )
+            }
+            ColorSpace::Indexed(ref palette) => {
+                let index = (components[0].max(0.0) as usize).min(palette.len().checked_sub(1)?);
+                palette[index]
+            }
+            ColorSpace::Tint(count) => {
+                let tint = components[..count].iter().sum::<CGFloat>() / count.max(1) as CGFloat;
+                let gray = 1.0 - tint.clamp(0.0, 1.0);
+                (gray, gray, gray)
+            }
+            ColorSpace::Pattern => return None,
+        })
+    }
+}
+
+/// A font selected with `Tf`, and how to map character codes to glyphs.
+struct PdfFont {
+    font: Rc<Font>,
+    /// Whether character codes are two bytes long, as in composite fonts.
+    /// Otherwise they are one byte long.
+    two_byte: bool,
+    /// For simple fonts, the glyph for each code.
+    glyphs: Vec<u16>,
+    /// For composite fonts, the glyph for each CID. [None] means CIDs are
+    /// glyph IDs.
+    cid_to_gid: Option<Vec<u16>>,
+    /// Advance widths for codes, in thousandths of the font size.
+    widths: HashMap<u32, CGFloat>,
+    /// Width of codes missing from `widths`. If [None], the font's own
+    /// advance width is used.
+    default_width: Option<CGFloat>,
+}
+impl PdfFont {
+    fn glyph(&self, code: u32) -> u16 {
+        if self.two_byte {
+            match self.cid_to_gid {
+                Some(ref map) => map.get(code as usize).copied().unwrap_or(0),
+                None => code as u16,
+            }
+        } else {
+            self.glyphs.get(code as usize).copied().unwrap_or(0)
+        }
+    }
+
+    fn width(&self, code: u32, glyph: u16) -> CGFloat {
+        self.widths
+            .get(&code)
+            .copied()
+            .or(self.default_width)
+            .unwrap_or_else(|| {
+                self.font.glyph_advance_units(glyph) * 1000.0 / self.font.units_per_em() as CGFloat
+            })
+    }
+}
+
+/// Names of the glyphs for U+00C0 to U+00FF.
+const LATIN_1_GLYPH_NAMES: [&str; 64] = [
+    "Agrave",
+    "Aacute",
+    "Acircumflex",
+    "Atilde",
+    "Adieresis",
+    "Aring",
+    "AE",
+    "Ccedilla",
+    "Egrave",
+    "Eacute",
+    "Ecircumflex",
+    "Edieresis",
+    "Igrave",
+    "Iacute",
+    "Icircumflex",
+    "Idieresis",
+    "Eth",
+    "Ntilde",
+    "Ograve",
+    "Oacute",
+    "Ocircumflex",
+    "Otilde",
+    "Odieresis",
+    "multiply",
+    "Oslash",
+    "Ugrave",
+    "Uacute",
+    "Ucircumflex",
+    "Udieresis",
+    "Yacute",
+    "Thorn",
+    "germandbls",
+    "agrave",
+    "aacute",
+    "acircumflex",
+    "atilde",
+    "adieresis",
+    "aring",
+    "ae",
+    "ccedilla",
+    "egrave",
+    "eacute",
+    "ecircumflex",
+    "edieresis",
+    "igrave",
+    "iacute",
+    "icircumflex",
+    "idieresis",
+    "eth",
+    "ntilde",
+    "ograve",
+    "oacute",
+    "ocircumflex",
+    "otilde",
+    "odieresis",
+    "divide",
+    "oslash",
+    "ugrave",
+    "uacute",
+    "ucircumflex",
+    "udieresis",
+    "yacute",
+    "thorn",
+    "ydieresis",
+];
+
+/// Other common glyph names, for fonts with a `/Differences` encoding.
+const GLYPH_NAMES: &[(&str, char)] = &[
+    ("space", ' '),
+    ("exclam", '!'),
+    ("quotedbl", '"'),
+    ("numbersign", '#'),
+    ("dollar", '$'),
+    ("percent", '%'),
+    ("ampersand", '&'),
+    ("quotesingle", '\''),
+    ("parenleft", '('),
+    ("parenright", ')'),
+    ("asterisk", '*'),
+    ("plus", '+'),
+    ("comma", ','),
+    ("hyphen", '-'),
+    ("period", '.'),
+    ("slash", '/'),
+    ("zero", '0'),
+    ("one", '1'),
+    ("two", '2'),
+    ("three", '3'),
+    ("four", '4'),
+    ("five", '5'),
+    ("six", '6'),
+    ("seven", '7'),
+    ("eight", '8'),
+    ("nine", '9'),
+    ("colon", ':'),
+    ("semicolon", ';'),
+    ("less", '<'),
+    ("equal", '='),
+    ("greater", '>'),
+    ("question", '?'),
+    ("at", '@'),
+    ("bracketleft", '['),
+    ("backslash", '\\'),
+    ("bracketright", ']'),
+    ("asciicircum", '^'),
+    ("underscore", '_'),
+    ("grave", '`'),
+    ("braceleft", '{'),
+    ("bar", '|'),
+    ("braceright", '}'),
+    ("asciitilde", '~'),
+    ("quoteleft", '‘'),
+    ("quoteright", '’'),
+    ("quotedblleft", '“'),
+    ("quotedblright", '”'),
+    ("quotesinglbase", '‚'),
+    ("quotedblbase", '„'),
+    ("bullet", '•'),
+    ("endash", '–'),
+    ("emdash", '—'),
+    ("ellipsis", '…'),
+    ("dagger", '†'),
+    ("daggerdbl", '‡'),
+    ("fi", 'ﬁ'),
+    ("fl", 'ﬂ'),
+    ("trademark", '™'),
+    ("copyright", '©'),
+    ("registered", '®'),
+    ("degree", '°'),
+    ("section", '§'),
+    ("paragraph", '¶'),
+    ("periodcentered", '·'),
+    ("Euro", '€'),
+    ("sterling", '£'),
+    ("yen", '¥'),
+    ("cent", '¢'),
+    ("exclamdown", '¡'),
+    ("questiondown", '¿'),
+    ("guillemotleft", '«'),
+    ("guillemotright", '»'),
+    ("minus", '−'),
+    ("nbspace", '\u{A0}'),
+];
+
+fn glyph_name_to_char(name: &str) -> Option<char> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return c.is_ascii_alphabetic().then_some(c);
+    }
+    if let Some(&(_, c)) = GLYPH_NAMES.iter().find(|&&(n, _)| n == name) {
+        return Some(c);
+    }
+    if let Some(i) = LATIN_1_GLYPH_NAMES.iter().position(|&n| n == name) {
+        return char::from_u32(0xC0 + i as u32);
+    }
+    // "uniXXXX" and "uXXXX" to "uXXXXXX" name Unicode characters directly.
+    let hex = name
+        .strip_prefix("uni")
+        .filter(|hex| hex.len() == 4)
+        .or_else(|| {
+            name.strip_prefix('u')
+                .filter(|hex| (4..=6).contains(&hex.len()))
+        })?;
+    char::from_u32(u32::from_str_radix(hex, 16).ok()?)
+}
+
+/// The parts of the PDF graphics state that aren't kept in the context.
+#[derive(Clone)]
+struct PdfState {
+    fill_space: ColorSpace,
+    stroke_space: ColorSpace,
+    fill_color: (CGFloat, CGFloat, CGFloat),
+    stroke_color: (CGFloat, CGFloat, CGFloat),
+    fill_alpha: CGFloat,
+    stroke_alpha: CGFloat,
+    char_spacing: CGFloat,
+    word_spacing: CGFloat,
+    horizontal_scale: CGFloat,
+    leading: CGFloat,
+    font: Option<Rc<PdfFont>>,
+    font_size: CGFloat,
+    render_mode: i32,
+    rise: CGFloat,
+}
+impl Default for PdfState {
+    fn default() -> Self {
+        PdfState {
+            fill_space: ColorSpace::Gray,
+            stroke_space: ColorSpace::Gray,
+            fill_color: (0.0, 0.0, 0.0),
+            stroke_color: (0.0, 0.0, 0.0),
+            fill_alpha: 1.0,
+            stroke_alpha: 1.0,
+            char_spacing: 0.0,
+            word_spacing: 0.0,
+            horizontal_scale: 1.0,
+            leading: 0.0,
+            font: None,
+            font_size: 0.0,
+            render_mode: 0,
+            rise: 0.0,
+        }
+    }
+}
+
+/// Form XObjects can contain other forms, so this limits how deep that can
+/// go, in case of a loop.
+const MAX_FORM_DEPTH: u32 = 16;
+
+struct Renderer<'a> {
+    env: &'a mut Environment,
+    context: CGContextRef,
+    document: &'a Document,
+    state: PdfState,
+    state_stack: Vec<PdfState>,
+    /// Current path, in device space.
+    path: Path,
+    /// Current point, in user space, for the `v` and `y` operators.
+    current_point: CGPoint,
+    /// Clip set by `W` or `W*`, which applies once the path is painted.
+    pending_clip: Option<FillRule>,
+    text_matrix: CGAffineTransform,
+    text_line_matrix: CGAffineTransform,
+    /// Text drawn with a clipping rendering mode since `BT`. It all becomes
+    /// the clip at `ET`.
+    text_clip: Option<Vec<GlyphRun>>,
+    /// Fonts that have been loaded, by object number.
+    fonts: HashMap<u32, Option<Rc<PdfFont>>>,
+    form_depth: u32,
+    /// Unsupported features that have already been logged.
+    warned: HashSet<String>,
+}
+
+impl Renderer<'_> {
+    fn host_state(&mut self) -> &mut CGContextState {
+        &mut self
+            .env
+            .objc
+            .borrow_mut::<CGContextHostObject>(self.context)
+            .state
+    }
+
+    fn ctm(&mut self) -> CGAffineTransform {
+        self.host_state().transform
+    }
+
+    fn unsupported(&mut self, feature: &str) {
+        if self.warned.insert(feature.to_string()) {
+            log!(
+                "Warning: CGContextDrawPDFPage: unsupported PDF feature: {}",
+                feature
+            );
+        }
+    }
+
+    fn save_state(&mut self) {
+        CGContextSaveGState(self.env, self.context);
+        self.state_stack.push(self.state.clone());
+    }
+
+    fn restore_state(&mut self) {
+        if let Some(state) = self.state_stack.pop() {
+            self.state = state;
+            CGContextRestoreGState(self.env, self.context);
+        }
+    }
+
+    /// Look up a named resource, e.g. a font, in a resource dictionary.
+    fn resource(&self, resources: &Dict, category: &str, name: &str) -> Object {
+        match self.document.get_key(resources, category) {
+            Object::Dict(dict) => self.document.get_key(&dict, name),
+            _ => Object::Null,
+        }
+    }
+
+    /// Run a content stream, restoring any graphics states it saved but
+    /// didn't restore.
+    fn run(&mut self, content: &[u8], resources: &Rc<Dict>) {
+        let depth = self.state_stack.len();
+        for operation in pdf::parse_content(content) {
+            self.execute(&operation.operator, &operation.operands, resources);
+        }
+        while self.state_stack.len() > depth {
+            self.restore_state();
+        }
+    }
+
+    fn execute(&mut self, operator: &str, operands: &[Object], resources: &Rc<Dict>) {
+        let numbers: Vec<CGFloat> = operands.iter().filter_map(Object::as_number).collect();
+        let name = operands.first().and_then(Object::as_name);
+        let point = |x, y| CGPoint { x, y };
+        match (operator, &numbers[..]) {
+            // Graphics state
+            ("q", _) => self.save_state(),
+            ("Q", _) => self.restore_state(),
+            ("cm", &[a, b, c, d, tx, ty]) => {
+                let matrix = CGAffineTransform { a, b, c, d, tx, ty };
+                CGContextConcatCTM(self.env, self.context, matrix);
+            }
+            ("w", &[width]) => self.host_state().stroke_style.width = width,
+            ("J", &[cap]) => self.host_state().stroke_style.cap = cap as i32,
+            ("j", &[join]) => self.host_state().stroke_style.join = join as i32,
+            ("M", &[limit]) => self.host_state().stroke_style.miter_limit = limit,
+            ("d", _) => {
+                if let [Object::Array(ref lengths), ref phase] = operands[..] {
+                    self.set_dash(lengths, phase);
+                }
+            }
+            ("gs", _) => {
+                if let Some(name) = name {
+                    self.set_ext_g_state(name, resources);
+                }
+            }
+            ("ri" | "i", _) => (),
+
+            // Paths
+            ("m", &[x, y]) => {
+                let ctm = self.ctm();
+                self.path
+                    .add_transformed(ctm, |path| path.move_to(point(x, y)));
+                self.current_point = point(x, y);
+            }
+            ("l", &[x, y]) => {
+                let ctm = self.ctm();
+                self.path
+                    .add_transformed(ctm, |path| path.line_to(point(x, y)));
+                self.current_point = point(x, y);
+            }
+            ("c", &[x1, y1, x2, y2, x3, y3]) => {
+                let ctm = self.ctm();
+                self.path.add_transformed(ctm, |path| {
+                    path.curve_to(point(x1, y1), point(x2, y2), point(x3, y3))
+                });
+                self.current_point = point(x3, y3);
+            }
+            ("v", &[x2, y2, x3, y3]) => {
+                let ctm = self.ctm();
+                let current_point = self.current_point;
+                self.path.add_transformed(ctm, |path| {
+                    path.curve_to(current_point, point(x2, y2), point(x3, y3))
+                });
+                self.current_point = point(x3, y3);
+            }
+            ("y", &[x1, y1, x3, y3]) => {
+                let ctm = self.ctm();
+                self.path.add_transformed(ctm, |path| {
+                    path.curve_to(point(x1, y1), point(x3, y3), point(x3, y3))
+                });
+                self.current_point = point(x3, y3);
+            }
+            ("h", _) => self.path.close_subpath(),
+            ("re", &[x, y, width, height]) => {
+                let ctm = self.ctm();
+                let rect = CGRect {
+                    origin: point(x, y),
+                    size: CGSize { width, height },
+                };
+                self.path.add_transformed(ctm, |path| path.add_rect(rect));
+                self.current_point = point(x, y);
+            }
+
+            // Painting and clipping
+            ("S", _) => self.paint(None, true, false),
+            ("s", _) => self.paint(None, true, true),
+            ("f" | "F", _) => self.paint(Some(FillRule::Winding), false, false),
+            ("f*", _) => self.paint(Some(FillRule::EvenOdd), false, false),
+            ("B", _) => self.paint(Some(FillRule::Winding), true, false),
+            ("B*", _) => self.paint(Some(FillRule::EvenOdd), true, false),
+            ("b", _) => self.paint(Some(FillRule::Winding), true, true),
+            ("b*", _) => self.paint(Some(FillRule::EvenOdd), true, true),
+            ("n", _) => self.paint(None, false, false),
+            ("W", _) => self.pending_clip = Some(FillRule::Winding),
+            ("W*", _) => self.pending_clip = Some(FillRule::EvenOdd),
+
+            // Colors
+            ("CS" | "cs", _) => {
+                let Some(space) = operands
+                    .first()
+                    .and_then(|o| self.color_space(o, resources))
+                else {
+                    return;
+                };
+                let color = space.initial_color();
+                self.set_color(operator == "CS", space, &color);
+            }
+            ("SC" | "SCN", _) => {
+                let space = self.state.stroke_space.clone();
+                self.set_color(true, space, &numbers);
+            }
+            ("sc" | "scn", _) => {
+                let space = self.state.fill_space.clone();
+                self.set_color(false, space, &numbers);
+            }
+            ("G", _) => self.set_color(true, ColorSpace::Gray, &numbers),
+            ("g", _) => self.set_color(false, ColorSpace::Gray, &numbers),
+            ("RG", _) => self.set_color(true, ColorSpace::Rgb, &numbers),
+            ("rg", _) => self.set_color(false, ColorSpace::Rgb, &numbers),
+            ("K", _) => self.set_color(true, ColorSpace::Cmyk, &numbers),
+            ("k", _) => self.set_color(false, ColorSpace::Cmyk, &numbers),
+
+            // XObjects, images and shadings
+            ("Do", _) => {
+                if let Some(name) = name {
+                    self.draw_x_object(name, resources);
+                }
+            }
+            ("BI", _) => {
+                if let [Object::Dict(ref dict), Object::String(ref data)] = operands[..] {
+                    self.draw_inline_image(dict, data, resources);
+                }
+            }
+            ("sh", _) => self.unsupported("shadings"),
+
+            // Text
+            ("BT", _) => {
+                self.text_matrix = CGAffineTransformIdentity;
+                self.text_line_matrix = CGAffineTransformIdentity;
+                self.text_clip = None;
+            }
+            ("ET", _) => {
+                if let Some(runs) = self.text_clip.take() {
+                    self.host_state().text_drawing_mode = kCGTextClip;
+                    cg_bitmap_context::show_glyphs(self.env, self.context, &runs);
+                }
+            }
+            ("Tc", &[spacing]) => self.state.char_spacing = spacing,
+            ("Tw", &[spacing]) => self.state.word_spacing = spacing,
+            ("Tz", &[scale]) => self.state.horizontal_scale = scale / 100.0,
+            ("TL", &[leading]) => self.state.leading = leading,
+            ("Ts", &[rise]) => self.state.rise = rise,
+            ("Tr", &[mode]) => self.state.render_mode = mode as i32,
+            ("Tf", &[size]) => {
+                let font = name.and_then(|name| {
+                    let font = match self.document.get_key(resources, "Font") {
+                        Object::Dict(fonts) => fonts.get(name).cloned(),
+                        _ => None,
+                    };
+                    self.load_font(&font?)
+                });
+                self.state.font = font;
+                self.state.font_size = size;
+            }
+            ("Td", &[tx, ty]) => self.move_text(tx, ty),
+            ("TD", &[tx, ty]) => {
+                self.state.leading = -ty;
+                self.move_text(tx, ty);
+            }
+            ("Tm", &[a, b, c, d, tx, ty]) => {
+                self.text_matrix = CGAffineTransform { a, b, c, d, tx, ty };
+                self.text_line_matrix = self.text_matrix;
+            }
+            ("T*", _) => self.move_text(0.0, -self.state.leading),
+            ("Tj", _) => self.show_text(operands),
+            ("'", _) => {
+                self.move_text(0.0, -self.state.leading);
+                self.show_text(operands);
+            }
+            ("\"", _) => {
+                if let [ref word_spacing, ref char_spacing, ref string] = operands[..] {
+                    self.state.word_spacing = word_spacing.as_number().unwrap_or(0.0);
+                    self.state.char_spacing = char_spacing.as_number().unwrap_or(0.0);
+                    self.move_text(0.0, -self.state.leading);
+                    self.show_text(std::slice::from_ref(string));
+                }
+            }
+            ("TJ", _) => {
+                if let Some(elements) = operands.first().and_then(Object::as_array) {
+                    self.show_text(elements);
+                }
+            }
+
+            // Type 3 glyph metrics, marked content and compatibility sections
+            ("d0" | "d1" | "BMC" | "BDC" | "EMC" | "MP" | "DP" | "BX" | "EX", _) => (),
+
+            _ => log_dbg!(
+                "CGContextDrawPDFPage: ignoring {:?} with operands {:?}",
+                operator,
+                operands
+            ),
+        }
+    }
+
+    fn set_dash(&mut self, lengths: &[Object], phase: &Object) {
+        let lengths = lengths
+            .iter()
+            .filter_map(|length| self.document.resolve(length).as_number())
+            .collect();
+        let style = &mut self.host_state().stroke_style;
+        style.dash_lengths = lengths;
+        style.dash_phase = phase.as_number().unwrap_or(0.0);
+    }
+
+    fn set_ext_g_state(&mut self, name: &str, resources: &Dict) {
+        let ext_g_state = self.resource(resources, "ExtGState", name);
+        let Some(ext_g_state) = ext_g_state.as_dict() else {
+            return;
+        };
+        for (key, value) in ext_g_state {
+            let value = self.document.resolve(value);
+            let number = value.as_number();
+            match (key.as_str(), number) {
+                ("LW", Some(width)) => self.host_state().stroke_style.width = width,
+                ("LC", Some(cap)) => self.host_state().stroke_style.cap = cap as i32,
+                ("LJ", Some(join)) => self.host_state().stroke_style.join = join as i32,
+                ("ML", Some(limit)) => self.host_state().stroke_style.miter_limit = limit,
+                ("D", _) => {
+                    if let Some([Object::Array(ref lengths), ref phase]) = value.as_array() {
+                        self.set_dash(lengths, phase);
+                    }
+                }
+                ("CA", Some(alpha)) => self.state.stroke_alpha = alpha.clamp(0.0, 1.0),
+                ("ca", Some(alpha)) => self.state.fill_alpha = alpha.clamp(0.0, 1.0),
+                ("BM", _) => {
+                    // This may be an array of modes to try in order.
+                    let mode = match value {
+                        Object::Array(ref modes) => modes.first().cloned().unwrap_or(Object::Null),
+                        mode => mode,
+                    };
+                    let mode = mode.as_name().and_then(blend_mode).unwrap_or_else(|| {
+                        self.unsupported("blend modes other than the standard ones");
+                        kCGBlendModeNormal
+                    });
+                    self.host_state().blend_mode = mode;
+                }
+                ("Font", _) => {
+                    if let Some([ref font, ref size]) = value.as_array() {
+                        self.state.font = self.load_font(font);
+                        self.state.font_size = size.as_number().unwrap_or(0.0);
+                    }
+                }
+                ("SMask", _) if value.as_name() != Some("None") => {
+                    self.unsupported("soft masks");
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Paint the current path and end it, applying any pending clip.
+    fn paint(&mut self, fill: Option<FillRule>, stroke: bool, close: bool) {
+        if close {
+            self.path.close_subpath();
+        }
+        let path = std::mem::take(&mut self.path);
+        self.apply_colors();
+        if let Some(rule) = fill {
+            cg_bitmap_context::fill_path(self.env, self.context, &path, rule);
+        }
+        if stroke {
+            cg_bitmap_context::stroke_path(self.env, self.context, &path);
+        }
+        if let Some(rule) = self.pending_clip.take() {
+            cg_bitmap_context::clip_to_path(self.env, self.context, &path, rule);
+        }
+    }
+
+    fn clip_to_rect(&mut self, rect: CGRect) {
+        let mut path = Path::default();
+        path.add_transformed(self.ctm(), |path| path.add_rect(rect));
+        cg_bitmap_context::clip_to_path(self.env, self.context, &path, FillRule::Winding);
+    }
+
+    /// Copy the PDF colors to the context, for painting.
+    fn apply_colors(&mut self) {
+        let (r, g, b) = self.state.fill_color;
+        let fill_color = (r, g, b, self.state.fill_alpha);
+        let (r, g, b) = self.state.stroke_color;
+        let stroke_color = (r, g, b, self.state.stroke_alpha);
+        let host_state = self.host_state();
+        host_state.rgb_fill_color = fill_color;
+        host_state.rgb_stroke_color = stroke_color;
+        host_state.fill_pattern = None;
+        host_state.stroke_pattern = None;
+    }
+
+    fn set_color(&mut self, stroke: bool, space: ColorSpace, components: &[CGFloat]) {
+        if matches!(space, ColorSpace::Pattern) {
+            self.unsupported("patterns");
+        }
+        let rgb = space.to_rgb(components);
+        let (space_field, color_field) = if stroke {
+            (&mut self.state.stroke_space, &mut self.state.stroke_color)
+        } else {
+            (&mut self.state.fill_space, &mut self.state.fill_color)
+        };
+        *space_field = space;
+        if let Some((r, g, b)) = rgb {
+            *color_field = (r.clamp(0.0, 1.0), g.clamp(0.0, 1.0), b.clamp(0.0, 1.0));
+        }
+    }
+
+    fn color_space(&mut self, object: &Object, resources: &Dict) -> Option<ColorSpace> {
+        self.color_space_inner(object, resources, 0)
+    }
+
+    fn color_space_inner(
+        &mut self,
+        object: &Object,
+        resources: &Dict,
+        depth: u32,
+    ) -> Option<ColorSpace> {
+        // Color spaces can refer to others, e.g. indexed color spaces to
+        // their base, so this limits how deep that can go.
+        if depth > 4 {
+            return None;
+        }
+        let object = self.document.resolve(object);
+        let (family, params): (&str, &[Object]) = match object {
+            Object::Name(ref name) => (name, &[]),
+            Object::Array(ref array) => match array.first().map(|o| self.document.resolve(o)) {
+                Some(Object::Name(ref name)) => (&name.clone(), &array[1..]),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        Some(match family {
+            "DeviceGray" | "G" | "CalGray" => ColorSpace::Gray,
+            "DeviceRGB" | "RGB" | "CalRGB" => ColorSpace::Rgb,
+            "DeviceCMYK" | "CMYK" => ColorSpace::Cmyk,
+            "Pattern" => ColorSpace::Pattern,
+            "ICCBased" => {
+                let stream = self.document.resolve(params.first()?);
+                let dict = stream.as_dict()?;
+                match self.document.get_key(dict, "N").as_int() {
+                    Some(1) => ColorSpace::Gray,
+                    Some(3) => ColorSpace::Rgb,
+                    Some(4) => ColorSpace::Cmyk,
+                    _ => {
+                        let alternate = dict.get("Alternate")?.clone();
+                        return self.color_space_inner(&alternate, resources, depth + 1);
+                    }
+                }
+            }
+            "Indexed" | "I" => {
+                let [ref base, ref high, ref lookup] = params[..] else {
+                    return None;
+                };
+                let base = self.color_space_inner(base, resources, depth + 1)?;
+                let high = self.document.resolve(high).as_int()?.clamp(0, 255) as usize;
+                let lookup = match self.document.resolve(lookup) {
+                    Object::String(string) => string.to_vec(),
+                    Object::Stream(stream) => self.document.decode_stream(&stream).ok()?.data,
+                    _ => return None,
+                };
+                let count = base.components();
+                let palette: Vec<_> = (0..=high)
+                    .map(|i| {
+                        let components: Vec<CGFloat> = (0..count)
+                            .map(|j| *lookup.get(i * count + j).unwrap_or(&0) as CGFloat / 255.0)
+                            .collect();
+                        base.to_rgb(&components).unwrap_or((0.0, 0.0, 0.0))
+                    })
+                    .collect();
+                ColorSpace::Indexed(palette.into())
+            }
+            "Separation" => ColorSpace::Tint(1),
+            "DeviceN" => {
+                let names = self.document.resolve(params.first()?);
+                ColorSpace::Tint(names.as_array()?.len())
+            }
+            _ if depth == 0 && params.is_empty() => {
+                // Named color spaces are defined in the resources.
+                let space = self.resource(resources, "ColorSpace", family);
+                return self.color_space_inner(&space, resources, depth + 1);
+            }
+            _ => {
+                self.unsupported(&format!("{} color spaces", family));
+                return None;
+            }
+        })
+    }
+
+    fn draw_x_object(&mut self, name: &str, resources: &Rc<Dict>) {
+        let Object::Stream(stream) = self.resource(resources, "XObject", name) else {
+            return;
+        };
+        match stream.dict.get("Subtype").and_then(Object::as_name) {
+            Some("Image") => self.draw_image(&stream, resources),
+            Some("Form") => self.draw_form(&stream, resources),
+            _ => (),
+        }
+    }
+
+    fn draw_form(&mut self, stream: &Stream, resources: &Rc<Dict>) {
+        if self.form_depth >= MAX_FORM_DEPTH {
+            log!("Warning: PDF forms nested too deeply, skipping");
+            return;
+        }
+        let data = match self.document.decode_stream(stream) {
+            Ok(decoded) => decoded.data,
+            Err(e) => {
+                log!("Warning: couldn't decode PDF form: {}", e);
+                return;
+            }
+        };
+        let matrix: Vec<CGFloat> = match self.document.get_key(&stream.dict, "Matrix") {
+            Object::Array(matrix) => matrix.iter().filter_map(Object::as_number).collect(),
+            _ => Vec::new(),
+        };
+        let resources = match self.document.get_key(&stream.dict, "Resources") {
+            Object::Dict(resources) => resources,
+            _ => resources.clone(),
+        };
+
+        self.save_state();
+        if let [a, b, c, d, tx, ty] = matrix[..] {
+            let matrix = CGAffineTransform { a, b, c, d, tx, ty };
+            CGContextConcatCTM(self.env, self.context, matrix);
+        }
+        if let Some(bbox) = self.document.get_rect(&stream.dict, "BBox") {
+            self.clip_to_rect(to_rect(bbox));
+        }
+        self.form_depth += 1;
+        self.run(&data, &resources);
+        self.form_depth -= 1;
+        self.restore_state();
+    }
+
+    fn draw_inline_image(&mut self, dict: &Dict, data: &[u8], resources: &Dict) {
+        // Inline images can use abbreviated keys.
+        let dict = dict
+            .iter()
+            .map(|(key, value)| {
+                let key = match key.as_str() {
+                    "BPC" => "BitsPerComponent",
+                    "CS" => "ColorSpace",
+                    "D" => "Decode",
+                    "DP" => "DecodeParms",
+                    "F" => "Filter",
+                    "H" => "Height",
+                    "IM" => "ImageMask",
+                    "I" => "Interpolate",
+                    "W" => "Width",
+                    key => key,
+                };
+                (key.to_string(), value.clone())
+            })
+            .collect();
+        let stream = Stream {
+            dict,
+            data: data.to_vec(),
+        };
+        self.draw_image(&stream, resources);
+    }
+
+    fn draw_image(&mut self, stream: &Stream, resources: &Dict) {
+        let Some((image, is_mask)) = self.decode_image(stream, resources, false) else {
+            return;
+        };
+        self.apply_colors();
+        let cg_image = cg_image::create(self.env, image, is_mask);
+        // Images fill the unit square of user space. Image masks are painted
+        // with the fill color, which includes the fill alpha, but other images
+        // need it to be applied separately.
+        let rect = CGRect {
+            origin: CGPoint { x: 0.0, y: 0.0 },
+            size: CGSize {
+                width: 1.0,
+                height: 1.0,
+            },
+        };
+        let alpha = self.host_state().alpha;
+        if !is_mask {
+            self.host_state().alpha = alpha * self.state.fill_alpha;
+        }
+        cg_bitmap_context::draw_image(self.env, self.context, rect, cg_image);
+        self.host_state().alpha = alpha;
+        CFRelease(self.env, cg_image);
+    }
+
+    /// Convert an image XObject to an [Image], also returning whether it is
+    /// an image mask. `is_soft_mask` should be set when decoding an image's
+    /// soft mask, whose own `/SMask` is ignored (a damaged file could have a
+    /// loop of soft masks).
+    fn decode_image(
+        &mut self,
+        stream: &Stream,
+        resources: &Dict,
+        is_soft_mask: bool,
+    ) -> Option<(Image, bool)> {
+        let get = |key| self.document.get_key(&stream.dict, key);
+        let width = get("Width").as_int().filter(|&w| w > 0 && w <= 0x4000)? as u32;
+        let height = get("Height").as_int().filter(|&h| h > 0 && h <= 0x4000)? as u32;
+        let is_mask = get("ImageMask").as_bool() == Some(true);
+        let decode: Option<Vec<(CGFloat, CGFloat)>> = get("Decode").as_array().map(|decode| {
+            decode
+                .chunks_exact(2)
+                .map(|pair| {
+                    let min = pair[0].as_number().unwrap_or(0.0);
+                    let max = pair[1].as_number().unwrap_or(1.0);
+                    (min, max)
+                })
+                .collect()
+        });
+        let color_space = get("ColorSpace");
+        let bits_per_component = get("BitsPerComponent").as_int();
+        let soft_mask = get("SMask");
+
+        let decoded = match self.document.decode_stream(stream) {
+            Ok(decoded) => decoded,
+            Err(e) => {
+                log!("Warning: couldn't decode PDF image: {}", e);
+                return None;
+            }
+        };
+        let image = match decoded.image_filter.as_deref() {
+            // TODO: Apply the decode array to JPEG images too.
+            Some("DCTDecode") => match Image::from_bytes(&decoded.data) {
+                Ok(image) => image,
+                Err(e) => {
+                    log!("Warning: couldn't decode JPEG image in PDF: {}", e);
+                    return None;
+                }
+            },
+            Some(filter) => {
+                self.unsupported(&format!("{} images", filter));
+                return None;
+            }
+            None if is_mask => {
+                // Samples of 0 are painted by default, so they are the inverse
+                // of alpha, like for CGImageMaskCreate().
+                let (min, max) = decode
+                    .as_ref()
+                    .and_then(|d| d.first().copied())
+                    .unwrap_or((0.0, 1.0));
+                let decode = [(1.0 - min, 1.0 - max)];
+                let format =
+                    PixelFormat::new(ColorModel::None, 1, Some(1), kCGImageAlphaOnly).unwrap();
+                let bytes_per_row = width.div_ceil(8);
+                let pixels =
+                    format.to_rgba8(&decoded.data, (width, height), bytes_per_row, Some(&decode));
+                Image::from_pixel_vec(pixels, (width, height))
+            }
+            None => {
+                let space = self.color_space(&color_space, resources)?;
+                let bits_per_component = bits_per_component.unwrap_or(8).clamp(1, 16) as u32;
+                let pixels = self.decode_raw_image(
+                    &decoded.data,
+                    (width, height),
+                    &space,
+                    bits_per_component,
+                    decode.as_deref(),
+                )?;
+                Image::from_pixel_vec(pixels, (width, height))
+            }
+        };
+
+        if let Object::Stream(soft_mask) = soft_mask {
+            if !is_mask && !is_soft_mask {
+                return Some((self.apply_soft_mask(image, &soft_mask, resources), false));
+            }
+        }
+        Some((image, is_mask))
+    }
+
+    /// Convert uncompressed image samples to 8-bit RGBA.
+    fn decode_raw_image(
+        &mut self,
+        data: &[u8],
+        (width, height): (u32, u32),
+        space: &ColorSpace,
+        bits_per_component: u32,
+        decode: Option<&[(CGFloat, CGFloat)]>,
+    ) -> Option<Vec<u8>> {
+        let mut decode = decode.map(|decode| decode.to_vec());
+        let model = match *space {
+            ColorSpace::Gray => ColorModel::Gray,
+            ColorSpace::Rgb => ColorModel::Rgb,
+            ColorSpace::Indexed(ref palette) => {
+                // Decode arrays for indexed images are in terms of indices,
+                // which the pixel format doesn't support.
+                decode = None;
+                ColorModel::Indexed(palette.clone())
+            }
+            ColorSpace::Tint(1) => {
+                // A tint of 1 is the darkest, so it is the inverse of gray.
+                let (min, max) = decode
+                    .as_ref()
+                    .and_then(|d| d.first().copied())
+                    .unwrap_or((0.0, 1.0));
+                decode = Some(vec![(1.0 - min, 1.0 - max)]);
+                ColorModel::Gray
+            }
+            ColorSpace::Cmyk if bits_per_component == 8 => {
+                let pixel_count = width as usize * height as usize;
+                let mut pixels = Vec::with_capacity(pixel_count * 4);
+                for i in 0..pixel_count {
+                    let mut components = [0.0; 4];
+                    for (j, component) in components.iter_mut().enumerate() {
+                        let sample = *data.get(i * 4 + j).unwrap_or(&0) as CGFloat / 255.0;
+                        let (min, max) = decode
+                            .as_ref()
+                            .and_then(|d| d.get(j).copied())
+                            .unwrap_or((0.0, 1.0));
+                        *component = min + sample * (max - min);
+                    }
+                    let (r, g, b) = ColorSpace::Cmyk.to_rgb(&components).unwrap();
+                    pixels.extend_from_slice(&[
+                        (r * 255.0).round() as u8,
+                        (g * 255.0).round() as u8,
+                        (b * 255.0).round() as u8,
+                        255,
+                    ]);
+                }
+                return Some(pixels);
+            }
+            _ => {
+                self.unsupported(&format!(
+                    "{:?} images with {} bits per component",
+                    space, bits_per_component
+                ));
+                return None;
+            }
+        };
+        let components = space.components() as u32;
+        let format = PixelFormat::new(
+            model,
+            bits_per_component,
+            Some(bits_per_component * components),
+            kCGImageAlphaNone,
+        );
+        let format = match format {
+            Ok(format) => format,
+            Err(e) => {
+                self.unsupported(&format!("image format ({})", e));
+                return None;
+            }
+        };
+        let bytes_per_row = (width * components * bits_per_component).div_ceil(8);
+        Some(format.to_rgba8(data, (width, height), bytes_per_row, decode.as_deref()))
+    }
+
+    /// Apply a soft mask, which is a grayscale image used as alpha.
+    fn apply_soft_mask(&mut self, image: Image, soft_mask: &Stream, resources: &Dict) -> Image {
+        let Some((mask, _)) = self.decode_image(soft_mask, resources, true) else {
+            return image;
+        };
+        let (width, height) = image.dimensions();
+        let (mask_width, mask_height) = mask.dimensions();
+        let mut pixels = image.pixels().to_vec();
+        for (i, pixel) in pixels.chunks_exact_mut(4).enumerate() {
+            // The mask can have a different size to the image.
+            let x = (i as u32 % width) * mask_width / width;
+            let y = (i as u32 / width) * mask_height / height;
+            let mask_index = (y * mask_width + x) as usize * 4;
+            let alpha = mask.pixels()[mask_index] as u32;
+            // Image pixels have premultiplied alpha.
+            for component in pixel {
+                *component = (*component as u32 * alpha / 255) as u8;
+            }
+        }
+        Image::from_pixel_vec(pixels, (width, height))
+    }
+
+    /// Load a font from its dictionary, or a reference to it.
+    fn load_font(&mut self, object: &Object) -> Option<Rc<PdfFont>> {
+        let Object::Ref(number) = *object else {
+            let object = object.clone();
+            return self.parse_font(object.as_dict()?).map(Rc::new);
+        };
+        if let Some(font) = self.fonts.get(&number) {
+            return font.clone();
+        }
+        let font = match self.document.get(number) {
+            Object::Dict(dict) => self.parse_font(&dict).map(Rc::new),
+            _ => None,
+        };
+        self.fonts.insert(number, font.clone());
+        font
+    }
+
+    fn parse_font(&mut self, dict: &Dict) -> Option<PdfFont> {
+        let get = |key| self.document.get_key(dict, key);
+        let base_font = get("BaseFont").as_name().unwrap_or("").to_string();
+        match get("Subtype").as_name() {
+            Some("Type0") => self.parse_composite_font(dict, &base_font),
+            Some("Type3") => {
+                self.unsupported("Type 3 fonts");
+                None
+            }
+            subtype => {
+                let is_true_type = subtype == Some("TrueType");
+                self.parse_simple_font(dict, &base_font, is_true_type)
+            }
+        }
+    }
+
+    /// Get the font program embedded in a PDF, or substitute one.
+    fn font_program(&mut self, descriptor: &Object, base_font: &str) -> (Rc<Font>, bool) {
+        if let Some(descriptor) = descriptor.as_dict() {
+            for key in ["FontFile2", "FontFile3"] {
+                let Object::Stream(stream) = self.document.get_key(descriptor, key) else {
+                    continue;
+                };
+                // Bare CFF and Type 1 fonts can't be loaded, only TrueType
+                // and OpenType.
+                let subtype = stream.dict.get("Subtype").and_then(Object::as_name);
+                if key == "FontFile3" && subtype != Some("OpenType") {
+                    continue;
+                }
+                let font = self
+                    .document
+                    .decode_stream(&stream)
+                    .ok()
+                    .and_then(|decoded| Font::from_bytes(decoded.data));
+                match font {
+                    Some(font) => return (Rc::new(font), true),
+                    None => {
+                        log!("Warning: couldn't load embedded PDF font {}", base_font);
+                    }
+                }
+            }
+            if descriptor.contains_key("FontFile") || descriptor.contains_key("FontFile3") {
+                self.unsupported("embedded Type 1 and CFF fonts");
+            }
+        }
+        // Subset fonts have names like "ABCDEF+Helvetica".
+        let name = match base_font.split_once('+') {
+            Some((tag, name)) if tag.len() == 6 => name,
+            _ => base_font,
+        };
+        (cg_font::font_with_name(self.env, name), false)
+    }
+
+    fn parse_simple_font(
+        &mut self,
+        dict: &Dict,
+        base_font: &str,
+        is_true_type: bool,
+    ) -> Option<PdfFont> {
+        let descriptor = self.document.get_key(dict, "FontDescriptor");
+        let (font, embedded) = self.font_program(&descriptor, base_font);
+
+        let first_char = self
+            .document
+            .get_key(dict, "FirstChar")
+            .as_int()
+            .unwrap_or(0);
+        let mut widths = HashMap::new();
+        if let Object::Array(array) = self.document.get_key(dict, "Widths") {
+            for (i, width) in array.iter().enumerate() {
+                if let Some(width) = self.document.resolve(width).as_number() {
+                    widths.insert((first_char + i as i64) as u32, width);
+                }
+            }
+        }
+        let default_width = (!widths.is_empty()).then(|| {
+            descriptor
+                .as_dict()
+                .and_then(|d| self.document.get_key(d, "MissingWidth").as_number())
+                .unwrap_or(0.0)
+        });
+
+        let (base_encoding, differences) = match self.document.get_key(dict, "Encoding") {
+            Object::Name(name) => (Some(name), None),
+            Object::Dict(encoding) => (
+                self.document
+                    .get_key(&encoding, "BaseEncoding")
+                    .as_name()
+                    .map(Rc::from),
+                Some(self.document.get_key(&encoding, "Differences")),
+            ),
+            _ => (None, None),
+        };
+        let mut chars: Vec<Option<char>> = (0..=255u8)
+            .map(|code| match (base_encoding.as_deref(), code) {
+                (Some("MacRomanEncoding"), _) => {
+                    Some(cg_font::decode_char(kCGEncodingMacRoman, code))
+                }
+                // WinAnsiEncoding is Windows code page 1252, which is also
+                // the best guess for fonts that don't specify an encoding.
+                _ => CP1252.decode(&[code]).chars().next(),
+            })
+            .collect();
+        if let Some(Object::Array(differences)) = differences {
+            let mut code = 0;
+            for item in differences.iter() {
+                match *item {
+                    Object::Int(new_code) => code = new_code as usize,
+                    Object::Name(ref name) => {
+                        if let Some(c) = chars.get_mut(code) {
+                            *c = glyph_name_to_char(name);
+                        }
+                        code += 1;
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        let glyphs = (0..256u32)
+            .map(|code| {
+                let mut glyph = chars[code as usize].map_or(0, |c| font.glyph_id(c));
+                if glyph == 0 && embedded {
+                    // Symbolic TrueType fonts map codes to the private use
+                    // area, and subset fonts often have no usable character
+                    // map at all and use codes as glyph IDs.
+                    glyph = char::from_u32(0xF000 + code).map_or(0, |c| font.glyph_id(c));
+                    if glyph == 0 && is_true_type && (code as usize) < font.glyph_count() {
+                        glyph = code as u16;
+                    }
+                }
+                glyph
+            })
+            .collect();
+
+        Some(PdfFont {
+            font,
+            two_byte: false,
+            glyphs,
+            cid_to_gid: None,
+            widths,
+            default_width,
+        })
+    }
+
+    fn parse_composite_font(&mut self, dict: &Dict, base_font: &str) -> Option<PdfFont> {
+        match self.document.get_key(dict, "Encoding") {
+            Object::Name(ref name) if matches!(&**name, "Identity-H" | "Identity-V") => (),
+            _ => self.unsupported("CMaps other than Identity-H"),
+        }
+        let descendants = self.document.get_key(dict, "DescendantFonts");
+        let descendant = self.document.resolve(descendants.as_array()?.first()?);
+        let descendant = descendant.as_dict()?;
+
+        let descriptor = self.document.get_key(descendant, "FontDescriptor");
+        let (font, embedded) = self.font_program(&descriptor, base_font);
+        if !embedded {
+            // Without the font program, there is no way to know which CID is
+            // which character.
+            self.unsupported("composite fonts that aren't embedded");
+            return None;
+        }
+        let cid_to_gid = match self.document.get_key(descendant, "CIDToGIDMap") {
+            Object::Stream(stream) => {
+                let data = self.document.decode_stream(&stream).ok()?.data;
+                let map = data
+                    .chunks_exact(2)
+                    .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                    .collect();
+                Some(map)
+            }
+            _ => None,
+        };
+
+        // Widths are given as `first [w1 w2 ...]` or `first last w`.
+        let mut widths = HashMap::new();
+        if let Object::Array(array) = self.document.get_key(descendant, "W") {
+            let items: Vec<Object> = array.iter().map(|o| self.document.resolve(o)).collect();
+            let mut i = 0;
+            while i + 1 < items.len() {
+                let Some(first) = items[i].as_int() else {
+                    break;
+                };
+                if let Object::Array(ref list) = items[i + 1] {
+                    for (j, width) in list.iter().enumerate() {
+                        if let Some(width) = self.document.resolve(width).as_number() {
+                            widths.insert((first + j as i64) as u32, width);
+                        }
+                    }
+                    i += 2;
+                } else {
+                    let (Some(last), Some(width)) = (
+                        items[i + 1].as_int(),
+                        items.get(i + 2).and_then(Object::as_number),
+                    ) else {
+                        break;
+                    };
+                    // Don't let a huge range take up lots of memory.
+                    for cid in first..=last.min(first + 0xFFFF) {
+                        widths.insert(cid as u32, width);
+                    }
+                    i += 3;
+                }
+            }
+        }
+        let default_width = self
+            .document
+            .get_key(descendant, "DW")
+            .as_number()
+            .unwrap_or(1000.0);
+
+        Some(PdfFont {
+            font,
+            two_byte: true,
+            glyphs: Vec::new(),
+            cid_to_gid,
+            widths,
+            default_width: Some(default_width),
+        })
+    }
+
+    fn move_text(&mut self, tx: CGFloat, ty: CGFloat) {
+        self.text_line_matrix = self.text_line_matrix.translate(tx, ty);
+        self.text_matrix = self.text_line_matrix;
+    }
+
+    /// Show strings of text, which may be separated by adjustments to the
+    /// position as in `TJ`.
+    fn show_text(&mut self, elements: &[Object]) {
+        let Some(font) = self.state.font.clone() else {
+            return;
+        };
+        let PdfState {
+            char_spacing,
+            word_spacing,
+            horizontal_scale,
+            font_size,
+            render_mode,
+            rise,
+            ..
+        } = self.state;
+
+        let mut pen = 0.0;
+        let mut glyphs = Vec::new();
+        for element in elements {
+            let Object::String(ref bytes) = *element else {
+                // Adjustments are in thousandths of the font size, and
+                // positive numbers move backwards.
+                if let Some(adjustment) = element.as_number() {
+                    pen -= adjustment / 1000.0 * font_size;
+                }
+                continue;
+            };
+            let codes: Vec<u32> = if font.two_byte {
+                bytes
+                    .chunks_exact(2)
+                    .map(|pair| u16::from_be_bytes([pair[0], pair[1]]).into())
+                    .collect()
+            } else {
+                bytes.iter().map(|&byte| byte.into()).collect()
+            };
+            for code in codes {
+                let glyph = font.glyph(code);
+                glyphs.push((glyph, CGPoint { x: pen, y: rise }));
+                pen += font.width(code, glyph) / 1000.0 * font_size + char_spacing;
+                // Word spacing only applies to single-byte spaces.
+                if code == 32 && !font.two_byte {
+                    pen += word_spacing;
+                }
+            }
+        }
+
+        // A negative font size flips the glyphs, which is done with the text
+        // matrix since fonts can't be drawn at negative sizes.
+        let sign = if font_size < 0.0 { -1.0 } else { 1.0 };
+        let run = GlyphRun {
+            font: font.font.clone(),
+            font_size: font_size.abs(),
+            text_matrix: CGAffineTransform::make_scale(horizontal_scale * sign, sign)
+                .concat(self.text_matrix),
+            glyphs: glyphs
+                .into_iter()
+                .map(|(glyph, CGPoint { x, y })| {
+                    (
+                        glyph,
+                        CGPoint {
+                            x: x * sign,
+                            y: y * sign,
+                        },
+                    )
+                })
+                .collect(),
+        };
+        self.text_matrix = self.text_matrix.translate(pen * horizontal_scale, 0.0);
+
+        // The text rendering modes are the same as CGTextDrawingMode, except
+        // that clipping happens at the end of the text object.
+        let render_mode = if (0..=7).contains(&render_mode) {
+            render_mode
+        } else {
+            kCGTextFill
+        };
+        let paint_mode = render_mode % 4;
+        if paint_mode != kCGTextInvisible {
+            self.apply_colors();
+            self.host_state().text_drawing_mode = paint_mode;
+            cg_bitmap_context::show_glyphs(self.env, self.context, std::slice::from_ref(&run));
+        }
+        if render_mode >= 4 {
+            self.text_clip.get_or_insert_with(Vec::new).push(run);
+        }
+    }
+}
+
+fn blend_mode(name: &str) -> Option<CGBlendMode> {
+    Some(match name {
+        "Normal" | "Compatible" => kCGBlendModeNormal,
+        "Multiply" => kCGBlendModeMultiply,
+        "Screen" => kCGBlendModeScreen,
+        "Overlay" => kCGBlendModeOverlay,
+        "Darken" => kCGBlendModeDarken,
+        "Lighten" => kCGBlendModeLighten,
+        "ColorDodge" => kCGBlendModeColorDodge,
+        "ColorBurn" => kCGBlendModeColorBurn,
+        "HardLight" => kCGBlendModeHardLight,
+        "SoftLight" => kCGBlendModeSoftLight,
+        "Difference" => kCGBlendModeDifference,
+        "Exclusion" => kCGBlendModeExclusion,
+        "Hue" => kCGBlendModeHue,
+        "Saturation" => kCGBlendModeSaturation,
+        "Color" => kCGBlendModeColor,
+        "Luminosity" => kCGBlendModeLuminosity,
+        _ => return None,
+    })
+}
+
+pub const FUNCTIONS: FunctionExports = &[
+    export_c_func!(CGPDFPageRetain(_)),
+    export_c_func!(CGPDFPageRelease(_)),
+    export_c_func!(CGPDFPageGetDocument(_)),
+    export_c_func!(CGPDFPageGetPageNumber(_)),
+    export_c_func!(CGPDFPageGetRotationAngle(_)),
+    export_c_func!(CGPDFPageGetBoxRect(_, _)),
+    export_c_func!(CGPDFPageGetDrawingTransform(_, _, _, _, _)),
+];
@@ -0,0 +1,297 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! `CGShading.h`
+//!
+//! Besides the `CGShading` API, this module contains the host-side geometry of
+//! axial and radial shadings, which is shared with `CGGradient`.
+
+use super::cg_color_space::{self, CGColorSpaceHostObject, CGColorSpaceRef};
+use super::cg_function::{self, CGFunctionRef, CGFunctionRelease, CGFunctionRetain};
+use super::{CGFloat, CGPoint};
+use crate::dyld::{export_c_func, FunctionExports};
+use crate::frameworks::core_foundation::{CFRelease, CFRetain, CFTypeRef};
+use crate::objc::{objc_classes, ClassExports, HostObject};
+use crate::Environment;
+
+pub const CLASSES: ClassExports = objc_classes! {
+
+(env, this, _cmd);
+
+// CGShading seems to be a CFType-based type, but in our implementation those
+// are just Objective-C types, so we need a class for it, but its name is not
+// visible anywhere.
+@implementation _touchHLE_CGShading: NSObject
+
+- (())dealloc {
+    let function = env.objc.borrow::<CGShadingHostObject>(this).function;
+    CGFunctionRelease(env, function);
+    env.objc.dealloc_object(this, &mut env.mem)
+}
+
+@end
+
+};
+
+/// The shape of an axial or radial shading, in user space.
+#[derive(Copy, Clone, Debug)]
+pub(super) enum ShadingGeometry {
+    /// Color varies along the line from `start` to `end`.
+    Axial { start: CGPoint, end: CGPoint },
+    /// Color varies across a series of circles that are interpolated between
+    /// the start and end circles.
+    Radial {
+        start: CGPoint,
+        start_radius: CGFloat,
+        end: CGPoint,
+        end_radius: CGFloat,
+    },
+}
+impl ShadingGeometry {
+    /// Find the parameter (0 at the start, 1 at the end) of the shading at a
+    /// point. Returns [None] if the point isn't painted, which depends on
+    /// whether the shading extends beyond its start and end.
+    pub(super) fn parameter_at(
+        &self,
+        point: CGPoint,
+        extend_start: bool,
+        extend_end: bool,
+    ) -> Option<CGFloat> {
+        let in_extent = |s: CGFloat| (s >= 0.0 || extend_start) && (s <= 1.0 || extend_end);
+        match *self {
+            ShadingGeometry::Axial { start, end } => {
+                let (dx, dy) = (end.x - start.x, end.y - start.y);
+                let length_squared = dx * dx + dy * dy;
+                if length_squared == 0.0 {
+                    return None;
+                }
+                let s = ((point.x - start.x) * dx + (point.y - start.y) * dy) / length_squared;
+                in_extent(s).then_some(s.clamp(0.0, 1.0))
+            }
+            ShadingGeometry::Radial {
+                start,
+                start_radius,
+                end,
+                end_radius,
+            } => {
+                // Find the largest s for which the point is on the circle
+                // centred at lerp(start, end, s) with radius
+                // lerp(start_radius, end_radius, s), by solving
+                // a*s^2 - 2*b*s + c = 0.
+                let (cx, cy) = (end.x - start.x, end.y - start.y);
+                let (px, py) = (point.x - start.x, point.y - start.y);
+                let dr = end_radius - start_radius;
+                let a = cx * cx + cy * cy - dr * dr;
+                let b = px * cx + py * cy + start_radius * dr;
+                let c = px * px + py * py - start_radius * start_radius;
+                let candidates = if a.abs() < 1e-6 {
+                    if b == 0.0 {
+                        return None;
+                    }
+                    [c / (2.0 * b), CGFloat::NAN]
+                } else {
+                    let discriminant = b * b - a * c;
+                    if discriminant < 0.0 {
+                        return None;
+                    }
+                    let root = discriminant.sqrt();
+                    let (s1, s2) = ((b + root) / a, (b - root) / a);
+                    [s1.max(s2), s1.min(s2)]
+                };
+                candidates
+                    .into_iter()
+                    .find(|&s| !s.is_nan() && start_radius + dr * s >= 0.0 && in_extent(s))
+                    .map(|s| s.clamp(0.0, 1.0))
+            }
+        }
+    }
+}
+
+/// Number of colors sampled along a shading or gradient when drawing it.
+pub(super) const SAMPLE_COUNT: usize = 256;
+
+/// A shading or gradient ready to be drawn: its geometry and evenly spaced
+/// samples of its colors (sRGB, not premultiplied) from start to end.
+pub(super) struct Shader {
+    pub(super) geometry: ShadingGeometry,
+    pub(super) extend_start: bool,
+    pub(super) extend_end: bool,
+    pub(super) samples: Vec<(CGFloat, CGFloat, CGFloat, CGFloat)>,
+}
+impl Shader {
+    /// Get the color at a point in user space, if it is painted.
+    pub(super) fn color_at(&self, point: CGPoint) -> Option<(CGFloat, CGFloat, CGFloat, CGFloat)> {
+        let s = self
+            .geometry
+            .parameter_at(point, self.extend_start, self.extend_end)?;
+        let position = s * (self.samples.len() - 1) as CGFloat;
+        let index = (position.floor() as usize).min(self.samples.len() - 2);
+        let t = position - index as CGFloat;
+        let (a, b) = (self.samples[index], self.samples[index + 1]);
+        Some((
+            a.0 + (b.0 - a.0) * t,
+            a.1 + (b.1 - a.1) * t,
+            a.2 + (b.2 - a.2) * t,
+            a.3 + (b.3 - a.3) * t,
+        ))
+    }
+}
+
+struct CGShadingHostObject {
+    geometry: ShadingGeometry,
+    color_space: &'static str,
+    function: CGFunctionRef,
+    extend_start: bool,
+    extend_end: bool,
+}
+impl HostObject for CGShadingHostObject {}
+
+pub type CGShadingRef = CFTypeRef;
+
+fn create_shading(
+    env: &mut Environment,
+    space: CGColorSpaceRef,
+    geometry: ShadingGeometry,
+    function: CGFunctionRef,
+    extend_start: bool,
+    extend_end: bool,
+) -> CGShadingRef {
+    let color_space = env.objc.borrow::<CGColorSpaceHostObject>(space).name;
+    let function = CGFunctionRetain(env, function);
+    let host_object = CGShadingHostObject {
+        geometry,
+        color_space,
+        function,
+        extend_start,
+        extend_end,
+    };
+    let isa = env
+        .objc
+        .get_known_class("_touchHLE_CGShading", &mut env.mem);
+    env.objc
+        .alloc_object(isa, Box::new(host_object), &mut env.mem)
+}
+
+fn CGShadingCreateAxial(
+    env: &mut Environment,
+    space: CGColorSpaceRef,
+    start: CGPoint,
+    end: CGPoint,
+    function: CGFunctionRef,
+    extend_start: bool,
+    extend_end: bool,
+) -> CGShadingRef {
+    let geometry = ShadingGeometry::Axial { start, end };
+    create_shading(env, space, geometry, function, extend_start, extend_end)
+}
+
+fn CGShadingCreateRadial(
+    env: &mut Environment,
+    space: CGColorSpaceRef,
+    start: CGPoint,
+    start_radius: CGFloat,
+    end: CGPoint,
+    end_radius: CGFloat,
+    function: CGFunctionRef,
+    extend_start: bool,
+    extend_end: bool,
+) -> CGShadingRef {
+    let geometry = ShadingGeometry::Radial {
+        start,
+        start_radius,
+        end,
+        end_radius,
+    };
+    create_shading(env, space, geometry, function, extend_start, extend_end)
+}
+
+pub fn CGShadingRelease(env: &mut Environment, shading: CGShadingRef) {
+    if !shading.is_null() {
+        CFRelease(env, shading);
+    }
+}
+pub fn CGShadingRetain(env: &mut Environment, shading: CGShadingRef) -> CGShadingRef {
+    if !shading.is_null() {
+        CFRetain(env, shading)
+    } else {
+        shading
+    }
+}
+
+/// Prepare a shading for drawing. This calls the shading's function, which is
+/// app code, to sample its colors.
+pub(super) fn make_shader(env: &mut Environment, shading: CGShadingRef) -> Shader {
+    let &CGShadingHostObject {
+        geometry,
+        color_space,
+        function,
+        extend_start,
+        extend_end,
+    } = env.objc.borrow(shading);
+
+    let color_count = cg_color_space::components_per_color(color_space) as usize;
+    let (domain_start, domain_end) = cg_function::input_domain(env, function);
+    let samples = (0..SAMPLE_COUNT)
+        .map(|i| {
+            let s = i as CGFloat / (SAMPLE_COUNT - 1) as CGFloat;
+            let input = domain_start + (domain_end - domain_start) * s;
+            let mut components = cg_function::evaluate(env, function, input);
+            // The alpha component is optional.
+            components.resize(color_count + 1, 1.0);
+            cg_color_space::to_rgba(color_space, &components)
+        })
+        .collect();
+
+    Shader {
+        geometry,
+        extend_start,
+        extend_end,
+        samples,
+    }
+}
+
+pub const FUNCTIONS: FunctionExports = &[
+    export_c_func!(CGShadingCreateAxial(_, _, _, _, _, _)),
+    export_c_func!(CGShadingCreateRadial(_, _, _, _, _, _, _, _)),
+    export_c_func!(CGShadingRetain(_)),
+    export_c_func!(CGShadingRelease(_)),
+];
+
+#[cfg(test)]
+#[test]
+fn test_parameter_at() {
+    let p = |x, y| CGPoint { x, y };
+
+    let axial = ShadingGeometry::Axial {
+        start: p(10.0, 0.0),
+        end: p(20.0, 0.0),
+    };
+    assert_eq!(axial.parameter_at(p(15.0, 7.0), false, false), Some(0.5));
+    assert_eq!(axial.parameter_at(p(5.0, 0.0), false, false), None);
+    assert_eq!(axial.parameter_at(p(5.0, 0.0), true, false), Some(0.0));
+    assert_eq!(axial.parameter_at(p(25.0, 0.0), false, true), Some(1.0));
+
+    let radial = ShadingGeometry::Radial {
+        start: p(0.0, 0.0),
+        start_radius: 0.0,
+        end: p(0.0, 0.0),
+        end_radius: 10.0,
+    };
+    assert_eq!(radial.parameter_at(p(0.0, 5.0), false, false), Some(0.5));
+    assert_eq!(radial.parameter_at(p(-3.0, 4.0), false, false), Some(0.5));
+    assert_eq!(radial.parameter_at(p(0.0, 15.0), false, false), None);
+    assert_eq!(radial.parameter_at(p(0.0, 15.0), false, true), Some(1.0));
+
+    // A start circle inside the end circle, off-centre.
+    let radial = ShadingGeometry::Radial {
+        start: p(5.0, 0.0),
+        start_radius: 0.0,
+        end: p(0.0, 0.0),
+        end_radius: 10.0,
+    };
+    assert_eq!(radial.parameter_at(p(5.0, 0.0), false, false), Some(0.0));
+    assert_eq!(radial.parameter_at(p(-10.0, 0.0), false, false), Some(1.0));
+    assert_eq!(radial.parameter_at(p(10.0, 0.0), false, false), Some(1.0));
+}
@@ -0,0 +1,1508 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! Reading and writing PDF files, for `CGPDFDocument` and `CGPDFContext`.
+//!
+//! Only as much of the format as is needed to draw pages is supported. Both
+//! classic cross-reference tables and cross-reference streams are read, and
+//! damaged files are recovered by scanning for objects. Encrypted documents
+//! are not supported.
+//!
+//! Useful resources:
+//! - Adobe's [PDF Reference, sixth edition](https://opensource.adobe.com/dc-acrobat-sdk-docs/pdfstandards/pdfreference1.7old.pdf)
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
+use std::rc::Rc;
+
+pub type Dict = HashMap<String, Object>;
+
+/// A PDF object. Cloning is cheap.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Object {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Real(f32),
+    String(Rc<[u8]>),
+    Name(Rc<str>),
+    Array(Rc<[Object]>),
+    Dict(Rc<Dict>),
+    Stream(Rc<Stream>),
+    /// Indirect reference, by object number. Generation numbers are ignored.
+    Ref(u32),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Stream {
+    pub dict: Dict,
+    /// The stream's data, with its filters not yet applied.
+    pub data: Vec<u8>,
+}
+
+impl Object {
+    pub fn as_number(&self) -> Option<f32> {
+        match *self {
+            Object::Int(i) => Some(i as f32),
+            Object::Real(r) => Some(r),
+            _ => None,
+        }
+    }
+    pub fn as_int(&self) -> Option<i64> {
+        match *self {
+            Object::Int(i) => Some(i),
+            Object::Real(r) => Some(r as i64),
+            _ => None,
+        }
+    }
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Object::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+    pub fn as_name(&self) -> Option<&str> {
+        match self {
+            Object::Name(name) => Some(name),
+            _ => None,
+        }
+    }
+    pub fn as_array(&self) -> Option<&[Object]> {
+        match self {
+            Object::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+    /// Get the dictionary of a dictionary or stream.
+    pub fn as_dict(&self) -> Option<&Dict> {
+        match self {
+            Object::Dict(dict) => Some(dict),
+            Object::Stream(stream) => Some(&stream.dict),
+            _ => None,
+        }
+    }
+    pub fn as_stream(&self) -> Option<&Stream> {
+        match self {
+            Object::Stream(stream) => Some(stream),
+            _ => None,
+        }
+    }
+}
+
+fn is_whitespace(byte: u8) -> bool {
+    matches!(byte, b'\0' | b'\t' | b'\n' | b'\x0c' | b'\r' | b' ')
+}
+fn is_delimiter(byte: u8) -> bool {
+    matches!(
+        byte,
+        b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%'
+    )
+}
+
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    Object(Object),
+    ArrayStart,
+    ArrayEnd,
+    DictStart,
+    DictEnd,
+    /// `obj`, `R`, `true`, a content stream operator, etc.
+    Keyword(&'a [u8]),
+}
+
+struct Lexer<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+impl<'a> Lexer<'a> {
+    fn new(bytes: &'a [u8], pos: usize) -> Self {
+        Lexer { bytes, pos }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(byte) = self.peek() {
+            if is_whitespace(byte) {
+                self.pos += 1;
+            } else if byte == b'%' {
+                while !matches!(self.peek(), None | Some(b'\r' | b'\n')) {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn next_token(&mut self) -> Option<Token<'a>> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let byte = self.peek()?;
+        self.pos += 1;
+        Some(match byte {
+            b'[' => Token::ArrayStart,
+            b']' => Token::ArrayEnd,
+            b'<' if self.peek() == Some(b'<') => {
+                self.pos += 1;
+                Token::DictStart
+            }
+            b'>' if self.peek() == Some(b'>') => {
+                self.pos += 1;
+                Token::DictEnd
+            }
+            b'<' => Token::Object(self.hex_string()),
+            b'(' => Token::Object(self.literal_string()),
+            b'/' => {
+                let name = self.regular_run();
+                Token::Object(Object::Name(decode_name(name).into()))
+            }
+            b'+' | b'-' | b'.' | b'0'..=b'9' => {
+                self.pos = start;
+                let number = self.regular_run();
+                Token::Object(parse_number(number))
+            }
+            // Stray delimiters, e.g. `{` or a lone `>`, are skipped.
+            _ if is_delimiter(byte) => Token::Keyword(&self.bytes[start..self.pos]),
+            _ => {
+                self.pos = start;
+                let keyword = self.regular_run();
+                match keyword {
+                    b"true" => Token::Object(Object::Bool(true)),
+                    b"false" => Token::Object(Object::Bool(false)),
+                    b"null" => Token::Object(Object::Null),
+                    _ => Token::Keyword(keyword),
+                }
+            }
+        })
+    }
+
+    /// Read bytes up to the next whitespace or delimiter.
+    fn regular_run(&mut self) -> &'a [u8] {
+        let start = self.pos;
+        while matches!(self.peek(), Some(byte) if !is_whitespace(byte) && !is_delimiter(byte)) {
+            self.pos += 1;
+        }
+        &self.bytes[start..self.pos]
+    }
+
+    fn hex_string(&mut self) -> Object {
+        let mut string = Vec::new();
+        let mut high = None;
+        while let Some(byte) = self.peek() {
+            self.pos += 1;
+            if byte == b'>' {
+                break;
+            }
+            let Some(digit) = (byte as char).to_digit(16) else {
+                continue;
+            };
+            match high.take() {
+                None => high = Some(digit as u8),
+                Some(high) => string.push((high << 4) | digit as u8),
+            }
+        }
+        // An odd final digit is followed by an implicit 0.
+        if let Some(high) = high {
+            string.push(high << 4);
+        }
+        Object::String(string.into())
+    }
+
+    fn literal_string(&mut self) -> Object {
+        let mut string = Vec::new();
+        let mut depth = 0;
+        while let Some(byte) = self.peek() {
+            self.pos += 1;
+            match byte {
+                b'(' => {
+                    depth += 1;
+                    string.push(byte);
+                }
+                b')' if depth == 0 => break,
+                b')' => {
+                    depth -= 1;
+                    string.push(byte);
+                }
+                b'\\' => {
+                    let Some(escaped) = self.peek() else {
+                        break;
+                    };
+                    self.pos += 1;
+                    match escaped {
+                        b'n' => string.push(b'\n'),
+                        b'r' => string.push(b'\r'),
+                        b't' => string.push(b'\t'),
+                        b'b' => string.push(b'\x08'),
+                        b'f' => string.push(b'\x0c'),
+                        b'0'..=b'7' => {
+                            let mut value = (escaped - b'0') as u32;
+                            for _ in 0..2 {
+                                match self.peek() {
+                                    Some(digit @ b'0'..=b'7') => {
+                                        value = value * 8 + (digit - b'0') as u32;
+                                        self.pos += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+                            string.push(value as u8);
+                        }
+                        // A backslash at the end of a line continues it.
+                        b'\r' => {
+                            if self.peek() == Some(b'\n') {
+                                self.pos += 1;
+                            }
+                        }
+                        b'\n' => (),
+                        _ => string.push(escaped),
+                    }
+                }
+                _ => string.push(byte),
+            }
+        }
+        Object::String(string.into())
+    }
+
+    /// Parse an object. Indirect references are only recognised if
+    /// `allow_refs` is [true], since content streams can't contain them.
+    fn parse_object(&mut self, allow_refs: bool) -> Result<Object, String> {
+        let token = self.next_token().ok_or("unexpected end of data")?;
+        self.parse_object_from(token, allow_refs)
+    }
+
+    fn parse_object_from(&mut self, token: Token<'a>, allow_refs: bool) -> Result<Object, String> {
+        match token {
+            Token::Object(Object::Int(number)) if allow_refs => {
+                // Look ahead for `<number> <generation> R`.
+                let saved_pos = self.pos;
+                if let Some(Token::Object(Object::Int(_))) = self.next_token() {
+                    if let Some(Token::Keyword(b"R")) = self.next_token() {
+                        return Ok(Object::Ref(number as u32));
+                    }
+                }
+                self.pos = saved_pos;
+                Ok(Object::Int(number))
+            }
+            Token::Object(object) => Ok(object),
+            Token::ArrayStart => {
+                let mut array = Vec::new();
+                loop {
+                    match self.next_token().ok_or("unterminated array")? {
+                        Token::ArrayEnd => break,
+                        token => array.push(self.parse_object_from(token, allow_refs)?),
+                    }
+                }
+                Ok(Object::Array(array.into()))
+            }
+            Token::DictStart => Ok(Object::Dict(Rc::new(self.parse_dict_body(allow_refs)?))),
+            Token::ArrayEnd | Token::DictEnd => Err("unexpected end of collection".to_string()),
+            Token::Keyword(keyword) => Err(format!(
+                "unexpected keyword {:?}",
+                String::from_utf8_lossy(keyword)
+            )),
+        }
+    }
+
+    /// Parse the contents of a dictionary, after the `<<`.
+    fn parse_dict_body(&mut self, allow_refs: bool) -> Result<Dict, String> {
+        let mut dict = Dict::new();
+        loop {
+            let key = match self.next_token().ok_or("unterminated dictionary")? {
+                Token::DictEnd => break,
+                Token::Object(Object::Name(key)) => key,
+                token => return Err(format!("bad dictionary key {:?}", token)),
+            };
+            let value = self.parse_object(allow_refs)?;
+            dict.insert(key.to_string(), value);
+        }
+        Ok(dict)
+    }
+}
+
+fn decode_name(name: &[u8]) -> String {
+    let mut decoded = Vec::with_capacity(name.len());
+    let mut i = 0;
+    while i < name.len() {
+        let escaped = (name[i] == b'#')
+            .then(|| name.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        if let Some(byte) = escaped {
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(name[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn parse_number(number: &[u8]) -> Object {
+    let number = std::str::from_utf8(number).unwrap_or("0");
+    if let Ok(int) = number.parse() {
+        Object::Int(int)
+    } else {
+        // Malformed numbers like "--1" or "1.2.3" are treated as zero.
+        Object::Real(number.parse().unwrap_or(0.0))
+    }
+}
+
+/// Parse an indirect object (`1 0 obj ... endobj`) starting at `offset`,
+/// returning its number and value. `get_length` is used to resolve an
+/// indirect `/Length` for a stream; if it can't, the end of the stream is
+/// found by searching for `endstream`.
+fn parse_indirect_object(
+    bytes: &[u8],
+    offset: usize,
+    get_length: &dyn Fn(u32) -> Option<i64>,
+) -> Result<(u32, Object), String> {
+    let mut lexer = Lexer::new(bytes, offset);
+    let (
+        Some(Token::Object(Object::Int(number))),
+        Some(Token::Object(Object::Int(_))),
+        Some(Token::Keyword(b"obj")),
+    ) = (lexer.next_token(), lexer.next_token(), lexer.next_token())
+    else {
+        return Err(format!("no object at offset {}", offset));
+    };
+    let object = lexer.parse_object(true)?;
+
+    let Object::Dict(ref dict) = object else {
+        return Ok((number as u32, object));
+    };
+    if lexer.next_token() != Some(Token::Keyword(b"stream")) {
+        return Ok((number as u32, object));
+    }
+    // The data starts after the end of the line.
+    if lexer.peek() == Some(b'\r') {
+        lexer.pos += 1;
+    }
+    if lexer.peek() == Some(b'\n') {
+        lexer.pos += 1;
+    }
+    let start = lexer.pos;
+
+    let length = match dict.get("Length") {
+        Some(&Object::Int(length)) => Some(length),
+        Some(&Object::Ref(length_ref)) => get_length(length_ref),
+        _ => None,
+    };
+    let length_is_valid = |length: i64| {
+        let Some(end) = usize::try_from(length)
+            .ok()
+            .and_then(|l| start.checked_add(l))
+        else {
+            return false;
+        };
+        let mut after = Lexer::new(bytes, end.min(bytes.len()));
+        end <= bytes.len() && after.next_token() == Some(Token::Keyword(b"endstream"))
+    };
+    let end = match length {
+        Some(length) if length_is_valid(length) => start + length as usize,
+        _ => {
+            let Some(end) = find(&bytes[start..], b"endstream") else {
+                return Err("unterminated stream".to_string());
+            };
+            let mut end = start + end;
+            // Drop the end-of-line marker before the keyword.
+            if end > start && bytes[end - 1] == b'\n' {
+                end -= 1;
+            }
+            if end > start && bytes[end - 1] == b'\r' {
+                end -= 1;
+            }
+            end
+        }
+    };
+    let stream = Stream {
+        dict: (**dict).clone(),
+        data: bytes[start..end].to_vec(),
+    };
+    Ok((number as u32, Object::Stream(Rc::new(stream))))
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+fn rfind(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .rposition(|window| window == needle)
+}
+
+#[derive(Copy, Clone, Debug)]
+enum XrefEntry {
+    /// Byte offset of the object in the file.
+    Offset(usize),
+    /// The object is number `index` in object stream `stream`.
+    Compressed { stream: u32, index: usize },
+}
+
+type Xref = HashMap<u32, XrefEntry>;
+
+/// Read the cross-reference sections of a file, starting from the last one
+/// and following the `/Prev` links to older ones.
+fn read_xref(bytes: &[u8]) -> Result<(Xref, Dict), String> {
+    let startxref = rfind(bytes, b"startxref").ok_or("no startxref")?;
+    let mut lexer = Lexer::new(bytes, startxref + b"startxref".len());
+    let Some(Token::Object(Object::Int(offset))) = lexer.next_token() else {
+        return Err("bad startxref".to_string());
+    };
+
+    let mut xref = Xref::new();
+    let mut trailer: Option<Dict> = None;
+    let mut pending = vec![offset];
+    let mut visited = HashSet::new();
+    while let Some(offset) = pending.pop() {
+        let offset = usize::try_from(offset).map_err(|_| "bad xref offset")?;
+        if offset >= bytes.len() || !visited.insert(offset) {
+            continue;
+        }
+        let mut lexer = Lexer::new(bytes, offset);
+        let section_trailer = if lexer.next_token() == Some(Token::Keyword(b"xref")) {
+            read_xref_table(&mut lexer, &mut xref)?
+        } else {
+            read_xref_stream(bytes, offset, &mut xref)?
+        };
+        // Entries from newer sections take precedence, which is ensured by
+        // only inserting entries that are not yet present. A hybrid file's
+        // `/XRefStm` is newer than the table that refers to it.
+        if let Some(prev) = section_trailer.get("Prev").and_then(Object::as_int) {
+            pending.push(prev);
+        }
+        if let Some(xref_stream) = section_trailer.get("XRefStm").and_then(Object::as_int) {
+            pending.push(xref_stream);
+        }
+        if trailer.is_none() {
+            trailer = Some(section_trailer);
+        }
+    }
+    Ok((xref, trailer.ok_or("no trailer")?))
+}
+
+/// Read a classic cross-reference table, after the `xref` keyword, and the
+/// trailer following it.
+fn read_xref_table(lexer: &mut Lexer, xref: &mut Xref) -> Result<Dict, String> {
+    loop {
+        match lexer.next_token() {
+            Some(Token::Keyword(b"trailer")) => break,
+            Some(Token::Object(Object::Int(first))) => {
+                let Some(Token::Object(Object::Int(count))) = lexer.next_token() else {
+                    return Err("bad xref subsection".to_string());
+                };
+                for number in first..first.saturating_add(count) {
+                    let (
+                        Some(Token::Object(Object::Int(offset))),
+                        Some(Token::Object(Object::Int(_))),
+                        Some(Token::Keyword(kind)),
+                    ) = (lexer.next_token(), lexer.next_token(), lexer.next_token())
+                    else {
+                        return Err("bad xref entry".to_string());
+                    };
+                    if kind == b"n" {
+                        xref.entry(number as u32)
+                            .or_insert(XrefEntry::Offset(offset as usize));
+                    }
+                }
+            }
+            _ => return Err("bad xref table".to_string()),
+        }
+    }
+    let Some(Token::DictStart) = lexer.next_token() else {
+        return Err("bad trailer".to_string());
+    };
+    lexer.parse_dict_body(true)
+}
+
+/// Read a cross-reference stream, returning its dictionary as the trailer.
+fn read_xref_stream(bytes: &[u8], offset: usize, xref: &mut Xref) -> Result<Dict, String> {
+    let (_, object) = parse_indirect_object(bytes, offset, &|_| None)?;
+    let Object::Stream(stream) = object else {
+        return Err("xref is not a stream".to_string());
+    };
+    let widths: Vec<usize> = stream
+        .dict
+        .get("W")
+        .and_then(Object::as_array)
+        .ok_or("xref stream has no /W")?
+        .iter()
+        .map(|width| width.as_int().unwrap_or(0).clamp(0, 8) as usize)
+        .collect();
+    let [type_width, field2_width, field3_width] = widths[..] else {
+        return Err("bad xref stream /W".to_string());
+    };
+    let size = stream
+        .dict
+        .get("Size")
+        .and_then(Object::as_int)
+        .unwrap_or(0);
+    let index: Vec<i64> = match stream.dict.get("Index").and_then(Object::as_array) {
+        Some(index) => index.iter().filter_map(Object::as_int).collect(),
+        None => vec![0, size],
+    };
+    let data = decode_filters(&stream.data, &stream_filters(&stream.dict, &|o| o.clone()))?.data;
+
+    let read_field = |entry: &[u8], start: usize, width: usize| {
+        entry[start..start + width]
+            .iter()
+            .fold(0usize, |value, &byte| (value << 8) | byte as usize)
+    };
+    let entry_size = type_width + field2_width + field3_width;
+    let mut entries = data.chunks_exact(entry_size.max(1));
+    for range in index.chunks_exact(2) {
+        for number in range[0]..range[0].saturating_add(range[1]) {
+            let Some(entry) = entries.next() else {
+                break;
+            };
+            let kind = if type_width == 0 {
+                1
+            } else {
+                read_field(entry, 0, type_width)
+            };
+            let field2 = read_field(entry, type_width, field2_width);
+            let field3 = read_field(entry, type_width + field2_width, field3_width);
+            let entry = match kind {
+                1 => XrefEntry::Offset(field2),
+                2 => XrefEntry::Compressed {
+                    stream: field2 as u32,
+                    index: field3,
+                },
+                _ => continue,
+            };
+            xref.entry(number as u32).or_insert(entry);
+        }
+    }
+    Ok(stream.dict.clone())
+}
+
+/// Rebuild the cross-reference information of a damaged file by looking for
+/// the start of each object.
+fn rebuild_xref(bytes: &[u8]) -> Result<(Xref, Dict), String> {
+    let mut xref = Xref::new();
+    let mut trailer = None;
+    for i in 0..bytes.len() {
+        let at_line_start = i == 0 || matches!(bytes[i - 1], b'\r' | b'\n');
+        if !at_line_start {
+            continue;
+        }
+        if bytes[i].is_ascii_digit() {
+            let mut lexer = Lexer::new(bytes, i);
+            if let (
+                Some(Token::Object(Object::Int(number))),
+                Some(Token::Object(Object::Int(_))),
+                Some(Token::Keyword(b"obj")),
+            ) = (lexer.next_token(), lexer.next_token(), lexer.next_token())
+            {
+                // Later definitions replace earlier ones.
+                xref.insert(number as u32, XrefEntry::Offset(i));
+            }
+        } else if bytes[i..].starts_with(b"trailer") {
+            let mut lexer = Lexer::new(bytes, i + b"trailer".len());
+            if lexer.next_token() == Some(Token::DictStart) {
+                if let Ok(dict) = lexer.parse_dict_body(true) {
+                    trailer = Some(dict);
+                }
+            }
+        }
+    }
+
+    // Files with cross-reference streams have no trailer, and may keep
+    // objects in object streams.
+    let mut object_streams = Vec::new();
+    for (&number, &entry) in &xref {
+        let XrefEntry::Offset(offset) = entry else {
+            unreachable!();
+        };
+        let Ok((_, Object::Stream(stream))) = parse_indirect_object(bytes, offset, &|_| None)
+        else {
+            continue;
+        };
+        match stream.dict.get("Type").and_then(Object::as_name) {
+            Some("XRef") if trailer.is_none() => trailer = Some(stream.dict.clone()),
+            Some("ObjStm") => object_streams.push((number, stream)),
+            _ => (),
+        }
+    }
+    for (stream_number, stream) in object_streams {
+        let Ok(headers) = object_stream_headers(&stream) else {
+            continue;
+        };
+        for (index, (number, _)) in headers.into_iter().enumerate() {
+            xref.entry(number).or_insert(XrefEntry::Compressed {
+                stream: stream_number,
+                index,
+            });
+        }
+    }
+
+    Ok((xref, trailer.ok_or("no trailer found")?))
+}
+
+/// Decode an object stream, returning its data and the number and offset of
+/// each object in it.
+fn object_stream_headers(stream: &Stream) -> Result<Vec<(u32, usize)>, String> {
+    Ok(decode_object_stream(stream)?.1)
+}
+
+#[allow(clippy::type_complexity)]
+fn decode_object_stream(stream: &Stream) -> Result<(Vec<u8>, Vec<(u32, usize)>), String> {
+    let data = decode_filters(&stream.data, &stream_filters(&stream.dict, &|o| o.clone()))?.data;
+    let count = stream.dict.get("N").and_then(Object::as_int).unwrap_or(0);
+    let first = stream
+        .dict
+        .get("First")
+        .and_then(Object::as_int)
+        .unwrap_or(0) as usize;
+    let mut lexer = Lexer::new(&data, 0);
+    let mut headers = Vec::new();
+    for _ in 0..count {
+        let (Some(Token::Object(Object::Int(number))), Some(Token::Object(Object::Int(offset)))) =
+            (lexer.next_token(), lexer.next_token())
+        else {
+            break;
+        };
+        headers.push((number as u32, first + offset as usize));
+    }
+    Ok((data, headers))
+}
+
+/// A parsed PDF file. Objects are parsed when they are first needed.
+pub struct Document {
+    bytes: Vec<u8>,
+    xref: Xref,
+    trailer: Dict,
+    version: (i32, i32),
+    cache: RefCell<HashMap<u32, Object>>,
+}
+
+/// A page of a document, with the attributes it inherits from the page tree
+/// filled in.
+#[derive(Debug)]
+pub struct Page {
+    pub dict: Rc<Dict>,
+    pub resources: Rc<Dict>,
+    pub media_box: [f32; 4],
+    pub crop_box: Option<[f32; 4]>,
+    /// Clockwise rotation in degrees, a multiple of 90.
+    pub rotate: i32,
+}
+
+/// The result of [Document::decode_stream].
+pub struct DecodedStream {
+    pub data: Vec<u8>,
+    /// An image compression filter that still needs to be applied, e.g.
+    /// `DCTDecode` for JPEG data. Such filters are always the last one.
+    pub image_filter: Option<String>,
+}
+
+impl Document {
+    pub fn parse(bytes: Vec<u8>) -> Result<Document, String> {
+        let header_end = bytes.len().min(1024);
+        let header = find(&bytes[..header_end], b"%PDF-").ok_or("not a PDF file")?;
+        let version = std::str::from_utf8(&bytes[header + 5..(header + 8).min(bytes.len())])
+            .ok()
+            .and_then(|version| {
+                let (major, minor) = version.split_once('.')?;
+                Some((major.parse().ok()?, minor.parse().ok()?))
+            })
+            .unwrap_or((1, 0));
+
+        let (xref, trailer) = match read_xref(&bytes) {
+            Ok(xref) if xref.1.contains_key("Root") => xref,
+            result => {
+                if let Err(e) = result {
+                    log!("Warning: damaged PDF cross-reference ({}), recovering", e);
+                }
+                rebuild_xref(&bytes)?
+            }
+        };
+        if trailer.contains_key("Encrypt") {
+            return Err("encrypted documents are not supported".to_string());
+        }
+        Ok(Document {
+            bytes,
+            xref,
+            trailer,
+            version,
+            cache: RefCell::new(HashMap::new()),
+        })
+    }
+
+    pub fn version(&self) -> (i32, i32) {
+        self.version
+    }
+
+    /// Get an object by its number. Missing objects are [Object::Null].
+    pub fn get(&self, number: u32) -> Object {
+        if let Some(object) = self.cache.borrow().get(&number) {
+            return object.clone();
+        }
+        // A damaged file could have an object that depends on itself, e.g. a
+        // stream whose length is a reference to the stream, so make sure any
+        // attempt to get this object while it's being parsed finds
+        // [Object::Null] rather than recursing forever.
+        self.cache.borrow_mut().insert(number, Object::Null);
+        let object = match self.xref.get(&number) {
+            Some(&XrefEntry::Offset(offset)) => {
+                let get_length = |number| self.get(number).as_int();
+                match parse_indirect_object(&self.bytes, offset, &get_length) {
+                    Ok((_, object)) => object,
+                    Err(e) => {
+                        log!("Warning: couldn't parse PDF object {}: {}", number, e);
+                        Object::Null
+                    }
+                }
+            }
+            Some(&XrefEntry::Compressed { stream, .. }) => {
+                self.load_object_stream(stream);
+                // If the object stream was damaged, this is still the
+                // placeholder, so it won't be tried again.
+                self.cache.borrow()[&number].clone()
+            }
+            None => Object::Null,
+        };
+        self.cache.borrow_mut().insert(number, object.clone());
+        object
+    }
+
+    /// Parse all the objects in an object stream that the cross-reference
+    /// information says are there, and put them in the cache.
+    fn load_object_stream(&self, stream_number: u32) {
+        let Object::Stream(stream) = self.get(stream_number) else {
+            return;
+        };
+        let Ok((data, headers)) = decode_object_stream(&stream) else {
+            return;
+        };
+        for (index, (number, offset)) in headers.into_iter().enumerate() {
+            let belongs_here = matches!(
+                self.xref.get(&number),
+                Some(&XrefEntry::Compressed { stream, index: i })
+                    if stream == stream_number && i == index
+            );
+            if !belongs_here || offset >= data.len() {
+                continue;
+            }
+            let object = Lexer::new(&data, offset)
+                .parse_object(true)
+                .unwrap_or(Object::Null);
+            self.cache.borrow_mut().insert(number, object);
+        }
+    }
+
+    /// Follow indirect references until a direct object is reached.
+    pub fn resolve(&self, object: &Object) -> Object {
+        let mut object = object.clone();
+        // Limit the number of steps in case of a reference loop.
+        for _ in 0..32 {
+            let Object::Ref(number) = object else {
+                return object;
+            };
+            object = self.get(number);
+        }
+        Object::Null
+    }
+
+    /// Get the resolved value of a dictionary entry, or [Object::Null].
+    pub fn get_key(&self, dict: &Dict, key: &str) -> Object {
+        dict.get(key)
+            .map_or(Object::Null, |object| self.resolve(object))
+    }
+
+    /// Get a rectangle from a dictionary entry, normalized so that the first
+    /// corner is the bottom-left one.
+    pub fn get_rect(&self, dict: &Dict, key: &str) -> Option<[f32; 4]> {
+        let array = self.get_key(dict, key);
+        let numbers: Vec<f32> = array
+            .as_array()?
+            .iter()
+            .filter_map(|object| self.resolve(object).as_number())
+            .collect();
+        let [x0, y0, x1, y1] = numbers[..] else {
+            return None;
+        };
+        Some([x0.min(x1), y0.min(y1), x0.max(x1), y0.max(y1)])
+    }
+
+    /// Apply a stream's filters to its data, except for image compression.
+    pub fn decode_stream(&self, stream: &Stream) -> Result<DecodedStream, String> {
+        decode_filters(
+            &stream.data,
+            &stream_filters(&stream.dict, &|object| self.resolve(object)),
+        )
+    }
+
+    /// Find all the pages in the document's page tree, in order.
+    pub fn pages(&self) -> Vec<Page> {
+        let root = self.get_key(&self.trailer, "Root");
+        let Some(root) = root.as_dict() else {
+            return Vec::new();
+        };
+        let mut pages = Vec::new();
+        let inherited = Inherited {
+            resources: Rc::new(Dict::new()),
+            media_box: [0.0, 0.0, 612.0, 792.0], // US Letter
+            crop_box: None,
+            rotate: 0,
+        };
+        let mut visited = HashSet::new();
+        self.collect_pages(
+            root.get("Pages").unwrap_or(&Object::Null),
+            &inherited,
+            &mut visited,
+            &mut pages,
+        );
+        pages
+    }
+
+    fn collect_pages(
+        &self,
+        node: &Object,
+        inherited: &Inherited,
+        visited: &mut HashSet<u32>,
+        pages: &mut Vec<Page>,
+    ) {
+        if let Object::Ref(number) = *node {
+            if !visited.insert(number) {
+                return;
+            }
+        }
+        let node = self.resolve(node);
+        let Object::Dict(ref dict) = node else {
+            return;
+        };
+        let inherited = Inherited {
+            resources: match self.get_key(dict, "Resources") {
+                Object::Dict(resources) => resources,
+                _ => inherited.resources.clone(),
+            },
+            media_box: self
+                .get_rect(dict, "MediaBox")
+                .unwrap_or(inherited.media_box),
+            crop_box: self.get_rect(dict, "CropBox").or(inherited.crop_box),
+            rotate: self
+                .get_key(dict, "Rotate")
+                .as_int()
+                .map_or(inherited.rotate, |rotate| {
+                    rotate.rem_euclid(360) as i32 / 90 * 90
+                }),
+        };
+
+        match self.get_key(dict, "Kids") {
+            Object::Array(kids) => {
+                for kid in kids.iter() {
+                    self.collect_pages(kid, &inherited, visited, pages);
+                }
+            }
+            _ => pages.push(Page {
+                dict: dict.clone(),
+                resources: inherited.resources,
+                media_box: inherited.media_box,
+                crop_box: inherited.crop_box,
+                rotate: inherited.rotate,
+            }),
+        }
+    }
+}
+
+/// Page attributes that are inherited from the page tree.
+struct Inherited {
+    resources: Rc<Dict>,
+    media_box: [f32; 4],
+    crop_box: Option<[f32; 4]>,
+    rotate: i32,
+}
+
+/// Get a stream's filters and their parameters, in the order they should be
+/// applied. Abbreviated names from inline images are expanded.
+fn stream_filters(dict: &Dict, resolve: &dyn Fn(&Object) -> Object) -> Vec<(String, Dict)> {
+    let get = |full: &str, abbreviated: &str| {
+        dict.get(full)
+            .or_else(|| dict.get(abbreviated))
+            .map_or(Object::Null, resolve)
+    };
+    let filters = get("Filter", "F");
+    let params = get("DecodeParms", "DP");
+    let as_list = |object: &Object| match object {
+        Object::Array(array) => array.iter().map(resolve).collect(),
+        Object::Null => Vec::new(),
+        _ => vec![object.clone()],
+    };
+    let params = as_list(&params);
+    as_list(&filters)
+        .iter()
+        .enumerate()
+        .filter_map(|(i, filter)| {
+            let name = match filter.as_name()? {
+                "AHx" => "ASCIIHexDecode",
+                "A85" => "ASCII85Decode",
+                "LZW" => "LZWDecode",
+                "Fl" => "FlateDecode",
+                "RL" => "RunLengthDecode",
+                "CCF" => "CCITTFaxDecode",
+                "DCT" => "DCTDecode",
+                name => name,
+            };
+            let params = params
+                .get(i)
+                .and_then(Object::as_dict)
+                .cloned()
+                .unwrap_or_default();
+            Some((name.to_string(), params))
+        })
+        .collect()
+}
+
+fn decode_filters(data: &[u8], filters: &[(String, Dict)]) -> Result<DecodedStream, String> {
+    let mut data = data.to_vec();
+    for (i, (filter, params)) in filters.iter().enumerate() {
+        data = match filter.as_str() {
+            "ASCIIHexDecode" => decode_ascii_hex(&data),
+            "ASCII85Decode" => decode_ascii_85(&data)?,
+            "RunLengthDecode" => decode_run_length(&data),
+            "FlateDecode" => apply_predictor(decode_flate(&data)?, params)?,
+            "LZWDecode" => {
+                let early_change = params.get("EarlyChange").and_then(Object::as_int) != Some(0);
+                apply_predictor(decode_lzw(&data, early_change), params)?
+            }
+            "DCTDecode" | "JPXDecode" | "CCITTFaxDecode" | "JBIG2Decode" => {
+                if i != filters.len() - 1 {
+                    return Err(format!("{} is not the last filter", filter));
+                }
+                return Ok(DecodedStream {
+                    data,
+                    image_filter: Some(filter.clone()),
+                });
+            }
+            _ => return Err(format!("unsupported filter {}", filter)),
+        };
+    }
+    Ok(DecodedStream {
+        data,
+        image_filter: None,
+    })
+}
+
+fn decode_ascii_hex(data: &[u8]) -> Vec<u8> {
+    let mut lexer = Lexer::new(data, 0);
+    let Object::String(string) = lexer.hex_string() else {
+        unreachable!();
+    };
+    string.to_vec()
+}
+
+fn decode_ascii_85(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut decoded = Vec::new();
+    let mut group = [0u8; 5];
+    let mut group_len = 0;
+    let data = data.strip_prefix(b"<~").unwrap_or(data);
+    for &byte in data {
+        match byte {
+            b'~' => break,
+            b'z' if group_len == 0 => decoded.extend_from_slice(&[0; 4]),
+            b'!'..=b'u' => {
+                group[group_len] = byte - b'!';
+                group_len += 1;
+                if group_len == 5 {
+                    let value = group
+                        .iter()
+                        .fold(0u32, |v, &d| v.wrapping_mul(85).wrapping_add(d as u32));
+                    decoded.extend_from_slice(&value.to_be_bytes());
+                    group_len = 0;
+                }
+            }
+            _ if is_whitespace(byte) => (),
+            _ => return Err(format!("bad ASCII85 character {:?}", byte as char)),
+        }
+    }
+    // A partial final group is padded with 'u' and then truncated.
+    if group_len > 1 {
+        for digit in &mut group[group_len..] {
+            *digit = 84;
+        }
+        let value = group
+            .iter()
+            .fold(0u32, |v, &d| v.wrapping_mul(85).wrapping_add(d as u32));
+        decoded.extend_from_slice(&value.to_be_bytes()[..group_len - 1]);
+    }
+    Ok(decoded)
+}
+
+fn decode_run_length(data: &[u8]) -> Vec<u8> {
+    let mut decoded = Vec::new();
+    let mut i = 0;
+    while let Some(&length) = data.get(i) {
+        match length {
+            0..=127 => {
+                let run = &data[(i + 1).min(data.len())..(i + 2 + length as usize).min(data.len())];
+                decoded.extend_from_slice(run);
+                i += 2 + length as usize;
+            }
+            128 => break,
+            _ => {
+                if let Some(&byte) = data.get(i + 1) {
+                    decoded.extend(std::iter::repeat_n(byte, 257 - length as usize));
+                }
+                i += 2;
+            }
+        }
+    }
+    decoded
+}
+
+fn decode_flate(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut decoded = Vec::new();
+    let result = flate2::read::ZlibDecoder::new(data).read_to_end(&mut decoded);
+    // Truncated or slightly corrupt streams are common, so keep whatever could
+    // be decompressed.
+    match result {
+        Err(e) if decoded.is_empty() => Err(format!("FlateDecode failed: {}", e)),
+        _ => Ok(decoded),
+    }
+}
+
+fn decode_lzw(data: &[u8], early_change: bool) -> Vec<u8> {
+    const CLEAR: usize = 256;
+    const END: usize = 257;
+    let mut decoded = Vec::new();
+    let mut table: Vec<Vec<u8>> = Vec::new();
+    let reset = |table: &mut Vec<Vec<u8>>| {
+        table.clear();
+        table.extend((0..=255u8).map(|byte| vec![byte]));
+        table.push(Vec::new()); // CLEAR
+        table.push(Vec::new()); // END
+    };
+    reset(&mut table);
+    let mut code_width = 9;
+    let mut previous: Option<usize> = None;
+    let mut bit_buffer = 0u32;
+    let mut bit_count = 0;
+    for &byte in data {
+        bit_buffer = (bit_buffer << 8) | byte as u32;
+        bit_count += 8;
+        while bit_count >= code_width {
+            let code =
+                ((bit_buffer >> (bit_count - code_width)) & ((1 << code_width) - 1)) as usize;
+            bit_count -= code_width;
+            match code {
+                CLEAR => {
+                    reset(&mut table);
+                    code_width = 9;
+                    previous = None;
+                    continue;
+                }
+                END => return decoded,
+                _ => (),
+            }
+            let entry = match (table.get(code), previous) {
+                (Some(entry), _) => entry.clone(),
+                (None, Some(previous)) if code == table.len() => {
+                    let mut entry = table[previous].clone();
+                    entry.push(table[previous][0]);
+                    entry
+                }
+                _ => return decoded,
+            };
+            decoded.extend_from_slice(&entry);
+            if let Some(previous) = previous {
+                let mut new_entry = table[previous].clone();
+                new_entry.push(entry[0]);
+                table.push(new_entry);
+            }
+            previous = Some(code);
+            let next_size = table.len() + usize::from(early_change);
+            if next_size >= (1 << code_width) && code_width < 12 {
+                code_width += 1;
+            }
+        }
+    }
+    decoded
+}
+
+/// Undo the prediction applied before Flate or LZW compression, as described
+/// by a filter's `/Predictor`, `/Colors`, `/BitsPerComponent` and `/Columns`.
+fn apply_predictor(data: Vec<u8>, params: &Dict) -> Result<Vec<u8>, String> {
+    let get = |key, default| params.get(key).and_then(Object::as_int).unwrap_or(default);
+    let predictor = get("Predictor", 1);
+    if predictor == 1 {
+        return Ok(data);
+    }
+    let colors = get("Colors", 1).clamp(1, 32) as usize;
+    let bits_per_component = get("BitsPerComponent", 8).clamp(1, 16) as usize;
+    let columns = get("Columns", 1).max(1) as usize;
+    let bytes_per_pixel = (colors * bits_per_component).div_ceil(8);
+    let row_len = (colors * bits_per_component * columns).div_ceil(8);
+
+    if predictor == 2 {
+        // TIFF predictor: each component is a difference from the one to its
+        // left.
+        if bits_per_component != 8 {
+            return Err(format!(
+                "TIFF predictor with {} bits per component",
+                bits_per_component
+            ));
+        }
+        let mut data = data;
+        for row in data.chunks_mut(row_len) {
+            for i in bytes_per_pixel..row.len() {
+                row[i] = row[i].wrapping_add(row[i - bytes_per_pixel]);
+            }
+        }
+        return Ok(data);
+    }
+
+    // PNG predictors: each row is preceded by the filter type for that row.
+    let mut decoded = Vec::with_capacity(data.len());
+    let mut previous_row = vec![0u8; row_len];
+    for chunk in data.chunks(row_len + 1) {
+        let filter_type = chunk[0];
+        let mut row = chunk[1..].to_vec();
+        row.resize(row_len, 0);
+        for i in 0..row_len {
+            let left = if i >= bytes_per_pixel {
+                row[i - bytes_per_pixel]
+            } else {
+                0
+            };
+            let up = previous_row[i];
+            let up_left = if i >= bytes_per_pixel {
+                previous_row[i - bytes_per_pixel]
+            } else {
+                0
+            };
+            let prediction = match filter_type {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => {
+                    let estimate = left as i16 + up as i16 - up_left as i16;
+                    let (to_left, to_up, to_up_left) = (
+                        (estimate - left as i16).abs(),
+                        (estimate - up as i16).abs(),
+                        (estimate - up_left as i16).abs(),
+                    );
+                    if to_left <= to_up && to_left <= to_up_left {
+                        left
+                    } else if to_up <= to_up_left {
+                        up
+                    } else {
+                        up_left
+                    }
+                }
+                _ => return Err(format!("bad PNG filter type {}", filter_type)),
+            };
+            row[i] = row[i].wrapping_add(prediction);
+        }
+        decoded.extend_from_slice(&row);
+        previous_row = row;
+    }
+    Ok(decoded)
+}
+
+/// An operation in a content stream.
+#[derive(Debug, PartialEq)]
+pub struct Operation {
+    pub operator: String,
+    pub operands: Vec<Object>,
+}
+
+/// Parse a content stream into its operations. Inline images become a `BI`
+/// operation whose operands are the image's dictionary and data.
+pub fn parse_content(data: &[u8]) -> Vec<Operation> {
+    let mut lexer = Lexer::new(data, 0);
+    let mut operations = Vec::new();
+    let mut operands = Vec::new();
+    while let Some(token) = lexer.next_token() {
+        match token {
+            Token::Keyword(b"BI") => {
+                operands.clear();
+                let Some(operation) = parse_inline_image(&mut lexer) else {
+                    break;
+                };
+                operations.push(operation);
+            }
+            Token::Keyword(operator) => operations.push(Operation {
+                operator: String::from_utf8_lossy(operator).into_owned(),
+                operands: std::mem::take(&mut operands),
+            }),
+            token => match lexer.parse_object_from(token, false) {
+                Ok(object) => operands.push(object),
+                // Skip malformed operands, like real PDF readers do.
+                Err(_) => operands.clear(),
+            },
+        }
+    }
+    operations
+}
+
+/// Parse an inline image, after the `BI`.
+fn parse_inline_image(lexer: &mut Lexer) -> Option<Operation> {
+    let mut dict = Dict::new();
+    loop {
+        match lexer.next_token()? {
+            Token::Keyword(b"ID") => break,
+            Token::Object(Object::Name(key)) => {
+                let value = lexer.parse_object(false).ok()?;
+                dict.insert(key.to_string(), value);
+            }
+            _ => return None,
+        }
+    }
+    // A single whitespace byte separates `ID` from the data, which ends with
+    // `EI` surrounded by whitespace.
+    let start = lexer.pos + 1;
+    let bytes = lexer.bytes;
+    let mut end = start;
+    loop {
+        end += find(bytes.get(end..)?, b"EI")?;
+        let before_ok = end > 0 && is_whitespace(bytes[end - 1]);
+        let after_ok = bytes.get(end + 2).is_none_or(|&byte| is_whitespace(byte));
+        if before_ok && after_ok {
+            break;
+        }
+        end += 2;
+    }
+    lexer.pos = end + 2;
+    let data = bytes.get(start..end - 1).unwrap_or(&[]);
+    Some(Operation {
+        operator: "BI".to_string(),
+        operands: vec![Object::Dict(Rc::new(dict)), Object::String(data.into())],
+    })
+}
+
+/// A page of a PDF being written, which consists of a single image covering
+/// the whole media box.
+pub struct PageImage {
+    /// x and y of the bottom-left and top-right corners.
+    pub media_box: [f32; 4],
+    pub width: u32,
+    pub height: u32,
+    /// 8-bit RGB pixels, with rows in top-to-bottom order.
+    pub rgb: Vec<u8>,
+}
+
+/// Write a PDF file with image pages.
+pub fn write_document(pages: &[PageImage]) -> Vec<u8> {
+    let mut out = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+    let mut offsets = Vec::new();
+    let mut begin_object = |out: &mut Vec<u8>, number: usize| {
+        offsets.push(out.len());
+        debug_assert_eq!(offsets.len(), number);
+        writeln!(out, "{} 0 obj", number).unwrap();
+    };
+
+    // Objects 1 and 2 are the catalog and page tree, then each page has three
+    // objects: the page, its content stream and its image.
+    let page_number = |i: usize| 3 + i * 3;
+    begin_object(&mut out, 1);
+    out.extend_from_slice(b"<< /Type /Catalog /Pages 2 0 R >>\nendobj\n");
+    begin_object(&mut out, 2);
+    let kids: Vec<String> = (0..pages.len())
+        .map(|i| format!("{} 0 R", page_number(i)))
+        .collect();
+    write!(
+        out,
+        "<< /Type /Pages /Kids [{}] /Count {} >>\nendobj\n",
+        kids.join(" "),
+        pages.len()
+    )
+    .unwrap();
+
+    for (i, page) in pages.iter().enumerate() {
+        let number = page_number(i);
+        let [x0, y0, x1, y1] = page.media_box;
+        begin_object(&mut out, number);
+        write!(
+            out,
+            "<< /Type /Page /Parent 2 0 R /MediaBox [{} {} {} {}] \
+             /Resources << /XObject << /Im0 {} 0 R >> >> /Contents {} 0 R >>\nendobj\n",
+            x0,
+            y0,
+            x1,
+            y1,
+            number + 2,
+            number + 1
+        )
+        .unwrap();
+
+        let content = format!("q {} 0 0 {} {} {} cm /Im0 Do Q", x1 - x0, y1 - y0, x0, y0);
+        begin_object(&mut out, number + 1);
+        write!(
+            out,
+            "<< /Length {} >>\nstream\n{}\nendstream\nendobj\n",
+            content.len(),
+            content
+        )
+        .unwrap();
+
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&page.rgb).unwrap();
+        let compressed = encoder.finish().unwrap();
+        begin_object(&mut out, number + 2);
+        write!(
+            out,
+            "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB \
+             /BitsPerComponent 8 /Filter /FlateDecode /Length {} >>\nstream\n",
+            page.width,
+            page.height,
+            compressed.len()
+        )
+        .unwrap();
+        out.extend_from_slice(&compressed);
+        out.extend_from_slice(b"\nendstream\nendobj\n");
+    }
+
+    let xref_offset = out.len();
+    write!(out, "xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1).unwrap();
+    for offset in offsets.iter() {
+        writeln!(out, "{:010} 00000 n ", offset).unwrap();
+    }
+    write!(
+        out,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        offsets.len() + 1,
+        xref_offset
+    )
+    .unwrap();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let content = b"q 1 0 0 RG [1 2] 0 d 0 0 m 10 10 l S Q\n\
+                        BT /F1 12 Tf (a\\(b\\)\\101) Tj <6162 2> Tj [(x) -250 (y)] TJ ET\n\
+                        BI /W 2 /H 1 /BPC 8 /CS /G ID \x00\xff EI Q";
+        let operations = parse_content(content);
+        let operators: Vec<&str> = operations.iter().map(|o| o.operator.as_str()).collect();
+        assert_eq!(
+            operators,
+            ["q", "RG", "d", "m", "l", "S", "Q", "BT", "Tf", "Tj", "Tj", "TJ", "ET", "BI", "Q"]
+        );
+        assert_eq!(
+            operations[1].operands,
+            [Object::Int(1), Object::Int(0), Object::Int(0)]
+        );
+        assert_eq!(
+            operations[9].operands,
+            [Object::String(b"a(b)A".as_slice().into())]
+        );
+        assert_eq!(
+            operations[10].operands,
+            [Object::String(b"ab ".as_slice().into())]
+        );
+        let Object::Dict(ref image_dict) = operations[13].operands[0] else {
+            panic!();
+        };
+        assert_eq!(image_dict.get("W"), Some(&Object::Int(2)));
+        assert_eq!(
+            operations[13].operands[1],
+            Object::String(b"\x00\xff".as_slice().into())
+        );
+
+        // A minimal document, whose page gets its media box from the page
+        // tree. The cross-reference offsets are deliberately wrong, so that
+        // the file has to be recovered.
+        let document = b"%PDF-1.3\n\
+            1 0 obj << /Type /Catalog /Pages 2 0 R >> endobj\n\
+            2 0 obj << /Type /Pages /Kids [3 0 R] /Count 1 /MediaBox [0 0 200 100] >> endobj\n\
+            3 0 obj << /Type /Page /Parent 2 0 R /Contents 4 0 R /Rotate -90 >> endobj\n\
+            4 0 obj << /Length 5 0 R >>\nstream\n0 0 m\nendstream\nendobj\n\
+            5 0 obj 5 endobj\n\
+            xref\n0 6\n0000000000 65535 f \n0000000001 00000 n \n\
+            trailer << /Size 6 /Root 1 0 R >>\nstartxref\n9999\n%%EOF\n";
+        let document = Document::parse(document.to_vec()).unwrap();
+        assert_eq!(document.version(), (1, 3));
+        let pages = document.pages();
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0].media_box, [0.0, 0.0, 200.0, 100.0]);
+        assert_eq!(pages[0].rotate, 270);
+        let contents = document.get_key(&pages[0].dict, "Contents");
+        let decoded = document
+            .decode_stream(contents.as_stream().unwrap())
+            .unwrap();
+        assert_eq!(decoded.data, b"0 0 m");
+
+        // A stream whose length refers to itself must not recurse forever.
+        let document = b"%PDF-1.3\n\
+            1 0 obj << /Type /Catalog /Pages 2 0 R >> endobj\n\
+            2 0 obj << /Length 2 0 R >>\nstream\nabc\nendstream\nendobj\n\
+            trailer << /Size 3 /Root 1 0 R >>\n%%EOF\n";
+        let document = Document::parse(document.to_vec()).unwrap();
+        let stream = document.get(2);
+        let decoded = document.decode_stream(stream.as_stream().unwrap()).unwrap();
+        assert_eq!(decoded.data, b"abc");
+    }
+
+    #[test]
+    fn test_filters() {
+        assert_eq!(decode_ascii_hex(b"48 65 6c6C 6>"), b"Hel\x6c\x60");
+        assert_eq!(
+            decode_ascii_85(b"<~87cURD]i,\"Ebo80~>").unwrap(),
+            b"Hello World!"
+        );
+        assert_eq!(decode_ascii_85(b"z9Q~>").unwrap(), b"\0\0\0\0L");
+        assert_eq!(decode_run_length(b"\x01ab\xfdc\x80"), b"abcccc");
+        // "-----A---B" from the PDF reference's LZW example
+        assert_eq!(
+            decode_lzw(
+                &[0x80, 0x0b, 0x60, 0x50, 0x22, 0x0c, 0x0c, 0x85, 0x01],
+                true
+            ),
+            b"-----A---B"
+        );
+
+        let params: Dict = [
+            ("Predictor".to_string(), Object::Int(12)),
+            ("Columns".to_string(), Object::Int(2)),
+        ]
+        .into_iter()
+        .collect();
+        let data = vec![0, 1, 2, 2, 1, 1, 1, 10, 20, 4, 1, 1];
+        assert_eq!(
+            apply_predictor(data, &params).unwrap(),
+            [1, 2, 2, 3, 10, 30, 11, 31]
+        );
+    }
+
+    #[test]
+    fn test_write_document() {
+        let pages = [PageImage {
+            media_box: [0.0, 0.0, 2.0, 1.0],
+            width: 2,
+            height: 1,
+            rgb: vec![255, 0, 0, 0, 0, 255],
+        }];
+        let document = Document::parse(write_document(&pages)).unwrap();
+        let pages = document.pages();
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0].media_box, [0.0, 0.0, 2.0, 1.0]);
+        let x_objects = document.get_key(&pages[0].resources, "XObject");
+        let image = document.get_key(x_objects.as_dict().unwrap(), "Im0");
+        let decoded = document.decode_stream(image.as_stream().unwrap()).unwrap();
+        assert_eq!(decoded.data, [255, 0, 0, 0, 0, 255]);
+        let contents = document.get_key(&pages[0].dict, "Contents");
+        let decoded = document
+            .decode_stream(contents.as_stream().unwrap())
+            .unwrap();
+        let operators: Vec<String> = parse_content(&decoded.data)
+            .into_iter()
+            .map(|operation| operation.operator)
+            .collect();
+        assert_eq!(operators, ["q", "cm", "Do", "Q"]);
+    }
+}
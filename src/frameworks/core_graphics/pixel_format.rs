@@ -0,0 +1,532 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! Layouts of pixels in memory, as described by a color space, bit depths and
+//! a `CGBitmapInfo`. This is shared by bitmap contexts and images.
+
+use super::cg_color_space::{
+    kCGColorSpaceGenericGray, kCGColorSpaceGenericRGB, CGColorSpaceHostObject, INDEXED_COLOR_SPACE,
+};
+use super::cg_image::{
+    kCGBitmapAlphaInfoMask, kCGBitmapByteOrderMask, kCGBitmapFloatComponents, kCGImageAlphaFirst,
+    kCGImageAlphaLast, kCGImageAlphaNone, kCGImageAlphaNoneSkipFirst, kCGImageAlphaNoneSkipLast,
+    kCGImageAlphaOnly, kCGImageAlphaPremultipliedFirst, kCGImageAlphaPremultipliedLast,
+    kCGImageByteOrder16Big, kCGImageByteOrder16Little, kCGImageByteOrder32Big,
+    kCGImageByteOrder32Little, kCGImageByteOrderDefault, CGBitmapInfo, CGImageAlphaInfo,
+};
+use super::CGFloat;
+use std::rc::Rc;
+
+/// How the color (as opposed to alpha) of a pixel is stored.
+#[derive(Clone, Debug)]
+pub(super) enum ColorModel {
+    Gray,
+    Rgb,
+    /// Each pixel is an index into a table of sRGB colors.
+    Indexed(Rc<[(CGFloat, CGFloat, CGFloat)]>),
+    /// No color at all, for alpha-only formats.
+    None,
+}
+impl ColorModel {
+    /// Get the color model for a color space. [None] means the color space
+    /// can't be used for pixels.
+    pub(super) fn for_color_space(space: Option<&CGColorSpaceHostObject>) -> Option<ColorModel> {
+        let Some(space) = space else {
+            return Some(ColorModel::None);
+        };
+        match space.name {
+            kCGColorSpaceGenericGray => Some(ColorModel::Gray),
+            kCGColorSpaceGenericRGB => Some(ColorModel::Rgb),
+            INDEXED_COLOR_SPACE => Some(ColorModel::Indexed(space.palette.clone().unwrap())),
+            _ => None,
+        }
+    }
+
+    fn color_components(&self) -> u32 {
+        match self {
+            ColorModel::Gray | ColorModel::Indexed(_) => 1,
+            ColorModel::Rgb => 3,
+            ColorModel::None => 0,
+        }
+    }
+}
+
+/// What a part of a pixel is used for.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Slot {
+    /// Color component with this index.
+    Color(usize),
+    Alpha,
+    /// Padding.
+    Skip,
+}
+
+#[derive(Clone, Debug)]
+pub(super) struct PixelFormat {
+    pub(super) model: ColorModel,
+    pub(super) bits_per_component: u32,
+    pub(super) bits_per_pixel: u32,
+    pub(super) alpha_info: CGImageAlphaInfo,
+    pub(super) bitmap_info: CGBitmapInfo,
+    /// Parts of the pixel and their sizes in bits, in the order they are
+    /// found when the pixel is read as a big-endian number.
+    slots: Vec<(Slot, u32)>,
+    /// If the pixel is made of little-endian words, their size in bytes.
+    little_endian_word: Option<usize>,
+    float: bool,
+}
+impl PixelFormat {
+    /// Describe a pixel format, or explain why it isn't valid. If
+    /// `bits_per_pixel` is [None], the smallest size that fits all the
+    /// components is used.
+    pub(super) fn new(
+        model: ColorModel,
+        bits_per_component: u32,
+        bits_per_pixel: Option<u32>,
+        bitmap_info: CGBitmapInfo,
+    ) -> Result<PixelFormat, String> {
+        let alpha_info = bitmap_info & kCGBitmapAlphaInfoMask;
+        let byte_order = bitmap_info & kCGBitmapByteOrderMask;
+        let float = (bitmap_info & kCGBitmapFloatComponents) != 0;
+        if bitmap_info
+            & !(kCGBitmapAlphaInfoMask | kCGBitmapByteOrderMask | kCGBitmapFloatComponents)
+            != 0
+        {
+            return Err(format!("unknown bitmap info flags {:#x}", bitmap_info));
+        }
+
+        let model = match alpha_info {
+            // Any color is ignored.
+            kCGImageAlphaOnly => ColorModel::None,
+            _ if matches!(model, ColorModel::None) => {
+                return Err("only alpha-only formats can have no color space".to_string());
+            }
+            kCGImageAlphaNone | kCGImageAlphaNoneSkipLast | kCGImageAlphaNoneSkipFirst => model,
+            _ if matches!(model, ColorModel::Indexed(_)) => {
+                return Err("indexed formats can't have alpha".to_string());
+            }
+            kCGImageAlphaPremultipliedLast
+            | kCGImageAlphaPremultipliedFirst
+            | kCGImageAlphaLast
+            | kCGImageAlphaFirst => model,
+            _ => return Err(format!("unknown alpha info {}", alpha_info)),
+        };
+
+        if float && bits_per_component != 32 {
+            return Err(format!(
+                "{}-bit float components are unsupported",
+                bits_per_component
+            ));
+        }
+        if !float && !(1..=16).contains(&bits_per_component) {
+            return Err(format!(
+                "{}-bit integer components are unsupported",
+                bits_per_component
+            ));
+        }
+
+        let colors = model.color_components();
+        let has_alpha_slot = !matches!(alpha_info, kCGImageAlphaNone | kCGImageAlphaOnly);
+        let used_bits = (colors + has_alpha_slot as u32) * bits_per_component;
+        let bits_per_pixel = match bits_per_pixel {
+            Some(bits_per_pixel) => bits_per_pixel,
+            // 5-5-5 formats have a single bit of padding.
+            None if bits_per_component == 5 => 16,
+            None if alpha_info == kCGImageAlphaOnly => bits_per_component,
+            None => used_bits,
+        };
+        let skip_bits = match alpha_info {
+            kCGImageAlphaNoneSkipLast | kCGImageAlphaNoneSkipFirst => {
+                let color_bits = colors * bits_per_component;
+                if bits_per_pixel <= color_bits {
+                    return Err("no room for padding in pixel".to_string());
+                }
+                bits_per_pixel - color_bits
+            }
+            _ => bits_per_component,
+        };
+        let content_bits = match alpha_info {
+            kCGImageAlphaNoneSkipLast | kCGImageAlphaNoneSkipFirst => bits_per_pixel,
+            kCGImageAlphaOnly => bits_per_component,
+            _ => used_bits,
+        };
+        if bits_per_pixel < content_bits || bits_per_pixel > 128 {
+            return Err(format!(
+                "{} bits per pixel is wrong for {} bits of components",
+                bits_per_pixel, content_bits
+            ));
+        }
+        if bits_per_pixel % 8 != 0 && bits_per_pixel > 8 {
+            return Err(format!("{} bits per pixel is unaligned", bits_per_pixel));
+        }
+
+        let color_slots = (0..colors as usize).map(|i| (Slot::Color(i), bits_per_component));
+        let mut slots = Vec::new();
+        match alpha_info {
+            kCGImageAlphaPremultipliedFirst | kCGImageAlphaFirst => {
+                slots.push((Slot::Alpha, bits_per_component))
+            }
+            kCGImageAlphaNoneSkipFirst => slots.push((Slot::Skip, skip_bits)),
+            _ => (),
+        }
+        slots.extend(color_slots);
+        match alpha_info {
+            kCGImageAlphaPremultipliedLast | kCGImageAlphaLast | kCGImageAlphaOnly => {
+                slots.push((Slot::Alpha, bits_per_component))
+            }
+            kCGImageAlphaNoneSkipLast => slots.push((Slot::Skip, skip_bits)),
+            _ => (),
+        }
+
+        let little_endian_word = match byte_order {
+            kCGImageByteOrderDefault | kCGImageByteOrder16Big | kCGImageByteOrder32Big => None,
+            kCGImageByteOrder16Little => Some(2),
+            kCGImageByteOrder32Little => Some(4),
+            _ => return Err(format!("unknown byte order {:#x}", byte_order)),
+        };
+        // The byte order only matters for pixels made of whole words.
+        let little_endian_word =
+            little_endian_word.filter(|&size| bits_per_pixel % (size as u32 * 8) == 0);
+        // Floats always need to be read as whole words.
+        if float && (bits_per_pixel % 32 != 0 || little_endian_word == Some(2)) {
+            return Err("float components must be 32-bit words".to_string());
+        }
+
+        Ok(PixelFormat {
+            model,
+            bits_per_component,
+            bits_per_pixel,
+            alpha_info,
+            bitmap_info,
+            slots,
+            little_endian_word,
+            float,
+        })
+    }
+
+    /// The smallest number of bytes a row of pixels can fit in.
+    pub(super) fn min_bytes_per_row(&self, width: u32) -> Option<u32> {
+        let bits = width.checked_mul(self.bits_per_pixel)?;
+        Some(bits.div_ceil(8))
+    }
+
+    /// Whether colors are stored with premultiplied alpha.
+    pub(super) fn is_premultiplied(&self) -> bool {
+        matches!(
+            self.alpha_info,
+            kCGImageAlphaPremultipliedLast | kCGImageAlphaPremultipliedFirst
+        )
+    }
+
+    /// Whether this is the layout [crate::image::Image] uses internally:
+    /// 8-bit RGBA with premultiplied alpha.
+    pub(super) fn is_rgba8(&self) -> bool {
+        matches!(self.model, ColorModel::Rgb)
+            && self.bits_per_component == 8
+            && self.bits_per_pixel == 32
+            && self.alpha_info == kCGImageAlphaPremultipliedLast
+            && self.little_endian_word.is_none()
+            && !self.float
+    }
+
+    fn max_value(&self) -> CGFloat {
+        ((1u64 << self.bits_per_component) - 1) as CGFloat
+    }
+
+    /// Read the pixel at column `x` of a row. The result is sRGB (not linear)
+    /// and only has premultiplied alpha if the format does. Formats without
+    /// alpha have an alpha of 1, and alpha-only formats have a color of black.
+    pub(super) fn read(&self, row: &[u8], x: u32) -> (CGFloat, CGFloat, CGFloat, CGFloat) {
+        let mut colors = [0.0; 3];
+        let mut alpha = 1.0;
+        self.for_each_slot(x, |slot, bit_offset, bits| {
+            let Some(value) = read_bits(row, bit_offset, bits, self.little_endian_word) else {
+                return;
+            };
+            let value = if self.float {
+                f32::from_bits(value)
+            } else {
+                value as CGFloat / self.max_value()
+            };
+            match slot {
+                Slot::Color(i) => colors[i] = value,
+                Slot::Alpha => alpha = value,
+                Slot::Skip => (),
+            }
+        });
+        let (r, g, b) = match self.model {
+            ColorModel::Gray => (colors[0], colors[0], colors[0]),
+            ColorModel::Rgb => (colors[0], colors[1], colors[2]),
+            ColorModel::Indexed(ref palette) => {
+                let index = (colors[0] * self.max_value()).round() as usize;
+                palette
+                    .get(index.min(palette.len().saturating_sub(1)))
+                    .copied()
+                    .unwrap_or((0.0, 0.0, 0.0))
+            }
+            ColorModel::None => (0.0, 0.0, 0.0),
+        };
+        (r, g, b, alpha)
+    }
+
+    /// Write the pixel at column `x` of a row. The color should be in the
+    /// same form [Self::read] returns. Padding is left as it is.
+    pub(super) fn write(
+        &self,
+        row: &mut [u8],
+        x: u32,
+        color: (CGFloat, CGFloat, CGFloat, CGFloat),
+    ) {
+        let (r, g, b, a) = color;
+        let colors = match self.model {
+            ColorModel::Gray => [0.3 * r + 0.59 * g + 0.11 * b, 0.0, 0.0],
+            ColorModel::Rgb => [r, g, b],
+            ColorModel::Indexed(ref palette) => {
+                let distance = |&(pr, pg, pb): &(CGFloat, CGFloat, CGFloat)| {
+                    (pr - r).powi(2) + (pg - g).powi(2) + (pb - b).powi(2)
+                };
+                let index = (0..palette.len())
+                    .min_by(|&i, &j| distance(&palette[i]).total_cmp(&distance(&palette[j])))
+                    .unwrap_or(0);
+                [index as CGFloat / self.max_value(), 0.0, 0.0]
+            }
+            ColorModel::None => [0.0; 3],
+        };
+        self.for_each_slot(x, |slot, bit_offset, bits| {
+            let value = match slot {
+                Slot::Color(i) => colors[i],
+                Slot::Alpha => a,
+                Slot::Skip => return,
+            };
+            let value = if self.float {
+                value.to_bits()
+            } else {
+                (value.clamp(0.0, 1.0) * self.max_value()).round() as u32
+            };
+            write_bits(row, bit_offset, bits, self.little_endian_word, value);
+        });
+    }
+
+    /// Convert rows of pixels in this format to 8-bit RGBA with premultiplied
+    /// alpha, the layout [crate::image::Image] uses. Pixels missing from the
+    /// end of `bytes` are left transparent. `decode`, if given, maps the
+    /// range of each component (colors, then alpha) to a new range.
+    pub(super) fn to_rgba8(
+        &self,
+        bytes: &[u8],
+        dimensions: (u32, u32),
+        bytes_per_row: u32,
+        decode: Option<&[(CGFloat, CGFloat)]>,
+    ) -> Vec<u8> {
+        let (width, height) = dimensions;
+        let mut pixels = vec![0; width as usize * height as usize * 4];
+        if width == 0 {
+            return pixels;
+        }
+        let rows = bytes.chunks(bytes_per_row as usize).take(height as usize);
+        let out_rows = pixels.chunks_exact_mut(width as usize * 4);
+        for (row, out_row) in rows.zip(out_rows) {
+            if row.len() * 8 < width as usize * self.bits_per_pixel as usize {
+                break;
+            }
+            for (x, out_pixel) in out_row.chunks_exact_mut(4).enumerate() {
+                let (mut r, mut g, mut b, mut a) = self.read(row, x as u32);
+                if let Some(decode) = decode {
+                    (r, g, b, a) = self.apply_decode(decode, (r, g, b, a));
+                }
+                if !self.is_premultiplied() {
+                    (r, g, b) = (r * a, g * a, b * a);
+                }
+                for (out, component) in out_pixel.iter_mut().zip([r, g, b, a]) {
+                    *out = (component.clamp(0.0, 1.0) * 255.0).round() as u8;
+                }
+            }
+        }
+        pixels
+    }
+
+    fn apply_decode(
+        &self,
+        decode: &[(CGFloat, CGFloat)],
+        color: (CGFloat, CGFloat, CGFloat, CGFloat),
+    ) -> (CGFloat, CGFloat, CGFloat, CGFloat) {
+        let (r, g, b, a) = color;
+        let map = |i: usize, value: CGFloat| match decode.get(i) {
+            Some(&(min, max)) => min + value * (max - min),
+            None => value,
+        };
+        let has_alpha = !matches!(
+            self.alpha_info,
+            kCGImageAlphaNone | kCGImageAlphaNoneSkipLast | kCGImageAlphaNoneSkipFirst
+        );
+        match self.model {
+            ColorModel::Gray => {
+                let gray = map(0, r);
+                (gray, gray, gray, if has_alpha { map(1, a) } else { a })
+            }
+            ColorModel::Rgb => (
+                map(0, r),
+                map(1, g),
+                map(2, b),
+                if has_alpha { map(3, a) } else { a },
+            ),
+            // TODO: decoding of the indices themselves
+            ColorModel::Indexed(_) => color,
+            ColorModel::None => (r, g, b, map(0, a)),
+        }
+    }
+
+    /// Call `f` with each slot of the pixel at column `x`, its offset in bits
+    /// from the start of the row, and its size in bits.
+    fn for_each_slot(&self, x: u32, mut f: impl FnMut(Slot, usize, u32)) {
+        let mut bit_offset = x as usize * self.bits_per_pixel as usize;
+        for &(slot, bits) in &self.slots {
+            f(slot, bit_offset, bits);
+            bit_offset += bits as usize;
+        }
+    }
+}
+
+/// Map a bit offset within a row (as if the row were big-endian) to the index
+/// of the byte that really contains it.
+fn byte_index(bit_offset: usize, little_endian_word: Option<usize>) -> usize {
+    let byte = bit_offset / 8;
+    match little_endian_word {
+        Some(size) => byte - byte % size + (size - 1 - byte % size),
+        None => byte,
+    }
+}
+
+/// Read `count` bits (at most 32) from a row, most significant first. Returns
+/// [None] if they are beyond the end of the row.
+fn read_bits(
+    row: &[u8],
+    bit_offset: usize,
+    count: u32,
+    little_endian_word: Option<usize>,
+) -> Option<u32> {
+    if (bit_offset + count as usize).div_ceil(8) > row.len() {
+        return None;
+    }
+    let mut value: u32 = 0;
+    let mut bit = bit_offset;
+    let end = bit_offset + count as usize;
+    while bit < end {
+        let byte = row[byte_index(bit, little_endian_word)];
+        // Take as many bits as possible from this byte at once.
+        let start_in_byte = bit % 8;
+        let take = (8 - start_in_byte).min(end - bit);
+        let bits = (byte >> (8 - start_in_byte - take)) & ((1u16 << take) - 1) as u8;
+        value = ((value as u64) << take) as u32 | bits as u32;
+        bit += take;
+    }
+    Some(value)
+}
+
+/// Inverse of [read_bits]. Does nothing if the bits are beyond the end of the
+/// row.
+fn write_bits(
+    row: &mut [u8],
+    bit_offset: usize,
+    count: u32,
+    little_endian_word: Option<usize>,
+    value: u32,
+) {
+    if (bit_offset + count as usize).div_ceil(8) > row.len() {
+        return;
+    }
+    let mut bit = bit_offset;
+    let end = bit_offset + count as usize;
+    while bit < end {
+        let byte = &mut row[byte_index(bit, little_endian_word)];
+        let start_in_byte = bit % 8;
+        let take = (8 - start_in_byte).min(end - bit);
+        let shift = 8 - start_in_byte - take;
+        let mask = (((1u16 << take) - 1) as u8) << shift;
+        let bits = ((value as u64 >> (end - bit - take)) as u8) << shift;
+        *byte = (*byte & !mask) | (bits & mask);
+        bit += take;
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_pixel_format() {
+    let rgb = || ColorModel::Rgb;
+
+    // BGRA, as used by many games for textures.
+    let bgra = PixelFormat::new(
+        rgb(),
+        8,
+        None,
+        kCGImageByteOrder32Little | kCGImageAlphaPremultipliedFirst,
+    )
+    .unwrap();
+    assert_eq!(bgra.bits_per_pixel, 32);
+    let row = [0, 51, 255, 102, 255, 255, 255, 255];
+    assert_eq!(bgra.read(&row, 0), (1.0, 0.2, 0.0, 0.4));
+    let mut written = [255; 8];
+    bgra.write(&mut written, 0, (1.0, 0.2, 0.0, 0.4));
+    assert_eq!(written, row);
+
+    // 5-5-5 with a bit of padding at the start, in 16-bit big-endian words.
+    let rgb555 = PixelFormat::new(rgb(), 5, None, kCGImageAlphaNoneSkipFirst).unwrap();
+    assert_eq!(rgb555.bits_per_pixel, 16);
+    let row = [0b0111_1100, 0b0001_1111];
+    assert_eq!(rgb555.read(&row, 0), (1.0, 0.0, 1.0, 1.0));
+
+    // 4-4-4-4 in 16-bit little-endian words.
+    let rgba4444 = PixelFormat::new(
+        rgb(),
+        4,
+        None,
+        kCGImageByteOrder16Little | kCGImageAlphaPremultipliedLast,
+    )
+    .unwrap();
+    let mut row = [0; 2];
+    rgba4444.write(&mut row, 0, (1.0, 0.0, 0.2, 1.0));
+    assert_eq!(row, [0x3F, 0xF0]);
+
+    // 32-bit float RGBA.
+    let float = PixelFormat::new(
+        rgb(),
+        32,
+        None,
+        kCGBitmapFloatComponents | kCGImageByteOrder32Little | kCGImageAlphaLast,
+    )
+    .unwrap();
+    let mut row = [0; 16];
+    float.write(&mut row, 0, (0.25, 0.5, 0.75, 1.0));
+    assert_eq!(row[..4], 0.25f32.to_le_bytes());
+    assert_eq!(float.read(&row, 0), (0.25, 0.5, 0.75, 1.0));
+
+    // 16-bit gray.
+    let gray16 = PixelFormat::new(ColorModel::Gray, 16, None, kCGImageByteOrder16Big).unwrap();
+    assert_eq!(gray16.read(&[0xFF, 0xFF, 0, 0], 1), (0.0, 0.0, 0.0, 1.0));
+
+    // 1-bit alpha-only masks.
+    let mask = PixelFormat::new(ColorModel::None, 1, None, kCGImageAlphaOnly).unwrap();
+    assert_eq!(mask.read(&[0b0100_0000], 1), (0.0, 0.0, 0.0, 1.0));
+    assert_eq!(mask.read(&[0b0100_0000], 2), (0.0, 0.0, 0.0, 0.0));
+
+    // 4-bit indexed.
+    let palette: Rc<[_]> = Rc::from(vec![(0.0, 0.0, 0.0), (1.0, 0.0, 0.0)]);
+    let indexed = PixelFormat::new(ColorModel::Indexed(palette), 4, None, 0).unwrap();
+    let mut row = [0];
+    indexed.write(&mut row, 1, (0.9, 0.1, 0.0, 1.0));
+    assert_eq!(row, [0x01]);
+    assert_eq!(indexed.read(&row, 1), (1.0, 0.0, 0.0, 1.0));
+
+    // Inverted 8-bit gray with straight alpha, converted for an image.
+    let gray_alpha = PixelFormat::new(ColorModel::Gray, 8, None, kCGImageAlphaLast).unwrap();
+    let decode = [(1.0, 0.0), (0.0, 1.0)];
+    assert_eq!(
+        gray_alpha.to_rgba8(&[0, 255, 255, 0, 0, 0], (1, 3), 2, Some(&decode)),
+        [255, 255, 255, 255, 0, 0, 0, 0, 0, 0, 0, 0]
+    );
+
+    assert!(PixelFormat::new(rgb(), 16, None, kCGBitmapFloatComponents).is_err());
+    assert!(PixelFormat::new(ColorModel::None, 8, None, kCGImageAlphaLast).is_err());
+}
@@ -72,7 +72,7 @@ pub type NSUInteger = u32;
 // this should be equal to NSIntegerMax
 pub const NSNotFound: i32 = 0x7fffffff;
 
-#[derive(Debug)]
+#[derive(Copy, Clone, Debug)]
 #[repr(C, packed)]
 pub struct NSRange {
     pub location: NSUInteger,
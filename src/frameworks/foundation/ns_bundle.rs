@@ -128,20 +128,15 @@ pub const CLASSES: ClassExports = objc_classes! {
 }
 
 - (id)loadNibNamed:(id)name // NSString*
-             owner:(id)_owner
+             owner:(id)owner
            options:(id)options { // NSDictionary<UINibOptionsKey, id> *
-    if !options.is_null() {
-        let options_count: NSUInteger = msg![env; options count];
-        assert!(options_count == 0);
+    let extension = get_static_str(env, "nib");
+    let nib_path: id = msg![env; this pathForResource:name ofType:extension];
+    if nib_path == nil {
+        panic!("Couldn't find nib file {:?} in bundle {:?}", to_rust_string(env, name), this);
     }
-    let name_string = to_rust_string(env, name);
-    let bundle_path = to_rust_string(env, env.objc.borrow::<NSBundleHostObject>(this).bundle_path);
-    let nib_path = format!("{}/{}.nib", bundle_path, name_string);
-    let unarchiver = load_nib_file(env, GuestPathBuf::from(nib_path)).unwrap(); // TODO: Set owner and use options
-    let top_level_objects_key = get_static_str(env, "UINibTopLevelObjectsKey");
-    let top_level_objects = msg![env; unarchiver decodeObjectForKey:top_level_objects_key];
-    release(env, unarchiver);
-    top_level_objects
+    let nib_path = to_rust_string(env, nib_path).into_owned();
+    load_nib_file(env, GuestPathBuf::from(nib_path), owner, options).unwrap()
 }
 
 - (id)resourcePath {
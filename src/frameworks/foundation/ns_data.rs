@@ -244,3 +244,15 @@ pub fn to_rust_slice(env: &mut Environment, data: id) -> &[u8] {
     env.mem
         .bytes_at(borrowed_data.bytes.cast(), borrowed_data.length)
 }
+
+/// Shortcut for host code: creates a new `NSData` with a copy of `bytes`.
+/// The result is retained, like [super::ns_string::from_rust_string].
+pub fn from_rust_slice(env: &mut Environment, bytes: &[u8]) -> id {
+    let length: NSUInteger = bytes.len().try_into().unwrap();
+    let alloc: MutVoidPtr = env.mem.alloc(length);
+    env.mem
+        .bytes_at_mut(alloc.cast(), length)
+        .copy_from_slice(bytes);
+    let data: id = msg_class![env; NSData alloc];
+    msg![env; data initWithBytesNoCopy:alloc length:length]
+}
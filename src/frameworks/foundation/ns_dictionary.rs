@@ -10,7 +10,7 @@ use super::ns_property_list_serialization::{
     deserialize_plist_from_file, NSPropertyListBinaryFormat_v1_0,
 };
 use super::ns_string::{from_rust_string, to_rust_string};
-use super::{ns_string, ns_url, NSUInteger};
+use super::{ns_keyed_unarchiver, ns_string, ns_url, NSUInteger};
 use crate::abi::{CallFromHost, GuestFunction, VaList};
 use crate::frameworks::core_foundation::{CFHashCode, CFIndex};
 use crate::fs::GuestPath;
@@ -428,6 +428,11 @@ pub const CLASSES: ClassExports = objc_classes! {
     env.objc.dealloc_object(this, &mut env.mem)
 }
 
+// NSCoding implementation
+- (id)initWithCoder:(id)coder {
+    init_with_coder(env, this, coder)
+}
+
 - (id)initWithObjectsAndKeys:(id)first_object, ...dots {
     init_with_objects_and_keys(env, this, first_object, dots.start())
 }
@@ -486,6 +491,11 @@ pub const CLASSES: ClassExports = objc_classes! {
     env.objc.dealloc_object(this, &mut env.mem)
 }
 
+// NSCoding implementation
+- (id)initWithCoder:(id)coder {
+    init_with_coder(env, this, coder)
+}
+
 - (id)initWithObjectsAndKeys:(id)first_object, ...dots {
     init_with_objects_and_keys(env, this, first_object, dots.start())
 }
@@ -634,6 +644,22 @@ pub const CLASSES: ClassExports = objc_classes! {
 
 };
 
+/// Shared implementation of `initWithCoder:` for the mutable and immutable
+/// dictionary classes.
+fn init_with_coder(env: &mut Environment, this: id, coder: id) -> id {
+    // FIXME: What if it's not an NSKeyedUnarchiver?
+    let keys_and_objects = ns_keyed_unarchiver::decode_current_dictionary(env, coder);
+    let mut host_object = <DictionaryHostObject as Default>::default();
+    for (key, object) in keys_and_objects {
+        host_object.insert(env, key, object, /* copy_key: */ true);
+        // The keys and objects were retained by the Vec.
+        release(env, key);
+        release(env, object);
+    }
+    *env.objc.borrow_mut(this) = host_object;
+    this
+}
+
 /// Direct constructor for use by host code, similar to
 /// `[[NSDictionary alloc] initWithObjectsAndKeys:]` but without variadics and
 /// with a more intuitive argument order. Unlike [super::ns_array::from_vec],
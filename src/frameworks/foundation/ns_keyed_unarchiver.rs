@@ -11,6 +11,7 @@
 //!   plists, e.g. `plutil -p` or `println!("{:#?}", plist::Value::...);`.
 //! - Apple's [Archives and Serializations Programming Guide](https://developer.apple.com/library/archive/documentation/Cocoa/Conceptual/Archiving/Articles/archives.html)
 
+use super::ns_property_list_serialization::deserialize_plist;
 use super::ns_string::{from_rust_string, get_static_str, to_rust_string};
 use crate::dyld::{ConstantExports, HostConstant};
 use crate::frameworks::core_graphics::{CGPoint, CGRect, CGSize};
@@ -18,7 +19,7 @@ use crate::frameworks::foundation::{NSInteger, NSUInteger};
 use crate::frameworks::uikit::ui_geometry::{
     CGPointFromString, CGRectFromString, CGSizeFromString,
 };
-use crate::mem::ConstVoidPtr;
+use crate::mem::{ConstVoidPtr, MutPtr, MutVoidPtr, Ptr};
 use crate::objc::{
     autorelease, id, msg, msg_class, nil, objc_classes, release, retain, ClassExports, HostObject,
     NSZonePtr,
@@ -121,6 +122,10 @@ pub const CLASSES: ClassExports = objc_classes! {
 // They are all from the NSCoder abstract class and they return default values
 // if the key is unknown.
 
+- (bool)containsValueForKey:(id)key { // NSString *
+    get_value_to_decode_for_key(env, this, key).is_some()
+}
+
 - (bool)decodeBoolForKey:(id)key { // NSString *
     get_value_to_decode_for_key(env, this, key).map_or(
         false,
@@ -129,17 +134,23 @@ pub const CLASSES: ClassExports = objc_classes! {
 }
 
 - (f64)decodeDoubleForKey:(id)key { // NSString *
+    // Whole-number values can be archived as integers.
     get_value_to_decode_for_key(env, this, key).map_or(
         0.0,
-        |value| value.as_real().unwrap()
+        |value| value.as_real().or_else(
+            || value.as_signed_integer().map(|i| i as f64)
+        ).unwrap()
     )
 }
 
 - (f32)decodeFloatForKey:(id)key { // NSString *
     // TODO: Check bounds, raise NSRangeException if it doesn't fit
+    // Whole-number values can be archived as integers.
     get_value_to_decode_for_key(env, this, key).map_or(
         0.0,
-        |value| value.as_real().unwrap()
+        |value| value.as_real().or_else(
+            || value.as_signed_integer().map(|i| i as f64)
+        ).unwrap()
     ) as f32
 }
 
@@ -186,6 +197,30 @@ pub const CLASSES: ClassExports = objc_classes! {
     autorelease(env, object)
 }
 
+- (ConstVoidPtr)decodeBytesForKey:(id)key // NSString*
+                   returnedLength:(MutPtr<NSUInteger>)length_ptr {
+    let Some(bytes) = decode_bytes_for_key(env, this, key) else {
+        if !length_ptr.is_null() {
+            env.mem.write(length_ptr, 0);
+        }
+        return Ptr::null();
+    };
+    // The bytes only need to live as long as the unarchiver, but keeping an
+    // NSData around for the autorelease pool's lifetime is close enough.
+    let length: NSUInteger = bytes.len().try_into().unwrap();
+    let alloc: MutVoidPtr = env.mem.alloc(length);
+    env.mem
+        .bytes_at_mut(alloc.cast(), length)
+        .copy_from_slice(&bytes);
+    let data: id = msg_class![env; NSData alloc];
+    let data: id = msg![env; data initWithBytesNoCopy:alloc length:length];
+    autorelease(env, data);
+    if !length_ptr.is_null() {
+        env.mem.write(length_ptr, length);
+    }
+    alloc.cast_const()
+}
+
 // TODO: add more decode methods
 
 // These come from a category in UIKit's UIGeometry.h
@@ -224,6 +259,12 @@ fn get_value_to_decode_for_key(env: &mut Environment, unarchiver: id, key: id) -
     scope.get(&key)
 }
 
+/// Shortcut for host code that wants the result of `decodeBytesForKey:` without
+/// copying it into guest memory.
+pub fn decode_bytes_for_key(env: &mut Environment, unarchiver: id, key: id) -> Option<Vec<u8>> {
+    get_value_to_decode_for_key(env, unarchiver, key).map(|value| value.as_data().unwrap().to_vec())
+}
+
 /// The core of the implementation: unarchive something by its uid.
 ///
 /// This is recursive in practice: the `initWithCoder:` messages sent by this
@@ -280,10 +321,19 @@ fn unarchive_key(env: &mut Environment, unarchiver: id, key: Uid) -> id {
 
             new_object
         }
+        // The first object is always the string "$null", which stands in for
+        // nil.
+        Value::String(s) if key.get() == 0 && s == "$null" => nil,
         Value::String(s) => {
             let s = s.to_string();
             from_rust_string(env, s)
         }
+        // Numbers and data appear directly, rather than as NSNumber and NSData
+        // objects.
+        Value::Boolean(_) | Value::Integer(_) | Value::Real(_) | Value::Data(_) => {
+            let item = item.clone();
+            deserialize_plist(env, &item)
+        }
         _ => unimplemented!("Unarchive: {:#?}", item),
     };
 
@@ -292,6 +342,40 @@ fn unarchive_key(env: &mut Environment, unarchiver: id, key: Uid) -> id {
     new_object
 }
 
+/// Shortcut for use by `[_touchHLE_NSDictionary initWithCoder:]`.
+///
+/// The keys and objects are to be considered retained by the `Vec`.
+pub fn decode_current_dictionary(env: &mut Environment, unarchiver: id) -> Vec<(id, id)> {
+    let keys_and_objects: Vec<(Uid, Uid)> = {
+        let host_obj = borrow_host_obj(env, unarchiver);
+        let objects = host_obj.plist["$objects"].as_array().unwrap();
+        let item = &objects[host_obj.current_key.unwrap().get() as usize];
+        let item = item.as_dictionary().unwrap();
+        let keys = item["NS.keys"].as_array().unwrap();
+        let objects = item["NS.objects"].as_array().unwrap();
+        assert!(keys.len() == objects.len());
+        keys.iter()
+            .zip(objects.iter())
+            .map(|(key, object)| {
+                (
+                    key.as_uid().copied().unwrap(),
+                    object.as_uid().copied().unwrap(),
+                )
+            })
+            .collect()
+    };
+
+    keys_and_objects
+        .into_iter()
+        .map(|(key, object)| {
+            let key = unarchive_key(env, unarchiver, key);
+            let object = unarchive_key(env, unarchiver, object);
+            // key and object are retained by the Vec
+            (retain(env, key), retain(env, object))
+        })
+        .collect()
+}
+
 /// Shortcut for use by `[_touchHLE_NSArray initWithCoder:]`.
 ///
 /// The objects are to be considered retained by the `Vec`.
@@ -207,6 +207,12 @@ forUndefinedKey:(id)key { // NSString*
         env.objc.debug_all_class_ivars_as_strings(class).join(", "));
 }
 
+// From UIKit's UINibLoadingAdditions category. Sent by the nib loader, see
+// crate::frameworks::uikit::ui_nib.
+- (())awakeFromNib {
+    // default implementation does nothing
+}
+
 - (bool)respondsToSelector:(SEL)selector {
     let class = msg![env; this class];
     env.objc.class_has_method(class, selector)
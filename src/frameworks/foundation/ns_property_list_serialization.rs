@@ -97,7 +97,8 @@ pub(super) fn deserialize_plist_from_file(
     deserialize_plist(env, &root)
 }
 
-fn deserialize_plist(env: &mut Environment, value: &Value) -> id {
+/// The returned object is retained, not autoreleased.
+pub(super) fn deserialize_plist(env: &mut Environment, value: &Value) -> id {
     match value {
         Value::Array(array) => {
             let array = array
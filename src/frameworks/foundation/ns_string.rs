@@ -18,7 +18,8 @@ use super::{
 use crate::abi::VaList;
 use crate::frameworks::core_graphics::{CGFloat, CGPoint, CGRect, CGSize};
 use crate::frameworks::uikit::ui_font::{
-    self, UILineBreakMode, UILineBreakModeWordWrap, UITextAlignment, UITextAlignmentLeft,
+    self, UIBaselineAdjustment, UILineBreakMode, UILineBreakModeWordWrap, UITextAlignment,
+    UITextAlignmentLeft,
 };
 use crate::fs::GuestPath;
 use crate::mach_o::MachO;
@@ -739,6 +740,33 @@ pub const CLASSES: ClassExports = objc_classes! {
     autorelease(env, res)
 }
 
+- (id)stringByReplacingCharactersInRange:(NSRange)range
+                              withString:(id)replacement { // NSString*
+    let len: NSUInteger = msg![env; this length];
+    // TODO: raise NSRangeException instead of panicking
+    assert!(range.location <= len && range.length <= len - range.location);
+    let end = range.location + range.length;
+
+    let mut res_utf16: Utf16String = Vec::with_capacity(len as usize);
+    for_each_code_unit(env, this, |idx, c| {
+        if idx < range.location {
+            res_utf16.push(c);
+        }
+    });
+    for_each_code_unit(env, replacement, |_idx, c| {
+        res_utf16.push(c);
+    });
+    for_each_code_unit(env, this, |idx, c| {
+        if idx >= end {
+            res_utf16.push(c);
+        }
+    });
+
+    let res = msg_class![env; _touchHLE_NSString alloc];
+    *env.objc.borrow_mut(res) = StringHostObject::Utf16(res_utf16);
+    autorelease(env, res)
+}
+
 - (id)stringByTrimmingCharactersInSet:(id)set { // NSCharacterSet*
     let initial_length: NSUInteger = msg![env; this length];
 
@@ -968,6 +996,27 @@ pub const CLASSES: ClassExports = objc_classes! {
     let text = to_rust_string(env, this);
     ui_font::size_with_font(env, font, &text, Some((size, line_break_mode)))
 }
+- (CGSize)sizeWithFont:(id)font // UIFont*
+              forWidth:(CGFloat)width
+         lineBreakMode:(UILineBreakMode)line_break_mode {
+    // TODO: avoid copy
+    let text = to_rust_string(env, this);
+    ui_font::size_with_font_for_width(env, font, &text, width, line_break_mode)
+}
+- (CGSize)sizeWithFont:(id)font // UIFont*
+           minFontSize:(CGFloat)min_font_size
+        actualFontSize:(MutPtr<CGFloat>)actual_font_size
+              forWidth:(CGFloat)width
+         lineBreakMode:(UILineBreakMode)line_break_mode {
+    // TODO: avoid copy
+    let text = to_rust_string(env, this);
+    let font_size = ui_font::font_size_to_fit_width(env, font, &text, width, min_font_size);
+    if !actual_font_size.is_null() {
+        env.mem.write(actual_font_size, font_size);
+    }
+    let font: id = msg![env; font fontWithSize:font_size];
+    ui_font::size_with_font_for_width(env, font, &text, width, line_break_mode)
+}
 
 - (CGSize)drawAtPoint:(CGPoint)point
              withFont:(id)font { // UIFont*
@@ -984,6 +1033,43 @@ pub const CLASSES: ClassExports = objc_classes! {
     let text = to_rust_string(env, this);
     ui_font::draw_at_point(env, font, &text, point, Some((width, line_break_mode)))
 }
+- (CGSize)drawAtPoint:(CGPoint)point
+             forWidth:(CGFloat)width
+             withFont:(id)font // UIFont*
+             fontSize:(CGFloat)font_size
+        lineBreakMode:(UILineBreakMode)line_break_mode
+   baselineAdjustment:(UIBaselineAdjustment)baseline_adjustment {
+    // TODO: avoid copy
+    let text = to_rust_string(env, this);
+    let y_offset =
+        ui_font::baseline_adjustment_offset(env, font, font_size, baseline_adjustment);
+    let font: id = msg![env; font fontWithSize:font_size];
+    let point = CGPoint {
+        x: point.x,
+        y: point.y + y_offset,
+    };
+    ui_font::draw_at_point(env, font, &text, point, Some((width, line_break_mode)))
+}
+- (CGSize)drawAtPoint:(CGPoint)point
+             forWidth:(CGFloat)width
+             withFont:(id)font // UIFont*
+          minFontSize:(CGFloat)min_font_size
+       actualFontSize:(MutPtr<CGFloat>)actual_font_size
+        lineBreakMode:(UILineBreakMode)line_break_mode
+   baselineAdjustment:(UIBaselineAdjustment)baseline_adjustment {
+    // TODO: avoid copy
+    let text = to_rust_string(env, this);
+    let font_size = ui_font::font_size_to_fit_width(env, font, &text, width, min_font_size);
+    if !actual_font_size.is_null() {
+        env.mem.write(actual_font_size, font_size);
+    }
+    msg![env; this drawAtPoint:point
+                      forWidth:width
+                      withFont:font
+                      fontSize:font_size
+                 lineBreakMode:line_break_mode
+            baselineAdjustment:baseline_adjustment]
+}
 
 - (CGSize)drawInRect:(CGRect)rect
             withFont:(id)font { // UIFont*
@@ -97,7 +97,14 @@ pub const CLASSES: ClassExports = objc_classes! {
     }
 
     // FIXME: this should parse the URL
-    assert!(!to_rust_string(env, url).starts_with("file:")); // TODO
+    let url_string = to_rust_string(env, url);
+    if let Some(path) = url_string.strip_prefix("file://") {
+        // TODO: unescaping
+        let path = path.strip_prefix("localhost").unwrap_or(path);
+        let path = from_rust_string(env, path.to_string());
+        *env.objc.borrow_mut(this) = NSURLHostObject::FileURL { ns_string: path, working_directory: env.fs.working_directory().into() };
+        return this;
+    }
     let url: id = msg![env; url copy];
     *env.objc.borrow_mut(this) = NSURLHostObject::OtherURL { ns_string: url };
     this
@@ -118,6 +125,10 @@ pub const CLASSES: ClassExports = objc_classes! {
     }
 }
 
+- (bool)isFileURL {
+    matches!(env.objc.borrow(this), NSURLHostObject::FileURL { .. })
+}
+
 - (id)path {
     match *env.objc.borrow(this) {
         NSURLHostObject::FileURL { ns_string, .. } => ns_string,
@@ -4,13 +4,29 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 //! `NSURLRequest and NSMutableURLRequest`.
+//!
+//! touchHLE has no networking, so requests are only useful for things that
+//! access local files, like `UIWebView`.
 
 use super::{NSTimeInterval, NSUInteger};
-use crate::frameworks::foundation::ns_string::to_rust_string;
-use crate::msg;
-use crate::objc::{id, nil, objc_classes, ClassExports};
+use crate::objc::{
+    autorelease, id, msg, msg_class, nil, objc_classes, release, retain, ClassExports, HostObject,
+    NSZonePtr,
+};
 
 type NSURLRequestCachePolicy = NSUInteger;
+const NSURLRequestUseProtocolCachePolicy: NSURLRequestCachePolicy = 0;
+
+/// Default timeout, in seconds.
+const DEFAULT_TIMEOUT_INTERVAL: NSTimeInterval = 60.0;
+
+struct NSURLRequestHostObject {
+    /// `NSURL*`
+    url: id,
+    cache_policy: NSURLRequestCachePolicy,
+    timeout_interval: NSTimeInterval,
+}
+impl HostObject for NSURLRequestHostObject {}
 
 pub const CLASSES: ClassExports = objc_classes! {
 
@@ -18,26 +34,98 @@ pub const CLASSES: ClassExports = objc_classes! {
 
 @implementation NSURLRequest: NSObject
 
-+ (id)requestWithURL:(id)url
++ (id)allocWithZone:(NSZonePtr)_zone {
+    let host_object = Box::new(NSURLRequestHostObject {
+        url: nil,
+        cache_policy: NSURLRequestUseProtocolCachePolicy,
+        timeout_interval: DEFAULT_TIMEOUT_INTERVAL,
+    });
+    env.objc.alloc_object(this, host_object, &mut env.mem)
+}
+
++ (id)requestWithURL:(id)url { // NSURL*
+    let new: id = msg![env; this alloc];
+    let new: id = msg![env; new initWithURL:url];
+    autorelease(env, new)
+}
+
++ (id)requestWithURL:(id)url // NSURL*
          cachePolicy:(NSURLRequestCachePolicy)cache_policy
      timeoutInterval:(NSTimeInterval)timeout_interval {
-    if url == nil {
-        return nil;
-    }
-    let url_desc: id = msg![env; url description];
-    log!(
-        "TODO: [NSURLRequest requestWithURL:{} cachePolicy:{} timeoutInterval:{}]",
-        to_rust_string(env, url_desc),
-        cache_policy,
-        timeout_interval,
-    );
-    nil
+    let new: id = msg![env; this alloc];
+    let new: id = msg![env; new initWithURL:url
+                                cachePolicy:cache_policy
+                            timeoutInterval:timeout_interval];
+    autorelease(env, new)
+}
+
+- (id)initWithURL:(id)url { // NSURL*
+    msg![env; this initWithURL:url
+                   cachePolicy:NSURLRequestUseProtocolCachePolicy
+               timeoutInterval:DEFAULT_TIMEOUT_INTERVAL]
+}
+
+- (id)initWithURL:(id)url // NSURL*
+      cachePolicy:(NSURLRequestCachePolicy)cache_policy
+  timeoutInterval:(NSTimeInterval)timeout_interval {
+    let url: id = msg![env; url copy];
+    let host_object = env.objc.borrow_mut::<NSURLRequestHostObject>(this);
+    host_object.url = url;
+    host_object.cache_policy = cache_policy;
+    host_object.timeout_interval = timeout_interval;
+    this
+}
+
+- (())dealloc {
+    let url = env.objc.borrow::<NSURLRequestHostObject>(this).url;
+    release(env, url);
+    env.objc.dealloc_object(this, &mut env.mem)
+}
+
+// NSCopying implementation
+- (id)copyWithZone:(NSZonePtr)_zone {
+    retain(env, this)
+}
+
+- (id)URL {
+    env.objc.borrow::<NSURLRequestHostObject>(this).url
+}
+
+- (NSURLRequestCachePolicy)cachePolicy {
+    env.objc.borrow::<NSURLRequestHostObject>(this).cache_policy
+}
+
+- (NSTimeInterval)timeoutInterval {
+    env.objc.borrow::<NSURLRequestHostObject>(this).timeout_interval
 }
 
 @end
 
 @implementation NSMutableURLRequest: NSURLRequest
-//TODO
+
+- (id)copyWithZone:(NSZonePtr)_zone {
+    let &NSURLRequestHostObject { url, cache_policy, timeout_interval } = env.objc.borrow(this);
+    let new: id = msg_class![env; NSURLRequest alloc];
+    msg![env; new initWithURL:url
+                  cachePolicy:cache_policy
+              timeoutInterval:timeout_interval]
+}
+
+- (())setURL:(id)url { // NSURL*
+    let url: id = msg![env; url copy];
+    let host_object = env.objc.borrow_mut::<NSURLRequestHostObject>(this);
+    let old_url = std::mem::replace(&mut host_object.url, url);
+    release(env, old_url);
+}
+
+- (())setCachePolicy:(NSURLRequestCachePolicy)cache_policy {
+    env.objc.borrow_mut::<NSURLRequestHostObject>(this).cache_policy = cache_policy;
+}
+
+- (())setTimeoutInterval:(NSTimeInterval)timeout_interval {
+    env.objc.borrow_mut::<NSURLRequestHostObject>(this).timeout_interval = timeout_interval;
+}
+
 @end
 
 };
@@ -6,6 +6,7 @@
 //! The `NSValue` class cluster, including `NSNumber`.
 
 use super::NSUInteger;
+use crate::frameworks::core_graphics::{CGPoint, CGRect, CGSize};
 use crate::frameworks::foundation::ns_string::from_rust_string;
 use crate::frameworks::foundation::NSInteger;
 use crate::objc::{
@@ -59,14 +60,36 @@ impl NSNumberHostObject {
     impl_AsValue!(as_double, f64);
 }
 
+/// Host object for the private concrete subclass of `NSValue`. Only the
+/// geometry types from UIKit's `NSValue` additions are supported so far.
+#[derive(Debug)]
+#[allow(clippy::enum_variant_names)] // named after the wrapped types
+enum NSValueHostObject {
+    CGPoint(CGPoint),
+    CGSize(CGSize),
+    CGRect(CGRect),
+}
+impl HostObject for NSValueHostObject {}
+
 pub const CLASSES: ClassExports = objc_classes! {
 
 (env, this, _cmd);
 
-// NSValue is an abstract class. None of the things it should provide are
-// implemented here yet (TODO).
+// NSValue is an abstract class. Only the UIKit geometry additions are
+// implemented here so far (TODO: arbitrary types, pointers, ranges).
 @implementation NSValue: NSObject
 
+// These come from a category in UIKit (UIGeometry.h)
++ (id)valueWithCGPoint:(CGPoint)point {
+    new_value(env, NSValueHostObject::CGPoint(point))
+}
++ (id)valueWithCGSize:(CGSize)size {
+    new_value(env, NSValueHostObject::CGSize(size))
+}
++ (id)valueWithCGRect:(CGRect)rect {
+    new_value(env, NSValueHostObject::CGRect(rect))
+}
+
 // NSCopying implementation
 - (id)copyWithZone:(NSZonePtr)_zone {
     retain(env, this)
@@ -74,6 +97,66 @@ pub const CLASSES: ClassExports = objc_classes! {
 
 @end
 
+@implementation _touchHLE_NSValue: NSValue
+
++ (id)allocWithZone:(NSZonePtr)_zone {
+    let host_object = Box::new(NSValueHostObject::CGPoint(CGPoint { x: 0.0, y: 0.0 }));
+    env.objc.alloc_object(this, host_object, &mut env.mem)
+}
+
+- (CGPoint)CGPointValue {
+    match env.objc.borrow(this) {
+        &NSValueHostObject::CGPoint(point) => point,
+        other => panic!("CGPointValue called on NSValue containing {:?}", other),
+    }
+}
+- (CGSize)CGSizeValue {
+    match env.objc.borrow(this) {
+        &NSValueHostObject::CGSize(size) => size,
+        other => panic!("CGSizeValue called on NSValue containing {:?}", other),
+    }
+}
+- (CGRect)CGRectValue {
+    match env.objc.borrow(this) {
+        &NSValueHostObject::CGRect(rect) => rect,
+        other => panic!("CGRectValue called on NSValue containing {:?}", other),
+    }
+}
+
+- (id)description {
+    // Matches the format used by the real UIKit.
+    let desc = match *env.objc.borrow(this) {
+        NSValueHostObject::CGPoint(CGPoint { x, y }) => format!("NSPoint: {{{}, {}}}", x, y),
+        NSValueHostObject::CGSize(CGSize { width, height }) => {
+            format!("NSSize: {{{}, {}}}", width, height)
+        }
+        NSValueHostObject::CGRect(CGRect {
+            origin: CGPoint { x, y },
+            size: CGSize { width, height },
+        }) => format!("NSRect: {{{{{}, {}}}, {{{}, {}}}}}", x, y, width, height),
+    };
+    let desc = from_rust_string(env, desc);
+    autorelease(env, desc)
+}
+
+- (bool)isEqualTo:(id)other {
+    if this == other {
+        return true;
+    }
+    let class: Class = msg_class![env; _touchHLE_NSValue class];
+    if !msg![env; other isKindOfClass:class] {
+        return false;
+    }
+    match (env.objc.borrow(this), env.objc.borrow(other)) {
+        (NSValueHostObject::CGPoint(a), NSValueHostObject::CGPoint(b)) => a == b,
+        (NSValueHostObject::CGSize(a), NSValueHostObject::CGSize(b)) => a == b,
+        (NSValueHostObject::CGRect(a), NSValueHostObject::CGRect(b)) => a == b,
+        _ => false,
+    }
+}
+
+@end
+
 // NSNumber is not an abstract class.
 @implementation NSNumber: NSValue
 
@@ -242,6 +325,12 @@ pub const CLASSES: ClassExports = objc_classes! {
 
 };
 
+fn new_value(env: &mut Environment, host_object: NSValueHostObject) -> id {
+    let value: id = msg_class![env; _touchHLE_NSValue alloc];
+    *env.objc.borrow_mut(value) = host_object;
+    autorelease(env, value)
+}
+
 fn equality_helper(env: &mut Environment, this: id, other: id) -> bool {
     if this == other {
         return true;
@@ -9,12 +9,15 @@
 //! likely to use UIKit in very simple and limited ways, so this implementation
 //! will probably take a lot of shortcuts.
 
-use crate::{msg, Environment};
+use crate::objc::{id, msg, msg_class, release};
+use crate::window::TextInputEvent;
+use crate::Environment;
 use std::time::Instant;
 
 pub mod ui_accelerometer;
 pub mod ui_activity_indicator_view;
 pub mod ui_application;
+pub mod ui_bar_item;
 pub mod ui_color;
 pub mod ui_device;
 pub mod ui_event;
@@ -36,8 +39,10 @@ pub struct State {
     ui_application: ui_application::State,
     ui_color: ui_color::State,
     ui_device: ui_device::State,
-    ui_font: ui_font::State,
     ui_graphics: ui_graphics::State,
+    ui_image: ui_image::State,
+    ui_image_picker_controller: ui_image_picker_controller::State,
+    ui_nib: ui_nib::State,
     ui_screen: ui_screen::State,
     ui_touch: ui_touch::State,
     pub ui_view: ui_view::State,
@@ -50,7 +55,6 @@ pub struct State {
 /// time an accelerometer input is due.
 pub fn handle_events(env: &mut Environment) -> Option<Instant> {
     use crate::window::Event;
-    use crate::window::TextInputEvent;
 
     loop {
         // NSRunLoop will never call this function in headless mode.
@@ -98,27 +102,58 @@ pub fn handle_events(env: &mut Environment) -> Option<Instant> {
                     log!("Ignoring EnterDebugger event: no debugger connected.");
                 }
             }
+            Event::RotateDevice { clockwise } => ui_device::handle_rotation(env, clockwise),
             Event::TextInput(text_event) => {
-                let responder = env.framework_state.uikit.ui_responder.first_responder;
-                let class = msg![env; responder class];
-                let ui_text_field_class = env.objc.get_known_class("UITextField", &mut env.mem);
-                if !responder.is_null() && env.objc.class_is_subclass_of(class, ui_text_field_class)
-                {
-                    match text_event {
-                        TextInputEvent::Text(text) => {
-                            ui_view::ui_control::ui_text_field::handle_text(env, responder, text)
-                        }
-                        TextInputEvent::Backspace => {
-                            ui_view::ui_control::ui_text_field::handle_backspace(env, responder)
-                        }
-                        TextInputEvent::Return => {
-                            ui_view::ui_control::ui_text_field::handle_return(env, responder)
-                        }
-                    }
-                }
+                let pool: id = msg_class![env; NSAutoreleasePool new];
+                handle_text_input(env, text_event);
+                release(env, pool);
+            }
+            Event::KeyboardNavigation(navigation) => {
+                let pool: id = msg_class![env; NSAutoreleasePool new];
+                ui_view::ui_keyboard::handle_navigation(env, navigation);
+                release(env, pool);
             }
         }
     }
 
+    ui_view::layout_views_if_needed(env);
+
     ui_accelerometer::handle_accelerometer(env)
 }
+
+/// Sends text input, from the host keyboard or touchHLE's on-screen keyboard,
+/// to the first responder.
+fn handle_text_input(env: &mut Environment, text_event: TextInputEvent) {
+    let responder = env.framework_state.uikit.ui_responder.first_responder;
+    if responder.is_null() {
+        return;
+    }
+    let class = msg![env; responder class];
+    let ui_text_field_class = env.objc.get_known_class("UITextField", &mut env.mem);
+    let ui_text_view_class = env.objc.get_known_class("UITextView", &mut env.mem);
+    if env.objc.class_is_subclass_of(class, ui_text_field_class) {
+        match text_event {
+            TextInputEvent::Text(text) => {
+                ui_view::ui_control::ui_text_field::handle_text(env, responder, text)
+            }
+            TextInputEvent::Backspace => {
+                ui_view::ui_control::ui_text_field::handle_backspace(env, responder)
+            }
+            TextInputEvent::Return => {
+                ui_view::ui_control::ui_text_field::handle_return(env, responder)
+            }
+            // TODO: caret movement within text fields
+            TextInputEvent::MoveCaret(_) => (),
+        }
+    } else if env.objc.class_is_subclass_of(class, ui_text_view_class) {
+        use ui_view::ui_scroll_view::ui_text_view;
+        match text_event {
+            TextInputEvent::Text(text) => ui_text_view::handle_text(env, responder, text),
+            TextInputEvent::Backspace => ui_text_view::handle_backspace(env, responder),
+            TextInputEvent::Return => ui_text_view::handle_return(env, responder),
+            TextInputEvent::MoveCaret(movement) => {
+                ui_text_view::handle_caret_movement(env, responder, movement)
+            }
+        }
+    }
+}
@@ -90,11 +90,7 @@ pub const CLASSES: ClassExports = objc_classes! {
 }
 
 - (UIInterfaceOrientation)statusBarOrientation {
-    match env.window().current_rotation() {
-        DeviceOrientation::Portrait => UIDeviceOrientationPortrait,
-        DeviceOrientation::LandscapeLeft => UIDeviceOrientationLandscapeLeft,
-        DeviceOrientation::LandscapeRight => UIDeviceOrientationLandscapeRight
-    }
+    orientation_from_device_orientation(env.window().current_rotation())
 }
 - (())setStatusBarOrientation:(UIInterfaceOrientation)orientation {
     env.window_mut().rotate_device(match orientation {
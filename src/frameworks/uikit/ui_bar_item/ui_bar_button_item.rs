@@ -0,0 +1,248 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! `UIBarButtonItem`.
+
+use crate::frameworks::core_graphics::CGFloat;
+use crate::frameworks::foundation::ns_string::get_static_str;
+use crate::frameworks::foundation::NSInteger;
+use crate::objc::{
+    id, impl_HostObject_with_superclass, msg, msg_send, msg_super, nil, objc_classes, release,
+    retain, ClassExports, NSZonePtr, SEL,
+};
+use crate::Environment;
+
+pub type UIBarButtonItemStyle = NSInteger;
+pub const UIBarButtonItemStylePlain: UIBarButtonItemStyle = 0;
+pub const UIBarButtonItemStyleBordered: UIBarButtonItemStyle = 1;
+pub const UIBarButtonItemStyleDone: UIBarButtonItemStyle = 2;
+
+pub type UIBarButtonSystemItem = NSInteger;
+const UIBarButtonSystemItemDone: UIBarButtonSystemItem = 0;
+pub const UIBarButtonSystemItemCancel: UIBarButtonSystemItem = 1;
+const UIBarButtonSystemItemEdit: UIBarButtonSystemItem = 2;
+const UIBarButtonSystemItemSave: UIBarButtonSystemItem = 3;
+const UIBarButtonSystemItemAdd: UIBarButtonSystemItem = 4;
+pub const UIBarButtonSystemItemFlexibleSpace: UIBarButtonSystemItem = 5;
+pub const UIBarButtonSystemItemFixedSpace: UIBarButtonSystemItem = 6;
+const UIBarButtonSystemItemUndo: UIBarButtonSystemItem = 21;
+const UIBarButtonSystemItemRedo: UIBarButtonSystemItem = 22;
+
+pub struct UIBarButtonItemHostObject {
+    superclass: super::UIBarItemHostObject,
+    style: UIBarButtonItemStyle,
+    /// Weak reference, like `UIControl` targets.
+    target: id,
+    action: Option<SEL>,
+    width: CGFloat,
+    /// `UIView*`
+    custom_view: id,
+    system_item: Option<UIBarButtonSystemItem>,
+}
+impl_HostObject_with_superclass!(UIBarButtonItemHostObject);
+impl Default for UIBarButtonItemHostObject {
+    fn default() -> Self {
+        UIBarButtonItemHostObject {
+            superclass: Default::default(),
+            style: UIBarButtonItemStylePlain,
+            target: nil,
+            action: None,
+            width: 0.0,
+            custom_view: nil,
+            system_item: None,
+        }
+    }
+}
+
+/// Title to use for a system item, since touchHLE doesn't have the icons.
+fn system_item_title(system_item: UIBarButtonSystemItem) -> &'static str {
+    match system_item {
+        UIBarButtonSystemItemDone => "Done",
+        UIBarButtonSystemItemCancel => "Cancel",
+        UIBarButtonSystemItemEdit => "Edit",
+        UIBarButtonSystemItemSave => "Save",
+        UIBarButtonSystemItemAdd => "+",
+        UIBarButtonSystemItemFlexibleSpace | UIBarButtonSystemItemFixedSpace => "",
+        UIBarButtonSystemItemUndo => "Undo",
+        UIBarButtonSystemItemRedo => "Redo",
+        _ => {
+            log!("TODO: Icon for UIBarButtonSystemItem {}", system_item);
+            "?"
+        }
+    }
+}
+
+/// Shortcut for host code (e.g. `UIToolbar`) that needs to know whether an item
+/// is a flexible or fixed space.
+pub fn system_item(env: &Environment, item: id) -> Option<UIBarButtonSystemItem> {
+    env.objc
+        .borrow::<UIBarButtonItemHostObject>(item)
+        .system_item
+}
+
+fn set_target_and_action(env: &mut Environment, this: id, target: id, action: SEL) {
+    let host_obj = env.objc.borrow_mut::<UIBarButtonItemHostObject>(this);
+    host_obj.target = target;
+    host_obj.action = (!action.is_null()).then_some(action);
+}
+
+pub const CLASSES: ClassExports = objc_classes! {
+
+(env, this, _cmd);
+
+@implementation UIBarButtonItem: UIBarItem
+
++ (id)allocWithZone:(NSZonePtr)_zone {
+    let host_object = Box::<UIBarButtonItemHostObject>::default();
+    env.objc.alloc_object(this, host_object, &mut env.mem)
+}
+
+- (id)initWithTitle:(id)title // NSString*
+              style:(UIBarButtonItemStyle)style
+             target:(id)target
+             action:(SEL)action {
+    () = msg![env; this setTitle:title];
+    env.objc.borrow_mut::<UIBarButtonItemHostObject>(this).style = style;
+    set_target_and_action(env, this, target, action);
+    this
+}
+
+- (id)initWithImage:(id)image // UIImage*
+              style:(UIBarButtonItemStyle)style
+             target:(id)target
+             action:(SEL)action {
+    () = msg![env; this setImage:image];
+    env.objc.borrow_mut::<UIBarButtonItemHostObject>(this).style = style;
+    set_target_and_action(env, this, target, action);
+    this
+}
+
+- (id)initWithBarButtonSystemItem:(UIBarButtonSystemItem)system_item
+                           target:(id)target
+                           action:(SEL)action {
+    let title = get_static_str(env, system_item_title(system_item));
+    () = msg![env; this setTitle:title];
+    let host_obj = env.objc.borrow_mut::<UIBarButtonItemHostObject>(this);
+    host_obj.system_item = Some(system_item);
+    host_obj.style = if system_item == UIBarButtonSystemItemDone {
+        UIBarButtonItemStyleDone
+    } else {
+        UIBarButtonItemStyleBordered
+    };
+    set_target_and_action(env, this, target, action);
+    this
+}
+
+- (id)initWithCustomView:(id)custom_view { // UIView*
+    () = msg![env; this setCustomView:custom_view];
+    this
+}
+
+- (id)initWithCoder:(id)coder {
+    let this: id = msg_super![env; this initWithCoder:coder];
+
+    let key = get_static_str(env, "UIStyle");
+    let style: UIBarButtonItemStyle = msg![env; coder decodeIntegerForKey:key];
+    let key = get_static_str(env, "UIWidth");
+    let width: CGFloat = msg![env; coder decodeFloatForKey:key];
+    let key = get_static_str(env, "UICustomView");
+    let custom_view: id = msg![env; coder decodeObjectForKey:key];
+    let key = get_static_str(env, "UIIsSystemItem");
+    let is_system_item: bool = msg![env; coder decodeBoolForKey:key];
+    let system_item = if is_system_item {
+        let key = get_static_str(env, "UISystemItemIdentifier");
+        let system_item: UIBarButtonSystemItem = msg![env; coder decodeIntegerForKey:key];
+        let title = get_static_str(env, system_item_title(system_item));
+        () = msg![env; this setTitle:title];
+        Some(system_item)
+    } else {
+        None
+    };
+
+    () = msg![env; this setCustomView:custom_view];
+    let host_obj = env.objc.borrow_mut::<UIBarButtonItemHostObject>(this);
+    host_obj.style = style;
+    host_obj.width = width;
+    host_obj.system_item = system_item;
+    // The target and action are connected separately by the nib loader.
+    this
+}
+
+- (())dealloc {
+    let &UIBarButtonItemHostObject { custom_view, .. } = env.objc.borrow(this);
+    release(env, custom_view);
+    msg_super![env; this dealloc]
+}
+
+- (UIBarButtonItemStyle)style {
+    env.objc.borrow::<UIBarButtonItemHostObject>(this).style
+}
+- (())setStyle:(UIBarButtonItemStyle)style {
+    env.objc.borrow_mut::<UIBarButtonItemHostObject>(this).style = style;
+}
+
+- (CGFloat)width {
+    env.objc.borrow::<UIBarButtonItemHostObject>(this).width
+}
+- (())setWidth:(CGFloat)width {
+    env.objc.borrow_mut::<UIBarButtonItemHostObject>(this).width = width;
+}
+
+- (id)customView {
+    env.objc.borrow::<UIBarButtonItemHostObject>(this).custom_view
+}
+- (())setCustomView:(id)custom_view { // UIView*
+    retain(env, custom_view);
+    let host_obj = env.objc.borrow_mut::<UIBarButtonItemHostObject>(this);
+    let old_view = std::mem::replace(&mut host_obj.custom_view, custom_view);
+    release(env, old_view);
+}
+
+- (id)target {
+    env.objc.borrow::<UIBarButtonItemHostObject>(this).target
+}
+- (())setTarget:(id)target {
+    env.objc.borrow_mut::<UIBarButtonItemHostObject>(this).target = target;
+}
+
+- (SEL)action {
+    let action = env.objc.borrow::<UIBarButtonItemHostObject>(this).action;
+    action.unwrap_or(SEL::null())
+}
+- (())setAction:(SEL)action {
+    let host_obj = env.objc.borrow_mut::<UIBarButtonItemHostObject>(this);
+    host_obj.action = (!action.is_null()).then_some(action);
+}
+
+// Used as the action of the buttons that bars create to represent items.
+- (())_touchHLE_sendActionFromButton:(id)button { // UIButton*
+    let &UIBarButtonItemHostObject { target, action, .. } = env.objc.borrow(this);
+    let Some(action) = action else {
+        return;
+    };
+    if !msg![env; this isEnabled] {
+        return;
+    }
+    if target == nil {
+        // UIControl knows how to find a target in the responder chain.
+        // TODO: the sender should be the bar button item, not the button
+        () = msg![env; button sendAction:action to:nil forEvent:nil];
+        return;
+    }
+
+    let sel_str = action.as_str(&env.mem);
+    log_dbg!("UIBarButtonItem {:?} sending {} to {:?}", this, sel_str, target);
+    if sel_str.ends_with(':') {
+        // - (IBAction)action:(id)sender;
+        () = msg_send(env, (target, action, this));
+    } else {
+        // - (IBAction)action;
+        () = msg_send(env, (target, action));
+    }
+}
+
+@end
+
+};
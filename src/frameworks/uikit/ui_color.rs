@@ -7,12 +7,17 @@
 
 use super::ui_graphics::UIGraphicsGetCurrentContext;
 use crate::frameworks::core_graphics::cg_color::{CGColorRef, CGColorRelease, CGColorRetain};
-use crate::frameworks::core_graphics::cg_context::CGContextSetRGBFillColor;
+use crate::frameworks::core_graphics::cg_context::{
+    CGContextSetRGBFillColor, CGContextSetRGBStrokeColor,
+};
 use crate::frameworks::core_graphics::{cg_color, CGFloat};
+use crate::frameworks::foundation::ns_keyed_unarchiver::decode_bytes_for_key;
+use crate::frameworks::foundation::ns_string::{get_static_str, to_rust_string};
+use crate::frameworks::foundation::NSInteger;
 use crate::mem::MutPtr;
 use crate::objc::{
-    autorelease, id, msg, msg_class, nil, objc_classes, ClassExports, HostObject, NSZonePtr, ObjC,
-    SEL,
+    autorelease, id, msg, msg_class, nil, objc_classes, release, retain, ClassExports, HostObject,
+    NSZonePtr, ObjC, SEL,
 };
 use crate::Environment;
 use std::collections::HashMap;
@@ -130,6 +135,96 @@ pub const CLASSES: ClassExports = objc_classes! {
     this
 }
 
+// NSCoding implementation
+- (id)initWithCoder:(id)coder {
+    // Interface Builder refers to standard colors by name.
+    let key = get_static_str(env, "UISystemColorName");
+    let name: id = msg![env; coder decodeObjectForKey:key];
+    if name != nil {
+        let name = to_rust_string(env, name);
+        let standard_color: id = match &*name {
+            "clearColor" => msg_class![env; UIColor clearColor],
+            "blackColor" => msg_class![env; UIColor blackColor],
+            "whiteColor" => msg_class![env; UIColor whiteColor],
+            "darkGrayColor" => msg_class![env; UIColor darkGrayColor],
+            "grayColor" => msg_class![env; UIColor grayColor],
+            "lightGrayColor" => msg_class![env; UIColor lightGrayColor],
+            "blueColor" => msg_class![env; UIColor blueColor],
+            "brownColor" => msg_class![env; UIColor brownColor],
+            "cyanColor" => msg_class![env; UIColor cyanColor],
+            "greenColor" => msg_class![env; UIColor greenColor],
+            "magentaColor" => msg_class![env; UIColor magentaColor],
+            "orangeColor" => msg_class![env; UIColor orangeColor],
+            "purpleColor" => msg_class![env; UIColor purpleColor],
+            "redColor" => msg_class![env; UIColor redColor],
+            "yellowColor" => msg_class![env; UIColor yellowColor],
+            // Colors like groupTableViewBackgroundColor also have their
+            // components archived, which are used below.
+            _ => nil,
+        };
+        if standard_color != nil {
+            release(env, this);
+            return retain(env, standard_color);
+        }
+    }
+
+    let key = get_static_str(env, "UIColorComponentCount");
+    if msg![env; coder containsValueForKey:key] {
+        let component_count: NSInteger = msg![env; coder decodeIntegerForKey:key];
+        let key = get_static_str(env, "UIAlpha");
+        let a: CGFloat = msg![env; coder decodeFloatForKey:key];
+        if component_count == 2 {
+            let key = get_static_str(env, "UIWhite");
+            let w: CGFloat = msg![env; coder decodeFloatForKey:key];
+            return msg![env; this initWithWhite:w alpha:a];
+        }
+        let key = get_static_str(env, "UIRed");
+        let r: CGFloat = msg![env; coder decodeFloatForKey:key];
+        let key = get_static_str(env, "UIGreen");
+        let g: CGFloat = msg![env; coder decodeFloatForKey:key];
+        let key = get_static_str(env, "UIBlue");
+        let b: CGFloat = msg![env; coder decodeFloatForKey:key];
+        return msg![env; this initWithRed:r green:g blue:b alpha:a];
+    }
+
+    // Older archives use NSColor's format, where the components are a string
+    // of decimal numbers separated by spaces.
+    let key = get_static_str(env, "NSColorSpace");
+    let color_space: NSInteger = msg![env; coder decodeIntegerForKey:key];
+    let components_key = match color_space {
+        1 | 2 => "NSRGB",
+        3 | 4 => "NSWhite",
+        _ => {
+            log!(
+                "Warning: UIColor archive with unsupported NSColorSpace {}, using clear color",
+                color_space
+            );
+            return msg![env; this initWithWhite:0.0 alpha:0.0];
+        }
+    };
+    let key = get_static_str(env, components_key);
+    let bytes = decode_bytes_for_key(env, coder, key).unwrap_or_default();
+    let components = String::from_utf8_lossy(&bytes);
+    let components = components.trim_end_matches('\0');
+    let parsed: Option<Vec<CGFloat>> = components
+        .split_ascii_whitespace()
+        .map(|component| component.parse().ok())
+        .collect();
+    match (color_space, parsed.as_deref()) {
+        (1 | 2, Some(&[r, g, b])) => msg![env; this initWithRed:r green:g blue:b alpha:1.0],
+        (1 | 2, Some(&[r, g, b, a])) => msg![env; this initWithRed:r green:g blue:b alpha:a],
+        (3 | 4, Some(&[w])) => msg![env; this initWithWhite:w alpha:1.0],
+        (3 | 4, Some(&[w, a])) => msg![env; this initWithWhite:w alpha:a],
+        _ => {
+            log!(
+                "Warning: bad UIColor archive components {:?}, using clear color",
+                components
+            );
+            msg![env; this initWithWhite:0.0 alpha:0.0]
+        }
+    }
+}
+
 - (bool)getRed:(MutPtr<CGFloat>)r
          green:(MutPtr<CGFloat>)g
           blue:(MutPtr<CGFloat>)b
@@ -144,8 +239,8 @@ pub const CLASSES: ClassExports = objc_classes! {
 }
 
 - (())set {
-    msg![env; this setFill]
-    // TODO: set stroke color as well
+    () = msg![env; this setFill];
+    msg![env; this setStroke]
 }
 
 - (())setFill {
@@ -155,6 +250,13 @@ pub const CLASSES: ClassExports = objc_classes! {
     CGContextSetRGBFillColor(env, context, r, g, b, a);
 }
 
+- (())setStroke {
+    let context = UIGraphicsGetCurrentContext(env);
+    assert_ne!(context, nil);
+    let (r, g, b, a) = get_rgba(&env.objc, this);
+    CGContextSetRGBStrokeColor(env, context, r, g, b, a);
+}
+
 - (CGColorRef)CGColor {
     env.objc.borrow::<UIColorHostObject>(this).cg_color
 }
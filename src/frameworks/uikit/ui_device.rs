@@ -5,12 +5,17 @@
  */
 //! `UIDevice`.
 
+use super::ui_view::get_view_controller;
 use crate::dyld::ConstantExports;
 use crate::dyld::HostConstant;
+use crate::frameworks::core_graphics::{CGPoint, CGRect, CGSize};
 use crate::frameworks::foundation::ns_string;
-use crate::frameworks::foundation::NSInteger;
-use crate::objc::{id, objc_classes, ClassExports, TrivialHostObject};
+use crate::frameworks::foundation::{NSInteger, NSTimeInterval, NSUInteger};
+use crate::objc::{
+    id, msg, msg_class, nil, objc_classes, release, ClassExports, TrivialHostObject,
+};
 use crate::window::DeviceOrientation;
+use crate::Environment;
 
 pub const UIDeviceOrientationDidChangeNotification: &str =
     "UIDeviceOrientationDidChangeNotification";
@@ -31,6 +36,126 @@ pub const UIDeviceOrientationFaceDown: UIDeviceOrientation = 6;
 #[derive(Default)]
 pub struct State {
     current_device: Option<id>,
+    /// The orientation the user has rotated the virtual device to. This can
+    /// differ from the window's rotation (the interface orientation) if the
+    /// app doesn't support the new orientation. [None] until the first
+    /// rotation, in which case the window's rotation is used.
+    orientation: Option<DeviceOrientation>,
+    /// Number of `beginGeneratingDeviceOrientationNotifications` calls not yet
+    /// balanced by `endGeneratingDeviceOrientationNotifications`.
+    generating_notifications: u32,
+}
+
+pub fn orientation_from_device_orientation(orientation: DeviceOrientation) -> UIDeviceOrientation {
+    match orientation {
+        DeviceOrientation::Portrait => UIDeviceOrientationPortrait,
+        DeviceOrientation::LandscapeLeft => UIDeviceOrientationLandscapeLeft,
+        DeviceOrientation::LandscapeRight => UIDeviceOrientationLandscapeRight,
+    }
+}
+
+/// How long the real UIKit's rotation animation takes.
+const ROTATION_DURATION: NSTimeInterval = 0.3;
+
+/// For use by [super::handle_events]: the user wants to rotate the virtual
+/// device.
+///
+/// This posts `UIDeviceOrientationDidChangeNotification` if the app asked for
+/// it, and then asks the view controller of the key window (the one whose view
+/// is the window's first subview, as was the convention before
+/// `rootViewController` existed) whether the interface should follow.
+pub(super) fn handle_rotation(env: &mut Environment, clockwise: bool) {
+    let old_orientation = env
+        .framework_state
+        .uikit
+        .ui_device
+        .orientation
+        .unwrap_or_else(|| env.window().current_rotation());
+    let new_orientation = old_orientation.rotated(clockwise);
+    env.framework_state.uikit.ui_device.orientation = Some(new_orientation);
+    log!(
+        "Rotating device {} to {:?}",
+        if clockwise {
+            "clockwise"
+        } else {
+            "counterclockwise"
+        },
+        new_orientation
+    );
+
+    // UIKit creates and drains autorelease pools when handling events.
+    let pool: id = msg_class![env; NSAutoreleasePool new];
+
+    if env.framework_state.uikit.ui_device.generating_notifications > 0 {
+        let device: id = msg_class![env; UIDevice currentDevice];
+        let name = ns_string::get_static_str(env, UIDeviceOrientationDidChangeNotification);
+        let center: id = msg_class![env; NSNotificationCenter defaultCenter];
+        () = msg![env; center postNotificationName:name object:device];
+    }
+
+    let view_controller = root_view_controller(env);
+    if view_controller != nil {
+        let app: id = msg_class![env; UIApplication sharedApplication];
+        let from: UIDeviceOrientation = msg![env; app statusBarOrientation];
+        let to = orientation_from_device_orientation(new_orientation);
+        let should_rotate: bool =
+            from != to && msg![env; view_controller shouldAutorotateToInterfaceOrientation:to];
+        if should_rotate {
+            () = msg![env; view_controller willRotateToInterfaceOrientation:to
+                                                                    duration:ROTATION_DURATION];
+            () = msg![env; view_controller willAnimateRotationToInterfaceOrientation:to
+                                                                             duration:ROTATION_DURATION];
+            () = msg![env; app setStatusBarOrientation:to];
+            // Resizing the view lets autoresizing lay out its subviews for the
+            // new orientation.
+            // TODO: Also transform the view. This can't be displayed correctly
+            // until Core Animation supports layer transforms.
+            let frame = rotated_application_frame(env, to);
+            let view: id = msg![env; view_controller view];
+            () = msg![env; view setFrame:frame];
+            () = msg![env; view_controller didRotateFromInterfaceOrientation:from];
+        }
+    }
+
+    release(env, pool);
+}
+
+/// The screen's application frame (the part not covered by the status bar) in
+/// the co-ordinate space of an interface orientation.
+fn rotated_application_frame(env: &mut Environment, orientation: UIDeviceOrientation) -> CGRect {
+    let screen: id = msg_class![env; UIScreen mainScreen];
+    let bounds: CGRect = msg![env; screen bounds];
+    let application_frame: CGRect = msg![env; screen applicationFrame];
+    let status_bar_height = bounds.size.height - application_frame.size.height;
+    let CGSize { width, height } = bounds.size;
+    let (width, height) = match orientation {
+        UIDeviceOrientationLandscapeLeft | UIDeviceOrientationLandscapeRight => (height, width),
+        _ => (width, height),
+    };
+    CGRect {
+        origin: CGPoint {
+            x: 0.0,
+            y: status_bar_height,
+        },
+        size: CGSize {
+            width,
+            height: height - status_bar_height,
+        },
+    }
+}
+
+/// Find the view controller responsible for autorotation, if any.
+fn root_view_controller(env: &mut Environment) -> id {
+    let Some(window) = env.framework_state.uikit.ui_view.ui_window.key_window else {
+        return nil;
+    };
+    let subviews: id = msg![env; window subviews];
+    let count: NSUInteger = msg![env; subviews count];
+    if count == 0 {
+        return nil;
+    }
+    let view: id = msg![env; subviews objectAtIndex:0u32];
+    get_view_controller(env, view)
 }
 
 pub const CONSTANTS: ConstantExports = &[(
@@ -59,10 +184,14 @@ pub const CLASSES: ClassExports = objc_classes! {
 }
 
 - (())beginGeneratingDeviceOrientationNotifications {
-    log!("TODO: beginGeneratingDeviceOrientationNotifications");
+    env.framework_state.uikit.ui_device.generating_notifications += 1;
 }
 - (())endGeneratingDeviceOrientationNotifications {
-    log!("TODO: endGeneratingDeviceOrientationNotifications");
+    let count = &mut env.framework_state.uikit.ui_device.generating_notifications;
+    *count = count.saturating_sub(1);
+}
+- (bool)isGeneratingDeviceOrientationNotifications {
+    env.framework_state.uikit.ui_device.generating_notifications > 0
 }
 - (id)model {
     // TODO: Hardcoded to iPhone for now
@@ -94,11 +223,13 @@ pub const CLASSES: ClassExports = objc_classes! {
 }
 
 - (UIDeviceOrientation)orientation {
-    match env.window().current_rotation() {
-        DeviceOrientation::Portrait => UIDeviceOrientationPortrait,
-        DeviceOrientation::LandscapeLeft => UIDeviceOrientationLandscapeLeft,
-        DeviceOrientation::LandscapeRight => UIDeviceOrientationLandscapeRight
-    }
+    let orientation = env
+        .framework_state
+        .uikit
+        .ui_device
+        .orientation
+        .unwrap_or_else(|| env.window().current_rotation());
+    orientation_from_device_orientation(orientation)
 }
 
 @end
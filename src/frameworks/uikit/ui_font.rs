@@ -6,62 +6,28 @@
 //! `UIFont`.
 
 use super::ui_graphics::UIGraphicsGetCurrentContext;
-use crate::font::{Font, TextAlignment, WrapMode};
+use crate::font::{Font, FontChain, TextAlignment, Truncation, Wrap, WrapMode};
 use crate::frameworks::core_graphics::cg_bitmap_context::CGBitmapContextDrawer;
+use crate::frameworks::core_graphics::cg_font::{self, BuiltinFamily, BuiltinFont};
 use crate::frameworks::core_graphics::{CGFloat, CGPoint, CGRect, CGSize};
-use crate::frameworks::foundation::ns_string::to_rust_string;
-use crate::frameworks::foundation::NSInteger;
-use crate::objc::{autorelease, id, objc_classes, ClassExports, HostObject};
+use crate::frameworks::foundation::ns_string::{self, get_static_str, to_rust_string};
+use crate::frameworks::foundation::{ns_array, NSInteger};
+use crate::objc::{
+    autorelease, id, msg, msg_class, objc_classes, release, retain, ClassExports, HostObject,
+};
 use crate::Environment;
-use std::collections::HashMap;
 use std::ops::Range;
-
-#[derive(Default)]
-pub(super) struct State {
-    fonts: HashMap<FontKind, Font>,
-    sans_regular_ja: Option<Font>,
-    sans_bold_ja: Option<Font>,
-}
-impl State {
-    fn get_font_by_kind(&mut self, font_kind: FontKind) -> &Font {
-        self.fonts
-            .entry(font_kind)
-            .or_insert_with(|| match font_kind {
-                FontKind::MonoRegular => Font::mono_regular(),
-                FontKind::MonoBold => Font::mono_bold(),
-                FontKind::MonoBoldItalic => Font::mono_bold_italic(),
-                FontKind::MonoItalic => Font::mono_italic(),
-                FontKind::SansRegular => Font::sans_regular(),
-                FontKind::SansBold => Font::sans_bold(),
-                FontKind::SansBoldItalic => Font::sans_bold_italic(),
-                FontKind::SansItalic => Font::sans_italic(),
-                FontKind::SerifRegular => Font::serif_regular(),
-                FontKind::SerifBold => Font::serif_bold(),
-                FontKind::SerifBoldItalic => Font::serif_bold_italic(),
-                FontKind::SerifItalic => Font::serif_italic(),
-            })
-    }
-}
-
-#[derive(Copy, Clone, PartialEq, Eq, Hash)]
-enum FontKind {
-    MonoRegular,
-    MonoBold,
-    MonoBoldItalic,
-    MonoItalic,
-    SansRegular,
-    SansBold,
-    SansBoldItalic,
-    SansItalic,
-    SerifRegular,
-    SerifBold,
-    SerifBoldItalic,
-    SerifItalic,
-}
+use std::rc::Rc;
 
 struct UIFontHostObject {
     size: CGFloat,
-    kind: FontKind,
+    font: Rc<Font>,
+    /// Name the font was requested with, e.g. "Helvetica-Bold".
+    name: String,
+    /// Family the font belongs to, e.g. "Helvetica".
+    family_name: String,
+    /// Used to pick a fallback font for text the font has no glyphs for.
+    bold: bool,
 }
 impl HostObject for UIFontHostObject {}
 
@@ -72,12 +38,9 @@ impl HostObject for UIFontHostObject {}
 pub type UILineBreakMode = NSInteger;
 pub const UILineBreakModeWordWrap: UILineBreakMode = 0;
 pub const UILineBreakModeCharacterWrap: UILineBreakMode = 1;
-#[allow(dead_code)]
 pub const UILineBreakModeClip: UILineBreakMode = 2;
-#[allow(dead_code)]
 pub const UILineBreakModeHeadTruncation: UILineBreakMode = 3;
 pub const UILineBreakModeTailTruncation: UILineBreakMode = 4;
-#[allow(dead_code)]
 pub const UILineBreakModeMiddleTruncation: UILineBreakMode = 5;
 
 /// Text alignment.
@@ -89,6 +52,16 @@ pub const UITextAlignmentLeft: UITextAlignment = 0;
 pub const UITextAlignmentCenter: UITextAlignment = 1;
 pub const UITextAlignmentRight: UITextAlignment = 2;
 
+/// Baseline adjustment, for when text is drawn with a smaller font size than
+/// requested so that it fits.
+///
+/// This is put here for convenience since it's font-related.
+/// Apple puts it in its own header, also in UIKit.
+pub type UIBaselineAdjustment = NSInteger;
+pub const UIBaselineAdjustmentAlignBaselines: UIBaselineAdjustment = 0;
+pub const UIBaselineAdjustmentAlignCenters: UIBaselineAdjustment = 1;
+pub const UIBaselineAdjustmentNone: UIBaselineAdjustment = 2;
+
 pub const CLASSES: ClassExports = objc_classes! {
 
 (env, this, _cmd);
@@ -96,102 +69,168 @@ pub const CLASSES: ClassExports = objc_classes! {
 @implementation UIFont: NSObject
 
 + (id)systemFontOfSize:(CGFloat)size {
-    let host_object = UIFontHostObject {
-        size,
-        kind: FontKind::SansRegular,
-    };
+    let host_object = system_font(env, size, /* bold: */ false, /* italic: */ false);
     let new = env.objc.alloc_object(this, Box::new(host_object), &mut env.mem);
     autorelease(env, new)
 }
 + (id)boldSystemFontOfSize:(CGFloat)size {
-    let host_object = UIFontHostObject {
-        size,
-        kind: FontKind::SansBold,
-    };
+    let host_object = system_font(env, size, /* bold: */ true, /* italic: */ false);
     let new = env.objc.alloc_object(this, Box::new(host_object), &mut env.mem);
     autorelease(env, new)
 }
 + (id)italicSystemFontOfSize:(CGFloat)size {
-    let host_object = UIFontHostObject {
-        size,
-        kind: FontKind::SansItalic,
-    };
+    let host_object = system_font(env, size, /* bold: */ false, /* italic: */ true);
     let new = env.objc.alloc_object(this, Box::new(host_object), &mut env.mem);
     autorelease(env, new)
 }
 + (id)fontWithName:(id)fontName // NSString*
             size:(CGFloat)fontSize {
     let font_name = to_rust_string(env, fontName).to_string();
+    let host_object = font_with_name(env, font_name, fontSize);
+    let new = env.objc.alloc_object(this, Box::new(host_object), &mut env.mem);
+    autorelease(env, new)
+}
+
++ (id)familyNames {
+    let names = family_names(env);
+    let names = names
+        .into_iter()
+        .map(|name| ns_string::from_rust_string(env, name))
+        .collect();
+    let array = ns_array::from_vec(env, names);
+    autorelease(env, array)
+}
++ (id)fontNamesForFamilyName:(id)familyName { // NSString*
+    let family_name = to_rust_string(env, familyName).to_string();
+    let names = font_names_for_family_name(env, &family_name);
+    let names = names
+        .into_iter()
+        .map(|name| ns_string::from_rust_string(env, name))
+        .collect();
+    let array = ns_array::from_vec(env, names);
+    autorelease(env, array)
+}
+
+// NSCoding implementation
+- (id)initWithCoder:(id)coder {
+    let key = get_static_str(env, "UIFontPointSize");
+    let size: CGFloat = msg![env; coder decodeFloatForKey:key];
+    let key = get_static_str(env, "UISystemFont");
+    let font: id = if msg![env; coder decodeBoolForKey:key] {
+        // The traits use the same bits as CTFontSymbolicTraits.
+        let key = get_static_str(env, "UIFontTraits");
+        let traits: NSInteger = msg![env; coder decodeIntegerForKey:key];
+        match traits & 3 {
+            0 => msg_class![env; UIFont systemFontOfSize:size],
+            1 => msg_class![env; UIFont italicSystemFontOfSize:size],
+            _ => msg_class![env; UIFont boldSystemFontOfSize:size],
+        }
+    } else {
+        let key = get_static_str(env, "UIFontName");
+        let name: id = msg![env; coder decodeObjectForKey:key];
+        msg_class![env; UIFont fontWithName:name size:size]
+    };
+    // UIFont instances are created by the class methods above, so this one
+    // is replaced.
+    release(env, this);
+    retain(env, font)
+}
+
+- (id)fontWithSize:(CGFloat)size {
+    let host_object = env.objc.borrow::<UIFontHostObject>(this);
     let host_object = UIFontHostObject {
-        kind: get_equivalent_font(&font_name).unwrap_or_else(|| {
-            log!("No replacement found for font {}. Using system font instead.", font_name);
-            FontKind::SansRegular
-        }),
-        size: fontSize,
+        size,
+        font: host_object.font.clone(),
+        name: host_object.name.clone(),
+        family_name: host_object.family_name.clone(),
+        bold: host_object.bold,
     };
-    let new = env.objc.alloc_object(this, Box::new(host_object), &mut env.mem);
+    let class = msg![env; this class];
+    let new = env.objc.alloc_object(class, Box::new(host_object), &mut env.mem);
     autorelease(env, new)
 }
 
+- (id)fontName {
+    let name = env.objc.borrow::<UIFontHostObject>(this).name.clone();
+    let name = ns_string::from_rust_string(env, name);
+    autorelease(env, name)
+}
+- (id)familyName {
+    let name = env.objc.borrow::<UIFontHostObject>(this).family_name.clone();
+    let name = ns_string::from_rust_string(env, name);
+    autorelease(env, name)
+}
+
+- (CGFloat)pointSize {
+    env.objc.borrow::<UIFontHostObject>(this).size
+}
+
 - (CGFloat)ascender {
     let host_object = env.objc.borrow::<UIFontHostObject>(this);
-    let font = env.framework_state.uikit.ui_font.get_font_by_kind(host_object.kind);
-    font.ascent(host_object.size)
+    host_object.font.ascent(host_object.size)
 }
 - (CGFloat)descender {
     let host_object = env.objc.borrow::<UIFontHostObject>(this);
-    let font = env.framework_state.uikit.ui_font.get_font_by_kind(host_object.kind);
-    font.descent(host_object.size)
+    host_object.font.descent(host_object.size)
+}
+- (CGFloat)leading {
+    let host_object = env.objc.borrow::<UIFontHostObject>(this);
+    host_object.font.line_spacing(host_object.size)
+}
+- (CGFloat)lineHeight {
+    msg![env; this leading]
 }
 
 @end
 
 };
 
-fn convert_line_break_mode(ui_mode: UILineBreakMode) -> WrapMode {
+fn convert_line_break_mode(ui_mode: UILineBreakMode) -> (WrapMode, Truncation) {
     match ui_mode {
-        UILineBreakModeWordWrap => WrapMode::Word,
-        UILineBreakModeCharacterWrap => WrapMode::Char,
-        // TODO: support this properly; fake support is so that UILabel works,
-        // which has this as its default line break mode
-        UILineBreakModeTailTruncation => WrapMode::Word,
-        _ => unimplemented!("TODO: line break mode {}", ui_mode),
+        UILineBreakModeWordWrap => (WrapMode::Word, Truncation::Clip),
+        UILineBreakModeCharacterWrap => (WrapMode::Char, Truncation::Clip),
+        UILineBreakModeClip => (WrapMode::Word, Truncation::Clip),
+        UILineBreakModeHeadTruncation => (WrapMode::Word, Truncation::Head),
+        UILineBreakModeTailTruncation => (WrapMode::Word, Truncation::Tail),
+        UILineBreakModeMiddleTruncation => (WrapMode::Word, Truncation::Middle),
+        _ => {
+            log!("Unknown line break mode {}, using word wrap", ui_mode);
+            (WrapMode::Word, Truncation::Clip)
+        }
     }
 }
 
-#[rustfmt::skip]
-fn get_font<'a>(state: &'a mut State, kind: FontKind, text: &str) -> &'a Font {
-    // The default fonts (see font.rs) are the Liberation family, which are a
-    // good substitute for Helvetica, the iPhone OS system font. Unfortunately,
-    // there is no CJK support in these fonts. To support Super Monkey Ball in
-    // Japanese, let's fall back to Noto Sans JP when necessary.
-    // FIXME: This heuristic is incomplete and a proper font fallback system
-    // should be used instead.
-    for c in text.chars() {
-        let c = c as u32;
-        if (0x3000..=0x30FF).contains(&c) || // JA punctuation, kana
-           (0xFF00..=0xFFEF).contains(&c) || // full-width/half-width chars
-           (0x4e00..=0x9FA0).contains(&c) || // various kanji
-           (0x3400..=0x4DBF).contains(&c) { // more kanji
-            match kind {
-                // CJK has no italic equivalent
-                FontKind::MonoRegular | FontKind::MonoItalic | FontKind::SansRegular | FontKind::SansItalic | FontKind::SerifRegular | FontKind::SerifItalic => {
-                    if state.sans_regular_ja.is_none() {
-                        state.sans_regular_ja = Some(Font::sans_regular_ja());
-                    }
-                    return state.sans_regular_ja.as_ref().unwrap();
-                },
-                FontKind::MonoBold | FontKind::MonoBoldItalic | FontKind::SansBold | FontKind::SansBoldItalic | FontKind::SerifBold | FontKind::SerifBoldItalic => {
-                    if state.sans_bold_ja.is_none() {
-                        state.sans_bold_ja = Some(Font::sans_bold_ja());
-                    }
-                    return state.sans_bold_ja.as_ref().unwrap();
-                },
-            }
-        }
+/// Layout constraints for text that may wrap onto as many lines as fit within
+/// `size`.
+fn wrap_in_size(font: &Font, font_size: CGFloat, size: CGSize, ui_mode: UILineBreakMode) -> Wrap {
+    let (mode, truncation) = convert_line_break_mode(ui_mode);
+    Wrap {
+        width: size.width,
+        mode,
+        max_lines: Some(font.lines_in_height(font_size, size.height)),
+        truncation,
     }
+}
 
-    state.get_font_by_kind(kind)
+/// Layout constraints for a single line of text.
+fn wrap_single_line(width: CGFloat, ui_mode: UILineBreakMode) -> Wrap {
+    let (mode, truncation) = convert_line_break_mode(ui_mode);
+    Wrap {
+        width,
+        mode,
+        max_lines: Some(1),
+        truncation,
+    }
+}
+
+/// Get the size of a font, and the fonts to use for `text`: the font itself,
+/// followed by fallback fonts for any characters it has no glyphs for.
+fn fonts_for_text(env: &mut Environment, font: id, text: &str) -> (CGFloat, Vec<Rc<Font>>) {
+    let host_object = env.objc.borrow::<UIFontHostObject>(font);
+    let size = host_object.size;
+    let font = host_object.font.clone();
+    let bold = host_object.bold;
+    (size, cg_font::fonts_for_text(env, font, bold, text))
 }
 
 /// Called by the `sizeWithFont:` method family on `NSString`.
@@ -201,21 +240,108 @@ pub fn size_with_font(
     text: &str,
     constrained: Option<(CGSize, UILineBreakMode)>,
 ) -> CGSize {
-    let host_object = env.objc.borrow::<UIFontHostObject>(font);
+    let (font_size, fonts) = fonts_for_text(env, font, text);
+    let font = FontChain::new(&fonts);
 
-    let font = get_font(
-        &mut env.framework_state.uikit.ui_font,
-        host_object.kind,
-        text,
-    );
+    let wrap =
+        constrained.map(|(size, ui_mode)| wrap_in_size(font.primary(), font_size, size, ui_mode));
 
-    let wrap = constrained.map(|(size, ui_mode)| (size.width, convert_line_break_mode(ui_mode)));
+    let (width, height) = font.calculate_text_size(font_size, text, wrap);
 
-    let (width, height) = font.calculate_text_size(host_object.size, text, wrap);
+    CGSize { width, height }
+}
+
+/// Called by the `sizeWithFont:forWidth:lineBreakMode:` method family on
+/// `NSString`. This is for a single line of text.
+pub fn size_with_font_for_width(
+    env: &mut Environment,
+    font: id,
+    text: &str,
+    width: CGFloat,
+    line_break_mode: UILineBreakMode,
+) -> CGSize {
+    let (font_size, fonts) = fonts_for_text(env, font, text);
+    let font = FontChain::new(&fonts);
+
+    let wrap = wrap_single_line(width, line_break_mode);
+    let (width, height) = font.calculate_text_size(font_size, text, Some(wrap));
 
     CGSize { width, height }
 }
 
+/// Find the font size, no smaller than `min_size`, at which a single line of
+/// text fits within a width. Used for `adjustsFontSizeToFitWidth` and the
+/// `minFontSize:` method family on `NSString`.
+pub fn font_size_to_fit_width(
+    env: &mut Environment,
+    font: id,
+    text: &str,
+    width: CGFloat,
+    min_size: CGFloat,
+) -> CGFloat {
+    let (size, fonts) = fonts_for_text(env, font, text);
+    let font = FontChain::new(&fonts);
+
+    let (text_width, _) = font.calculate_text_size(size, text, None);
+    if text_width <= width || text_width == 0.0 {
+        return size;
+    }
+    // Text width is roughly proportional to the font size.
+    let mut new_size = (size * width / text_width).floor().max(min_size);
+    while new_size > min_size && font.calculate_text_size(new_size, text, None).0 > width {
+        new_size = (new_size - 1.0).max(min_size);
+    }
+    new_size
+}
+
+/// Calculate how far down the text drawn with a font size of `actual_size`
+/// must be shifted to respect the baseline adjustment, compared to text drawn
+/// with the font's own size.
+pub fn baseline_adjustment_offset(
+    env: &mut Environment,
+    font: id,
+    actual_size: CGFloat,
+    baseline_adjustment: UIBaselineAdjustment,
+) -> CGFloat {
+    let host_object = env.objc.borrow::<UIFontHostObject>(font);
+    let size = host_object.size;
+    let font = &host_object.font;
+    match baseline_adjustment {
+        UIBaselineAdjustmentAlignBaselines => font.ascent(size) - font.ascent(actual_size),
+        UIBaselineAdjustmentAlignCenters => {
+            let height = |size| font.ascent(size) - font.descent(size);
+            (height(size) - height(actual_size)) / 2.0
+        }
+        UIBaselineAdjustmentNone => 0.0,
+        _ => {
+            log!(
+                "Unknown baseline adjustment {}, using none",
+                baseline_adjustment
+            );
+            0.0
+        }
+    }
+}
+
+/// Byte ranges of the lines of word-wrapped text, for text editing views.
+/// The lines match what [draw_in_rect] draws with `UILineBreakModeWordWrap`.
+pub fn editing_line_ranges(
+    env: &mut Environment,
+    font: id,
+    text: &str,
+    width: CGFloat,
+) -> Vec<Range<usize>> {
+    let (font_size, fonts) = fonts_for_text(env, font, text);
+    FontChain::new(&fonts).editing_line_ranges(font_size, text, width)
+}
+
+/// Horizontal position of the character at byte offset `idx` within a line
+/// of text, for placing a caret.
+pub fn offset_in_line(env: &mut Environment, font: id, line: &str, idx: usize) -> CGFloat {
+    let (font_size, fonts) = fonts_for_text(env, font, line);
+    FontChain::new(&fonts).offset_in_line(font_size, line, idx)
+}
+
 #[inline(always)]
 fn draw_font_glyph(
     drawer: &mut CGBitmapContextDrawer,
@@ -264,9 +390,7 @@ fn draw_font_glyph(
             (tex_x * glyph_rect.size.width - 0.5).round() as i32,
             (tex_y * glyph_rect.size.height - 0.5).round() as i32,
         ));
-        let (r, g, b, a) = fill_color;
-        let (r, g, b, a) = (r * coverage, g * coverage, b * coverage, a * coverage);
-        drawer.put_pixel((x, y), (r, g, b, a), /* blend: */ true);
+        drawer.blend_pixel((x, y), fill_color, coverage);
     }
 }
 
@@ -280,28 +404,21 @@ pub fn draw_at_point(
 ) -> CGSize {
     let context = UIGraphicsGetCurrentContext(env);
 
-    let host_object = env.objc.borrow::<UIFontHostObject>(font);
-
-    let font = get_font(
-        &mut env.framework_state.uikit.ui_font,
-        host_object.kind,
-        text,
-    );
+    let (font_size, fonts) = fonts_for_text(env, font, text);
+    let font = FontChain::new(&fonts);
 
-    let width_and_line_break_mode =
-        width_and_line_break_mode.map(|(width, ui_mode)| (width, convert_line_break_mode(ui_mode)));
+    let wrap = width_and_line_break_mode.map(|(width, ui_mode)| wrap_single_line(width, ui_mode));
     let clip_x = width_and_line_break_mode.map(|(width, _)| point.x..(point.x + width));
-    let (width, height) =
-        font.calculate_text_size(host_object.size, text, width_and_line_break_mode);
+    let (width, height) = font.calculate_text_size(font_size, text, wrap);
 
     let mut drawer = CGBitmapContextDrawer::new(&env.objc, &mut env.mem, context);
     let fill_color = drawer.rgb_fill_color();
 
     font.draw(
-        host_object.size,
+        font_size,
         text,
         (point.x, point.y),
-        width_and_line_break_mode,
+        wrap,
         TextAlignment::Left,
         |raster_glyph| {
             draw_font_glyph(
@@ -330,13 +447,10 @@ pub fn draw_in_rect(
 
     let text_size = size_with_font(env, font, text, Some((rect.size, line_break_mode)));
 
-    let host_object = env.objc.borrow::<UIFontHostObject>(font);
+    let (font_size, fonts) = fonts_for_text(env, font, text);
+    let font = FontChain::new(&fonts);
 
-    let font = get_font(
-        &mut env.framework_state.uikit.ui_font,
-        host_object.kind,
-        text,
-    );
+    let wrap = wrap_in_size(font.primary(), font_size, rect.size, line_break_mode);
 
     let mut drawer = CGBitmapContextDrawer::new(&env.objc, &mut env.mem, context);
     let fill_color = drawer.rgb_fill_color();
@@ -349,10 +463,10 @@ pub fn draw_in_rect(
     };
 
     font.draw(
-        host_object.size,
+        font_size,
         text,
         (rect.origin.x + origin_x_offset, rect.origin.y),
-        Some((rect.size.width, convert_line_break_mode(line_break_mode))),
+        Some(wrap),
         alignment,
         |raster_glyph| {
             draw_font_glyph(
@@ -368,69 +482,246 @@ pub fn draw_in_rect(
     text_size
 }
 
-fn get_equivalent_font(system_font: &str) -> Option<FontKind> {
-    // Maps every font found in every font family in an iOS 2 Simulator
-    match system_font {
-        // Font Family: Courier
-        "Courier" => None,
-        "Courier-BoldOblique" => None,
-        "Courier-Oblique" => None,
-        "Courier-Bold" => None,
-        // Font Family: AppleGothic
-        "AppleGothic" => None,
-        // Font Family: Arial
-        "ArialMT" => Some(FontKind::SansRegular),
-        "Arial-BoldMT" => Some(FontKind::SansBold),
-        "Arial-BoldItalicMT" => Some(FontKind::SansBoldItalic),
-        "Arial-ItalicMT" => Some(FontKind::SansItalic),
-        // Font Family: STHeiti TC
-        "STHeitiTC-Light" => None,
-        "STHeitiTC-Medium" => None,
-        // Font Family: Hiragino Kaku Gothic ProN
-        "HiraKakuProN-W6" => None,
-        "HiraKakuProN-W3" => None,
-        // Font Family: Courier New
-        "CourierNewPS-BoldMT" => Some(FontKind::MonoRegular),
-        "CourierNewPS-ItalicMT" => Some(FontKind::MonoBold),
-        "CourierNewPS-BoldItalicMT" => Some(FontKind::MonoBoldItalic),
-        "CourierNewPSMT" => Some(FontKind::MonoItalic),
-        // Font Family: Zapfino
-        "Zapfino" => None,
-        // Font Family: Arial Unicode MS
-        "ArialUnicodeMS" => None,
-        // Font Family: STHeiti SC
-        "STHeitiSC-Medium" => None,
-        "STHeitiSC-Light" => None,
-        // Font Family: American Typewriter
-        "AmericanTypewriter" => Some(FontKind::MonoRegular),
-        "AmericanTypewriter-Bold" => Some(FontKind::MonoBold),
-        // Font Family: Helvetica
-        "Helvetica-Oblique" => None,
-        "Helvetica-BoldOblique" => None,
-        "Helvetica" => None,
-        "Helvetica-Bold" => None,
-        // Font Family: Marker Felt
-        "MarkerFelt-Thin" => None,
-        // Font Family: Helvetica Neue
-        "HelveticaNeue" => None,
-        "HelveticaNeue-Bold" => None,
-        // Font Family: DB LCD Temp
-        "DBLCDTempBlack" => None,
-        // Font Family: Verdana
-        "Verdana-Bold" => None,
-        "Verdana-BoldItalic" => None,
-        "Verdana" => None,
-        "Verdana-Italic" => None,
-        // Font Family: Times New Roman
-        "TimesNewRomanPSMT" => Some(FontKind::SerifRegular),
-        "TimesNewRomanPS-BoldMT" => Some(FontKind::SerifBold),
-        "TimesNewRomanPS-BoldItalicMT" => Some(FontKind::SerifBoldItalic),
-        "TimesNewRomanPS-ItalicMT" => Some(FontKind::SerifItalic),
-        // Font Family: Georgia
-        "Georgia-Bold" => None,
-        "Georgia" => None,
-        "Georgia-BoldItalic" => None,
-        "Georgia-Italic" => None,
-        _ => None,
+/// Every font found in every font family in an iOS 2 Simulator, with the
+/// touchHLE font family used in its place. Whether to use the bold and italic
+/// variants is guessed from the name.
+const SYSTEM_FONTS: &[(&str, BuiltinFamily, &[&str])] = &[
+    (
+        "Courier",
+        BuiltinFamily::Mono,
+        &[
+            "Courier",
+            "Courier-BoldOblique",
+            "Courier-Oblique",
+            "Courier-Bold",
+        ],
+    ),
+    ("AppleGothic", BuiltinFamily::Sans, &["AppleGothic"]),
+    (
+        "Arial",
+        BuiltinFamily::Sans,
+        &[
+            "ArialMT",
+            "Arial-BoldMT",
+            "Arial-BoldItalicMT",
+            "Arial-ItalicMT",
+        ],
+    ),
+    (
+        "STHeiti TC",
+        BuiltinFamily::Sans,
+        &["STHeitiTC-Light", "STHeitiTC-Medium"],
+    ),
+    (
+        "Hiragino Kaku Gothic ProN",
+        BuiltinFamily::Sans,
+        &["HiraKakuProN-W6", "HiraKakuProN-W3"],
+    ),
+    (
+        "Courier New",
+        BuiltinFamily::Mono,
+        &[
+            "CourierNewPS-BoldMT",
+            "CourierNewPS-ItalicMT",
+            "CourierNewPS-BoldItalicMT",
+            "CourierNewPSMT",
+        ],
+    ),
+    ("Zapfino", BuiltinFamily::Serif, &["Zapfino"]),
+    ("Arial Unicode MS", BuiltinFamily::Sans, &["ArialUnicodeMS"]),
+    (
+        "STHeiti SC",
+        BuiltinFamily::Sans,
+        &["STHeitiSC-Medium", "STHeitiSC-Light"],
+    ),
+    (
+        "American Typewriter",
+        BuiltinFamily::Mono,
+        &["AmericanTypewriter", "AmericanTypewriter-Bold"],
+    ),
+    (
+        "Helvetica",
+        BuiltinFamily::Sans,
+        &[
+            "Helvetica-Oblique",
+            "Helvetica-BoldOblique",
+            "Helvetica",
+            "Helvetica-Bold",
+        ],
+    ),
+    ("Marker Felt", BuiltinFamily::Sans, &["MarkerFelt-Thin"]),
+    (
+        "Helvetica Neue",
+        BuiltinFamily::Sans,
+        &["HelveticaNeue", "HelveticaNeue-Bold"],
+    ),
+    ("DB LCD Temp", BuiltinFamily::Mono, &["DBLCDTempBlack"]),
+    (
+        "Verdana",
+        BuiltinFamily::Sans,
+        &[
+            "Verdana-Bold",
+            "Verdana-BoldItalic",
+            "Verdana",
+            "Verdana-Italic",
+        ],
+    ),
+    (
+        "Times New Roman",
+        BuiltinFamily::Serif,
+        &[
+            "TimesNewRomanPSMT",
+            "TimesNewRomanPS-BoldMT",
+            "TimesNewRomanPS-BoldItalicMT",
+            "TimesNewRomanPS-ItalicMT",
+        ],
+    ),
+    (
+        "Georgia",
+        BuiltinFamily::Serif,
+        &[
+            "Georgia-Bold",
+            "Georgia",
+            "Georgia-BoldItalic",
+            "Georgia-Italic",
+        ],
+    ),
+];
+
+/// Create the host object for Helvetica, the iPhone OS system font.
+fn system_font(env: &mut Environment, size: CGFloat, bold: bool, italic: bool) -> UIFontHostObject {
+    let name = match (bold, italic) {
+        (false, false) => "Helvetica",
+        (true, false) => "Helvetica-Bold",
+        (false, true) => "Helvetica-Oblique",
+        (true, true) => "Helvetica-BoldOblique",
+    };
+    let builtin = BuiltinFont {
+        family: BuiltinFamily::Sans,
+        bold,
+        italic,
+    };
+    UIFontHostObject {
+        size,
+        font: cg_font::builtin_font(env, builtin),
+        name: name.to_string(),
+        family_name: "Helvetica".to_string(),
+        bold,
+    }
+}
+
+/// Find a font by PostScript name or family name and create its host object.
+/// Fonts bundled with the app are preferred, then the fonts that come with
+/// touchHLE are used, either directly or in place of iPhone OS system fonts.
+fn font_with_name(env: &mut Environment, name: String, size: CGFloat) -> UIFontHostObject {
+    if let Some(font) = cg_font::bundle_font_with_name(env, &name) {
+        let names = font.names();
+        let family_name = names.family.clone().unwrap_or_else(|| name.clone());
+        let style_name = names.postscript.as_deref().unwrap_or(&name);
+        let bold = BuiltinFont::style_for_name(style_name).bold;
+        return UIFontHostObject {
+            size,
+            font,
+            name,
+            family_name,
+            bold,
+        };
+    }
+
+    let system_font = SYSTEM_FONTS
+        .iter()
+        .find_map(|&(family_name, family, font_names)| {
+            if font_names.contains(&name.as_str()) {
+                let style = BuiltinFont::style_for_name(&name);
+                Some((family_name, BuiltinFont { family, ..style }))
+            } else if family_name == name {
+                // The family's fonts aren't listed in any particular order.
+                let style = font_names
+                    .iter()
+                    .map(|name| BuiltinFont::style_for_name(name))
+                    .min_by_key(|style| style.bold as u8 + style.italic as u8)
+                    .unwrap();
+                Some((family_name, BuiltinFont { family, ..style }))
+            } else {
+                None
+            }
+        });
+    let (family_name, builtin) = if let Some((family_name, builtin)) = system_font {
+        (family_name, builtin)
+    } else if let Some(builtin) = cg_font::builtin_font_for_exact_name(&name) {
+        (builtin.family.name(), builtin)
+    } else {
+        let builtin = BuiltinFont::for_name(&name);
+        log!(
+            "No font found with name {}, using {} instead.",
+            name,
+            builtin.postscript_name()
+        );
+        (builtin.family.name(), builtin)
+    };
+    UIFontHostObject {
+        size,
+        font: cg_font::builtin_font(env, builtin),
+        name,
+        family_name: family_name.to_string(),
+        bold: builtin.bold,
+    }
+}
+
+/// Called by `[UIFont familyNames]`: the iPhone OS system font families,
+/// followed by those of the fonts that come with touchHLE and those bundled
+/// with the app.
+fn family_names(env: &mut Environment) -> Vec<String> {
+    let mut names: Vec<String> = SYSTEM_FONTS
+        .iter()
+        .map(|&(family_name, _, _)| family_name.to_string())
+        .collect();
+    names.extend(
+        BuiltinFamily::ALL
+            .iter()
+            .map(|family| family.name().to_string()),
+    );
+    for font in cg_font::bundle_fonts(env) {
+        let Some(ref family_name) = font.names().family else {
+            continue;
+        };
+        if !names.contains(family_name) {
+            names.push(family_name.clone());
+        }
+    }
+    names
+}
+
+/// Called by `[UIFont fontNamesForFamilyName:]`.
+fn font_names_for_family_name(env: &mut Environment, family_name: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    if let Some(&(_, _, font_names)) = SYSTEM_FONTS
+        .iter()
+        .find(|&&(name, _, _)| name == family_name)
+    {
+        names.extend(font_names.iter().map(|&name| name.to_string()));
+    }
+    if let Some(&family) = BuiltinFamily::ALL
+        .iter()
+        .find(|family| family.name() == family_name)
+    {
+        names.extend(
+            BuiltinFont::all_in_family(family)
+                .iter()
+                .map(|font| font.postscript_name()),
+        );
+    }
+    for font in cg_font::bundle_fonts(env) {
+        let font_names = font.names();
+        if font_names.family.as_deref() != Some(family_name) {
+            continue;
+        }
+        let Some(name) = font_names.postscript.as_ref().or(font_names.full.as_ref()) else {
+            continue;
+        };
+        if !names.contains(name) {
+            names.push(name.clone());
+        }
     }
+    names
 }
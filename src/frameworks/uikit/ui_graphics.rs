@@ -6,15 +6,44 @@
 //! `UIGraphics.h`
 
 use crate::dyld::{export_c_func, FunctionExports};
+use crate::frameworks::core_graphics::cg_bitmap_context::{
+    CGBitmapContextCreate, CGBitmapContextCreateImage,
+};
+use crate::frameworks::core_graphics::cg_color_space::{
+    CGColorSpaceCreateDeviceRGB, CGColorSpaceRelease,
+};
 use crate::frameworks::core_graphics::cg_context::{
-    CGContextRef, CGContextRelease, CGContextRetain,
+    kCGBlendModeCopy, CGBlendMode, CGContextFillRect, CGContextRef, CGContextRelease,
+    CGContextRestoreGState, CGContextRetain, CGContextSaveGState, CGContextScaleCTM,
+    CGContextSetBlendMode, CGContextTranslateCTM,
+};
+use crate::frameworks::core_graphics::cg_data_consumer::{
+    CGDataConsumerCreateWithCFData, CGDataConsumerCreateWithURL, CGDataConsumerRef,
+    CGDataConsumerRelease,
 };
-use crate::objc::nil;
+use crate::frameworks::core_graphics::cg_image::{
+    kCGImageAlphaPremultipliedLast, kCGImageByteOrder32Big, CGImageRelease,
+};
+use crate::frameworks::core_graphics::cg_pdf_context::{self, CGPDFContextClose};
+use crate::frameworks::core_graphics::{CGFloat, CGPoint, CGRect, CGSize};
+use crate::mem::{GuestUSize, Ptr};
+use crate::objc::{id, msg_class, nil};
 use crate::Environment;
 
 #[derive(Default)]
 pub(super) struct State {
     pub(super) context_stack: Vec<CGContextRef>,
+    /// Contexts created by `UIGraphicsBeginImageContext`, which are also on
+    /// the context stack.
+    image_context_stack: Vec<CGContextRef>,
+    /// Context created by `UIGraphicsBeginPDFContextToData` or similar, which
+    /// is also on the context stack.
+    pdf_context: Option<CGContextRef>,
+}
+impl State {
+    fn get(env: &mut Environment) -> &mut State {
+        &mut env.framework_state.uikit.ui_graphics
+    }
 }
 
 pub fn UIGraphicsPushContext(env: &mut Environment, context: CGContextRef) {
@@ -39,8 +68,246 @@ pub fn UIGraphicsGetCurrentContext(env: &mut Environment) -> CGContextRef {
         .unwrap_or(nil)
 }
 
+/// UIKit's co-ordinate system has the origin in the top-left corner, so the
+/// contexts it creates are flipped compared to Core Graphics.
+fn flip_context(env: &mut Environment, context: CGContextRef, height: CGFloat) {
+    CGContextTranslateCTM(env, context, 0.0, height);
+    CGContextScaleCTM(env, context, 1.0, -1.0);
+}
+
+fn UIGraphicsBeginImageContext(env: &mut Environment, size: CGSize) {
+    UIGraphicsBeginImageContextWithOptions(env, size, false, 1.0);
+}
+
+fn UIGraphicsBeginImageContextWithOptions(
+    env: &mut Environment,
+    size: CGSize,
+    _opaque: bool,
+    scale: CGFloat,
+) {
+    // A scale of 0 means the screen's scale, which is always 1 in touchHLE.
+    if scale != 0.0 && scale != 1.0 {
+        // TODO: UIImage doesn't support scales yet either.
+        log!(
+            "TODO: UIGraphicsBeginImageContextWithOptions() scale {}, using 1",
+            scale
+        );
+    }
+
+    let width = size.width.ceil();
+    let height = size.height.ceil();
+    if !(width >= 1.0 && height >= 1.0) {
+        log!(
+            "Warning: UIGraphicsBeginImageContext() with invalid size {:?}, no context created",
+            size
+        );
+        return;
+    }
+    let (width, height) = (width as GuestUSize, height as GuestUSize);
+
+    // This is the same format that CALayer uses.
+    let color_space = CGColorSpaceCreateDeviceRGB(env);
+    let context = CGBitmapContextCreate(
+        env,
+        Ptr::null(),
+        width,
+        height,
+        8, // bits per component
+        width.checked_mul(4).unwrap(),
+        color_space,
+        kCGImageByteOrder32Big | kCGImageAlphaPremultipliedLast,
+    );
+    CGColorSpaceRelease(env, color_space);
+    flip_context(env, context, height as CGFloat);
+
+    UIGraphicsPushContext(env, context);
+    // The context stack now owns the context.
+    CGContextRelease(env, context);
+    State::get(env).image_context_stack.push(context);
+}
+
+fn UIGraphicsGetImageFromCurrentImageContext(env: &mut Environment) -> id {
+    let context = UIGraphicsGetCurrentContext(env);
+    if context == nil || State::get(env).image_context_stack.last() != Some(&context) {
+        log!("Warning: UIGraphicsGetImageFromCurrentImageContext() without an image context, returning nil");
+        return nil;
+    }
+    let cg_image = CGBitmapContextCreateImage(env, context);
+    let image: id = msg_class![env; UIImage imageWithCGImage:cg_image];
+    CGImageRelease(env, cg_image);
+    image
+}
+
+fn UIGraphicsEndImageContext(env: &mut Environment) {
+    let context = UIGraphicsGetCurrentContext(env);
+    if context == nil || State::get(env).image_context_stack.last() != Some(&context) {
+        log!("Warning: UIGraphicsEndImageContext() without an image context, ignoring");
+        return;
+    }
+    State::get(env).image_context_stack.pop();
+    UIGraphicsPopContext(env);
+}
+
+fn UIRectFill(env: &mut Environment, rect: CGRect) {
+    UIRectFillUsingBlendMode(env, rect, kCGBlendModeCopy);
+}
+
+fn UIRectFillUsingBlendMode(env: &mut Environment, rect: CGRect, blend_mode: CGBlendMode) {
+    let context = UIGraphicsGetCurrentContext(env);
+    if context == nil {
+        return;
+    }
+    CGContextSaveGState(env, context);
+    CGContextSetBlendMode(env, context, blend_mode);
+    CGContextFillRect(env, context, rect);
+    CGContextRestoreGState(env, context);
+}
+
+fn UIRectFrame(env: &mut Environment, rect: CGRect) {
+    UIRectFrameUsingBlendMode(env, rect, kCGBlendModeCopy);
+}
+
+/// Fill a frame one point wide just inside the edges of `rect`.
+fn UIRectFrameUsingBlendMode(env: &mut Environment, rect: CGRect, blend_mode: CGBlendMode) {
+    let context = UIGraphicsGetCurrentContext(env);
+    if context == nil {
+        return;
+    }
+    let CGRect {
+        origin: CGPoint { x, y },
+        size: CGSize { width, height },
+    } = rect;
+    let line = 1.0f32.min(width).min(height);
+    if line <= 0.0 {
+        return;
+    }
+    let edge = |x, y, width, height| CGRect {
+        origin: CGPoint { x, y },
+        size: CGSize { width, height },
+    };
+    let edges = [
+        edge(x, y, width, line),
+        edge(x, y + height - line, width, line),
+        edge(x, y + line, line, height - line * 2.0),
+        edge(x + width - line, y + line, line, height - line * 2.0),
+    ];
+    CGContextSaveGState(env, context);
+    CGContextSetBlendMode(env, context, blend_mode);
+    for edge in edges {
+        if edge.size.height > 0.0 {
+            CGContextFillRect(env, context, edge);
+        }
+    }
+    CGContextRestoreGState(env, context);
+}
+
+fn begin_pdf_context(
+    env: &mut Environment,
+    consumer: CGDataConsumerRef,
+    bounds: CGRect,
+    document_info: id, // NSDictionary*
+) {
+    if State::get(env).pdf_context.is_some() {
+        log!("Warning: PDF context begun while another is active, ignoring");
+        return;
+    }
+    // An empty rectangle means the default page size.
+    let bounds = (bounds.size.width > 0.0 && bounds.size.height > 0.0).then_some(bounds);
+    let context = cg_pdf_context::create(env, consumer, bounds, document_info);
+    if context == nil {
+        return;
+    }
+    UIGraphicsPushContext(env, context);
+    // The context stack now owns the context.
+    CGContextRelease(env, context);
+    State::get(env).pdf_context = Some(context);
+}
+
+fn UIGraphicsBeginPDFContextToData(
+    env: &mut Environment,
+    data: id, // NSMutableData*
+    bounds: CGRect,
+    document_info: id, // NSDictionary*
+) {
+    let consumer = CGDataConsumerCreateWithCFData(env, data);
+    begin_pdf_context(env, consumer, bounds, document_info);
+    CGDataConsumerRelease(env, consumer);
+}
+
+fn UIGraphicsBeginPDFContextToFile(
+    env: &mut Environment,
+    path: id, // NSString*
+    bounds: CGRect,
+    document_info: id, // NSDictionary*
+) -> bool {
+    let url: id = msg_class![env; NSURL fileURLWithPath:path];
+    let consumer = CGDataConsumerCreateWithURL(env, url);
+    begin_pdf_context(env, consumer, bounds, document_info);
+    CGDataConsumerRelease(env, consumer);
+    State::get(env).pdf_context.is_some()
+}
+
+fn UIGraphicsBeginPDFPage(env: &mut Environment) {
+    UIGraphicsBeginPDFPageWithInfo(env, CGRect::default(), nil);
+}
+
+fn UIGraphicsBeginPDFPageWithInfo(
+    env: &mut Environment,
+    bounds: CGRect,
+    page_info: id, // NSDictionary*
+) {
+    let Some(context) = State::get(env).pdf_context else {
+        log!("Warning: UIGraphicsBeginPDFPage() without a PDF context, ignoring");
+        return;
+    };
+    // An empty rectangle means the page info's media box, if any, or else the
+    // context's default page size.
+    let bounds = if bounds.size.width > 0.0 && bounds.size.height > 0.0 {
+        Some(bounds)
+    } else {
+        cg_pdf_context::page_info_media_box(env, page_info)
+    };
+    let Some(media_box) = cg_pdf_context::begin_page(env, context, bounds) else {
+        return;
+    };
+    // The page's top edge has to end up at y = 0.
+    let CGRect { origin, size } = media_box;
+    flip_context(env, context, origin.y * 2.0 + size.height);
+}
+
+fn UIGraphicsEndPDFContext(env: &mut Environment) {
+    let Some(context) = State::get(env).pdf_context else {
+        log!("Warning: UIGraphicsEndPDFContext() without a PDF context, ignoring");
+        return;
+    };
+    CGPDFContextClose(env, context);
+    State::get(env).pdf_context = None;
+    if UIGraphicsGetCurrentContext(env) == context {
+        UIGraphicsPopContext(env);
+    } else {
+        log!("Warning: UIGraphicsEndPDFContext() when the PDF context isn't the current context");
+        let stack = &mut State::get(env).context_stack;
+        let idx = stack.iter().rposition(|&c| c == context).unwrap();
+        stack.remove(idx);
+        CGContextRelease(env, context);
+    }
+}
+
 pub const FUNCTIONS: FunctionExports = &[
     export_c_func!(UIGraphicsPushContext(_)),
     export_c_func!(UIGraphicsPopContext()),
     export_c_func!(UIGraphicsGetCurrentContext()),
+    export_c_func!(UIGraphicsBeginImageContext(_)),
+    export_c_func!(UIGraphicsBeginImageContextWithOptions(_, _, _)),
+    export_c_func!(UIGraphicsGetImageFromCurrentImageContext()),
+    export_c_func!(UIGraphicsEndImageContext()),
+    export_c_func!(UIRectFill(_)),
+    export_c_func!(UIRectFillUsingBlendMode(_, _)),
+    export_c_func!(UIRectFrame(_)),
+    export_c_func!(UIRectFrameUsingBlendMode(_, _)),
+    export_c_func!(UIGraphicsBeginPDFContextToData(_, _, _)),
+    export_c_func!(UIGraphicsBeginPDFContextToFile(_, _, _)),
+    export_c_func!(UIGraphicsBeginPDFPage()),
+    export_c_func!(UIGraphicsBeginPDFPageWithInfo(_, _)),
+    export_c_func!(UIGraphicsEndPDFContext()),
 ];
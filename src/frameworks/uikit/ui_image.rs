@@ -5,23 +5,197 @@
  */
 //! `UIImage`.
 
-use crate::frameworks::core_graphics::cg_context::CGContextDrawImage;
+use crate::dyld::{export_c_func, FunctionExports};
+use crate::frameworks::core_graphics::cg_context::{
+    CGBlendMode, CGContextDrawImage, CGContextRestoreGState, CGContextSaveGState,
+    CGContextSetAlpha, CGContextSetBlendMode,
+};
 use crate::frameworks::core_graphics::cg_image::{self, CGImageRef, CGImageRelease, CGImageRetain};
-use crate::frameworks::core_graphics::{CGRect, CGSize};
+use crate::frameworks::core_graphics::{CGFloat, CGPoint, CGRect, CGSize};
 use crate::frameworks::foundation::{ns_data, ns_string, NSInteger};
 use crate::frameworks::uikit::ui_graphics::UIGraphicsGetCurrentContext;
 use crate::fs::GuestPath;
 use crate::image::Image;
 use crate::objc::{
-    autorelease, id, msg, msg_class, nil, objc_classes, release, ClassExports, HostObject,
+    autorelease, id, msg, msg_class, nil, objc_classes, release, retain, ClassExports, HostObject,
     NSZonePtr,
 };
+use crate::Environment;
+use std::collections::HashMap;
+
+pub type UIImageOrientation = NSInteger;
+pub const UIImageOrientationUp: UIImageOrientation = 0;
+pub const UIImageOrientationDown: UIImageOrientation = 1;
+pub const UIImageOrientationLeft: UIImageOrientation = 2;
+pub const UIImageOrientationRight: UIImageOrientation = 3;
+pub const UIImageOrientationUpMirrored: UIImageOrientation = 4;
+pub const UIImageOrientationDownMirrored: UIImageOrientation = 5;
+pub const UIImageOrientationLeftMirrored: UIImageOrientation = 6;
+pub const UIImageOrientationRightMirrored: UIImageOrientation = 7;
+
+#[derive(Default)]
+pub(super) struct State {
+    /// Images loaded by `imageNamed:`, which are cached for the lifetime of
+    /// the app. `imageWithContentsOfFile:` never uses this cache.
+    named_images: HashMap<String, id>,
+}
 
 struct UIImageHostObject {
     cg_image: CGImageRef,
+    orientation: UIImageOrientation,
+    /// Copy of the image rotated/flipped according to the orientation, made
+    /// when first drawn. Only used if the orientation isn't "up".
+    oriented_cg_image: CGImageRef,
+    left_cap_width: NSInteger,
+    top_cap_height: NSInteger,
 }
 impl HostObject for UIImageHostObject {}
 
+/// Makes a copy of an image that appears the right way up for an orientation.
+fn apply_orientation(image: &Image, orientation: UIImageOrientation) -> Image {
+    let (width, height) = image.dimensions();
+    let (w, h) = (width as usize, height as usize);
+    let rotated = matches!(
+        orientation,
+        UIImageOrientationLeft
+            | UIImageOrientationRight
+            | UIImageOrientationLeftMirrored
+            | UIImageOrientationRightMirrored
+    );
+    let (out_width, out_height) = if rotated { (h, w) } else { (w, h) };
+
+    let pixels = image.pixels();
+    let mut out_pixels = Vec::with_capacity(pixels.len());
+    for y in 0..out_height {
+        for x in 0..out_width {
+            let (src_x, src_y) = match orientation {
+                UIImageOrientationDown => (w - 1 - x, h - 1 - y),
+                UIImageOrientationLeft => (w - 1 - y, x),
+                UIImageOrientationRight => (y, h - 1 - x),
+                UIImageOrientationUpMirrored => (w - 1 - x, y),
+                UIImageOrientationDownMirrored => (x, h - 1 - y),
+                UIImageOrientationLeftMirrored => (w - 1 - y, h - 1 - x),
+                UIImageOrientationRightMirrored => (y, x),
+                _ => (x, y),
+            };
+            out_pixels.extend_from_slice(&pixels[(src_y * w + src_x) * 4..][..4]);
+        }
+    }
+    Image::from_pixel_vec(out_pixels, (out_width as u32, out_height as u32))
+}
+
+/// Gets the `CGImage` to draw for a `UIImage`, taking orientation into
+/// account.
+fn oriented_cg_image(env: &mut Environment, this: id) -> CGImageRef {
+    let &UIImageHostObject {
+        cg_image,
+        orientation,
+        oriented_cg_image,
+        ..
+    } = env.objc.borrow(this);
+    if orientation == UIImageOrientationUp {
+        return cg_image;
+    }
+    if oriented_cg_image != nil {
+        return oriented_cg_image;
+    }
+    let image = apply_orientation(cg_image::borrow_image(&env.objc, cg_image), orientation);
+    let oriented_cg_image = cg_image::from_image(env, image);
+    env.objc
+        .borrow_mut::<UIImageHostObject>(this)
+        .oriented_cg_image = oriented_cg_image;
+    oriented_cg_image
+}
+
+/// Splits one axis of a stretchable image into its end caps and the
+/// one-pixel stretchable part between them. Each slice is given as
+/// `(source start, source length, destination start, destination length)`.
+fn stretch_slices(cap: u32, size: u32, dest_size: CGFloat) -> Vec<(u32, u32, CGFloat, CGFloat)> {
+    if cap == 0 || size == 0 {
+        return vec![(0, size, 0.0, dest_size)];
+    }
+    let cap = cap.min(size - 1);
+    let end_cap = size - cap - 1;
+    // If the destination is too small for the caps, they are squashed.
+    let caps_size = (cap + end_cap) as CGFloat;
+    let scale = if caps_size > dest_size {
+        dest_size / caps_size
+    } else {
+        1.0
+    };
+    let start_length = cap as CGFloat * scale;
+    let end_length = end_cap as CGFloat * scale;
+    let middle_length = dest_size - start_length - end_length;
+    vec![
+        (0, cap, 0.0, start_length),
+        (cap, 1, start_length, middle_length),
+        (cap + 1, end_cap, start_length + middle_length, end_length),
+    ]
+}
+
+fn draw_in_rect(env: &mut Environment, this: id, rect: CGRect) {
+    let context = UIGraphicsGetCurrentContext(env);
+    let image = oriented_cg_image(env, this);
+    let &UIImageHostObject {
+        left_cap_width,
+        top_cap_height,
+        ..
+    } = env.objc.borrow(this);
+    if left_cap_width <= 0 && top_cap_height <= 0 {
+        CGContextDrawImage(env, context, rect, image);
+        return;
+    }
+
+    // Stretchable images are drawn in up to nine slices.
+    let (width, height) = cg_image::borrow_image(&env.objc, image).dimensions();
+    let columns = stretch_slices(left_cap_width.max(0) as u32, width, rect.size.width);
+    let rows = stretch_slices(top_cap_height.max(0) as u32, height, rect.size.height);
+    for &(src_y, src_height, dest_y, dest_height) in &rows {
+        for &(src_x, src_width, dest_x, dest_width) in &columns {
+            if src_width == 0 || src_height == 0 || dest_width <= 0.0 || dest_height <= 0.0 {
+                continue;
+            }
+            let slice = cg_image::borrow_image(&env.objc, image)
+                .sub_image((src_x, src_y), (src_width, src_height));
+            let slice = cg_image::from_image(env, slice);
+            // CGContextDrawImage puts the top of the image at the maximum y
+            // co-ordinate, so the slices must be placed the same way.
+            let dest_rect = CGRect {
+                origin: CGPoint {
+                    x: rect.origin.x + dest_x,
+                    y: rect.origin.y + rect.size.height - dest_y - dest_height,
+                },
+                size: CGSize {
+                    width: dest_width,
+                    height: dest_height,
+                },
+            };
+            CGContextDrawImage(env, context, dest_rect, slice);
+            CGImageRelease(env, slice);
+        }
+    }
+}
+
+fn image_size(env: &mut Environment, this: id) -> CGSize {
+    let &UIImageHostObject {
+        cg_image,
+        orientation,
+        ..
+    } = env.objc.borrow(this);
+    let (width, height) = cg_image::borrow_image(&env.objc, cg_image).dimensions();
+    let (width, height) = match orientation {
+        UIImageOrientationLeft
+        | UIImageOrientationRight
+        | UIImageOrientationLeftMirrored
+        | UIImageOrientationRightMirrored => (height, width),
+        _ => (width, height),
+    };
+    CGSize {
+        width: width as _,
+        height: height as _,
+    }
+}
+
 pub const CLASSES: ClassExports = objc_classes! {
 
 (env, this, _cmd);
@@ -29,7 +203,13 @@ pub const CLASSES: ClassExports = objc_classes! {
 @implementation UIImage: NSObject
 
 + (id)allocWithZone:(NSZonePtr)_zone {
-    let host_object = Box::new(UIImageHostObject { cg_image: nil });
+    let host_object = Box::new(UIImageHostObject {
+        cg_image: nil,
+        orientation: UIImageOrientationUp,
+        oriented_cg_image: nil,
+        left_cap_width: 0,
+        top_cap_height: 0,
+    });
     env.objc.alloc_object(this, host_object, &mut env.mem)
 }
 
@@ -39,15 +219,35 @@ pub const CLASSES: ClassExports = objc_classes! {
     autorelease(env, new)
 }
 
++ (id)imageWithCGImage:(CGImageRef)cg_image
+                 scale:(CGFloat)scale
+           orientation:(UIImageOrientation)orientation {
+    let new: id = msg![env; this alloc];
+    let new: id = msg![env; new initWithCGImage:cg_image
+                                          scale:scale
+                                    orientation:orientation];
+    autorelease(env, new)
+}
+
 + (id)imageNamed:(id)name { // NSString*
+    let name_string = ns_string::to_rust_string(env, name).into_owned();
+    if let Some(&image) = env.framework_state.uikit.ui_image.named_images.get(&name_string) {
+        return image;
+    }
+
     // TODO: figure out whether this is actually correct in all cases
     let bundle: id = msg_class![env; NSBundle mainBundle];
     let path: id = msg![env; bundle pathForResource:name ofType:nil];
     if path == nil {
-        log!("Warning: [UIImage imageNamed:{:?}] => nil", ns_string::to_rust_string(env, name));
+        log!("Warning: [UIImage imageNamed:{:?}] => nil", name_string);
         return nil;
     }
-    msg![env; this imageWithContentsOfFile:path]
+    let image: id = msg![env; this imageWithContentsOfFile:path];
+    if image != nil {
+        retain(env, image);
+        env.framework_state.uikit.ui_image.named_images.insert(name_string, image);
+    }
+    image
 }
 
 + (id)imageWithContentsOfFile:(id)path { // NSString*
@@ -63,8 +263,9 @@ pub const CLASSES: ClassExports = objc_classes! {
 }
 
 - (())dealloc {
-    let &UIImageHostObject { cg_image } = env.objc.borrow(this);
+    let &UIImageHostObject { cg_image, oriented_cg_image, .. } = env.objc.borrow(this);
     CGImageRelease(env, cg_image);
+    CGImageRelease(env, oriented_cg_image);
 
     env.objc.dealloc_object(this, &mut env.mem)
 }
@@ -75,6 +276,17 @@ pub const CLASSES: ClassExports = objc_classes! {
     this
 }
 
+- (id)initWithCGImage:(CGImageRef)cg_image
+                scale:(CGFloat)scale
+          orientation:(UIImageOrientation)orientation {
+    if scale != 1.0 {
+        log!("TODO: [UIImage initWithCGImage:{:?} scale:{} orientation:{}], ignoring scale", cg_image, scale, orientation);
+    }
+    let this: id = msg![env; this initWithCGImage:cg_image];
+    env.objc.borrow_mut::<UIImageHostObject>(this).orientation = orientation;
+    this
+}
+
 - (id)initWithContentsOfFile:(id)path { // NSString*
     let path = ns_string::to_rust_string(env, path); // TODO: avoid copy
     let Ok(bytes) = env.fs.read(GuestPath::new(&path)) else {
@@ -100,34 +312,165 @@ pub const CLASSES: ClassExports = objc_classes! {
     this
 }
 
-// TODO: more init methods
-// TODO: more accessors
+// NSCoding implementation
+- (id)initWithCoder:(id)coder {
+    // Nib files refer to images in the app bundle by name.
+    let key = ns_string::get_static_str(env, "UIResourceName");
+    let name: id = msg![env; coder decodeObjectForKey:key];
+    release(env, this);
+    if name == nil {
+        log!("TODO: UIImage archive without a resource name, returning nil");
+        return nil;
+    }
+    let image: id = msg_class![env; UIImage imageNamed:name];
+    retain(env, image)
+}
+
+- (id)stretchableImageWithLeftCapWidth:(NSInteger)left_cap_width
+                          topCapHeight:(NSInteger)top_cap_height {
+    let &UIImageHostObject { cg_image, orientation, .. } = env.objc.borrow(this);
+    let new: id = msg_class![env; UIImage alloc];
+    let new: id = msg![env; new initWithCGImage:cg_image];
+    let host_obj = env.objc.borrow_mut::<UIImageHostObject>(new);
+    host_obj.orientation = orientation;
+    host_obj.left_cap_width = left_cap_width;
+    host_obj.top_cap_height = top_cap_height;
+    autorelease(env, new)
+}
+
+- (NSInteger)leftCapWidth {
+    env.objc.borrow::<UIImageHostObject>(this).left_cap_width
+}
+- (NSInteger)topCapHeight {
+    env.objc.borrow::<UIImageHostObject>(this).top_cap_height
+}
 
 - (CGImageRef)CGImage {
     env.objc.borrow::<UIImageHostObject>(this).cg_image
 }
 
-// TODO: should have UIImageOrientation type
-- (NSInteger)imageOrientation {
+- (UIImageOrientation)imageOrientation {
     // FIXME: load image orientation info from file?
-    0 // UIImageOrientationUp
+    env.objc.borrow::<UIImageHostObject>(this).orientation
 }
 
 - (CGSize)size {
-    let image = env.objc.borrow::<UIImageHostObject>(this).cg_image;
-    let (width, height) = cg_image::borrow_image(&env.objc, image).dimensions();
-    CGSize {
-        width: width as _,
-        height: height as _,
-    }
+    image_size(env, this)
+}
+
+- (())drawAtPoint:(CGPoint)point {
+    let size = image_size(env, this);
+    draw_in_rect(env, this, CGRect { origin: point, size });
+}
+- (())drawAtPoint:(CGPoint)point
+        blendMode:(CGBlendMode)blend_mode
+            alpha:(CGFloat)alpha {
+    let size = image_size(env, this);
+    msg![env; this drawInRect:(CGRect { origin: point, size })
+                    blendMode:blend_mode
+                        alpha:alpha]
 }
 
 - (())drawInRect:(CGRect)rect {
+    draw_in_rect(env, this, rect);
+}
+- (())drawInRect:(CGRect)rect
+       blendMode:(CGBlendMode)blend_mode
+           alpha:(CGFloat)alpha {
     let context = UIGraphicsGetCurrentContext(env);
-    let image = env.objc.borrow::<UIImageHostObject>(this).cg_image;
-    CGContextDrawImage(env, context, rect, image);
+    CGContextSaveGState(env, context);
+    CGContextSetBlendMode(env, context, blend_mode);
+    CGContextSetAlpha(env, context, alpha);
+    draw_in_rect(env, this, rect);
+    CGContextRestoreGState(env, context);
+}
+
+- (())drawAsPatternInRect:(CGRect)rect {
+    let context = UIGraphicsGetCurrentContext(env);
+    let image = oriented_cg_image(env, this);
+    let (width, height) = cg_image::borrow_image(&env.objc, image).dimensions();
+    if width == 0 || height == 0 {
+        return;
+    }
+    let (tile_width, tile_height) = (width as CGFloat, height as CGFloat);
+
+    // The pattern is anchored at the origin, not at the rect.
+    let rect_end_x = rect.origin.x + rect.size.width;
+    let rect_end_y = rect.origin.y + rect.size.height;
+    let mut tile_y = (rect.origin.y / tile_height).floor() * tile_height;
+    while tile_y < rect_end_y {
+        let mut tile_x = (rect.origin.x / tile_width).floor() * tile_width;
+        while tile_x < rect_end_x {
+            // Tiles at the edges of the rect are cropped.
+            let x0 = tile_x.max(rect.origin.x);
+            let y0 = tile_y.max(rect.origin.y);
+            let x1 = (tile_x + tile_width).min(rect_end_x);
+            let y1 = (tile_y + tile_height).min(rect_end_y);
+            let dest_rect = CGRect {
+                origin: CGPoint { x: x0, y: y0 },
+                size: CGSize { width: x1 - x0, height: y1 - y0 },
+            };
+            let cropped = x0 != tile_x
+                || y0 != tile_y
+                || x1 != tile_x + tile_width
+                || y1 != tile_y + tile_height;
+            if !cropped {
+                CGContextDrawImage(env, context, dest_rect, image);
+            } else if x1 > x0 && y1 > y0 {
+                // As in draw_in_rect, the top of the image is at the maximum
+                // y co-ordinate.
+                let src_x = (x0 - tile_x) as u32;
+                let src_y = (tile_y + tile_height - y1) as u32;
+                let src_size = (((x1 - x0).ceil()) as u32, ((y1 - y0).ceil()) as u32);
+                let slice = cg_image::borrow_image(&env.objc, image)
+                    .sub_image((src_x, src_y), src_size);
+                let slice = cg_image::from_image(env, slice);
+                CGContextDrawImage(env, context, dest_rect, slice);
+                CGImageRelease(env, slice);
+            }
+            tile_x += tile_width;
+        }
+        tile_y += tile_height;
+    }
 }
 
 @end
 
+// An undocumented type that nib files use for images, which are archived by
+// name rather than by content.
+@implementation UIImageNibPlaceholder: UIImage
+@end
+
 };
+
+fn UIImagePNGRepresentation(env: &mut Environment, image: id) -> id {
+    if image == nil {
+        return nil;
+    }
+    let cg_image = env.objc.borrow::<UIImageHostObject>(image).cg_image;
+    let Ok(bytes) = cg_image::borrow_image(&env.objc, cg_image).to_png() else {
+        log!("Warning: UIImagePNGRepresentation() failed to encode image, returning nil");
+        return nil;
+    };
+    let data = ns_data::from_rust_slice(env, &bytes);
+    autorelease(env, data)
+}
+
+fn UIImageJPEGRepresentation(env: &mut Environment, image: id, quality: CGFloat) -> id {
+    if image == nil {
+        return nil;
+    }
+    // TODO: Write the orientation to the EXIF data.
+    let cg_image = env.objc.borrow::<UIImageHostObject>(image).cg_image;
+    let Ok(bytes) = cg_image::borrow_image(&env.objc, cg_image).to_jpeg(quality) else {
+        log!("Warning: UIImageJPEGRepresentation() failed to encode image, returning nil");
+        return nil;
+    };
+    let data = ns_data::from_rust_slice(env, &bytes);
+    autorelease(env, data)
+}
+
+pub const FUNCTIONS: FunctionExports = &[
+    export_c_func!(UIImagePNGRepresentation(_)),
+    export_c_func!(UIImageJPEGRepresentation(_, _)),
+];
@@ -3,12 +3,343 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
-//! `UIImagePickerController`
+//! `UIImagePickerController` and `UIImageWriteToSavedPhotosAlbum`.
+//!
+//! touchHLE has no photo library or camera of its own, so both are backed by
+//! host files. The photo library is a directory of images (see
+//! [paths::PHOTOS_DIR] and the `--photo-library=` option) shown in a simple
+//! grid. The camera is an image, or a directory of images used in turn, given
+//! with the `--camera=` option.
 
-use crate::frameworks::foundation::NSInteger;
-use crate::objc::{objc_classes, ClassExports};
+use crate::dyld::{export_c_func, ConstantExports, FunctionExports, HostConstant};
+use crate::frameworks::core_graphics::cg_context::{CGContextDrawImage, CGContextSetRGBFillColor};
+use crate::frameworks::core_graphics::cg_image::{self, CGImageRef, CGImageRelease};
+use crate::frameworks::core_graphics::{CGFloat, CGPoint, CGRect, CGSize};
+use crate::frameworks::foundation::ns_dictionary::dict_from_keys_and_objects;
+use crate::frameworks::foundation::ns_string::get_static_str;
+use crate::frameworks::foundation::{ns_array, NSInteger};
+use crate::frameworks::uikit::ui_bar_item::ui_bar_button_item::{
+    UIBarButtonItemStyleBordered, UIBarButtonSystemItemCancel, UIBarButtonSystemItemFlexibleSpace,
+};
+use crate::frameworks::uikit::ui_font;
+use crate::frameworks::uikit::ui_graphics::UIGraphicsGetCurrentContext;
+use crate::frameworks::uikit::ui_view::ui_alert_view::delegate_responds;
+use crate::frameworks::uikit::ui_view::ui_scroll_view::UIScrollViewHostObject;
+use crate::frameworks::uikit::ui_view::{
+    UIViewAutoresizingFlexibleHeight, UIViewAutoresizingFlexibleTopMargin,
+    UIViewAutoresizingFlexibleWidth,
+};
+use crate::frameworks::uikit::ui_view_controller::UIViewControllerHostObject;
+use crate::image::Image;
+use crate::mem::MutVoidPtr;
+use crate::objc::{
+    autorelease, id, impl_HostObject_with_superclass, msg, msg_class, msg_send, msg_super, nil,
+    objc_classes, release, retain, ClassExports, NSZonePtr, SEL,
+};
+use crate::paths;
+use crate::Environment;
+use std::path::{Path, PathBuf};
+
+pub type UIImagePickerControllerSourceType = NSInteger;
+pub const UIImagePickerControllerSourceTypePhotoLibrary: UIImagePickerControllerSourceType = 0;
+pub const UIImagePickerControllerSourceTypeCamera: UIImagePickerControllerSourceType = 1;
+pub const UIImagePickerControllerSourceTypeSavedPhotosAlbum: UIImagePickerControllerSourceType = 2;
+
+/// Uniform type identifier for images, the only media type supported.
+const kUTTypeImage: &str = "public.image";
+
+pub const UIImagePickerControllerMediaType: &str = "UIImagePickerControllerMediaType";
+pub const UIImagePickerControllerOriginalImage: &str = "UIImagePickerControllerOriginalImage";
+pub const UIImagePickerControllerEditedImage: &str = "UIImagePickerControllerEditedImage";
+pub const UIImagePickerControllerCropRect: &str = "UIImagePickerControllerCropRect";
+pub const UIImagePickerControllerMediaURL: &str = "UIImagePickerControllerMediaURL";
+
+pub const CONSTANTS: ConstantExports = &[
+    (
+        "_UIImagePickerControllerMediaType",
+        HostConstant::NSString(UIImagePickerControllerMediaType),
+    ),
+    (
+        "_UIImagePickerControllerOriginalImage",
+        HostConstant::NSString(UIImagePickerControllerOriginalImage),
+    ),
+    (
+        "_UIImagePickerControllerEditedImage",
+        HostConstant::NSString(UIImagePickerControllerEditedImage),
+    ),
+    (
+        "_UIImagePickerControllerCropRect",
+        HostConstant::NSString(UIImagePickerControllerCropRect),
+    ),
+    (
+        "_UIImagePickerControllerMediaURL",
+        HostConstant::NSString(UIImagePickerControllerMediaURL),
+    ),
+];
+
+/// `NSFileWriteUnknownError`, used when saving a photo fails.
+const NSFileWriteUnknownError: NSInteger = 512;
+
+/// Height of the bar with the picker's buttons.
+const BAR_HEIGHT: CGFloat = 44.0;
+/// Size of each square thumbnail in the photo grid.
+const THUMBNAIL_SIZE: CGFloat = 75.0;
+/// Gap between and around thumbnails. Four thumbnails fit across the screen in
+/// portrait orientation, like on the real device.
+const THUMBNAIL_GAP: CGFloat = 4.0;
+
+#[derive(Default)]
+pub struct State {
+    /// Index of the next image to use when `--camera=` is a directory.
+    next_camera_frame: usize,
+}
+
+fn photo_library_dir(env: &Environment) -> PathBuf {
+    env.options
+        .photo_library_dir
+        .clone()
+        .unwrap_or_else(|| paths::user_data_base_path().join(paths::PHOTOS_DIR))
+}
+
+/// Lists the image files in a host directory, in alphabetical order.
+fn list_image_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .and_then(|extension| extension.to_str())
+                    .is_some_and(|extension| {
+                        matches!(
+                            extension.to_ascii_lowercase().as_str(),
+                            "png" | "jpg" | "jpeg" | "gif" | "bmp"
+                        )
+                    })
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// Loads an image from a host file.
+fn load_image_file(path: &Path) -> Option<Image> {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            log!("Warning: couldn't read image {:?}: {}", path, e);
+            return None;
+        }
+    };
+    match Image::from_bytes(&bytes) {
+        Ok(image) => Some(image),
+        Err(e) => {
+            log!("Warning: couldn't decode image {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
+/// Creates a new autoreleased `UIImage*` for an image.
+fn ui_image_from_image(env: &mut Environment, image: Image) -> id {
+    let cg_image = cg_image::from_image(env, image);
+    let ui_image: id = msg_class![env; UIImage imageWithCGImage:cg_image];
+    CGImageRelease(env, cg_image);
+    ui_image
+}
+
+/// Takes a "photo" with the stand-in camera, if there is one.
+fn take_camera_photo(env: &mut Environment) -> Option<Image> {
+    let source = env.options.camera_source.clone()?;
+    let path = if source.is_dir() {
+        let frames = list_image_files(&source);
+        if frames.is_empty() {
+            log!("Warning: no images in camera directory {:?}", source);
+            return None;
+        }
+        let state = &mut env.framework_state.uikit.ui_image_picker_controller;
+        let index = state.next_camera_frame % frames.len();
+        state.next_camera_frame = index + 1;
+        frames[index].clone()
+    } else {
+        source
+    };
+    load_image_file(&path)
+}
+
+/// Creates an autoreleased `NSArray*` of the supported media types.
+fn default_media_types(env: &mut Environment) -> id {
+    let media_type = get_static_str(env, kUTTypeImage);
+    retain(env, media_type);
+    let media_types = ns_array::from_vec(env, vec![media_type]);
+    autorelease(env, media_types)
+}
+
+fn is_source_type_available(
+    env: &Environment,
+    source_type: UIImagePickerControllerSourceType,
+) -> bool {
+    match source_type {
+        UIImagePickerControllerSourceTypePhotoLibrary
+        | UIImagePickerControllerSourceTypeSavedPhotosAlbum => true,
+        UIImagePickerControllerSourceTypeCamera => env.options.camera_source.is_some(),
+        _ => false,
+    }
+}
 
-type UIImagePickerControllerSourceType = NSInteger;
+pub struct UIImagePickerControllerHostObject {
+    superclass: UIViewControllerHostObject,
+    /// Weak reference.
+    delegate: id,
+    source_type: UIImagePickerControllerSourceType,
+    allows_editing: bool,
+    /// `NSArray*` of `NSString*`, or `nil` for the default.
+    media_types: id,
+    shows_camera_controls: bool,
+    /// `UIImage*` shown by the camera stand-in.
+    camera_image: id,
+}
+impl_HostObject_with_superclass!(UIImagePickerControllerHostObject);
+impl Default for UIImagePickerControllerHostObject {
+    fn default() -> Self {
+        UIImagePickerControllerHostObject {
+            superclass: Default::default(),
+            delegate: nil,
+            source_type: UIImagePickerControllerSourceTypePhotoLibrary,
+            allows_editing: false,
+            media_types: nil,
+            shows_camera_controls: true,
+            camera_image: nil,
+        }
+    }
+}
+
+/// Creates a (retained) `UIToolbar` with the picker's buttons.
+fn create_bar(env: &mut Environment, this: id, frame: CGRect, camera: bool) -> id {
+    let bar: id = msg_class![env; UIToolbar alloc];
+    let bar: id = msg![env; bar initWithFrame:frame];
+
+    let cancel_action = env.objc.lookup_selector("_touchHLE_cancel:").unwrap();
+    let cancel: id = msg_class![env; UIBarButtonItem alloc];
+    let cancel: id = msg![env; cancel initWithBarButtonSystemItem:UIBarButtonSystemItemCancel
+                                                           target:this
+                                                           action:cancel_action];
+    let space: id = msg_class![env; UIBarButtonItem alloc];
+    let space: id = msg![env; space initWithBarButtonSystemItem:UIBarButtonSystemItemFlexibleSpace
+                                                         target:nil
+                                                         action:(SEL::null())];
+    let items = if camera {
+        let take_action = env.objc.lookup_selector("_touchHLE_takePicture:").unwrap();
+        let title = get_static_str(env, "Take Photo");
+        let take: id = msg_class![env; UIBarButtonItem alloc];
+        let take: id = msg![env; take initWithTitle:title
+                                              style:UIBarButtonItemStyleBordered
+                                             target:this
+                                             action:take_action];
+        vec![cancel, space, take]
+    } else {
+        vec![space, cancel]
+    };
+    // from_vec doesn't retain the items, so the array now owns them.
+    let items = ns_array::from_vec(env, items);
+    () = msg![env; bar setItems:items];
+    release(env, items);
+    bar
+}
+
+/// Delivers the picked image to the delegate, or dismisses the picker if the
+/// delegate doesn't handle it.
+fn finish_picking(env: &mut Environment, this: id, image: id) {
+    let &UIImagePickerControllerHostObject {
+        delegate,
+        allows_editing,
+        ..
+    } = env.objc.borrow(this);
+    let size: CGSize = msg![env; image size];
+    let crop_rect = CGRect {
+        origin: CGPoint { x: 0.0, y: 0.0 },
+        size,
+    };
+
+    if delegate_responds(
+        env,
+        delegate,
+        "imagePickerController:didFinishPickingMediaWithInfo:",
+    ) {
+        let media_type_key = get_static_str(env, UIImagePickerControllerMediaType);
+        let media_type = get_static_str(env, kUTTypeImage);
+        let original_key = get_static_str(env, UIImagePickerControllerOriginalImage);
+        let mut keys_and_objects = vec![(media_type_key, media_type), (original_key, image)];
+        // There's no editing UI, so the "edited" image is the whole image.
+        if allows_editing {
+            let edited_key = get_static_str(env, UIImagePickerControllerEditedImage);
+            let crop_rect_key = get_static_str(env, UIImagePickerControllerCropRect);
+            let crop_rect: id = msg_class![env; NSValue valueWithCGRect:crop_rect];
+            keys_and_objects.push((edited_key, image));
+            keys_and_objects.push((crop_rect_key, crop_rect));
+        }
+        let info = dict_from_keys_and_objects(env, &keys_and_objects);
+        () = msg![env; delegate imagePickerController:this didFinishPickingMediaWithInfo:info];
+        release(env, info);
+    } else if delegate_responds(
+        env,
+        delegate,
+        "imagePickerController:didFinishPickingImage:editingInfo:",
+    ) {
+        // The iPhone OS 2.x version of the delegate method.
+        let editing_info = if allows_editing {
+            let original_key = get_static_str(env, UIImagePickerControllerOriginalImage);
+            let crop_rect_key = get_static_str(env, UIImagePickerControllerCropRect);
+            let crop_rect: id = msg_class![env; NSValue valueWithCGRect:crop_rect];
+            let info = dict_from_keys_and_objects(
+                env,
+                &[(original_key, image), (crop_rect_key, crop_rect)],
+            );
+            autorelease(env, info)
+        } else {
+            nil
+        };
+        () = msg![env; delegate imagePickerController:this
+                                didFinishPickingImage:image
+                                          editingInfo:editing_info];
+    } else {
+        () = msg![env; this dismissModalViewControllerAnimated:true];
+    }
+}
+
+#[derive(Default)]
+pub struct UIImagePickerGridViewHostObject {
+    superclass: UIScrollViewHostObject,
+    /// `UIImagePickerController*`, weak reference.
+    picker: id,
+    /// `UIImage*` and thumbnail for each photo, both retained.
+    photos: Vec<(id, CGImageRef)>,
+}
+impl_HostObject_with_superclass!(UIImagePickerGridViewHostObject);
+
+fn grid_columns(width: CGFloat) -> usize {
+    (((width - THUMBNAIL_GAP) / (THUMBNAIL_SIZE + THUMBNAIL_GAP)) as usize).max(1)
+}
+
+fn thumbnail_rect(width: CGFloat, index: usize) -> CGRect {
+    let columns = grid_columns(width);
+    // Spread any spare width evenly between the gaps.
+    let gap = (width - columns as CGFloat * THUMBNAIL_SIZE) / (columns + 1) as CGFloat;
+    let (row, column) = (index / columns, index % columns);
+    CGRect {
+        origin: CGPoint {
+            x: gap + column as CGFloat * (THUMBNAIL_SIZE + gap),
+            y: THUMBNAIL_GAP + row as CGFloat * (THUMBNAIL_SIZE + THUMBNAIL_GAP),
+        },
+        size: CGSize {
+            width: THUMBNAIL_SIZE,
+            height: THUMBNAIL_SIZE,
+        },
+    }
+}
 
 pub const CLASSES: ClassExports = objc_classes! {
 
@@ -18,12 +349,369 @@ pub const CLASSES: ClassExports = objc_classes! {
 //       UIViewController.
 @implementation UIImagePickerController: UIViewController
 
-+ (bool)isSourceTypeAvailable:(UIImagePickerControllerSourceType)_type {
-    // For now, simply claim no sources are available.
-    // TODO: support some sources.
-    false
++ (id)allocWithZone:(NSZonePtr)_zone {
+    let host_object = Box::<UIImagePickerControllerHostObject>::default();
+    env.objc.alloc_object(this, host_object, &mut env.mem)
+}
+
++ (bool)isSourceTypeAvailable:(UIImagePickerControllerSourceType)source_type {
+    is_source_type_available(env, source_type)
+}
+
++ (id)availableMediaTypesForSourceType:(UIImagePickerControllerSourceType)source_type {
+    if !is_source_type_available(env, source_type) {
+        return nil;
+    }
+    default_media_types(env)
+}
+
+- (())dealloc {
+    let &UIImagePickerControllerHostObject {
+        media_types,
+        camera_image,
+        ..
+    } = env.objc.borrow(this);
+    release(env, media_types);
+    release(env, camera_image);
+    msg_super![env; this dealloc]
+}
+
+- (id)delegate {
+    env.objc.borrow::<UIImagePickerControllerHostObject>(this).delegate
+}
+- (())setDelegate:(id)delegate {
+    env.objc.borrow_mut::<UIImagePickerControllerHostObject>(this).delegate = delegate;
+}
+
+- (UIImagePickerControllerSourceType)sourceType {
+    env.objc.borrow::<UIImagePickerControllerHostObject>(this).source_type
+}
+- (())setSourceType:(UIImagePickerControllerSourceType)source_type {
+    // The real UIKit throws an exception in this case.
+    if !is_source_type_available(env, source_type) {
+        log!(
+            "Warning: UIImagePickerController source type {} is not available, keeping the previous source type",
+            source_type
+        );
+        return;
+    }
+    env.objc.borrow_mut::<UIImagePickerControllerHostObject>(this).source_type = source_type;
+}
+
+- (bool)allowsEditing {
+    env.objc.borrow::<UIImagePickerControllerHostObject>(this).allows_editing
+}
+- (())setAllowsEditing:(bool)allows_editing {
+    env.objc.borrow_mut::<UIImagePickerControllerHostObject>(this).allows_editing = allows_editing;
+}
+// Deprecated in iPhone OS 3.1.
+- (bool)allowsImageEditing {
+    msg![env; this allowsEditing]
+}
+- (())setAllowsImageEditing:(bool)allows_editing {
+    msg![env; this setAllowsEditing:allows_editing]
+}
+
+- (id)mediaTypes {
+    let media_types = env.objc.borrow::<UIImagePickerControllerHostObject>(this).media_types;
+    if media_types != nil {
+        return media_types;
+    }
+    default_media_types(env)
+}
+- (())setMediaTypes:(id)media_types { // NSArray*
+    let media_types: id = msg![env; media_types copy];
+    let host_obj = env.objc.borrow_mut::<UIImagePickerControllerHostObject>(this);
+    let old = std::mem::replace(&mut host_obj.media_types, media_types);
+    release(env, old);
+}
+
+- (bool)showsCameraControls {
+    env.objc.borrow::<UIImagePickerControllerHostObject>(this).shows_camera_controls
+}
+- (())setShowsCameraControls:(bool)shows_camera_controls {
+    env.objc.borrow_mut::<UIImagePickerControllerHostObject>(this).shows_camera_controls =
+        shows_camera_controls;
+}
+
+- (())loadView {
+    let &UIImagePickerControllerHostObject {
+        source_type,
+        shows_camera_controls,
+        ..
+    } = env.objc.borrow(this);
+    let screen: id = msg_class![env; UIScreen mainScreen];
+    let frame: CGRect = msg![env; screen applicationFrame];
+    let (width, height) = (frame.size.width, frame.size.height);
+
+    let view: id = msg_class![env; UIView alloc];
+    let view: id = msg![env; view initWithFrame:frame];
+    () = msg![env; this setView:view];
+    release(env, view);
+
+    let content: id = if source_type == UIImagePickerControllerSourceTypeCamera {
+        let black: id = msg_class![env; UIColor blackColor];
+        () = msg![env; view setBackgroundColor:black];
+
+        let image = take_camera_photo(env).map_or(nil, |image| ui_image_from_image(env, image));
+        retain(env, image);
+        env.objc.borrow_mut::<UIImagePickerControllerHostObject>(this).camera_image = image;
+
+        let bar_height = if shows_camera_controls { BAR_HEIGHT } else { 0.0 };
+        let image_frame = CGRect {
+            origin: CGPoint { x: 0.0, y: 0.0 },
+            size: CGSize { width, height: height - bar_height },
+        };
+        let image_view: id = msg_class![env; UIImageView alloc];
+        let image_view: id = msg![env; image_view initWithFrame:image_frame];
+        () = msg![env; image_view setImage:image];
+        image_view
+    } else {
+        let grid_frame = CGRect {
+            origin: CGPoint { x: 0.0, y: BAR_HEIGHT },
+            size: CGSize { width, height: height - BAR_HEIGHT },
+        };
+        let grid: id = msg_class![env; _touchHLE_UIImagePickerGridView alloc];
+        let grid: id = msg![env; grid initWithFrame:grid_frame];
+        env.objc.borrow_mut::<UIImagePickerGridViewHostObject>(grid).picker = this;
+        () = msg![env; grid _touchHLE_loadPhotos];
+        grid
+    };
+    let mask = UIViewAutoresizingFlexibleWidth | UIViewAutoresizingFlexibleHeight;
+    () = msg![env; content setAutoresizingMask:mask];
+    () = msg![env; view addSubview:content];
+    release(env, content);
+
+    if source_type != UIImagePickerControllerSourceTypeCamera || shows_camera_controls {
+        let camera = source_type == UIImagePickerControllerSourceTypeCamera;
+        let bar_frame = CGRect {
+            origin: CGPoint { x: 0.0, y: if camera { height - BAR_HEIGHT } else { 0.0 } },
+            size: CGSize { width, height: BAR_HEIGHT },
+        };
+        let bar = create_bar(env, this, bar_frame, camera);
+        let mask = if camera {
+            UIViewAutoresizingFlexibleWidth | UIViewAutoresizingFlexibleTopMargin
+        } else {
+            UIViewAutoresizingFlexibleWidth
+        };
+        () = msg![env; bar setAutoresizingMask:mask];
+        () = msg![env; view addSubview:bar];
+        release(env, bar);
+    }
+}
+
+- (())takePicture {
+    let &UIImagePickerControllerHostObject {
+        source_type,
+        camera_image,
+        ..
+    } = env.objc.borrow(this);
+    if source_type != UIImagePickerControllerSourceTypeCamera {
+        log!("Warning: [(UIImagePickerController*){:?} takePicture] without camera source", this);
+        return;
+    }
+    if camera_image == nil {
+        log!("Warning: the camera has no image, ignoring takePicture");
+        return;
+    }
+    finish_picking(env, this, camera_image);
+}
+
+- (())_touchHLE_takePicture:(id)_sender {
+    msg![env; this takePicture]
+}
+
+- (())_touchHLE_cancel:(id)_sender {
+    let delegate = env.objc.borrow::<UIImagePickerControllerHostObject>(this).delegate;
+    if delegate_responds(env, delegate, "imagePickerControllerDidCancel:") {
+        () = msg![env; delegate imagePickerControllerDidCancel:this];
+    } else {
+        () = msg![env; this dismissModalViewControllerAnimated:true];
+    }
+}
+
+- (())_touchHLE_pickImage:(id)image { // UIImage*
+    finish_picking(env, this, image);
+}
+
+@end
+
+// Private class that shows the photo library as a grid of thumbnails.
+@implementation _touchHLE_UIImagePickerGridView: UIScrollView
+
++ (id)allocWithZone:(NSZonePtr)_zone {
+    let host_object = Box::<UIImagePickerGridViewHostObject>::default();
+    env.objc.alloc_object(this, host_object, &mut env.mem)
+}
+
+- (())dealloc {
+    let host_obj = env.objc.borrow_mut::<UIImagePickerGridViewHostObject>(this);
+    let photos = std::mem::take(&mut host_obj.photos);
+    for (image, thumbnail) in photos {
+        release(env, image);
+        CGImageRelease(env, thumbnail);
+    }
+    msg_super![env; this dealloc]
+}
+
+- (())_touchHLE_loadPhotos {
+    let white: id = msg_class![env; UIColor whiteColor];
+    () = msg![env; this setBackgroundColor:white];
+
+    let dir = photo_library_dir(env);
+    log!("Loading photo library from {:?}", dir);
+    let mut photos = Vec::new();
+    for path in list_image_files(&dir) {
+        let Some(image) = load_image_file(&path) else {
+            continue;
+        };
+        // Thumbnails show the middle of the image, cropped to a square.
+        let (width, height) = image.dimensions();
+        let side = width.min(height);
+        let thumbnail = image.sub_image(((width - side) / 2, (height - side) / 2), (side, side));
+        let thumbnail = cg_image::from_image(env, thumbnail);
+        let image = ui_image_from_image(env, image);
+        retain(env, image);
+        photos.push((image, thumbnail));
+    }
+    env.objc.borrow_mut::<UIImagePickerGridViewHostObject>(this).photos = photos;
+    () = msg![env; this layoutSubviews];
+}
+
+- (())layoutSubviews {
+    () = msg_super![env; this layoutSubviews];
+    let bounds: CGRect = msg![env; this bounds];
+    let count = env.objc.borrow::<UIImagePickerGridViewHostObject>(this).photos.len();
+    let columns = grid_columns(bounds.size.width);
+    let rows = count.div_ceil(columns);
+    let content_size = CGSize {
+        width: bounds.size.width,
+        height: THUMBNAIL_GAP + rows as CGFloat * (THUMBNAIL_SIZE + THUMBNAIL_GAP),
+    };
+    () = msg![env; this setContentSize:content_size];
+}
+
+- (())touchesEnded:(id)touches // NSSet* of UITouch*
+         withEvent:(id)event { // UIEvent*
+    let was_dragging: bool = msg![env; this isDragging];
+    () = msg_super![env; this touchesEnded:touches withEvent:event];
+    if was_dragging {
+        return;
+    }
+
+    let touch: id = msg![env; touches anyObject];
+    let point: CGPoint = msg![env; touch locationInView:this];
+    let bounds: CGRect = msg![env; this bounds];
+    let host_obj = env.objc.borrow::<UIImagePickerGridViewHostObject>(this);
+    let picker = host_obj.picker;
+    let image = (0..host_obj.photos.len())
+        .find(|&i| {
+            let rect = thumbnail_rect(bounds.size.width, i);
+            point.x >= rect.origin.x
+                && point.x < rect.origin.x + rect.size.width
+                && point.y >= rect.origin.y
+                && point.y < rect.origin.y + rect.size.height
+        })
+        .map(|i| host_obj.photos[i].0);
+    if let Some(image) = image {
+        () = msg![env; picker _touchHLE_pickImage:image];
+    }
+}
+
+- (())drawRect:(CGRect)_rect {
+    let bounds: CGRect = msg![env; this bounds];
+    let context = UIGraphicsGetCurrentContext(env);
+
+    let thumbnails: Vec<CGImageRef> = env
+        .objc
+        .borrow::<UIImagePickerGridViewHostObject>(this)
+        .photos
+        .iter()
+        .map(|&(_, thumbnail)| thumbnail)
+        .collect();
+    if thumbnails.is_empty() {
+        let font: id = msg_class![env; UIFont systemFontOfSize:(20.0 as CGFloat)];
+        let text = "No Photos";
+        let size = ui_font::size_with_font(env, font, text, None);
+        let point = CGPoint {
+            x: (bounds.size.width - size.width) / 2.0,
+            y: (bounds.size.height - size.height) / 2.0,
+        };
+        CGContextSetRGBFillColor(env, context, 0.5, 0.5, 0.5, 1.0);
+        ui_font::draw_at_point(env, font, text, point, None);
+        return;
+    }
+
+    // Only what's currently scrolled into view needs to be drawn.
+    for (i, thumbnail) in thumbnails.into_iter().enumerate() {
+        let rect = thumbnail_rect(bounds.size.width, i);
+        if rect.origin.y > bounds.origin.y + bounds.size.height
+            || rect.origin.y + rect.size.height < bounds.origin.y
+        {
+            continue;
+        }
+        CGContextDrawImage(env, context, rect, thumbnail);
+    }
 }
 
 @end
 
 };
+
+fn UIImageWriteToSavedPhotosAlbum(
+    env: &mut Environment,
+    image: id,
+    completion_target: id,
+    completion_selector: SEL,
+    context_info: MutVoidPtr,
+) {
+    let dir = photo_library_dir(env);
+    let cg_image: CGImageRef = msg![env; image CGImage];
+    // TODO: Apply the image's orientation.
+    let result = cg_image::borrow_image(&env.objc, cg_image)
+        .to_png()
+        .map_err(|_| "couldn't encode image".to_string())
+        .and_then(|bytes| {
+            std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+            // Name photos the same way the iPhone's camera does.
+            let path = (1..)
+                .map(|number| dir.join(format!("IMG_{:04}.PNG", number)))
+                .find(|path| !path.exists())
+                .unwrap();
+            std::fs::write(&path, bytes).map_err(|e| e.to_string())?;
+            Ok(path)
+        });
+    let error = match result {
+        Ok(path) => {
+            log!("Saved photo to {:?}", path);
+            nil
+        }
+        Err(e) => {
+            log!("Warning: couldn't save photo to {:?}: {}", dir, e);
+            let domain = get_static_str(env, "NSCocoaErrorDomain");
+            let error: id = msg_class![env; NSError alloc];
+            let error: id = msg![env; error initWithDomain:domain
+                                                      code:NSFileWriteUnknownError
+                                                  userInfo:nil];
+            autorelease(env, error)
+        }
+    };
+
+    // The selector should have the same signature as
+    // -[image:didFinishSavingWithError:contextInfo:].
+    if completion_target != nil && !completion_selector.is_null() {
+        () = msg_send(
+            env,
+            (
+                completion_target,
+                completion_selector,
+                image,
+                error,
+                context_info,
+            ),
+        );
+    }
+}
+
+pub const FUNCTIONS: FunctionExports =
+    &[export_c_func!(UIImageWriteToSavedPhotosAlbum(_, _, _, _))];
@@ -9,15 +9,49 @@
 //! - Apple's [Resource Programming Guide](https://developer.apple.com/library/archive/documentation/Cocoa/Conceptual/LoadingResources/CocoaNibs/CocoaNibs.html) is very helpful.
 //! - GitHub user 0xced's [reverse-engineering of UIClassSwapper](https://gist.github.com/0xced/45daf79b62ad6a20be1c).
 
+use crate::dyld::{ConstantExports, HostConstant};
 use crate::frameworks::foundation::ns_string::{get_static_str, to_rust_string};
 use crate::frameworks::foundation::{ns_string, NSUInteger};
+use crate::frameworks::uikit::ui_view::ui_control::UIControlEvents;
 use crate::fs::GuestPathBuf;
 use crate::objc::{
-    id, impl_HostObject_with_superclass, msg, msg_class, msg_super, nil, objc_classes, release,
-    retain, ClassExports, HostObject,
+    autorelease, id, impl_HostObject_with_superclass, msg, msg_class, msg_super, nil, objc_classes,
+    release, retain, ClassExports, HostObject, NSZonePtr,
 };
 use crate::Environment;
 
+pub const UINibExternalObjects: &str = "UINibExternalObjects";
+
+pub const CONSTANTS: ConstantExports = &[(
+    "_UINibExternalObjects",
+    HostConstant::NSString(UINibExternalObjects),
+)];
+
+#[derive(Default)]
+pub struct State {
+    /// Nib files currently being loaded, innermost last. Loading can nest if
+    /// an object loads another nib file while it's being deserialized.
+    loading: Vec<NibLoadingContext>,
+}
+
+struct NibLoadingContext {
+    /// The object that replaces the "File's Owner" proxy. May be nil.
+    owner: id,
+    /// `NSDictionary*` of other objects that can replace proxies, or nil.
+    external_objects: id,
+    /// Objects that have replaced proxies. They are not part of the nib, so
+    /// they mustn't be sent `awakeFromNib` or returned as top-level objects.
+    proxied_objects: Vec<id>,
+}
+
+struct UINibHostObject {
+    /// `NSData*`
+    data: id,
+    /// `NSBundle*`
+    bundle: id,
+}
+impl HostObject for UINibHostObject {}
+
 struct UIRuntimeConnectionHostObject {
     destination: id,
     label: id,
@@ -45,8 +79,63 @@ pub const CLASSES: ClassExports = objc_classes! {
 
 (env, this, _cmd);
 
-// TODO actual UINib class. It's not needed for the main nib file which is
-// loaded implicitly.
+@implementation UINib: NSObject
+
++ (id)allocWithZone:(NSZonePtr)_zone {
+    let host_object = Box::new(UINibHostObject {
+        data: nil,
+        bundle: nil,
+    });
+    env.objc.alloc_object(this, host_object, &mut env.mem)
+}
+
++ (id)nibWithNibName:(id)name // NSString*
+              bundle:(id)bundle { // NSBundle*
+    let bundle: id = if bundle == nil {
+        msg_class![env; NSBundle mainBundle]
+    } else {
+        bundle
+    };
+    let extension = get_static_str(env, "nib");
+    let path: id = msg![env; bundle pathForResource:name ofType:extension];
+    let data: id = if path == nil {
+        nil
+    } else {
+        msg_class![env; NSData dataWithContentsOfFile:path]
+    };
+    if data == nil {
+        log!("Warning: couldn't find nib file {:?} in bundle {:?}", to_rust_string(env, name), bundle);
+        return nil;
+    }
+    msg![env; this nibWithData:data bundle:bundle]
+}
+
++ (id)nibWithData:(id)data // NSData*
+           bundle:(id)bundle { // NSBundle*
+    let new: id = msg![env; this alloc];
+    let new: id = msg![env; new init];
+    retain(env, data);
+    retain(env, bundle);
+    let host_obj = env.objc.borrow_mut::<UINibHostObject>(new);
+    host_obj.data = data;
+    host_obj.bundle = bundle;
+    autorelease(env, new)
+}
+
+- (())dealloc {
+    let &UINibHostObject { data, bundle } = env.objc.borrow(this);
+    release(env, data);
+    release(env, bundle);
+    env.objc.dealloc_object(this, &mut env.mem)
+}
+
+- (id)instantiateWithOwner:(id)owner
+                   options:(id)options { // NSDictionary*
+    let data = env.objc.borrow::<UINibHostObject>(this).data;
+    instantiate_nib(env, data, owner, options)
+}
+
+@end
 
 // An undocumented type that nib files reference by name. NSKeyedUnarchiver will
 // find and instantiate this class.
@@ -58,30 +147,43 @@ pub const CLASSES: ClassExports = objc_classes! {
     let id_nss: id = msg![env; coder decodeObjectForKey:id_key];
     let id = to_rust_string(env, id_nss);
 
-    if id == "IBFilesOwner" {
-        // The file owner is usually the UIApplication instance.
-        // Replacing the proxy with that instance is important so that the
-        // "delegate" outlet can be connected between it and the
-        // UIApplicationDelegate.
-        //
-        // TODO: This is a bit of a hack. Eventually it would be good to fix:
-        // - The name "UIProxyObject" implies that it might be intended to
-        //   proxy messages to another object, rather than be replaced by it.
-        //   Check what iPhone OS does?
-        // - If/when the UINib class is implemented and arbitrary nib files can
-        //   be deserialized, an app could pick some other object to be the nib
-        //   file owner, which this would need to handle.
-        // - If this object is meant to be replaced, it's probably not meant to
-        //   be done via `initWithCoder:`, but instead by providing a delegate
-        //   to the NSKeyedUnarchiver. That might be needed to implement
-        //   replacement for objects other than the UIApplication instance.
-
-        release(env, this);
-        msg_class![env; UIApplication sharedApplication]
+    let Some(context) = env.framework_state.uikit.ui_nib.loading.last() else {
+        log!("Warning: UIProxyObject for {} decoded outside of nib loading, left unreplaced", id);
+        return this;
+    };
+    let &NibLoadingContext { owner, external_objects, .. } = context;
+
+    // The proxy is replaced by the object it stands for. This would probably
+    // be better done by a delegate of the NSKeyedUnarchiver, but that isn't
+    // supported yet.
+    let object: id = if id == "IBFilesOwner" {
+        owner
+    } else if id == "IBFirstResponder" {
+        // There's no object for this: actions connected to the first
+        // responder have a nil target, so the proxy is kept so that
+        // UIRuntimeEventConnection can recognize it.
+        return this;
+    } else if external_objects != nil {
+        msg![env; external_objects objectForKey:id_nss]
     } else {
-        log!("TODO: UIProxyObject replacement for {}, instance {:?} left unreplaced", id, this);
-        this
+        nil
+    };
+    if object == nil {
+        log!("Warning: no object for UIProxyObject {}, instance {:?} left unreplaced", id, this);
+        return this;
     }
+
+    retain(env, object);
+    release(env, this);
+    env.framework_state
+        .uikit
+        .ui_nib
+        .loading
+        .last_mut()
+        .unwrap()
+        .proxied_objects
+        .push(object);
+    object
 }
 
 @end
@@ -189,7 +291,26 @@ pub const CLASSES: ClassExports = objc_classes! {
 }
 
 - (())connect {
-    log!("TODO: [(UIRuntimeEventConnection*) {:?} connect]", this);
+    let &UIRuntimeEventConnectionHostObject {
+        superclass: UIRuntimeConnectionHostObject {
+            destination,
+            label,
+            source,
+        },
+        eventMask: event_mask,
+    } = env.objc.borrow(this);
+
+    // Actions connected to "First Responder" are sent up the responder chain,
+    // which is what a nil target means.
+    let target = if is_proxy(env, destination) {
+        nil
+    } else {
+        destination
+    };
+    let action = to_rust_string(env, label).into_owned();
+    let action = env.objc.register_host_selector(action, &mut env.mem);
+    let event_mask = event_mask as UIControlEvents;
+    () = msg![env; source addTarget:target action:action forControlEvents:event_mask];
 }
 
 // NSCoding implementation
@@ -205,10 +326,6 @@ pub const CLASSES: ClassExports = objc_classes! {
     this
 }
 
-- (())dealloc {
-    env.objc.dealloc_object(this, &mut env.mem)
-}
-
 @end
 
 // Another undocumented type referenced by nib files by name.
@@ -236,6 +353,16 @@ pub const CLASSES: ClassExports = objc_classes! {
         source
     } = env.objc.borrow(this);
 
+    if is_proxy(env, source) || is_proxy(env, destination) {
+        log!(
+            "Warning: skipping connection of outlet {:?} between {:?} and {:?}, one of which is an unreplaced UIProxyObject",
+            to_rust_string(env, label),
+            source,
+            destination,
+        );
+        return;
+    }
+
     () = msg![env; source setValue:destination forKey:label];
 }
 
@@ -244,6 +371,11 @@ pub const CLASSES: ClassExports = objc_classes! {
 
 };
 
+fn is_proxy(env: &mut Environment, object: id) -> bool {
+    let ui_proxy_object_class = env.objc.get_known_class("UIProxyObject", &mut env.mem);
+    msg![env; object isKindOfClass:ui_proxy_object_class]
+}
+
 /// Shortcut for use by [super::ui_application::UIApplicationMain].
 /// Calls [load_nib_file] underneath.
 ///
@@ -253,23 +385,25 @@ pub const CLASSES: ClassExports = objc_classes! {
 /// return [nib instantiateWithOwner:[UIApplication sharedApplication]
 ///                     optionsOrNil:nil];
 /// ```
-pub fn load_main_nib_file(env: &mut Environment, _ui_application: id) {
+pub fn load_main_nib_file(env: &mut Environment, ui_application: id) {
     let Some(path) = env.bundle.main_nib_file_path() else {
         return;
     };
 
-    let loaded_nib = load_nib_file(env, path);
-
-    if let Ok(unarchiver) = loaded_nib {
-        release(env, unarchiver);
-    }
+    let _ = load_nib_file(env, path, ui_application, nil);
 }
 
-/// Takes a [GuestPathBuf] where a nib file is located and deserializes it.
+/// Takes a [GuestPathBuf] where a nib file is located and deserializes it,
+/// replacing the "File's Owner" with `owner`. `options` is an `NSDictionary*`
+/// that may specify external objects, or nil.
 /// Returns an empty [Err] if the file couldn't be loaded or an [Ok] wrapping
-/// an NSKeyedUnarchiver.
-/// The unarchiver should later be manually [release]d
-pub fn load_nib_file(env: &mut Environment, path: GuestPathBuf) -> Result<id, ()> {
+/// an autoreleased NSArray of the nib's top-level objects.
+pub fn load_nib_file(
+    env: &mut Environment,
+    path: GuestPathBuf,
+    owner: id,
+    options: id,
+) -> Result<id, ()> {
     let path = ns_string::from_rust_string(env, path.as_str().to_string());
     assert!(msg![env; path isAbsolutePath]);
     let ns_data: id = msg_class![env; NSData dataWithContentsOfFile:path];
@@ -277,8 +411,33 @@ pub fn load_nib_file(env: &mut Environment, path: GuestPathBuf) -> Result<id, ()
         // Apparently it's permitted to specify the nib file key in the
         // Info.plist, yet not have it point to a valid nib file?!
         log!("Warning: couldn't load nib file {:?}", path);
+        release(env, path);
         return Err(());
     };
+    release(env, path);
+
+    Ok(instantiate_nib(env, ns_data, owner, options))
+}
+
+/// The core of nib loading: deserializes the nib in `ns_data`, connects its
+/// outlets and actions, and sends `awakeFromNib` to its objects. Returns an
+/// autoreleased NSArray of the nib's top-level objects.
+fn instantiate_nib(env: &mut Environment, ns_data: id, owner: id, options: id) -> id {
+    let external_objects: id = if options == nil {
+        nil
+    } else {
+        let key = get_static_str(env, UINibExternalObjects);
+        msg![env; options objectForKey:key]
+    };
+    env.framework_state
+        .uikit
+        .ui_nib
+        .loading
+        .push(NibLoadingContext {
+            owner,
+            external_objects,
+            proxied_objects: Vec::new(),
+        });
 
     let unarchiver = msg_class![env; NSKeyedUnarchiver alloc];
     let unarchiver = msg![env; unarchiver initForReadingWithData:ns_data];
@@ -288,12 +447,13 @@ pub fn load_nib_file(env: &mut Environment, path: GuestPathBuf) -> Result<id, ()
     // UINibObjectsKey, UINibTopLevelObjectsKey and UINibVisibleWindowsKey.
     // Each corresponds to an NSArray.
 
-    // We don't need to do anything with the list of objects, but deserializing
-    // it ensures everything else is deserialized.
+    // Deserializing the list of objects ensures everything else is
+    // deserialized.
     let objects_key = get_static_str(env, "UINibObjectsKey");
-    let _objects: id = msg![env; unarchiver decodeObjectForKey:objects_key];
+    let objects: id = msg![env; unarchiver decodeObjectForKey:objects_key];
 
-    // Connect all the outlets with UIRuntimeOutletConnection
+    // Connect all the outlets and actions with UIRuntimeOutletConnection and
+    // UIRuntimeEventConnection
     let conns_key = get_static_str(env, "UINibConnectionsKey");
     let conns: id = msg![env; unarchiver decodeObjectForKey:conns_key];
     let conns_count: NSUInteger = msg![env; conns count];
@@ -302,6 +462,20 @@ pub fn load_nib_file(env: &mut Environment, path: GuestPathBuf) -> Result<id, ()
         () = msg![env; conn connect];
     }
 
+    let context = env.framework_state.uikit.ui_nib.loading.pop().unwrap();
+    let belongs_to_nib = |env: &mut Environment, object: id| {
+        !context.proxied_objects.contains(&object) && !is_proxy(env, object)
+    };
+
+    // Only once everything is connected can the objects be woken up.
+    let objects_count: NSUInteger = msg![env; objects count];
+    for i in 0..objects_count {
+        let object: id = msg![env; objects objectAtIndex:i];
+        if belongs_to_nib(env, object) {
+            () = msg![env; object awakeFromNib];
+        }
+    }
+
     // Make visible windows visible
     let visibles_key = get_static_str(env, "UINibVisibleWindowsKey");
     let visibles: id = msg![env; unarchiver decodeObjectForKey:visibles_key];
@@ -311,5 +485,18 @@ pub fn load_nib_file(env: &mut Environment, path: GuestPathBuf) -> Result<id, ()
         () = msg![env; visible setHidden:false];
     }
 
-    Ok(unarchiver)
+    let top_level_key = get_static_str(env, "UINibTopLevelObjectsKey");
+    let top_level: id = msg![env; unarchiver decodeObjectForKey:top_level_key];
+    let top_level_count: NSUInteger = msg![env; top_level count];
+    let result: id = msg_class![env; NSMutableArray array];
+    for i in 0..top_level_count {
+        let object: id = msg![env; top_level objectAtIndex:i];
+        if belongs_to_nib(env, object) {
+            () = msg![env; result addObject:object];
+        }
+    }
+
+    release(env, unarchiver);
+
+    result
 }
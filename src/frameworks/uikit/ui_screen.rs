@@ -41,7 +41,8 @@ pub const CLASSES: ClassExports = objc_classes! {
 // TODO: more accessors
 
 - (CGRect)bounds {
-    // TODO: once rotation is supported, this must change with the rotation!
+    // On iPhone OS, the screen's bounds are always in the portrait
+    // orientation, even when the interface is rotated.
     CGRect {
         origin: CGPoint { x: 0.0, y: 0.0 },
         size: CGSize { width: 320.0, height: 480.0 },
@@ -8,15 +8,25 @@
 //! Useful resources:
 //! - Apple's [View Programming Guide for iOS](https://developer.apple.com/library/archive/documentation/WindowsViews/Conceptual/ViewPG_iPhoneOS/Introduction/Introduction.html)
 
+pub mod ui_action_sheet;
 pub mod ui_alert_view;
 pub mod ui_control;
 pub mod ui_image_view;
+pub mod ui_keyboard;
 pub mod ui_label;
 pub mod ui_picker_view;
+pub mod ui_progress_view;
 pub mod ui_scroll_view;
+pub mod ui_toolbar;
+pub mod ui_web_view;
 pub mod ui_window;
 
 use super::ui_graphics::{UIGraphicsPopContext, UIGraphicsPushContext};
+use crate::frameworks::core_animation::ca_layer::{
+    kCAGravityBottom, kCAGravityBottomLeft, kCAGravityBottomRight, kCAGravityCenter,
+    kCAGravityLeft, kCAGravityResize, kCAGravityResizeAspect, kCAGravityResizeAspectFill,
+    kCAGravityRight, kCAGravityTop, kCAGravityTopLeft, kCAGravityTopRight,
+};
 use crate::frameworks::core_graphics::cg_affine_transform::{
     CGAffineTransform, CGAffineTransformIdentity,
 };
@@ -36,6 +46,7 @@ pub struct State {
     /// List of views for internal purposes. Non-retaining!
     pub(super) views: Vec<id>,
     pub ui_window: ui_window::State,
+    ui_keyboard: ui_keyboard::State,
 }
 
 pub(super) struct UIViewHostObject {
@@ -50,6 +61,15 @@ pub(super) struct UIViewHostObject {
     clears_context_before_drawing: bool,
     user_interaction_enabled: bool,
     multiple_touch_enabled: bool,
+    /// `UIViewAutoresizing` bitmask.
+    autoresizing_mask: NSUInteger,
+    autoresizes_subviews: bool,
+    /// `UIViewContentMode`.
+    content_mode: NSInteger,
+    tag: NSInteger,
+    /// Set by `setNeedsLayout`, cleared when `layoutSubviews` is called by
+    /// [layout_views_if_needed] or `layoutIfNeeded`.
+    needs_layout: bool,
 }
 impl HostObject for UIViewHostObject {}
 impl Default for UIViewHostObject {
@@ -64,14 +84,209 @@ impl Default for UIViewHostObject {
             clears_context_before_drawing: true,
             user_interaction_enabled: true,
             multiple_touch_enabled: false,
+            autoresizing_mask: UIViewAutoresizingNone,
+            autoresizes_subviews: true,
+            content_mode: UIViewContentModeScaleToFill,
+            tag: 0,
+            needs_layout: false,
+        }
+    }
+}
+
+pub type UIViewAutoresizing = NSUInteger;
+pub const UIViewAutoresizingNone: UIViewAutoresizing = 0;
+pub const UIViewAutoresizingFlexibleLeftMargin: UIViewAutoresizing = 1 << 0;
+pub const UIViewAutoresizingFlexibleWidth: UIViewAutoresizing = 1 << 1;
+pub const UIViewAutoresizingFlexibleRightMargin: UIViewAutoresizing = 1 << 2;
+pub const UIViewAutoresizingFlexibleTopMargin: UIViewAutoresizing = 1 << 3;
+pub const UIViewAutoresizingFlexibleHeight: UIViewAutoresizing = 1 << 4;
+pub const UIViewAutoresizingFlexibleBottomMargin: UIViewAutoresizing = 1 << 5;
+
+pub type UIViewContentMode = NSInteger;
+pub const UIViewContentModeScaleToFill: UIViewContentMode = 0;
+pub const UIViewContentModeScaleAspectFit: UIViewContentMode = 1;
+pub const UIViewContentModeScaleAspectFill: UIViewContentMode = 2;
+pub const UIViewContentModeRedraw: UIViewContentMode = 3;
+pub const UIViewContentModeCenter: UIViewContentMode = 4;
+pub const UIViewContentModeTop: UIViewContentMode = 5;
+pub const UIViewContentModeBottom: UIViewContentMode = 6;
+pub const UIViewContentModeLeft: UIViewContentMode = 7;
+pub const UIViewContentModeRight: UIViewContentMode = 8;
+pub const UIViewContentModeTopLeft: UIViewContentMode = 9;
+pub const UIViewContentModeTopRight: UIViewContentMode = 10;
+pub const UIViewContentModeBottomLeft: UIViewContentMode = 11;
+pub const UIViewContentModeBottomRight: UIViewContentMode = 12;
+
+/// The `CALayer` `contentsGravity` equivalent of a `UIViewContentMode`.
+fn gravity_for_content_mode(content_mode: UIViewContentMode) -> &'static str {
+    match content_mode {
+        UIViewContentModeScaleToFill | UIViewContentModeRedraw => kCAGravityResize,
+        UIViewContentModeScaleAspectFit => kCAGravityResizeAspect,
+        UIViewContentModeScaleAspectFill => kCAGravityResizeAspectFill,
+        UIViewContentModeCenter => kCAGravityCenter,
+        UIViewContentModeTop => kCAGravityTop,
+        UIViewContentModeBottom => kCAGravityBottom,
+        UIViewContentModeLeft => kCAGravityLeft,
+        UIViewContentModeRight => kCAGravityRight,
+        UIViewContentModeTopLeft => kCAGravityTopLeft,
+        UIViewContentModeTopRight => kCAGravityTopRight,
+        UIViewContentModeBottomLeft => kCAGravityBottomLeft,
+        UIViewContentModeBottomRight => kCAGravityBottomRight,
+        _ => {
+            log!(
+                "Warning: unknown UIViewContentMode {}, using scale to fill",
+                content_mode
+            );
+            kCAGravityResize
         }
     }
 }
 
+/// Compute the new position and length of a view along one axis, when its
+/// superview's length along that axis changes from `old_super` to `new_super`.
+///
+/// The change is distributed between the flexible parts (the margin before
+/// the view, the view itself, and the margin after it) in proportion to their
+/// current lengths, or evenly if they are all zero-length.
+fn autoresize_axis(
+    start: CGFloat,
+    length: CGFloat,
+    old_super: CGFloat,
+    new_super: CGFloat,
+    (flexible_start, flexible_length, flexible_end): (bool, bool, bool),
+) -> (CGFloat, CGFloat) {
+    let delta = new_super - old_super;
+    let end = old_super - start - length;
+
+    let parts = [
+        (flexible_start, start),
+        (flexible_length, length),
+        (flexible_end, end),
+    ];
+    let flexible_count = parts.iter().filter(|&&(flexible, _)| flexible).count();
+    if flexible_count == 0 {
+        return (start, length);
+    }
+    let flexible_total: CGFloat = parts
+        .iter()
+        .filter(|&&(flexible, _)| flexible)
+        .map(|&(_, part)| part)
+        .sum();
+    let share = |flexible: bool, part: CGFloat| -> CGFloat {
+        if !flexible {
+            0.0
+        } else if flexible_total == 0.0 {
+            delta / flexible_count as CGFloat
+        } else {
+            delta * part / flexible_total
+        }
+    };
+
+    (
+        start + share(flexible_start, start),
+        (length + share(flexible_length, length)).max(0.0),
+    )
+}
+
+/// Applies the autoresizing masks of `view`'s subviews after `view`'s bounds
+/// size changed from `old_size`, and marks it as needing layout.
+fn bounds_size_changed(env: &mut Environment, view: id, old_size: CGSize) {
+    let new_size: CGRect = msg![env; view bounds];
+    let new_size = new_size.size;
+    if new_size == old_size {
+        return;
+    }
+
+    let &UIViewHostObject {
+        autoresizes_subviews,
+        content_mode,
+        ..
+    } = env.objc.borrow(view);
+    env.objc.borrow_mut::<UIViewHostObject>(view).needs_layout = true;
+    if content_mode == UIViewContentModeRedraw {
+        () = msg![env; view setNeedsDisplay];
+    }
+    if !autoresizes_subviews {
+        return;
+    }
+
+    let subviews = env.objc.borrow::<UIViewHostObject>(view).subviews.clone();
+    for subview in subviews {
+        let mask = env
+            .objc
+            .borrow::<UIViewHostObject>(subview)
+            .autoresizing_mask;
+        if mask == UIViewAutoresizingNone {
+            continue;
+        }
+        let mut frame: CGRect = msg![env; subview frame];
+        (frame.origin.x, frame.size.width) = autoresize_axis(
+            frame.origin.x,
+            frame.size.width,
+            old_size.width,
+            new_size.width,
+            (
+                mask & UIViewAutoresizingFlexibleLeftMargin != 0,
+                mask & UIViewAutoresizingFlexibleWidth != 0,
+                mask & UIViewAutoresizingFlexibleRightMargin != 0,
+            ),
+        );
+        (frame.origin.y, frame.size.height) = autoresize_axis(
+            frame.origin.y,
+            frame.size.height,
+            old_size.height,
+            new_size.height,
+            (
+                mask & UIViewAutoresizingFlexibleTopMargin != 0,
+                mask & UIViewAutoresizingFlexibleHeight != 0,
+                mask & UIViewAutoresizingFlexibleBottomMargin != 0,
+            ),
+        );
+        () = msg![env; subview setFrame:frame];
+    }
+}
+
+/// For use by [super::handle_events]: calls `layoutSubviews` on any views
+/// that had `setNeedsLayout` called on them (explicitly or by resizing) since
+/// the last time this was called.
+pub(super) fn layout_views_if_needed(env: &mut Environment) {
+    // Layout can cause other views to need layout, so loop until settled, but
+    // give up eventually in case some views never settle.
+    const MAX_PASSES: usize = 100;
+    for _ in 0..MAX_PASSES {
+        let views: Vec<id> = env
+            .framework_state
+            .uikit
+            .ui_view
+            .views
+            .iter()
+            .copied()
+            .filter(|&view| env.objc.borrow::<UIViewHostObject>(view).needs_layout)
+            .collect();
+        if views.is_empty() {
+            return;
+        }
+        for view in views {
+            // An earlier view's layoutSubviews may have freed this view.
+            if !env.framework_state.uikit.ui_view.views.contains(&view) {
+                continue;
+            }
+            () = msg![env; view layoutIfNeeded];
+        }
+    }
+    log!(
+        "Warning: views still need layout after {} passes, giving up until next time",
+        MAX_PASSES
+    );
+}
+
 pub fn set_view_controller(env: &mut Environment, view: id, controller: id) {
     let host_obj = env.objc.borrow_mut::<UIViewHostObject>(view);
     host_obj.view_controller = controller;
 }
+pub fn get_view_controller(env: &Environment, view: id) -> id {
+    env.objc.borrow::<UIViewHostObject>(view).view_controller
+}
 
 /// Shared parts of `initWithCoder:` and `initWithFrame:`. These can't call
 /// `init`: the subclass may have overridden `init` and will not expect to be
@@ -139,8 +354,6 @@ pub const CLASSES: ClassExports = objc_classes! {
 - (id)initWithCoder:(id)coder {
     let this = init_common(env, this);
 
-    // TODO: decode the various other UIView properties
-
     let key_ns_string = get_static_str(env, "UIBounds");
     let bounds: CGRect = msg![env; coder decodeCGRectForKey:key_ns_string];
 
@@ -153,6 +366,49 @@ pub const CLASSES: ClassExports = objc_classes! {
     let key_ns_string = get_static_str(env, "UIOpaque");
     let opaque: bool = msg![env; coder decodeBoolForKey:key_ns_string];
 
+    let key_ns_string = get_static_str(env, "UIAlpha");
+    let alpha: CGFloat = if msg![env; coder containsValueForKey:key_ns_string] {
+        msg![env; coder decodeFloatForKey:key_ns_string]
+    } else {
+        1.0
+    };
+
+    let key_ns_string = get_static_str(env, "UIBackgroundColor");
+    let background_color: id = msg![env; coder decodeObjectForKey:key_ns_string];
+
+    let key_ns_string = get_static_str(env, "UIClipsToBounds");
+    let clips_to_bounds: bool = msg![env; coder decodeBoolForKey:key_ns_string];
+
+    let key_ns_string = get_static_str(env, "UIClearsContextBeforeDrawing");
+    let clears_context_before_drawing: bool =
+        if msg![env; coder containsValueForKey:key_ns_string] {
+            msg![env; coder decodeBoolForKey:key_ns_string]
+        } else {
+            true
+        };
+
+    let key_ns_string = get_static_str(env, "UIUserInteractionDisabled");
+    let user_interaction_disabled: bool = msg![env; coder decodeBoolForKey:key_ns_string];
+
+    let key_ns_string = get_static_str(env, "UIMultipleTouchEnabled");
+    let multiple_touch_enabled: bool = msg![env; coder decodeBoolForKey:key_ns_string];
+
+    let key_ns_string = get_static_str(env, "UITag");
+    let tag: NSInteger = msg![env; coder decodeIntegerForKey:key_ns_string];
+
+    let key_ns_string = get_static_str(env, "UIAutoresizingMask");
+    let autoresizing_mask: NSInteger = msg![env; coder decodeIntegerForKey:key_ns_string];
+
+    let key_ns_string = get_static_str(env, "UIAutoresizeSubviews");
+    let autoresizes_subviews: bool = if msg![env; coder containsValueForKey:key_ns_string] {
+        msg![env; coder decodeBoolForKey:key_ns_string]
+    } else {
+        true
+    };
+
+    let key_ns_string = get_static_str(env, "UIContentMode");
+    let content_mode: NSInteger = msg![env; coder decodeIntegerForKey:key_ns_string];
+
     let key_ns_string = get_static_str(env, "UISubviews");
     let subviews: id = msg![env; coder decodeObjectForKey:key_ns_string];
     let subview_count: NSUInteger = msg![env; subviews count];
@@ -172,6 +428,20 @@ pub const CLASSES: ClassExports = objc_classes! {
     () = msg![env; this setCenter:center];
     () = msg![env; this setHidden:hidden];
     () = msg![env; this setOpaque:opaque];
+    () = msg![env; this setAutoresizingMask:(autoresizing_mask as NSUInteger)];
+    () = msg![env; this setAutoresizesSubviews:autoresizes_subviews];
+    () = msg![env; this setContentMode:content_mode];
+    () = msg![env; this setAlpha:alpha];
+    if background_color != nil {
+        () = msg![env; this setBackgroundColor:background_color];
+    }
+    if clips_to_bounds {
+        () = msg![env; this setClipsToBounds:true];
+    }
+    () = msg![env; this setClearsContextBeforeDrawing:clears_context_before_drawing];
+    () = msg![env; this setUserInteractionEnabled:(!user_interaction_disabled)];
+    () = msg![env; this setMultipleTouchEnabled:multiple_touch_enabled];
+    () = msg![env; this setTag:tag];
 
     for i in 0..subview_count {
         let subview: id = msg![env; subviews objectAtIndex:i];
@@ -203,6 +473,15 @@ pub const CLASSES: ClassExports = objc_classes! {
     // On iOS 5.1 and earlier, the default implementation of this method does
     // nothing.
 }
+- (())setNeedsLayout {
+    env.objc.borrow_mut::<UIViewHostObject>(this).needs_layout = true;
+}
+- (())layoutIfNeeded {
+    let host_obj = env.objc.borrow_mut::<UIViewHostObject>(this);
+    if std::mem::take(&mut host_obj.needs_layout) {
+        () = msg![env; this layoutSubviews];
+    }
+}
 
 - (id)superview {
     env.objc.borrow::<UIViewHostObject>(this).superview
@@ -225,6 +504,27 @@ pub const CLASSES: ClassExports = objc_classes! {
     window
 }
 
+- (NSInteger)tag {
+    env.objc.borrow::<UIViewHostObject>(this).tag
+}
+- (())setTag:(NSInteger)tag {
+    env.objc.borrow_mut::<UIViewHostObject>(this).tag = tag;
+}
+- (id)viewWithTag:(NSInteger)tag {
+    // The search is depth-first and includes the view itself.
+    if env.objc.borrow::<UIViewHostObject>(this).tag == tag {
+        return this;
+    }
+    let subviews = env.objc.borrow::<UIViewHostObject>(this).subviews.clone();
+    for subview in subviews {
+        let found: id = msg![env; subview viewWithTag:tag];
+        if found != nil {
+            return found;
+        }
+    }
+    nil
+}
+
 - (id)subviews {
     let views = env.objc.borrow::<UIViewHostObject>(this).subviews.clone();
     for view in &views {
@@ -312,6 +612,11 @@ pub const CLASSES: ClassExports = objc_classes! {
         clears_context_before_drawing: _,
         user_interaction_enabled: _,
         multiple_touch_enabled: _,
+        autoresizing_mask: _,
+        autoresizes_subviews: _,
+        content_mode: _,
+        tag: _,
+        needs_layout: _,
     } = std::mem::take(env.objc.borrow_mut(this));
 
     release(env, layer);
@@ -387,7 +692,9 @@ pub const CLASSES: ClassExports = objc_classes! {
 }
 - (())setBounds:(CGRect)bounds {
     let layer = env.objc.borrow::<UIViewHostObject>(this).layer;
-    msg![env; layer setBounds:bounds]
+    let old_bounds: CGRect = msg![env; layer bounds];
+    () = msg![env; layer setBounds:bounds];
+    bounds_size_changed(env, this, old_bounds.size);
 }
 - (CGPoint)center {
     // FIXME: what happens if [layer anchorPoint] isn't (0.5, 0.5)?
@@ -404,7 +711,9 @@ pub const CLASSES: ClassExports = objc_classes! {
 }
 - (())setFrame:(CGRect)frame {
     let layer = env.objc.borrow::<UIViewHostObject>(this).layer;
-    msg![env; layer setFrame:frame]
+    let old_bounds: CGRect = msg![env; layer bounds];
+    () = msg![env; layer setFrame:frame];
+    bounds_size_changed(env, this, old_bounds.size);
 }
 
 - (CGAffineTransform)transform {
@@ -414,8 +723,16 @@ pub const CLASSES: ClassExports = objc_classes! {
     log!("TODO: [{:?} setTransform:{:?}]", this, transform);
 }
 
-- (())setContentMode:(NSInteger)content_mode { // should be UIViewContentMode
-    log!("TODO: [UIView {:?} setContentMode:{:?}] => ()", this, content_mode);
+- (UIViewContentMode)contentMode {
+    env.objc.borrow::<UIViewHostObject>(this).content_mode
+}
+- (())setContentMode:(UIViewContentMode)content_mode {
+    let gravity = gravity_for_content_mode(content_mode);
+    let gravity = get_static_str(env, gravity);
+    let host_obj = env.objc.borrow_mut::<UIViewHostObject>(this);
+    host_obj.content_mode = content_mode;
+    let layer = host_obj.layer;
+    () = msg![env; layer setContentsGravity:gravity];
 }
 
 - (bool)clearsContextBeforeDrawing {
@@ -540,11 +857,17 @@ pub const CLASSES: ClassExports = objc_classes! {
     msg![env; this_layer convertPoint:point toLayer:other_layer]
 }
 
-- (())setAutoresizingMask:(NSUInteger)mask {
-    log!("TODO: [(UIView*){:?} setAutoresizingMask:{}]", this, mask);
+- (UIViewAutoresizing)autoresizingMask {
+    env.objc.borrow::<UIViewHostObject>(this).autoresizing_mask
+}
+- (())setAutoresizingMask:(UIViewAutoresizing)mask {
+    env.objc.borrow_mut::<UIViewHostObject>(this).autoresizing_mask = mask;
+}
+- (bool)autoresizesSubviews {
+    env.objc.borrow::<UIViewHostObject>(this).autoresizes_subviews
 }
 - (())setAutoresizesSubviews:(bool)enabled {
-    log!("TODO: [(UIView*){:?} setAutoresizesSubviews:{}]", this, enabled);
+    env.objc.borrow_mut::<UIViewHostObject>(this).autoresizes_subviews = enabled;
 }
 
 - (CGSize)sizeThatFits:(CGSize)size {
@@ -552,9 +875,47 @@ pub const CLASSES: ClassExports = objc_classes! {
     size
 }
 - (())sizeToFit {
-    log!("TODO: [(UIView *){:?} sizeToFit]", this);
+    let bounds: CGRect = msg![env; this bounds];
+    let size: CGSize = msg![env; this sizeThatFits:(bounds.size)];
+    let mut frame: CGRect = msg![env; this frame];
+    frame.size = size;
+    () = msg![env; this setFrame:frame];
 }
 
 @end
 
 };
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_autoresize_axis() {
+        // Fixed margins, flexible width: the view takes all the change.
+        assert_eq!(
+            autoresize_axis(10.0, 80.0, 100.0, 200.0, (false, true, false)),
+            (10.0, 180.0)
+        );
+        // Flexible margins only: the view stays centered.
+        assert_eq!(
+            autoresize_axis(10.0, 80.0, 100.0, 200.0, (true, false, true)),
+            (60.0, 80.0)
+        );
+        // Everything flexible: proportional distribution.
+        assert_eq!(
+            autoresize_axis(25.0, 50.0, 100.0, 200.0, (true, true, true)),
+            (50.0, 100.0)
+        );
+        // Flexible left margin only: the view sticks to the right edge.
+        assert_eq!(
+            autoresize_axis(10.0, 80.0, 100.0, 50.0, (true, false, false)),
+            (-40.0, 80.0)
+        );
+        // Nothing flexible.
+        assert_eq!(
+            autoresize_axis(10.0, 80.0, 100.0, 200.0, (false, false, false)),
+            (10.0, 80.0)
+        );
+    }
+}
@@ -0,0 +1,442 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! `UIActionSheet`.
+//!
+//! Like `UIAlertView`, the sheet is presented as a full-screen subview of the
+//! window, so that it can dim the rest of the UI and block touches to it.
+
+use super::ui_alert_view::{
+    collect_button_titles, create_button, delegate_responds, draw_centered_text, draw_dimming,
+    text_height, window_for_presenting,
+};
+use crate::frameworks::core_graphics::cg_context::{CGContextFillRect, CGContextSetRGBFillColor};
+use crate::frameworks::core_graphics::{CGFloat, CGPoint, CGRect, CGSize};
+use crate::frameworks::foundation::NSInteger;
+use crate::frameworks::uikit::ui_graphics::UIGraphicsGetCurrentContext;
+use crate::objc::{
+    id, impl_HostObject_with_superclass, msg, msg_class, msg_super, nil, objc_classes, release,
+    retain, ClassExports, NSZonePtr,
+};
+use crate::Environment;
+
+type UIActionSheetStyle = NSInteger;
+#[allow(dead_code)]
+const UIActionSheetStyleAutomatic: UIActionSheetStyle = -1;
+#[allow(dead_code)]
+const UIActionSheetStyleDefault: UIActionSheetStyle = 0;
+const UIActionSheetStyleBlackTranslucent: UIActionSheetStyle = 1;
+const UIActionSheetStyleBlackOpaque: UIActionSheetStyle = 2;
+
+/// Padding around the edges of the sheet and between buttons.
+const PADDING: CGFloat = 10.0;
+/// Extra gap above the cancel button.
+const CANCEL_GAP: CGFloat = 8.0;
+const BUTTON_HEIGHT: CGFloat = 46.0;
+
+pub struct UIActionSheetHostObject {
+    superclass: super::UIViewHostObject,
+    /// `NSString*`
+    title: id,
+    /// Weak reference.
+    delegate: id,
+    /// `NSString*`s
+    button_titles: Vec<id>,
+    /// `UIButton*`s, created when the sheet is shown.
+    buttons: Vec<id>,
+    cancel_button_index: NSInteger,
+    destructive_button_index: NSInteger,
+    style: UIActionSheetStyle,
+    visible: bool,
+}
+impl_HostObject_with_superclass!(UIActionSheetHostObject);
+impl Default for UIActionSheetHostObject {
+    fn default() -> Self {
+        UIActionSheetHostObject {
+            superclass: Default::default(),
+            title: nil,
+            delegate: nil,
+            button_titles: Vec::new(),
+            buttons: Vec::new(),
+            cancel_button_index: -1,
+            destructive_button_index: -1,
+            style: UIActionSheetStyleAutomatic,
+            visible: false,
+        }
+    }
+}
+
+fn title_font(env: &mut Environment) -> id {
+    msg_class![env; UIFont systemFontOfSize:(13.0 as CGFloat)]
+}
+
+/// Rects of the sheet background, the title and each button.
+struct Layout {
+    sheet: CGRect,
+    title: CGRect,
+    buttons: Vec<CGRect>,
+}
+
+fn layout(env: &mut Environment, this: id) -> Layout {
+    let bounds: CGRect = msg![env; this bounds];
+    let &UIActionSheetHostObject {
+        title,
+        cancel_button_index,
+        ..
+    } = env.objc.borrow(this);
+    let button_count = env
+        .objc
+        .borrow::<UIActionSheetHostObject>(this)
+        .button_titles
+        .len();
+    let cancel_index = usize::try_from(cancel_button_index)
+        .ok()
+        .filter(|&i| i < button_count);
+
+    let content_width = bounds.size.width - PADDING * 2.0;
+    let font = title_font(env);
+    let title_height = text_height(env, title, font, content_width);
+    let title_block = if title != nil {
+        title_height + PADDING
+    } else {
+        0.0
+    };
+    let buttons_height = button_count as CGFloat * (BUTTON_HEIGHT + PADDING)
+        + if cancel_index.is_some() {
+            CANCEL_GAP
+        } else {
+            0.0
+        };
+    let sheet_height = PADDING + title_block + buttons_height;
+
+    // The sheet rises from the bottom of the screen.
+    let sheet = CGRect {
+        origin: CGPoint {
+            x: bounds.origin.x,
+            y: bounds.origin.y + bounds.size.height - sheet_height,
+        },
+        size: CGSize {
+            width: bounds.size.width,
+            height: sheet_height,
+        },
+    };
+    let title = CGRect {
+        origin: CGPoint {
+            x: sheet.origin.x + PADDING,
+            y: sheet.origin.y + PADDING,
+        },
+        size: CGSize {
+            width: content_width,
+            height: title_height,
+        },
+    };
+
+    // The cancel button always goes at the bottom, separated from the others.
+    let mut y = sheet.origin.y + PADDING + title_block;
+    let mut buttons = vec![CGRect::default(); button_count];
+    let order = (0..button_count)
+        .filter(|&i| Some(i) != cancel_index)
+        .chain(cancel_index);
+    for i in order {
+        if Some(i) == cancel_index {
+            y += CANCEL_GAP;
+        }
+        buttons[i] = CGRect {
+            origin: CGPoint {
+                x: sheet.origin.x + PADDING,
+                y,
+            },
+            size: CGSize {
+                width: content_width,
+                height: BUTTON_HEIGHT,
+            },
+        };
+        y += BUTTON_HEIGHT + PADDING;
+    }
+
+    Layout {
+        sheet,
+        title,
+        buttons,
+    }
+}
+
+fn show_in_view(env: &mut Environment, this: id, view: id) {
+    if env.objc.borrow::<UIActionSheetHostObject>(this).visible {
+        return;
+    }
+    let Some(window) = window_for_presenting(env, view) else {
+        log!("Warning: no window to show UIActionSheet {:?} in", this);
+        return;
+    };
+
+    let delegate = env.objc.borrow::<UIActionSheetHostObject>(this).delegate;
+    if delegate_responds(env, delegate, "willPresentActionSheet:") {
+        () = msg![env; delegate willPresentActionSheet:this];
+    }
+
+    let &UIActionSheetHostObject {
+        cancel_button_index,
+        destructive_button_index,
+        ..
+    } = env.objc.borrow(this);
+    let titles = env
+        .objc
+        .borrow::<UIActionSheetHostObject>(this)
+        .button_titles
+        .clone();
+    let buttons: Vec<id> = titles
+        .into_iter()
+        .enumerate()
+        .map(|(i, title)| {
+            let i = i as NSInteger;
+            if i == destructive_button_index {
+                create_button(env, this, title, (0.8, 0.1, 0.1, 1.0), false)
+            } else if i == cancel_button_index {
+                create_button(env, this, title, (0.2, 0.2, 0.2, 1.0), false)
+            } else {
+                create_button(env, this, title, (0.9, 0.9, 0.9, 1.0), true)
+            }
+        })
+        .collect();
+    for &button in &buttons {
+        () = msg![env; this addSubview:button];
+    }
+    let host_obj = env.objc.borrow_mut::<UIActionSheetHostObject>(this);
+    host_obj.buttons = buttons;
+    host_obj.visible = true;
+
+    let window_bounds: CGRect = msg![env; window bounds];
+    () = msg![env; this setFrame:window_bounds];
+    () = msg![env; window addSubview:this];
+    () = msg![env; this layoutSubviews];
+    () = msg![env; this setNeedsDisplay];
+
+    if delegate_responds(env, delegate, "didPresentActionSheet:") {
+        () = msg![env; delegate didPresentActionSheet:this];
+    }
+}
+
+fn dismiss(env: &mut Environment, this: id, button_index: NSInteger) {
+    if !env.objc.borrow::<UIActionSheetHostObject>(this).visible {
+        return;
+    }
+
+    // The delegate might release the sheet, and so does removing it from its
+    // superview, so keep it alive until the end.
+    retain(env, this);
+
+    let delegate = env.objc.borrow::<UIActionSheetHostObject>(this).delegate;
+    if delegate_responds(env, delegate, "actionSheet:willDismissWithButtonIndex:") {
+        () = msg![env; delegate actionSheet:this willDismissWithButtonIndex:button_index];
+    }
+
+    let host_obj = env.objc.borrow_mut::<UIActionSheetHostObject>(this);
+    host_obj.visible = false;
+    let buttons = std::mem::take(&mut host_obj.buttons);
+    for button in buttons {
+        () = msg![env; button removeFromSuperview];
+        release(env, button);
+    }
+    () = msg![env; this removeFromSuperview];
+
+    let delegate = env.objc.borrow::<UIActionSheetHostObject>(this).delegate;
+    if delegate_responds(env, delegate, "actionSheet:didDismissWithButtonIndex:") {
+        () = msg![env; delegate actionSheet:this didDismissWithButtonIndex:button_index];
+    }
+
+    release(env, this);
+}
+
+pub const CLASSES: ClassExports = objc_classes! {
+
+(env, this, _cmd);
+
+@implementation UIActionSheet: UIView
+
++ (id)allocWithZone:(NSZonePtr)_zone {
+    let host_object = Box::<UIActionSheetHostObject>::default();
+    env.objc.alloc_object(this, host_object, &mut env.mem)
+}
+
+- (id)initWithTitle:(id)title // NSString*
+               delegate:(id)delegate
+      cancelButtonTitle:(id)cancel_button_title // NSString*
+ destructiveButtonTitle:(id)destructive_button_title // NSString*
+      otherButtonTitles:(id)first_other_button_title, ...dots { // NSString*
+    let this: id = msg![env; this init];
+
+    let title: id = msg![env; title copy];
+    let other_titles = collect_button_titles(env, first_other_button_title, dots.start());
+
+    let host_obj = env.objc.borrow_mut::<UIActionSheetHostObject>(this);
+    host_obj.title = title;
+    host_obj.delegate = delegate;
+
+    // Buttons are ordered: destructive, others, cancel.
+    if destructive_button_title != nil {
+        () = msg![env; this addButtonWithTitle:destructive_button_title];
+        env.objc.borrow_mut::<UIActionSheetHostObject>(this).destructive_button_index = 0;
+    }
+    env.objc.borrow_mut::<UIActionSheetHostObject>(this).button_titles.extend(other_titles);
+    if cancel_button_title != nil {
+        let index: NSInteger = msg![env; this addButtonWithTitle:cancel_button_title];
+        env.objc.borrow_mut::<UIActionSheetHostObject>(this).cancel_button_index = index;
+    }
+
+    this
+}
+
+- (id)initWithFrame:(CGRect)frame {
+    let this: id = msg_super![env; this initWithFrame:frame];
+    () = msg![env; this setOpaque:false];
+    this
+}
+
+- (())dealloc {
+    let UIActionSheetHostObject {
+        title,
+        button_titles,
+        buttons,
+        ..
+    } = std::mem::take(env.objc.borrow_mut(this));
+    release(env, title);
+    for title in button_titles {
+        release(env, title);
+    }
+    for button in buttons {
+        release(env, button);
+    }
+    msg_super![env; this dealloc]
+}
+
+- (id)title {
+    env.objc.borrow::<UIActionSheetHostObject>(this).title
+}
+- (())setTitle:(id)title { // NSString*
+    let title: id = msg![env; title copy];
+    let host_obj = env.objc.borrow_mut::<UIActionSheetHostObject>(this);
+    let old_title = std::mem::replace(&mut host_obj.title, title);
+    release(env, old_title);
+    () = msg![env; this layoutSubviews];
+    () = msg![env; this setNeedsDisplay];
+}
+
+- (id)delegate {
+    env.objc.borrow::<UIActionSheetHostObject>(this).delegate
+}
+- (())setDelegate:(id)delegate {
+    env.objc.borrow_mut::<UIActionSheetHostObject>(this).delegate = delegate;
+}
+
+- (UIActionSheetStyle)actionSheetStyle {
+    env.objc.borrow::<UIActionSheetHostObject>(this).style
+}
+- (())setActionSheetStyle:(UIActionSheetStyle)style {
+    env.objc.borrow_mut::<UIActionSheetHostObject>(this).style = style;
+    () = msg![env; this setNeedsDisplay];
+}
+
+- (NSInteger)addButtonWithTitle:(id)title { // NSString*
+    let title: id = msg![env; title copy];
+    let host_obj = env.objc.borrow_mut::<UIActionSheetHostObject>(this);
+    host_obj.button_titles.push(title);
+    (host_obj.button_titles.len() - 1).try_into().unwrap()
+}
+- (NSInteger)numberOfButtons {
+    let count = env.objc.borrow::<UIActionSheetHostObject>(this).button_titles.len();
+    count.try_into().unwrap()
+}
+- (id)buttonTitleAtIndex:(NSInteger)index {
+    let titles = &env.objc.borrow::<UIActionSheetHostObject>(this).button_titles;
+    usize::try_from(index).ok().and_then(|i| titles.get(i)).copied().unwrap_or(nil)
+}
+
+- (NSInteger)cancelButtonIndex {
+    env.objc.borrow::<UIActionSheetHostObject>(this).cancel_button_index
+}
+- (())setCancelButtonIndex:(NSInteger)index {
+    env.objc.borrow_mut::<UIActionSheetHostObject>(this).cancel_button_index = index;
+}
+- (NSInteger)destructiveButtonIndex {
+    env.objc.borrow::<UIActionSheetHostObject>(this).destructive_button_index
+}
+- (())setDestructiveButtonIndex:(NSInteger)index {
+    env.objc.borrow_mut::<UIActionSheetHostObject>(this).destructive_button_index = index;
+}
+- (NSInteger)firstOtherButtonIndex {
+    let host_obj = env.objc.borrow::<UIActionSheetHostObject>(this);
+    (0..host_obj.button_titles.len() as NSInteger)
+        .find(|&i| i != host_obj.cancel_button_index && i != host_obj.destructive_button_index)
+        .unwrap_or(-1)
+}
+
+- (bool)isVisible {
+    env.objc.borrow::<UIActionSheetHostObject>(this).visible
+}
+
+- (())showInView:(id)view { // UIView*
+    show_in_view(env, this, view);
+}
+- (())showFromToolbar:(id)toolbar { // UIToolbar*
+    show_in_view(env, this, toolbar);
+}
+- (())showFromTabBar:(id)tab_bar { // UITabBar*
+    show_in_view(env, this, tab_bar);
+}
+
+- (())dismissWithClickedButtonIndex:(NSInteger)button_index animated:(bool)_animated {
+    // TODO: animation
+    dismiss(env, this, button_index);
+}
+
+- (())_touchHLE_buttonClicked:(id)button { // UIButton*
+    let buttons = &env.objc.borrow::<UIActionSheetHostObject>(this).buttons;
+    let Some(index) = buttons.iter().position(|&b| b == button) else {
+        return;
+    };
+    let index = index as NSInteger;
+
+    retain(env, this);
+    let delegate = env.objc.borrow::<UIActionSheetHostObject>(this).delegate;
+    if delegate_responds(env, delegate, "actionSheet:clickedButtonAtIndex:") {
+        () = msg![env; delegate actionSheet:this clickedButtonAtIndex:index];
+    }
+    dismiss(env, this, index);
+    release(env, this);
+}
+
+- (())layoutSubviews {
+    let layout = layout(env, this);
+    let buttons = env.objc.borrow::<UIActionSheetHostObject>(this).buttons.clone();
+    for (button, frame) in buttons.into_iter().zip(layout.buttons) {
+        () = msg![env; button setFrame:frame];
+    }
+}
+
+- (())drawRect:(CGRect)_rect {
+    let bounds: CGRect = msg![env; this bounds];
+    draw_dimming(env, bounds);
+
+    let layout = layout(env, this);
+    let context = UIGraphicsGetCurrentContext(env);
+    let &UIActionSheetHostObject { title, style, .. } = env.objc.borrow(this);
+    let (r, g, b, a) = match style {
+        UIActionSheetStyleBlackTranslucent => (0.0, 0.0, 0.0, 0.8),
+        UIActionSheetStyleBlackOpaque => (0.0, 0.0, 0.0, 1.0),
+        // UIActionSheetStyleAutomatic, UIActionSheetStyleDefault
+        _ => (0.35, 0.4, 0.5, 0.95),
+    };
+    CGContextSetRGBFillColor(env, context, r, g, b, a);
+    CGContextFillRect(env, context, layout.sheet);
+
+    CGContextSetRGBFillColor(env, context, 1.0, 1.0, 1.0, 1.0);
+    let font = title_font(env);
+    draw_centered_text(env, title, font, layout.title);
+}
+
+@end
+
+};
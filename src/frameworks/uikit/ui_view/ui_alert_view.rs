@@ -4,32 +4,560 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 //! `UIAlertView`.
+//!
+//! The alert is presented by adding it to the top window as a full-screen
+//! subview. It draws the dimmed background and the alert box itself, and
+//! swallows any touches that don't hit one of its buttons, which makes it
+//! modal.
 
-use crate::frameworks::foundation::ns_string;
-use crate::objc::{id, msg_super, objc_classes, ClassExports};
+use super::ui_control::ui_button::UIButtonTypeCustom;
+use super::ui_control::{UIControlEventTouchUpInside, UIControlStateNormal};
+use crate::abi::VaList;
+use crate::frameworks::core_graphics::cg_context::{CGContextFillRect, CGContextSetRGBFillColor};
+use crate::frameworks::core_graphics::{CGFloat, CGPoint, CGRect, CGSize};
+use crate::frameworks::foundation::{ns_string, NSInteger};
+use crate::frameworks::uikit::ui_font::{UILineBreakModeWordWrap, UITextAlignmentCenter};
+use crate::frameworks::uikit::ui_graphics::UIGraphicsGetCurrentContext;
+use crate::objc::{
+    id, impl_HostObject_with_superclass, msg, msg_class, msg_super, nil, objc_classes, release,
+    retain, ClassExports, NSZonePtr, SEL,
+};
+use crate::Environment;
+
+/// Width of the alert box.
+const BOX_WIDTH: CGFloat = 284.0;
+/// Padding inside the alert box, and between its elements.
+const PADDING: CGFloat = 12.0;
+const BUTTON_HEIGHT: CGFloat = 43.0;
+
+pub struct UIAlertViewHostObject {
+    superclass: super::UIViewHostObject,
+    /// `NSString*`
+    title: id,
+    /// `NSString*`
+    message: id,
+    /// Weak reference.
+    delegate: id,
+    /// `NSString*`s
+    button_titles: Vec<id>,
+    /// `UIButton*`s, created when the alert is shown.
+    buttons: Vec<id>,
+    cancel_button_index: NSInteger,
+    visible: bool,
+}
+impl_HostObject_with_superclass!(UIAlertViewHostObject);
+impl Default for UIAlertViewHostObject {
+    fn default() -> Self {
+        UIAlertViewHostObject {
+            superclass: Default::default(),
+            title: nil,
+            message: nil,
+            delegate: nil,
+            button_titles: Vec::new(),
+            buttons: Vec::new(),
+            cancel_button_index: -1,
+            visible: false,
+        }
+    }
+}
+
+/// Collects the titles from a nil-terminated `otherButtonTitles:` list.
+/// The titles are copied.
+pub(super) fn collect_button_titles(env: &mut Environment, first: id, mut dots: VaList) -> Vec<id> {
+    let mut titles = Vec::new();
+    let mut title = first;
+    while title != nil {
+        titles.push(msg![env; title copy]);
+        title = dots.next(env);
+    }
+    titles
+}
+
+/// Returns the window a modal view should be added to, if there is one.
+pub fn window_for_presenting(env: &mut Environment, view: id) -> Option<id> {
+    let window: id = if view != nil {
+        msg![env; view window]
+    } else {
+        nil
+    };
+    if window != nil {
+        return Some(window);
+    }
+    env.framework_state
+        .uikit
+        .ui_view
+        .ui_window
+        .visible_windows
+        .last()
+        .copied()
+}
+
+/// Creates a (retained) button for a modal view's button list. The button
+/// sends `_touchHLE_buttonClicked:` to `target` when tapped.
+pub(super) fn create_button(
+    env: &mut Environment,
+    target: id,
+    title: id,
+    background: (CGFloat, CGFloat, CGFloat, CGFloat),
+    dark_text: bool,
+) -> id {
+    let button: id = msg_class![env; UIButton buttonWithType:UIButtonTypeCustom];
+    retain(env, button);
+    () = msg![env; button setTitle:title forState:UIControlStateNormal];
+    let text_color: id = if dark_text {
+        msg_class![env; UIColor blackColor]
+    } else {
+        msg_class![env; UIColor whiteColor]
+    };
+    () = msg![env; button setTitleColor:text_color forState:UIControlStateNormal];
+    let (r, g, b, a) = background;
+    let bg_color: id = msg_class![env; UIColor colorWithRed:r green:g blue:b alpha:a];
+    () = msg![env; button setBackgroundColor:bg_color];
+    let font: id = msg_class![env; UIFont boldSystemFontOfSize:(16.0 as CGFloat)];
+    () = msg![env; button setFont:font];
+
+    let selector = env
+        .objc
+        .lookup_selector("_touchHLE_buttonClicked:")
+        .unwrap();
+    () = msg![env; button addTarget:target
+                             action:selector
+                   forControlEvents:UIControlEventTouchUpInside];
+    button
+}
+
+/// Whether a delegate exists and implements an optional delegate method.
+pub fn delegate_responds(env: &mut Environment, delegate: id, selector: &str) -> bool {
+    if delegate == nil {
+        return false;
+    }
+    let sel: SEL = env
+        .objc
+        .register_host_selector(selector.to_string(), &mut env.mem);
+    msg![env; delegate respondsToSelector:sel]
+}
+
+/// Fills a view's bounds with the translucent black used behind modal views.
+pub(super) fn draw_dimming(env: &mut Environment, bounds: CGRect) {
+    let context = UIGraphicsGetCurrentContext(env);
+    CGContextSetRGBFillColor(env, context, 0.0, 0.0, 0.0, 0.45);
+    CGContextFillRect(env, context, bounds);
+}
+
+/// Height of some text wrapped to `width`, or 0 if the text is `nil`.
+pub(super) fn text_height(env: &mut Environment, text: id, font: id, width: CGFloat) -> CGFloat {
+    if text == nil {
+        return 0.0;
+    }
+    let size: CGSize = msg![env; text sizeWithFont:font
+                             constrainedToSize:(CGSize { width, height: 1000.0 })
+                                 lineBreakMode:UILineBreakModeWordWrap];
+    size.height
+}
+
+/// Draws text centered horizontally in a rect.
+pub(super) fn draw_centered_text(env: &mut Environment, text: id, font: id, rect: CGRect) {
+    if text == nil {
+        return;
+    }
+    let _: CGSize = msg![env; text drawInRect:rect
+                                     withFont:font
+                                lineBreakMode:UILineBreakModeWordWrap
+                                    alignment:UITextAlignmentCenter];
+}
+
+fn title_font(env: &mut Environment) -> id {
+    msg_class![env; UIFont boldSystemFontOfSize:(18.0 as CGFloat)]
+}
+fn message_font(env: &mut Environment) -> id {
+    msg_class![env; UIFont systemFontOfSize:(16.0 as CGFloat)]
+}
+
+/// Rects of the alert box, the title, the message and each button.
+struct Layout {
+    alert_box: CGRect,
+    title: CGRect,
+    message: CGRect,
+    buttons: Vec<CGRect>,
+}
+
+fn layout(env: &mut Environment, this: id) -> Layout {
+    let bounds: CGRect = msg![env; this bounds];
+    let &UIAlertViewHostObject {
+        title,
+        message,
+        cancel_button_index,
+        ..
+    } = env.objc.borrow(this);
+    let button_count = env
+        .objc
+        .borrow::<UIAlertViewHostObject>(this)
+        .button_titles
+        .len();
+
+    let text_width = BOX_WIDTH - PADDING * 2.0;
+    let font = title_font(env);
+    let title_height = text_height(env, title, font, text_width);
+    let font = message_font(env);
+    let message_height = text_height(env, message, font, text_width);
+
+    // Two buttons go side by side. Otherwise, they are stacked, with the
+    // cancel button at the bottom.
+    let side_by_side = button_count == 2;
+    let button_rows = if side_by_side { 1 } else { button_count };
+    let buttons_height = button_rows as CGFloat * (BUTTON_HEIGHT + PADDING);
+
+    let box_height = PADDING * 2.0 + title_height + PADDING + message_height + buttons_height;
+    let alert_box = CGRect {
+        origin: CGPoint {
+            x: bounds.origin.x + (bounds.size.width - BOX_WIDTH) / 2.0,
+            y: bounds.origin.y + ((bounds.size.height - box_height) / 2.0).max(0.0),
+        },
+        size: CGSize {
+            width: BOX_WIDTH,
+            height: box_height,
+        },
+    };
+
+    let text_x = alert_box.origin.x + PADDING;
+    let title = CGRect {
+        origin: CGPoint {
+            x: text_x,
+            y: alert_box.origin.y + PADDING,
+        },
+        size: CGSize {
+            width: text_width,
+            height: title_height,
+        },
+    };
+    let message = CGRect {
+        origin: CGPoint {
+            x: text_x,
+            y: title.origin.y + title_height + PADDING,
+        },
+        size: CGSize {
+            width: text_width,
+            height: message_height,
+        },
+    };
+
+    let buttons_y = message.origin.y + message_height + PADDING;
+    let buttons = if side_by_side {
+        let width = (text_width - PADDING) / 2.0;
+        (0..2)
+            .map(|i| CGRect {
+                origin: CGPoint {
+                    x: text_x + i as CGFloat * (width + PADDING),
+                    y: buttons_y,
+                },
+                size: CGSize {
+                    width,
+                    height: BUTTON_HEIGHT,
+                },
+            })
+            .collect()
+    } else {
+        let cancel_index = usize::try_from(cancel_button_index).ok();
+        let mut row = 0;
+        let mut rows = vec![0; button_count];
+        for (i, slot) in rows.iter_mut().enumerate() {
+            if Some(i) != cancel_index {
+                *slot = row;
+                row += 1;
+            }
+        }
+        if let Some(cancel_index) = cancel_index.filter(|&i| i < button_count) {
+            rows[cancel_index] = row;
+        }
+        rows.into_iter()
+            .map(|row| CGRect {
+                origin: CGPoint {
+                    x: text_x,
+                    y: buttons_y + row as CGFloat * (BUTTON_HEIGHT + PADDING),
+                },
+                size: CGSize {
+                    width: text_width,
+                    height: BUTTON_HEIGHT,
+                },
+            })
+            .collect()
+    };
+
+    Layout {
+        alert_box,
+        title,
+        message,
+        buttons,
+    }
+}
+
+fn dismiss(env: &mut Environment, this: id, button_index: NSInteger) {
+    if !env.objc.borrow::<UIAlertViewHostObject>(this).visible {
+        return;
+    }
+
+    // The delegate might release the alert, and so does removing it from its
+    // superview, so keep it alive until the end.
+    retain(env, this);
+
+    let delegate = env.objc.borrow::<UIAlertViewHostObject>(this).delegate;
+    if delegate_responds(env, delegate, "alertView:willDismissWithButtonIndex:") {
+        () = msg![env; delegate alertView:this willDismissWithButtonIndex:button_index];
+    }
+
+    let host_obj = env.objc.borrow_mut::<UIAlertViewHostObject>(this);
+    host_obj.visible = false;
+    let buttons = std::mem::take(&mut host_obj.buttons);
+    for button in buttons {
+        () = msg![env; button removeFromSuperview];
+        release(env, button);
+    }
+    () = msg![env; this removeFromSuperview];
+
+    let delegate = env.objc.borrow::<UIAlertViewHostObject>(this).delegate;
+    if delegate_responds(env, delegate, "alertView:didDismissWithButtonIndex:") {
+        () = msg![env; delegate alertView:this didDismissWithButtonIndex:button_index];
+    }
+
+    release(env, this);
+}
 
 pub const CLASSES: ClassExports = objc_classes! {
 
 (env, this, _cmd);
 
 @implementation UIAlertView: UIView
-- (id)initWithTitle:(id)title
-                      message:(id)message
-                     delegate:(id)delegate
-            cancelButtonTitle:(id)cancelButtonTitle
-            otherButtonTitles:(id)otherButtonTitles {
 
-    log!("TODO: [(UIAlertView*){:?} initWithTitle:{:?} message:{:?} delegate:{:?} cancelButtonTitle:{:?} otherButtonTitles:{:?}]", this, title, message, delegate, cancelButtonTitle, otherButtonTitles);
++ (id)allocWithZone:(NSZonePtr)_zone {
+    let host_object = Box::<UIAlertViewHostObject>::default();
+    env.objc.alloc_object(this, host_object, &mut env.mem)
+}
+
+- (id)initWithTitle:(id)title // NSString*
+            message:(id)message // NSString*
+           delegate:(id)delegate
+  cancelButtonTitle:(id)cancel_button_title // NSString*
+  otherButtonTitles:(id)first_other_button_title, ...dots { // NSString*
+    let this: id = msg![env; this init];
 
-    let msg = ns_string::to_rust_string(env, message);
-    let title = ns_string::to_rust_string(env, title);
-    log!("UIAlertView: title: {:?}, message: {:?}", title, msg);
+    let title: id = msg![env; title copy];
+    let message: id = msg![env; message copy];
+    let other_titles = collect_button_titles(env, first_other_button_title, dots.start());
 
-    msg_super![env; this init]
+    let host_obj = env.objc.borrow_mut::<UIAlertViewHostObject>(this);
+    host_obj.title = title;
+    host_obj.message = message;
+    host_obj.delegate = delegate;
+    if cancel_button_title != nil {
+        let cancel_button_title: id = msg![env; cancel_button_title copy];
+        let host_obj = env.objc.borrow_mut::<UIAlertViewHostObject>(this);
+        host_obj.cancel_button_index = 0;
+        host_obj.button_titles.push(cancel_button_title);
+    }
+    env.objc.borrow_mut::<UIAlertViewHostObject>(this).button_titles.extend(other_titles);
+
+    this
+}
+
+- (id)initWithFrame:(CGRect)frame {
+    let this: id = msg_super![env; this initWithFrame:frame];
+    () = msg![env; this setOpaque:false];
+    this
+}
+
+- (())dealloc {
+    let UIAlertViewHostObject {
+        title,
+        message,
+        button_titles,
+        buttons,
+        ..
+    } = std::mem::take(env.objc.borrow_mut(this));
+    release(env, title);
+    release(env, message);
+    for title in button_titles {
+        release(env, title);
+    }
+    for button in buttons {
+        release(env, button);
+    }
+    msg_super![env; this dealloc]
+}
+
+- (id)title {
+    env.objc.borrow::<UIAlertViewHostObject>(this).title
+}
+- (())setTitle:(id)title { // NSString*
+    let title: id = msg![env; title copy];
+    let host_obj = env.objc.borrow_mut::<UIAlertViewHostObject>(this);
+    let old_title = std::mem::replace(&mut host_obj.title, title);
+    release(env, old_title);
+    () = msg![env; this layoutSubviews];
+    () = msg![env; this setNeedsDisplay];
+}
+
+- (id)message {
+    env.objc.borrow::<UIAlertViewHostObject>(this).message
+}
+- (())setMessage:(id)message { // NSString*
+    let message: id = msg![env; message copy];
+    let host_obj = env.objc.borrow_mut::<UIAlertViewHostObject>(this);
+    let old_message = std::mem::replace(&mut host_obj.message, message);
+    release(env, old_message);
+    () = msg![env; this layoutSubviews];
+    () = msg![env; this setNeedsDisplay];
+}
+
+- (id)delegate {
+    env.objc.borrow::<UIAlertViewHostObject>(this).delegate
+}
+- (())setDelegate:(id)delegate {
+    env.objc.borrow_mut::<UIAlertViewHostObject>(this).delegate = delegate;
+}
+
+- (NSInteger)addButtonWithTitle:(id)title { // NSString*
+    let title: id = msg![env; title copy];
+    let host_obj = env.objc.borrow_mut::<UIAlertViewHostObject>(this);
+    host_obj.button_titles.push(title);
+    (host_obj.button_titles.len() - 1).try_into().unwrap()
+}
+- (NSInteger)numberOfButtons {
+    let count = env.objc.borrow::<UIAlertViewHostObject>(this).button_titles.len();
+    count.try_into().unwrap()
+}
+- (id)buttonTitleAtIndex:(NSInteger)index {
+    let titles = &env.objc.borrow::<UIAlertViewHostObject>(this).button_titles;
+    usize::try_from(index).ok().and_then(|i| titles.get(i)).copied().unwrap_or(nil)
+}
+
+- (NSInteger)cancelButtonIndex {
+    env.objc.borrow::<UIAlertViewHostObject>(this).cancel_button_index
+}
+- (())setCancelButtonIndex:(NSInteger)index {
+    env.objc.borrow_mut::<UIAlertViewHostObject>(this).cancel_button_index = index;
 }
+- (NSInteger)firstOtherButtonIndex {
+    let host_obj = env.objc.borrow::<UIAlertViewHostObject>(this);
+    let index = if host_obj.cancel_button_index == 0 { 1 } else { 0 };
+    if index < host_obj.button_titles.len() {
+        index.try_into().unwrap()
+    } else {
+        -1
+    }
+}
+
+- (bool)isVisible {
+    env.objc.borrow::<UIAlertViewHostObject>(this).visible
+}
+
 - (())show {
-    log!("TODO: [(UIAlertView*){:?} show]", this);
+    if env.objc.borrow::<UIAlertViewHostObject>(this).visible {
+        return;
+    }
+    let Some(window) = window_for_presenting(env, nil) else {
+        log!("Warning: no window to show UIAlertView {:?} in", this);
+        return;
+    };
+
+    let &UIAlertViewHostObject { title, message, delegate, .. } = env.objc.borrow(this);
+    let title = (title != nil).then(|| ns_string::to_rust_string(env, title));
+    let message = (message != nil).then(|| ns_string::to_rust_string(env, message));
+    log!("Showing UIAlertView {:?}: title {:?}, message {:?}", this, title, message);
+
+    if delegate_responds(env, delegate, "willPresentAlertView:") {
+        () = msg![env; delegate willPresentAlertView:this];
+    }
+
+    let &UIAlertViewHostObject { cancel_button_index, .. } = env.objc.borrow(this);
+    let titles = env.objc.borrow::<UIAlertViewHostObject>(this).button_titles.clone();
+    let buttons: Vec<id> = titles
+        .into_iter()
+        .enumerate()
+        .map(|(i, title)| {
+            let background = if i as NSInteger == cancel_button_index {
+                (0.25, 0.3, 0.45, 1.0)
+            } else {
+                (0.45, 0.52, 0.7, 1.0)
+            };
+            create_button(env, this, title, background, false)
+        })
+        .collect();
+    for &button in &buttons {
+        () = msg![env; this addSubview:button];
+    }
+    let host_obj = env.objc.borrow_mut::<UIAlertViewHostObject>(this);
+    host_obj.buttons = buttons;
+    host_obj.visible = true;
+
+    let window_bounds: CGRect = msg![env; window bounds];
+    () = msg![env; this setFrame:window_bounds];
+    () = msg![env; window addSubview:this];
+    () = msg![env; this layoutSubviews];
+    () = msg![env; this setNeedsDisplay];
+
+    if delegate_responds(env, delegate, "didPresentAlertView:") {
+        () = msg![env; delegate didPresentAlertView:this];
+    }
+}
+
+- (())dismissWithClickedButtonIndex:(NSInteger)button_index animated:(bool)_animated {
+    // TODO: animation
+    dismiss(env, this, button_index);
 }
+
+- (())_touchHLE_buttonClicked:(id)button { // UIButton*
+    let buttons = &env.objc.borrow::<UIAlertViewHostObject>(this).buttons;
+    let Some(index) = buttons.iter().position(|&b| b == button) else {
+        return;
+    };
+    let index = index as NSInteger;
+
+    retain(env, this);
+    let delegate = env.objc.borrow::<UIAlertViewHostObject>(this).delegate;
+    if delegate_responds(env, delegate, "alertView:clickedButtonAtIndex:") {
+        () = msg![env; delegate alertView:this clickedButtonAtIndex:index];
+    }
+    dismiss(env, this, index);
+    release(env, this);
+}
+
+- (())layoutSubviews {
+    let layout = layout(env, this);
+    let buttons = env.objc.borrow::<UIAlertViewHostObject>(this).buttons.clone();
+    for (button, frame) in buttons.into_iter().zip(layout.buttons) {
+        () = msg![env; button setFrame:frame];
+    }
+}
+
+- (())drawRect:(CGRect)_rect {
+    let bounds: CGRect = msg![env; this bounds];
+    draw_dimming(env, bounds);
+
+    let layout = layout(env, this);
+    let context = UIGraphicsGetCurrentContext(env);
+    // Border, then the box itself.
+    CGContextSetRGBFillColor(env, context, 0.85, 0.88, 0.95, 1.0);
+    CGContextFillRect(env, context, CGRect {
+        origin: CGPoint {
+            x: layout.alert_box.origin.x - 2.0,
+            y: layout.alert_box.origin.y - 2.0,
+        },
+        size: CGSize {
+            width: layout.alert_box.size.width + 4.0,
+            height: layout.alert_box.size.height + 4.0,
+        },
+    });
+    CGContextSetRGBFillColor(env, context, 0.1, 0.16, 0.35, 0.92);
+    CGContextFillRect(env, context, layout.alert_box);
+
+    let &UIAlertViewHostObject { title, message, .. } = env.objc.borrow(this);
+    CGContextSetRGBFillColor(env, context, 1.0, 1.0, 1.0, 1.0);
+    let font = title_font(env);
+    draw_centered_text(env, title, font, layout.title);
+    let font = message_font(env);
+    draw_centered_text(env, message, font, layout.message);
+}
+
 @end
 
 };
@@ -9,10 +9,14 @@
 //! - The [Target-Action section](https://developer.apple.com/library/archive/documentation/General/Conceptual/CocoaEncyclopedia/Target-Action/Target-Action.html) of Apple's "Concepts in Objective-C Programming".
 
 pub mod ui_button;
+pub mod ui_page_control;
+pub mod ui_segmented_control;
+pub mod ui_slider;
 pub mod ui_switch;
 pub mod ui_text_field;
 
 use crate::frameworks::core_graphics::CGPoint;
+use crate::frameworks::foundation::ns_string::get_static_str;
 use crate::frameworks::foundation::NSUInteger;
 use crate::objc::{
     id, impl_HostObject_with_superclass, msg, msg_send, msg_super, nil, objc_classes, release,
@@ -21,7 +25,7 @@ use crate::objc::{
 use crate::Environment;
 
 // TODO: There are many members of this enum missing.
-type UIControlEvents = NSUInteger;
+pub type UIControlEvents = NSUInteger;
 const UIControlEventTouchDown: UIControlEvents = 1 << 0;
 const UIControlEventTouchDragInside: UIControlEvents = 1 << 2;
 const UIControlEventTouchDragOutside: UIControlEvents = 1 << 3;
@@ -29,6 +33,11 @@ const UIControlEventTouchDragEnter: UIControlEvents = 1 << 4;
 const UIControlEventTouchDragExit: UIControlEvents = 1 << 5;
 pub const UIControlEventTouchUpInside: UIControlEvents = 1 << 6;
 const UIControlEventTouchUpOutside: UIControlEvents = 1 << 7;
+const UIControlEventValueChanged: UIControlEvents = 1 << 12;
+const UIControlEventEditingDidBegin: UIControlEvents = 1 << 16;
+const UIControlEventEditingChanged: UIControlEvents = 1 << 17;
+const UIControlEventEditingDidEnd: UIControlEvents = 1 << 18;
+const UIControlEventEditingDidEndOnExit: UIControlEvents = 1 << 19;
 
 struct UIControlHostObject {
     superclass: super::UIViewHostObject,
@@ -81,8 +90,6 @@ fn send_actions(env: &mut Environment, this: id, event: id, control_event: UICon
         .collect();
 
     for (target, action) in action_targets {
-        assert!(target != nil); // TODO
-
         () = msg![env; this sendAction:action to:target forEvent:event];
     }
 }
@@ -115,6 +122,25 @@ pub const CLASSES: ClassExports = objc_classes! {
     msg_super![env; this dealloc]
 }
 
+// NSCoding implementation
+- (id)initWithCoder:(id)coder {
+    let this: id = msg_super![env; this initWithCoder:coder];
+
+    let key = get_static_str(env, "UIDisabled");
+    let disabled: bool = msg![env; coder decodeBoolForKey:key];
+    let key = get_static_str(env, "UISelected");
+    let selected: bool = msg![env; coder decodeBoolForKey:key];
+    let key = get_static_str(env, "UIHighlighted");
+    let highlighted: bool = msg![env; coder decodeBoolForKey:key];
+
+    let host_obj = env.objc.borrow_mut::<UIControlHostObject>(this);
+    host_obj.enabled = !disabled;
+    host_obj.selected = selected;
+    host_obj.highlighted = highlighted;
+
+    this
+}
+
 - (UIControlState)state {
     let &UIControlHostObject {
         highlighted,
@@ -262,18 +288,8 @@ pub const CLASSES: ClassExports = objc_classes! {
 - (())addTarget:(id)target
          action:(SEL)action
 forControlEvents:(UIControlEvents)events {
-    if target == nil {
-        // TODO: when the target is nil, the responder chain is searched for
-        // a suitable target
-        log!(
-            "TODO: [{:?} addTarget:nil action:{:?} forControlEvents:{:?}] (ignored)",
-            target,
-            action,
-            events,
-        );
-        return;
-    }
-    // The target is a *weak* reference!
+    // The target is a *weak* reference! If it's nil, it's looked up in the
+    // responder chain each time the action is sent.
 
     // The selector must be for a method with zero to two arguments
     let sel_str = action.as_str(&env.mem);
@@ -286,7 +302,31 @@ forControlEvents:(UIControlEvents)events {
 - (())sendAction:(SEL)action
               to:(id)target
         forEvent:(id)event { // UIEvent*
-    assert!(target != nil); // TODO
+    let target = if target == nil {
+        // A nil target means the action goes to the first object in the
+        // responder chain that can handle it.
+        let first_responder = env.framework_state.uikit.ui_responder.first_responder;
+        let mut responder = if first_responder == nil {
+            this
+        } else {
+            first_responder
+        };
+        while responder != nil && !msg![env; responder respondsToSelector:action] {
+            responder = msg![env; responder nextResponder];
+        }
+        if responder == nil {
+            log!(
+                "Warning: no responder for action {:?} ({:?}) sent by {:?}, ignoring",
+                action,
+                action.as_str(&env.mem),
+                this,
+            );
+            return;
+        }
+        responder
+    } else {
+        target
+    };
 
     let sel_str = action.as_str(&env.mem);
     let colon_count = sel_str.bytes().filter(|&b| b == b':').count();
@@ -5,13 +5,17 @@
  */
 //! `UIButton`.
 
-use super::{UIControlState, UIControlStateNormal};
-use crate::frameworks::core_graphics::{CGPoint, CGRect};
+use super::{
+    UIControlState, UIControlStateDisabled, UIControlStateHighlighted, UIControlStateNormal,
+    UIControlStateSelected,
+};
+use crate::frameworks::core_graphics::{CGPoint, CGRect, CGSize};
+use crate::frameworks::foundation::ns_string::get_static_str;
 use crate::frameworks::foundation::NSInteger;
 use crate::frameworks::uikit::ui_font::UITextAlignmentCenter;
 use crate::objc::{
     autorelease, id, impl_HostObject_with_superclass, msg, msg_class, msg_super, nil, objc_classes,
-    release, retain, ClassExports, NSZonePtr,
+    release, retain, ClassExports, HostObject, NSZonePtr,
 };
 use crate::Environment;
 use std::collections::HashMap;
@@ -79,19 +83,40 @@ fn update(env: &mut Environment, this: id) {
     () = msg![env; background_image_view setImage:background_image];
 }
 
-pub const CLASSES: ClassExports = objc_classes! {
+/// Creates the subviews and the defaults for the normal state. Shared by
+/// `initWithFrame:` and `initWithCoder:`.
+fn init_common(env: &mut Environment, this: id) {
+    let bg_color: id = msg_class![env; UIColor clearColor];
+    let title_label: id = msg_class![env; UILabel new];
+    () = msg![env; title_label setBackgroundColor:bg_color];
+    () = msg![env; title_label setTextAlignment:UITextAlignmentCenter];
 
-(env, this, _cmd);
+    let text_color: id = msg_class![env; UIColor whiteColor];
 
-@implementation UIButton: UIControl
+    let image_view: id = msg_class![env; UIImageView new];
+    let background_image_view: id = msg_class![env; UIImageView new];
 
-+ (id)allocWithZone:(NSZonePtr)_zone {
-    let host_object = Box::<UIButtonHostObject>::default();
-    env.objc.alloc_object(this, host_object, &mut env.mem)
+    let host_obj = env.objc.borrow_mut::<UIButtonHostObject>(this);
+    host_obj.title_label = title_label;
+    host_obj.image_view = image_view;
+    host_obj.background_image_view = background_image_view;
+    host_obj.titles_for_states.insert(UIControlStateNormal, nil);
+    host_obj
+        .title_colors_for_states
+        .insert(UIControlStateNormal, text_color);
+    host_obj.images_for_states.insert(UIControlStateNormal, nil);
+    host_obj
+        .background_images_for_states
+        .insert(UIControlStateNormal, nil);
+
+    () = msg![env; this addSubview:background_image_view];
+    () = msg![env; this addSubview:title_label];
+    () = msg![env; this addSubview:image_view];
+    update(env, this);
 }
 
-+ (id)buttonWithType:(UIButtonType)type_ {
-    let button: id = msg![env; this new];
+fn set_up_type(env: &mut Environment, button: id, type_: UIButtonType) {
+    env.objc.borrow_mut::<UIButtonHostObject>(button).type_ = type_;
     match type_ {
         UIButtonTypeCustom => (),
         UIButtonTypeRoundedRect => {
@@ -105,11 +130,42 @@ pub const CLASSES: ClassExports = objc_classes! {
             () = msg![env; button setTitleColor:text_color
                                        forState:UIControlStateNormal];
             // TODO: set border and corner rounding, once supported
-        },
+        }
         _ => {
             log!("TODO: UIButtonType {}", type_);
         }
     }
+}
+
+/// Host object for `UIButtonContent`, which nib files use to store the
+/// button's properties for a particular state.
+#[derive(Default)]
+struct UIButtonContentHostObject {
+    /// `NSString*`
+    title: id,
+    /// `UIColor*`
+    title_color: id,
+    /// `UIImage*`
+    image: id,
+    /// `UIImage*`
+    background_image: id,
+}
+impl HostObject for UIButtonContentHostObject {}
+
+pub const CLASSES: ClassExports = objc_classes! {
+
+(env, this, _cmd);
+
+@implementation UIButton: UIControl
+
++ (id)allocWithZone:(NSZonePtr)_zone {
+    let host_object = Box::<UIButtonHostObject>::default();
+    env.objc.alloc_object(this, host_object, &mut env.mem)
+}
+
++ (id)buttonWithType:(UIButtonType)type_ {
+    let button: id = msg![env; this new];
+    set_up_type(env, button, type_);
     autorelease(env, button)
 }
 
@@ -120,34 +176,63 @@ pub const CLASSES: ClassExports = objc_classes! {
     let bg_color: id = msg_class![env; UIColor clearColor];
     () = msg![env; this setBackgroundColor:bg_color];
 
-    let title_label: id = msg_class![env; UILabel new];
-    () = msg![env; title_label setBackgroundColor:bg_color];
-    () = msg![env; title_label setTextAlignment:UITextAlignmentCenter];
+    init_common(env, this);
 
-    let text_color: id = msg_class![env; UIColor whiteColor];
+    this
+}
 
-    let image_view: id = msg_class![env; UIImageView new];
-    let background_image_view: id = msg_class![env; UIImageView new];
+// NSCoding implementation
+- (id)initWithCoder:(id)coder {
+    let this: id = msg_super![env; this initWithCoder:coder];
 
-    let host_obj = env.objc.borrow_mut::<UIButtonHostObject>(this);
-    host_obj.title_label = title_label;
-    host_obj.image_view = image_view;
-    host_obj.background_image_view = background_image_view;
-    host_obj.titles_for_states.insert(UIControlStateNormal, nil);
-    host_obj.title_colors_for_states.insert(UIControlStateNormal, text_color);
-    host_obj.images_for_states.insert(UIControlStateNormal, nil);
-    host_obj.background_images_for_states.insert(UIControlStateNormal, nil);
+    init_common(env, this);
 
-    () = msg![env; this addSubview:background_image_view];
-    () = msg![env; this addSubview:title_label];
-    () = msg![env; this addSubview:image_view];
-    update(env, this);
+    let key = get_static_str(env, "UIButtonType");
+    let type_: UIButtonType = msg![env; coder decodeIntegerForKey:key];
+    set_up_type(env, this, type_);
+
+    let key = get_static_str(env, "UIFont");
+    let font: id = msg![env; coder decodeObjectForKey:key];
+    if font != nil {
+        () = msg![env; this setFont:font];
+    }
+
+    // This is a dictionary of UIControlState numbers to UIButtonContent.
+    let key = get_static_str(env, "UIButtonStatefulContent");
+    let contents: id = msg![env; coder decodeObjectForKey:key];
+    if contents != nil {
+        // There is no support for enumerating dictionaries from host code, so
+        // every possible combination of states is tried instead.
+        for state in 0..=(UIControlStateHighlighted | UIControlStateDisabled | UIControlStateSelected) {
+            let state_number: id = msg_class![env; NSNumber numberWithLongLong:(state as i64)];
+            let content: id = msg![env; contents objectForKey:state_number];
+            if content == nil {
+                continue;
+            }
+            let &UIButtonContentHostObject {
+                title,
+                title_color,
+                image,
+                background_image,
+            } = env.objc.borrow(content);
+            if title != nil {
+                () = msg![env; this setTitle:title forState:state];
+            }
+            if title_color != nil {
+                () = msg![env; this setTitleColor:title_color forState:state];
+            }
+            if image != nil {
+                () = msg![env; this setImage:image forState:state];
+            }
+            if background_image != nil {
+                () = msg![env; this setBackgroundImage:background_image forState:state];
+            }
+        }
+    }
 
     this
 }
 
-// TODO: initWithCoder:
-
 - (())dealloc {
     let UIButtonHostObject {
         superclass: _,
@@ -303,6 +388,48 @@ pub const CLASSES: ClassExports = objc_classes! {
     update(env, this);
 }
 
+- (CGSize)sizeThatFits:(CGSize)_size {
+    let state: UIControlState = msg![env; this state];
+    let &UIButtonHostObject {
+        title_label,
+        ref titles_for_states,
+        ref images_for_states,
+        ref background_images_for_states,
+        ..
+    } = env.objc.borrow(this);
+    // The getters for these panic if nothing is set for the normal state.
+    let get = |map: &HashMap<UIControlState, id>| {
+        map.get(&state).or_else(|| map.get(&UIControlStateNormal)).copied().unwrap_or(nil)
+    };
+    let title = get(titles_for_states);
+    let image = get(images_for_states);
+    let background_image = get(background_images_for_states);
+
+    let zero = CGSize { width: 0.0, height: 0.0 };
+    let title_size: CGSize = if title != nil {
+        let font: id = msg![env; title_label font];
+        msg![env; title sizeWithFont:font]
+    } else {
+        zero
+    };
+    let image_size: CGSize = if image != nil {
+        msg![env; image size]
+    } else {
+        zero
+    };
+    let background_size: CGSize = if background_image != nil {
+        msg![env; background_image size]
+    } else {
+        zero
+    };
+
+    // The title and image are side by side, on top of the background.
+    CGSize {
+        width: (title_size.width + image_size.width).max(background_size.width),
+        height: title_size.height.max(image_size.height).max(background_size.height),
+    }
+}
+
 // TODO: actions, etc
 
 - (id)hitTest:(CGPoint)point
@@ -317,4 +444,52 @@ pub const CLASSES: ClassExports = objc_classes! {
 
 @end
 
+// An undocumented type used by nib files, see UIButtonContentHostObject.
+@implementation UIButtonContent: NSObject
+
++ (id)allocWithZone:(NSZonePtr)_zone {
+    let host_object = Box::<UIButtonContentHostObject>::default();
+    env.objc.alloc_object(this, host_object, &mut env.mem)
+}
+
+// NSCoding implementation
+- (id)initWithCoder:(id)coder {
+    let key = get_static_str(env, "UITitle");
+    let title: id = msg![env; coder decodeObjectForKey:key];
+    let key = get_static_str(env, "UITitleColor");
+    let title_color: id = msg![env; coder decodeObjectForKey:key];
+    let key = get_static_str(env, "UIImage");
+    let image: id = msg![env; coder decodeObjectForKey:key];
+    let key = get_static_str(env, "UIBackgroundImage");
+    let background_image: id = msg![env; coder decodeObjectForKey:key];
+
+    retain(env, title);
+    retain(env, title_color);
+    retain(env, image);
+    retain(env, background_image);
+    *env.objc.borrow_mut(this) = UIButtonContentHostObject {
+        title,
+        title_color,
+        image,
+        background_image,
+    };
+    this
+}
+
+- (())dealloc {
+    let &UIButtonContentHostObject {
+        title,
+        title_color,
+        image,
+        background_image,
+    } = env.objc.borrow(this);
+    release(env, title);
+    release(env, title_color);
+    release(env, image);
+    release(env, background_image);
+    env.objc.dealloc_object(this, &mut env.mem)
+}
+
+@end
+
 };
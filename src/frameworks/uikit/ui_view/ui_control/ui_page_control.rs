@@ -0,0 +1,207 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! `UIPageControl`.
+
+use super::{send_actions, UIControlEventValueChanged};
+use crate::frameworks::core_graphics::cg_context::{CGContextFillRect, CGContextSetRGBFillColor};
+use crate::frameworks::core_graphics::{CGFloat, CGPoint, CGRect, CGSize};
+use crate::frameworks::foundation::ns_string::get_static_str;
+use crate::frameworks::foundation::NSInteger;
+use crate::frameworks::uikit::ui_graphics::UIGraphicsGetCurrentContext;
+use crate::objc::{
+    id, impl_HostObject_with_superclass, msg, msg_super, objc_classes, ClassExports, NSZonePtr,
+};
+
+/// Size of each page indicator dot.
+const DOT_SIZE: CGFloat = 6.0;
+/// Distance between the centres of two adjacent dots.
+const DOT_SPACING: CGFloat = 16.0;
+/// Height used by `sizeForNumberOfPages:`.
+const CONTROL_HEIGHT: CGFloat = 36.0;
+
+#[derive(Default)]
+pub struct UIPageControlHostObject {
+    superclass: super::UIControlHostObject,
+    number_of_pages: NSInteger,
+    current_page: NSInteger,
+    /// The page shown by the indicator, which lags behind `current_page` if
+    /// `defers_current_page_display` is set.
+    displayed_page: NSInteger,
+    hides_for_single_page: bool,
+    defers_current_page_display: bool,
+}
+impl_HostObject_with_superclass!(UIPageControlHostObject);
+
+fn size_for_number_of_pages(number_of_pages: NSInteger) -> CGSize {
+    CGSize {
+        width: DOT_SPACING * number_of_pages.max(0) as CGFloat,
+        height: CONTROL_HEIGHT,
+    }
+}
+
+pub const CLASSES: ClassExports = objc_classes! {
+
+(env, this, _cmd);
+
+@implementation UIPageControl: UIControl
+
++ (id)allocWithZone:(NSZonePtr)_zone {
+    let host_object = Box::<UIPageControlHostObject>::default();
+    env.objc.alloc_object(this, host_object, &mut env.mem)
+}
+
+- (id)initWithFrame:(CGRect)frame {
+    let this: id = msg_super![env; this initWithFrame:frame];
+    // Built-in views don't have user-controlled opaqueness.
+    () = msg![env; this setOpaque:false];
+    this
+}
+
+- (id)initWithCoder:(id)coder {
+    let this: id = msg_super![env; this initWithCoder:coder];
+
+    let key = get_static_str(env, "UINumberOfPages");
+    let number_of_pages: NSInteger = msg![env; coder decodeIntegerForKey:key];
+    let key = get_static_str(env, "UICurrentPage");
+    let current_page: NSInteger = msg![env; coder decodeIntegerForKey:key];
+    let key = get_static_str(env, "UIHidesForSinglePage");
+    let hides_for_single_page: bool = msg![env; coder decodeBoolForKey:key];
+    let key = get_static_str(env, "UIDefersCurrentPageDisplay");
+    let defers_current_page_display: bool = msg![env; coder decodeBoolForKey:key];
+
+    let host_obj = env.objc.borrow_mut::<UIPageControlHostObject>(this);
+    host_obj.number_of_pages = number_of_pages.max(0);
+    host_obj.current_page = current_page.clamp(0, (number_of_pages - 1).max(0));
+    host_obj.displayed_page = host_obj.current_page;
+    host_obj.hides_for_single_page = hides_for_single_page;
+    host_obj.defers_current_page_display = defers_current_page_display;
+
+    () = msg![env; this setOpaque:false];
+    this
+}
+
+- (NSInteger)numberOfPages {
+    env.objc.borrow::<UIPageControlHostObject>(this).number_of_pages
+}
+- (())setNumberOfPages:(NSInteger)number_of_pages {
+    let host_obj = env.objc.borrow_mut::<UIPageControlHostObject>(this);
+    host_obj.number_of_pages = number_of_pages.max(0);
+    let last_page = (host_obj.number_of_pages - 1).max(0);
+    host_obj.current_page = host_obj.current_page.min(last_page);
+    host_obj.displayed_page = host_obj.displayed_page.min(last_page);
+    () = msg![env; this setNeedsDisplay];
+}
+
+- (NSInteger)currentPage {
+    env.objc.borrow::<UIPageControlHostObject>(this).current_page
+}
+- (())setCurrentPage:(NSInteger)current_page {
+    let host_obj = env.objc.borrow_mut::<UIPageControlHostObject>(this);
+    let current_page = current_page.clamp(0, (host_obj.number_of_pages - 1).max(0));
+    host_obj.current_page = current_page;
+    // Setting the page programmatically always updates the display.
+    host_obj.displayed_page = current_page;
+    () = msg![env; this setNeedsDisplay];
+}
+
+- (bool)hidesForSinglePage {
+    env.objc.borrow::<UIPageControlHostObject>(this).hides_for_single_page
+}
+- (())setHidesForSinglePage:(bool)hides {
+    env.objc.borrow_mut::<UIPageControlHostObject>(this).hides_for_single_page = hides;
+    () = msg![env; this setNeedsDisplay];
+}
+
+- (bool)defersCurrentPageDisplay {
+    env.objc.borrow::<UIPageControlHostObject>(this).defers_current_page_display
+}
+- (())setDefersCurrentPageDisplay:(bool)defers {
+    env.objc.borrow_mut::<UIPageControlHostObject>(this).defers_current_page_display = defers;
+}
+- (())updateCurrentPageDisplay {
+    let host_obj = env.objc.borrow_mut::<UIPageControlHostObject>(this);
+    host_obj.displayed_page = host_obj.current_page;
+    () = msg![env; this setNeedsDisplay];
+}
+
+- (CGSize)sizeForNumberOfPages:(NSInteger)number_of_pages {
+    size_for_number_of_pages(number_of_pages)
+}
+- (CGSize)sizeThatFits:(CGSize)_size {
+    let number_of_pages = env.objc.borrow::<UIPageControlHostObject>(this).number_of_pages;
+    size_for_number_of_pages(number_of_pages)
+}
+
+// UIControl overrides
+
+- (bool)beginTrackingWithTouch:(id)_touch // UITouch*
+                     withEvent:(id)_event { // UIEvent*
+    true
+}
+- (())endTrackingWithTouch:(id)touch // UITouch*
+                  withEvent:(id)event { // UIEvent*
+    () = msg_super![env; this endTrackingWithTouch:touch withEvent:event];
+
+    // Tapping to the left or right of the current page's dot moves one page
+    // in that direction.
+    let bounds: CGRect = msg![env; this bounds];
+    let location: CGPoint = msg![env; touch locationInView:this];
+    if !msg![env; this pointInside:location withEvent:event] {
+        return;
+    }
+    let host_obj = env.objc.borrow_mut::<UIPageControlHostObject>(this);
+    let indicator_width = size_for_number_of_pages(host_obj.number_of_pages).width;
+    let indicator_x = bounds.origin.x + (bounds.size.width - indicator_width) / 2.0;
+    let current_dot_center = indicator_x + DOT_SPACING * (host_obj.displayed_page as CGFloat + 0.5);
+    let new_page = if location.x < current_dot_center {
+        host_obj.current_page - 1
+    } else {
+        host_obj.current_page + 1
+    };
+    if new_page < 0 || new_page >= host_obj.number_of_pages {
+        return;
+    }
+    host_obj.current_page = new_page;
+    if !host_obj.defers_current_page_display {
+        host_obj.displayed_page = new_page;
+    }
+    () = msg![env; this setNeedsDisplay];
+    send_actions(env, this, event, UIControlEventValueChanged);
+}
+
+- (())drawRect:(CGRect)_rect {
+    let bounds: CGRect = msg![env; this bounds];
+    let context = UIGraphicsGetCurrentContext(env);
+
+    let &UIPageControlHostObject {
+        number_of_pages,
+        displayed_page,
+        hides_for_single_page,
+        ..
+    } = env.objc.borrow(this);
+    if number_of_pages == 0 || (number_of_pages == 1 && hides_for_single_page) {
+        return;
+    }
+
+    let indicator_width = size_for_number_of_pages(number_of_pages).width;
+    let indicator_x = bounds.origin.x + (bounds.size.width - indicator_width) / 2.0;
+    let dot_y = bounds.origin.y + (bounds.size.height - DOT_SIZE) / 2.0;
+
+    // The real dots are round, but only rectangles can be drawn at the moment.
+    for page in 0..number_of_pages {
+        let alpha = if page == displayed_page { 1.0 } else { 0.3 };
+        CGContextSetRGBFillColor(env, context, 1.0, 1.0, 1.0, alpha);
+        let dot_x = indicator_x + DOT_SPACING * (page as CGFloat + 0.5) - DOT_SIZE / 2.0;
+        CGContextFillRect(env, context, CGRect {
+            origin: CGPoint { x: dot_x, y: dot_y },
+            size: CGSize { width: DOT_SIZE, height: DOT_SIZE },
+        });
+    }
+}
+
+@end
+
+};
@@ -0,0 +1,558 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! `UISegmentedControl`.
+
+use super::{send_actions, UIControlEventValueChanged};
+use crate::frameworks::core_graphics::cg_context::{CGContextFillRect, CGContextSetRGBFillColor};
+use crate::frameworks::core_graphics::{CGFloat, CGPoint, CGRect, CGSize};
+use crate::frameworks::foundation::ns_string::get_static_str;
+use crate::frameworks::foundation::{NSInteger, NSUInteger};
+use crate::frameworks::uikit::ui_color;
+use crate::frameworks::uikit::ui_font::{UILineBreakModeTailTruncation, UITextAlignmentCenter};
+use crate::frameworks::uikit::ui_graphics::UIGraphicsGetCurrentContext;
+use crate::objc::{
+    id, impl_HostObject_with_superclass, msg, msg_class, msg_super, nil, objc_classes, release,
+    retain, ClassExports, NSZonePtr,
+};
+use crate::Environment;
+
+type UISegmentedControlStyle = NSInteger;
+const UISegmentedControlStylePlain: UISegmentedControlStyle = 0;
+#[allow(dead_code)]
+const UISegmentedControlStyleBordered: UISegmentedControlStyle = 1;
+const UISegmentedControlStyleBar: UISegmentedControlStyle = 2;
+#[allow(dead_code)]
+const UISegmentedControlStyleBezeled: UISegmentedControlStyle = 3;
+
+const UISegmentedControlNoSegment: NSInteger = -1;
+
+struct Segment {
+    /// `NSString*`, may be [nil]
+    title: id,
+    /// `UIImage*`, may be [nil]
+    image: id,
+    enabled: bool,
+    /// 0.0 means the width is calculated automatically.
+    width: CGFloat,
+}
+impl Segment {
+    fn new(title: id, image: id) -> Segment {
+        Segment {
+            title,
+            image,
+            enabled: true,
+            width: 0.0,
+        }
+    }
+}
+
+pub struct UISegmentedControlHostObject {
+    superclass: super::UIControlHostObject,
+    /// Titles and images are strong references.
+    segments: Vec<Segment>,
+    selected_segment_index: NSInteger,
+    momentary: bool,
+    style: UISegmentedControlStyle,
+    /// `UIColor*`, [nil] means the default
+    tint_color: id,
+}
+impl_HostObject_with_superclass!(UISegmentedControlHostObject);
+impl Default for UISegmentedControlHostObject {
+    fn default() -> Self {
+        UISegmentedControlHostObject {
+            superclass: Default::default(),
+            segments: Vec::new(),
+            selected_segment_index: UISegmentedControlNoSegment,
+            momentary: false,
+            style: UISegmentedControlStylePlain,
+            tint_color: nil,
+        }
+    }
+}
+
+/// Undocumented view class used for segments in nib files. The real UIKit
+/// draws segments with it, but here it's only used to get the segment contents
+/// out of the archive.
+struct UISegmentHostObject {
+    superclass: super::super::UIViewHostObject,
+    /// `NSString*` or `UIImage*`
+    info: id,
+    width: CGFloat,
+}
+impl_HostObject_with_superclass!(UISegmentHostObject);
+impl Default for UISegmentHostObject {
+    fn default() -> Self {
+        UISegmentHostObject {
+            superclass: Default::default(),
+            info: nil,
+            width: 0.0,
+        }
+    }
+}
+
+/// Calculates the horizontal extents of each segment, in the co-ordinate space
+/// of the control's bounds. Segments with no explicit width share the remaining
+/// space equally.
+fn segment_frames(env: &mut Environment, this: id) -> Vec<CGRect> {
+    let bounds: CGRect = msg![env; this bounds];
+    let segments = &env
+        .objc
+        .borrow::<UISegmentedControlHostObject>(this)
+        .segments;
+
+    let fixed_width: CGFloat = segments.iter().map(|s| s.width).sum();
+    let auto_count = segments.iter().filter(|s| s.width == 0.0).count();
+    let auto_width = if auto_count > 0 {
+        ((bounds.size.width - fixed_width) / auto_count as CGFloat).max(0.0)
+    } else {
+        0.0
+    };
+
+    let mut x = bounds.origin.x;
+    segments
+        .iter()
+        .map(|segment| {
+            let width = if segment.width == 0.0 {
+                auto_width
+            } else {
+                segment.width
+            };
+            let frame = CGRect {
+                origin: CGPoint {
+                    x,
+                    y: bounds.origin.y,
+                },
+                size: CGSize {
+                    width,
+                    height: bounds.size.height,
+                },
+            };
+            x += width;
+            frame
+        })
+        .collect()
+}
+
+fn segment_index_at_point(env: &mut Environment, this: id, point: CGPoint) -> Option<usize> {
+    segment_frames(env, this)
+        .iter()
+        .position(|frame| point.x >= frame.origin.x && point.x < frame.origin.x + frame.size.width)
+}
+
+fn insert_segment(env: &mut Environment, this: id, segment: Segment, index: NSUInteger) {
+    retain(env, segment.title);
+    retain(env, segment.image);
+    let host_obj = env.objc.borrow_mut::<UISegmentedControlHostObject>(this);
+    let index = (index as usize).min(host_obj.segments.len());
+    host_obj.segments.insert(index, segment);
+    // Inserting before the selection shifts it along.
+    if host_obj.selected_segment_index >= index as NSInteger {
+        host_obj.selected_segment_index += 1;
+    }
+    () = msg![env; this setNeedsDisplay];
+}
+
+pub const CLASSES: ClassExports = objc_classes! {
+
+(env, this, _cmd);
+
+@implementation UISegmentedControl: UIControl
+
++ (id)allocWithZone:(NSZonePtr)_zone {
+    let host_object = Box::<UISegmentedControlHostObject>::default();
+    env.objc.alloc_object(this, host_object, &mut env.mem)
+}
+
+- (id)initWithItems:(id)items { // NSArray* of NSString* or UIImage*
+    // The real UIKit gives the control a size matching its contents. This
+    // doesn't do that, but the app will almost always set a frame anyway.
+    let this: id = msg![env; this initWithFrame:(<CGRect as Default>::default())];
+
+    let count: NSUInteger = if items == nil { 0 } else { msg![env; items count] };
+    let string_class = env.objc.get_known_class("NSString", &mut env.mem);
+    for i in 0..count {
+        let item: id = msg![env; items objectAtIndex:i];
+        let is_string: bool = msg![env; item isKindOfClass:string_class];
+        let segment = if is_string {
+            Segment::new(item, nil)
+        } else {
+            Segment::new(nil, item)
+        };
+        insert_segment(env, this, segment, i);
+    }
+
+    this
+}
+
+- (id)initWithFrame:(CGRect)frame {
+    let this: id = msg_super![env; this initWithFrame:frame];
+    // Built-in views don't have user-controlled opaqueness.
+    () = msg![env; this setOpaque:false];
+    this
+}
+
+- (id)initWithCoder:(id)coder {
+    let this: id = msg_super![env; this initWithCoder:coder];
+
+    let key = get_static_str(env, "UISegments");
+    let segments: id = msg![env; coder decodeObjectForKey:key];
+    let count: NSUInteger = if segments == nil { 0 } else { msg![env; segments count] };
+    let string_class = env.objc.get_known_class("NSString", &mut env.mem);
+    for i in 0..count {
+        let segment: id = msg![env; segments objectAtIndex:i];
+        // The segments may also have been decoded as subviews, but the control
+        // draws them itself.
+        () = msg![env; segment removeFromSuperview];
+        let &UISegmentHostObject { info, width, .. } = env.objc.borrow(segment);
+        let is_string: bool = info != nil && msg![env; info isKindOfClass:string_class];
+        let mut segment = if is_string {
+            Segment::new(info, nil)
+        } else {
+            Segment::new(nil, info)
+        };
+        segment.width = width;
+        insert_segment(env, this, segment, i);
+    }
+
+    let key = get_static_str(env, "UISelectedSegmentIndex");
+    let selected: NSInteger = if msg![env; coder containsValueForKey:key] {
+        msg![env; coder decodeIntegerForKey:key]
+    } else {
+        UISegmentedControlNoSegment
+    };
+    let key = get_static_str(env, "UISegmentedControlStyle");
+    let style: NSInteger = msg![env; coder decodeIntegerForKey:key];
+    let key = get_static_str(env, "UIMomentary");
+    let momentary: bool = msg![env; coder decodeBoolForKey:key];
+
+    let host_obj = env.objc.borrow_mut::<UISegmentedControlHostObject>(this);
+    host_obj.selected_segment_index = selected;
+    host_obj.style = style;
+    host_obj.momentary = momentary;
+
+    () = msg![env; this setOpaque:false];
+    this
+}
+
+- (())dealloc {
+    let UISegmentedControlHostObject {
+        segments,
+        tint_color,
+        ..
+    } = std::mem::take(env.objc.borrow_mut(this));
+    for segment in segments {
+        release(env, segment.title);
+        release(env, segment.image);
+    }
+    release(env, tint_color);
+    msg_super![env; this dealloc]
+}
+
+- (NSUInteger)numberOfSegments {
+    env.objc.borrow::<UISegmentedControlHostObject>(this).segments.len() as NSUInteger
+}
+
+- (())insertSegmentWithTitle:(id)title // NSString*
+                     atIndex:(NSUInteger)index
+                    animated:(bool)_animated {
+    insert_segment(env, this, Segment::new(title, nil), index);
+}
+- (())insertSegmentWithImage:(id)image // UIImage*
+                     atIndex:(NSUInteger)index
+                    animated:(bool)_animated {
+    insert_segment(env, this, Segment::new(nil, image), index);
+}
+- (())removeSegmentAtIndex:(NSUInteger)index
+                  animated:(bool)_animated {
+    let host_obj = env.objc.borrow_mut::<UISegmentedControlHostObject>(this);
+    let index = index as usize;
+    if index >= host_obj.segments.len() {
+        return;
+    }
+    let segment = host_obj.segments.remove(index);
+    let selected = host_obj.selected_segment_index;
+    if selected == index as NSInteger {
+        host_obj.selected_segment_index = UISegmentedControlNoSegment;
+    } else if selected > index as NSInteger {
+        host_obj.selected_segment_index -= 1;
+    }
+    release(env, segment.title);
+    release(env, segment.image);
+    () = msg![env; this setNeedsDisplay];
+}
+- (())removeAllSegments {
+    let host_obj = env.objc.borrow_mut::<UISegmentedControlHostObject>(this);
+    let segments = std::mem::take(&mut host_obj.segments);
+    host_obj.selected_segment_index = UISegmentedControlNoSegment;
+    for segment in segments {
+        release(env, segment.title);
+        release(env, segment.image);
+    }
+    () = msg![env; this setNeedsDisplay];
+}
+
+- (id)titleForSegmentAtIndex:(NSUInteger)index {
+    let host_obj = env.objc.borrow::<UISegmentedControlHostObject>(this);
+    host_obj.segments.get(index as usize).map_or(nil, |segment| segment.title)
+}
+- (())setTitle:(id)title // NSString*
+forSegmentAtIndex:(NSUInteger)index {
+    let host_obj = env.objc.borrow_mut::<UISegmentedControlHostObject>(this);
+    let Some(segment) = host_obj.segments.get_mut(index as usize) else {
+        return;
+    };
+    // A segment has either a title or an image, not both.
+    let old_title = std::mem::replace(&mut segment.title, title);
+    let old_image = std::mem::replace(&mut segment.image, nil);
+    retain(env, title);
+    release(env, old_title);
+    release(env, old_image);
+    () = msg![env; this setNeedsDisplay];
+}
+
+- (id)imageForSegmentAtIndex:(NSUInteger)index {
+    let host_obj = env.objc.borrow::<UISegmentedControlHostObject>(this);
+    host_obj.segments.get(index as usize).map_or(nil, |segment| segment.image)
+}
+- (())setImage:(id)image // UIImage*
+forSegmentAtIndex:(NSUInteger)index {
+    let host_obj = env.objc.borrow_mut::<UISegmentedControlHostObject>(this);
+    let Some(segment) = host_obj.segments.get_mut(index as usize) else {
+        return;
+    };
+    let old_image = std::mem::replace(&mut segment.image, image);
+    let old_title = std::mem::replace(&mut segment.title, nil);
+    retain(env, image);
+    release(env, old_image);
+    release(env, old_title);
+    () = msg![env; this setNeedsDisplay];
+}
+
+- (CGFloat)widthForSegmentAtIndex:(NSUInteger)index {
+    let host_obj = env.objc.borrow::<UISegmentedControlHostObject>(this);
+    host_obj.segments.get(index as usize).map_or(0.0, |segment| segment.width)
+}
+- (())setWidth:(CGFloat)width
+forSegmentAtIndex:(NSUInteger)index {
+    let host_obj = env.objc.borrow_mut::<UISegmentedControlHostObject>(this);
+    if let Some(segment) = host_obj.segments.get_mut(index as usize) {
+        segment.width = width;
+    }
+    () = msg![env; this setNeedsDisplay];
+}
+
+- (bool)isEnabledForSegmentAtIndex:(NSUInteger)index {
+    let host_obj = env.objc.borrow::<UISegmentedControlHostObject>(this);
+    host_obj.segments.get(index as usize).is_some_and(|segment| segment.enabled)
+}
+- (())setEnabled:(bool)enabled
+forSegmentAtIndex:(NSUInteger)index {
+    let host_obj = env.objc.borrow_mut::<UISegmentedControlHostObject>(this);
+    if let Some(segment) = host_obj.segments.get_mut(index as usize) {
+        segment.enabled = enabled;
+    }
+    () = msg![env; this setNeedsDisplay];
+}
+
+- (NSInteger)selectedSegmentIndex {
+    env.objc.borrow::<UISegmentedControlHostObject>(this).selected_segment_index
+}
+- (())setSelectedSegmentIndex:(NSInteger)index {
+    let host_obj = env.objc.borrow_mut::<UISegmentedControlHostObject>(this);
+    host_obj.selected_segment_index = if index >= 0 && (index as usize) < host_obj.segments.len() {
+        index
+    } else {
+        UISegmentedControlNoSegment
+    };
+    () = msg![env; this setNeedsDisplay];
+}
+
+- (bool)isMomentary {
+    env.objc.borrow::<UISegmentedControlHostObject>(this).momentary
+}
+- (())setMomentary:(bool)momentary {
+    env.objc.borrow_mut::<UISegmentedControlHostObject>(this).momentary = momentary;
+}
+
+- (UISegmentedControlStyle)segmentedControlStyle {
+    env.objc.borrow::<UISegmentedControlHostObject>(this).style
+}
+- (())setSegmentedControlStyle:(UISegmentedControlStyle)style {
+    env.objc.borrow_mut::<UISegmentedControlHostObject>(this).style = style;
+    () = msg![env; this setNeedsDisplay];
+}
+
+- (id)tintColor {
+    env.objc.borrow::<UISegmentedControlHostObject>(this).tint_color
+}
+- (())setTintColor:(id)color { // UIColor*
+    retain(env, color);
+    let host_obj = env.objc.borrow_mut::<UISegmentedControlHostObject>(this);
+    let old_color = std::mem::replace(&mut host_obj.tint_color, color);
+    release(env, old_color);
+    () = msg![env; this setNeedsDisplay];
+}
+
+// UIControl overrides
+
+- (bool)beginTrackingWithTouch:(id)touch // UITouch*
+                     withEvent:(id)event { // UIEvent*
+    let location: CGPoint = msg![env; touch locationInView:this];
+    let Some(index) = segment_index_at_point(env, this, location) else {
+        return false;
+    };
+    let host_obj = env.objc.borrow_mut::<UISegmentedControlHostObject>(this);
+    if !host_obj.segments[index].enabled {
+        return false;
+    }
+    let index = index as NSInteger;
+    let changed = host_obj.selected_segment_index != index;
+    // Momentary controls still report the touched segment as selected while
+    // the action is sent, but deselect it as soon as tracking ends.
+    host_obj.selected_segment_index = index;
+    let momentary = host_obj.momentary;
+    () = msg![env; this setNeedsDisplay];
+    if changed || momentary {
+        send_actions(env, this, event, UIControlEventValueChanged);
+    }
+    true
+}
+- (())endTrackingWithTouch:(id)touch // UITouch*
+                  withEvent:(id)event { // UIEvent*
+    let host_obj = env.objc.borrow_mut::<UISegmentedControlHostObject>(this);
+    if host_obj.momentary {
+        host_obj.selected_segment_index = UISegmentedControlNoSegment;
+        () = msg![env; this setNeedsDisplay];
+    }
+    msg_super![env; this endTrackingWithTouch:touch withEvent:event]
+}
+
+- (())drawRect:(CGRect)_rect {
+    let bounds: CGRect = msg![env; this bounds];
+    let context = UIGraphicsGetCurrentContext(env);
+    let frames = segment_frames(env, this);
+
+    let &UISegmentedControlHostObject {
+        selected_segment_index,
+        style,
+        tint_color,
+        ..
+    } = env.objc.borrow(this);
+    let enabled: bool = msg![env; this isEnabled];
+    let alpha = if enabled { 1.0 } else { 0.5 };
+
+    // Bar-style controls are filled with the tint color (default: a grey-blue)
+    // and the others are white with a blue selection.
+    let tint = if tint_color != nil {
+        ui_color::get_rgba(&env.objc, tint_color)
+    } else if style == UISegmentedControlStyleBar {
+        (0.45, 0.53, 0.63, 1.0)
+    } else {
+        (0.16, 0.42, 0.87, 1.0)
+    };
+    let is_bar = style == UISegmentedControlStyleBar;
+
+    // Border
+    CGContextSetRGBFillColor(env, context, 0.45, 0.45, 0.45, alpha);
+    CGContextFillRect(env, context, bounds);
+
+    let font_size: CGFloat = if is_bar { 12.0 } else { 13.0 };
+    let font: id = msg_class![env; UIFont boldSystemFontOfSize:font_size];
+
+    let segment_count = frames.len();
+    for (index, frame) in frames.into_iter().enumerate() {
+        let selected = index as NSInteger == selected_segment_index;
+        // Leave a one-pixel border around the control, which also gives a
+        // divider between segments.
+        let is_last = index + 1 == segment_count;
+        let inner = CGRect {
+            origin: CGPoint {
+                x: frame.origin.x + 1.0,
+                y: frame.origin.y + 1.0,
+            },
+            size: CGSize {
+                width: (frame.size.width - if is_last { 2.0 } else { 1.0 }).max(0.0),
+                height: (frame.size.height - 2.0).max(0.0),
+            },
+        };
+
+        let (fill, text) = match (is_bar, selected) {
+            (true, false) => (tint, (1.0, 1.0, 1.0)),
+            (true, true) => ((tint.0 * 0.7, tint.1 * 0.7, tint.2 * 0.7, tint.3), (1.0, 1.0, 1.0)),
+            (false, false) => ((1.0, 1.0, 1.0, 1.0), (0.3, 0.3, 0.3)),
+            (false, true) => (tint, (1.0, 1.0, 1.0)),
+        };
+        CGContextSetRGBFillColor(env, context, fill.0, fill.1, fill.2, fill.3 * alpha);
+        CGContextFillRect(env, context, inner);
+
+        let (title, image, segment_enabled) = {
+            let segment = &env.objc.borrow::<UISegmentedControlHostObject>(this).segments[index];
+            (segment.title, segment.image, segment.enabled)
+        };
+        let segment_alpha = if segment_enabled { alpha } else { alpha * 0.5 };
+
+        if title != nil {
+            let text_size: CGSize = msg![env; title sizeWithFont:font];
+            let text_rect = CGRect {
+                origin: CGPoint {
+                    x: inner.origin.x + 2.0,
+                    y: inner.origin.y + (inner.size.height - text_size.height) / 2.0,
+                },
+                size: CGSize {
+                    width: (inner.size.width - 4.0).max(0.0),
+                    height: text_size.height,
+                },
+            };
+            CGContextSetRGBFillColor(env, context, text.0, text.1, text.2, segment_alpha);
+            let _: CGSize = msg![env; title drawInRect:text_rect
+                                              withFont:font
+                                         lineBreakMode:UILineBreakModeTailTruncation
+                                             alignment:UITextAlignmentCenter];
+        } else if image != nil {
+            let image_size: CGSize = msg![env; image size];
+            let image_rect = CGRect {
+                origin: CGPoint {
+                    x: inner.origin.x + (inner.size.width - image_size.width) / 2.0,
+                    y: inner.origin.y + (inner.size.height - image_size.height) / 2.0,
+                },
+                size: image_size,
+            };
+            () = msg![env; image drawInRect:image_rect];
+        }
+    }
+}
+
+@end
+
+@implementation UISegment: UIView
+
++ (id)allocWithZone:(NSZonePtr)_zone {
+    let host_object = Box::<UISegmentHostObject>::default();
+    env.objc.alloc_object(this, host_object, &mut env.mem)
+}
+
+- (id)initWithCoder:(id)coder {
+    let this: id = msg_super![env; this initWithCoder:coder];
+    let key = get_static_str(env, "UISegmentInfo");
+    let info: id = msg![env; coder decodeObjectForKey:key];
+    let key = get_static_str(env, "UISegmentWidth");
+    let width: CGFloat = msg![env; coder decodeFloatForKey:key];
+    retain(env, info);
+    let host_obj = env.objc.borrow_mut::<UISegmentHostObject>(this);
+    host_obj.info = info;
+    host_obj.width = width;
+    this
+}
+
+- (())dealloc {
+    let &UISegmentHostObject { info, .. } = env.objc.borrow(this);
+    release(env, info);
+    msg_super![env; this dealloc]
+}
+
+@end
+
+};
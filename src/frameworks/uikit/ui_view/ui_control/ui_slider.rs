@@ -0,0 +1,342 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! `UISlider`.
+
+use super::{send_actions, UIControlEventValueChanged, UIControlState};
+use crate::frameworks::core_graphics::cg_context::{CGContextFillRect, CGContextSetRGBFillColor};
+use crate::frameworks::core_graphics::{CGFloat, CGPoint, CGRect, CGSize};
+use crate::frameworks::foundation::ns_string::get_static_str;
+use crate::frameworks::uikit::ui_color;
+use crate::frameworks::uikit::ui_graphics::UIGraphicsGetCurrentContext;
+use crate::objc::{
+    id, impl_HostObject_with_superclass, msg, msg_super, nil, objc_classes, release, retain,
+    ClassExports, NSZonePtr,
+};
+use crate::Environment;
+
+/// Size of the square thumb. The real one is round, but only rectangles can be
+/// drawn at the moment.
+const THUMB_SIZE: CGFloat = 23.0;
+/// Height of the track the thumb slides along.
+const TRACK_HEIGHT: CGFloat = 9.0;
+
+pub struct UISliderHostObject {
+    superclass: super::UIControlHostObject,
+    value: f32,
+    minimum_value: f32,
+    maximum_value: f32,
+    continuous: bool,
+    /// `UIColor*`, [nil] means the default
+    minimum_track_tint_color: id,
+    /// `UIColor*`, [nil] means the default
+    maximum_track_tint_color: id,
+    /// `UIColor*`, [nil] means the default
+    thumb_tint_color: id,
+}
+impl_HostObject_with_superclass!(UISliderHostObject);
+impl Default for UISliderHostObject {
+    fn default() -> Self {
+        UISliderHostObject {
+            superclass: Default::default(),
+            value: 0.0,
+            minimum_value: 0.0,
+            maximum_value: 1.0,
+            continuous: true,
+            minimum_track_tint_color: nil,
+            maximum_track_tint_color: nil,
+            thumb_tint_color: nil,
+        }
+    }
+}
+
+/// Converts a touch position to a slider value and applies it. Returns [true]
+/// if the value changed.
+fn track_touch(env: &mut Environment, this: id, touch: id) -> bool {
+    let bounds: CGRect = msg![env; this bounds];
+    let location: CGPoint = msg![env; touch locationInView:this];
+
+    let &UISliderHostObject {
+        value: old_value,
+        minimum_value,
+        maximum_value,
+        ..
+    } = env.objc.borrow(this);
+
+    let usable_width = (bounds.size.width - THUMB_SIZE).max(1.0);
+    let fraction = (location.x - bounds.origin.x - THUMB_SIZE / 2.0) / usable_width;
+    let new_value = minimum_value + fraction.clamp(0.0, 1.0) * (maximum_value - minimum_value);
+
+    if new_value == old_value {
+        return false;
+    }
+    () = msg![env; this setValue:new_value];
+    true
+}
+
+fn set_color(
+    env: &mut Environment,
+    this: id,
+    color: id,
+    field: fn(&mut UISliderHostObject) -> &mut id,
+) {
+    retain(env, color);
+    let host_obj = env.objc.borrow_mut::<UISliderHostObject>(this);
+    let old = std::mem::replace(field(host_obj), color);
+    release(env, old);
+    () = msg![env; this setNeedsDisplay];
+}
+
+pub const CLASSES: ClassExports = objc_classes! {
+
+(env, this, _cmd);
+
+@implementation UISlider: UIControl
+
++ (id)allocWithZone:(NSZonePtr)_zone {
+    let host_object = Box::<UISliderHostObject>::default();
+    env.objc.alloc_object(this, host_object, &mut env.mem)
+}
+
+- (id)initWithFrame:(CGRect)frame {
+    let this: id = msg_super![env; this initWithFrame:frame];
+    // Built-in views don't have user-controlled opaqueness.
+    () = msg![env; this setOpaque:false];
+    this
+}
+
+- (id)initWithCoder:(id)coder {
+    let this: id = msg_super![env; this initWithCoder:coder];
+
+    let key = get_static_str(env, "UIMinValue");
+    let minimum_value: f32 = msg![env; coder decodeFloatForKey:key];
+    let key = get_static_str(env, "UIMaxValue");
+    let maximum_value: f32 = if msg![env; coder containsValueForKey:key] {
+        msg![env; coder decodeFloatForKey:key]
+    } else {
+        1.0
+    };
+    let key = get_static_str(env, "UIValue");
+    let value: f32 = msg![env; coder decodeFloatForKey:key];
+    let key = get_static_str(env, "UIContinuous");
+    let continuous: bool = if msg![env; coder containsValueForKey:key] {
+        msg![env; coder decodeBoolForKey:key]
+    } else {
+        true
+    };
+
+    let host_obj = env.objc.borrow_mut::<UISliderHostObject>(this);
+    host_obj.minimum_value = minimum_value;
+    host_obj.maximum_value = maximum_value;
+    host_obj.value = value.clamp(minimum_value, maximum_value);
+    host_obj.continuous = continuous;
+
+    () = msg![env; this setOpaque:false];
+    this
+}
+
+- (())dealloc {
+    let UISliderHostObject {
+        minimum_track_tint_color,
+        maximum_track_tint_color,
+        thumb_tint_color,
+        ..
+    } = std::mem::take(env.objc.borrow_mut(this));
+    release(env, minimum_track_tint_color);
+    release(env, maximum_track_tint_color);
+    release(env, thumb_tint_color);
+    msg_super![env; this dealloc]
+}
+
+- (f32)value {
+    env.objc.borrow::<UISliderHostObject>(this).value
+}
+- (())setValue:(f32)value {
+    let host_obj = env.objc.borrow_mut::<UISliderHostObject>(this);
+    host_obj.value = value.clamp(host_obj.minimum_value, host_obj.maximum_value);
+    () = msg![env; this setNeedsDisplay];
+}
+- (())setValue:(f32)value animated:(bool)_animated {
+    // TODO: animation
+    msg![env; this setValue:value]
+}
+
+- (f32)minimumValue {
+    env.objc.borrow::<UISliderHostObject>(this).minimum_value
+}
+- (())setMinimumValue:(f32)minimum_value {
+    let host_obj = env.objc.borrow_mut::<UISliderHostObject>(this);
+    host_obj.minimum_value = minimum_value;
+    if host_obj.maximum_value < minimum_value {
+        host_obj.maximum_value = minimum_value;
+    }
+    let value = host_obj.value;
+    () = msg![env; this setValue:value];
+}
+
+- (f32)maximumValue {
+    env.objc.borrow::<UISliderHostObject>(this).maximum_value
+}
+- (())setMaximumValue:(f32)maximum_value {
+    let host_obj = env.objc.borrow_mut::<UISliderHostObject>(this);
+    host_obj.maximum_value = maximum_value;
+    if host_obj.minimum_value > maximum_value {
+        host_obj.minimum_value = maximum_value;
+    }
+    let value = host_obj.value;
+    () = msg![env; this setValue:value];
+}
+
+- (bool)isContinuous {
+    env.objc.borrow::<UISliderHostObject>(this).continuous
+}
+- (())setContinuous:(bool)continuous {
+    env.objc.borrow_mut::<UISliderHostObject>(this).continuous = continuous;
+}
+
+- (id)minimumTrackTintColor {
+    env.objc.borrow::<UISliderHostObject>(this).minimum_track_tint_color
+}
+- (())setMinimumTrackTintColor:(id)color { // UIColor*
+    set_color(env, this, color, |host_obj| &mut host_obj.minimum_track_tint_color);
+}
+- (id)maximumTrackTintColor {
+    env.objc.borrow::<UISliderHostObject>(this).maximum_track_tint_color
+}
+- (())setMaximumTrackTintColor:(id)color { // UIColor*
+    set_color(env, this, color, |host_obj| &mut host_obj.maximum_track_tint_color);
+}
+- (id)thumbTintColor {
+    env.objc.borrow::<UISliderHostObject>(this).thumb_tint_color
+}
+- (())setThumbTintColor:(id)color { // UIColor*
+    set_color(env, this, color, |host_obj| &mut host_obj.thumb_tint_color);
+}
+
+- (())setThumbImage:(id)image // UIImage*
+           forState:(UIControlState)state {
+    log!("TODO: [(UISlider*){:?} setThumbImage:{:?} forState:{}]", this, image, state);
+}
+- (())setMinimumTrackImage:(id)image // UIImage*
+                  forState:(UIControlState)state {
+    log!("TODO: [(UISlider*){:?} setMinimumTrackImage:{:?} forState:{}]", this, image, state);
+}
+- (())setMaximumTrackImage:(id)image // UIImage*
+                  forState:(UIControlState)state {
+    log!("TODO: [(UISlider*){:?} setMaximumTrackImage:{:?} forState:{}]", this, image, state);
+}
+
+- (CGSize)sizeThatFits:(CGSize)size {
+    CGSize {
+        width: size.width,
+        height: THUMB_SIZE,
+    }
+}
+
+// UIControl overrides
+
+- (bool)beginTrackingWithTouch:(id)touch // UITouch*
+                     withEvent:(id)event { // UIEvent*
+    let continuous = env.objc.borrow::<UISliderHostObject>(this).continuous;
+    if track_touch(env, this, touch) && continuous {
+        send_actions(env, this, event, UIControlEventValueChanged);
+    }
+    true
+}
+- (bool)continueTrackingWithTouch:(id)touch // UITouch*
+                        withEvent:(id)event { // UIEvent*
+    let continuous = env.objc.borrow::<UISliderHostObject>(this).continuous;
+    if track_touch(env, this, touch) && continuous {
+        send_actions(env, this, event, UIControlEventValueChanged);
+    }
+    true
+}
+- (())endTrackingWithTouch:(id)touch // UITouch*
+                  withEvent:(id)event { // UIEvent*
+    let continuous = env.objc.borrow::<UISliderHostObject>(this).continuous;
+    let changed = track_touch(env, this, touch);
+    // A non-continuous slider only reports the final value.
+    if changed || !continuous {
+        send_actions(env, this, event, UIControlEventValueChanged);
+    }
+    msg_super![env; this endTrackingWithTouch:touch withEvent:event]
+}
+
+- (())drawRect:(CGRect)_rect {
+    let bounds: CGRect = msg![env; this bounds];
+    let context = UIGraphicsGetCurrentContext(env);
+
+    let &UISliderHostObject {
+        value,
+        minimum_value,
+        maximum_value,
+        minimum_track_tint_color,
+        maximum_track_tint_color,
+        thumb_tint_color,
+        ..
+    } = env.objc.borrow(this);
+    let enabled: bool = msg![env; this isEnabled];
+    let highlighted: bool = msg![env; this isHighlighted];
+
+    let fraction = if maximum_value > minimum_value {
+        (value - minimum_value) / (maximum_value - minimum_value)
+    } else {
+        0.0
+    };
+    let usable_width = (bounds.size.width - THUMB_SIZE).max(0.0);
+    let thumb_x = bounds.origin.x + fraction * usable_width;
+    let center_y = bounds.origin.y + bounds.size.height / 2.0;
+
+    let alpha = if enabled { 1.0 } else { 0.5 };
+    let color_or = |env: &mut Environment, color: id, default| if color == nil {
+        default
+    } else {
+        let (r, g, b, a) = ui_color::get_rgba(&env.objc, color);
+        (r, g, b, a * alpha)
+    };
+
+    let track_y = center_y - TRACK_HEIGHT / 2.0;
+    let (r, g, b, a) = color_or(env, minimum_track_tint_color, (0.16, 0.42, 0.87, alpha));
+    CGContextSetRGBFillColor(env, context, r, g, b, a);
+    CGContextFillRect(env, context, CGRect {
+        origin: CGPoint { x: bounds.origin.x + 2.0, y: track_y },
+        size: CGSize {
+            width: (thumb_x + THUMB_SIZE / 2.0 - bounds.origin.x - 2.0).max(0.0),
+            height: TRACK_HEIGHT,
+        },
+    });
+    let (r, g, b, a) = color_or(env, maximum_track_tint_color, (0.72, 0.72, 0.72, alpha));
+    CGContextSetRGBFillColor(env, context, r, g, b, a);
+    CGContextFillRect(env, context, CGRect {
+        origin: CGPoint { x: thumb_x + THUMB_SIZE / 2.0, y: track_y },
+        size: CGSize {
+            width: (bounds.origin.x + bounds.size.width - 2.0 - thumb_x - THUMB_SIZE / 2.0).max(0.0),
+            height: TRACK_HEIGHT,
+        },
+    });
+
+    let thumb_default = if highlighted {
+        (0.85, 0.85, 0.85, alpha)
+    } else {
+        (0.97, 0.97, 0.97, alpha)
+    };
+    let thumb_rect = CGRect {
+        origin: CGPoint { x: thumb_x, y: center_y - THUMB_SIZE / 2.0 },
+        size: CGSize { width: THUMB_SIZE, height: THUMB_SIZE },
+    };
+    // Border, then face
+    CGContextSetRGBFillColor(env, context, 0.5, 0.5, 0.5, alpha);
+    CGContextFillRect(env, context, thumb_rect);
+    let (r, g, b, a) = color_or(env, thumb_tint_color, thumb_default);
+    CGContextSetRGBFillColor(env, context, r, g, b, a);
+    CGContextFillRect(env, context, CGRect {
+        origin: CGPoint { x: thumb_rect.origin.x + 1.0, y: thumb_rect.origin.y + 1.0 },
+        size: CGSize { width: THUMB_SIZE - 2.0, height: THUMB_SIZE - 2.0 },
+    });
+}
+
+@end
+
+};
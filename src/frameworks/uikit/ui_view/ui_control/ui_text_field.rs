@@ -8,32 +8,96 @@
 //! Useful resources:
 //! - [UITextFieldDelegate overview](https://developer.apple.com/documentation/uikit/uitextfielddelegate?language=objc)
 
-use sdl2_sys::{SDL_StartTextInput, SDL_StopTextInput};
-
-use crate::frameworks::core_graphics::CGRect;
-use crate::frameworks::foundation::{ns_string, NSInteger, NSRange, NSUInteger};
-use crate::frameworks::uikit::ui_font::UITextAlignmentLeft;
-use crate::frameworks::uikit::ui_view::ui_window::{
-    UIKeyboardDidHideNotification, UIKeyboardDidShowNotification, UIKeyboardWillHideNotification,
-    UIKeyboardWillShowNotification,
+use super::{
+    send_actions, UIControlEventEditingChanged, UIControlEventEditingDidBegin,
+    UIControlEventEditingDidEnd, UIControlEventEditingDidEndOnExit, UIControlEventTouchUpInside,
+    UIControlHostObject, UIControlStateNormal,
 };
+use crate::dyld::{ConstantExports, HostConstant};
+use crate::frameworks::core_graphics::cg_context::{
+    CGContextFillRect, CGContextRef, CGContextSetRGBFillColor,
+};
+use crate::frameworks::core_graphics::{CGFloat, CGPoint, CGRect, CGSize};
+use crate::frameworks::foundation::ns_string::{from_rust_string, get_static_str, to_rust_string};
+use crate::frameworks::foundation::{NSInteger, NSRange, NSUInteger};
+use crate::frameworks::uikit::ui_font::{UITextAlignment, UITextAlignmentLeft};
+use crate::frameworks::uikit::ui_graphics::UIGraphicsGetCurrentContext;
+use crate::frameworks::uikit::ui_view::ui_alert_view::delegate_responds;
+use crate::frameworks::uikit::ui_view::ui_keyboard::{self, UIKeyboardType};
 use crate::impl_HostObject_with_superclass;
 use crate::objc::{
-    id, msg, msg_class, msg_super, nil, objc_classes, release, ClassExports, NSZonePtr, SEL,
+    autorelease, id, msg, msg_class, msg_super, nil, objc_classes, release, retain, ClassExports,
+    NSZonePtr,
 };
 use crate::Environment;
 
 type UIKeyboardAppearance = NSInteger;
-type UIKeyboardType = NSInteger;
 type UIReturnKeyType = NSInteger;
 type UITextAutocapitalizationType = NSInteger;
 type UITextAutocorrectionType = NSInteger;
 
+type UITextBorderStyle = NSInteger;
+const UITextBorderStyleNone: UITextBorderStyle = 0;
+const UITextBorderStyleLine: UITextBorderStyle = 1;
+const UITextBorderStyleBezel: UITextBorderStyle = 2;
+const UITextBorderStyleRoundedRect: UITextBorderStyle = 3;
+
+type UITextFieldViewMode = NSInteger;
+const UITextFieldViewModeNever: UITextFieldViewMode = 0;
+const UITextFieldViewModeWhileEditing: UITextFieldViewMode = 1;
+const UITextFieldViewModeUnlessEditing: UITextFieldViewMode = 2;
+#[allow(dead_code)]
+const UITextFieldViewModeAlways: UITextFieldViewMode = 3;
+
+pub const UITextFieldTextDidBeginEditingNotification: &str =
+    "UITextFieldTextDidBeginEditingNotification";
+pub const UITextFieldTextDidChangeNotification: &str = "UITextFieldTextDidChangeNotification";
+pub const UITextFieldTextDidEndEditingNotification: &str =
+    "UITextFieldTextDidEndEditingNotification";
+
+pub const CONSTANTS: ConstantExports = &[
+    (
+        "_UITextFieldTextDidBeginEditingNotification",
+        HostConstant::NSString(UITextFieldTextDidBeginEditingNotification),
+    ),
+    (
+        "_UITextFieldTextDidChangeNotification",
+        HostConstant::NSString(UITextFieldTextDidChangeNotification),
+    ),
+    (
+        "_UITextFieldTextDidEndEditingNotification",
+        HostConstant::NSString(UITextFieldTextDidEndEditingNotification),
+    ),
+];
+
+/// Size of the (square) clear button.
+const CLEAR_BUTTON_SIZE: CGFloat = 19.0;
+/// Gap between the clear button and the right edge / the text.
+const CLEAR_BUTTON_MARGIN: CGFloat = 4.0;
+/// Character used to mask the text when `secureTextEntry` is set.
+const SECURE_TEXT_BULLET: &str = "\u{2022}";
+
 struct UITextFieldHostObject {
-    superclass: super::UIControlHostObject,
+    superclass: UIControlHostObject,
     delegate: id,
     editing: bool,
+    /// The real text (an `NSString*`), which may differ from what the text
+    /// label displays if secure text entry is on.
+    text: id,
+    placeholder: id,
     text_label: id,
+    placeholder_label: id,
+    clear_button: id,
+    border_style: UITextBorderStyle,
+    clear_button_mode: UITextFieldViewMode,
+    clears_on_begin_editing: bool,
+    secure_text_entry: bool,
+    autocapitalization_type: UITextAutocapitalizationType,
+    autocorrection_type: UITextAutocorrectionType,
+    keyboard_appearance: UIKeyboardAppearance,
+    keyboard_type: UIKeyboardType,
+    return_key_type: UIReturnKeyType,
+    enables_return_key_automatically: bool,
 }
 impl_HostObject_with_superclass!(UITextFieldHostObject);
 impl Default for UITextFieldHostObject {
@@ -42,7 +106,21 @@ impl Default for UITextFieldHostObject {
             superclass: Default::default(),
             delegate: nil,
             editing: false,
+            text: nil,
+            placeholder: nil,
             text_label: nil,
+            placeholder_label: nil,
+            clear_button: nil,
+            border_style: UITextBorderStyleNone,
+            clear_button_mode: UITextFieldViewModeNever,
+            clears_on_begin_editing: false,
+            secure_text_entry: false,
+            autocapitalization_type: 0, // UITextAutocapitalizationTypeNone
+            autocorrection_type: 0,     // UITextAutocorrectionTypeDefault
+            keyboard_appearance: 0,     // UIKeyboardAppearanceDefault
+            keyboard_type: 0,           // UIKeyboardTypeDefault
+            return_key_type: 0,         // UIReturnKeyDefault
+            enables_return_key_automatically: false,
         }
     }
 }
@@ -53,9 +131,7 @@ pub const CLASSES: ClassExports = objc_classes! {
 
 @implementation UITextField: UIControl
 
-// TODO: additional rendering (e.g. placeholder, border, clear button, etc.)
-// TODO: more properties
-// TODO: notifications
+// TODO: left/right views, background images, caret and selection
 
 + (id)allocWithZone:(NSZonePtr)_zone {
     let host_object = Box::<UITextFieldHostObject>::default();
@@ -64,87 +140,307 @@ pub const CLASSES: ClassExports = objc_classes! {
 
 - (id)initWithFrame:(CGRect)frame {
     let this: id = msg_super![env; this initWithFrame:frame];
-
-    () = msg![env; this setOpaque:true];
-    let bg_color: id = msg_class![env; UIColor whiteColor];
-    () = msg![env; this setBackgroundColor:bg_color];
-
-    let text_label: id = msg_class![env; UILabel new];
-    () = msg![env; text_label setBackgroundColor:bg_color];
-    () = msg![env; text_label setTextAlignment:UITextAlignmentLeft];
-
-    let text_color: id = msg_class![env; UIColor blackColor];
-    () = msg![env; text_label setTextColor:text_color];
-
-    let host_obj = env.objc.borrow_mut::<UITextFieldHostObject>(this);
-    host_obj.text_label = text_label;
-
-    () = msg![env; this addSubview:text_label];
-
+    init_common(env, this);
     this
 }
 
 - (id)initWithCoder:(id)coder {
     let this: id = msg_super![env; this initWithCoder: coder];
-
-    // TODO: actual decoding of properties
-
-    let text_label: id = msg_class![env; UILabel new];
-
-    let host_obj = env.objc.borrow_mut::<UITextFieldHostObject>(this);
-    host_obj.text_label = text_label;
-
-    () = msg![env; this addSubview:text_label];
+    init_common(env, this);
+
+    let key = get_static_str(env, "UIText");
+    let text: id = msg![env; coder decodeObjectForKey:key];
+    () = msg![env; this setText:text];
+    let key = get_static_str(env, "UIPlaceholder");
+    let placeholder: id = msg![env; coder decodeObjectForKey:key];
+    () = msg![env; this setPlaceholder:placeholder];
+    let key = get_static_str(env, "UIBorderStyle");
+    let border_style: UITextBorderStyle = msg![env; coder decodeIntegerForKey:key];
+    () = msg![env; this setBorderStyle:border_style];
+    let key = get_static_str(env, "UIClearButtonMode");
+    let clear_button_mode: UITextFieldViewMode = msg![env; coder decodeIntegerForKey:key];
+    () = msg![env; this setClearButtonMode:clear_button_mode];
+    let key = get_static_str(env, "UIClearsOnBeginEditing");
+    let clears: bool = msg![env; coder decodeBoolForKey:key];
+    () = msg![env; this setClearsOnBeginEditing:clears];
+
+    let key = get_static_str(env, "UIFont");
+    let font: id = msg![env; coder decodeObjectForKey:key];
+    if font != nil {
+        () = msg![env; this setFont:font];
+    }
+    let key = get_static_str(env, "UITextColor");
+    let text_color: id = msg![env; coder decodeObjectForKey:key];
+    if text_color != nil {
+        () = msg![env; this setTextColor:text_color];
+    }
+    let key = get_static_str(env, "UITextAlignment");
+    let text_alignment: UITextAlignment = msg![env; coder decodeIntegerForKey:key];
+    () = msg![env; this setTextAlignment:text_alignment];
+    let key = get_static_str(env, "UIAdjustsFontSizeToFit");
+    let adjusts: bool = msg![env; coder decodeBoolForKey:key];
+    () = msg![env; this setAdjustsFontSizeToFitWidth:adjusts];
+    let key = get_static_str(env, "UIMinimumFontSize");
+    let minimum_font_size: CGFloat = msg![env; coder decodeFloatForKey:key];
+    () = msg![env; this setMinimumFontSize:minimum_font_size];
+
+    // UITextInputTraits
+    let key = get_static_str(env, "UIAutocapitalizationType");
+    let autocapitalization_type: UITextAutocapitalizationType =
+        msg![env; coder decodeIntegerForKey:key];
+    () = msg![env; this setAutocapitalizationType:autocapitalization_type];
+    let key = get_static_str(env, "UIAutocorrectionType");
+    let autocorrection_type: UITextAutocorrectionType = msg![env; coder decodeIntegerForKey:key];
+    () = msg![env; this setAutocorrectionType:autocorrection_type];
+    let key = get_static_str(env, "UIKeyboardType");
+    let keyboard_type: UIKeyboardType = msg![env; coder decodeIntegerForKey:key];
+    () = msg![env; this setKeyboardType:keyboard_type];
+    let key = get_static_str(env, "UIKeyboardAppearance");
+    let keyboard_appearance: UIKeyboardAppearance = msg![env; coder decodeIntegerForKey:key];
+    () = msg![env; this setKeyboardAppearance:keyboard_appearance];
+    let key = get_static_str(env, "UIReturnKeyType");
+    let return_key_type: UIReturnKeyType = msg![env; coder decodeIntegerForKey:key];
+    () = msg![env; this setReturnKeyType:return_key_type];
+    let key = get_static_str(env, "UIEnablesReturnKeyAutomatically");
+    let enables: bool = msg![env; coder decodeBoolForKey:key];
+    () = msg![env; this setEnablesReturnKeyAutomatically:enables];
+    let key = get_static_str(env, "UISecureTextEntry");
+    let secure: bool = msg![env; coder decodeBoolForKey:key];
+    () = msg![env; this setSecureTextEntry:secure];
 
     this
 }
 
 - (())dealloc {
     let UITextFieldHostObject {
+        text,
+        placeholder,
         text_label,
+        placeholder_label,
+        clear_button,
         ..
     } = std::mem::take(env.objc.borrow_mut(this));
 
+    release(env, text);
+    release(env, placeholder);
     release(env, text_label);
+    release(env, placeholder_label);
+    release(env, clear_button);
     msg_super![env; this dealloc]
 }
 
+- (CGRect)borderRectForBounds:(CGRect)bounds {
+    bounds
+}
+- (CGRect)textRectForBounds:(CGRect)bounds {
+    let border_style = env.objc.borrow::<UITextFieldHostObject>(this).border_style;
+    let (dx, dy) = match border_style {
+        UITextBorderStyleLine => (2.0, 2.0),
+        UITextBorderStyleBezel => (3.0, 3.0),
+        UITextBorderStyleRoundedRect => (7.0, 3.0),
+        _ => (0.0, 0.0),
+    };
+    inset_rect(bounds, dx, dy)
+}
+- (CGRect)placeholderRectForBounds:(CGRect)bounds {
+    msg![env; this textRectForBounds:bounds]
+}
+- (CGRect)editingRectForBounds:(CGRect)bounds {
+    let rect: CGRect = msg![env; this textRectForBounds:bounds];
+    let clear_button = env.objc.borrow::<UITextFieldHostObject>(this).clear_button;
+    if msg![env; clear_button isHidden] {
+        return rect;
+    }
+    // Don't let the text run under the clear button.
+    let clear_rect: CGRect = msg![env; this clearButtonRectForBounds:bounds];
+    CGRect {
+        size: CGSize {
+            width: (clear_rect.origin.x - CLEAR_BUTTON_MARGIN - rect.origin.x).max(0.0),
+            ..rect.size
+        },
+        ..rect
+    }
+}
+- (CGRect)clearButtonRectForBounds:(CGRect)bounds {
+    CGRect {
+        origin: CGPoint {
+            x: bounds.origin.x + bounds.size.width - CLEAR_BUTTON_SIZE - CLEAR_BUTTON_MARGIN,
+            y: bounds.origin.y + (bounds.size.height - CLEAR_BUTTON_SIZE) / 2.0,
+        },
+        size: CGSize {
+            width: CLEAR_BUTTON_SIZE,
+            height: CLEAR_BUTTON_SIZE,
+        },
+    }
+}
+
 - (())layoutSubviews {
-    let text_label = env.objc.borrow_mut::<UITextFieldHostObject>(this).text_label;
+    let &UITextFieldHostObject {
+        editing,
+        text_label,
+        placeholder_label,
+        clear_button,
+        ..
+    } = env.objc.borrow(this);
     let bounds: CGRect = msg![env; this bounds];
 
-    () = msg![env; text_label setFrame:bounds];
+    let text_rect: CGRect = if editing {
+        msg![env; this editingRectForBounds:bounds]
+    } else {
+        msg![env; this textRectForBounds:bounds]
+    };
+    () = msg![env; text_label setFrame:text_rect];
+    let placeholder_rect: CGRect = msg![env; this placeholderRectForBounds:bounds];
+    () = msg![env; placeholder_label setFrame:placeholder_rect];
+    let clear_rect: CGRect = msg![env; this clearButtonRectForBounds:bounds];
+    () = msg![env; clear_button setFrame:clear_rect];
+}
+
+- (())drawRect:(CGRect)_rect {
+    let border_style = env.objc.borrow::<UITextFieldHostObject>(this).border_style;
+    if border_style == UITextBorderStyleNone {
+        return;
+    }
+
+    let bounds: CGRect = msg![env; this bounds];
+    let border_rect: CGRect = msg![env; this borderRectForBounds:bounds];
+    let context = UIGraphicsGetCurrentContext(env);
+    match border_style {
+        UITextBorderStyleLine => {
+            CGContextSetRGBFillColor(env, context, 0.0, 0.0, 0.0, 1.0);
+            draw_outline(env, context, border_rect, 1.0);
+        }
+        UITextBorderStyleBezel => {
+            CGContextSetRGBFillColor(env, context, 0.5, 0.5, 0.5, 1.0);
+            draw_outline(env, context, border_rect, 1.0);
+            // The bezel is darker along the top and left edges, so that the
+            // field looks sunken.
+            CGContextSetRGBFillColor(env, context, 0.25, 0.25, 0.25, 1.0);
+            let inner = inset_rect(border_rect, 1.0, 1.0);
+            CGContextFillRect(env, context, CGRect {
+                size: CGSize { height: 1.0, ..inner.size },
+                ..inner
+            });
+            CGContextFillRect(env, context, CGRect {
+                size: CGSize { width: 1.0, ..inner.size },
+                ..inner
+            });
+        }
+        UITextBorderStyleRoundedRect => {
+            // TODO: actually round the corners
+            CGContextSetRGBFillColor(env, context, 1.0, 1.0, 1.0, 1.0);
+            CGContextFillRect(env, context, border_rect);
+            CGContextSetRGBFillColor(env, context, 0.6, 0.6, 0.6, 1.0);
+            draw_outline(env, context, border_rect, 1.0);
+        }
+        _ => {
+            log!("TODO: UITextBorderStyle {}", border_style);
+        }
+    }
 }
 
 - (id)text {
-    let text_label = env.objc.borrow_mut::<UITextFieldHostObject>(this).text_label;
-    msg![env; text_label text]
+    env.objc.borrow::<UITextFieldHostObject>(this).text
 }
 - (())setText:(id)text { // NSString*
-    let text_label = env.objc.borrow_mut::<UITextFieldHostObject>(this).text_label;
-    () = msg![env; text_label setText:text];
+    let text: id = msg![env; text copy];
+    let old_text = std::mem::replace(
+        &mut env.objc.borrow_mut::<UITextFieldHostObject>(this).text,
+        text,
+    );
+    release(env, old_text);
+    update(env, this);
 }
 
+- (id)placeholder {
+    env.objc.borrow::<UITextFieldHostObject>(this).placeholder
+}
+- (())setPlaceholder:(id)placeholder { // NSString*
+    let placeholder: id = msg![env; placeholder copy];
+    let host_obj = env.objc.borrow_mut::<UITextFieldHostObject>(this);
+    let old_placeholder = std::mem::replace(&mut host_obj.placeholder, placeholder);
+    let placeholder_label = host_obj.placeholder_label;
+    release(env, old_placeholder);
+    () = msg![env; placeholder_label setText:placeholder];
+    update(env, this);
+}
+
+- (id)textColor {
+    let text_label = env.objc.borrow::<UITextFieldHostObject>(this).text_label;
+    msg![env; text_label textColor]
+}
 - (())setTextColor:(id)color { // UIColor*
-    let text_label = env.objc.borrow_mut::<UITextFieldHostObject>(this).text_label;
+    let text_label = env.objc.borrow::<UITextFieldHostObject>(this).text_label;
     msg![env; text_label setTextColor:color]
 }
 
+- (id)font {
+    let text_label = env.objc.borrow::<UITextFieldHostObject>(this).text_label;
+    msg![env; text_label font]
+}
 - (())setFont:(id)new_font { // UIFont*
-    let text_label = env.objc.borrow_mut::<UITextFieldHostObject>(this).text_label;
-    msg![env; text_label setFont:new_font]
+    let &UITextFieldHostObject {
+        text_label,
+        placeholder_label,
+        ..
+    } = env.objc.borrow(this);
+    () = msg![env; text_label setFont:new_font];
+    () = msg![env; placeholder_label setFont:new_font];
 }
 
-- (())setClearsOnBeginEditing:(bool)clear {
-    log!("TODO: setClearsOnBeginEditing:{}", clear);
+- (UITextAlignment)textAlignment {
+    let text_label = env.objc.borrow::<UITextFieldHostObject>(this).text_label;
+    msg![env; text_label textAlignment]
+}
+- (())setTextAlignment:(UITextAlignment)alignment {
+    let &UITextFieldHostObject {
+        text_label,
+        placeholder_label,
+        ..
+    } = env.objc.borrow(this);
+    () = msg![env; text_label setTextAlignment:alignment];
+    () = msg![env; placeholder_label setTextAlignment:alignment];
 }
 
-- (())setClearButtonMode:(NSInteger)mode {
-    log!("TODO: setClearButtonMode:{}", mode);
+- (bool)adjustsFontSizeToFitWidth {
+    let text_label = env.objc.borrow::<UITextFieldHostObject>(this).text_label;
+    msg![env; text_label adjustsFontSizeToFitWidth]
+}
+- (())setAdjustsFontSizeToFitWidth:(bool)adjusts {
+    let text_label = env.objc.borrow::<UITextFieldHostObject>(this).text_label;
+    msg![env; text_label setAdjustsFontSizeToFitWidth:adjusts]
+}
+- (CGFloat)minimumFontSize {
+    let text_label = env.objc.borrow::<UITextFieldHostObject>(this).text_label;
+    msg![env; text_label minimumFontSize]
+}
+- (())setMinimumFontSize:(CGFloat)size {
+    let text_label = env.objc.borrow::<UITextFieldHostObject>(this).text_label;
+    msg![env; text_label setMinimumFontSize:size]
 }
 
-- (())setSecureTextEntry:(bool)secure {
-    log!("TODO: setSecureTextEntry:{}", secure);
+- (UITextBorderStyle)borderStyle {
+    env.objc.borrow::<UITextFieldHostObject>(this).border_style
+}
+- (())setBorderStyle:(UITextBorderStyle)style {
+    env.objc.borrow_mut::<UITextFieldHostObject>(this).border_style = style;
+    () = msg![env; this setNeedsLayout];
+    () = msg![env; this setNeedsDisplay];
+}
+
+- (bool)clearsOnBeginEditing {
+    env.objc.borrow::<UITextFieldHostObject>(this).clears_on_begin_editing
+}
+- (())setClearsOnBeginEditing:(bool)clear {
+    env.objc.borrow_mut::<UITextFieldHostObject>(this).clears_on_begin_editing = clear;
+}
+
+- (UITextFieldViewMode)clearButtonMode {
+    env.objc.borrow::<UITextFieldHostObject>(this).clear_button_mode
+}
+- (())setClearButtonMode:(UITextFieldViewMode)mode {
+    env.objc.borrow_mut::<UITextFieldHostObject>(this).clear_button_mode = mode;
+    update(env, this);
 }
 
 // weak/non-retaining
@@ -158,26 +454,51 @@ pub const CLASSES: ClassExports = objc_classes! {
 }
 
 // UITextInputTraits implementation
+- (UITextAutocapitalizationType)autocapitalizationType {
+    env.objc.borrow::<UITextFieldHostObject>(this).autocapitalization_type
+}
 - (())setAutocapitalizationType:(UITextAutocapitalizationType)type_ {
-    log!("TODO: setAutocapitalizationType:{}", type_);
+    env.objc.borrow_mut::<UITextFieldHostObject>(this).autocapitalization_type = type_;
+}
+- (UITextAutocorrectionType)autocorrectionType {
+    env.objc.borrow::<UITextFieldHostObject>(this).autocorrection_type
 }
 - (())setAutocorrectionType:(UITextAutocorrectionType)type_ {
-    log!("TODO: setAutocorrectionType:{}", type_);
+    env.objc.borrow_mut::<UITextFieldHostObject>(this).autocorrection_type = type_;
+}
+- (UIReturnKeyType)returnKeyType {
+    env.objc.borrow::<UITextFieldHostObject>(this).return_key_type
 }
 - (())setReturnKeyType:(UIReturnKeyType)type_ {
-    log!("TODO: setReturnKeyType:{}", type_);
+    env.objc.borrow_mut::<UITextFieldHostObject>(this).return_key_type = type_;
+}
+- (UIKeyboardAppearance)keyboardAppearance {
+    env.objc.borrow::<UITextFieldHostObject>(this).keyboard_appearance
 }
 - (())setKeyboardAppearance:(UIKeyboardAppearance)appearance {
-    log!("TODO: setKeyboardAppearance:{}", appearance);
+    env.objc.borrow_mut::<UITextFieldHostObject>(this).keyboard_appearance = appearance;
+}
+- (UIKeyboardType)keyboardType {
+    env.objc.borrow::<UITextFieldHostObject>(this).keyboard_type
 }
 - (())setKeyboardType:(UIKeyboardType)type_ {
-    log!("TODO: setKeyboardType:{}", type_);
+    env.objc.borrow_mut::<UITextFieldHostObject>(this).keyboard_type = type_;
 }
-- (())setBorderStyle:(NSInteger)style {
-    log!("TODO: setBorderStyle:{}", style);
+- (bool)enablesReturnKeyAutomatically {
+    env.objc.borrow::<UITextFieldHostObject>(this).enables_return_key_automatically
 }
 - (())setEnablesReturnKeyAutomatically:(bool)enables {
-    log!("TODO: setEnablesReturnKeyAutomatically:{}", enables);
+    env.objc.borrow_mut::<UITextFieldHostObject>(this).enables_return_key_automatically = enables;
+}
+- (bool)isSecureTextEntry {
+    env.objc.borrow::<UITextFieldHostObject>(this).secure_text_entry
+}
+- (bool)secureTextEntry {
+    env.objc.borrow::<UITextFieldHostObject>(this).secure_text_entry
+}
+- (())setSecureTextEntry:(bool)secure {
+    env.objc.borrow_mut::<UITextFieldHostObject>(this).secure_text_entry = secure;
+    update(env, this);
 }
 
 - (())touchesBegan:(id)_touches // NSSet* of UITouch*
@@ -185,10 +506,28 @@ pub const CLASSES: ClassExports = objc_classes! {
     let _: bool = msg![env; this becomeFirstResponder];
 }
 
+- (())_touchHLE_clearButtonTapped:(id)_sender {
+    let delegate = env.objc.borrow::<UITextFieldHostObject>(this).delegate;
+    if delegate_responds(env, delegate, "textFieldShouldClear:")
+        && !msg![env; delegate textFieldShouldClear:this] {
+        return;
+    }
+    let empty = get_static_str(env, "");
+    () = msg![env; this setText:empty];
+    text_did_change(env, this);
+}
+
 - (bool)isEditing {
     env.objc.borrow::<UITextFieldHostObject>(this).editing
 }
 
+- (bool)canBecomeFirstResponder {
+    true
+}
+- (bool)isFirstResponder {
+    env.framework_state.uikit.ui_responder.first_responder == this
+}
+
 - (bool)becomeFirstResponder {
     log_dbg!("becomeFirstResponder");
 
@@ -197,44 +536,47 @@ pub const CLASSES: ClassExports = objc_classes! {
     }
 
     let delegate: id = env.objc.borrow::<UITextFieldHostObject>(this).delegate;
-    let sel: SEL = env.objc.register_host_selector("textFieldShouldBeginEditing:".to_string(), &mut env.mem);
-    let responds: bool = msg![env; delegate respondsToSelector:sel];
-    if delegate != nil && responds && !msg![env; delegate textFieldShouldBeginEditing:this] {
+    if delegate_responds(env, delegate, "textFieldShouldBeginEditing:")
+        && !msg![env; delegate textFieldShouldBeginEditing:this] {
         return false;
     }
 
+    // Only one text field can be edited at once.
+    let previous = env.framework_state.uikit.ui_responder.first_responder;
+    if previous != nil && previous != this && !msg![env; previous resignFirstResponder] {
+        return false;
+    }
+
+    let clears = env.objc.borrow::<UITextFieldHostObject>(this).clears_on_begin_editing;
+    if clears
+        && (!delegate_responds(env, delegate, "textFieldShouldClear:")
+            || msg![env; delegate textFieldShouldClear:this]) {
+        let empty = get_static_str(env, "");
+        () = msg![env; this setText:empty];
+    }
+
     // If text is nil, it becomes an empty string
     // on becoming the first responder.
     // This behaviour was validated on the Aspen Simulator
-    let text_label = env
-        .objc
-        .borrow_mut::<UITextFieldHostObject>(this)
-        .text_label;
-    let curr_text: id = msg![env; text_label text];
+    let curr_text: id = msg![env; this text];
     if curr_text == nil {
-        let empty = ns_string::get_static_str(env, "");
-        () = msg![env; text_label setText:empty];
+        let empty = get_static_str(env, "");
+        () = msg![env; this setText:empty];
     }
 
-    let center: id = msg_class![env; NSNotificationCenter defaultCenter];
-    let name = ns_string::get_static_str(env, UIKeyboardWillShowNotification);
-    // TODO: userInfo
-    let _: () = msg![env; center postNotificationName:name object:this userInfo:nil];
-
     env.framework_state.uikit.ui_responder.first_responder = this;
-    unsafe { SDL_StartTextInput(); }
-
-    let name = ns_string::get_static_str(env, UIKeyboardDidShowNotification);
-    // TODO: userInfo
-    let _: () = msg![env; center postNotificationName:name object:this userInfo:nil];
+    ui_keyboard::show(env, this);
 
-    // TODO: is it the right spot?
     env.objc.borrow_mut::<UITextFieldHostObject>(this).editing = true;
+    update(env, this);
 
-    let sel: SEL = env.objc.register_host_selector("textFieldDidBeginEditing:".to_string(), &mut env.mem);
-    if msg![env; delegate respondsToSelector:sel] {
+    if delegate_responds(env, delegate, "textFieldDidBeginEditing:") {
         () = msg![env; delegate textFieldDidBeginEditing:this];
     }
+    send_actions(env, this, nil, UIControlEventEditingDidBegin);
+    let center: id = msg_class![env; NSNotificationCenter defaultCenter];
+    let name = get_static_str(env, UITextFieldTextDidBeginEditingNotification);
+    () = msg![env; center postNotificationName:name object:this];
 
     true
 }
@@ -247,31 +589,24 @@ pub const CLASSES: ClassExports = objc_classes! {
     }
 
     let delegate: id = env.objc.borrow::<UITextFieldHostObject>(this).delegate;
-    let sel: SEL = env.objc.register_host_selector("textFieldShouldEndEditing:".to_string(), &mut env.mem);
-    let responds: bool = msg![env; delegate respondsToSelector:sel];
-    if delegate != nil && responds && !msg![env; delegate textFieldShouldEndEditing:this] {
+    if delegate_responds(env, delegate, "textFieldShouldEndEditing:")
+        && !msg![env; delegate textFieldShouldEndEditing:this] {
         return false;
     }
 
-    let center: id = msg_class![env; NSNotificationCenter defaultCenter];
-    let name = ns_string::get_static_str(env, UIKeyboardWillHideNotification);
-    // TODO: userInfo
-    let _: () = msg![env; center postNotificationName:name object:this userInfo:nil];
-
     env.framework_state.uikit.ui_responder.first_responder = nil;
-    unsafe { SDL_StopTextInput(); }
+    ui_keyboard::hide(env);
 
-    let name = ns_string::get_static_str(env, UIKeyboardDidHideNotification);
-    // TODO: userInfo
-    let _: () = msg![env; center postNotificationName:name object:this userInfo:nil];
-
-    // TODO: is it the right spot?
     env.objc.borrow_mut::<UITextFieldHostObject>(this).editing = false;
+    update(env, this);
 
-    let sel: SEL = env.objc.register_host_selector("textFieldDidEndEditing:".to_string(), &mut env.mem);
-    if msg![env; delegate respondsToSelector:sel] {
+    if delegate_responds(env, delegate, "textFieldDidEndEditing:") {
         () = msg![env; delegate textFieldDidEndEditing:this];
     }
+    send_actions(env, this, nil, UIControlEventEditingDidEnd);
+    let center: id = msg_class![env; NSNotificationCenter defaultCenter];
+    let name = get_static_str(env, UITextFieldTextDidEndEditingNotification);
+    () = msg![env; center postNotificationName:name object:this];
 
     true
 }
@@ -280,66 +615,204 @@ pub const CLASSES: ClassExports = objc_classes! {
 
 };
 
-pub fn handle_text(env: &mut Environment, text_field: id, text: String) {
-    log_dbg!("Calling handle_text for {:?} with '{}'", text_field, text);
-    let txt = ns_string::from_rust_string(env, text);
-    let txt_len: NSUInteger = msg![env; txt length];
-    assert_eq!(txt_len, 1);
+/// Shared part of `initWithFrame:` and `initWithCoder:`.
+fn init_common(env: &mut Environment, this: id) {
+    // Built-in views don't have user-controlled opaqueness, and text fields
+    // are transparent unless they have a border style that draws a background.
+    () = msg![env; this setOpaque:false];
+    let clear_color: id = msg_class![env; UIColor clearColor];
+    () = msg![env; this setBackgroundColor:clear_color];
+
+    let text_label: id = msg_class![env; UILabel new];
+    () = msg![env; text_label setBackgroundColor:clear_color];
+    () = msg![env; text_label setTextAlignment:UITextAlignmentLeft];
+
+    let placeholder_label: id = msg_class![env; UILabel new];
+    () = msg![env; placeholder_label setBackgroundColor:clear_color];
+    () = msg![env; placeholder_label setTextAlignment:UITextAlignmentLeft];
+    let placeholder_color: id = msg_class![env; UIColor colorWithWhite:(0.7 as CGFloat)
+                                                                 alpha:(1.0 as CGFloat)];
+    () = msg![env; placeholder_label setTextColor:placeholder_color];
+
+    let clear_button: id = msg_class![env; UIButton buttonWithType:0]; // custom
+    retain(env, clear_button);
+    let title = get_static_str(env, "\u{00D7}");
+    () = msg![env; clear_button setTitle:title forState:UIControlStateNormal];
+    let title_color: id = msg_class![env; UIColor whiteColor];
+    () = msg![env; clear_button setTitleColor:title_color forState:UIControlStateNormal];
+    let button_color: id = msg_class![env; UIColor colorWithWhite:(0.7 as CGFloat)
+                                                            alpha:(1.0 as CGFloat)];
+    () = msg![env; clear_button setBackgroundColor:button_color];
+    let selector = env
+        .objc
+        .lookup_selector("_touchHLE_clearButtonTapped:")
+        .unwrap();
+    () = msg![env; clear_button addTarget:this
+                                   action:selector
+                         forControlEvents:UIControlEventTouchUpInside];
+
+    let host_obj = env.objc.borrow_mut::<UITextFieldHostObject>(this);
+    host_obj.text_label = text_label;
+    host_obj.placeholder_label = placeholder_label;
+    host_obj.clear_button = clear_button;
+
+    () = msg![env; this addSubview:placeholder_label];
+    () = msg![env; this addSubview:text_label];
+    () = msg![env; this addSubview:clear_button];
+    update(env, this);
+}
+
+/// Brings the subviews in line with the text field's properties: masks the
+/// text for secure entry and shows or hides the placeholder and clear button.
+fn update(env: &mut Environment, this: id) {
+    let &UITextFieldHostObject {
+        editing,
+        text,
+        text_label,
+        placeholder_label,
+        clear_button,
+        clear_button_mode,
+        secure_text_entry,
+        ..
+    } = env.objc.borrow(this);
+
+    let len: NSUInteger = if text == nil {
+        0
+    } else {
+        msg![env; text length]
+    };
+
+    let displayed_text = if secure_text_entry && len > 0 {
+        let bullets = from_rust_string(env, SECURE_TEXT_BULLET.repeat(len as usize));
+        autorelease(env, bullets)
+    } else {
+        text
+    };
+    () = msg![env; text_label setText:displayed_text];
+    () = msg![env; placeholder_label setHidden:(len > 0)];
+
+    let show_clear_button = len > 0
+        && match clear_button_mode {
+            UITextFieldViewModeNever => false,
+            UITextFieldViewModeWhileEditing => editing,
+            UITextFieldViewModeUnlessEditing => !editing,
+            _ => true, // UITextFieldViewModeAlways
+        };
+    () = msg![env; clear_button setHidden:(!show_clear_button)];
+
+    () = msg![env; this setNeedsLayout];
+}
+
+fn inset_rect(rect: CGRect, dx: CGFloat, dy: CGFloat) -> CGRect {
+    CGRect {
+        origin: CGPoint {
+            x: rect.origin.x + dx,
+            y: rect.origin.y + dy,
+        },
+        size: CGSize {
+            width: (rect.size.width - dx * 2.0).max(0.0),
+            height: (rect.size.height - dy * 2.0).max(0.0),
+        },
+    }
+}
 
-    let text_label = env
+/// Draws the edges of a rectangle with the current fill color.
+fn draw_outline(env: &mut Environment, context: CGContextRef, rect: CGRect, width: CGFloat) {
+    let CGRect { origin, size } = rect;
+    let edges = [
+        (origin.x, origin.y, size.width, width),
+        (origin.x, origin.y + size.height - width, size.width, width),
+        (origin.x, origin.y, width, size.height),
+        (origin.x + size.width - width, origin.y, width, size.height),
+    ];
+    for (x, y, width, height) in edges {
+        CGContextFillRect(
+            env,
+            context,
+            CGRect {
+                origin: CGPoint { x, y },
+                size: CGSize { width, height },
+            },
+        );
+    }
+}
+
+/// Sends the control event and notification for a user-initiated change.
+fn text_did_change(env: &mut Environment, text_field: id) {
+    send_actions(env, text_field, nil, UIControlEventEditingChanged);
+    let center: id = msg_class![env; NSNotificationCenter defaultCenter];
+    let name = get_static_str(env, UITextFieldTextDidChangeNotification);
+    () = msg![env; center postNotificationName:name object:text_field];
+}
+
+/// Replaces a range of the text field's text on behalf of the user, if the
+/// delegate allows it. Delegates commonly use
+/// `textField:shouldChangeCharactersInRange:replacementString:` to enforce a
+/// maximum length, possibly by setting the text themselves and returning
+/// `NO`, so the text must be re-read afterwards.
+fn replace_text(env: &mut Environment, text_field: id, range: NSRange, replacement: id) {
+    let delegate = env
         .objc
-        .borrow_mut::<UITextFieldHostObject>(text_field)
-        .text_label;
-    let mut curr_text = msg![env; text_label text];
+        .borrow::<UITextFieldHostObject>(text_field)
+        .delegate;
+    if delegate_responds(
+        env,
+        delegate,
+        "textField:shouldChangeCharactersInRange:replacementString:",
+    ) && !msg![env; delegate textField:text_field
+                shouldChangeCharactersInRange:range
+                            replacementString:replacement]
+    {
+        return;
+    }
+
+    let mut curr_text: id = msg![env; text_field text];
     if curr_text == nil {
-        curr_text = ns_string::get_static_str(env, "");
+        curr_text = get_static_str(env, "");
     }
+    // The delegate may have changed the text, so the range might be stale.
+    let len: NSUInteger = msg![env; curr_text length];
+    let location = range.location.min(len);
+    let range = NSRange {
+        location,
+        length: range.length.min(len - location),
+    };
+    let new_text: id = msg![env; curr_text stringByReplacingCharactersInRange:range
+                                                                   withString:replacement];
     log_dbg!(
-        "handle_text, curr_text: {}",
-        ns_string::to_rust_string(env, curr_text)
+        "Text field {:?} text changed to {:?}",
+        text_field,
+        to_rust_string(env, new_text)
     );
+    () = msg![env; text_field setText:new_text];
+    text_did_change(env, text_field);
+}
 
-    let len = msg![env; curr_text length];
+pub fn handle_text(env: &mut Environment, text_field: id, text: String) {
+    log_dbg!("Calling handle_text for {:?} with '{}'", text_field, text);
+    let curr_text: id = msg![env; text_field text];
+    let len: NSUInteger = if curr_text == nil {
+        0
+    } else {
+        msg![env; curr_text length]
+    };
     let range = NSRange {
         location: len,
         length: 0,
     };
-
-    let delegate: id = env
-        .objc
-        .borrow::<UITextFieldHostObject>(text_field)
-        .delegate;
-    let sel: SEL = env.objc.register_host_selector(
-        "textField:shouldChangeCharactersInRange:replacementString:".to_string(),
-        &mut env.mem,
-    );
-    let responds: bool = msg![env; delegate respondsToSelector:sel];
-    let should = delegate == nil
-        || !responds
-        || msg![env; delegate textField:text_field shouldChangeCharactersInRange:range replacementString:txt];
-    if should {
-        let new_text: id = msg![env; curr_text stringByAppendingString:txt];
-        log_dbg!(
-            "handle_text, new_text: {}",
-            ns_string::to_rust_string(env, new_text)
-        );
-        // TODO: refactor this to proper update() method
-        () = msg![env; text_label setText:new_text];
-        () = msg![env; text_field setNeedsDisplay];
-        release(env, new_text);
-    }
+    let txt = from_rust_string(env, text);
+    replace_text(env, text_field, range, txt);
     release(env, txt);
 }
 
 pub fn handle_backspace(env: &mut Environment, text_field: id) {
     log_dbg!("Calling handle_backspace for {:?}", text_field);
-    let text_label = env
-        .objc
-        .borrow_mut::<UITextFieldHostObject>(text_field)
-        .text_label;
-    let curr_text: id = msg![env; text_label text];
-
-    let len: NSUInteger = msg![env; curr_text length];
+    let curr_text: id = msg![env; text_field text];
+    let len: NSUInteger = if curr_text == nil {
+        0
+    } else {
+        msg![env; curr_text length]
+    };
     if len == 0 {
         return;
     }
@@ -347,31 +820,8 @@ pub fn handle_backspace(env: &mut Environment, text_field: id) {
         location: len - 1,
         length: 1,
     };
-    let empty = ns_string::get_static_str(env, "");
-
-    let delegate: id = env
-        .objc
-        .borrow::<UITextFieldHostObject>(text_field)
-        .delegate;
-    let sel: SEL = env.objc.register_host_selector(
-        "textField:shouldChangeCharactersInRange:replacementString:".to_string(),
-        &mut env.mem,
-    );
-    let responds: bool = msg![env; delegate respondsToSelector:sel];
-    let should = delegate == nil
-        || !responds
-        || msg![env; delegate textField:text_field shouldChangeCharactersInRange:range replacementString:empty];
-    if should {
-        let new_text: id = msg![env; curr_text substringToIndex:(len-1)];
-        log_dbg!(
-            "handle_backspace, new_text: {}",
-            ns_string::to_rust_string(env, new_text)
-        );
-        // TODO: refactor this to proper update() method
-        () = msg![env; text_label setText:new_text];
-        () = msg![env; text_field setNeedsDisplay];
-        release(env, new_text);
-    }
+    let empty = get_static_str(env, "");
+    replace_text(env, text_field, range, empty);
 }
 
 pub fn handle_return(env: &mut Environment, text_field: id) {
@@ -380,11 +830,22 @@ pub fn handle_return(env: &mut Environment, text_field: id) {
         .objc
         .borrow::<UITextFieldHostObject>(text_field)
         .delegate;
-    let sel: SEL = env
+    if delegate_responds(env, delegate, "textFieldShouldReturn:")
+        && !msg![env; delegate textFieldShouldReturn:text_field]
+    {
+        return;
+    }
+
+    // Like on iOS, having an action for "Did End On Exit" implies the keyboard
+    // should be dismissed when Return is pressed.
+    let dismisses = env
         .objc
-        .register_host_selector("textFieldShouldReturn:".to_string(), &mut env.mem);
-    if msg![env; delegate respondsToSelector:sel] {
-        log_dbg!("handle_return");
-        () = msg![env; delegate textFieldShouldReturn:text_field];
+        .borrow::<UIControlHostObject>(text_field)
+        .action_targets
+        .iter()
+        .any(|&(_, _, events)| events & UIControlEventEditingDidEndOnExit != 0);
+    send_actions(env, text_field, nil, UIControlEventEditingDidEndOnExit);
+    if dismisses {
+        let _: bool = msg![env; text_field resignFirstResponder];
     }
 }
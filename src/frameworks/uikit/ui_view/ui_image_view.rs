@@ -7,9 +7,10 @@
 
 use crate::frameworks::core_graphics::cg_image::CGImageRef;
 use crate::frameworks::core_graphics::{CGPoint, CGRect, CGSize};
+use crate::frameworks::foundation::ns_string::get_static_str;
 use crate::frameworks::foundation::NSTimeInterval;
 use crate::objc::{
-    id, impl_HostObject_with_superclass, msg, msg_super, objc_classes, release, retain,
+    id, impl_HostObject_with_superclass, msg, msg_super, nil, objc_classes, release, retain,
     ClassExports, NSZonePtr,
 };
 
@@ -49,7 +50,14 @@ pub const CLASSES: ClassExports = objc_classes! {
     msg_super![env; this dealloc]
 }
 
-// TODO: initWithCoder:
+// NSCoding implementation
+- (id)initWithCoder:(id)coder {
+    let this: id = msg_super![env; this initWithCoder:coder];
+    let key = get_static_str(env, "UIImage");
+    let image: id = msg![env; coder decodeObjectForKey:key];
+    () = msg![env; this setImage:image];
+    this
+}
 
 - (id)initWithImage:(id)image { // UIImage*
     let size: CGSize = msg![env; image size];
@@ -79,6 +87,15 @@ pub const CLASSES: ClassExports = objc_classes! {
     () = msg![env; layer setNeedsDisplay];
 }
 
+- (CGSize)sizeThatFits:(CGSize)_size {
+    let image = env.objc.borrow::<UIImageViewHostObject>(this).image;
+    if image == nil {
+        CGSize { width: 0.0, height: 0.0 }
+    } else {
+        msg![env; image size]
+    }
+}
+
 - (())setAnimationImages:(id)images { // NSArray<UIImage *>*
     log!("TODO: [(UIImageView*) {:?} setAnimationImages:{:?}]", this, images);
     // TODO: Use all images in the array instead of just the first one
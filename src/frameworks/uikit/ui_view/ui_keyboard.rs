@@ -0,0 +1,719 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! touchHLE's on-screen keyboard.
+//!
+//! iPhone OS shows its keyboard whenever a text input view becomes the first
+//! responder, and apps often rely on its notifications to move their views out
+//! of the way. This is an imitation of it, drawn by touchHLE, so that text can
+//! be entered without a host keyboard (e.g. on Android, or with a game
+//! controller). Host keyboard input still works while it is visible.
+//!
+//! The keyboard is a private view that is added to the text input view's
+//! window when shown.
+
+use super::ui_alert_view::window_for_presenting;
+use crate::dyld::{ConstantExports, HostConstant};
+use crate::frameworks::core_graphics::cg_context::{CGContextFillRect, CGContextSetRGBFillColor};
+use crate::frameworks::core_graphics::{CGFloat, CGPoint, CGRect, CGSize};
+use crate::frameworks::foundation::ns_dictionary::dict_from_keys_and_objects;
+use crate::frameworks::foundation::ns_string::{from_rust_string, get_static_str};
+use crate::frameworks::foundation::{NSInteger, NSUInteger};
+use crate::frameworks::uikit::ui_font::{UILineBreakModeClip, UITextAlignmentCenter};
+use crate::frameworks::uikit::ui_graphics::UIGraphicsGetCurrentContext;
+use crate::objc::{
+    id, impl_HostObject_with_superclass, msg, msg_class, nil, objc_classes, release, ClassExports,
+    NSZonePtr,
+};
+use crate::window::{KeyboardNavigationEvent, TextInputEvent};
+use crate::Environment;
+use sdl2_sys::{SDL_StartTextInput, SDL_StopTextInput};
+
+// TODO: more keyboard notifications
+pub const UIKeyboardWillShowNotification: &str = "UIKeyboardWillShowNotification";
+pub const UIKeyboardDidShowNotification: &str = "UIKeyboardDidShowNotification";
+pub const UIKeyboardWillHideNotification: &str = "UIKeyboardWillHideNotification";
+pub const UIKeyboardDidHideNotification: &str = "UIKeyboardDidHideNotification";
+
+pub const UIKeyboardFrameBeginUserInfoKey: &str = "UIKeyboardFrameBeginUserInfoKey";
+pub const UIKeyboardFrameEndUserInfoKey: &str = "UIKeyboardFrameEndUserInfoKey";
+pub const UIKeyboardAnimationDurationUserInfoKey: &str = "UIKeyboardAnimationDurationUserInfoKey";
+pub const UIKeyboardAnimationCurveUserInfoKey: &str = "UIKeyboardAnimationCurveUserInfoKey";
+// These three were deprecated in iPhone OS 3.2, but older apps use them.
+pub const UIKeyboardCenterBeginUserInfoKey: &str = "UIKeyboardCenterBeginUserInfoKey";
+pub const UIKeyboardCenterEndUserInfoKey: &str = "UIKeyboardCenterEndUserInfoKey";
+pub const UIKeyboardBoundsUserInfoKey: &str = "UIKeyboardBoundsUserInfoKey";
+
+pub const CONSTANTS: ConstantExports = &[
+    (
+        "_UIKeyboardWillShowNotification",
+        HostConstant::NSString(UIKeyboardWillShowNotification),
+    ),
+    (
+        "_UIKeyboardDidShowNotification",
+        HostConstant::NSString(UIKeyboardDidShowNotification),
+    ),
+    (
+        "_UIKeyboardWillHideNotification",
+        HostConstant::NSString(UIKeyboardWillHideNotification),
+    ),
+    (
+        "_UIKeyboardDidHideNotification",
+        HostConstant::NSString(UIKeyboardDidHideNotification),
+    ),
+    (
+        "_UIKeyboardFrameBeginUserInfoKey",
+        HostConstant::NSString(UIKeyboardFrameBeginUserInfoKey),
+    ),
+    (
+        "_UIKeyboardFrameEndUserInfoKey",
+        HostConstant::NSString(UIKeyboardFrameEndUserInfoKey),
+    ),
+    (
+        "_UIKeyboardAnimationDurationUserInfoKey",
+        HostConstant::NSString(UIKeyboardAnimationDurationUserInfoKey),
+    ),
+    (
+        "_UIKeyboardAnimationCurveUserInfoKey",
+        HostConstant::NSString(UIKeyboardAnimationCurveUserInfoKey),
+    ),
+    (
+        "_UIKeyboardCenterBeginUserInfoKey",
+        HostConstant::NSString(UIKeyboardCenterBeginUserInfoKey),
+    ),
+    (
+        "_UIKeyboardCenterEndUserInfoKey",
+        HostConstant::NSString(UIKeyboardCenterEndUserInfoKey),
+    ),
+    (
+        "_UIKeyboardBoundsUserInfoKey",
+        HostConstant::NSString(UIKeyboardBoundsUserInfoKey),
+    ),
+];
+
+pub type UIKeyboardType = NSInteger;
+pub const UIKeyboardTypeDefault: UIKeyboardType = 0;
+#[allow(dead_code)]
+pub const UIKeyboardTypeASCIICapable: UIKeyboardType = 1;
+pub const UIKeyboardTypeNumbersAndPunctuation: UIKeyboardType = 2;
+pub const UIKeyboardTypeURL: UIKeyboardType = 3;
+pub const UIKeyboardTypeNumberPad: UIKeyboardType = 4;
+pub const UIKeyboardTypePhonePad: UIKeyboardType = 5;
+#[allow(dead_code)]
+pub const UIKeyboardTypeNamePhonePad: UIKeyboardType = 6;
+pub const UIKeyboardTypeEmailAddress: UIKeyboardType = 7;
+pub const UIKeyboardTypeDecimalPad: UIKeyboardType = 8;
+
+type UITextAutocapitalizationType = NSInteger;
+const UITextAutocapitalizationTypeNone: UITextAutocapitalizationType = 0;
+
+type UIReturnKeyType = NSInteger;
+
+/// Height of the keyboard in portrait orientation.
+const KEYBOARD_HEIGHT: CGFloat = 216.0;
+/// Gap between keys.
+const KEY_SPACING: CGFloat = 4.0;
+/// Duration reported in the notifications. The keyboard isn't actually
+/// animated (TODO).
+const ANIMATION_DURATION: f64 = 0.3;
+
+#[derive(Default)]
+pub struct State {
+    /// The keyboard view, created the first time it is needed. This is a
+    /// strong reference.
+    keyboard: Option<id>,
+}
+
+/// A set of keys that can be displayed at once.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Plane {
+    Letters,
+    Numbers,
+    Symbols,
+    NumberPad,
+    DecimalPad,
+    PhonePad,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Key {
+    /// Types some text. Letters are uppercased when shift is on.
+    Text(&'static str),
+    Shift,
+    Backspace,
+    /// Switches to another plane, with the given label.
+    Plane(Plane, &'static str),
+    Space,
+    Return,
+    /// Empty space in a number pad.
+    Blank,
+}
+
+struct UIKeyboardHostObject {
+    superclass: super::UIViewHostObject,
+    keyboard_type: UIKeyboardType,
+    return_key_type: UIReturnKeyType,
+    plane: Plane,
+    shifted: bool,
+    /// Index of the key currently being touched.
+    pressed: Option<usize>,
+    /// Index of the key highlighted by controller navigation.
+    selected: Option<usize>,
+}
+impl_HostObject_with_superclass!(UIKeyboardHostObject);
+impl Default for UIKeyboardHostObject {
+    fn default() -> Self {
+        UIKeyboardHostObject {
+            superclass: Default::default(),
+            keyboard_type: UIKeyboardTypeDefault,
+            return_key_type: 0, // UIReturnKeyDefault
+            plane: Plane::Letters,
+            shifted: false,
+            pressed: None,
+            selected: None,
+        }
+    }
+}
+
+/// The rows of keys for a plane, with each key's width in units of a normal
+/// key's width.
+fn rows_for_plane(plane: Plane, keyboard_type: UIKeyboardType) -> Vec<Vec<(Key, CGFloat)>> {
+    fn text_row(keys: &[&'static str], width: CGFloat) -> Vec<(Key, CGFloat)> {
+        keys.iter().map(|&k| (Key::Text(k), width)).collect()
+    }
+
+    let bottom_row = |switch_key: Key| -> Vec<(Key, CGFloat)> {
+        match keyboard_type {
+            UIKeyboardTypeEmailAddress => vec![
+                (switch_key, 2.0),
+                (Key::Space, 4.0),
+                (Key::Text("@"), 1.0),
+                (Key::Text("."), 1.0),
+                (Key::Return, 2.0),
+            ],
+            UIKeyboardTypeURL => vec![
+                (switch_key, 2.5),
+                (Key::Text("."), 1.5),
+                (Key::Text("/"), 1.5),
+                (Key::Text(".com"), 2.0),
+                (Key::Return, 2.5),
+            ],
+            _ => vec![(switch_key, 2.5), (Key::Space, 5.0), (Key::Return, 2.5)],
+        }
+    };
+
+    match plane {
+        Plane::Letters => {
+            let mut third_row = vec![(Key::Shift, 1.5)];
+            third_row.extend(text_row(&["z", "x", "c", "v", "b", "n", "m"], 1.0));
+            third_row.push((Key::Backspace, 1.5));
+            vec![
+                text_row(&["q", "w", "e", "r", "t", "y", "u", "i", "o", "p"], 1.0),
+                text_row(&["a", "s", "d", "f", "g", "h", "j", "k", "l"], 1.0),
+                third_row,
+                bottom_row(Key::Plane(Plane::Numbers, "123")),
+            ]
+        }
+        Plane::Numbers | Plane::Symbols => {
+            let (first, second, switch_key) = if plane == Plane::Numbers {
+                (
+                    ["1", "2", "3", "4", "5", "6", "7", "8", "9", "0"],
+                    ["-", "/", ":", ";", "(", ")", "$", "&", "@", "\""],
+                    Key::Plane(Plane::Symbols, "#+="),
+                )
+            } else {
+                (
+                    ["[", "]", "{", "}", "#", "%", "^", "*", "+", "="],
+                    [
+                        "_", "\\", "|", "~", "<", ">", "\u{20AC}", "\u{00A3}", "\u{00A5}",
+                        "\u{2022}",
+                    ],
+                    Key::Plane(Plane::Numbers, "123"),
+                )
+            };
+            let mut third_row = vec![(switch_key, 1.5)];
+            third_row.extend(text_row(&[".", ",", "?", "!", "'"], 1.4));
+            third_row.push((Key::Backspace, 1.5));
+            vec![
+                text_row(&first, 1.0),
+                text_row(&second, 1.0),
+                third_row,
+                bottom_row(Key::Plane(Plane::Letters, "ABC")),
+            ]
+        }
+        Plane::NumberPad | Plane::DecimalPad | Plane::PhonePad => {
+            let extra_key = match plane {
+                Plane::DecimalPad => Key::Text("."),
+                Plane::PhonePad => Key::Text("+"),
+                _ => Key::Blank,
+            };
+            vec![
+                text_row(&["1", "2", "3"], 1.0),
+                text_row(&["4", "5", "6"], 1.0),
+                text_row(&["7", "8", "9"], 1.0),
+                vec![
+                    (extra_key, 1.0),
+                    (Key::Text("0"), 1.0),
+                    (Key::Backspace, 1.0),
+                ],
+            ]
+        }
+    }
+}
+
+fn initial_plane(keyboard_type: UIKeyboardType) -> Plane {
+    match keyboard_type {
+        UIKeyboardTypeNumbersAndPunctuation => Plane::Numbers,
+        UIKeyboardTypeNumberPad => Plane::NumberPad,
+        UIKeyboardTypePhonePad => Plane::PhonePad,
+        UIKeyboardTypeDecimalPad => Plane::DecimalPad,
+        _ => Plane::Letters,
+    }
+}
+
+/// Positions the keys of a plane within the keyboard's bounds. Each row is
+/// centered horizontally, and the rows share the height equally.
+fn layout_keys(rows: &[Vec<(Key, CGFloat)>], bounds: CGRect) -> Vec<(Key, CGRect)> {
+    let row_units = |row: &Vec<(Key, CGFloat)>| row.iter().map(|&(_, w)| w).sum::<CGFloat>();
+    let max_units = rows.iter().map(row_units).fold(0.0, CGFloat::max);
+    let unit_width = (bounds.size.width - KEY_SPACING) / max_units;
+    let row_height = (bounds.size.height - KEY_SPACING) / rows.len() as CGFloat;
+
+    let mut keys = Vec::new();
+    for (row_idx, row) in rows.iter().enumerate() {
+        let mut x = bounds.origin.x + KEY_SPACING + (max_units - row_units(row)) * unit_width / 2.0;
+        let y = bounds.origin.y + KEY_SPACING + row_idx as CGFloat * row_height;
+        for &(key, units) in row {
+            let width = units * unit_width;
+            keys.push((
+                key,
+                CGRect {
+                    origin: CGPoint { x, y },
+                    size: CGSize {
+                        width: width - KEY_SPACING,
+                        height: row_height - KEY_SPACING,
+                    },
+                },
+            ));
+            x += width;
+        }
+    }
+    keys
+}
+
+fn rect_contains(rect: CGRect, point: CGPoint) -> bool {
+    point.x >= rect.origin.x
+        && point.y >= rect.origin.y
+        && point.x < rect.origin.x + rect.size.width
+        && point.y < rect.origin.y + rect.size.height
+}
+
+fn rect_center(rect: CGRect) -> CGPoint {
+    CGPoint {
+        x: rect.origin.x + rect.size.width / 2.0,
+        y: rect.origin.y + rect.size.height / 2.0,
+    }
+}
+
+pub const CLASSES: ClassExports = objc_classes! {
+
+(env, this, _cmd);
+
+@implementation _touchHLE_UIKeyboard: UIView
+
++ (id)allocWithZone:(NSZonePtr)_zone {
+    let host_object = Box::<UIKeyboardHostObject>::default();
+    env.objc.alloc_object(this, host_object, &mut env.mem)
+}
+
+- (())drawRect:(CGRect)_rect {
+    let context = UIGraphicsGetCurrentContext(env);
+    let bounds: CGRect = msg![env; this bounds];
+    CGContextSetRGBFillColor(env, context, 0.55, 0.57, 0.62, 1.0);
+    CGContextFillRect(env, context, bounds);
+
+    let &UIKeyboardHostObject {
+        shifted,
+        pressed,
+        selected,
+        return_key_type,
+        ..
+    } = env.objc.borrow(this);
+    let letter_font: id = msg_class![env; UIFont systemFontOfSize:(20.0 as CGFloat)];
+    let special_font: id = msg_class![env; UIFont boldSystemFontOfSize:(14.0 as CGFloat)];
+
+    for (idx, (key, rect)) in keys(env, this).into_iter().enumerate() {
+        if key == Key::Blank {
+            continue;
+        }
+
+        if selected == Some(idx) {
+            // Controller focus ring
+            CGContextSetRGBFillColor(env, context, 0.2, 0.45, 0.95, 1.0);
+            CGContextFillRect(env, context, CGRect {
+                origin: CGPoint {
+                    x: rect.origin.x - 2.0,
+                    y: rect.origin.y - 2.0,
+                },
+                size: CGSize {
+                    width: rect.size.width + 4.0,
+                    height: rect.size.height + 4.0,
+                },
+            });
+        }
+
+        let is_text_key = matches!(key, Key::Text(_) | Key::Space);
+        let (r, g, b) = if pressed == Some(idx) || (key == Key::Shift && shifted) {
+            (0.4, 0.55, 0.85)
+        } else if is_text_key {
+            (0.98, 0.98, 0.98)
+        } else {
+            (0.68, 0.7, 0.75)
+        };
+        CGContextSetRGBFillColor(env, context, r, g, b, 1.0);
+        CGContextFillRect(env, context, rect);
+
+        let (label, font) = match key {
+            Key::Text(text) if shifted => (text.to_uppercase(), letter_font),
+            Key::Text(text) => (text.to_string(), letter_font),
+            Key::Shift => ("\u{2191}".to_string(), special_font),
+            Key::Backspace => ("\u{2190}".to_string(), special_font),
+            Key::Plane(_, label) => (label.to_string(), special_font),
+            Key::Space => ("space".to_string(), special_font),
+            Key::Return => (return_key_label(return_key_type).to_string(), special_font),
+            Key::Blank => unreachable!(),
+        };
+        let label = from_rust_string(env, label);
+        let line_height: CGFloat = msg![env; font lineHeight];
+        let label_rect = CGRect {
+            origin: CGPoint {
+                x: rect.origin.x,
+                y: rect.origin.y + ((rect.size.height - line_height) / 2.0).max(0.0),
+            },
+            size: rect.size,
+        };
+        CGContextSetRGBFillColor(env, context, 0.0, 0.0, 0.0, 1.0);
+        let _: CGSize = msg![env; label drawInRect:label_rect
+                                          withFont:font
+                                     lineBreakMode:UILineBreakModeClip
+                                         alignment:UITextAlignmentCenter];
+        release(env, label);
+    }
+}
+
+- (())touchesBegan:(id)touches // NSSet* of UITouch*
+         withEvent:(id)_event { // UIEvent*
+    let pressed = key_index_for_touches(env, this, touches);
+    env.objc.borrow_mut::<UIKeyboardHostObject>(this).pressed = pressed;
+    () = msg![env; this setNeedsDisplay];
+}
+
+- (())touchesMoved:(id)touches // NSSet* of UITouch*
+         withEvent:(id)_event { // UIEvent*
+    let pressed = key_index_for_touches(env, this, touches);
+    env.objc.borrow_mut::<UIKeyboardHostObject>(this).pressed = pressed;
+    () = msg![env; this setNeedsDisplay];
+}
+
+- (())touchesEnded:(id)touches // NSSet* of UITouch*
+         withEvent:(id)_event { // UIEvent*
+    // Like the real keyboard, keys act when the finger is lifted.
+    let pressed = key_index_for_touches(env, this, touches);
+    env.objc.borrow_mut::<UIKeyboardHostObject>(this).pressed = None;
+    () = msg![env; this setNeedsDisplay];
+    if let Some(idx) = pressed {
+        press_key(env, this, idx);
+    }
+}
+
+@end
+
+};
+
+fn keys(env: &mut Environment, keyboard: id) -> Vec<(Key, CGRect)> {
+    let &UIKeyboardHostObject {
+        plane,
+        keyboard_type,
+        ..
+    } = env.objc.borrow(keyboard);
+    let bounds: CGRect = msg![env; keyboard bounds];
+    layout_keys(&rows_for_plane(plane, keyboard_type), bounds)
+}
+
+fn key_index_for_touches(env: &mut Environment, keyboard: id, touches: id) -> Option<usize> {
+    let touch: id = msg![env; touches anyObject];
+    let point: CGPoint = msg![env; touch locationInView:keyboard];
+    keys(env, keyboard)
+        .into_iter()
+        .position(|(key, rect)| key != Key::Blank && rect_contains(rect, point))
+}
+
+fn return_key_label(return_key_type: UIReturnKeyType) -> &'static str {
+    match return_key_type {
+        1 => "Go",
+        2 => "Google",
+        3 => "Join",
+        4 => "Next",
+        5 => "Route",
+        6 => "Search",
+        7 => "Send",
+        8 => "Yahoo!",
+        9 => "Done",
+        10 => "Emergency Call",
+        _ => "return", // UIReturnKeyDefault
+    }
+}
+
+fn press_key(env: &mut Environment, keyboard: id, idx: usize) {
+    let (key, _) = keys(env, keyboard)[idx];
+    log_dbg!("On-screen keyboard key pressed: {:?}", key);
+
+    let host_obj = env.objc.borrow_mut::<UIKeyboardHostObject>(keyboard);
+    let text_event = match key {
+        Key::Text(text) => {
+            let text = if host_obj.shifted {
+                text.to_uppercase()
+            } else {
+                text.to_string()
+            };
+            // Shift only applies to one key press.
+            host_obj.shifted = false;
+            Some(TextInputEvent::Text(text))
+        }
+        Key::Space => Some(TextInputEvent::Text(" ".to_string())),
+        Key::Backspace => Some(TextInputEvent::Backspace),
+        Key::Return => Some(TextInputEvent::Return),
+        Key::Shift => {
+            host_obj.shifted = !host_obj.shifted;
+            None
+        }
+        Key::Plane(plane, _) => {
+            host_obj.plane = plane;
+            host_obj.shifted = false;
+            // The keys have moved around.
+            host_obj.selected = host_obj.selected.map(|_| 0);
+            None
+        }
+        Key::Blank => None,
+    };
+    () = msg![env; keyboard setNeedsDisplay];
+
+    if let Some(text_event) = text_event {
+        crate::frameworks::uikit::handle_text_input(env, text_event);
+    }
+}
+
+/// Frame of the keyboard in the window's co-ordinate space, when visible.
+fn frame_in_window(env: &mut Environment, window: id) -> CGRect {
+    // TODO: landscape keyboard
+    let window_bounds: CGRect = msg![env; window bounds];
+    CGRect {
+        origin: CGPoint {
+            x: 0.0,
+            y: window_bounds.size.height - KEYBOARD_HEIGHT,
+        },
+        size: CGSize {
+            width: window_bounds.size.width,
+            height: KEYBOARD_HEIGHT,
+        },
+    }
+}
+
+fn post_notification(env: &mut Environment, name: &'static str, begin: CGRect, end: CGRect) {
+    let mut keys_and_objects = Vec::new();
+    for (key, rect) in [
+        (UIKeyboardFrameBeginUserInfoKey, begin),
+        (UIKeyboardFrameEndUserInfoKey, end),
+        (
+            UIKeyboardBoundsUserInfoKey,
+            CGRect {
+                origin: CGPoint { x: 0.0, y: 0.0 },
+                size: end.size,
+            },
+        ),
+    ] {
+        let key = get_static_str(env, key);
+        let value: id = msg_class![env; NSValue valueWithCGRect:rect];
+        keys_and_objects.push((key, value));
+    }
+    for (key, rect) in [
+        (UIKeyboardCenterBeginUserInfoKey, begin),
+        (UIKeyboardCenterEndUserInfoKey, end),
+    ] {
+        let key = get_static_str(env, key);
+        let value: id = msg_class![env; NSValue valueWithCGPoint:(rect_center(rect))];
+        keys_and_objects.push((key, value));
+    }
+    let key = get_static_str(env, UIKeyboardAnimationDurationUserInfoKey);
+    let value: id = msg_class![env; NSNumber numberWithDouble:ANIMATION_DURATION];
+    keys_and_objects.push((key, value));
+    let key = get_static_str(env, UIKeyboardAnimationCurveUserInfoKey);
+    let value: id = msg_class![env; NSNumber numberWithInt:0]; // UIViewAnimationCurveEaseInOut
+    keys_and_objects.push((key, value));
+
+    let user_info = dict_from_keys_and_objects(env, &keys_and_objects);
+    let center: id = msg_class![env; NSNotificationCenter defaultCenter];
+    let name = get_static_str(env, name);
+    () = msg![env; center postNotificationName:name object:nil userInfo:user_info];
+    release(env, user_info);
+}
+
+/// Shows the keyboard for a text input view that has just become the first
+/// responder, and starts host text input.
+pub fn show(env: &mut Environment, responder: id) {
+    let Some(window) = window_for_presenting(env, responder) else {
+        log!(
+            "Warning: can't show the keyboard for {:?}, no window",
+            responder
+        );
+        unsafe { SDL_StartTextInput() };
+        return;
+    };
+
+    let keyboard = match env.framework_state.uikit.ui_view.ui_keyboard.keyboard {
+        Some(keyboard) => keyboard,
+        None => {
+            let keyboard: id = msg_class![env; _touchHLE_UIKeyboard alloc];
+            let keyboard: id = msg![env; keyboard initWithFrame:(CGRect::default())];
+            env.framework_state.uikit.ui_view.ui_keyboard.keyboard = Some(keyboard);
+            keyboard
+        }
+    };
+
+    let keyboard_type: UIKeyboardType = msg![env; responder keyboardType];
+    let return_key_type: UIReturnKeyType = msg![env; responder returnKeyType];
+    let autocapitalization: UITextAutocapitalizationType =
+        msg![env; responder autocapitalizationType];
+    let text: id = msg![env; responder text];
+    let text_length: NSUInteger = if text == nil {
+        0
+    } else {
+        msg![env; text length]
+    };
+
+    let host_obj = env.objc.borrow_mut::<UIKeyboardHostObject>(keyboard);
+    host_obj.keyboard_type = keyboard_type;
+    host_obj.return_key_type = return_key_type;
+    host_obj.plane = initial_plane(keyboard_type);
+    host_obj.shifted = text_length == 0 && autocapitalization != UITextAutocapitalizationTypeNone;
+    host_obj.pressed = None;
+    host_obj.selected = None;
+
+    let superview: id = msg![env; keyboard superview];
+    let frame = frame_in_window(env, window);
+    let hidden_frame = CGRect {
+        origin: CGPoint {
+            x: frame.origin.x,
+            y: frame.origin.y + frame.size.height,
+        },
+        ..frame
+    };
+    // If the keyboard is already visible (e.g. moving between text fields),
+    // it just needs updating.
+    let begin = if superview == window {
+        frame
+    } else {
+        hidden_frame
+    };
+
+    post_notification(env, UIKeyboardWillShowNotification, begin, frame);
+    () = msg![env; keyboard setFrame:frame];
+    // Bring to front
+    () = msg![env; window addSubview:keyboard];
+    () = msg![env; keyboard setNeedsDisplay];
+    unsafe { SDL_StartTextInput() };
+    post_notification(env, UIKeyboardDidShowNotification, begin, frame);
+}
+
+/// Hides the keyboard when the text input view resigns first responder, and
+/// stops host text input.
+pub fn hide(env: &mut Environment) {
+    unsafe { SDL_StopTextInput() };
+
+    let Some(keyboard) = env.framework_state.uikit.ui_view.ui_keyboard.keyboard else {
+        return;
+    };
+    let superview: id = msg![env; keyboard superview];
+    if superview == nil {
+        return;
+    }
+
+    let frame: CGRect = msg![env; keyboard frame];
+    let hidden_frame = CGRect {
+        origin: CGPoint {
+            x: frame.origin.x,
+            y: frame.origin.y + frame.size.height,
+        },
+        ..frame
+    };
+    post_notification(env, UIKeyboardWillHideNotification, frame, hidden_frame);
+    () = msg![env; keyboard removeFromSuperview];
+    post_notification(env, UIKeyboardDidHideNotification, frame, hidden_frame);
+}
+
+/// Handles controller input for the keyboard.
+pub fn handle_navigation(env: &mut Environment, event: KeyboardNavigationEvent) {
+    let Some(keyboard) = env.framework_state.uikit.ui_view.ui_keyboard.keyboard else {
+        return;
+    };
+    let superview: id = msg![env; keyboard superview];
+    if superview == nil {
+        return;
+    }
+
+    let keys = keys(env, keyboard);
+    let Some(selected) = env.objc.borrow::<UIKeyboardHostObject>(keyboard).selected else {
+        // The first navigation input just makes the highlight appear.
+        let first = keys.iter().position(|&(key, _)| key != Key::Blank);
+        env.objc
+            .borrow_mut::<UIKeyboardHostObject>(keyboard)
+            .selected = first;
+        () = msg![env; keyboard setNeedsDisplay];
+        return;
+    };
+    let selected = selected.min(keys.len() - 1);
+    let current = rect_center(keys[selected].1);
+
+    // Finds the nearest key in the given direction, preferring keys that are
+    // roughly in line with the current one.
+    let nearest = |dx: CGFloat, dy: CGFloat| -> Option<usize> {
+        keys.iter()
+            .enumerate()
+            .filter(|&(_, &(key, _))| key != Key::Blank)
+            .filter_map(|(idx, &(_, rect))| {
+                let center = rect_center(rect);
+                let along = (center.x - current.x) * dx + (center.y - current.y) * dy;
+                let across = (center.y - current.y) * dx - (center.x - current.x) * dy;
+                (along > 1.0).then_some((idx, along + across.abs() * 2.0))
+            })
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            .map(|(idx, _)| idx)
+    };
+
+    let new_selected = match event {
+        KeyboardNavigationEvent::Up => nearest(0.0, -1.0),
+        KeyboardNavigationEvent::Down => nearest(0.0, 1.0),
+        KeyboardNavigationEvent::Left => nearest(-1.0, 0.0),
+        KeyboardNavigationEvent::Right => nearest(1.0, 0.0),
+        KeyboardNavigationEvent::Press => {
+            press_key(env, keyboard, selected);
+            return;
+        }
+        KeyboardNavigationEvent::Backspace => {
+            crate::frameworks::uikit::handle_text_input(env, TextInputEvent::Backspace);
+            return;
+        }
+        KeyboardNavigationEvent::Return => {
+            crate::frameworks::uikit::handle_text_input(env, TextInputEvent::Return);
+            return;
+        }
+    };
+    if let Some(new_selected) = new_selected {
+        env.objc
+            .borrow_mut::<UIKeyboardHostObject>(keyboard)
+            .selected = Some(new_selected);
+        () = msg![env; keyboard setNeedsDisplay];
+    }
+}
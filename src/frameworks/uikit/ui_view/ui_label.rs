@@ -7,17 +7,20 @@
 
 use crate::frameworks::core_graphics::cg_context::CGContextSetRGBFillColor;
 use crate::frameworks::core_graphics::{CGFloat, CGPoint, CGRect, CGSize};
+use crate::frameworks::foundation::ns_string::{get_static_str, to_rust_string};
 use crate::frameworks::foundation::NSInteger;
 use crate::frameworks::uikit::ui_color;
 use crate::frameworks::uikit::ui_font::{
-    UILineBreakMode, UILineBreakModeTailTruncation, UITextAlignment, UITextAlignmentCenter,
-    UITextAlignmentLeft, UITextAlignmentRight,
+    self, UIBaselineAdjustment, UIBaselineAdjustmentAlignBaselines, UILineBreakMode,
+    UILineBreakModeTailTruncation, UITextAlignment, UITextAlignmentCenter, UITextAlignmentLeft,
+    UITextAlignmentRight,
 };
 use crate::frameworks::uikit::ui_graphics::UIGraphicsGetCurrentContext;
 use crate::objc::{
     id, impl_HostObject_with_superclass, msg, msg_class, msg_super, nil, objc_classes, release,
     retain, ClassExports, NSZonePtr,
 };
+use crate::Environment;
 
 pub struct UILabelHostObject {
     superclass: super::UIViewHostObject,
@@ -30,6 +33,9 @@ pub struct UILabelHostObject {
     text_alignment: UITextAlignment,
     line_break_mode: UILineBreakMode,
     number_of_lines: NSInteger,
+    adjusts_font_size_to_fit_width: bool,
+    minimum_font_size: CGFloat,
+    baseline_adjustment: UIBaselineAdjustment,
 }
 impl_HostObject_with_superclass!(UILabelHostObject);
 impl Default for UILabelHostObject {
@@ -42,10 +48,33 @@ impl Default for UILabelHostObject {
             text_alignment: UITextAlignmentLeft,
             line_break_mode: UILineBreakModeTailTruncation,
             number_of_lines: 1,
+            adjusts_font_size_to_fit_width: false,
+            minimum_font_size: 0.0,
+            baseline_adjustment: UIBaselineAdjustmentAlignBaselines,
         }
     }
 }
 
+/// The size that multi-line text is constrained to, taking `numberOfLines`
+/// into account.
+fn text_constraint(env: &mut Environment, this: id, size: CGSize) -> CGSize {
+    let &UILabelHostObject {
+        font,
+        number_of_lines,
+        ..
+    } = env.objc.borrow(this);
+    let height = if number_of_lines > 0 {
+        let leading: CGFloat = msg![env; font leading];
+        size.height.min(leading * number_of_lines as CGFloat)
+    } else {
+        size.height
+    };
+    CGSize {
+        width: size.width,
+        height,
+    }
+}
+
 pub const CLASSES: ClassExports = objc_classes! {
 
 (env, this, _cmd);
@@ -59,11 +88,46 @@ pub const CLASSES: ClassExports = objc_classes! {
 
 - (id)initWithCoder:(id)coder {
     let this: id = msg_super![env; this initWithCoder: coder];
-    // Use default values by calling the setters
-    // TODO: Decode the actual property values from the coder
-    () = msg![env; this setFont:nil];
-    () = msg![env; this setTextColor:nil];
-    () = msg![env; this setBackgroundColor:nil];
+
+    // Anything missing from the archive gets the default value, since the
+    // setters fetch the real defaults when passed nil.
+    let key = get_static_str(env, "UIText");
+    let text: id = msg![env; coder decodeObjectForKey:key];
+    () = msg![env; this setText:text];
+    let key = get_static_str(env, "UIFont");
+    let font: id = msg![env; coder decodeObjectForKey:key];
+    () = msg![env; this setFont:font];
+    let key = get_static_str(env, "UITextColor");
+    let text_color: id = msg![env; coder decodeObjectForKey:key];
+    () = msg![env; this setTextColor:text_color];
+    let key = get_static_str(env, "UIBackgroundColor");
+    if !msg![env; coder containsValueForKey:key] {
+        () = msg![env; this setBackgroundColor:nil];
+    }
+
+    let key = get_static_str(env, "UITextAlignment");
+    let text_alignment: UITextAlignment = msg![env; coder decodeIntegerForKey:key];
+    () = msg![env; this setTextAlignment:text_alignment];
+    let key = get_static_str(env, "UILineBreakMode");
+    if msg![env; coder containsValueForKey:key] {
+        let line_break_mode: UILineBreakMode = msg![env; coder decodeIntegerForKey:key];
+        () = msg![env; this setLineBreakMode:line_break_mode];
+    }
+    let key = get_static_str(env, "UINumberOfLines");
+    if msg![env; coder containsValueForKey:key] {
+        let number_of_lines: NSInteger = msg![env; coder decodeIntegerForKey:key];
+        () = msg![env; this setNumberOfLines:number_of_lines];
+    }
+    let key = get_static_str(env, "UIAdjustsFontSizeToFit");
+    let adjusts: bool = msg![env; coder decodeBoolForKey:key];
+    () = msg![env; this setAdjustsFontSizeToFitWidth:adjusts];
+    let key = get_static_str(env, "UIMinimumFontSize");
+    let minimum_font_size: CGFloat = msg![env; coder decodeFloatForKey:key];
+    () = msg![env; this setMinimumFontSize:minimum_font_size];
+    let key = get_static_str(env, "UIBaselineAdjustment");
+    let baseline_adjustment: UIBaselineAdjustment = msg![env; coder decodeIntegerForKey:key];
+    () = msg![env; this setBaselineAdjustment:baseline_adjustment];
+
     // Built-in views don't have user-controlled opaqueness.
     () = msg_super![env; this setOpaque:false];
     this
@@ -89,6 +153,9 @@ pub const CLASSES: ClassExports = objc_classes! {
         text_alignment: _,
         line_break_mode: _,
         number_of_lines: _,
+        adjusts_font_size_to_fit_width: _,
+        minimum_font_size: _,
+        baseline_adjustment: _,
     } = env.objc.borrow(this);
     release(env, text);
     release(env, font);
@@ -192,17 +259,62 @@ pub const CLASSES: ClassExports = objc_classes! {
 }
 - (())setNumberOfLines:(NSInteger)number {
     env.objc.borrow_mut::<UILabelHostObject>(this).number_of_lines = number;
-    if number != 0 && number != 1 {
-        log!("TODO: UILabel numberOfLines > 1 (label {:?})", this);
-    }
     () = msg![env; this setNeedsDisplay];
 }
 
+- (bool)adjustsFontSizeToFitWidth {
+    env.objc.borrow::<UILabelHostObject>(this).adjusts_font_size_to_fit_width
+}
+- (())setAdjustsFontSizeToFitWidth:(bool)adjusts {
+    env.objc.borrow_mut::<UILabelHostObject>(this).adjusts_font_size_to_fit_width = adjusts;
+    () = msg![env; this setNeedsDisplay];
+}
+
+- (CGFloat)minimumFontSize {
+    env.objc.borrow::<UILabelHostObject>(this).minimum_font_size
+}
+- (())setMinimumFontSize:(CGFloat)size {
+    env.objc.borrow_mut::<UILabelHostObject>(this).minimum_font_size = size;
+    () = msg![env; this setNeedsDisplay];
+}
+
+- (UIBaselineAdjustment)baselineAdjustment {
+    env.objc.borrow::<UILabelHostObject>(this).baseline_adjustment
+}
+- (())setBaselineAdjustment:(UIBaselineAdjustment)adjustment {
+    env.objc.borrow_mut::<UILabelHostObject>(this).baseline_adjustment = adjustment;
+    () = msg![env; this setNeedsDisplay];
+}
+
+- (CGSize)sizeThatFits:(CGSize)size {
+    let &UILabelHostObject {
+        text,
+        font,
+        line_break_mode,
+        number_of_lines,
+        ..
+    } = env.objc.borrow(this);
+    if text == nil {
+        return CGSize { width: 0.0, height: 0.0 };
+    }
+    if number_of_lines == 1 {
+        msg![env; text sizeWithFont:font]
+    } else {
+        let constraint = text_constraint(env, this, CGSize {
+            width: size.width,
+            height: CGFloat::MAX,
+        });
+        msg![env; text sizeWithFont:font
+                  constrainedToSize:constraint
+                      lineBreakMode:line_break_mode]
+    }
+}
+
 - (())drawRect:(CGRect)_rect {
     let bounds: CGRect = msg![env; this bounds];
     let context = UIGraphicsGetCurrentContext(env);
 
-    let &mut UILabelHostObject {
+    let &UILabelHostObject {
         superclass: _,
         text,
         font,
@@ -210,58 +322,84 @@ pub const CLASSES: ClassExports = objc_classes! {
         text_alignment,
         line_break_mode,
         number_of_lines,
-    } = env.objc.borrow_mut(this);
+        adjusts_font_size_to_fit_width,
+        minimum_font_size,
+        baseline_adjustment,
+    } = env.objc.borrow(this);
+
+    if text == nil {
+        return;
+    }
 
     let (r, g, b, a) = ui_color::get_rgba(&env.objc, text_color);
     CGContextSetRGBFillColor(env, context, r, g, b, a);
 
-    // TODO: handle line counts other than 0 and 1 properly. 0 = unlimited
-    // (note the log message in setNumberOfLines:)
-    let single_line = number_of_lines == 1;
-
-    let calculated_size: CGSize = if single_line {
-        msg![env; text sizeWithFont:font]
-    } else {
-        msg![env; text sizeWithFont:font
-                  constrainedToSize:(bounds.size)
-                      lineBreakMode:line_break_mode]
+    let x_offset = match text_alignment {
+        UITextAlignmentLeft => 0.0,
+        UITextAlignmentCenter => 0.5,
+        UITextAlignmentRight => 1.0,
+        _ => unimplemented!(),
     };
 
-    // UILabel always vertically centers text
-    // (TODO: check whether this is actually a UILabel thing, or a property of
-    // UIStringDrawing?)
-    let rect = CGRect {
-        origin: CGPoint {
-            x: bounds.origin.x,
-            y: bounds.origin.y + (bounds.size.height - calculated_size.height) / 2.0,
-        },
-        size: CGSize {
-            width: bounds.size.width,
-            // This is necessary for when the calculated size is actually larger
-            // than the bounds.
-            height: calculated_size.height,
-        },
-    };
+    if number_of_lines == 1 {
+        let natural_size: CGSize = msg![env; text sizeWithFont:font];
 
-    let _size: CGSize = if single_line {
-        let x_offset = match text_alignment {
-            UITextAlignmentLeft => 0.0,
-            UITextAlignmentCenter => 0.5,
-            UITextAlignmentRight => 1.0,
-            _ => unimplemented!(),
-        };
+        let mut font_size: CGFloat = msg![env; font pointSize];
+        if adjusts_font_size_to_fit_width && natural_size.width > bounds.size.width {
+            // TODO: avoid copy
+            let text = to_rust_string(env, text);
+            font_size = ui_font::font_size_to_fit_width(
+                env,
+                font,
+                &text,
+                bounds.size.width,
+                minimum_font_size,
+            );
+        }
+        let actual_font: id = msg![env; font fontWithSize:font_size];
+        let size: CGSize = msg![env; text sizeWithFont:actual_font
+                                               forWidth:(bounds.size.width)
+                                          lineBreakMode:line_break_mode];
+
+        // UILabel always vertically centers text
+        // (TODO: check whether this is actually a UILabel thing, or a property
+        // of UIStringDrawing?)
+        // The position is based on the unadjusted font size, the baseline
+        // adjustment takes care of the rest.
         let point = CGPoint {
-            x: rect.origin.x + x_offset * (bounds.size.width - calculated_size.width),
-            y: rect.origin.y
+            x: bounds.origin.x + x_offset * (bounds.size.width - size.width).max(0.0),
+            y: bounds.origin.y + (bounds.size.height - natural_size.height) / 2.0,
         };
-        msg![env; text drawAtPoint:point
-                          withFont:font]
+        let _size: CGSize = msg![env; text drawAtPoint:point
+                                              forWidth:(bounds.size.width)
+                                              withFont:font
+                                              fontSize:font_size
+                                         lineBreakMode:line_break_mode
+                                    baselineAdjustment:baseline_adjustment];
     } else {
-        msg![env; text drawInRect:rect
-                         withFont:font
-                    lineBreakMode:line_break_mode
-                        alignment:text_alignment]
-    };
+        let constraint = text_constraint(env, this, bounds.size);
+        let calculated_size: CGSize = msg![env; text sizeWithFont:font
+                                                 constrainedToSize:constraint
+                                                     lineBreakMode:line_break_mode];
+
+        // UILabel always vertically centers text
+        let rect = CGRect {
+            origin: CGPoint {
+                x: bounds.origin.x,
+                y: bounds.origin.y + (bounds.size.height - calculated_size.height) / 2.0,
+            },
+            size: CGSize {
+                width: bounds.size.width,
+                // This is necessary for when the calculated size is actually
+                // larger than the bounds.
+                height: calculated_size.height,
+            },
+        };
+        let _size: CGSize = msg![env; text drawInRect:rect
+                                             withFont:font
+                                        lineBreakMode:line_break_mode
+                                            alignment:text_alignment];
+    }
 }
 
 @end
@@ -0,0 +1,153 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! `UIProgressView`.
+
+use crate::frameworks::core_graphics::cg_context::{CGContextFillRect, CGContextSetRGBFillColor};
+use crate::frameworks::core_graphics::{CGFloat, CGPoint, CGRect, CGSize};
+use crate::frameworks::foundation::ns_string::get_static_str;
+use crate::frameworks::foundation::NSInteger;
+use crate::frameworks::uikit::ui_graphics::UIGraphicsGetCurrentContext;
+use crate::objc::{
+    id, impl_HostObject_with_superclass, msg, msg_super, objc_classes, ClassExports, NSZonePtr,
+};
+
+type UIProgressViewStyle = NSInteger;
+#[allow(dead_code)]
+const UIProgressViewStyleDefault: UIProgressViewStyle = 0;
+const UIProgressViewStyleBar: UIProgressViewStyle = 1;
+
+/// Height of the bar. The real UIKit forces this regardless of the frame.
+const BAR_HEIGHT: CGFloat = 9.0;
+
+#[derive(Default)]
+pub struct UIProgressViewHostObject {
+    superclass: super::UIViewHostObject,
+    progress: f32,
+    style: UIProgressViewStyle,
+}
+impl_HostObject_with_superclass!(UIProgressViewHostObject);
+
+pub const CLASSES: ClassExports = objc_classes! {
+
+(env, this, _cmd);
+
+@implementation UIProgressView: UIView
+
++ (id)allocWithZone:(NSZonePtr)_zone {
+    let host_object = Box::<UIProgressViewHostObject>::default();
+    env.objc.alloc_object(this, host_object, &mut env.mem)
+}
+
+- (id)initWithProgressViewStyle:(UIProgressViewStyle)style {
+    let frame = CGRect {
+        origin: CGPoint { x: 0.0, y: 0.0 },
+        size: CGSize { width: 150.0, height: BAR_HEIGHT },
+    };
+    let this: id = msg![env; this initWithFrame:frame];
+    env.objc.borrow_mut::<UIProgressViewHostObject>(this).style = style;
+    this
+}
+
+- (id)initWithFrame:(CGRect)frame {
+    let this: id = msg_super![env; this initWithFrame:frame];
+    // Built-in views don't have user-controlled opaqueness.
+    () = msg![env; this setOpaque:false];
+    this
+}
+
+- (id)initWithCoder:(id)coder {
+    let this: id = msg_super![env; this initWithCoder:coder];
+
+    let key = get_static_str(env, "UIProgress");
+    let progress: f32 = msg![env; coder decodeFloatForKey:key];
+    let key = get_static_str(env, "UIProgressViewStyle");
+    let style: UIProgressViewStyle = msg![env; coder decodeIntegerForKey:key];
+
+    let host_obj = env.objc.borrow_mut::<UIProgressViewHostObject>(this);
+    host_obj.progress = progress.clamp(0.0, 1.0);
+    host_obj.style = style;
+
+    () = msg![env; this setOpaque:false];
+    this
+}
+
+- (f32)progress {
+    env.objc.borrow::<UIProgressViewHostObject>(this).progress
+}
+- (())setProgress:(f32)progress {
+    env.objc.borrow_mut::<UIProgressViewHostObject>(this).progress = progress.clamp(0.0, 1.0);
+    () = msg![env; this setNeedsDisplay];
+}
+- (())setProgress:(f32)progress animated:(bool)_animated {
+    // TODO: animation
+    msg![env; this setProgress:progress]
+}
+
+- (UIProgressViewStyle)progressViewStyle {
+    env.objc.borrow::<UIProgressViewHostObject>(this).style
+}
+- (())setProgressViewStyle:(UIProgressViewStyle)style {
+    env.objc.borrow_mut::<UIProgressViewHostObject>(this).style = style;
+    () = msg![env; this setNeedsDisplay];
+}
+
+- (CGSize)sizeThatFits:(CGSize)size {
+    CGSize {
+        width: size.width,
+        height: BAR_HEIGHT,
+    }
+}
+
+- (())drawRect:(CGRect)_rect {
+    let bounds: CGRect = msg![env; this bounds];
+    let context = UIGraphicsGetCurrentContext(env);
+    let &UIProgressViewHostObject { progress, style, .. } = env.objc.borrow(this);
+
+    let bar = CGRect {
+        origin: CGPoint {
+            x: bounds.origin.x,
+            y: bounds.origin.y + ((bounds.size.height - BAR_HEIGHT) / 2.0).max(0.0),
+        },
+        size: CGSize {
+            width: bounds.size.width,
+            height: bounds.size.height.min(BAR_HEIGHT),
+        },
+    };
+
+    let (track, fill) = match style {
+        UIProgressViewStyleBar => ((0.75, 0.78, 0.82), (0.30, 0.40, 0.55)),
+        // UIProgressViewStyleDefault
+        _ => ((0.95, 0.95, 0.95), (0.16, 0.42, 0.87)),
+    };
+
+    // Border, then the empty track, then the filled part.
+    CGContextSetRGBFillColor(env, context, 0.45, 0.45, 0.45, 1.0);
+    CGContextFillRect(env, context, bar);
+    let inner = CGRect {
+        origin: CGPoint {
+            x: bar.origin.x + 1.0,
+            y: bar.origin.y + 1.0,
+        },
+        size: CGSize {
+            width: (bar.size.width - 2.0).max(0.0),
+            height: (bar.size.height - 2.0).max(0.0),
+        },
+    };
+    CGContextSetRGBFillColor(env, context, track.0, track.1, track.2, 1.0);
+    CGContextFillRect(env, context, inner);
+    CGContextSetRGBFillColor(env, context, fill.0, fill.1, fill.2, 1.0);
+    CGContextFillRect(env, context, CGRect {
+        size: CGSize {
+            width: inner.size.width * progress,
+            ..inner.size
+        },
+        ..inner
+    });
+}
+
+@end
+
+};
@@ -4,12 +4,64 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 //! `UIScrollView`.
+//!
+//! Scrolling physics (deceleration, bouncing, paging) are driven by an
+//! `NSTimer` that fires at the display refresh rate while an animation is in
+//! progress.
 
 pub mod ui_text_view;
-use crate::frameworks::core_graphics::{CGPoint, CGRect, CGSize};
+use super::ui_alert_view::delegate_responds;
+use crate::frameworks::core_graphics::{CGFloat, CGPoint, CGRect, CGSize};
+use crate::frameworks::foundation::{NSInteger, NSTimeInterval, NSUInteger};
 use crate::objc::{
-    id, impl_HostObject_with_superclass, msg, nil, objc_classes, ClassExports, NSZonePtr, SEL,
+    id, impl_HostObject_with_superclass, msg, msg_class, msg_super, nil, objc_classes, release,
+    retain, ClassExports, NSZonePtr,
 };
+use crate::Environment;
+use std::time::Instant;
+
+type UIScrollViewIndicatorStyle = NSInteger;
+const UIScrollViewIndicatorStyleDefault: UIScrollViewIndicatorStyle = 0;
+#[allow(dead_code)]
+const UIScrollViewIndicatorStyleBlack: UIScrollViewIndicatorStyle = 1;
+const UIScrollViewIndicatorStyleWhite: UIScrollViewIndicatorStyle = 2;
+
+/// `UIScrollViewDecelerationRateNormal`
+const DECELERATION_RATE_NORMAL: CGFloat = 0.998;
+/// `UIScrollViewDecelerationRateFast`
+const DECELERATION_RATE_FAST: CGFloat = 0.99;
+
+/// Interval between animation steps.
+const FRAME_INTERVAL: NSTimeInterval = 1.0 / 60.0;
+/// Below this speed (in points per second), deceleration stops.
+const MIN_VELOCITY: CGFloat = 10.0;
+/// How much of a drag beyond the content edge is applied to the offset.
+const RUBBER_BAND_FACTOR: CGFloat = 0.5;
+/// Duration of the animation that returns an overscrolled view to its edge.
+const BOUNCE_DURATION: NSTimeInterval = 0.3;
+/// Duration of `setContentOffset:animated:` and page snapping.
+const SCROLL_ANIMATION_DURATION: NSTimeInterval = 0.3;
+/// Thickness of a scroll indicator, and its distance from the edge.
+const INDICATOR_THICKNESS: CGFloat = 3.0;
+const INDICATOR_INSET: CGFloat = 2.0;
+const INDICATOR_MIN_LENGTH: CGFloat = 9.0;
+
+#[derive(Debug, Copy, Clone)]
+enum Animation {
+    /// Inertial scrolling after the finger is lifted.
+    Decelerating,
+    /// Eased movement to a fixed offset: page snapping, bouncing back from
+    /// beyond the edge, or `setContentOffset:animated:`.
+    ToOffset {
+        from: CGPoint,
+        to: CGPoint,
+        start: Instant,
+        duration: NSTimeInterval,
+        /// Whether this was started by `setContentOffset:animated:` or
+        /// similar, rather than by the user lifting their finger.
+        programmatic: bool,
+    },
+}
 
 pub struct UIScrollViewHostObject {
     superclass: super::UIViewHostObject,
@@ -18,6 +70,36 @@ pub struct UIScrollViewHostObject {
     scroll_enabled: bool,
     content_offset: CGPoint,
     content_size: CGSize,
+    bounces: bool,
+    always_bounce_vertical: bool,
+    always_bounce_horizontal: bool,
+    paging_enabled: bool,
+    delays_content_touches: bool,
+    shows_horizontal_scroll_indicator: bool,
+    shows_vertical_scroll_indicator: bool,
+    indicator_style: UIScrollViewIndicatorStyle,
+    deceleration_rate: CGFloat,
+    minimum_zoom_scale: CGFloat,
+    maximum_zoom_scale: CGFloat,
+    zoom_scale: CGFloat,
+    bounces_zoom: bool,
+    /// Unscaled size of the view returned by `viewForZoomingInScrollView:`.
+    zoom_view_size: Option<CGSize>,
+    tracking: bool,
+    dragging: bool,
+    zooming: bool,
+    /// Distance between the two fingers and zoom scale when pinching began.
+    pinch_start: Option<(CGFloat, CGFloat)>,
+    /// Estimated finger velocity, in points per second.
+    velocity: CGPoint,
+    last_move_timestamp: NSTimeInterval,
+    animation: Option<Animation>,
+    last_animation_step: Option<Instant>,
+    /// `NSTimer*`, strong reference
+    animation_timer: id,
+    /// `UIView*`s, strong references
+    horizontal_indicator: id,
+    vertical_indicator: id,
 }
 impl_HostObject_with_superclass!(UIScrollViewHostObject);
 impl Default for UIScrollViewHostObject {
@@ -31,10 +113,524 @@ impl Default for UIScrollViewHostObject {
                 width: 0.0,
                 height: 0.0,
             },
+            bounces: true,
+            always_bounce_vertical: false,
+            always_bounce_horizontal: false,
+            paging_enabled: false,
+            delays_content_touches: true,
+            shows_horizontal_scroll_indicator: true,
+            shows_vertical_scroll_indicator: true,
+            indicator_style: UIScrollViewIndicatorStyleDefault,
+            deceleration_rate: DECELERATION_RATE_NORMAL,
+            minimum_zoom_scale: 1.0,
+            maximum_zoom_scale: 1.0,
+            zoom_scale: 1.0,
+            bounces_zoom: true,
+            zoom_view_size: None,
+            tracking: false,
+            dragging: false,
+            zooming: false,
+            pinch_start: None,
+            velocity: CGPoint { x: 0.0, y: 0.0 },
+            last_move_timestamp: 0.0,
+            animation: None,
+            last_animation_step: None,
+            animation_timer: nil,
+            horizontal_indicator: nil,
+            vertical_indicator: nil,
         }
     }
 }
 
+/// Returns the delegate if it exists and implements an optional method.
+fn responding_delegate(env: &mut Environment, this: id, selector: &str) -> Option<id> {
+    let delegate = env.objc.borrow::<UIScrollViewHostObject>(this).delegate;
+    delegate_responds(env, delegate, selector).then_some(delegate)
+}
+
+/// The range of valid content offsets, as (minimum, maximum).
+fn offset_limits(env: &mut Environment, this: id) -> (CGPoint, CGPoint) {
+    let bounds: CGRect = msg![env; this bounds];
+    let content_size = env.objc.borrow::<UIScrollViewHostObject>(this).content_size;
+    (
+        CGPoint { x: 0.0, y: 0.0 },
+        CGPoint {
+            x: (content_size.width - bounds.size.width).max(0.0),
+            y: (content_size.height - bounds.size.height).max(0.0),
+        },
+    )
+}
+
+fn clamp_offset(offset: CGPoint, (min, max): (CGPoint, CGPoint)) -> CGPoint {
+    CGPoint {
+        x: offset.x.clamp(min.x, max.x),
+        y: offset.y.clamp(min.y, max.y),
+    }
+}
+
+/// Whether the content can be dragged along each axis, as (x, y).
+fn scrollable_axes(env: &mut Environment, this: id) -> (bool, bool) {
+    let (_, max) = offset_limits(env, this);
+    let host_obj = env.objc.borrow::<UIScrollViewHostObject>(this);
+    let bounces = host_obj.bounces;
+    (
+        max.x > 0.0 || (bounces && host_obj.always_bounce_horizontal),
+        max.y > 0.0 || (bounces && host_obj.always_bounce_vertical),
+    )
+}
+
+/// Sets the content offset and notifies the delegate, if it changed.
+fn scroll_to(env: &mut Environment, this: id, new_offset: CGPoint) {
+    let offset = env
+        .objc
+        .borrow::<UIScrollViewHostObject>(this)
+        .content_offset;
+    log_dbg!("content offset: old {:?}, new {:?}", offset, new_offset);
+    if new_offset == offset {
+        return;
+    }
+    () = msg![env; this setContentOffset:new_offset];
+    if let Some(delegate) = responding_delegate(env, this, "scrollViewDidScroll:") {
+        () = msg![env; delegate scrollViewDidScroll:this];
+    }
+}
+
+/// Offset to snap to when paging is enabled and the user lifts their finger.
+fn page_target(env: &mut Environment, this: id) -> CGPoint {
+    let bounds: CGRect = msg![env; this bounds];
+    let &UIScrollViewHostObject {
+        content_offset,
+        velocity,
+        ..
+    } = env.objc.borrow(this);
+    let limits = offset_limits(env, this);
+
+    let snap = |offset: CGFloat, velocity: CGFloat, page_size: CGFloat| {
+        if page_size <= 0.0 {
+            return offset;
+        }
+        let page = offset / page_size;
+        // A quick flick moves to the next page even if it isn't halfway there.
+        let page = if velocity > 300.0 {
+            page.ceil()
+        } else if velocity < -300.0 {
+            page.floor()
+        } else {
+            page.round()
+        };
+        page * page_size
+    };
+    clamp_offset(
+        CGPoint {
+            x: snap(content_offset.x, velocity.x, bounds.size.width),
+            y: snap(content_offset.y, velocity.y, bounds.size.height),
+        },
+        limits,
+    )
+}
+
+fn start_animation(env: &mut Environment, this: id, animation: Animation) {
+    let host_obj = env.objc.borrow_mut::<UIScrollViewHostObject>(this);
+    host_obj.animation = Some(animation);
+    host_obj.last_animation_step = Some(Instant::now());
+    if host_obj.animation_timer != nil {
+        return;
+    }
+    let selector = env
+        .objc
+        .lookup_selector("_touchHLE_scrollAnimationStep:")
+        .unwrap();
+    let timer: id = msg_class![env; NSTimer scheduledTimerWithTimeInterval:FRAME_INTERVAL
+                                                                    target:this
+                                                                  selector:selector
+                                                                  userInfo:nil
+                                                                   repeats:true];
+    retain(env, timer);
+    env.objc
+        .borrow_mut::<UIScrollViewHostObject>(this)
+        .animation_timer = timer;
+}
+
+/// Stops any animation in progress, without notifying the delegate.
+fn stop_animation(env: &mut Environment, this: id) -> Option<Animation> {
+    let host_obj = env.objc.borrow_mut::<UIScrollViewHostObject>(this);
+    let animation = host_obj.animation.take();
+    host_obj.last_animation_step = None;
+    let timer = std::mem::take(&mut host_obj.animation_timer);
+    if timer != nil {
+        () = msg![env; timer invalidate];
+        release(env, timer);
+    }
+    animation
+}
+
+/// Called when an animation has run to completion.
+fn finish_animation(env: &mut Environment, this: id) {
+    let Some(animation) = stop_animation(env, this) else {
+        return;
+    };
+    set_indicators_visible(env, this, false);
+    match animation {
+        Animation::ToOffset {
+            programmatic: true, ..
+        } => {
+            if let Some(delegate) =
+                responding_delegate(env, this, "scrollViewDidEndScrollingAnimation:")
+            {
+                () = msg![env; delegate scrollViewDidEndScrollingAnimation:this];
+            }
+        }
+        _ => {
+            if let Some(delegate) = responding_delegate(env, this, "scrollViewDidEndDecelerating:")
+            {
+                () = msg![env; delegate scrollViewDidEndDecelerating:this];
+            }
+        }
+    }
+}
+
+/// Eases out, like `UIViewAnimationCurveEaseOut`.
+fn ease_out(t: CGFloat) -> CGFloat {
+    1.0 - (1.0 - t).powi(3)
+}
+
+fn animation_step(env: &mut Environment, this: id) {
+    let now = Instant::now();
+    let host_obj = env.objc.borrow_mut::<UIScrollViewHostObject>(this);
+    let Some(animation) = host_obj.animation else {
+        return;
+    };
+    let dt = host_obj
+        .last_animation_step
+        .map_or(FRAME_INTERVAL, |last| (now - last).as_secs_f64());
+    host_obj.last_animation_step = Some(now);
+    let dt = dt as CGFloat;
+
+    match animation {
+        Animation::Decelerating => {
+            let &mut UIScrollViewHostObject {
+                content_offset,
+                velocity,
+                deceleration_rate,
+                bounces,
+                ..
+            } = host_obj;
+            let limits = offset_limits(env, this);
+            let clamped = clamp_offset(content_offset, limits);
+
+            // The deceleration rate is the fraction of velocity kept per
+            // millisecond.
+            let mut decay = deceleration_rate.powf(dt * 1000.0);
+            if clamped != content_offset {
+                // Overscrolled: brake hard, then bounce back.
+                decay = decay.powi(30);
+            }
+            let velocity = CGPoint {
+                x: velocity.x * decay,
+                y: velocity.y * decay,
+            };
+            let mut new_offset = CGPoint {
+                x: content_offset.x + velocity.x * dt,
+                y: content_offset.y + velocity.y * dt,
+            };
+            if !bounces {
+                new_offset = clamp_offset(new_offset, limits);
+            }
+            env.objc.borrow_mut::<UIScrollViewHostObject>(this).velocity = velocity;
+            scroll_to(env, this, new_offset);
+            update_indicators(env, this);
+
+            let slow = velocity.x.abs() < MIN_VELOCITY && velocity.y.abs() < MIN_VELOCITY;
+            let clamped = clamp_offset(new_offset, limits);
+            if clamped != new_offset && (slow || !bounces) {
+                env.objc
+                    .borrow_mut::<UIScrollViewHostObject>(this)
+                    .animation = Some(Animation::ToOffset {
+                    from: new_offset,
+                    to: clamped,
+                    start: now,
+                    duration: BOUNCE_DURATION,
+                    programmatic: false,
+                });
+            } else if slow {
+                finish_animation(env, this);
+            }
+        }
+        Animation::ToOffset {
+            from,
+            to,
+            start,
+            duration,
+            ..
+        } => {
+            let t = ((now - start).as_secs_f64() / duration).min(1.0) as CGFloat;
+            let e = ease_out(t);
+            let new_offset = CGPoint {
+                x: from.x + (to.x - from.x) * e,
+                y: from.y + (to.y - from.y) * e,
+            };
+            scroll_to(env, this, new_offset);
+            update_indicators(env, this);
+            if t >= 1.0 {
+                finish_animation(env, this);
+            }
+        }
+    }
+}
+
+fn create_indicator(env: &mut Environment, this: id) -> id {
+    let indicator: id = msg_class![env; UIView new];
+    () = msg![env; indicator setUserInteractionEnabled:false];
+    () = msg![env; indicator setHidden:true];
+    () = msg![env; this addSubview:indicator];
+    indicator
+}
+
+fn set_indicators_visible(env: &mut Environment, this: id, visible: bool) {
+    let &UIScrollViewHostObject {
+        horizontal_indicator,
+        vertical_indicator,
+        ..
+    } = env.objc.borrow(this);
+    for indicator in [horizontal_indicator, vertical_indicator] {
+        if indicator != nil {
+            () = msg![env; indicator setHidden:(!visible)];
+        }
+    }
+    if visible {
+        update_indicators(env, this);
+    }
+}
+
+/// Positions the scroll indicators to reflect the current content offset.
+fn update_indicators(env: &mut Environment, this: id) {
+    let bounds: CGRect = msg![env; this bounds];
+    let &UIScrollViewHostObject {
+        content_size,
+        content_offset,
+        shows_horizontal_scroll_indicator,
+        shows_vertical_scroll_indicator,
+        indicator_style,
+        ..
+    } = env.objc.borrow(this);
+    let (scrolls_x, scrolls_y) = (
+        content_size.width > bounds.size.width,
+        content_size.height > bounds.size.height,
+    );
+
+    let color: id = if indicator_style == UIScrollViewIndicatorStyleWhite {
+        msg_class![env; UIColor colorWithWhite:(1.0 as CGFloat) alpha:(0.5 as CGFloat)]
+    } else {
+        msg_class![env; UIColor colorWithWhite:(0.0 as CGFloat) alpha:(0.5 as CGFloat)]
+    };
+
+    // Length is proportional to the visible fraction of the content, and the
+    // indicator shrinks when overscrolled.
+    let indicator_geometry =
+        |view_length: CGFloat, content_length: CGFloat, offset: CGFloat, track: CGFloat| {
+            let max_offset = (content_length - view_length).max(0.0);
+            let overscroll = if offset < 0.0 {
+                -offset
+            } else {
+                (offset - max_offset).max(0.0)
+            };
+            let length = (track * view_length / content_length - overscroll)
+                .max(INDICATOR_MIN_LENGTH)
+                .min(track);
+            let fraction = if max_offset > 0.0 {
+                (offset / max_offset).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            (fraction * (track - length), length)
+        };
+
+    if shows_vertical_scroll_indicator && scrolls_y {
+        let mut indicator = env
+            .objc
+            .borrow::<UIScrollViewHostObject>(this)
+            .vertical_indicator;
+        if indicator == nil {
+            indicator = create_indicator(env, this);
+            env.objc
+                .borrow_mut::<UIScrollViewHostObject>(this)
+                .vertical_indicator = indicator;
+        }
+        let track = bounds.size.height - INDICATOR_INSET * 2.0;
+        let (position, length) = indicator_geometry(
+            bounds.size.height,
+            content_size.height,
+            content_offset.y,
+            track,
+        );
+        let frame = CGRect {
+            origin: CGPoint {
+                x: bounds.origin.x + bounds.size.width - INDICATOR_THICKNESS - INDICATOR_INSET,
+                y: bounds.origin.y + INDICATOR_INSET + position,
+            },
+            size: CGSize {
+                width: INDICATOR_THICKNESS,
+                height: length,
+            },
+        };
+        () = msg![env; indicator setFrame:frame];
+        () = msg![env; indicator setBackgroundColor:color];
+        () = msg![env; this bringSubviewToFront:indicator];
+    }
+    if shows_horizontal_scroll_indicator && scrolls_x {
+        let mut indicator = env
+            .objc
+            .borrow::<UIScrollViewHostObject>(this)
+            .horizontal_indicator;
+        if indicator == nil {
+            indicator = create_indicator(env, this);
+            env.objc
+                .borrow_mut::<UIScrollViewHostObject>(this)
+                .horizontal_indicator = indicator;
+        }
+        let track = bounds.size.width - INDICATOR_INSET * 2.0;
+        let (position, length) = indicator_geometry(
+            bounds.size.width,
+            content_size.width,
+            content_offset.x,
+            track,
+        );
+        let frame = CGRect {
+            origin: CGPoint {
+                x: bounds.origin.x + INDICATOR_INSET + position,
+                y: bounds.origin.y + bounds.size.height - INDICATOR_THICKNESS - INDICATOR_INSET,
+            },
+            size: CGSize {
+                width: length,
+                height: INDICATOR_THICKNESS,
+            },
+        };
+        () = msg![env; indicator setFrame:frame];
+        () = msg![env; indicator setBackgroundColor:color];
+        () = msg![env; this bringSubviewToFront:indicator];
+    }
+}
+
+/// Returns the view to zoom, if zooming is possible.
+fn view_for_zooming(env: &mut Environment, this: id) -> Option<id> {
+    let &UIScrollViewHostObject {
+        minimum_zoom_scale,
+        maximum_zoom_scale,
+        ..
+    } = env.objc.borrow(this);
+    if minimum_zoom_scale == maximum_zoom_scale {
+        return None;
+    }
+    let delegate = responding_delegate(env, this, "viewForZoomingInScrollView:")?;
+    let view: id = msg![env; delegate viewForZoomingInScrollView:this];
+    (view != nil).then_some(view)
+}
+
+/// Applies a zoom scale to the zooming view and content size.
+///
+/// The real UIKit applies a transform to the zooming view. touchHLE can't yet
+/// composite transformed views, so the view is resized instead. This looks
+/// the same for image views, which most zoomable content is.
+fn apply_zoom_scale(env: &mut Environment, this: id, view: id, scale: CGFloat) {
+    let host_obj = env.objc.borrow_mut::<UIScrollViewHostObject>(this);
+    let old_scale = host_obj.zoom_scale;
+    let zoom_view_size = host_obj.zoom_view_size;
+    let base_size = match zoom_view_size {
+        Some(size) => size,
+        None => {
+            let frame: CGRect = msg![env; view frame];
+            let size = CGSize {
+                width: frame.size.width / old_scale,
+                height: frame.size.height / old_scale,
+            };
+            env.objc
+                .borrow_mut::<UIScrollViewHostObject>(this)
+                .zoom_view_size = Some(size);
+            size
+        }
+    };
+    let new_size = CGSize {
+        width: base_size.width * scale,
+        height: base_size.height * scale,
+    };
+    let frame = CGRect {
+        origin: CGPoint { x: 0.0, y: 0.0 },
+        size: new_size,
+    };
+    () = msg![env; view setFrame:frame];
+
+    // Keep the centre of the visible area fixed while zooming.
+    let bounds: CGRect = msg![env; this bounds];
+    let host_obj = env.objc.borrow_mut::<UIScrollViewHostObject>(this);
+    host_obj.zoom_scale = scale;
+    host_obj.content_size = new_size;
+    let ratio = scale / old_scale;
+    let centre = CGPoint {
+        x: (host_obj.content_offset.x + bounds.size.width / 2.0) * ratio,
+        y: (host_obj.content_offset.y + bounds.size.height / 2.0) * ratio,
+    };
+    let limits = offset_limits(env, this);
+    let new_offset = clamp_offset(
+        CGPoint {
+            x: centre.x - bounds.size.width / 2.0,
+            y: centre.y - bounds.size.height / 2.0,
+        },
+        limits,
+    );
+    scroll_to(env, this, new_offset);
+
+    if let Some(delegate) = responding_delegate(env, this, "scrollViewDidZoom:") {
+        () = msg![env; delegate scrollViewDidZoom:this];
+    }
+}
+
+/// Returns the first two touches in a set, if it has at least two.
+fn two_touches(env: &mut Environment, touches: id) -> Option<(id, id)> {
+    let touch_arr: id = msg![env; touches allObjects];
+    let count: NSUInteger = msg![env; touch_arr count];
+    if count < 2 {
+        return None;
+    }
+    let a: id = msg![env; touch_arr objectAtIndex:0u32];
+    let b: id = msg![env; touch_arr objectAtIndex:1u32];
+    Some((a, b))
+}
+
+fn touch_distance(env: &mut Environment, this: id, (a, b): (id, id)) -> CGFloat {
+    let a: CGPoint = msg![env; a locationInView:this];
+    let b: CGPoint = msg![env; b locationInView:this];
+    ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt()
+}
+
+fn end_zooming(env: &mut Environment, this: id) {
+    let host_obj = env.objc.borrow_mut::<UIScrollViewHostObject>(this);
+    host_obj.pinch_start = None;
+    if !std::mem::take(&mut host_obj.zooming) {
+        return;
+    }
+    let &mut UIScrollViewHostObject {
+        zoom_scale,
+        minimum_zoom_scale,
+        maximum_zoom_scale,
+        ..
+    } = host_obj;
+    let Some(view) = view_for_zooming(env, this) else {
+        return;
+    };
+    // Bounce back into range.
+    let clamped_scale = zoom_scale.clamp(minimum_zoom_scale, maximum_zoom_scale);
+    if clamped_scale != zoom_scale {
+        apply_zoom_scale(env, this, view, clamped_scale);
+    }
+    if let Some(delegate) =
+        responding_delegate(env, this, "scrollViewDidEndZooming:withView:atScale:")
+    {
+        () = msg![env; delegate scrollViewDidEndZooming:this
+                                               withView:view
+                                                atScale:clamped_scale];
+    }
+}
+
 pub const CLASSES: ClassExports = objc_classes! {
 
 (env, this, _cmd);
@@ -46,6 +642,32 @@ pub const CLASSES: ClassExports = objc_classes! {
     env.objc.alloc_object(this, host_object, &mut env.mem)
 }
 
+- (id)initWithFrame:(CGRect)frame {
+    let this: id = msg_super![env; this initWithFrame:frame];
+    () = msg![env; this setMultipleTouchEnabled:true];
+    this
+}
+
+- (id)initWithCoder:(id)coder {
+    let this: id = msg_super![env; this initWithCoder:coder];
+    () = msg![env; this setMultipleTouchEnabled:true];
+    this
+}
+
+- (())dealloc {
+    let UIScrollViewHostObject {
+        animation_timer,
+        horizontal_indicator,
+        vertical_indicator,
+        ..
+    } = std::mem::take(env.objc.borrow_mut(this));
+    // The timer retains this object, so it must have been stopped already.
+    assert!(animation_timer == nil);
+    release(env, horizontal_indicator);
+    release(env, vertical_indicator);
+    msg_super![env; this dealloc]
+}
+
 - (id)delegate {
     env.objc.borrow::<UIScrollViewHostObject>(this).delegate
 }
@@ -53,20 +675,96 @@ pub const CLASSES: ClassExports = objc_classes! {
     env.objc.borrow_mut::<UIScrollViewHostObject>(this).delegate = delegate;
 }
 
-- (())setDelaysContentTouches:(id)_delay_content_touches{
-    // TODO
+- (bool)delaysContentTouches {
+    env.objc.borrow::<UIScrollViewHostObject>(this).delays_content_touches
+}
+- (())setDelaysContentTouches:(bool)delays_content_touches {
+    // TODO: actually delay touches to subviews
+    env.objc.borrow_mut::<UIScrollViewHostObject>(this).delays_content_touches = delays_content_touches;
+}
+
+- (bool)bounces {
+    env.objc.borrow::<UIScrollViewHostObject>(this).bounces
+}
+- (())setBounces:(bool)bounces {
+    env.objc.borrow_mut::<UIScrollViewHostObject>(this).bounces = bounces;
+}
+- (bool)alwaysBounceVertical {
+    env.objc.borrow::<UIScrollViewHostObject>(this).always_bounce_vertical
+}
+- (())setAlwaysBounceVertical:(bool)always_bounce {
+    env.objc.borrow_mut::<UIScrollViewHostObject>(this).always_bounce_vertical = always_bounce;
+}
+- (bool)alwaysBounceHorizontal {
+    env.objc.borrow::<UIScrollViewHostObject>(this).always_bounce_horizontal
+}
+- (())setAlwaysBounceHorizontal:(bool)always_bounce {
+    env.objc.borrow_mut::<UIScrollViewHostObject>(this).always_bounce_horizontal = always_bounce;
+}
+
+- (bool)isPagingEnabled {
+    env.objc.borrow::<UIScrollViewHostObject>(this).paging_enabled
+}
+- (())setPagingEnabled:(bool)paging_enabled {
+    env.objc.borrow_mut::<UIScrollViewHostObject>(this).paging_enabled = paging_enabled;
+}
+
+- (CGFloat)decelerationRate {
+    env.objc.borrow::<UIScrollViewHostObject>(this).deceleration_rate
+}
+- (())setDecelerationRate:(CGFloat)rate {
+    env.objc.borrow_mut::<UIScrollViewHostObject>(this).deceleration_rate = rate.clamp(DECELERATION_RATE_FAST, DECELERATION_RATE_NORMAL);
+}
+
+- (bool)showsHorizontalScrollIndicator {
+    env.objc.borrow::<UIScrollViewHostObject>(this).shows_horizontal_scroll_indicator
+}
+- (())setShowsHorizontalScrollIndicator:(bool)shows {
+    env.objc.borrow_mut::<UIScrollViewHostObject>(this).shows_horizontal_scroll_indicator = shows;
+}
+- (bool)showsVerticalScrollIndicator {
+    env.objc.borrow::<UIScrollViewHostObject>(this).shows_vertical_scroll_indicator
+}
+- (())setShowsVerticalScrollIndicator:(bool)shows {
+    env.objc.borrow_mut::<UIScrollViewHostObject>(this).shows_vertical_scroll_indicator = shows;
+}
+- (UIScrollViewIndicatorStyle)indicatorStyle {
+    env.objc.borrow::<UIScrollViewHostObject>(this).indicator_style
+}
+- (())setIndicatorStyle:(UIScrollViewIndicatorStyle)style {
+    env.objc.borrow_mut::<UIScrollViewHostObject>(this).indicator_style = style;
 }
-- (())setBounces:(id)_bounces {
-    // TODO
+- (())flashScrollIndicators {
+    // TODO: fade out after a delay
+    update_indicators(env, this);
 }
 
 - (bool)scrollEnabled {
     env.objc.borrow::<UIScrollViewHostObject>(this).scroll_enabled
 }
+- (bool)isScrollEnabled {
+    env.objc.borrow::<UIScrollViewHostObject>(this).scroll_enabled
+}
 - (())setScrollEnabled:(bool)scroll_enabled {
     env.objc.borrow_mut::<UIScrollViewHostObject>(this).scroll_enabled = scroll_enabled;
 }
 
+- (bool)isTracking {
+    env.objc.borrow::<UIScrollViewHostObject>(this).tracking
+}
+- (bool)isDragging {
+    env.objc.borrow::<UIScrollViewHostObject>(this).dragging
+}
+- (bool)isDecelerating {
+    matches!(
+        env.objc.borrow::<UIScrollViewHostObject>(this).animation,
+        Some(Animation::Decelerating | Animation::ToOffset { programmatic: false, .. })
+    )
+}
+- (bool)isZooming {
+    env.objc.borrow::<UIScrollViewHostObject>(this).zooming
+}
+
 - (CGPoint)contentOffset {
     env.objc.borrow::<UIScrollViewHostObject>(this).content_offset
 }
@@ -78,6 +776,46 @@ pub const CLASSES: ClassExports = objc_classes! {
     () = msg![env; this setBounds:bounds];
     () = msg![env; this setNeedsDisplay];
 }
+- (())setContentOffset:(CGPoint)offset animated:(bool)animated {
+    stop_animation(env, this);
+    if !animated {
+        scroll_to(env, this, offset);
+        return;
+    }
+    let from = env.objc.borrow::<UIScrollViewHostObject>(this).content_offset;
+    if from == offset {
+        return;
+    }
+    set_indicators_visible(env, this, true);
+    start_animation(env, this, Animation::ToOffset {
+        from,
+        to: offset,
+        start: Instant::now(),
+        duration: SCROLL_ANIMATION_DURATION,
+        programmatic: true,
+    });
+}
+- (())scrollRectToVisible:(CGRect)rect animated:(bool)animated {
+    let bounds: CGRect = msg![env; this bounds];
+    let offset = env.objc.borrow::<UIScrollViewHostObject>(this).content_offset;
+    // Scroll by the minimum amount needed along each axis.
+    let scroll_axis = |offset: CGFloat, view_length: CGFloat, start: CGFloat, length: CGFloat| {
+        if start < offset || length > view_length {
+            start
+        } else if start + length > offset + view_length {
+            start + length - view_length
+        } else {
+            offset
+        }
+    };
+    let new_offset = CGPoint {
+        x: scroll_axis(offset.x, bounds.size.width, rect.origin.x, rect.size.width),
+        y: scroll_axis(offset.y, bounds.size.height, rect.origin.y, rect.size.height),
+    };
+    let limits = offset_limits(env, this);
+    let new_offset = clamp_offset(new_offset, limits);
+    msg![env; this setContentOffset:new_offset animated:animated]
+}
 
 - (CGSize)contentSize {
     env.objc.borrow::<UIScrollViewHostObject>(this).content_size
@@ -86,56 +824,254 @@ pub const CLASSES: ClassExports = objc_classes! {
     env.objc.borrow_mut::<UIScrollViewHostObject>(this).content_size = size;
 }
 
+- (CGFloat)minimumZoomScale {
+    env.objc.borrow::<UIScrollViewHostObject>(this).minimum_zoom_scale
+}
+- (())setMinimumZoomScale:(CGFloat)scale {
+    env.objc.borrow_mut::<UIScrollViewHostObject>(this).minimum_zoom_scale = scale;
+}
+- (CGFloat)maximumZoomScale {
+    env.objc.borrow::<UIScrollViewHostObject>(this).maximum_zoom_scale
+}
+- (())setMaximumZoomScale:(CGFloat)scale {
+    env.objc.borrow_mut::<UIScrollViewHostObject>(this).maximum_zoom_scale = scale;
+}
+- (bool)bouncesZoom {
+    env.objc.borrow::<UIScrollViewHostObject>(this).bounces_zoom
+}
+- (())setBouncesZoom:(bool)bounces_zoom {
+    env.objc.borrow_mut::<UIScrollViewHostObject>(this).bounces_zoom = bounces_zoom;
+}
+- (CGFloat)zoomScale {
+    env.objc.borrow::<UIScrollViewHostObject>(this).zoom_scale
+}
+- (())setZoomScale:(CGFloat)scale {
+    let &UIScrollViewHostObject {
+        minimum_zoom_scale,
+        maximum_zoom_scale,
+        ..
+    } = env.objc.borrow(this);
+    let scale = scale.clamp(minimum_zoom_scale, maximum_zoom_scale);
+    if let Some(view) = view_for_zooming(env, this) {
+        apply_zoom_scale(env, this, view, scale);
+    }
+}
+- (())setZoomScale:(CGFloat)scale animated:(bool)_animated {
+    // TODO: animation
+    msg![env; this setZoomScale:scale]
+}
+- (())zoomToRect:(CGRect)rect animated:(bool)animated {
+    let bounds: CGRect = msg![env; this bounds];
+    let &UIScrollViewHostObject {
+        zoom_scale,
+        minimum_zoom_scale,
+        maximum_zoom_scale,
+        ..
+    } = env.objc.borrow(this);
+    // The rect is in the zooming view's (unscaled) co-ordinate space.
+    let scale = (bounds.size.width / rect.size.width)
+        .min(bounds.size.height / rect.size.height)
+        .clamp(minimum_zoom_scale, maximum_zoom_scale);
+    if scale != zoom_scale {
+        () = msg![env; this setZoomScale:scale];
+    }
+    let scaled_rect = CGRect {
+        origin: CGPoint {
+            x: rect.origin.x * scale,
+            y: rect.origin.y * scale,
+        },
+        size: CGSize {
+            width: rect.size.width * scale,
+            height: rect.size.height * scale,
+        },
+    };
+    msg![env; this scrollRectToVisible:scaled_rect animated:animated]
+}
+
+- (())touchesBegan:(id)touches // NSSet* of UITouch*
+         withEvent:(id)_event { // UIEvent*
+    // Touching the view stops any scrolling in progress.
+    stop_animation(env, this);
+
+    let touch_arr: id = msg![env; touches allObjects];
+    let touch: id = msg![env; touch_arr objectAtIndex:0u32];
+    let timestamp: NSTimeInterval = msg![env; touch timestamp];
+    let host_obj = env.objc.borrow_mut::<UIScrollViewHostObject>(this);
+    host_obj.tracking = true;
+    host_obj.velocity = CGPoint { x: 0.0, y: 0.0 };
+    host_obj.last_move_timestamp = timestamp;
+}
+
 - (())touchesMoved:(id)touches // NSSet* of UITouch*
          withEvent:(id)_event { // UIEvent*
+    // Two fingers pinch to zoom.
+    if let Some(pair) = two_touches(env, touches) {
+        let Some(view) = view_for_zooming(env, this) else {
+            return;
+        };
+        let distance = touch_distance(env, this, pair);
+        let host_obj = env.objc.borrow_mut::<UIScrollViewHostObject>(this);
+        let Some((start_distance, start_scale)) = host_obj.pinch_start else {
+            host_obj.pinch_start = Some((distance, host_obj.zoom_scale));
+            host_obj.zooming = true;
+            if let Some(delegate) = responding_delegate(env, this, "scrollViewWillBeginZooming:withView:") {
+                () = msg![env; delegate scrollViewWillBeginZooming:this withView:view];
+            }
+            return;
+        };
+        if start_distance <= 0.0 {
+            return;
+        }
+        let &mut UIScrollViewHostObject {
+            minimum_zoom_scale,
+            maximum_zoom_scale,
+            bounces_zoom,
+            ..
+        } = host_obj;
+        // Allow going a little out of range if zoom bouncing is enabled.
+        let (min, max) = if bounces_zoom {
+            (minimum_zoom_scale * 0.8, maximum_zoom_scale * 1.25)
+        } else {
+            (minimum_zoom_scale, maximum_zoom_scale)
+        };
+        let scale = (start_scale * distance / start_distance).clamp(min, max);
+        apply_zoom_scale(env, this, view, scale);
+        return;
+    }
+
     let scroll_enabled: bool = msg![env; this scrollEnabled];
     if !scroll_enabled {
         return;
     }
 
     let touch_arr: id = msg![env; touches allObjects];
-    // Assume single finger touches for now
+    // Only one finger scrolls.
     let touch: id = msg![env; touch_arr objectAtIndex:0u32];
-    let bounds: CGRect = msg![env; this bounds];
 
     let prev_location: CGPoint = msg![env; touch previousLocationInView:this];
-    let prev_x = prev_location.x;
-    let prev_y = prev_location.y;
-
     let new_location: CGPoint = msg![env; touch locationInView:this];
-    let y = new_location.y;
-    let x = new_location.x;
+    let timestamp: NSTimeInterval = msg![env; touch timestamp];
+
+    let (scrolls_x, scrolls_y) = scrollable_axes(env, this);
+    let delta = CGPoint {
+        x: if scrolls_x { new_location.x - prev_location.x } else { 0.0 },
+        y: if scrolls_y { new_location.y - prev_location.y } else { 0.0 },
+    };
 
-    let delta_y = y - prev_y;
-    let delta_x = x - prev_x;
+    if !env.objc.borrow::<UIScrollViewHostObject>(this).dragging {
+        env.objc.borrow_mut::<UIScrollViewHostObject>(this).dragging = true;
+        if let Some(delegate) = responding_delegate(env, this, "scrollViewWillBeginDragging:") {
+            () = msg![env; delegate scrollViewWillBeginDragging:this];
+        }
+        set_indicators_visible(env, this, true);
+    }
 
-    let offset: CGPoint = msg![env; this contentOffset];
-    let content_size: CGSize = msg![env; this contentSize];
+    let &UIScrollViewHostObject {
+        content_offset: offset,
+        bounces,
+        velocity,
+        last_move_timestamp,
+        ..
+    } = env.objc.borrow(this);
+    let limits = offset_limits(env, this);
 
-    // Very rudimentary scrolling.
     // We emulate sliding up to scroll down like on the real iPhone.
-    let mut new_content_offset: CGPoint = CGPoint { x: offset.x - delta_x, y: offset.y - delta_y };
+    let mut new_content_offset = CGPoint { x: offset.x - delta.x, y: offset.y - delta.y };
+    if bounces {
+        // Resist dragging beyond the edge of the content.
+        let clamped = clamp_offset(new_content_offset, limits);
+        if clamped.x != new_content_offset.x {
+            new_content_offset.x = offset.x - delta.x * RUBBER_BAND_FACTOR;
+        }
+        if clamped.y != new_content_offset.y {
+            new_content_offset.y = offset.y - delta.y * RUBBER_BAND_FACTOR;
+        }
+    } else {
+        new_content_offset = clamp_offset(new_content_offset, limits);
+    }
+
+    // Smoothed estimate of the finger's velocity, used for flicking.
+    let dt = (timestamp - last_move_timestamp) as CGFloat;
+    if dt > 0.0 {
+        let new_velocity = CGPoint { x: -delta.x / dt, y: -delta.y / dt };
+        env.objc.borrow_mut::<UIScrollViewHostObject>(this).velocity = CGPoint {
+            x: velocity.x * 0.2 + new_velocity.x * 0.8,
+            y: velocity.y * 0.2 + new_velocity.y * 0.8,
+        };
+    }
+    env.objc.borrow_mut::<UIScrollViewHostObject>(this).last_move_timestamp = timestamp;
 
-    // Update content offset within bounds
-    new_content_offset.y = new_content_offset.y.min(content_size.height - bounds.size.height).max(0.0);
-    new_content_offset.x = new_content_offset.x.min(content_size.width - bounds.size.width).max(0.0);
+    scroll_to(env, this, new_content_offset);
+    update_indicators(env, this);
+}
 
-    // Trigger rerender only if required.
-    log_dbg!("content offset: old {:?}, new {:?}", offset, new_content_offset);
-    if new_content_offset != offset {
-        () = msg![env; this setContentOffset:new_content_offset];
+- (())touchesEnded:(id)touches // NSSet* of UITouch*
+         withEvent:(id)_event { // UIEvent*
+    end_zooming(env, this);
 
-        let delegate: id = msg![env; this delegate];
-        let sel: SEL = env
-            .objc
-            .register_host_selector("scrollViewDidScroll:".to_string(), &mut env.mem);
-        let responds: bool = msg![env; delegate respondsToSelector:sel];
-        if responds {
-            () = msg![env; delegate scrollViewDidScroll:this];
+    let touch_arr: id = msg![env; touches allObjects];
+    let touch: id = msg![env; touch_arr objectAtIndex:0u32];
+    let timestamp: NSTimeInterval = msg![env; touch timestamp];
+
+    let host_obj = env.objc.borrow_mut::<UIScrollViewHostObject>(this);
+    host_obj.tracking = false;
+    if !std::mem::take(&mut host_obj.dragging) {
+        return;
+    }
+    // If the finger rested before being lifted, there's no flick.
+    if timestamp - host_obj.last_move_timestamp > 0.1 {
+        host_obj.velocity = CGPoint { x: 0.0, y: 0.0 };
+    }
+    let &mut UIScrollViewHostObject {
+        content_offset,
+        velocity,
+        paging_enabled,
+        ..
+    } = host_obj;
+    let limits = offset_limits(env, this);
+    let clamped = clamp_offset(content_offset, limits);
+
+    let animation = if paging_enabled {
+        let to = page_target(env, this);
+        (to != content_offset).then_some(Animation::ToOffset {
+            from: content_offset,
+            to,
+            start: Instant::now(),
+            duration: SCROLL_ANIMATION_DURATION,
+            programmatic: false,
+        })
+    } else if velocity.x.abs() >= MIN_VELOCITY || velocity.y.abs() >= MIN_VELOCITY {
+        Some(Animation::Decelerating)
+    } else if clamped != content_offset {
+        Some(Animation::ToOffset {
+            from: content_offset,
+            to: clamped,
+            start: Instant::now(),
+            duration: BOUNCE_DURATION,
+            programmatic: false,
+        })
+    } else {
+        None
+    };
+
+    let will_decelerate = animation.is_some();
+    if let Some(delegate) = responding_delegate(env, this, "scrollViewDidEndDragging:willDecelerate:") {
+        () = msg![env; delegate scrollViewDidEndDragging:this willDecelerate:will_decelerate];
+    }
+    if let Some(animation) = animation {
+        if let Some(delegate) = responding_delegate(env, this, "scrollViewWillBeginDecelerating:") {
+            () = msg![env; delegate scrollViewWillBeginDecelerating:this];
         }
+        start_animation(env, this, animation);
+    } else {
+        set_indicators_visible(env, this, false);
     }
 }
 
+- (())_touchHLE_scrollAnimationStep:(id)_timer { // NSTimer*
+    animation_step(env, this);
+}
+
 @end
 
 };
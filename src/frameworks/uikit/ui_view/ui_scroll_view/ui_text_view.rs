@@ -4,24 +4,62 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 //! `UITextView`.
+//!
+//! Useful resources:
+//! - [UITextViewDelegate overview](https://developer.apple.com/documentation/uikit/uitextviewdelegate?language=objc)
 
-use crate::frameworks::core_graphics::cg_context::CGContextSetRGBFillColor;
+use crate::dyld::{ConstantExports, HostConstant};
+use crate::frameworks::core_graphics::cg_context::{CGContextFillRect, CGContextSetRGBFillColor};
 use crate::frameworks::core_graphics::cg_geometry::CGPointZero;
 use crate::frameworks::core_graphics::{CGFloat, CGPoint, CGRect, CGSize};
+use crate::frameworks::foundation::ns_string::{from_rust_string, get_static_str, to_rust_string};
+use crate::frameworks::foundation::{NSInteger, NSRange, NSUInteger};
 use crate::frameworks::uikit::ui_color;
 use crate::frameworks::uikit::ui_font::{
-    UILineBreakModeTailTruncation, UITextAlignment, UITextAlignmentLeft,
+    self, UILineBreakModeWordWrap, UITextAlignment, UITextAlignmentLeft,
 };
 use crate::frameworks::uikit::ui_graphics::UIGraphicsGetCurrentContext;
+use crate::frameworks::uikit::ui_view::ui_alert_view::delegate_responds;
+use crate::frameworks::uikit::ui_view::ui_keyboard::{self, UIKeyboardType, UIKeyboardTypeDefault};
 use crate::objc::{
     id, impl_HostObject_with_superclass, msg, msg_class, msg_super, nil, objc_classes, release,
     retain, ClassExports, NSZonePtr,
 };
+use crate::window::CaretMovement;
 use crate::Environment;
+use std::ops::Range;
+
+type UIReturnKeyType = NSInteger;
+type UITextAutocapitalizationType = NSInteger;
+const UITextAutocapitalizationTypeSentences: UITextAutocapitalizationType = 2;
+
+pub const UITextViewTextDidBeginEditingNotification: &str =
+    "UITextViewTextDidBeginEditingNotification";
+pub const UITextViewTextDidChangeNotification: &str = "UITextViewTextDidChangeNotification";
+pub const UITextViewTextDidEndEditingNotification: &str = "UITextViewTextDidEndEditingNotification";
+
+pub const CONSTANTS: ConstantExports = &[
+    (
+        "_UITextViewTextDidBeginEditingNotification",
+        HostConstant::NSString(UITextViewTextDidBeginEditingNotification),
+    ),
+    (
+        "_UITextViewTextDidChangeNotification",
+        HostConstant::NSString(UITextViewTextDidChangeNotification),
+    ),
+    (
+        "_UITextViewTextDidEndEditingNotification",
+        HostConstant::NSString(UITextViewTextDidEndEditingNotification),
+    ),
+];
+
+/// Width of the caret.
+const CARET_WIDTH: CGFloat = 2.0;
 
 pub struct UITextViewHostObject {
     superclass: super::UIScrollViewHostObject,
     editable: bool,
+    editing: bool,
     /// `NSString*`
     text: id,
     /// `UIFont*`
@@ -29,42 +67,222 @@ pub struct UITextViewHostObject {
     /// `UIColor*`
     text_color: id,
     text_alignment: UITextAlignment,
+    /// The selection, or the caret position if the length is zero. Like all
+    /// `NSRange`s for strings, this is in UTF-16 code units.
+    selected_range: NSRange,
+    autocapitalization_type: UITextAutocapitalizationType,
+    keyboard_type: UIKeyboardType,
+    return_key_type: UIReturnKeyType,
 }
 impl_HostObject_with_superclass!(UITextViewHostObject);
 impl Default for UITextViewHostObject {
     fn default() -> Self {
         UITextViewHostObject {
             superclass: Default::default(),
-            editable: false,
+            editable: true,
+            editing: false,
             font: nil,
             text: nil,
             text_color: nil,
             text_alignment: UITextAlignmentLeft,
+            selected_range: NSRange {
+                location: 0,
+                length: 0,
+            },
+            autocapitalization_type: UITextAutocapitalizationTypeSentences,
+            keyboard_type: UIKeyboardTypeDefault,
+            return_key_type: 0, // UIReturnKeyDefault
         }
     }
 }
 
+/// The text broken into lines, as drawn, for working out where the caret
+/// goes.
+struct TextLayout {
+    text: String,
+    /// Byte ranges of each line within [Self::text].
+    lines: Vec<Range<usize>>,
+    line_spacing: CGFloat,
+}
+
+fn layout(env: &mut Environment, this: id) -> TextLayout {
+    let &UITextViewHostObject { text, font, .. } = env.objc.borrow(this);
+    let text = if text == nil {
+        String::new()
+    } else {
+        to_rust_string(env, text).into_owned()
+    };
+    let bounds: CGRect = msg![env; this bounds];
+    let lines = ui_font::editing_line_ranges(env, font, &text, bounds.size.width);
+    let line_spacing: CGFloat = msg![env; font leading];
+    TextLayout {
+        text,
+        lines,
+        line_spacing,
+    }
+}
+
+impl TextLayout {
+    fn utf16_to_byte(&self, idx: NSUInteger) -> usize {
+        let mut utf16_idx = 0;
+        for (byte_idx, c) in self.text.char_indices() {
+            if utf16_idx >= idx {
+                return byte_idx;
+            }
+            utf16_idx += c.len_utf16() as NSUInteger;
+        }
+        self.text.len()
+    }
+    fn byte_to_utf16(&self, byte_idx: usize) -> NSUInteger {
+        self.text[..byte_idx].encode_utf16().count() as NSUInteger
+    }
+
+    /// The line the caret is on when it's before the byte at `byte_idx`.
+    /// When a wrapped line ends where the next begins, the caret goes at the
+    /// start of the next line.
+    fn line_for_byte(&self, byte_idx: usize) -> usize {
+        self.lines
+            .iter()
+            .rposition(|line| line.start <= byte_idx)
+            .unwrap_or(0)
+    }
+}
+
+fn caret_rect(env: &mut Environment, this: id, layout: &TextLayout, byte_idx: usize) -> CGRect {
+    let font = env.objc.borrow::<UITextViewHostObject>(this).font;
+    let line_idx = layout.line_for_byte(byte_idx);
+    let line = layout.lines[line_idx].clone();
+    // The caret may be in whitespace trimmed from the end of a wrapped line.
+    let idx_in_line = byte_idx.min(line.end) - line.start;
+    let x = ui_font::offset_in_line(env, font, &layout.text[line.clone()], idx_in_line);
+    CGRect {
+        origin: CGPoint {
+            x,
+            y: line_idx as CGFloat * layout.line_spacing,
+        },
+        size: CGSize {
+            width: CARET_WIDTH,
+            height: layout.line_spacing,
+        },
+    }
+}
+
+/// Find the caret position (as a byte index) closest to a point in the text
+/// view's content.
+fn byte_at_point(env: &mut Environment, this: id, layout: &TextLayout, point: CGPoint) -> usize {
+    let font = env.objc.borrow::<UITextViewHostObject>(this).font;
+    let line_idx = (point.y / layout.line_spacing).floor().max(0.0) as usize;
+    let line = layout.lines[line_idx.min(layout.lines.len() - 1)].clone();
+    let line_text = &layout.text[line.clone()];
+    let mut best = (line.start, CGFloat::MAX);
+    for idx in line_text
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain(std::iter::once(line_text.len()))
+    {
+        let x = ui_font::offset_in_line(env, font, line_text, idx);
+        let distance = (x - point.x).abs();
+        if distance < best.1 {
+            best = (line.start + idx, distance);
+        }
+    }
+    best.0
+}
+
 // Update contentOffset and contentSize when anything that potentially affects
 // contentSize like font and text change.
 fn update_scroll(env: &mut Environment, this: id) {
     let bounds: CGRect = msg![env; this bounds];
-    let bound_size = bounds.size;
-    let font: id = msg![env; this font];
-    let text: id = msg![env; this text];
+    let layout = layout(env, this);
 
     // Calculate our new contentSize
-    let calculated_size: CGSize = msg![env; text sizeWithFont:font constrainedToSize:bound_size];
+    let calculated_size = CGSize {
+        width: bounds.size.width,
+        height: layout.lines.len() as CGFloat * layout.line_spacing,
+    };
     () = msg![env; this setContentSize:calculated_size];
 
-    // Reset contentOffset if we have now gone out of bounds of contentSize,
-    // otherwise ignore.
+    // Move the contentOffset back if we have now gone out of bounds of
+    // contentSize, otherwise ignore.
     let current_content_offset: CGPoint = msg![env; this contentOffset];
-    if current_content_offset.x > calculated_size.width - bounds.size.width
-        || current_content_offset.y > calculated_size.height - bounds.size.height
+    let max_offset = CGPoint {
+        x: (calculated_size.width - bounds.size.width).max(0.0),
+        y: (calculated_size.height - bounds.size.height).max(0.0),
+    };
+    if current_content_offset.x > max_offset.x || current_content_offset.y > max_offset.y {
+        let new_offset = CGPoint {
+            x: current_content_offset.x.min(max_offset.x),
+            y: current_content_offset.y.min(max_offset.y),
+        };
+        () = msg![env; this setContentOffset:new_offset];
+    }
+}
+
+fn scroll_to_caret(env: &mut Environment, this: id) {
+    let layout = layout(env, this);
+    let selected_range = env.objc.borrow::<UITextViewHostObject>(this).selected_range;
+    let byte_idx = layout.utf16_to_byte(selected_range.location + selected_range.length);
+    let rect = caret_rect(env, this, &layout, byte_idx);
+    () = msg![env; this scrollRectToVisible:rect animated:false];
+}
+
+/// Set the selection on behalf of the user.
+fn select(env: &mut Environment, this: id, range: NSRange) {
+    env.objc
+        .borrow_mut::<UITextViewHostObject>(this)
+        .selected_range = range;
+    scroll_to_caret(env, this);
+    () = msg![env; this setNeedsDisplay];
+
+    let delegate: id = msg![env; this delegate];
+    if delegate_responds(env, delegate, "textViewDidChangeSelection:") {
+        () = msg![env; delegate textViewDidChangeSelection:this];
+    }
+}
+
+/// Replace a range of the text on behalf of the user, if the delegate allows
+/// it, leaving the caret after the new text.
+fn replace_range(env: &mut Environment, this: id, range: NSRange, replacement: &str) {
+    let replacement = from_rust_string(env, replacement.to_string());
+    let delegate: id = msg![env; this delegate];
+    if delegate_responds(
+        env,
+        delegate,
+        "textView:shouldChangeTextInRange:replacementText:",
+    ) && !msg![env; delegate textView:this
+                shouldChangeTextInRange:range
+                        replacementText:replacement]
     {
-        () = msg![env; this setContentOffset:(CGPoint { x: 0.0, y: 0.0 })];
+        release(env, replacement);
+        return;
+    }
+
+    let mut text: id = env.objc.borrow::<UITextViewHostObject>(this).text;
+    if text == nil {
+        text = get_static_str(env, "");
     }
+    let new_text: id = msg![env; text stringByReplacingCharactersInRange:range
+                                                             withString:replacement];
+    () = msg![env; this setText:new_text];
+    let replacement_length: NSUInteger = msg![env; replacement length];
+    release(env, replacement);
+    select(
+        env,
+        this,
+        NSRange {
+            location: range.location + replacement_length,
+            length: 0,
+        },
+    );
+
+    if delegate_responds(env, delegate, "textViewDidChange:") {
+        () = msg![env; delegate textViewDidChange:this];
+    }
+    let center: id = msg_class![env; NSNotificationCenter defaultCenter];
+    let name = get_static_str(env, UITextViewTextDidChangeNotification);
+    () = msg![env; center postNotificationName:name object:this];
 }
+
 pub const CLASSES: ClassExports = objc_classes! {
 
 (env, this, _cmd);
@@ -76,24 +294,58 @@ pub const CLASSES: ClassExports = objc_classes! {
     env.objc.alloc_object(this, host_object, &mut env.mem)
 }
 
+- (id)initWithFrame:(CGRect)frame {
+    let this: id = msg_super![env; this initWithFrame:frame];
+    () = msg![env; this setFont:nil];
+    let text_color: id = msg_class![env; UIColor blackColor];
+    () = msg![env; this setTextColor:text_color];
+    let bg_color: id = msg_class![env; UIColor whiteColor];
+    () = msg![env; this setBackgroundColor:bg_color];
+    this
+}
+
 - (id)initWithCoder:(id)coder {
     let this: id = msg_super![env; this initWithCoder:coder];
-    // These aren't redundant, the setters fetch the real defaults.
-    () = msg![env; this setFont:nil];
-    () = msg![env; this setTextColor:nil];
-    // TODO: support background color
-    //() = msg![env; this setBackgroundColor:nil];
+    // If these are missing, the setters fetch the real defaults.
+    let key = get_static_str(env, "UIFont");
+    let font: id = msg![env; coder decodeObjectForKey:key];
+    () = msg![env; this setFont:font];
+    let key = get_static_str(env, "UITextColor");
+    let text_color: id = msg![env; coder decodeObjectForKey:key];
+    () = msg![env; this setTextColor:text_color];
+    let key = get_static_str(env, "UITextAlignment");
+    let text_alignment: UITextAlignment = msg![env; coder decodeIntegerForKey:key];
+    () = msg![env; this setTextAlignment:text_alignment];
+    let key = get_static_str(env, "UIText");
+    let text: id = msg![env; coder decodeObjectForKey:key];
+    () = msg![env; this setText:text];
+    let key = get_static_str(env, "UIEditable");
+    if msg![env; coder containsValueForKey:key] {
+        let editable: bool = msg![env; coder decodeBoolForKey:key];
+        () = msg![env; this setEditable:editable];
+    }
+
+    // UITextInputTraits
+    let key = get_static_str(env, "UIAutocapitalizationType");
+    if msg![env; coder containsValueForKey:key] {
+        let type_: UITextAutocapitalizationType = msg![env; coder decodeIntegerForKey:key];
+        () = msg![env; this setAutocapitalizationType:type_];
+    }
+    let key = get_static_str(env, "UIKeyboardType");
+    let keyboard_type: UIKeyboardType = msg![env; coder decodeIntegerForKey:key];
+    () = msg![env; this setKeyboardType:keyboard_type];
+    let key = get_static_str(env, "UIReturnKeyType");
+    let return_key_type: UIReturnKeyType = msg![env; coder decodeIntegerForKey:key];
+    () = msg![env; this setReturnKeyType:return_key_type];
     this
 }
 
 - (())dealloc {
     let UITextViewHostObject {
-        superclass: _,
-        editable: _,
         font,
         text,
         text_color,
-        text_alignment: _
+        ..
     } = std::mem::take(env.objc.borrow_mut(this));
 
     release(env, font);
@@ -106,10 +358,21 @@ pub const CLASSES: ClassExports = objc_classes! {
     env.objc.borrow::<UITextViewHostObject>(this).text
 }
 - (())setText:(id)new_text { // NSString*
+    let new_text: id = msg![env; new_text copy];
     let hostobj  = env.objc.borrow_mut::<UITextViewHostObject>(this);
     let old_text = std::mem::replace(&mut hostobj.text, new_text);
-    retain(env, new_text);
     release(env, old_text);
+
+    // Keep the selection within the text.
+    let length: NSUInteger = if new_text == nil {
+        0
+    } else {
+        msg![env; new_text length]
+    };
+    let selected_range = &mut env.objc.borrow_mut::<UITextViewHostObject>(this).selected_range;
+    selected_range.location = selected_range.location.min(length);
+    selected_range.length = selected_range.length.min(length - selected_range.location);
+
     update_scroll(env,this);
     () = msg![env; this setNeedsDisplay];
 }
@@ -160,26 +423,233 @@ pub const CLASSES: ClassExports = objc_classes! {
 
 }
 
-// TODO: Make editable actually do something
 - (bool)isEditable {
     env.objc.borrow::<UITextViewHostObject>(this).editable
 }
 - (())setEditable:(bool)editable {
     env.objc.borrow_mut::<UITextViewHostObject>(this).editable = editable;
+    if !editable {
+        let _: bool = msg![env; this resignFirstResponder];
+    }
+}
+
+- (NSRange)selectedRange {
+    env.objc.borrow::<UITextViewHostObject>(this).selected_range
+}
+- (())setSelectedRange:(NSRange)range {
+    let text: id = msg![env; this text];
+    let length: NSUInteger = if text == nil {
+        0
+    } else {
+        msg![env; text length]
+    };
+    let location = range.location.min(length);
+    env.objc.borrow_mut::<UITextViewHostObject>(this).selected_range = NSRange {
+        location,
+        length: range.length.min(length - location),
+    };
+    () = msg![env; this setNeedsDisplay];
+}
+
+- (bool)hasText {
+    let text: id = msg![env; this text];
+    if text == nil {
+        return false;
+    }
+    let length: NSUInteger = msg![env; text length];
+    length != 0
+}
+
+- (())scrollRangeToVisible:(NSRange)range {
+    let layout = layout(env, this);
+    let start = layout.utf16_to_byte(range.location);
+    let end = layout.utf16_to_byte(range.location + range.length);
+    let start_rect = caret_rect(env, this, &layout, start);
+    let end_rect = caret_rect(env, this, &layout, end);
+    let rect = CGRect {
+        origin: CGPoint { x: 0.0, y: start_rect.origin.y },
+        size: CGSize {
+            width: end_rect.origin.x.max(start_rect.origin.x) + CARET_WIDTH,
+            height: end_rect.origin.y + end_rect.size.height - start_rect.origin.y,
+        },
+    };
+    () = msg![env; this scrollRectToVisible:rect animated:false];
+}
+
+// UITextInputTraits implementation
+- (UITextAutocapitalizationType)autocapitalizationType {
+    env.objc.borrow::<UITextViewHostObject>(this).autocapitalization_type
+}
+- (())setAutocapitalizationType:(UITextAutocapitalizationType)type_ {
+    env.objc.borrow_mut::<UITextViewHostObject>(this).autocapitalization_type = type_;
+}
+- (UIKeyboardType)keyboardType {
+    env.objc.borrow::<UITextViewHostObject>(this).keyboard_type
+}
+- (())setKeyboardType:(UIKeyboardType)type_ {
+    env.objc.borrow_mut::<UITextViewHostObject>(this).keyboard_type = type_;
+}
+- (UIReturnKeyType)returnKeyType {
+    env.objc.borrow::<UITextViewHostObject>(this).return_key_type
+}
+- (())setReturnKeyType:(UIReturnKeyType)type_ {
+    env.objc.borrow_mut::<UITextViewHostObject>(this).return_key_type = type_;
+}
+
+- (bool)canBecomeFirstResponder {
+    env.objc.borrow::<UITextViewHostObject>(this).editable
+}
+- (bool)isFirstResponder {
+    env.framework_state.uikit.ui_responder.first_responder == this
+}
+
+- (bool)becomeFirstResponder {
+    let &UITextViewHostObject { editing, editable, .. } = env.objc.borrow(this);
+    if editing {
+        return true;
+    }
+    if !editable {
+        return false;
+    }
+
+    let delegate: id = msg![env; this delegate];
+    if delegate_responds(env, delegate, "textViewShouldBeginEditing:")
+        && !msg![env; delegate textViewShouldBeginEditing:this] {
+        return false;
+    }
+
+    // Only one view can be edited at once.
+    let previous = env.framework_state.uikit.ui_responder.first_responder;
+    if previous != nil && previous != this && !msg![env; previous resignFirstResponder] {
+        return false;
+    }
+
+    // Editing starts with the caret at the end.
+    let text: id = msg![env; this text];
+    let length: NSUInteger = if text == nil {
+        0
+    } else {
+        msg![env; text length]
+    };
+    let host_obj = env.objc.borrow_mut::<UITextViewHostObject>(this);
+    host_obj.editing = true;
+    host_obj.selected_range = NSRange {
+        location: length,
+        length: 0,
+    };
+
+    env.framework_state.uikit.ui_responder.first_responder = this;
+    ui_keyboard::show(env, this);
+    () = msg![env; this setNeedsDisplay];
+
+    if delegate_responds(env, delegate, "textViewDidBeginEditing:") {
+        () = msg![env; delegate textViewDidBeginEditing:this];
+    }
+    let center: id = msg_class![env; NSNotificationCenter defaultCenter];
+    let name = get_static_str(env, UITextViewTextDidBeginEditingNotification);
+    () = msg![env; center postNotificationName:name object:this];
+
+    true
+}
+
+- (bool)resignFirstResponder {
+    if !env.objc.borrow::<UITextViewHostObject>(this).editing {
+        return true;
+    }
+
+    let delegate: id = msg![env; this delegate];
+    if delegate_responds(env, delegate, "textViewShouldEndEditing:")
+        && !msg![env; delegate textViewShouldEndEditing:this] {
+        return false;
+    }
+
+    env.framework_state.uikit.ui_responder.first_responder = nil;
+    ui_keyboard::hide(env);
+    env.objc.borrow_mut::<UITextViewHostObject>(this).editing = false;
+    () = msg![env; this setNeedsDisplay];
+
+    if delegate_responds(env, delegate, "textViewDidEndEditing:") {
+        () = msg![env; delegate textViewDidEndEditing:this];
+    }
+    let center: id = msg_class![env; NSNotificationCenter defaultCenter];
+    let name = get_static_str(env, UITextViewTextDidEndEditingNotification);
+    () = msg![env; center postNotificationName:name object:this];
+
+    true
+}
+
+- (())touchesEnded:(id)touches // NSSet* of UITouch*
+         withEvent:(id)event { // UIEvent*
+    let was_dragging: bool = msg![env; this isDragging];
+    () = msg_super![env; this touchesEnded:touches withEvent:event];
+    if was_dragging || !env.objc.borrow::<UITextViewHostObject>(this).editable {
+        return;
+    }
+
+    // A tap starts editing and moves the caret.
+    if !msg![env; this becomeFirstResponder] {
+        return;
+    }
+    let touch: id = msg![env; touches anyObject];
+    let point: CGPoint = msg![env; touch locationInView:this];
+    let layout = layout(env, this);
+    let byte_idx = byte_at_point(env, this, &layout, point);
+    let location = layout.byte_to_utf16(byte_idx);
+    select(env, this, NSRange { location, length: 0 });
 }
 
 - (())drawRect:(CGRect)_rect {
     let bounds: CGRect = msg![env; this bounds];
     let context = UIGraphicsGetCurrentContext(env);
 
-    let &mut UITextViewHostObject {
-        superclass: _,
-        editable: _,
+    let &UITextViewHostObject {
         font,
         text,
         text_color,
-        text_alignment
-    } = env.objc.borrow_mut(this);
+        text_alignment,
+        editing,
+        selected_range,
+        ..
+    } = env.objc.borrow(this);
+
+    // Selection highlight and caret. These are only shown while editing.
+    // TODO: make the caret blink
+    if editing && text_alignment == UITextAlignmentLeft {
+        let layout = layout(env, this);
+        let start = layout.utf16_to_byte(selected_range.location);
+        let end = layout.utf16_to_byte(selected_range.location + selected_range.length);
+        if start == end {
+            let rect = caret_rect(env, this, &layout, start);
+            CGContextSetRGBFillColor(env, context, 0.26, 0.42, 0.95, 1.0);
+            CGContextFillRect(env, context, rect);
+        } else {
+            CGContextSetRGBFillColor(env, context, 0.7, 0.8, 1.0, 1.0);
+            for (line_idx, line) in layout.lines.iter().enumerate() {
+                if line.end < start || line.start > end {
+                    continue;
+                }
+                let line_text = &layout.text[line.clone()];
+                let from = start.clamp(line.start, line.end) - line.start;
+                let to = end.clamp(line.start, line.end) - line.start;
+                let x1 = ui_font::offset_in_line(env, font, line_text, from);
+                let x2 = ui_font::offset_in_line(env, font, line_text, to);
+                CGContextFillRect(env, context, CGRect {
+                    origin: CGPoint {
+                        x: x1,
+                        y: line_idx as CGFloat * layout.line_spacing,
+                    },
+                    size: CGSize {
+                        width: (x2 - x1).max(CARET_WIDTH),
+                        height: layout.line_spacing,
+                    },
+                });
+            }
+        }
+    }
+
+    if text == nil {
+        return;
+    }
 
     let (r, g, b, a) = ui_color::get_rgba(&env.objc, text_color);
     CGContextSetRGBFillColor(env, context, r, g, b, a);
@@ -198,10 +668,106 @@ pub const CLASSES: ClassExports = objc_classes! {
     log_dbg!("UItextView text rendering in rect {:?}", rect);
     let _size: CGSize = msg![env; text drawInRect:rect
                                          withFont:font
-                                    lineBreakMode:UILineBreakModeTailTruncation
+                                    lineBreakMode:UILineBreakModeWordWrap
                                         alignment:text_alignment];
 }
 
 @end
 
 };
+
+pub fn handle_text(env: &mut Environment, text_view: id, text: String) {
+    log_dbg!("Calling handle_text for {:?} with '{}'", text_view, text);
+    let range = env
+        .objc
+        .borrow::<UITextViewHostObject>(text_view)
+        .selected_range;
+    replace_range(env, text_view, range, &text);
+}
+
+pub fn handle_backspace(env: &mut Environment, text_view: id) {
+    log_dbg!("Calling handle_backspace for {:?}", text_view);
+    let range = env
+        .objc
+        .borrow::<UITextViewHostObject>(text_view)
+        .selected_range;
+    if range.length > 0 {
+        replace_range(env, text_view, range, "");
+        return;
+    }
+    if range.location == 0 {
+        return;
+    }
+    // Delete the whole character before the caret, which might be a
+    // surrogate pair.
+    let layout = layout(env, text_view);
+    let end = layout.utf16_to_byte(range.location);
+    let start = layout.text[..end]
+        .char_indices()
+        .next_back()
+        .map_or(0, |(i, _)| i);
+    let location = layout.byte_to_utf16(start);
+    let range = NSRange {
+        location,
+        length: range.location - location,
+    };
+    replace_range(env, text_view, range, "");
+}
+
+pub fn handle_return(env: &mut Environment, text_view: id) {
+    // Apps that want Return to do something else check for this in
+    // textView:shouldChangeTextInRange:replacementText:.
+    handle_text(env, text_view, "\n".to_string());
+}
+
+pub fn handle_caret_movement(env: &mut Environment, text_view: id, movement: CaretMovement) {
+    let range = env
+        .objc
+        .borrow::<UITextViewHostObject>(text_view)
+        .selected_range;
+    let layout = layout(env, text_view);
+    let start = layout.utf16_to_byte(range.location);
+    let end = layout.utf16_to_byte(range.location + range.length);
+
+    let new_byte_idx = match movement {
+        // With a selection, left and right just collapse it.
+        CaretMovement::Left if start != end => start,
+        CaretMovement::Right if start != end => end,
+        CaretMovement::Left => layout.text[..start]
+            .char_indices()
+            .next_back()
+            .map_or(0, |(i, _)| i),
+        CaretMovement::Right => layout.text[end..]
+            .chars()
+            .next()
+            .map_or(end, |c| end + c.len_utf8()),
+        CaretMovement::Up | CaretMovement::Down => {
+            let caret = caret_rect(env, text_view, &layout, end);
+            let y = if movement == CaretMovement::Up {
+                caret.origin.y - layout.line_spacing
+            } else {
+                caret.origin.y + layout.line_spacing
+            };
+            if y < 0.0 {
+                0
+            } else if y >= layout.lines.len() as CGFloat * layout.line_spacing {
+                layout.text.len()
+            } else {
+                let point = CGPoint {
+                    x: caret.origin.x,
+                    y: y + layout.line_spacing / 2.0,
+                };
+                byte_at_point(env, text_view, &layout, point)
+            }
+        }
+    };
+    let location = layout.byte_to_utf16(new_byte_idx);
+    select(
+        env,
+        text_view,
+        NSRange {
+            location,
+            length: 0,
+        },
+    );
+}
@@ -0,0 +1,326 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! `UIToolbar`.
+
+use super::ui_control::ui_button::UIButtonTypeCustom;
+use super::ui_control::{UIControlEventTouchUpInside, UIControlStateNormal};
+use crate::frameworks::core_graphics::cg_context::{CGContextFillRect, CGContextSetRGBFillColor};
+use crate::frameworks::core_graphics::{CGFloat, CGPoint, CGRect, CGSize};
+use crate::frameworks::foundation::ns_string::get_static_str;
+use crate::frameworks::foundation::{NSInteger, NSUInteger};
+use crate::frameworks::uikit::ui_bar_item::ui_bar_button_item::{
+    self, UIBarButtonSystemItemFixedSpace, UIBarButtonSystemItemFlexibleSpace,
+};
+use crate::frameworks::uikit::ui_color;
+use crate::frameworks::uikit::ui_graphics::UIGraphicsGetCurrentContext;
+use crate::objc::{
+    id, impl_HostObject_with_superclass, msg, msg_class, msg_super, nil, objc_classes, release,
+    retain, ClassExports, NSZonePtr,
+};
+use crate::Environment;
+
+type UIBarStyle = NSInteger;
+#[allow(dead_code)]
+const UIBarStyleDefault: UIBarStyle = 0;
+const UIBarStyleBlack: UIBarStyle = 1;
+const UIBarStyleBlackTranslucent: UIBarStyle = 2;
+
+/// Height of a toolbar in portrait orientation.
+const TOOLBAR_HEIGHT: CGFloat = 44.0;
+/// Padding at the left and right ends of the toolbar.
+const EDGE_MARGIN: CGFloat = 6.0;
+/// Gap between adjacent items.
+const ITEM_SPACING: CGFloat = 10.0;
+/// Height of bordered buttons.
+const BUTTON_HEIGHT: CGFloat = 30.0;
+/// Horizontal padding inside a button, on each side of its title.
+const BUTTON_PADDING: CGFloat = 10.0;
+
+#[derive(Default)]
+pub struct UIToolbarHostObject {
+    superclass: super::UIViewHostObject,
+    /// `NSArray*` of `UIBarButtonItem*`
+    items: id,
+    /// One view per item: either the item's custom view, or a `UIButton`
+    /// created to represent it. Space items have `nil`. Each non-`nil` view
+    /// is a subview of the toolbar and is retained.
+    item_views: Vec<id>,
+    bar_style: UIBarStyle,
+    /// `UIColor*`
+    tint_color: id,
+}
+impl_HostObject_with_superclass!(UIToolbarHostObject);
+
+fn items_vec(env: &mut Environment, items: id) -> Vec<id> {
+    if items == nil {
+        return Vec::new();
+    }
+    let count: NSUInteger = msg![env; items count];
+    (0..count)
+        .map(|i| msg![env; items objectAtIndex:i])
+        .collect()
+}
+
+fn button_font(env: &mut Environment) -> id {
+    msg_class![env; UIFont boldSystemFontOfSize:(12.0 as CGFloat)]
+}
+
+fn create_item_view(env: &mut Environment, item: id) -> id {
+    let custom_view: id = msg![env; item customView];
+    if custom_view != nil {
+        return retain(env, custom_view);
+    }
+    if matches!(
+        ui_bar_button_item::system_item(env, item),
+        Some(UIBarButtonSystemItemFlexibleSpace | UIBarButtonSystemItemFixedSpace)
+    ) {
+        return nil;
+    }
+
+    let button: id = msg_class![env; UIButton buttonWithType:UIButtonTypeCustom];
+    retain(env, button);
+    let title: id = msg![env; item title];
+    let image: id = msg![env; item image];
+    () = msg![env; button setTitle:title forState:UIControlStateNormal];
+    () = msg![env; button setImage:image forState:UIControlStateNormal];
+    let font = button_font(env);
+    () = msg![env; button setFont:font];
+    let enabled: bool = msg![env; item isEnabled];
+    () = msg![env; button setEnabled:enabled];
+
+    let selector = env
+        .objc
+        .lookup_selector("_touchHLE_sendActionFromButton:")
+        .unwrap();
+    () = msg![env; button addTarget:item
+                             action:selector
+                   forControlEvents:UIControlEventTouchUpInside];
+    button
+}
+
+fn set_items(env: &mut Environment, this: id, items: id) {
+    retain(env, items);
+    let host_obj = env.objc.borrow_mut::<UIToolbarHostObject>(this);
+    let old_items = std::mem::replace(&mut host_obj.items, items);
+    let old_views = std::mem::take(&mut host_obj.item_views);
+    release(env, old_items);
+    for view in old_views {
+        if view != nil {
+            () = msg![env; view removeFromSuperview];
+            release(env, view);
+        }
+    }
+
+    let items = items_vec(env, items);
+    let mut item_views = Vec::with_capacity(items.len());
+    for item in items {
+        let view = create_item_view(env, item);
+        if view != nil {
+            () = msg![env; this addSubview:view];
+        }
+        item_views.push(view);
+    }
+    env.objc.borrow_mut::<UIToolbarHostObject>(this).item_views = item_views;
+
+    layout_items(env, this);
+}
+
+/// Width an item would like to have, or [None] for flexible space.
+fn item_width(env: &mut Environment, item: id) -> Option<CGFloat> {
+    let system_item = ui_bar_button_item::system_item(env, item);
+    if system_item == Some(UIBarButtonSystemItemFlexibleSpace) {
+        return None;
+    }
+    let width: CGFloat = msg![env; item width];
+    if width > 0.0 || system_item == Some(UIBarButtonSystemItemFixedSpace) {
+        return Some(width);
+    }
+    let custom_view: id = msg![env; item customView];
+    if custom_view != nil {
+        let frame: CGRect = msg![env; custom_view frame];
+        return Some(frame.size.width);
+    }
+    let title: id = msg![env; item title];
+    if title != nil {
+        let font = button_font(env);
+        let size: CGSize = msg![env; title sizeWithFont:font];
+        return Some(size.width + BUTTON_PADDING * 2.0);
+    }
+    let image: id = msg![env; item image];
+    if image != nil {
+        let size: CGSize = msg![env; image size];
+        return Some(size.width + BUTTON_PADDING * 2.0);
+    }
+    Some(0.0)
+}
+
+fn layout_items(env: &mut Environment, this: id) {
+    let bounds: CGRect = msg![env; this bounds];
+    let items = env.objc.borrow::<UIToolbarHostObject>(this).items;
+    let items = items_vec(env, items);
+    let views = env
+        .objc
+        .borrow::<UIToolbarHostObject>(this)
+        .item_views
+        .clone();
+    assert!(items.len() == views.len());
+
+    let widths: Vec<Option<CGFloat>> = items.iter().map(|&item| item_width(env, item)).collect();
+
+    // Flexible spaces share whatever is left over equally.
+    let fixed_total: CGFloat = widths.iter().flatten().sum();
+    let spacing_total = ITEM_SPACING * (items.len().max(1) - 1) as CGFloat;
+    let flexible_count = widths.iter().filter(|w| w.is_none()).count();
+    let available = bounds.size.width - EDGE_MARGIN * 2.0 - spacing_total - fixed_total;
+    let flexible_width = if flexible_count > 0 {
+        (available / flexible_count as CGFloat).max(0.0)
+    } else {
+        0.0
+    };
+
+    let button_class = env.objc.get_known_class("UIButton", &mut env.mem);
+    let mut x = bounds.origin.x + EDGE_MARGIN;
+    for (width, view) in widths.into_iter().zip(views) {
+        let width = width.unwrap_or(flexible_width);
+        if view != nil {
+            let is_button: bool = msg![env; view isKindOfClass:button_class];
+            let height = if is_button {
+                BUTTON_HEIGHT
+            } else {
+                let custom_frame: CGRect = msg![env; view frame];
+                custom_frame.size.height
+            };
+            let frame = CGRect {
+                origin: CGPoint {
+                    x,
+                    y: bounds.origin.y + (bounds.size.height - height) / 2.0,
+                },
+                size: CGSize { width, height },
+            };
+            () = msg![env; view setFrame:frame];
+        }
+        x += width + ITEM_SPACING;
+    }
+}
+
+pub const CLASSES: ClassExports = objc_classes! {
+
+(env, this, _cmd);
+
+@implementation UIToolbar: UIView
+
++ (id)allocWithZone:(NSZonePtr)_zone {
+    let host_object = Box::<UIToolbarHostObject>::default();
+    env.objc.alloc_object(this, host_object, &mut env.mem)
+}
+
+- (id)initWithCoder:(id)coder {
+    let this: id = msg_super![env; this initWithCoder:coder];
+
+    let key = get_static_str(env, "UIBarStyle");
+    let bar_style: UIBarStyle = msg![env; coder decodeIntegerForKey:key];
+    env.objc.borrow_mut::<UIToolbarHostObject>(this).bar_style = bar_style;
+
+    let key = get_static_str(env, "UIItems");
+    let items: id = msg![env; coder decodeObjectForKey:key];
+    set_items(env, this, items);
+
+    this
+}
+
+- (())dealloc {
+    let UIToolbarHostObject {
+        superclass: _,
+        items,
+        item_views,
+        bar_style: _,
+        tint_color,
+    } = std::mem::take(env.objc.borrow_mut(this));
+    release(env, items);
+    for view in item_views {
+        release(env, view);
+    }
+    release(env, tint_color);
+    msg_super![env; this dealloc]
+}
+
+- (id)items {
+    env.objc.borrow::<UIToolbarHostObject>(this).items
+}
+- (())setItems:(id)items { // NSArray*
+    set_items(env, this, items);
+}
+- (())setItems:(id)items // NSArray*
+      animated:(bool)_animated {
+    // TODO: animation
+    set_items(env, this, items);
+}
+
+- (UIBarStyle)barStyle {
+    env.objc.borrow::<UIToolbarHostObject>(this).bar_style
+}
+- (())setBarStyle:(UIBarStyle)bar_style {
+    env.objc.borrow_mut::<UIToolbarHostObject>(this).bar_style = bar_style;
+    () = msg![env; this setNeedsDisplay];
+}
+
+- (id)tintColor {
+    env.objc.borrow::<UIToolbarHostObject>(this).tint_color
+}
+- (())setTintColor:(id)tint_color { // UIColor*
+    retain(env, tint_color);
+    let host_obj = env.objc.borrow_mut::<UIToolbarHostObject>(this);
+    let old_color = std::mem::replace(&mut host_obj.tint_color, tint_color);
+    release(env, old_color);
+    () = msg![env; this setNeedsDisplay];
+}
+
+- (CGSize)sizeThatFits:(CGSize)size {
+    CGSize {
+        width: size.width,
+        height: TOOLBAR_HEIGHT,
+    }
+}
+
+- (())layoutSubviews {
+    layout_items(env, this);
+}
+
+- (())drawRect:(CGRect)_rect {
+    let bounds: CGRect = msg![env; this bounds];
+    let context = UIGraphicsGetCurrentContext(env);
+    let &UIToolbarHostObject {
+        bar_style,
+        tint_color,
+        ..
+    } = env.objc.borrow(this);
+
+    let (r, g, b, a) = if tint_color != nil {
+        ui_color::get_rgba(&env.objc, tint_color)
+    } else {
+        match bar_style {
+            UIBarStyleBlack => (0.0, 0.0, 0.0, 1.0),
+            UIBarStyleBlackTranslucent => (0.0, 0.0, 0.0, 0.6),
+            // UIBarStyleDefault
+            _ => (0.43, 0.52, 0.64, 1.0),
+        }
+    };
+    CGContextSetRGBFillColor(env, context, r, g, b, a);
+    CGContextFillRect(env, context, bounds);
+    // Thin highlight along the top edge.
+    CGContextSetRGBFillColor(env, context, 1.0, 1.0, 1.0, 0.25);
+    CGContextFillRect(env, context, CGRect {
+        size: CGSize {
+            width: bounds.size.width,
+            height: 1.0,
+        },
+        ..bounds
+    });
+}
+
+@end
+
+};
@@ -0,0 +1,999 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! `UIWebView`.
+//!
+//! There's no browser engine here: pages are parsed by the small HTML parser
+//! in [html] and laid out as blocks of wrapped text and images. That's enough
+//! for the bundled help and credits pages most apps show, but there is no
+//! networking and no JavaScript.
+//!
+//! Useful resources:
+//! - [UIWebViewDelegate overview](https://developer.apple.com/documentation/uikit/uiwebviewdelegate?language=objc)
+
+pub mod html;
+
+use crate::frameworks::core_graphics::cg_context::{CGContextFillRect, CGContextSetRGBFillColor};
+use crate::frameworks::core_graphics::{CGFloat, CGPoint, CGRect, CGSize};
+use crate::frameworks::foundation::ns_string::{from_rust_string, get_static_str, to_rust_string};
+use crate::frameworks::foundation::{NSInteger, NSUInteger};
+use crate::frameworks::uikit::ui_font;
+use crate::frameworks::uikit::ui_graphics::UIGraphicsGetCurrentContext;
+use crate::frameworks::uikit::ui_view::ui_alert_view::delegate_responds;
+use crate::frameworks::uikit::ui_view::{
+    UIViewAutoresizingFlexibleHeight, UIViewAutoresizingFlexibleWidth,
+};
+use crate::objc::{
+    autorelease, id, impl_HostObject_with_superclass, msg, msg_class, msg_super, nil, objc_classes,
+    release, retain, ClassExports, NSZonePtr,
+};
+use crate::Environment;
+use html::{Align, Inline, Rgba, TextStyle};
+use std::collections::HashMap;
+
+type UIWebViewNavigationType = NSInteger;
+const UIWebViewNavigationTypeLinkClicked: UIWebViewNavigationType = 0;
+const UIWebViewNavigationTypeReload: UIWebViewNavigationType = 3;
+const UIWebViewNavigationTypeOther: UIWebViewNavigationType = 5;
+
+type UIDataDetectorTypes = NSUInteger;
+const UIDataDetectorTypePhoneNumber: UIDataDetectorTypes = 1 << 0;
+
+/// `NSURLErrorNotConnectedToInternet`, the error given for any non-file URL.
+const NSURLErrorNotConnectedToInternet: NSInteger = -1009;
+
+/// Margin around the page, like the default style of `<body>`.
+const BODY_MARGIN: CGFloat = 8.0;
+/// Space between a list item's marker and its content.
+const MARKER_GAP: CGFloat = 6.0;
+
+/// Content waiting to be loaded on the next run loop iteration. Like on the
+/// real iPhone OS, loading is asynchronous, so apps have a chance to set a
+/// delegate after starting a load.
+enum PendingLoad {
+    /// `NSURLRequest*`
+    Request(id),
+    /// `NSString*` and `NSURL*`
+    HTMLString { html: id, base_url: id },
+}
+
+pub struct UIWebViewHostObject {
+    superclass: super::UIViewHostObject,
+    /// Weak reference.
+    delegate: id,
+    /// `NSURLRequest*` for the current page.
+    request: id,
+    pending_load: Option<PendingLoad>,
+    /// `_touchHLE_UIWebDocumentView*`, weak reference (it is a subview).
+    document_view: id,
+    scales_page_to_fit: bool,
+    data_detector_types: UIDataDetectorTypes,
+}
+impl_HostObject_with_superclass!(UIWebViewHostObject);
+impl Default for UIWebViewHostObject {
+    fn default() -> Self {
+        UIWebViewHostObject {
+            superclass: Default::default(),
+            delegate: nil,
+            request: nil,
+            pending_load: None,
+            document_view: nil,
+            scales_page_to_fit: false,
+            data_detector_types: UIDataDetectorTypePhoneNumber,
+        }
+    }
+}
+
+/// Something to draw, positioned in the document's co-ordinate space.
+enum LaidOutItem {
+    Text {
+        text: String,
+        /// `UIFont*`
+        font: id,
+        color: Rgba,
+        rect: CGRect,
+        underline: Option<CGRect>,
+    },
+    Image {
+        /// `UIImage*`
+        image: id,
+        rect: CGRect,
+    },
+    Rule {
+        rect: CGRect,
+    },
+}
+impl LaidOutItem {
+    fn rect(&self) -> CGRect {
+        match *self {
+            LaidOutItem::Text { rect, .. }
+            | LaidOutItem::Image { rect, .. }
+            | LaidOutItem::Rule { rect } => rect,
+        }
+    }
+}
+
+/// The scrolling part of the web view, which lays out and draws the page.
+#[derive(Default)]
+pub struct UIWebDocumentViewHostObject {
+    superclass: super::ui_scroll_view::UIScrollViewHostObject,
+    document: html::Document,
+    /// Directory that relative links and image paths are resolved against.
+    base_path: Option<String>,
+    items: Vec<LaidOutItem>,
+    /// Areas of the page that are links, and their targets.
+    links: Vec<(CGRect, String)>,
+    /// The width the page was laid out for, if it has been laid out.
+    layout_width: Option<CGFloat>,
+    /// `UIFont*` for each combination of boldness, italicness and size.
+    fonts: HashMap<(bool, bool, u32), id>,
+    /// `UIImage*` (or `nil` if loading failed) for each image path.
+    images: HashMap<String, id>,
+}
+impl_HostObject_with_superclass!(UIWebDocumentViewHostObject);
+
+fn create_document_view(env: &mut Environment, this: id) {
+    let bounds: CGRect = msg![env; this bounds];
+    let document_view: id = msg_class![env; _touchHLE_UIWebDocumentView alloc];
+    let document_view: id = msg![env; document_view initWithFrame:bounds];
+    let mask = UIViewAutoresizingFlexibleWidth | UIViewAutoresizingFlexibleHeight;
+    () = msg![env; document_view setAutoresizingMask:mask];
+    () = msg![env; document_view setShowsHorizontalScrollIndicator:false];
+    () = msg![env; document_view setOpaque:false];
+    let clear: id = msg_class![env; UIColor clearColor];
+    () = msg![env; document_view setBackgroundColor:clear];
+    () = msg![env; this addSubview:document_view];
+    release(env, document_view);
+    env.objc
+        .borrow_mut::<UIWebViewHostObject>(this)
+        .document_view = document_view;
+}
+
+/// Asks the delegate whether to load a request, then schedules the load.
+fn load_request(
+    env: &mut Environment,
+    this: id,
+    request: id,
+    navigation_type: UIWebViewNavigationType,
+) {
+    let delegate = env.objc.borrow::<UIWebViewHostObject>(this).delegate;
+    if delegate_responds(
+        env,
+        delegate,
+        "webView:shouldStartLoadWithRequest:navigationType:",
+    ) {
+        let should_start: bool = msg![env; delegate webView:this
+                                     shouldStartLoadWithRequest:request
+                                                 navigationType:navigation_type];
+        if !should_start {
+            return;
+        }
+    }
+    retain(env, request);
+    schedule_load(env, this, PendingLoad::Request(request));
+}
+
+fn schedule_load(env: &mut Environment, this: id, load: PendingLoad) {
+    let already_scheduled = cancel_load(env, this);
+    env.objc
+        .borrow_mut::<UIWebViewHostObject>(this)
+        .pending_load = Some(load);
+    if already_scheduled {
+        return;
+    }
+    let selector = env.objc.lookup_selector("_touchHLE_finishLoad:").unwrap();
+    () = msg![env; this performSelector:selector withObject:nil afterDelay:0.0];
+}
+
+/// Discards any pending load. Returns [true] if there was one.
+fn cancel_load(env: &mut Environment, this: id) -> bool {
+    let pending = env
+        .objc
+        .borrow_mut::<UIWebViewHostObject>(this)
+        .pending_load
+        .take();
+    match pending {
+        Some(PendingLoad::Request(request)) => release(env, request),
+        Some(PendingLoad::HTMLString { html, base_url }) => {
+            release(env, html);
+            release(env, base_url);
+        }
+        None => return false,
+    }
+    true
+}
+
+/// Gets the HTML and base path for a request, if it is for a local file.
+fn read_request(env: &mut Environment, request: id) -> Option<(id, String)> {
+    let url: id = msg![env; request URL];
+    if url == nil {
+        return None;
+    }
+    let is_file_url: bool = msg![env; url isFileURL];
+    if !is_file_url {
+        return None;
+    }
+    let path: id = msg![env; url path];
+    let html: id = msg_class![env; NSString stringWithContentsOfFile:path];
+    if html == nil {
+        return None;
+    }
+    let path = to_rust_string(env, path);
+    let base_path = match path.rfind('/') {
+        Some(idx) => path[..idx].to_string(),
+        None => String::new(),
+    };
+    Some((html, base_path))
+}
+
+fn did_fail_load(env: &mut Environment, this: id, request: id) {
+    let url: id = msg![env; request URL];
+    let url_desc: id = msg![env; url description];
+    if url_desc != nil {
+        log!(
+            "Warning: UIWebView can't load {:?}, only local files are supported",
+            to_rust_string(env, url_desc)
+        );
+    }
+    let delegate = env.objc.borrow::<UIWebViewHostObject>(this).delegate;
+    if delegate_responds(env, delegate, "webView:didFailLoadWithError:") {
+        let domain = get_static_str(env, "NSURLErrorDomain");
+        let error: id = msg_class![env; NSError alloc];
+        let error: id = msg![env; error initWithDomain:domain
+                                                  code:NSURLErrorNotConnectedToInternet
+                                              userInfo:nil];
+        () = msg![env; delegate webView:this didFailLoadWithError:error];
+        release(env, error);
+    }
+}
+
+/// Resolves a relative path against a directory, like a browser would.
+fn resolve_path(base_path: &str, relative: &str) -> String {
+    // Query strings and fragments don't matter for local files.
+    let relative = relative.split(['?', '#']).next().unwrap();
+    let mut components: Vec<&str> = if relative.starts_with('/') {
+        Vec::new()
+    } else {
+        base_path.split('/').filter(|c| !c.is_empty()).collect()
+    };
+    for component in relative.split('/') {
+        match component {
+            "" | "." => (),
+            ".." => {
+                components.pop();
+            }
+            component => components.push(component),
+        }
+    }
+    format!("/{}", components.join("/"))
+}
+
+/// Whether a URL has a scheme (e.g. `http:`), rather than being a path.
+fn has_scheme(url: &str) -> bool {
+    url.split_once(':').is_some_and(|(scheme, _)| {
+        scheme.len() > 1
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '+')
+    })
+}
+
+fn follow_link(env: &mut Environment, this: id, href: &str) {
+    if href.starts_with('#') || href.starts_with("javascript:") {
+        log_dbg!("UIWebView: ignoring link to {:?}", href);
+        return;
+    }
+    let document_view = env.objc.borrow::<UIWebViewHostObject>(this).document_view;
+    let base_path = env
+        .objc
+        .borrow::<UIWebDocumentViewHostObject>(document_view)
+        .base_path
+        .clone()
+        .unwrap_or_default();
+    let url: id = if has_scheme(href) {
+        let href = from_rust_string(env, href.to_string());
+        let url: id = msg_class![env; NSURL URLWithString:href];
+        release(env, href);
+        url
+    } else {
+        let path = resolve_path(&base_path, href);
+        let path = from_rust_string(env, path);
+        let url: id = msg_class![env; NSURL fileURLWithPath:path];
+        release(env, path);
+        url
+    };
+    let request: id = msg_class![env; NSURLRequest requestWithURL:url];
+    load_request(env, this, request, UIWebViewNavigationTypeLinkClicked);
+}
+
+fn font_for_style(env: &mut Environment, this: id, style: &TextStyle) -> id {
+    let key = (style.bold, style.italic, style.font_size.to_bits());
+    let host_obj = env.objc.borrow::<UIWebDocumentViewHostObject>(this);
+    if let Some(&font) = host_obj.fonts.get(&key) {
+        return font;
+    }
+    let size: CGFloat = style.font_size;
+    // TODO: bold italic
+    let font: id = if style.bold {
+        msg_class![env; UIFont boldSystemFontOfSize:size]
+    } else if style.italic {
+        msg_class![env; UIFont italicSystemFontOfSize:size]
+    } else {
+        msg_class![env; UIFont systemFontOfSize:size]
+    };
+    retain(env, font);
+    env.objc
+        .borrow_mut::<UIWebDocumentViewHostObject>(this)
+        .fonts
+        .insert(key, font);
+    font
+}
+
+fn image_for_src(env: &mut Environment, this: id, src: &str) -> id {
+    let host_obj = env.objc.borrow::<UIWebDocumentViewHostObject>(this);
+    let path = if let Some(path) = src.strip_prefix("file://") {
+        path.to_string()
+    } else if has_scheme(src) {
+        log!("Warning: UIWebView can't load image {:?}", src);
+        return nil;
+    } else {
+        resolve_path(host_obj.base_path.as_deref().unwrap_or(""), src)
+    };
+    if let Some(&image) = host_obj.images.get(&path) {
+        return image;
+    }
+    let ns_path = from_rust_string(env, path.clone());
+    let image: id = msg_class![env; UIImage imageWithContentsOfFile:ns_path];
+    release(env, ns_path);
+    if image == nil {
+        log!("Warning: UIWebView couldn't load image {:?}", path);
+    }
+    retain(env, image);
+    env.objc
+        .borrow_mut::<UIWebDocumentViewHostObject>(this)
+        .images
+        .insert(path, image);
+    image
+}
+
+/// A piece of a line that hasn't been positioned vertically yet.
+struct Fragment {
+    kind: FragmentKind,
+    x: CGFloat,
+    width: CGFloat,
+    /// Width of trailing spaces, which don't count for alignment.
+    trailing_space: CGFloat,
+    height: CGFloat,
+    link: Option<String>,
+}
+enum FragmentKind {
+    Text {
+        text: String,
+        font: id,
+        color: Rgba,
+        underline: bool,
+    },
+    /// `UIImage*`, which may be `nil`.
+    Image(id),
+}
+
+/// Breaks the blocks of a document into lines.
+struct Layouter {
+    items: Vec<LaidOutItem>,
+    links: Vec<(CGRect, String)>,
+    y: CGFloat,
+    left: CGFloat,
+    width: CGFloat,
+    align: Align,
+    line: Vec<Fragment>,
+    line_x: CGFloat,
+    /// Height of the last line, used for empty lines.
+    last_line_height: CGFloat,
+    /// Top and height of the first line of the current block.
+    first_line: Option<(CGFloat, CGFloat)>,
+}
+
+impl Layouter {
+    fn finish_line(&mut self, env: &mut Environment) {
+        let line = std::mem::take(&mut self.line);
+        self.line_x = 0.0;
+        let height = line
+            .iter()
+            .map(|fragment| fragment.height)
+            .fold(0.0, CGFloat::max);
+        let height = if height > 0.0 {
+            height
+        } else {
+            self.last_line_height
+        };
+        let line_width = line
+            .last()
+            .map_or(0.0, |last| last.x + last.width - last.trailing_space);
+        let shift = match self.align {
+            Align::Left => 0.0,
+            Align::Center => ((self.width - line_width) / 2.0).max(0.0),
+            Align::Right => (self.width - line_width).max(0.0),
+        };
+
+        for fragment in line {
+            // Everything on a line shares the same bottom edge, which is a
+            // rough approximation of sharing a baseline.
+            let rect = CGRect {
+                origin: CGPoint {
+                    x: self.left + shift + fragment.x,
+                    y: self.y + height - fragment.height,
+                },
+                size: CGSize {
+                    width: fragment.width - fragment.trailing_space,
+                    height: fragment.height,
+                },
+            };
+            if let Some(link) = fragment.link {
+                self.links.push((rect, link));
+            }
+            match fragment.kind {
+                FragmentKind::Text {
+                    text,
+                    font,
+                    color,
+                    underline,
+                } => {
+                    let underline = underline.then(|| {
+                        let ascender: CGFloat = msg![env; font ascender];
+                        CGRect {
+                            origin: CGPoint {
+                                x: rect.origin.x,
+                                y: rect.origin.y + ascender + 1.0,
+                            },
+                            size: CGSize {
+                                width: rect.size.width,
+                                height: 1.0,
+                            },
+                        }
+                    });
+                    self.items.push(LaidOutItem::Text {
+                        text,
+                        font,
+                        color,
+                        rect,
+                        underline,
+                    });
+                }
+                FragmentKind::Image(image) => {
+                    if image != nil {
+                        self.items.push(LaidOutItem::Image { image, rect });
+                    }
+                }
+            }
+        }
+
+        self.first_line.get_or_insert((self.y, height));
+        self.last_line_height = height;
+        self.y += height;
+    }
+
+    /// Adds a fragment to the current line, first starting a new line if it
+    /// doesn't fit. `width` excludes any trailing space.
+    fn fit(&mut self, env: &mut Environment, width: CGFloat) {
+        if !self.line.is_empty() && self.line_x + width > self.width {
+            self.finish_line(env);
+        }
+    }
+
+    fn add_text(&mut self, env: &mut Environment, this: id, text: &str, style: &TextStyle) {
+        let font = font_for_style(env, this, style);
+        let space = ui_font::size_with_font(env, font, " ", None);
+        for piece in text.split_inclusive(' ') {
+            let word = piece.trim_end_matches(' ');
+            let trailing_space = (piece.len() - word.len()) as CGFloat * space.width;
+            let size = if word.is_empty() {
+                space
+            } else {
+                ui_font::size_with_font(env, font, word, None)
+            };
+            let word_width = if word.is_empty() { 0.0 } else { size.width };
+            self.fit(env, word_width);
+            // Spaces at the start of a line are dropped.
+            if self.line.is_empty() && word.is_empty() {
+                continue;
+            }
+
+            let advance = word_width + trailing_space;
+            if let Some(Fragment {
+                kind:
+                    FragmentKind::Text {
+                        text,
+                        font: last_font,
+                        color,
+                        underline,
+                    },
+                width,
+                trailing_space: last_trailing_space,
+                link,
+                ..
+            }) = self.line.last_mut()
+            {
+                if *last_font == font
+                    && *color == style.color
+                    && *underline == style.underline
+                    && *link == style.link
+                {
+                    text.push_str(piece);
+                    *width += advance;
+                    *last_trailing_space = trailing_space;
+                    self.line_x += advance;
+                    continue;
+                }
+            }
+            self.line.push(Fragment {
+                kind: FragmentKind::Text {
+                    text: piece.to_string(),
+                    font,
+                    color: style.color,
+                    underline: style.underline,
+                },
+                x: self.line_x,
+                width: advance,
+                trailing_space,
+                height: size.height,
+                link: style.link.clone(),
+            });
+            self.line_x += advance;
+        }
+    }
+
+    fn add_image(
+        &mut self,
+        env: &mut Environment,
+        image: id,
+        width: Option<CGFloat>,
+        height: Option<CGFloat>,
+        link: &Option<String>,
+    ) {
+        let natural: CGSize = if image == nil {
+            CGSize {
+                width: 0.0,
+                height: 0.0,
+            }
+        } else {
+            msg![env; image size]
+        };
+        let aspect = |a: CGFloat, b: CGFloat| if b > 0.0 { a / b } else { 1.0 };
+        let (mut width, mut height) = match (width, height) {
+            (Some(width), Some(height)) => (width, height),
+            (Some(width), None) => (width, width * aspect(natural.height, natural.width)),
+            (None, Some(height)) => (height * aspect(natural.width, natural.height), height),
+            (None, None) => (natural.width, natural.height),
+        };
+        if width <= 0.0 || height <= 0.0 {
+            return;
+        }
+        // Images never overflow the page.
+        if width > self.width {
+            height *= self.width / width;
+            width = self.width;
+        }
+        self.fit(env, width);
+        self.line.push(Fragment {
+            kind: FragmentKind::Image(image),
+            x: self.line_x,
+            width,
+            trailing_space: 0.0,
+            height,
+            link: link.clone(),
+        });
+        self.line_x += width;
+    }
+}
+
+/// Lays out the document for the view's current width and updates the
+/// content size.
+fn layout_document(env: &mut Environment, this: id) {
+    let bounds: CGRect = msg![env; this bounds];
+    let document = std::mem::take(
+        &mut env
+            .objc
+            .borrow_mut::<UIWebDocumentViewHostObject>(this)
+            .document,
+    );
+
+    let mut layouter = Layouter {
+        items: Vec::new(),
+        links: Vec::new(),
+        y: BODY_MARGIN,
+        left: BODY_MARGIN,
+        width: bounds.size.width - BODY_MARGIN * 2.0,
+        align: Align::Left,
+        line: Vec::new(),
+        line_x: 0.0,
+        last_line_height: html::DEFAULT_FONT_SIZE * 1.2,
+        first_line: None,
+    };
+
+    for block in &document.blocks {
+        layouter.y += block.margin_top;
+        layouter.left = BODY_MARGIN + block.indent;
+        layouter.width = (bounds.size.width - BODY_MARGIN * 2.0 - block.indent).max(0.0);
+        layouter.align = block.align;
+        layouter.first_line = None;
+
+        if block.rule {
+            layouter.items.push(LaidOutItem::Rule {
+                rect: CGRect {
+                    origin: CGPoint {
+                        x: layouter.left,
+                        y: layouter.y,
+                    },
+                    size: CGSize {
+                        width: layouter.width,
+                        height: 1.0,
+                    },
+                },
+            });
+            layouter.y += 2.0;
+            continue;
+        }
+
+        for inline in &block.inlines {
+            match inline {
+                Inline::Text(text, style) => layouter.add_text(env, this, text, style),
+                &Inline::Image {
+                    ref src,
+                    width,
+                    height,
+                    ref link,
+                } => {
+                    let image = image_for_src(env, this, src);
+                    layouter.add_image(env, image, width, height, link);
+                }
+                Inline::LineBreak => layouter.finish_line(env),
+            }
+        }
+        if !layouter.line.is_empty() || layouter.first_line.is_none() {
+            layouter.finish_line(env);
+        }
+
+        if let (Some((marker, style)), Some((top, height))) = (&block.marker, layouter.first_line) {
+            let font = font_for_style(env, this, style);
+            let size = ui_font::size_with_font(env, font, marker, None);
+            layouter.items.push(LaidOutItem::Text {
+                text: marker.clone(),
+                font,
+                color: style.color,
+                rect: CGRect {
+                    origin: CGPoint {
+                        x: layouter.left - size.width - MARKER_GAP,
+                        y: top + height - size.height,
+                    },
+                    size,
+                },
+                underline: None,
+            });
+        }
+    }
+
+    let content_size = CGSize {
+        width: bounds.size.width,
+        height: layouter.y + BODY_MARGIN,
+    };
+    let host_obj = env.objc.borrow_mut::<UIWebDocumentViewHostObject>(this);
+    host_obj.document = document;
+    host_obj.items = layouter.items;
+    host_obj.links = layouter.links;
+    host_obj.layout_width = Some(bounds.size.width);
+    () = msg![env; this setContentSize:content_size];
+    () = msg![env; this setNeedsDisplay];
+}
+
+/// Replaces the document shown by a document view.
+fn set_document(env: &mut Environment, this: id, document: html::Document, base_path: String) {
+    let host_obj = env.objc.borrow_mut::<UIWebDocumentViewHostObject>(this);
+    let background = document.background;
+    host_obj.document = document;
+    host_obj.base_path = Some(base_path);
+    host_obj.items.clear();
+    host_obj.links.clear();
+    let images = std::mem::take(&mut host_obj.images);
+    for (_, image) in images {
+        release(env, image);
+    }
+
+    let color: id = match background {
+        Some((r, g, b, a)) => {
+            msg_class![env; UIColor colorWithRed:r green:g blue:b alpha:a]
+        }
+        None => msg_class![env; UIColor clearColor],
+    };
+    () = msg![env; this setBackgroundColor:color];
+
+    let origin = CGPoint { x: 0.0, y: 0.0 };
+    () = msg![env; this setContentOffset:origin];
+    layout_document(env, this);
+}
+
+pub const CLASSES: ClassExports = objc_classes! {
+
+(env, this, _cmd);
+
+@implementation UIWebView: UIView
+
++ (id)allocWithZone:(NSZonePtr)_zone {
+    let host_object = Box::<UIWebViewHostObject>::default();
+    env.objc.alloc_object(this, host_object, &mut env.mem)
+}
+
+- (id)initWithFrame:(CGRect)frame {
+    let this: id = msg_super![env; this initWithFrame:frame];
+    create_document_view(env, this);
+    let white: id = msg_class![env; UIColor whiteColor];
+    () = msg![env; this setBackgroundColor:white];
+    this
+}
+
+- (id)initWithCoder:(id)coder {
+    let this: id = msg_super![env; this initWithCoder:coder];
+    create_document_view(env, this);
+    let key = get_static_str(env, "UIScalesPageToFit");
+    let scales_page_to_fit: bool = msg![env; coder decodeBoolForKey:key];
+    env.objc.borrow_mut::<UIWebViewHostObject>(this).scales_page_to_fit = scales_page_to_fit;
+    this
+}
+
+- (())dealloc {
+    cancel_load(env, this);
+    let request = env.objc.borrow::<UIWebViewHostObject>(this).request;
+    release(env, request);
+    msg_super![env; this dealloc]
+}
+
+- (id)delegate {
+    env.objc.borrow::<UIWebViewHostObject>(this).delegate
+}
+- (())setDelegate:(id)delegate {
+    env.objc.borrow_mut::<UIWebViewHostObject>(this).delegate = delegate;
+}
+
+- (id)request {
+    env.objc.borrow::<UIWebViewHostObject>(this).request
+}
+
+- (bool)scalesPageToFit {
+    env.objc.borrow::<UIWebViewHostObject>(this).scales_page_to_fit
+}
+- (())setScalesPageToFit:(bool)scales_page_to_fit {
+    // TODO: Pages are always laid out to fit the view's width, which is
+    // close enough for mobile-friendly pages.
+    env.objc.borrow_mut::<UIWebViewHostObject>(this).scales_page_to_fit = scales_page_to_fit;
+}
+
+- (UIDataDetectorTypes)dataDetectorTypes {
+    env.objc.borrow::<UIWebViewHostObject>(this).data_detector_types
+}
+- (())setDataDetectorTypes:(UIDataDetectorTypes)types {
+    env.objc.borrow_mut::<UIWebViewHostObject>(this).data_detector_types = types;
+}
+- (bool)detectsPhoneNumbers {
+    let types = env.objc.borrow::<UIWebViewHostObject>(this).data_detector_types;
+    types & UIDataDetectorTypePhoneNumber != 0
+}
+- (())setDetectsPhoneNumbers:(bool)detects {
+    let host_obj = env.objc.borrow_mut::<UIWebViewHostObject>(this);
+    if detects {
+        host_obj.data_detector_types |= UIDataDetectorTypePhoneNumber;
+    } else {
+        host_obj.data_detector_types &= !UIDataDetectorTypePhoneNumber;
+    }
+}
+
+- (())loadRequest:(id)request { // NSURLRequest*
+    load_request(env, this, request, UIWebViewNavigationTypeOther);
+}
+
+- (())loadHTMLString:(id)html // NSString*
+             baseURL:(id)base_url { // NSURL*
+    let html: id = msg![env; html copy];
+    retain(env, base_url);
+    schedule_load(env, this, PendingLoad::HTMLString { html, base_url });
+}
+
+- (())reload {
+    let request = env.objc.borrow::<UIWebViewHostObject>(this).request;
+    if request != nil {
+        load_request(env, this, request, UIWebViewNavigationTypeReload);
+    }
+}
+
+- (())stopLoading {
+    cancel_load(env, this);
+}
+
+- (bool)isLoading {
+    env.objc.borrow::<UIWebViewHostObject>(this).pending_load.is_some()
+}
+
+// TODO: history
+- (bool)canGoBack {
+    false
+}
+- (bool)canGoForward {
+    false
+}
+- (())goBack {
+    log!("TODO: [(UIWebView*){:?} goBack]", this);
+}
+- (())goForward {
+    log!("TODO: [(UIWebView*){:?} goForward]", this);
+}
+
+- (id)stringByEvaluatingJavaScriptFromString:(id)script { // NSString*
+    log!(
+        "TODO: [(UIWebView*){:?} stringByEvaluatingJavaScriptFromString:{:?}] => @\"\"",
+        this,
+        to_rust_string(env, script),
+    );
+    get_static_str(env, "")
+}
+
+- (())_touchHLE_finishLoad:(id)_object {
+    let Some(load) = env.objc.borrow_mut::<UIWebViewHostObject>(this).pending_load.take() else {
+        // Cancelled.
+        return;
+    };
+
+    let delegate = env.objc.borrow::<UIWebViewHostObject>(this).delegate;
+    if delegate_responds(env, delegate, "webViewDidStartLoad:") {
+        () = msg![env; delegate webViewDidStartLoad:this];
+    }
+
+    let (source, base_path, request) = match load {
+        PendingLoad::Request(request) => {
+            let Some((source, base_path)) = read_request(env, request) else {
+                did_fail_load(env, this, request);
+                release(env, request);
+                return;
+            };
+            (source, base_path, request)
+        }
+        PendingLoad::HTMLString { html, base_url } => {
+            // Without a base URL, resources come from the app bundle.
+            let is_file_url: bool = base_url != nil && msg![env; base_url isFileURL];
+            let base_path: id = if is_file_url {
+                msg![env; base_url path]
+            } else {
+                let bundle: id = msg_class![env; NSBundle mainBundle];
+                msg![env; bundle bundlePath]
+            };
+            let base_path = to_rust_string(env, base_path).into_owned();
+            let request: id = if base_url != nil {
+                let request: id = msg_class![env; NSURLRequest requestWithURL:base_url];
+                retain(env, request)
+            } else {
+                nil
+            };
+            release(env, base_url);
+            let source = autorelease(env, html);
+            (source, base_path, request)
+        }
+    };
+
+    let old_request = std::mem::replace(
+        &mut env.objc.borrow_mut::<UIWebViewHostObject>(this).request,
+        request,
+    );
+    release(env, old_request);
+
+    let source = if source == nil {
+        String::new()
+    } else {
+        to_rust_string(env, source).into_owned()
+    };
+    let document = html::parse(&source);
+    let document_view = env.objc.borrow::<UIWebViewHostObject>(this).document_view;
+    set_document(env, document_view, document, base_path);
+
+    if delegate_responds(env, delegate, "webViewDidFinishLoad:") {
+        () = msg![env; delegate webViewDidFinishLoad:this];
+    }
+}
+
+@end
+
+// Private class that scrolls and draws the page.
+@implementation _touchHLE_UIWebDocumentView: UIScrollView
+
++ (id)allocWithZone:(NSZonePtr)_zone {
+    let host_object = Box::<UIWebDocumentViewHostObject>::default();
+    env.objc.alloc_object(this, host_object, &mut env.mem)
+}
+
+- (())dealloc {
+    let host_obj = env.objc.borrow_mut::<UIWebDocumentViewHostObject>(this);
+    let fonts = std::mem::take(&mut host_obj.fonts);
+    let images = std::mem::take(&mut host_obj.images);
+    for object in fonts.into_values().chain(images.into_values()) {
+        release(env, object);
+    }
+    msg_super![env; this dealloc]
+}
+
+- (())layoutSubviews {
+    () = msg_super![env; this layoutSubviews];
+    let bounds: CGRect = msg![env; this bounds];
+    let layout_width = env.objc.borrow::<UIWebDocumentViewHostObject>(this).layout_width;
+    if layout_width.is_some_and(|width| width != bounds.size.width) {
+        layout_document(env, this);
+    }
+}
+
+- (())touchesEnded:(id)touches // NSSet* of UITouch*
+         withEvent:(id)event { // UIEvent*
+    let was_dragging: bool = msg![env; this isDragging];
+    () = msg_super![env; this touchesEnded:touches withEvent:event];
+    if was_dragging {
+        return;
+    }
+
+    let touch: id = msg![env; touches anyObject];
+    let point: CGPoint = msg![env; touch locationInView:this];
+    let link = env
+        .objc
+        .borrow::<UIWebDocumentViewHostObject>(this)
+        .links
+        .iter()
+        .find(|(rect, _)| {
+            point.x >= rect.origin.x
+                && point.x < rect.origin.x + rect.size.width
+                && point.y >= rect.origin.y
+                && point.y < rect.origin.y + rect.size.height
+        })
+        .map(|(_, href)| href.clone());
+    if let Some(href) = link {
+        let web_view: id = msg![env; this superview];
+        follow_link(env, web_view, &href);
+    }
+}
+
+- (())drawRect:(CGRect)_rect {
+    let bounds: CGRect = msg![env; this bounds];
+    let context = UIGraphicsGetCurrentContext(env);
+
+    // Only what's currently scrolled into view needs to be drawn.
+    let items = std::mem::take(&mut env.objc.borrow_mut::<UIWebDocumentViewHostObject>(this).items);
+    for item in &items {
+        let rect = item.rect();
+        if rect.origin.y > bounds.origin.y + bounds.size.height
+            || rect.origin.y + rect.size.height < bounds.origin.y
+        {
+            continue;
+        }
+        match *item {
+            LaidOutItem::Text {
+                ref text,
+                font,
+                color: (r, g, b, a),
+                rect,
+                underline,
+            } => {
+                CGContextSetRGBFillColor(env, context, r, g, b, a);
+                ui_font::draw_at_point(env, font, text, rect.origin, None);
+                if let Some(underline) = underline {
+                    CGContextFillRect(env, context, underline);
+                }
+            }
+            LaidOutItem::Image { image, rect } => {
+                () = msg![env; image drawInRect:rect];
+            }
+            LaidOutItem::Rule { rect } => {
+                CGContextSetRGBFillColor(env, context, 0.5, 0.5, 0.5, 1.0);
+                CGContextFillRect(env, context, rect);
+            }
+        }
+    }
+    env.objc.borrow_mut::<UIWebDocumentViewHostObject>(this).items = items;
+}
+
+@end
+
+};
@@ -0,0 +1,1080 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! A very small HTML parser for `UIWebView`.
+//!
+//! Apps mostly use `UIWebView` for help screens, credits and the like, which
+//! are simple bundled HTML files. This turns such a file into a flat list of
+//! blocks of styled text and images, which `UIWebView` then lays out. There is
+//! no DOM and no JavaScript, and only a handful of CSS properties are
+//! understood. Anything unknown is ignored rather than being an error, since
+//! real-world HTML is rarely well-formed.
+
+/// Red, green, blue and alpha, each from 0 to 1.
+pub type Rgba = (f32, f32, f32, f32);
+
+pub const DEFAULT_FONT_SIZE: f32 = 16.0;
+const BLACK: Rgba = (0.0, 0.0, 0.0, 1.0);
+const LINK_BLUE: Rgba = (0.0, 0.0, 0.93, 1.0);
+/// Indentation of list items and block quotes.
+const INDENT: f32 = 40.0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextStyle {
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub color: Rgba,
+    pub font_size: f32,
+    /// The `href` of the enclosing link, if any.
+    pub link: Option<String>,
+}
+impl Default for TextStyle {
+    fn default() -> Self {
+        TextStyle {
+            bold: false,
+            italic: false,
+            underline: false,
+            color: BLACK,
+            font_size: DEFAULT_FONT_SIZE,
+            link: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Inline {
+    /// A run of text in a single style. Whitespace is already collapsed.
+    Text(String, TextStyle),
+    Image {
+        src: String,
+        width: Option<f32>,
+        height: Option<f32>,
+        link: Option<String>,
+    },
+    LineBreak,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Block {
+    pub inlines: Vec<Inline>,
+    pub align: Align,
+    /// Left indentation in pixels, e.g. for list items.
+    pub indent: f32,
+    /// Vertical space before the block, already collapsed with the margins
+    /// of any neighbouring blocks.
+    pub margin_top: f32,
+    /// List item bullet or number, drawn to the left of the first line.
+    pub marker: Option<(String, TextStyle)>,
+    /// This block is a horizontal rule (`<hr>`) and has no content.
+    pub rule: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Document {
+    pub title: Option<String>,
+    pub background: Option<Rgba>,
+    pub blocks: Vec<Block>,
+}
+
+/// Parse an HTML document.
+pub fn parse(html: &str) -> Document {
+    let mut builder = Builder::new();
+    for token in tokenize(html) {
+        builder.token(token);
+    }
+    builder.finish()
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Text(String),
+    Open {
+        name: String,
+        attrs: Vec<(String, String)>,
+    },
+    Close(String),
+}
+
+/// Elements which never have content or a closing tag.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "wbr",
+];
+
+/// Elements whose content is not HTML and must not be tokenized.
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style", "textarea", "title"];
+
+fn tokenize(html: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut rest = html;
+    while !rest.is_empty() {
+        let Some(lt) = rest.find('<') else {
+            tokens.push(Token::Text(decode_entities(rest)));
+            break;
+        };
+        if lt > 0 {
+            tokens.push(Token::Text(decode_entities(&rest[..lt])));
+            rest = &rest[lt..];
+        }
+
+        // Comments, doctypes and processing instructions are skipped.
+        if let Some(after) = rest.strip_prefix("<!--") {
+            rest = after.find("-->").map_or("", move |end| &after[end + 3..]);
+            continue;
+        }
+        if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = rest.find('>').map_or("", move |end| &rest[end + 1..]);
+            continue;
+        }
+
+        let closing = rest.starts_with("</");
+        let name_start = if closing { 2 } else { 1 };
+        let name_len = rest[name_start..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == ':'))
+            .unwrap_or(rest.len() - name_start);
+        if name_len == 0 {
+            // A stray '<' is just text.
+            tokens.push(Token::Text("<".to_string()));
+            rest = &rest[1..];
+            continue;
+        }
+        let name = rest[name_start..name_start + name_len].to_ascii_lowercase();
+        rest = &rest[name_start + name_len..];
+
+        let (attrs, after) = parse_attributes(rest);
+        rest = after;
+
+        if closing {
+            tokens.push(Token::Close(name));
+            continue;
+        }
+
+        let raw = RAW_TEXT_ELEMENTS.contains(&name.as_str());
+        tokens.push(Token::Open {
+            name: name.clone(),
+            attrs,
+        });
+        if raw {
+            let end_tag = format!("</{}", name);
+            let end = find_ignore_ascii_case(rest, &end_tag).unwrap_or(rest.len());
+            let text = &rest[..end];
+            tokens.push(Token::Text(if name == "title" || name == "textarea" {
+                decode_entities(text)
+            } else {
+                text.to_string()
+            }));
+            rest = &rest[end..];
+            rest = rest.find('>').map_or("", move |end| &rest[end + 1..]);
+            tokens.push(Token::Close(name));
+        }
+    }
+    tokens
+}
+
+fn find_ignore_ascii_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+/// Parses the attributes of a tag, up to and including the closing `>`.
+/// Returns the attributes and the remaining input.
+fn parse_attributes(mut rest: &str) -> (Vec<(String, String)>, &str) {
+    let mut attrs = Vec::new();
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            return (attrs, rest);
+        }
+        if let Some(after) = rest.strip_prefix('>') {
+            return (attrs, after);
+        }
+        if let Some(after) = rest.strip_prefix("/>") {
+            return (attrs, after);
+        }
+        if rest.starts_with('/') {
+            rest = &rest[1..];
+            continue;
+        }
+
+        let name_len = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '>' || c == '/')
+            .unwrap_or(rest.len());
+        let name = rest[..name_len].to_ascii_lowercase();
+        rest = rest[name_len..].trim_start();
+
+        let value = if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            if let Some(quote) = after.chars().next().filter(|&c| c == '"' || c == '\'') {
+                let after = &after[1..];
+                let end = after.find(quote).unwrap_or(after.len());
+                rest = after.get(end + 1..).unwrap_or("");
+                decode_entities(&after[..end])
+            } else {
+                let end = after
+                    .find(|c: char| c.is_whitespace() || c == '>')
+                    .unwrap_or(after.len());
+                rest = &after[end..];
+                decode_entities(&after[..end])
+            }
+        } else {
+            String::new()
+        };
+        if !name.is_empty() {
+            attrs.push((name, value));
+        }
+    }
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|&len| len <= 10)
+            .and_then(|len| decode_entity(&rest[1..1 + len]).map(|c| (c, len + 2)));
+        if let Some((c, len)) = entity {
+            decoded.push(c);
+            rest = &rest[len..];
+        } else {
+            decoded.push('&');
+            rest = &rest[1..];
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    if let Some(number) = name.strip_prefix('#') {
+        let code = if let Some(hex) = number.strip_prefix(['x', 'X']) {
+            u32::from_str_radix(hex, 16).ok()?
+        } else {
+            number.parse().ok()?
+        };
+        return char::from_u32(code);
+    }
+    Some(match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{A0}',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "hellip" => '…',
+        "mdash" => '—',
+        "ndash" => '–',
+        "bull" => '•',
+        "middot" => '·',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "laquo" => '«',
+        "raquo" => '»',
+        "deg" => '°',
+        "times" => '×',
+        "eacute" => 'é',
+        "egrave" => 'è',
+        "agrave" => 'à',
+        "uuml" => 'ü',
+        "ouml" => 'ö',
+        "auml" => 'ä',
+        "szlig" => 'ß',
+        _ => return None,
+    })
+}
+
+/// A simple CSS selector: `tag`, `.class`, `tag.class` or `#id`.
+#[derive(Debug, Default)]
+struct Selector {
+    tag: Option<String>,
+    class: Option<String>,
+    id: Option<String>,
+}
+impl Selector {
+    fn parse(selector: &str) -> Option<Selector> {
+        let selector = selector.trim().to_ascii_lowercase();
+        // Links are never visited here, so these are as good as plain `a`.
+        let selector = selector
+            .strip_suffix(":link")
+            .or_else(|| selector.strip_suffix(":visited"))
+            .unwrap_or(selector.as_str());
+        // Combinators, pseudo-classes and attribute selectors aren't
+        // supported. Skipping the rule is better than misapplying it.
+        if selector.is_empty()
+            || selector.contains(|c: char| c.is_whitespace() || ">+~:[*".contains(c))
+        {
+            return None;
+        }
+        let mut parsed = Selector::default();
+        if let Some(id) = selector.strip_prefix('#') {
+            parsed.id = Some(id.to_string());
+        } else if let Some((tag, class)) = selector.split_once('.') {
+            parsed.tag = (!tag.is_empty()).then(|| tag.to_string());
+            parsed.class = Some(class.to_string());
+        } else {
+            parsed.tag = Some(selector.to_string());
+        }
+        Some(parsed)
+    }
+
+    fn matches(&self, name: &str, attrs: &[(String, String)]) -> bool {
+        let attr = |key: &str| attrs.iter().find(|(k, _)| k == key).map(|(_, v)| v);
+        if let Some(tag) = &self.tag {
+            if tag != name {
+                return false;
+            }
+        }
+        if let Some(class) = &self.class {
+            let has_class = attr("class").is_some_and(|classes| {
+                classes
+                    .split_whitespace()
+                    .any(|c| c.eq_ignore_ascii_case(class))
+            });
+            if !has_class {
+                return false;
+            }
+        }
+        if let Some(id) = &self.id {
+            if !attr("id").is_some_and(|v| v.eq_ignore_ascii_case(id)) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+type Declarations = Vec<(String, String)>;
+
+fn parse_declarations(style: &str) -> Declarations {
+    style
+        .split(';')
+        .filter_map(|declaration| {
+            let (property, value) = declaration.split_once(':')?;
+            let value = value.trim().trim_end_matches("!important").trim();
+            Some((property.trim().to_ascii_lowercase(), value.to_string()))
+        })
+        .collect()
+}
+
+fn parse_stylesheet(css: &str) -> Vec<(Selector, Declarations)> {
+    let mut rules = Vec::new();
+    let mut rest = css;
+    // Comments are stripped first so that they can't hide braces.
+    let mut stripped = String::new();
+    while let Some(start) = rest.find("/*") {
+        stripped.push_str(&rest[..start]);
+        rest = rest[start..]
+            .find("*/")
+            .map_or("", move |end| &rest[start + end + 2..]);
+    }
+    stripped.push_str(rest);
+
+    let mut rest = stripped.as_str();
+    while let Some(open) = rest.find('{') {
+        let selectors = &rest[..open];
+        let Some(close) = rest[open..].find('}') else {
+            break;
+        };
+        let body = &rest[open + 1..open + close];
+        rest = &rest[open + close + 1..];
+        // At-rules like @media are skipped, along with everything in them.
+        if selectors.trim_start().starts_with('@') {
+            if body.contains('{') {
+                rest = rest.find('}').map_or("", move |end| &rest[end + 1..]);
+            }
+            continue;
+        }
+        let declarations = parse_declarations(body);
+        for selector in selectors.split(',') {
+            if let Some(selector) = Selector::parse(selector) {
+                rules.push((selector, declarations.clone()));
+            }
+        }
+    }
+    rules
+}
+
+fn named_color(name: &str) -> Option<Rgba> {
+    let (r, g, b) = match name {
+        "black" => (0, 0, 0),
+        "white" => (255, 255, 255),
+        "red" => (255, 0, 0),
+        "green" => (0, 128, 0),
+        "lime" => (0, 255, 0),
+        "blue" => (0, 0, 255),
+        "yellow" => (255, 255, 0),
+        "orange" => (255, 165, 0),
+        "purple" => (128, 0, 128),
+        "gray" | "grey" => (128, 128, 128),
+        "silver" => (192, 192, 192),
+        "maroon" => (128, 0, 0),
+        "navy" => (0, 0, 128),
+        "teal" => (0, 128, 128),
+        "olive" => (128, 128, 0),
+        "aqua" | "cyan" => (0, 255, 255),
+        "fuchsia" | "magenta" => (255, 0, 255),
+        "brown" => (165, 42, 42),
+        "pink" => (255, 192, 203),
+        "gold" => (255, 215, 0),
+        "darkgray" | "darkgrey" => (169, 169, 169),
+        "lightgray" | "lightgrey" => (211, 211, 211),
+        "transparent" => return Some((0.0, 0.0, 0.0, 0.0)),
+        _ => return None,
+    };
+    Some((r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, 1.0))
+}
+
+fn parse_color(color: &str) -> Option<Rgba> {
+    let color = color.trim().to_ascii_lowercase();
+    if let Some(args) = color
+        .strip_prefix("rgba(")
+        .or_else(|| color.strip_prefix("rgb("))
+    {
+        let args: Vec<f32> = args
+            .trim_end_matches(')')
+            .split(',')
+            .map(|arg| arg.trim().parse())
+            .collect::<Result<_, _>>()
+            .ok()?;
+        return match args[..] {
+            [r, g, b] => Some((r / 255.0, g / 255.0, b / 255.0, 1.0)),
+            [r, g, b, a] => Some((r / 255.0, g / 255.0, b / 255.0, a)),
+            _ => None,
+        };
+    }
+    // Legacy attributes often omit the '#'.
+    let hex = color.strip_prefix('#').unwrap_or(&color);
+    if hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        let digits: Vec<u32> = hex.chars().map(|c| c.to_digit(16).unwrap()).collect();
+        let (r, g, b) = match digits[..] {
+            [r, g, b] => (r * 17, g * 17, b * 17),
+            [r1, r2, g1, g2, b1, b2] => (r1 * 16 + r2, g1 * 16 + g2, b1 * 16 + b2),
+            _ => return None,
+        };
+        return Some((r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, 1.0));
+    }
+    named_color(&color)
+}
+
+/// Parses a CSS length, resolving relative units against `parent`.
+fn parse_length(value: &str, parent: f32) -> Option<f32> {
+    let value = value.trim().to_ascii_lowercase();
+    let (number, factor) = if let Some(n) = value.strip_suffix("px") {
+        (n, 1.0)
+    } else if let Some(n) = value.strip_suffix("pt") {
+        (n, 4.0 / 3.0)
+    } else if let Some(n) = value.strip_suffix("em") {
+        (n, parent)
+    } else if let Some(n) = value.strip_suffix('%') {
+        (n, parent / 100.0)
+    } else {
+        (value.as_str(), 1.0)
+    };
+    number.trim().parse::<f32>().ok().map(|n| n * factor)
+}
+
+fn parse_font_size(value: &str, parent: f32) -> Option<f32> {
+    Some(match value.trim() {
+        "xx-small" => 9.0,
+        "x-small" => 10.0,
+        "small" => 13.0,
+        "medium" => 16.0,
+        "large" => 18.0,
+        "x-large" => 24.0,
+        "xx-large" => 32.0,
+        "smaller" => parent / 1.2,
+        "larger" => parent * 1.2,
+        other => parse_length(other, parent)?,
+    })
+}
+
+/// Font size for the legacy `<font size="...">` attribute.
+fn legacy_font_size(value: &str) -> Option<f32> {
+    const SIZES: [f32; 7] = [10.0, 13.0, 16.0, 18.0, 24.0, 32.0, 48.0];
+    let value = value.trim();
+    let size: i32 = if let Some(relative) = value.strip_prefix('+') {
+        3 + relative.parse::<i32>().ok()?
+    } else if value.starts_with('-') {
+        3 + value.parse::<i32>().ok()?
+    } else {
+        value.parse().ok()?
+    };
+    Some(SIZES[(size.clamp(1, 7) - 1) as usize])
+}
+
+fn parse_align(value: &str) -> Option<Align> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "left" | "justify" => Some(Align::Left),
+        "center" | "middle" => Some(Align::Center),
+        "right" => Some(Align::Right),
+        _ => None,
+    }
+}
+
+/// An open element and the styles it applies to its content.
+#[derive(Clone, Debug)]
+struct Element {
+    name: String,
+    style: TextStyle,
+    align: Align,
+    indent: f32,
+    hidden: bool,
+    preformatted: bool,
+    /// For `<ol>`, the number of the last list item.
+    counter: Option<u32>,
+}
+
+fn is_block(name: &str) -> bool {
+    matches!(
+        name,
+        "address"
+            | "article"
+            | "aside"
+            | "blockquote"
+            | "body"
+            | "caption"
+            | "center"
+            | "dd"
+            | "div"
+            | "dl"
+            | "dt"
+            | "fieldset"
+            | "footer"
+            | "form"
+            | "h1"
+            | "h2"
+            | "h3"
+            | "h4"
+            | "h5"
+            | "h6"
+            | "header"
+            | "hr"
+            | "html"
+            | "li"
+            | "nav"
+            | "ol"
+            | "p"
+            | "pre"
+            | "section"
+            | "table"
+            | "tr"
+            | "ul"
+    )
+}
+
+/// The font size and top/bottom margin (in ems) of a heading.
+fn heading(name: &str) -> Option<(f32, f32)> {
+    Some(match name {
+        "h1" => (2.0, 0.67),
+        "h2" => (1.5, 0.83),
+        "h3" => (1.17, 1.0),
+        "h4" => (1.0, 1.33),
+        "h5" => (0.83, 1.67),
+        "h6" => (0.67, 2.33),
+        _ => return None,
+    })
+}
+
+struct Builder {
+    document: Document,
+    stylesheet: Vec<(Selector, Declarations)>,
+    stack: Vec<Element>,
+    /// Block currently receiving content. It is only created once there's
+    /// some content, so that empty elements don't take up space.
+    current: Option<Block>,
+    /// Largest margin seen since the last block with content.
+    pending_margin: f32,
+    pending_marker: Option<(String, TextStyle)>,
+    /// Collapsed whitespace that should become a space if more text follows
+    /// on the same line.
+    pending_space: bool,
+    link_color: Rgba,
+    /// Name of the raw text element being read, e.g. `style`.
+    raw_text: Option<String>,
+}
+
+impl Builder {
+    fn new() -> Builder {
+        Builder {
+            document: Document::default(),
+            stylesheet: Vec::new(),
+            stack: vec![Element {
+                name: String::new(),
+                style: TextStyle::default(),
+                align: Align::Left,
+                indent: 0.0,
+                hidden: false,
+                preformatted: false,
+                counter: None,
+            }],
+            current: None,
+            pending_margin: 0.0,
+            pending_marker: None,
+            pending_space: false,
+            link_color: LINK_BLUE,
+            raw_text: None,
+        }
+    }
+
+    fn top(&self) -> &Element {
+        self.stack.last().unwrap()
+    }
+
+    fn token(&mut self, token: Token) {
+        match token {
+            Token::Text(text) => match self.raw_text.as_deref() {
+                Some("style") => {
+                    let rules = parse_stylesheet(&text);
+                    self.stylesheet.extend(rules);
+                }
+                Some("title") => {
+                    let title = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                    self.document.title = Some(title);
+                }
+                Some(_) => (),
+                None => self.text(&text),
+            },
+            Token::Open { name, attrs } => self.open(name, attrs),
+            Token::Close(name) => self.close(&name),
+        }
+    }
+
+    fn open(&mut self, name: String, attrs: Vec<(String, String)>) {
+        if RAW_TEXT_ELEMENTS.contains(&name.as_str()) {
+            self.raw_text = Some(name);
+            return;
+        }
+
+        // Some end tags are optional.
+        let name_str = name.as_str();
+        match name_str {
+            "p" | "li" | "dt" | "dd" | "tr" | "td" | "th" => {
+                let boundaries: &[&str] = match name_str {
+                    "li" => &["ul", "ol"],
+                    "dt" | "dd" => &["dl"],
+                    "tr" => &["table"],
+                    "td" | "th" => &["tr", "table"],
+                    _ => &["div", "td", "li", "blockquote", "body"],
+                };
+                let siblings: &[&str] = match name_str {
+                    "dt" | "dd" => &["dt", "dd"],
+                    "td" | "th" => &["td", "th"],
+                    _ => std::slice::from_ref(&name_str),
+                };
+                let open = self
+                    .stack
+                    .iter()
+                    .rev()
+                    .take_while(|e| !boundaries.contains(&e.name.as_str()))
+                    .position(|e| siblings.contains(&e.name.as_str()));
+                if let Some(depth) = open {
+                    let len = self.stack.len();
+                    self.stack.truncate(len - depth - 1);
+                    self.end_block(0.0);
+                }
+            }
+            _ => (),
+        }
+        let attr = |key: &str| {
+            attrs
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.as_str())
+        };
+
+        let parent = self.top().clone();
+        let mut element = Element {
+            name: name.clone(),
+            counter: None,
+            ..parent.clone()
+        };
+        let mut margin = 0.0;
+
+        match name.as_str() {
+            "b" | "strong" | "th" | "dt" => element.style.bold = true,
+            "i" | "em" | "cite" | "var" | "dfn" => element.style.italic = true,
+            "u" | "ins" => element.style.underline = true,
+            "big" => element.style.font_size *= 1.2,
+            "small" | "sub" | "sup" => element.style.font_size /= 1.2,
+            "center" => element.align = Align::Center,
+            "pre" => {
+                element.preformatted = true;
+                margin = element.style.font_size;
+            }
+            "p" | "blockquote" | "dl" => margin = element.style.font_size,
+            "ul" | "ol" => {
+                element.indent += INDENT;
+                if !self.stack.iter().any(|e| e.name == "ul" || e.name == "ol") {
+                    margin = element.style.font_size;
+                }
+                if name == "ol" {
+                    let start: u32 = attr("start").and_then(|s| s.parse().ok()).unwrap_or(1);
+                    element.counter = Some(start.saturating_sub(1));
+                }
+            }
+            "dd" => element.indent += INDENT,
+            "a" => {
+                if let Some(href) = attr("href") {
+                    element.style.link = Some(href.to_string());
+                    element.style.color = self.link_color;
+                    element.style.underline = true;
+                }
+            }
+            "font" => {
+                if let Some(color) = attr("color").and_then(parse_color) {
+                    element.style.color = color;
+                }
+                if let Some(size) = attr("size").and_then(legacy_font_size) {
+                    element.style.font_size = size;
+                }
+            }
+            "body" => {
+                if let Some(color) = attr("bgcolor").and_then(parse_color) {
+                    self.document.background = Some(color);
+                }
+                if let Some(color) = attr("text").and_then(parse_color) {
+                    element.style.color = color;
+                }
+                if let Some(color) = attr("link").and_then(parse_color) {
+                    self.link_color = color;
+                }
+            }
+            _ => (),
+        }
+        if name == "blockquote" {
+            element.indent += INDENT;
+        }
+        if let Some((size, heading_margin)) = heading(&name) {
+            element.style.font_size *= size;
+            element.style.bold = true;
+            margin = element.style.font_size * heading_margin;
+        }
+        if name == "li" {
+            let style = element.style.clone();
+            let list = self
+                .stack
+                .iter_mut()
+                .rev()
+                .find(|e| e.name == "ul" || e.name == "ol");
+            let marker = match list.and_then(|list| list.counter.as_mut()) {
+                Some(counter) => {
+                    *counter += 1;
+                    format!("{}.", counter)
+                }
+                None => "•".to_string(),
+            };
+            self.pending_marker = Some((marker, style));
+        }
+        if let Some(align) = attr("align").and_then(parse_align) {
+            if is_block(&name) || matches!(name.as_str(), "td" | "th") {
+                element.align = align;
+            }
+        }
+        if attr("hidden").is_some() {
+            element.hidden = true;
+        }
+
+        let mut declarations: Declarations = self
+            .stylesheet
+            .iter()
+            .filter(|(selector, _)| selector.matches(&name, &attrs))
+            .flat_map(|(_, declarations)| declarations.iter().cloned())
+            .collect();
+        if let Some(style) = attr("style") {
+            declarations.extend(parse_declarations(style));
+        }
+        for (property, value) in declarations {
+            self.apply_declaration(&mut element, &parent, &property, &value);
+        }
+
+        if is_block(&name) {
+            self.end_block(margin);
+        }
+        match name.as_str() {
+            "br" if !self.top().hidden => {
+                self.block().inlines.push(Inline::LineBreak);
+                self.pending_space = false;
+            }
+            "hr" if !self.top().hidden => {
+                let margin = self.pending_margin.max(8.0);
+                let indent = self.top().indent;
+                self.document.blocks.push(Block {
+                    inlines: Vec::new(),
+                    align: Align::Left,
+                    indent,
+                    margin_top: margin,
+                    marker: None,
+                    rule: true,
+                });
+                self.pending_margin = 8.0;
+            }
+            "img" => {
+                if let (false, Some(src)) = (element.hidden, attr("src")) {
+                    let size = |key| attr(key).and_then(|v| parse_length(v, 0.0));
+                    let inline = Inline::Image {
+                        src: src.to_string(),
+                        width: size("width"),
+                        height: size("height"),
+                        link: element.style.link.clone(),
+                    };
+                    self.flush_space();
+                    self.block().inlines.push(inline);
+                }
+            }
+            "td" | "th" => {
+                // Table cells are laid out as runs of text on one line.
+                self.pending_space = true;
+            }
+            _ => (),
+        }
+
+        if !VOID_ELEMENTS.contains(&name.as_str()) {
+            self.stack.push(element);
+        }
+    }
+
+    fn apply_declaration(
+        &mut self,
+        element: &mut Element,
+        parent: &Element,
+        property: &str,
+        value: &str,
+    ) {
+        let lower = value.to_ascii_lowercase();
+        match property {
+            "color" => {
+                if let Some(color) = parse_color(value) {
+                    element.style.color = color;
+                }
+            }
+            "background-color" | "background" if element.name == "body" => {
+                if let Some(color) = value.split_whitespace().find_map(parse_color) {
+                    self.document.background = Some(color);
+                }
+            }
+            "font-size" => {
+                if let Some(size) = parse_font_size(&lower, parent.style.font_size) {
+                    element.style.font_size = size;
+                }
+            }
+            "font-weight" => {
+                element.style.bold = match lower.as_str() {
+                    "bold" | "bolder" => true,
+                    "normal" | "lighter" => false,
+                    number => number
+                        .parse::<u32>()
+                        .map_or(element.style.bold, |n| n >= 600),
+                }
+            }
+            "font-style" => element.style.italic = lower == "italic" || lower == "oblique",
+            "text-decoration" => element.style.underline = lower.contains("underline"),
+            "text-align" => {
+                if let Some(align) = parse_align(&lower) {
+                    element.align = align;
+                }
+            }
+            "display" => element.hidden = lower == "none",
+            "visibility" => element.hidden = lower == "hidden",
+            "white-space" => element.preformatted = lower == "pre" || lower == "pre-wrap",
+            _ => (),
+        }
+    }
+
+    fn close(&mut self, name: &str) {
+        if self.raw_text.as_deref() == Some(name) {
+            self.raw_text = None;
+            return;
+        }
+        // Unmatched end tags are ignored. The root element is never popped.
+        let Some(depth) = self.stack[1..].iter().rev().position(|e| e.name == name) else {
+            return;
+        };
+        let len = self.stack.len();
+        let element = self.stack[len - depth - 1].clone();
+        self.stack.truncate(len - depth - 1);
+        if is_block(name) {
+            let margin = match name {
+                "p" | "blockquote" | "dl" | "pre" => element.style.font_size,
+                "ul" | "ol" if !self.stack.iter().any(|e| e.name == "ul" || e.name == "ol") => {
+                    element.style.font_size
+                }
+                _ => heading(name).map_or(0.0, |(_, margin)| element.style.font_size * margin),
+            };
+            self.end_block(margin);
+        }
+    }
+
+    /// Ends the current block, if any, and adds a margin before the next.
+    fn end_block(&mut self, margin: f32) {
+        if let Some(block) = self.current.take() {
+            self.document.blocks.push(block);
+        }
+        self.pending_margin = self.pending_margin.max(margin);
+        self.pending_space = false;
+    }
+
+    /// Gets the current block, starting a new one if needed.
+    fn block(&mut self) -> &mut Block {
+        if self.current.is_none() {
+            let &Element { align, indent, .. } = self.top();
+            let block = Block {
+                inlines: Vec::new(),
+                align,
+                indent,
+                margin_top: std::mem::take(&mut self.pending_margin),
+                marker: self.pending_marker.take(),
+                rule: false,
+            };
+            self.current = Some(block);
+        }
+        self.current.as_mut().unwrap()
+    }
+
+    fn push_char(&mut self, c: char) {
+        let style = self.top().style.clone();
+        let inlines = &mut self.block().inlines;
+        match inlines.last_mut() {
+            Some(Inline::Text(text, last_style)) if *last_style == style => text.push(c),
+            _ => inlines.push(Inline::Text(c.to_string(), style)),
+        }
+    }
+
+    /// Emits a collapsed space if there's anything on the line before it.
+    fn flush_space(&mut self) {
+        if !std::mem::take(&mut self.pending_space) {
+            return;
+        }
+        // The space belongs to the preceding text, so that e.g. it isn't
+        // underlined if a link follows.
+        match self
+            .current
+            .as_mut()
+            .and_then(|block| block.inlines.last_mut())
+        {
+            Some(Inline::Text(text, _)) => text.push(' '),
+            Some(Inline::Image { .. }) => self.push_char(' '),
+            Some(Inline::LineBreak) | None => (),
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.top().hidden {
+            return;
+        }
+        if self.top().preformatted {
+            for c in text.chars() {
+                match c {
+                    '\n' => self.block().inlines.push(Inline::LineBreak),
+                    '\r' => (),
+                    '\t' => (0..4).for_each(|_| self.push_char(' ')),
+                    c => self.push_char(c),
+                }
+            }
+            return;
+        }
+        for c in text.chars() {
+            // Non-breaking spaces are not collapsed.
+            if c.is_whitespace() && c != '\u{A0}' {
+                self.pending_space = true;
+            } else {
+                self.flush_space();
+                self.push_char(c);
+            }
+        }
+    }
+
+    fn finish(mut self) -> Document {
+        self.end_block(0.0);
+        self.document
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(block: &Block) -> String {
+        block
+            .inlines
+            .iter()
+            .map(|inline| match inline {
+                Inline::Text(text, _) => text.as_str(),
+                Inline::Image { .. } => "[img]",
+                Inline::LineBreak => "\n",
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let document = parse(
+            "<!DOCTYPE html><html><head><title>Help</title>\
+             <style>.note { color: #f00; text-align: center }</style></head>\
+             <body><h1>How  to\n play</h1>\
+             <p>Tap <b>here</b> &amp; <a href=\"more.html\">there</a>.<br>Done\
+             <p class=note>Note<!-- hidden --></p>\
+             <ul><li>One<li>Two</ul><ol><li>First</ol>\
+             <img src=\"logo.png\" width=\"100\"></body></html>",
+        );
+        assert_eq!(document.title.as_deref(), Some("Help"));
+
+        let texts: Vec<String> = document.blocks.iter().map(text).collect();
+        assert_eq!(
+            texts,
+            [
+                "How to play",
+                "Tap here & there.\nDone",
+                "Note",
+                "One",
+                "Two",
+                "First",
+                "[img]"
+            ]
+        );
+
+        let heading = &document.blocks[0];
+        let Inline::Text(_, style) = &heading.inlines[0] else {
+            panic!();
+        };
+        assert!(style.bold);
+        assert_eq!(style.font_size, 32.0);
+
+        let paragraph = &document.blocks[1];
+        assert_eq!(paragraph.margin_top, 32.0 * 0.67);
+        let Inline::Text(text, style) = &paragraph.inlines[3] else {
+            panic!();
+        };
+        assert_eq!(text, "there");
+        assert_eq!(style.link.as_deref(), Some("more.html"));
+
+        let note = &document.blocks[2];
+        assert_eq!(note.align, Align::Center);
+        let Inline::Text(_, style) = &note.inlines[0] else {
+            panic!();
+        };
+        assert_eq!(style.color, (1.0, 0.0, 0.0, 1.0));
+
+        assert_eq!(document.blocks[4].marker.as_ref().unwrap().0, "•");
+        assert_eq!(document.blocks[5].marker.as_ref().unwrap().0, "1.");
+        assert_eq!(document.blocks[5].indent, INDENT);
+    }
+}
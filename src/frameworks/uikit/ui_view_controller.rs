@@ -5,15 +5,28 @@
  */
 //! `UIViewController`.
 
-use crate::frameworks::foundation::ns_string::get_static_str;
+use crate::frameworks::core_graphics::CGRect;
+use crate::frameworks::foundation::ns_string::{get_static_str, to_rust_string};
+use crate::frameworks::foundation::NSTimeInterval;
+use crate::frameworks::uikit::ui_device::{UIDeviceOrientation, UIDeviceOrientationPortrait};
 use crate::frameworks::uikit::ui_view::set_view_controller;
+use crate::frameworks::uikit::ui_view::ui_alert_view::window_for_presenting;
 use crate::objc::{
     id, msg, msg_class, nil, objc_classes, release, retain, ClassExports, HostObject, NSZonePtr,
 };
 
 #[derive(Default)]
-struct UIViewControllerHostObject {
+pub struct UIViewControllerHostObject {
     view: id,
+    /// `NSString*`
+    nib_name: id,
+    /// `NSBundle*`
+    nib_bundle: id,
+    /// `UIViewController*` presented by this one with
+    /// `presentModalViewController:animated:`. Strong reference.
+    modal_view_controller: id,
+    /// `UIViewController*` that presented this one. Weak reference.
+    parent_view_controller: id,
 }
 impl HostObject for UIViewControllerHostObject {}
 
@@ -28,29 +41,87 @@ pub const CLASSES: ClassExports = objc_classes! {
     env.objc.alloc_object(this, host_object, &mut env.mem)
 }
 
+- (id)init {
+    msg![env; this initWithNibName:nil bundle:nil]
+}
+
+- (id)initWithNibName:(id)nib_name // NSString*
+               bundle:(id)nib_bundle { // NSBundle*
+    let nib_name: id = msg![env; nib_name copy];
+    retain(env, nib_bundle);
+    let host_obj = env.objc.borrow_mut::<UIViewControllerHostObject>(this);
+    host_obj.nib_name = nib_name;
+    host_obj.nib_bundle = nib_bundle;
+    this
+}
+
 - (id)initWithCoder:(id)coder {
     let key_ns_string = get_static_str(env, "UIView");
     let view: id = msg![env; coder decodeObjectForKey:key_ns_string];
 
     () = msg![env; this setView:view];
 
+    // A view controller in one nib file can have its view in another.
+    let key_ns_string = get_static_str(env, "UINibName");
+    let nib_name: id = msg![env; coder decodeObjectForKey:key_ns_string];
+    retain(env, nib_name);
+    env.objc.borrow_mut::<UIViewControllerHostObject>(this).nib_name = nib_name;
+
     this
 }
 
 - (())dealloc {
-    let &UIViewControllerHostObject { view } = env.objc.borrow(this);
+    let &UIViewControllerHostObject {
+        view,
+        nib_name,
+        nib_bundle,
+        modal_view_controller,
+        ..
+    } = env.objc.borrow(this);
 
     release(env, view);
+    release(env, nib_name);
+    release(env, nib_bundle);
+    release(env, modal_view_controller);
 
     env.objc.dealloc_object(this, &mut env.mem);
 }
 
+- (id)nibName {
+    env.objc.borrow::<UIViewControllerHostObject>(this).nib_name
+}
+- (id)nibBundle {
+    env.objc.borrow::<UIViewControllerHostObject>(this).nib_bundle
+}
+
 - (())loadView {
-    // TODO: Check if the UIViewController has an associated nib file and load
-    // the view from there instead if it does
+    let &UIViewControllerHostObject {
+        nib_name,
+        nib_bundle,
+        ..
+    } = env.objc.borrow(this);
+    if nib_name != nil {
+        let nib_bundle: id = if nib_bundle == nil {
+            msg_class![env; NSBundle mainBundle]
+        } else {
+            nib_bundle
+        };
+        // The nib's "view" outlet will call setView: on its File's Owner.
+        let _: id = msg![env; nib_bundle loadNibNamed:nib_name owner:this options:nil];
+        if env.objc.borrow::<UIViewControllerHostObject>(this).view != nil {
+            return;
+        }
+        log!(
+            "Warning: nib file {:?} loaded by {:?} didn't set its view",
+            to_rust_string(env, nib_name),
+            this,
+        );
+    }
+
     let view: id = msg_class![env; UIView alloc];
-    let view: id = msg![env; view init];
+    let view: id = msg![env; view initWithFrame:(CGRect::default())];
     () = msg![env; this setView: view];
+    release(env, view);
 }
 - (())setView:(id)new_view { // UIView*
     let host_obj = env.objc.borrow_mut::<UIViewControllerHostObject>(this);
@@ -75,12 +146,117 @@ pub const CLASSES: ClassExports = objc_classes! {
     }
 }
 
+// Autorotation. See ui_device::handle_rotation.
+- (UIDeviceOrientation)interfaceOrientation {
+    let app: id = msg_class![env; UIApplication sharedApplication];
+    msg![env; app statusBarOrientation]
+}
+- (bool)shouldAutorotateToInterfaceOrientation:(UIDeviceOrientation)orientation {
+    // The default implementation only supports portrait.
+    orientation == UIDeviceOrientationPortrait
+}
+- (())willRotateToInterfaceOrientation:(UIDeviceOrientation)_orientation
+                              duration:(NSTimeInterval)_duration {
+    // default implementation does nothing
+}
+- (())willAnimateRotationToInterfaceOrientation:(UIDeviceOrientation)_orientation
+                                       duration:(NSTimeInterval)_duration {
+    // default implementation does nothing
+}
+- (())didRotateFromInterfaceOrientation:(UIDeviceOrientation)_orientation {
+    // default implementation does nothing
+}
+
 - (())setEditing:(bool)editing {
     log!("TODO: [(UIViewController*){:?} setEditing:{}]", this, editing); // TODO
 }
 
+- (())viewWillAppear:(bool)_animated {
+    // default implementation does nothing
+}
+- (())viewDidAppear:(bool)_animated {
+    // default implementation does nothing
+}
+- (())viewWillDisappear:(bool)_animated {
+    // default implementation does nothing
+}
+- (())viewDidDisappear:(bool)_animated {
+    // default implementation does nothing
+}
+
+- (id)modalViewController {
+    env.objc.borrow::<UIViewControllerHostObject>(this).modal_view_controller
+}
+- (id)parentViewController {
+    env.objc.borrow::<UIViewControllerHostObject>(this).parent_view_controller
+}
+
+// TODO: animation, modal transition styles
+- (())presentModalViewController:(id)modal_view_controller // UIViewController*
+                        animated:(bool)_animated {
+    if env.objc.borrow::<UIViewControllerHostObject>(this).modal_view_controller != nil {
+        log!(
+            "Warning: {:?} is already presenting a modal view controller, ignoring {:?}",
+            this,
+            modal_view_controller,
+        );
+        return;
+    }
+    let view = env.objc.borrow::<UIViewControllerHostObject>(this).view;
+    let Some(window) = window_for_presenting(env, view) else {
+        log!("Warning: no window to present {:?} in", modal_view_controller);
+        return;
+    };
+
+    retain(env, modal_view_controller);
+    env.objc.borrow_mut::<UIViewControllerHostObject>(this).modal_view_controller =
+        modal_view_controller;
+    env.objc
+        .borrow_mut::<UIViewControllerHostObject>(modal_view_controller)
+        .parent_view_controller = this;
+
+    // The modal view covers everything except the status bar.
+    let modal_view: id = msg![env; modal_view_controller view];
+    let screen: id = msg_class![env; UIScreen mainScreen];
+    let frame: CGRect = msg![env; screen applicationFrame];
+    () = msg![env; modal_view setFrame:frame];
+    () = msg![env; modal_view_controller viewWillAppear:false];
+    () = msg![env; window addSubview:modal_view];
+    () = msg![env; modal_view_controller viewDidAppear:false];
+}
+
 - (())dismissModalViewControllerAnimated:(bool)animated {
-    log!("TODO: [(UIViewController*){:?} dismissModalViewControllerAnimated:{}]", this, animated); // TODO
+    let &UIViewControllerHostObject {
+        modal_view_controller,
+        parent_view_controller,
+        ..
+    } = env.objc.borrow(this);
+    // A modal view controller can ask its parent to dismiss it.
+    if modal_view_controller == nil {
+        if parent_view_controller != nil {
+            () = msg![env; parent_view_controller dismissModalViewControllerAnimated:animated];
+        } else {
+            log!("Warning: {:?} has no modal view controller to dismiss", this);
+        }
+        return;
+    }
+
+    // Dismissing a modal view controller also dismisses any it presented.
+    let nested: id = msg![env; modal_view_controller modalViewController];
+    if nested != nil {
+        () = msg![env; modal_view_controller dismissModalViewControllerAnimated:false];
+    }
+
+    let modal_view: id = msg![env; modal_view_controller view];
+    () = msg![env; modal_view_controller viewWillDisappear:false];
+    () = msg![env; modal_view removeFromSuperview];
+    () = msg![env; modal_view_controller viewDidDisappear:false];
+
+    env.objc
+        .borrow_mut::<UIViewControllerHostObject>(modal_view_controller)
+        .parent_view_controller = nil;
+    env.objc.borrow_mut::<UIViewControllerHostObject>(this).modal_view_controller = nil;
+    release(env, modal_view_controller);
 }
 
 @end
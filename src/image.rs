@@ -3,16 +3,18 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
-//! Image decoding. Currently only supports PNG files (treated as 8-bit sRGB).
+//! Image decoding and encoding. Currently decoding supports PNG, JPEG, GIF and
+//! BMP files (treated as 8-bit sRGB). Encoding supports PNG and JPEG.
 //!
 //! Implemented as a wrapper around the C library stb_image, since it supports
-//! "CgBI" PNG files (an Apple proprietary extension used in iPhone OS apps).
+//! "CgBI" PNG files (an Apple proprietary extension used in iPhone OS apps),
+//! and its sibling stb_image_write.
 //!
 //! This module also exposes decompression for Imagination Technologies' PVRTC
 //! format, implementing as a wrapper around their decoder from the PowerVR
 //! SDK.
 
-use std::ffi::{c_int, c_uchar, CStr};
+use std::ffi::{c_int, c_uchar, c_void, CStr};
 
 use touchHLE_pvrt_decompress_wrapper::*;
 use touchHLE_stb_image_wrapper::*;
@@ -147,6 +149,80 @@ impl Image {
         }
     }
 
+    /// Copy a rectangular part of the image into a new image. The rectangle
+    /// is clamped to the image's bounds. 0 on the y axis is the top of the
+    /// image.
+    pub fn sub_image(&self, origin: (u32, u32), size: (u32, u32)) -> Image {
+        let (width, height) = self.dimensions;
+        let x_start = origin.0.min(width);
+        let y_start = origin.1.min(height);
+        let x_end = origin.0.saturating_add(size.0).min(width);
+        let y_end = origin.1.saturating_add(size.1).min(height);
+        let row_bytes = (x_end - x_start) as usize * 4;
+
+        let mut pixels = Vec::with_capacity(row_bytes * (y_end - y_start) as usize);
+        for y in y_start..y_end {
+            let row_start = (y as usize * width as usize + x_start as usize) * 4;
+            pixels.extend_from_slice(&self.pixels()[row_start..][..row_bytes]);
+        }
+        Image::from_pixel_vec(pixels, (x_end - x_start, y_end - y_start))
+    }
+
+    /// Get image data with straight (non-premultiplied) alpha, as image files
+    /// expect.
+    fn unpremultiplied_pixels(&self) -> Vec<u8> {
+        let mut pixels = self.pixels().to_vec();
+        for rgba in pixels.chunks_exact_mut(4) {
+            let a = rgba[3] as f32 / 255.0;
+            if a > 0.0 {
+                for channel in &mut rgba[..3] {
+                    *channel = (*channel as f32 / a).round().min(255.0) as u8;
+                }
+            }
+        }
+        pixels
+    }
+
+    /// Encode the image as a PNG file.
+    pub fn to_png(&self) -> Result<Vec<u8>, ()> {
+        let (width, height) = self.dimensions;
+        let pixels = self.unpremultiplied_pixels();
+        write_with_stb(|func, context| unsafe {
+            stbi_write_png_to_func(
+                func,
+                context,
+                width.try_into().unwrap(),
+                height.try_into().unwrap(),
+                4,
+                pixels.as_ptr().cast(),
+                (width * 4).try_into().unwrap(),
+            )
+        })
+    }
+
+    /// Encode the image as a JPEG file. `quality` is from 0 to 1.
+    ///
+    /// JPEG has no alpha channel, so transparent parts of the image become
+    /// black, which matches iPhone OS.
+    pub fn to_jpeg(&self, quality: f32) -> Result<Vec<u8>, ()> {
+        let (width, height) = self.dimensions;
+        // stb_image_write ignores alpha, so premultiplied pixels are what
+        // give the right result here.
+        let pixels = self.pixels();
+        let quality = (quality.clamp(0.0, 1.0) * 100.0).round().max(1.0) as c_int;
+        write_with_stb(|func, context| unsafe {
+            stbi_write_jpg_to_func(
+                func,
+                context,
+                width.try_into().unwrap(),
+                height.try_into().unwrap(),
+                4,
+                pixels.as_ptr().cast(),
+                quality,
+            )
+        })
+    }
+
     // TODO: Eventually this should be in Core Animation instead?
     /// Modify the image to mask it with anti-aliased rounded corners.
     pub fn round_corners(&mut self, radius: f32) {
@@ -185,6 +261,27 @@ impl Drop for Image {
     }
 }
 
+/// Calls one of stb_image_write's `stbi_write_*_to_func` functions (via the
+/// provided closure) and collects its output.
+fn write_with_stb<F>(write: F) -> Result<Vec<u8>, ()>
+where
+    F: FnOnce(stbi_write_func, *mut c_void) -> c_int,
+{
+    unsafe extern "C" fn append(context: *mut c_void, data: *mut c_void, size: c_int) {
+        let output = &mut *context.cast::<Vec<u8>>();
+        let data = std::slice::from_raw_parts(data.cast::<u8>(), size.try_into().unwrap());
+        output.extend_from_slice(data);
+    }
+
+    let mut output = Vec::<u8>::new();
+    let success = write(append, (&mut output as *mut Vec<u8>).cast());
+    if success != 0 {
+        Ok(output)
+    } else {
+        Err(())
+    }
+}
+
 /// Approximate implementation of sRGB gamma encoding.
 pub fn gamma_encode(intensity: f32) -> f32 {
     // TODO: This doesn't implement the linear section near zero.
@@ -224,3 +321,21 @@ pub fn decode_pvrtc(pvrtc_data: &[u8], is_2bit: bool, width: u32, height: u32) -
     };
     rgba8_data
 }
+
+#[cfg(test)]
+#[test]
+fn test_jpeg_decoding() {
+    // A small opaque image with a flat color, so JPEG's lossy compression
+    // shouldn't change it much.
+    let color = [0xC0, 0x40, 0x20, 0xFF];
+    let pixels = color.repeat(16 * 8);
+    let jpeg = Image::from_pixel_vec(pixels, (16, 8)).to_jpeg(1.0).unwrap();
+
+    let image = Image::from_bytes(&jpeg).unwrap();
+    assert_eq!(image.dimensions(), (16, 8));
+    for pixel in image.pixels().chunks(4) {
+        for (&actual, &expected) in pixel.iter().zip(color.iter()) {
+            assert!(actual.abs_diff(expected) <= 4, "{:?} != {:?}", pixel, color);
+        }
+    }
+}
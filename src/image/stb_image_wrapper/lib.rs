@@ -9,10 +9,17 @@
 // Allow the crate to have a non-snake-case name (touchHLE).
 // This also allows items in the crate to have non-snake-case names.
 #![allow(non_snake_case)]
+// Type names match stb_image_write.h.
+#![allow(non_camel_case_types)]
 
 use std::ffi::{c_char, c_int, c_uchar, c_void};
 
-// See build.rs, lib.c and ../../../vendor/stb/stb_image.h
+/// Callback used by the `stbi_write_*_to_func` functions to output data.
+pub type stbi_write_func =
+    unsafe extern "C" fn(context: *mut c_void, data: *mut c_void, size: c_int);
+
+// See build.rs, lib.c, ../../../vendor/stb/stb_image.h and
+// ../../../vendor/stb/stb_image_write.h
 extern "C" {
     pub fn stbi_convert_iphone_png_to_rgb(flag_true_if_should_convert: c_int);
     pub fn stbi_set_unpremultiply_on_load(flag_true_if_should_unpremultiply: c_int);
@@ -26,4 +33,23 @@ extern "C" {
     ) -> *mut c_uchar;
     pub fn stbi_image_free(retval_from_stbi_load: *mut c_void);
     pub fn stbi_failure_reason() -> *const c_char;
+
+    pub fn stbi_write_png_to_func(
+        func: stbi_write_func,
+        context: *mut c_void,
+        w: c_int,
+        h: c_int,
+        comp: c_int,
+        data: *const c_void,
+        stride_in_bytes: c_int,
+    ) -> c_int;
+    pub fn stbi_write_jpg_to_func(
+        func: stbi_write_func,
+        context: *mut c_void,
+        x: c_int,
+        y: c_int,
+        comp: c_int,
+        data: *const c_void,
+        quality: c_int,
+    ) -> c_int;
 }
@@ -20,7 +20,16 @@ pub const CLASS_LISTS: &[super::ClassExports] = &[
     core_graphics::cg_color::CLASSES,
     core_graphics::cg_color_space::CLASSES,
     core_graphics::cg_context::CLASSES,
+    core_graphics::cg_font::CLASSES,
     core_graphics::cg_image::CLASSES,
+    core_graphics::cg_path::CLASSES,
+    core_graphics::cg_function::CLASSES,
+    core_graphics::cg_gradient::CLASSES,
+    core_graphics::cg_pattern::CLASSES,
+    core_graphics::cg_shading::CLASSES,
+    core_graphics::cg_data_consumer::CLASSES,
+    core_graphics::cg_pdf_document::CLASSES,
+    core_graphics::cg_pdf_page::CLASSES,
     core_foundation::cf_run_loop_timer::CLASSES, // Special internal classes.
     game_kit::gk_local_player::CLASSES,
     foundation::ns_array::CLASSES,
@@ -68,6 +77,8 @@ pub const CLASS_LISTS: &[super::ClassExports] = &[
     uikit::ui_accelerometer::CLASSES,
     uikit::ui_activity_indicator_view::CLASSES,
     uikit::ui_application::CLASSES,
+    uikit::ui_bar_item::CLASSES,
+    uikit::ui_bar_item::ui_bar_button_item::CLASSES,
     uikit::ui_color::CLASSES,
     uikit::ui_device::CLASSES,
     uikit::ui_event::CLASSES,
@@ -79,16 +90,24 @@ pub const CLASS_LISTS: &[super::ClassExports] = &[
     uikit::ui_screen::CLASSES,
     uikit::ui_touch::CLASSES,
     uikit::ui_view::CLASSES,
+    uikit::ui_view::ui_action_sheet::CLASSES,
     uikit::ui_view::ui_alert_view::CLASSES,
     uikit::ui_view::ui_control::CLASSES,
     uikit::ui_view::ui_control::ui_button::CLASSES,
+    uikit::ui_view::ui_control::ui_page_control::CLASSES,
+    uikit::ui_view::ui_control::ui_segmented_control::CLASSES,
+    uikit::ui_view::ui_control::ui_slider::CLASSES,
     uikit::ui_view::ui_control::ui_switch::CLASSES,
     uikit::ui_view::ui_control::ui_text_field::CLASSES,
     uikit::ui_view::ui_image_view::CLASSES,
+    uikit::ui_view::ui_keyboard::CLASSES,
     uikit::ui_view::ui_label::CLASSES,
     uikit::ui_view::ui_picker_view::CLASSES,
+    uikit::ui_view::ui_progress_view::CLASSES,
     uikit::ui_view::ui_scroll_view::CLASSES,
     uikit::ui_view::ui_scroll_view::ui_text_view::CLASSES,
+    uikit::ui_view::ui_toolbar::CLASSES,
+    uikit::ui_view::ui_web_view::CLASSES,
     uikit::ui_view::ui_window::CLASSES,
     uikit::ui_view_controller::CLASSES,
 ];
@@ -11,6 +11,7 @@ use std::collections::HashMap;
 use std::io::{BufRead, BufReader, Read};
 use std::net::{SocketAddr, ToSocketAddrs};
 use std::num::NonZeroU32;
+use std::path::PathBuf;
 
 pub const OPTIONS_HELP: &str =
     include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/OPTIONS_HELP.txt"));
@@ -49,6 +50,8 @@ pub struct Options {
     pub headless: bool,
     pub print_fps: bool,
     pub fps_limit: Option<f64>,
+    pub photo_library_dir: Option<PathBuf>,
+    pub camera_source: Option<PathBuf>,
 }
 
 impl Default for Options {
@@ -71,6 +74,8 @@ impl Default for Options {
             headless: false,
             print_fps: false,
             fps_limit: Some(60.0), // Original iPhone is 60Hz and uses v-sync
+            photo_library_dir: None,
+            camera_source: None,
         }
     }
 }
@@ -186,6 +191,10 @@ impl Options {
                     .ok_or_else(|| "Invalid value for --fps-limit=".to_string())?;
                 self.fps_limit = Some(limit);
             }
+        } else if let Some(value) = arg.strip_prefix("--photo-library=") {
+            self.photo_library_dir = Some(PathBuf::from(value));
+        } else if let Some(value) = arg.strip_prefix("--camera=") {
+            self.camera_source = Some(PathBuf::from(value));
         } else {
             return Ok(false);
         };
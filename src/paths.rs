@@ -15,8 +15,8 @@
 //!   [USER_OPTIONS_FILE], [WALLPAPER_FILES]. These are ordinary files and are
 //!   found in [user_data_base_path].
 //! * Files that touchHLE will create and modify, and the user may modify if
-//!   they want to: [SANDBOX_DIR]. These are ordinary files and are found in
-//!   [user_data_base_path].
+//!   they want to: [SANDBOX_DIR], [PHOTOS_DIR]. These are ordinary files and
+//!   are found in [user_data_base_path].
 //!
 //! See also [crate::fs], which provides a virtual filesystem for the guest app
 //! and defines path types.
@@ -88,6 +88,11 @@ pub const WALLPAPER_FILES: &[&str] = &[
 /// the `Documents` directory.
 pub const SANDBOX_DIR: &str = "touchHLE_sandbox";
 
+/// Name of the directory used as the device's photo library by
+/// `UIImagePickerController` and `UIImageWriteToSavedPhotosAlbum`, unless
+/// overridden with the `--photo-library=` option.
+pub const PHOTOS_DIR: &str = "touchHLE_photos";
+
 /// Get a platform-specific base path needed for accessing touchHLE's
 /// user-modifiable files. This is empty on platforms other than Android.
 pub fn user_data_base_path() -> &'static Path {
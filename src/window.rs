@@ -17,7 +17,8 @@ use crate::gles::{create_gles1_ctx, GLES};
 use crate::image::Image;
 use crate::matrix::Matrix;
 use crate::options::Options;
-use sdl2::mouse::MouseButton;
+use sdl2::keyboard::Mod;
+use sdl2::mouse::{MouseButton, MouseWheelDirection};
 use sdl2::pixels::PixelFormatEnum;
 use sdl2::surface::Surface;
 use std::collections::{HashMap, VecDeque};
@@ -26,12 +27,28 @@ use std::f32::consts::FRAC_PI_2;
 use std::num::NonZeroU32;
 use std::time::{Duration, Instant};
 
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum DeviceOrientation {
     Portrait,
     LandscapeLeft,
     LandscapeRight,
 }
+impl DeviceOrientation {
+    /// Get the orientation that results from rotating the device by 90°.
+    /// Upside-down portrait isn't supported, so rotating from one landscape
+    /// orientation in the direction of it gives the other landscape orientation
+    /// instead.
+    pub fn rotated(self, clockwise: bool) -> DeviceOrientation {
+        match (self, clockwise) {
+            (DeviceOrientation::Portrait, true) => DeviceOrientation::LandscapeRight,
+            (DeviceOrientation::Portrait, false) => DeviceOrientation::LandscapeLeft,
+            (DeviceOrientation::LandscapeLeft, true) => DeviceOrientation::Portrait,
+            (DeviceOrientation::LandscapeLeft, false) => DeviceOrientation::LandscapeRight,
+            (DeviceOrientation::LandscapeRight, true) => DeviceOrientation::LandscapeLeft,
+            (DeviceOrientation::LandscapeRight, false) => DeviceOrientation::Portrait,
+        }
+    }
+}
 fn size_for_orientation(orientation: DeviceOrientation, scale_hack: NonZeroU32) -> (u32, u32) {
     let scale_hack = scale_hack.get();
     match orientation {
@@ -71,17 +88,84 @@ fn set_sdl2_orientation(orientation: DeviceOrientation) {
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub enum FingerId {
     Mouse,
+    /// Second finger of a two-finger gesture made by dragging the mouse while
+    /// holding a modifier key. See [MouseGesture].
+    MouseSecondary,
+    /// One of the fingers of a pinch made with the scroll wheel.
+    ScrollWheelPinch {
+        left: bool,
+    },
     Touch(i64),
     VirtualCursor,
+    /// One of the fingers of a gesture made with both analog sticks while
+    /// holding the left trigger.
+    StickGesture {
+        left: bool,
+    },
     ButtonToTouch(crate::options::Button),
 }
 pub type Coords = (f32, f32);
 
+/// A two-finger gesture simulated with the mouse, so that apps can be pinched
+/// and rotated without a touch screen. The modifier key is only checked when
+/// the left mouse button is pressed.
+#[derive(Copy, Clone, Debug)]
+enum MouseGesture {
+    /// Ctrl + drag: the second finger mirrors the cursor around the centre of
+    /// the screen, so moving towards or away from the centre pinches and
+    /// moving around it rotates.
+    Mirror,
+    /// Shift + drag: the second finger follows the cursor at a fixed offset
+    /// (in window co-ordinates), for two-finger dragging.
+    Parallel { offset: (f32, f32) },
+}
+
+/// State of a pinch made with the scroll wheel. Each step of the wheel moves
+/// the fingers apart or together. They are lifted once the wheel has been
+/// still for [SCROLL_WHEEL_PINCH_TIMEOUT].
+#[derive(Copy, Clone, Debug)]
+struct ScrollWheelPinch {
+    /// Window co-ordinates of the point between the fingers.
+    centre: (f32, f32),
+    /// Distance from the centre to each finger, in window co-ordinates.
+    distance: f32,
+    last_scroll: Instant,
+}
+
+const SCROLL_WHEEL_PINCH_TIMEOUT: Duration = Duration::from_millis(250);
+/// How much each step of the scroll wheel scales the distance between the
+/// fingers of a pinch.
+const SCROLL_WHEEL_PINCH_FACTOR: f32 = 1.1;
+
 #[derive(Debug)]
 pub enum TextInputEvent {
     Text(String),
     Backspace,
     Return,
+    /// An arrow key, for moving the caret.
+    MoveCaret(CaretMovement),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CaretMovement {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// Controller input for touchHLE's on-screen keyboard, which takes over the
+/// D-pad and face buttons while text input is active.
+#[derive(Debug)]
+pub enum KeyboardNavigationEvent {
+    Up,
+    Down,
+    Left,
+    Right,
+    /// Press the highlighted key.
+    Press,
+    Backspace,
+    Return,
 }
 
 #[derive(Debug)]
@@ -101,6 +185,12 @@ pub enum Event {
     /// take over.
     EnterDebugger,
     TextInput(TextInputEvent),
+    KeyboardNavigation(KeyboardNavigationEvent),
+    /// User pressed F10/F11 or the controller's Back button, requesting that
+    /// the virtual device be rotated by 90°.
+    RotateDevice {
+        clockwise: bool,
+    },
 }
 
 pub enum GLVersion {
@@ -134,7 +224,7 @@ fn surface_from_image(image: &Image) -> Surface {
 }
 
 pub struct Window {
-    _sdl_ctx: sdl2::Sdl,
+    sdl_ctx: sdl2::Sdl,
     video_ctx: sdl2::VideoSubsystem,
     window: sdl2::video::Window,
     event_pump: sdl2::EventPump,
@@ -163,6 +253,14 @@ pub struct Window {
     virtual_cursor_last: Option<(f32, f32, bool, bool)>,
     virtual_cursor_last_unsticky: Option<(f32, f32, Instant)>,
     virtual_accelerometer_last: Option<(f32, f32, bool)>,
+    /// Whether the left mouse button is making a touch. This is cleared early
+    /// if a scroll wheel pinch interrupts the touch.
+    mouse_touch_active: bool,
+    mouse_gesture: Option<MouseGesture>,
+    scroll_wheel_pinch: Option<ScrollWheelPinch>,
+    /// Window co-ordinates of the fingers (left stick first) while a gesture
+    /// is being made with the analog sticks.
+    stick_gesture_last: Option<[Coords; 2]>,
 }
 impl Window {
     /// Returns [true] if touchHLE is running on a device where we should always
@@ -278,7 +376,7 @@ impl Window {
         let max_height = window.size().1;
 
         let mut window = Window {
-            _sdl_ctx: sdl_ctx,
+            sdl_ctx,
             video_ctx,
             window,
             event_pump,
@@ -303,6 +401,10 @@ impl Window {
             virtual_cursor_last: None,
             virtual_cursor_last_unsticky: None,
             virtual_accelerometer_last: None,
+            mouse_touch_active: false,
+            mouse_gesture: None,
+            scroll_wheel_pinch: None,
+            stick_gesture_last: None,
         };
 
         // Set up OpenGL ES context used for splash screen and app UI rendering
@@ -383,10 +485,81 @@ impl Window {
                 _ => None,
             }
         }
+        fn translate_keyboard_navigation(
+            button: sdl2::controller::Button,
+        ) -> Option<KeyboardNavigationEvent> {
+            match button {
+                sdl2::controller::Button::DPadLeft => Some(KeyboardNavigationEvent::Left),
+                sdl2::controller::Button::DPadUp => Some(KeyboardNavigationEvent::Up),
+                sdl2::controller::Button::DPadRight => Some(KeyboardNavigationEvent::Right),
+                sdl2::controller::Button::DPadDown => Some(KeyboardNavigationEvent::Down),
+                sdl2::controller::Button::A => Some(KeyboardNavigationEvent::Press),
+                sdl2::controller::Button::B => Some(KeyboardNavigationEvent::Backspace),
+                sdl2::controller::Button::Start => Some(KeyboardNavigationEvent::Return),
+                _ => None,
+            }
+        }
         fn finger_absolute_coords(window: &Window, (x, y): (f32, f32)) -> (f32, f32) {
             let (screen_width, screen_height) = window.window.drawable_size();
             (screen_width as f32 * x, screen_height as f32 * y)
         }
+        /// Touches for the mouse cursor at a position in the window, including
+        /// the second finger of a [MouseGesture] if one is being made.
+        fn mouse_touches(window: &Window, (x, y): (f32, f32)) -> HashMap<FingerId, Coords> {
+            let coords = transform_input_coords(window, (x, y), false);
+            log_dbg!("Mouse x {}, y {}, coords {:?}", x, y, coords);
+            let mut map = HashMap::from([(FingerId::Mouse, coords)]);
+            let secondary = match window.mouse_gesture {
+                Some(MouseGesture::Mirror) => {
+                    let (vx, vy, vw, vh) = window.viewport();
+                    let centre_x = vx as f32 + vw as f32 / 2.0;
+                    let centre_y = vy as f32 + vh as f32 / 2.0;
+                    Some((2.0 * centre_x - x, 2.0 * centre_y - y))
+                }
+                Some(MouseGesture::Parallel { offset: (dx, dy) }) => Some((x + dx, y + dy)),
+                None => None,
+            };
+            if let Some(secondary) = secondary {
+                let coords = transform_input_coords(window, secondary, false);
+                map.insert(FingerId::MouseSecondary, coords);
+            }
+            map
+        }
+        fn scroll_wheel_pinch_touches(
+            window: &Window,
+            pinch: ScrollWheelPinch,
+        ) -> HashMap<FingerId, Coords> {
+            let ScrollWheelPinch {
+                centre: (x, y),
+                distance,
+                ..
+            } = pinch;
+            HashMap::from([
+                (
+                    FingerId::ScrollWheelPinch { left: true },
+                    transform_input_coords(window, (x - distance, y), false),
+                ),
+                (
+                    FingerId::ScrollWheelPinch { left: false },
+                    transform_input_coords(window, (x + distance, y), false),
+                ),
+            ])
+        }
+        fn stick_gesture_touches(
+            window: &Window,
+            fingers: [Coords; 2],
+        ) -> HashMap<FingerId, Coords> {
+            HashMap::from([
+                (
+                    FingerId::StickGesture { left: true },
+                    transform_input_coords(window, fingers[0], false),
+                ),
+                (
+                    FingerId::StickGesture { left: false },
+                    transform_input_coords(window, fingers[1], false),
+                ),
+            ])
+        }
 
         let mut controller_updated = false;
         // event_pump doesn't have a method to peek on events
@@ -442,7 +615,7 @@ impl Window {
                 _ => {}
             }
 
-            self.event_queue.push_back(match event {
+            let event = match event {
                 E::Quit { .. } => Event::Quit,
                 E::MouseButtonDown {
                     x,
@@ -450,26 +623,93 @@ impl Window {
                     mouse_btn: MouseButton::Left,
                     ..
                 } => {
-                    let coords = transform_input_coords(self, (x as f32, y as f32), false);
-                    log_dbg!("MouseButtonDown x {}, y {}, coords {:?}", x, y, coords);
-                    Event::TouchesDown(HashMap::from([(FingerId::Mouse, coords)]))
+                    let (x, y) = (x as f32, y as f32);
+                    let mod_state = self.sdl_ctx.keyboard().mod_state();
+                    self.mouse_gesture = if mod_state.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) {
+                        Some(MouseGesture::Mirror)
+                    } else if mod_state.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                        // Put the second finger a little to the right of the
+                        // cursor, or to the left if that's off-screen.
+                        let (vx, _, vw, _) = self.viewport();
+                        let offset = vw as f32 / 8.0;
+                        let offset = if x + offset < (vx + vw) as f32 {
+                            offset
+                        } else {
+                            -offset
+                        };
+                        Some(MouseGesture::Parallel {
+                            offset: (offset, 0.0),
+                        })
+                    } else {
+                        None
+                    };
+                    log_dbg!("MouseButtonDown, gesture {:?}", self.mouse_gesture);
+                    self.mouse_touch_active = true;
+                    Event::TouchesDown(mouse_touches(self, (x, y)))
                 }
                 E::MouseMotion {
                     x, y, mousestate, ..
-                } if mousestate.left() => {
-                    let coords = transform_input_coords(self, (x as f32, y as f32), false);
-                    log_dbg!("MouseMotion x {}, y {}, coords {:?}", x, y, coords);
-                    Event::TouchesMove(HashMap::from([(FingerId::Mouse, coords)]))
+                } if mousestate.left() && self.mouse_touch_active => {
+                    log_dbg!("MouseMotion");
+                    Event::TouchesMove(mouse_touches(self, (x as f32, y as f32)))
                 }
                 E::MouseButtonUp {
                     x,
                     y,
                     mouse_btn: MouseButton::Left,
                     ..
-                } => {
-                    let coords = transform_input_coords(self, (x as f32, y as f32), false);
-                    log_dbg!("MouseButtonUp x {}, y {}, coords {:?}", x, y, coords);
-                    Event::TouchesUp(HashMap::from([(FingerId::Mouse, coords)]))
+                } if self.mouse_touch_active => {
+                    log_dbg!("MouseButtonUp");
+                    let touches = mouse_touches(self, (x as f32, y as f32));
+                    self.mouse_touch_active = false;
+                    self.mouse_gesture = None;
+                    Event::TouchesUp(touches)
+                }
+                E::MouseWheel { y, direction, .. } => {
+                    let steps = match direction {
+                        MouseWheelDirection::Flipped => -y,
+                        _ => y,
+                    };
+                    if steps == 0 {
+                        continue;
+                    }
+                    let (_, _, vw, _) = self.viewport();
+                    let vw = vw as f32;
+                    let pinch = if let Some(pinch) = self.scroll_wheel_pinch {
+                        pinch
+                    } else {
+                        // The pinch is centred on the cursor, and starts with
+                        // the fingers a short distance apart.
+                        let mouse_state = self.event_pump.mouse_state();
+                        if self.mouse_touch_active {
+                            // End the mouse's touch, so the app doesn't see a
+                            // third finger that never lifts.
+                            let touches = mouse_touches(
+                                self,
+                                (mouse_state.x() as f32, mouse_state.y() as f32),
+                            );
+                            self.event_queue.push_back(Event::TouchesUp(touches));
+                            self.mouse_touch_active = false;
+                            self.mouse_gesture = None;
+                        }
+                        let pinch = ScrollWheelPinch {
+                            centre: (mouse_state.x() as f32, mouse_state.y() as f32),
+                            distance: vw / 8.0,
+                            last_scroll: now,
+                        };
+                        let touches = scroll_wheel_pinch_touches(self, pinch);
+                        self.event_queue.push_back(Event::TouchesDown(touches));
+                        pinch
+                    };
+                    // Scrolling up spreads the fingers, which usually zooms in.
+                    let pinch = ScrollWheelPinch {
+                        distance: (pinch.distance * SCROLL_WHEEL_PINCH_FACTOR.powi(steps))
+                            .clamp(vw / 64.0, vw),
+                        last_scroll: now,
+                        ..pinch
+                    };
+                    self.scroll_wheel_pinch = Some(pinch);
+                    Event::TouchesMove(scroll_wheel_pinch_touches(self, pinch))
                 }
                 E::ControllerDeviceAdded { which, .. } => {
                     self.controller_added(which);
@@ -479,6 +719,27 @@ impl Window {
                     self.controller_removed(which);
                     continue;
                 }
+                E::ControllerButtonDown {
+                    button: sdl2::controller::Button::Back,
+                    ..
+                } => {
+                    controller_updated = true;
+                    Event::RotateDevice { clockwise: true }
+                }
+                // While the app is accepting text input, the on-screen
+                // keyboard is visible and takes over some of the buttons.
+                E::ControllerButtonUp { button, .. } | E::ControllerButtonDown { button, .. }
+                    if self.video_ctx.text_input().is_active()
+                        && translate_keyboard_navigation(button).is_some() =>
+                {
+                    controller_updated = true;
+                    match event {
+                        E::ControllerButtonDown { .. } => Event::KeyboardNavigation(
+                            translate_keyboard_navigation(button).unwrap(),
+                        ),
+                        _ => continue,
+                    }
+                }
                 // Note that accelerometer simulation with analog sticks is
                 // handled with polling, rather than being event-based.
                 E::ControllerButtonUp { button, .. } | E::ControllerButtonDown { button, .. } => {
@@ -623,6 +884,14 @@ impl Window {
                     echo!("F12 pressed, EnterDebugger event queued.");
                     Event::EnterDebugger
                 }
+                E::KeyDown {
+                    keycode: Some(sdl2::keyboard::Keycode::F10),
+                    ..
+                } => Event::RotateDevice { clockwise: false },
+                E::KeyDown {
+                    keycode: Some(sdl2::keyboard::Keycode::F11),
+                    ..
+                } => Event::RotateDevice { clockwise: true },
                 E::KeyDown {
                     keycode: Some(sdl2::keyboard::Keycode::Backspace),
                     ..
@@ -637,15 +906,74 @@ impl Window {
                     log_dbg!("SDL TextInput Return");
                     Event::TextInput(TextInputEvent::Return)
                 }
+                E::KeyDown {
+                    keycode:
+                        Some(
+                            keycode @ (sdl2::keyboard::Keycode::Left
+                            | sdl2::keyboard::Keycode::Right
+                            | sdl2::keyboard::Keycode::Up
+                            | sdl2::keyboard::Keycode::Down),
+                        ),
+                    ..
+                } if self.video_ctx.text_input().is_active() => {
+                    let movement = match keycode {
+                        sdl2::keyboard::Keycode::Left => CaretMovement::Left,
+                        sdl2::keyboard::Keycode::Right => CaretMovement::Right,
+                        sdl2::keyboard::Keycode::Up => CaretMovement::Up,
+                        _ => CaretMovement::Down,
+                    };
+                    Event::TextInput(TextInputEvent::MoveCaret(movement))
+                }
                 E::TextInput { text, .. } => {
                     log_dbg!("SDL TextInput {}", text);
                     Event::TextInput(TextInputEvent::Text(text))
                 }
                 _ => continue,
-            })
+            };
+            self.event_queue.push_back(event);
+        }
+
+        if let Some(pinch) = self.scroll_wheel_pinch {
+            if now.duration_since(pinch.last_scroll) >= SCROLL_WHEEL_PINCH_TIMEOUT {
+                self.scroll_wheel_pinch = None;
+                let touches = scroll_wheel_pinch_touches(self, pinch);
+                self.event_queue.push_back(Event::TouchesUp(touches));
+            }
         }
 
         if controller_updated {
+            // The virtual cursor isn't used while the sticks are making a
+            // gesture.
+            let (old_fingers, new_fingers) = self.update_stick_gesture(options);
+            if old_fingers.is_some() || new_fingers.is_some() {
+                match (old_fingers, new_fingers) {
+                    (None, Some(new_fingers)) => {
+                        // End the virtual cursor's touch, if any, so the app
+                        // doesn't see a third finger that never lifts.
+                        if let Some((x, y, true, _)) = self.virtual_cursor_last {
+                            let coords = transform_input_coords(self, (x, y), false);
+                            self.event_queue.push_back(Event::TouchesUp(HashMap::from([(
+                                FingerId::VirtualCursor,
+                                coords,
+                            )])));
+                        }
+                        self.virtual_cursor_last = None;
+                        let touches = stick_gesture_touches(self, new_fingers);
+                        self.event_queue.push_back(Event::TouchesDown(touches));
+                    }
+                    (Some(old_fingers), None) => {
+                        let touches = stick_gesture_touches(self, old_fingers);
+                        self.event_queue.push_back(Event::TouchesUp(touches));
+                    }
+                    (Some(old_fingers), Some(new_fingers)) if old_fingers != new_fingers => {
+                        let touches = stick_gesture_touches(self, new_fingers);
+                        self.event_queue.push_back(Event::TouchesMove(touches));
+                    }
+                    _ => (),
+                }
+                return;
+            }
+
             let (new_x, new_y, pressed, pressed_changed, moved) =
                 self.update_virtual_cursor(options);
             self.event_queue
@@ -747,6 +1075,9 @@ impl Window {
             self.virtual_accelerometer_last
                 .map(|(x, y, _right_click_hold)| (x, y))
                 .unwrap()
+        } else if self.stick_gesture_last.is_some() {
+            // The left analog stick is being used for a gesture.
+            (0.0, 0.0)
         } else {
             // Get left analog stick input. The range is [-1, 1] on each axis.
             let (x, y, _) = self.get_controller_stick(options, true);
@@ -808,6 +1139,40 @@ impl Window {
         }
     }
 
+    /// Update the state of the two-finger gesture made by holding the left
+    /// trigger and moving both analog sticks. Returns the old and new window
+    /// co-ordinates of the fingers (left stick first), where [None] means no
+    /// gesture is being made.
+    ///
+    /// Each stick moves its finger around the middle of its half of the
+    /// screen, so pushing both sticks outwards spreads the fingers apart,
+    /// pushing them in opposite vertical directions rotates, and pushing them
+    /// in the same direction drags with two fingers.
+    fn update_stick_gesture(
+        &mut self,
+        options: &Options,
+    ) -> (Option<[Coords; 2]>, Option<[Coords; 2]>) {
+        use sdl2::controller::Axis;
+
+        let trigger_held = self
+            .controllers
+            .iter()
+            .any(|controller| controller.axis(Axis::TriggerLeft) > i16::MAX / 2);
+        let new_fingers = trigger_held.then(|| {
+            let (vx, vy, vw, vh) = self.viewport();
+            let (vx, vy, vw, vh) = (vx as f32, vy as f32, vw as f32, vh as f32);
+            let finger = |left: bool| {
+                let (x, y, _) = self.get_controller_stick(options, left);
+                let home_x = vx + vw * if left { 0.25 } else { 0.75 };
+                let home_y = vy + vh * 0.5;
+                (home_x + x * vw * 0.25, home_y + y * vh * 0.5)
+            };
+            [finger(true), finger(false)]
+        });
+        let old_fingers = std::mem::replace(&mut self.stick_gesture_last, new_fingers);
+        (old_fingers, new_fingers)
+    }
+
     /// Update the virtual cursor's position, click state and visibility, then
     /// return the new position, pressed state, whether the press state changed
     /// and whether the cursor moved.